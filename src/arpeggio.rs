@@ -0,0 +1,215 @@
+// Arpeggios for `sound make --arp`: the notes of a chord played one after another
+// instead of together.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Signature, Span, Spanned, SyntaxShape, Value};
+use rodio::{buffer::SamplesBuffer, Source};
+use std::time::Duration;
+
+use crate::{
+    dsp::Rng,
+    sound_make::{Tone, TONE_RATE},
+    sound_sequence::note_envelope,
+    utils::flag_span,
+    validate,
+    wav::int_in,
+};
+
+const DEFAULT_BPM: i64 = 120;
+
+/// Note value of each step when `--rate` is not given: a sixteenth.
+const DEFAULT_RATE: &str = "1/16";
+
+/// Shortest step, below which the notes blur into a buzz.
+const MIN_STEP: Duration = Duration::from_millis(10);
+
+/// Orders an arpeggio can walk its chord in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    Up,
+    Down,
+    UpDown,
+    Random,
+}
+
+/// `--arp` names, with a description for completions.
+pub const ARP_ORDERS: [(&str, Order, &str); 4] = [
+    ("up", Order::Up, "lowest note to highest, then again"),
+    ("down", Order::Down, "highest note to lowest, then again"),
+    (
+        "up-down",
+        Order::UpDown,
+        "up to the highest note and back down, without repeating the ends",
+    ),
+    (
+        "random",
+        Order::Random,
+        "a random note each step, never the same twice in a row",
+    ),
+];
+
+/// Adds `--arp`, `--rate` and `--bpm`.
+pub fn arpeggio_flags(signature: Signature) -> Signature {
+    signature
+        .named(
+            "arp",
+            SyntaxShape::String,
+            "play the chord's notes one after another: up, down, up-down or random",
+            None,
+        )
+        .named(
+            "rate",
+            SyntaxShape::OneOf(vec![SyntaxShape::String, SyntaxShape::Duration]),
+            "length of each --arp note, as a note value at --bpm (default \"1/16\") or a duration",
+            None,
+        )
+        .named(
+            "bpm",
+            SyntaxShape::Int,
+            "tempo for a --rate note value, in quarter notes per minute (default 120)",
+            None,
+        )
+}
+
+/// How `--arp` plays a chord.
+pub struct Arpeggio {
+    order: Order,
+    /// Length of each note.
+    step: Duration,
+}
+
+impl Arpeggio {
+    /// The arpeggio the flags ask for, if any. `--rate` and `--bpm` need `--arp`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Arpeggio>, LabeledError> {
+        let Some(name) = call.get_flag::<Spanned<String>>("arp")? else {
+            if let Some(flag) = ["rate", "bpm"]
+                .into_iter()
+                .find(|flag| call.get_flag_value(flag).is_some())
+            {
+                return Err(LabeledError::new(format!("--{flag} needs --arp"))
+                    .with_label("only arpeggios have a rate", flag_span(call, flag)));
+            }
+            return Ok(None);
+        };
+        let order = ARP_ORDERS
+            .iter()
+            .find(|(n, _, _)| n.eq_ignore_ascii_case(name.item.trim()))
+            .map(|(_, order, _)| *order)
+            .ok_or_else(|| {
+                LabeledError::new(format!("unknown arpeggio '{}'", name.item))
+                    .with_label("unknown order", name.span)
+                    .with_help("use up, down, up-down or random")
+            })?;
+        if let Some(flag) = ["right-frequency", "expr"]
+            .into_iter()
+            .find(|flag| call.get_flag_value(flag).is_some())
+        {
+            return Err(
+                LabeledError::new(format!("--arp cannot be combined with --{flag}"))
+                    .with_label("arpeggios play one note at a time", flag_span(call, flag)),
+            );
+        }
+        let bpm = match call.get_flag_value("bpm") {
+            Some(value) => int_in(&value, 20..=400, "--bpm", "beats per minute")?,
+            None => DEFAULT_BPM,
+        };
+        let step = match call.get_flag_value("rate") {
+            Some(value) => rate(&value, bpm)?,
+            None => note_value(DEFAULT_RATE, bpm).expect("the default rate is a note value"),
+        };
+        Ok(Some(Arpeggio { order, step }))
+    }
+
+    /// Renders `tone` with its notes played in turn, each [`step`](Arpeggio::step) long
+    /// and with its own envelope, until the tone's duration is filled. Notes are sorted
+    /// by pitch first, so `up` climbs whatever order the chord was written in.
+    pub fn render(&self, tone: &Tone, call: &EvaluatedCall) -> Result<SamplesBuffer, LabeledError> {
+        let mut notes = tone.frequencies.clone();
+        notes.sort_by(f32::total_cmp);
+        let pattern: Vec<usize> = match self.order {
+            Order::Up | Order::Random => (0..notes.len()).collect(),
+            Order::Down => (0..notes.len()).rev().collect(),
+            Order::UpDown => (0..notes.len())
+                .chain((1..notes.len().saturating_sub(1)).rev())
+                .collect(),
+        };
+        let mut rng = Rng::from_entropy();
+        let mut previous = None;
+        let envelope = note_envelope(call)?;
+        let mut channels = 1;
+        let mut samples = Vec::new();
+        let mut elapsed = Duration::ZERO;
+        let mut n = 0;
+        while elapsed < tone.duration {
+            let index = match self.order {
+                Order::Random if notes.len() > 1 => loop {
+                    let index = (rng.next_u64() % notes.len() as u64) as usize;
+                    if Some(index) != previous {
+                        break index;
+                    }
+                },
+                _ => pattern[n % pattern.len()],
+            };
+            previous = Some(index);
+            let duration = self.step.min(tone.duration - elapsed);
+            let note = Tone {
+                frequencies: vec![notes[index]],
+                duration,
+                envelope,
+                ..tone.clone()
+            }
+            .source();
+            channels = note.channels();
+            samples.extend(note);
+            elapsed += duration;
+            n += 1;
+        }
+        Ok(SamplesBuffer::new(channels, TONE_RATE, samples))
+    }
+}
+
+/// A `--rate` of a note value like `"1/16"` or a duration like `125ms`.
+fn rate(value: &Value, bpm: i64) -> Result<Duration, LabeledError> {
+    let step = match value {
+        Value::String { val, .. } if val.contains('/') => {
+            note_value(val, bpm).ok_or_else(|| invalid_rate(value.span()))?
+        }
+        value => validate::duration(value, "--rate")?,
+    };
+    if step < MIN_STEP {
+        return Err(LabeledError::new("--rate too fast")
+            .with_label(
+                format!("each note must last at least {}ms", MIN_STEP.as_millis()),
+                value.span(),
+            )
+            .with_help("use a longer note value, or a lower --bpm"));
+    }
+    Ok(step)
+}
+
+/// Length of a note value written as a fraction of a whole note, e.g. `1/8`, with a
+/// trailing `t` for a triplet or `.` for a dotted note.
+fn note_value(text: &str, bpm: i64) -> Option<Duration> {
+    let text = text.trim().to_ascii_lowercase();
+    let (text, scale) = if let Some(text) = text.strip_suffix('t') {
+        (text, 2.0 / 3.0)
+    } else if let Some(text) = text.strip_suffix('.') {
+        (text, 1.5)
+    } else {
+        (text.as_str(), 1.0)
+    };
+    let (numerator, denominator) = text.split_once('/')?;
+    let numerator: f64 = numerator.trim().parse().ok()?;
+    let denominator: f64 = denominator.trim().parse().ok()?;
+    let whole = 4.0 * 60.0 / bpm as f64;
+    let seconds = whole * numerator / denominator * scale;
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|step| !step.is_zero())
+}
+
+fn invalid_rate(span: Span) -> LabeledError {
+    LabeledError::new("invalid --rate")
+        .with_label("expected a note value or a duration", span)
+        .with_help("e.g. \"1/16\", \"1/8t\" for triplets, \"1/8.\" dotted, or 125ms")
+}
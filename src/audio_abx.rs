@@ -0,0 +1,207 @@
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute,
+    terminal::{Clear, ClearType},
+};
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+use std::{
+    fs::File,
+    io::{stderr, Write},
+    time::Duration,
+};
+
+use crate::{
+    ear_guard::{ear_guard_flags, EarGuard},
+    dsp::Rng,
+    multitrack::{RawModeGuard, SyncedPlayback},
+    pcm::Pcm,
+    utils::{format_duration, load_file_path_at},
+    Sound,
+};
+
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_TRIALS: i64 = 10;
+
+/// Nushell command `sound abx` — blind ABX listening test between two files.
+///
+/// In every trial X is secretly A or B. The listener switches freely between A, B and X
+/// (playback stays in sync) and answers which one X is. The result includes the number
+/// of correct answers and the probability of scoring at least that well by guessing.
+pub struct SoundAbxCmd;
+impl SimplePluginCommand for SoundAbxCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound abx"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound abx")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .required("A", SyntaxShape::Filepath, "first file")
+            .required("B", SyntaxShape::Filepath, "second file")
+            .named(
+                "trials",
+                SyntaxShape::Int,
+                "number of trials (default: 10)",
+                Some('t'),
+            )
+            .category(Category::Experimental);
+        ear_guard_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "test whether a 128k MP3 can be told apart from the FLAC",
+            example: "sound abx song.flac song.mp3 --trials 16",
+            result: None,
+        }]
+    }
+
+    fn description(&self) -> &str {
+        "blind ABX test between two files: a/b/x switch what you hear, \
+        1 answers X = A, 2 answers X = B, r restarts, q stops early"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        abx(engine, call)
+    }
+}
+
+fn abx(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let mut clips = Vec::with_capacity(2);
+    for index in 0..2 {
+        let (file_span, path) = load_file_path_at(engine, call, index)?;
+        let file = File::open(&path).map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("error trying to open the file", file_span)
+        })?;
+        clips.push(Pcm::decode(file, file_span)?);
+    }
+
+    let trials: Spanned<i64> = call.get_flag("trials")?.unwrap_or(Spanned {
+        item: DEFAULT_TRIALS,
+        span,
+    });
+    if !(1..=100).contains(&trials.item) {
+        return Err(LabeledError::new("invalid trial count")
+            .with_label("must be between 1 and 100", trials.span));
+    }
+    let trials = trials.item as usize;
+    let guard = EarGuard::from_call(engine, call)?;
+
+    let mut rng = Rng::from_entropy();
+    let mut results = Vec::with_capacity(trials);
+    let mut correct = 0;
+    let _raw = RawModeGuard::enable(span)?;
+    let mut err = stderr();
+
+    'trials: for trial in 1..=trials {
+        // Index of the clip X plays this trial: 0 = A, 1 = B.
+        let x = usize::from(rng.next_bool());
+        let mut playback = SyncedPlayback::start(&clips, guard, span)?;
+        let mut listening = 'X';
+        playback.solo(x);
+
+        let answer = loop {
+            engine.signals().check(&span)?;
+            if playback.finished() {
+                playback = SyncedPlayback::start(&clips, guard, span)?;
+                playback.solo(match listening {
+                    'A' => 0,
+                    'B' => 1,
+                    _ => x,
+                });
+            }
+            if event::poll(KEY_POLL_INTERVAL).unwrap_or(false) {
+                if let Ok(Event::Key(KeyEvent { code, kind, .. })) = event::read() {
+                    if kind == KeyEventKind::Press {
+                        match code {
+                            KeyCode::Char('a') => {
+                                listening = 'A';
+                                playback.solo(0);
+                            }
+                            KeyCode::Char('b') => {
+                                listening = 'B';
+                                playback.solo(1);
+                            }
+                            KeyCode::Char('x') => {
+                                listening = 'X';
+                                playback.solo(x);
+                            }
+                            KeyCode::Char('r') => playback.seek(Duration::ZERO),
+                            KeyCode::Char('1') => break 0,
+                            KeyCode::Char('2') => break 1,
+                            KeyCode::Char('q') | KeyCode::Esc => {
+                                playback.stop();
+                                break 'trials;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
+            let _ = write!(
+                err,
+                "trial {trial}/{trials}  listening: {listening}  {}  [a/b/x listen, 1: X is A, 2: X is B]",
+                format_duration(playback.position()),
+            );
+            let _ = err.flush();
+        };
+        playback.stop();
+
+        if answer == x {
+            correct += 1;
+        }
+        let name = |i: usize| if i == 0 { "A" } else { "B" };
+        results.push(Value::record(
+            record! {
+                "trial" => Value::int(trial as i64, span),
+                "x" => Value::string(name(x), span),
+                "answer" => Value::string(name(answer), span),
+                "correct" => Value::bool(answer == x, span),
+            },
+            span,
+        ));
+    }
+    let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
+
+    let completed = results.len();
+    let p_value = binomial_tail(completed, correct);
+    Ok(Value::record(
+        record! {
+            "trials" => Value::int(completed as i64, span),
+            "correct" => Value::int(correct as i64, span),
+            "p_value" => Value::float((p_value * 10000.0).round() / 10000.0, span),
+            "significant" => Value::bool(completed > 0 && p_value < 0.05, span),
+            "results" => Value::list(results, span),
+        },
+        span,
+    ))
+}
+
+/// Probability of at least `correct` right answers out of `trials` by pure guessing.
+fn binomial_tail(trials: usize, correct: usize) -> f64 {
+    let mut coefficient = 1.0;
+    let mut tail = 0.0;
+    for k in 0..=trials {
+        if k > 0 {
+            coefficient *= (trials - k + 1) as f64 / k as f64;
+        }
+        if k >= correct {
+            tail += coefficient;
+        }
+    }
+    tail / 2f64.powi(trials as i32)
+}
@@ -0,0 +1,332 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Span, SyntaxShape, Type, Value,
+};
+use std::time::Duration;
+
+use crate::{
+    pcm::{load_blocks, seconds_value, Pcm},
+    simd,
+    validate::{duration_flag, duration_shape},
+    Sound,
+};
+
+/// Analysis window for speech detection; 20 ms is short enough to follow syllables.
+const SPEECH_FRAME: Duration = Duration::from_millis(20);
+const DEFAULT_MIN_SILENCE: Duration = Duration::from_millis(300);
+const DEFAULT_MIN_SPEECH: Duration = Duration::from_millis(200);
+/// Minimum distance above the noise floor (dB) a frame needs to count as speech.
+const DEFAULT_SPEECH_MARGIN_DB: f64 = 6.0;
+
+/// Hop size for onset detection; 10 ms resolves individual drum hits.
+const ONSET_HOP: Duration = Duration::from_millis(10);
+
+/// Window compared on each side of the loop point.
+const LOOP_EDGE: Duration = Duration::from_millis(50);
+
+/// Nushell command `sound analyze speech` — energy-based voice activity detection.
+///
+/// Frames are classified against an adaptive threshold placed between the recording's
+/// noise floor and its loud passages, then smoothed so short pauses stay inside a
+/// speech segment. Each segment carries a confidence derived from how far its frames sit
+/// from the threshold.
+pub struct SoundAnalyzeSpeechCmd;
+impl SimplePluginCommand for SoundAnalyzeSpeechCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound analyze speech"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound analyze speech")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .required("File Path", SyntaxShape::Filepath, "recording to analyze")
+            .named(
+                "min-silence",
+                duration_shape(),
+                "pauses shorter than this are kept inside a speech segment (default: 300ms)",
+                None,
+            )
+            .named(
+                "min-speech",
+                duration_shape(),
+                "speech bursts shorter than this are treated as noise (default: 200ms)",
+                None,
+            )
+            .named(
+                "margin",
+                SyntaxShape::Float,
+                "minimum dB above the noise floor for a frame to count as speech (default: 6)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "list speech and silence segments of a podcast episode",
+                example: "sound analyze speech episode.mp3",
+                result: None,
+            },
+            Example {
+                description: "find confident talking sections only",
+                example: "sound analyze speech episode.mp3 | where speech and confidence > 0.8",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "split a recording into speech and non-speech segments with a confidence score"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        analyze_speech(engine, call)
+    }
+}
+
+/// Nushell command `sound analyze loop` — scores how seamlessly a clip loops.
+///
+/// Looks at the wrap-around point from the last sample back to the first: the size of the
+/// jump relative to the clip's typical sample-to-sample step (a click), and the level
+/// change between the final and opening 50 ms (a pumping or dropout). Both are folded into
+/// a `score` from 0 (obvious seam) to 1 (seamless).
+pub struct SoundAnalyzeLoopCmd;
+impl SimplePluginCommand for SoundAnalyzeLoopCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound analyze loop"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound analyze loop")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .required("File Path", SyntaxShape::Filepath, "loop to check")
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "check a folder of loops for audible seams",
+                example: "ls loops/*.wav | each { |f| sound analyze loop $f.name | insert file $f.name } | where not seamless",
+                result: None,
+            },
+            Example {
+                description: "a tone of a whole number of cycles loops without a seam",
+                example: "let tone = $nu.temp-path | path join tone.wav; sound make 440 2sec --data | save -f $tone; sound analyze loop $tone",
+                result: Some(Value::test_record(record! {
+                    "score" => Value::test_float(1.0),
+                    "seamless" => Value::test_bool(true),
+                    "jump_ratio" => Value::test_float(0.0),
+                    "level_difference_db" => Value::test_float(0.0),
+                })),
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "score how seamless the wrap-around point of a loop is"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        analyze_loop(engine, call)
+    }
+}
+
+fn analyze_loop(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let (file_span, _, pcm) = Pcm::load(engine, call)?;
+    loop_report(&pcm, file_span, call.head)
+}
+
+fn loop_report(pcm: &Pcm, file_span: Span, span: Span) -> Result<Value, LabeledError> {
+    let edge = pcm.frames_for(LOOP_EDGE).max(1);
+    let frames = pcm.frames();
+    if frames < edge * 2 {
+        return Err(LabeledError::new("clip too short")
+            .with_label("need at least 100ms of audio", file_span));
+    }
+
+    let channels = pcm.channels as usize;
+    let mut jump_ratio: f64 = 0.0;
+    for c in 0..channels {
+        let channel: Vec<f64> = pcm.samples[c..]
+            .iter()
+            .step_by(channels)
+            .map(|s| *s as f64)
+            .collect();
+        let typical = channel.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>()
+            / (channel.len() - 1) as f64;
+        let jump = (channel[0] - channel[channel.len() - 1]).abs();
+        jump_ratio = jump_ratio.max(jump / typical.max(1e-6));
+    }
+
+    let mono = pcm.mono();
+    let level_difference = (level_db(&mono[frames - edge..]) - level_db(&mono[..edge])).abs();
+
+    // A jump up to ~2x the typical step is inaudible; each further 4x costs a factor e.
+    // Every 6 dB of level change costs a factor e.
+    let score = (-(jump_ratio - 2.0).max(0.0) / 4.0).exp() * (-level_difference / 6.0).exp();
+    let round = |v: f64| (v * 1000.0).round() / 1000.0;
+    Ok(Value::record(
+        record! {
+            "score" => Value::float(round(score), span),
+            "seamless" => Value::bool(score >= 0.8, span),
+            "jump_ratio" => Value::float(round(jump_ratio), span),
+            "level_difference_db" => Value::float(round(level_difference), span),
+        },
+        span,
+    ))
+}
+
+fn analyze_speech(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let margin: f64 = call.get_flag("margin")?.unwrap_or(DEFAULT_SPEECH_MARGIN_DB);
+    let min_silence = duration_flag(call, "min-silence")?.unwrap_or(DEFAULT_MIN_SILENCE);
+    let min_speech = duration_flag(call, "min-speech")?.unwrap_or(DEFAULT_MIN_SPEECH);
+    // Meetings run for hours; only the level of each frame is needed, not the samples.
+    let blocks = load_blocks(engine, call, SPEECH_FRAME, level_db)?;
+
+    let frame_secs = blocks.block_frames as f64 / blocks.sample_rate as f64;
+    let levels = &blocks.values;
+    if levels.is_empty() {
+        return Ok(Value::list(vec![], span));
+    }
+
+    let threshold = speech_threshold(levels, margin);
+    // Logistic over the distance to the threshold; 3 dB away is ~73% certain.
+    let probability: Vec<f64> = levels
+        .iter()
+        .map(|level| 1.0 / (1.0 + (-(level - threshold) / 3.0).exp()))
+        .collect();
+    let mut speech: Vec<bool> = probability.iter().map(|p| *p >= 0.5).collect();
+
+    let min_silence_frames = (min_silence.as_secs_f64() / frame_secs).round() as usize;
+    let min_speech_frames = (min_speech.as_secs_f64() / frame_secs).round() as usize;
+    fill_short_runs(&mut speech, false, min_silence_frames);
+    fill_short_runs(&mut speech, true, min_speech_frames);
+
+    let rows = runs(&speech)
+        .into_iter()
+        .map(|(start, end, is_speech)| {
+            let confidence = probability[start..end]
+                .iter()
+                .map(|p| if is_speech { *p } else { 1.0 - p })
+                .sum::<f64>()
+                / (end - start) as f64;
+            let end_secs = (end as f64 * frame_secs).min(blocks.duration().as_secs_f64());
+            Value::record(
+                record! {
+                    "start" => seconds_value(start as f64 * frame_secs, span),
+                    "end" => seconds_value(end_secs, span),
+                    "speech" => Value::bool(is_speech, span),
+                    "confidence" => Value::float((confidence * 1000.0).round() / 1000.0, span),
+                },
+                span,
+            )
+        })
+        .collect();
+    Ok(Value::list(rows, span))
+}
+
+/// RMS level of a block of samples in dBFS, floored at -100 dB for digital silence.
+pub(crate) fn level_db(block: &[f32]) -> f64 {
+    if block.is_empty() {
+        return -100.0;
+    }
+    let mean_square = simd::mean_square(block);
+    (10.0 * mean_square.log10()).max(-100.0)
+}
+
+/// Onset strength per 10 ms hop: the rise in RMS level from the previous hop, zero when
+/// the level falls. Returns the hop size in frames along with the curve.
+pub(crate) fn onset_strength(pcm: &Pcm) -> (usize, Vec<f64>) {
+    let hop = pcm.frames_for(ONSET_HOP).max(1);
+    let rms: Vec<f64> = pcm
+        .mono()
+        .chunks(hop)
+        .map(|block| simd::mean_square(block).sqrt())
+        .collect();
+    let strength = std::iter::once(0.0)
+        .chain(rms.windows(2).map(|w| (w[1] - w[0]).max(0.0)))
+        .collect();
+    (hop, strength)
+}
+
+/// Places the speech threshold 40% of the way from the noise floor (10th percentile) to
+/// the loud passages (95th percentile), but never closer than `margin` dB to the floor.
+/// When the recording has almost no dynamic range it is either all silence or all
+/// talking, decided by an absolute -45 dBFS level.
+fn speech_threshold(levels: &[f64], margin: f64) -> f64 {
+    let mut sorted = levels.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+    let floor = percentile(0.10);
+    let loud = percentile(0.95);
+
+    if loud - floor < margin {
+        return if loud > -45.0 {
+            floor - margin
+        } else {
+            loud + margin
+        };
+    }
+    (floor + (loud - floor) * 0.4).max(floor + margin)
+}
+
+/// Flips runs of `value` shorter than `min_len` frames to the opposite class, leaving
+/// runs at the very start and end alone.
+fn fill_short_runs(flags: &mut [bool], value: bool, min_len: usize) {
+    for (start, end, run_value) in runs(flags) {
+        if run_value == value && end - start < min_len && start > 0 && end < flags.len() {
+            flags[start..end].fill(!value);
+        }
+    }
+}
+
+/// Collapses a per-frame classification into `(start, end, value)` runs.
+fn runs(flags: &[bool]) -> Vec<(usize, usize, bool)> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    for i in 1..=flags.len() {
+        if i == flags.len() || flags[i] != flags[start] {
+            result.push((start, i, flags[start]));
+            start = i;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sound_make::Tone, utils::example_results, wav::WavOptions};
+    use rodio::{Decoder, Source};
+    use std::io::Cursor;
+
+    #[test]
+    fn loop_example_result_matches_report() {
+        let tone = Tone::sine(440.0, Duration::from_secs(2));
+        let wav = WavOptions::default().encode(tone.source()).unwrap();
+        let source = Decoder::new(Cursor::new(wav)).unwrap();
+        let (channels, sample_rate) = (source.channels(), source.sample_rate());
+        let pcm = Pcm::new(source.collect(), channels, sample_rate);
+        let report = loop_report(&pcm, Span::test_data(), Span::test_data()).unwrap();
+        assert_eq!(example_results(&SoundAnalyzeLoopCmd), [report]);
+    }
+}
@@ -0,0 +1,483 @@
+use lofty::config::WriteOptions;
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::picture::{MimeType, Picture, PictureType};
+use lofty::read_from_path;
+use lofty::tag::ItemKey;
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, record, Category, DynamicSuggestion, Example, LabeledError, PipelineData,
+    Signature, Span, Spanned, SyntaxShape, Value,
+};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    audio_meta::{parse_meta, primary_tag_mut_or_insert},
+    completions::image_formats,
+    jpeg, png,
+    raster::Raster,
+    utils::{force_flags, load_file, resolve_filepath, with_write_access},
+    Sound,
+};
+
+/// Quality of covers re-encoded as JPEG, on libjpeg's 1–100 scale.
+const JPEG_QUALITY: u8 = 90;
+
+/// Nushell command `sound meta artwork set` — embeds an image as the front cover.
+///
+/// `--max-size` and `--format` keep covers small and compatible: oversized images are
+/// downscaled and other formats re-encoded before embedding. Images that already fit are
+/// embedded byte for byte.
+pub struct SoundArtworkSetCmd;
+impl SimplePluginCommand for SoundArtworkSetCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta artwork set"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound meta artwork set")
+            .required("File Path", SyntaxShape::Filepath, "audio file to update")
+            .required(
+                "Image Path",
+                SyntaxShape::Filepath,
+                "JPEG or PNG image to embed",
+            )
+            .named(
+                "max-size",
+                SyntaxShape::Int,
+                "downscale images whose width or height exceeds this many pixels",
+                Some('m'),
+            )
+            .named(
+                "format",
+                SyntaxShape::String,
+                "convert the image to this format before embedding: jpeg or png",
+                Some('f'),
+            )
+            .category(Category::Experimental);
+        force_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "embed a front cover",
+                example: "sound meta artwork set audio.mp3 cover.jpg",
+                result: None,
+            },
+            Example {
+                description: "embed a phone photo as a JPEG cover of at most 600x600 pixels",
+                example: "sound meta artwork set audio.mp3 photo.png --max-size 600 --format jpeg",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "embed an image as the front cover of an audio file, replacing any existing front cover"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "format" => Some(image_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        artwork_set(engine, call)
+    }
+}
+
+fn artwork_set(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let (_, file_value, path) = load_file(engine, call)?;
+    drop(file_value);
+
+    let image_arg: Spanned<String> = call.req(1)?;
+    let image_path = resolve_filepath(engine, image_arg.span, PathBuf::from(&image_arg.item))?;
+    let mut image = std::fs::File::open(&image_path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error opening image", image_arg.span)
+    })?;
+    let mut picture = Picture::from_reader(&mut image).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("unsupported image", image_arg.span)
+    })?;
+
+    let format = match call.get_flag::<Spanned<String>>("format")? {
+        Some(format) => Some(match format.item.to_lowercase().as_str() {
+            "jpeg" | "jpg" => MimeType::Jpeg,
+            "png" => MimeType::Png,
+            other => {
+                return Err(LabeledError::new(format!("unknown image format '{other}'"))
+                    .with_label("expected jpeg or png", format.span))
+            }
+        }),
+        None => None,
+    };
+    let max_size = match call.get_flag::<Spanned<i64>>("max-size")? {
+        Some(max_size) if max_size.item <= 0 => {
+            return Err(LabeledError::new("--max-size must be positive")
+                .with_label("invalid size", max_size.span));
+        }
+        Some(max_size) => Some(max_size.item as usize),
+        None => None,
+    };
+
+    let convert = format
+        .as_ref()
+        .filter(|&format| picture.mime_type() != Some(format));
+    if convert.is_some() || max_size.is_some() {
+        let raster = Raster::decode(picture.data(), image_arg.span)?;
+        let oversized = max_size.is_some_and(|max| raster.width.max(raster.height) > max);
+        if convert.is_some() || oversized {
+            let raster = match max_size {
+                Some(max) => raster.fit(max),
+                None => raster,
+            };
+            // Only JPEG and PNG decode, so without --format the image stays what it was.
+            let mime =
+                format.unwrap_or_else(|| picture.mime_type().cloned().unwrap_or(MimeType::Jpeg));
+            let data = match mime {
+                MimeType::Png => png::encode(&raster),
+                _ => jpeg::encode(&raster, JPEG_QUALITY),
+            };
+            picture = Picture::unchecked(data).mime_type(mime).build();
+        }
+    }
+
+    embed_front_cover(call, path, picture)
+}
+
+/// Replaces the front cover of `path` with `picture`, saves the file, and returns the
+/// re-read metadata record.
+fn embed_front_cover(
+    call: &EvaluatedCall,
+    path: PathBuf,
+    mut picture: Picture,
+) -> Result<Value, LabeledError> {
+    picture.set_pic_type(PictureType::CoverFront);
+
+    let mut tagged_file = read_from_path(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error reading file", call.head)
+    })?;
+    let tag = primary_tag_mut_or_insert(&mut tagged_file, call.head)?;
+    tag.remove_picture_type(PictureType::CoverFront);
+    tag.push_picture(picture);
+
+    with_write_access(&path, call.has_flag("force")?, call.head, || {
+        tagged_file
+            .save_to_path(&path, WriteOptions::default())
+            .map_err(|e| {
+                LabeledError::new(e.to_string()).with_label("error saving file", call.head)
+            })
+    })?;
+
+    let file = std::fs::File::open(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error re-opening file for parsing", call.head)
+    })?;
+    parse_meta(call, file, path)
+}
+
+/// Nushell command `sound meta artwork fetch` — looks up cover art online.
+///
+/// Searches the Cover Art Archive (via the MusicBrainz release ID tag, or a MusicBrainz
+/// search on album/artist) or the iTunes Search API. Without `--select` the candidates are
+/// returned as a table for review; `--select <index>` downloads and embeds that candidate.
+/// Requests go through Nushell's own `http get`, so proxy settings apply as usual.
+pub struct SoundArtworkFetchCmd;
+impl SimplePluginCommand for SoundArtworkFetchCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta artwork fetch"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound meta artwork fetch")
+            .required("File Path", SyntaxShape::Filepath, "audio file to look up")
+            .named(
+                "provider",
+                SyntaxShape::String,
+                "artwork provider: caa (Cover Art Archive, default) or itunes",
+                Some('p'),
+            )
+            .named(
+                "select",
+                SyntaxShape::Int,
+                "embed the candidate with this index instead of listing candidates",
+                Some('s'),
+            )
+            .category(Category::Experimental);
+        force_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "list cover art candidates from the Cover Art Archive",
+                example: "sound meta artwork fetch audio.mp3",
+                result: None,
+            },
+            Example {
+                description: "embed the first iTunes candidate",
+                example: "sound meta artwork fetch audio.mp3 --provider itunes --select 0",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "look up cover art online using the file's album/artist tags and optionally embed it"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        artwork_fetch(engine, call)
+    }
+}
+
+/// One cover art search result.
+struct ArtworkCandidate {
+    artist: String,
+    album: String,
+    url: String,
+}
+
+fn artwork_fetch(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let (_, file_value, path) = load_file(engine, call)?;
+    drop(file_value);
+
+    let provider = call.get_flag::<Spanned<String>>("provider")?;
+    let candidates = match provider.as_ref().map(|p| p.item.to_lowercase()).as_deref() {
+        None | Some("caa") => caa_candidates(engine, call.head, &path)?,
+        Some("itunes") => itunes_candidates(engine, call.head, &path)?,
+        Some(other) => {
+            return Err(
+                LabeledError::new(format!("unknown provider '{other}'")).with_label(
+                    "expected caa or itunes",
+                    provider.map(|p| p.span).unwrap_or(call.head),
+                ),
+            )
+        }
+    };
+
+    let Some(select) = call.get_flag::<Spanned<i64>>("select")? else {
+        let rows = candidates
+            .iter()
+            .enumerate()
+            .map(|(index, c)| {
+                Value::record(
+                    record! {
+                        "index" => Value::int(index as i64, call.head),
+                        "artist" => Value::string(&c.artist, call.head),
+                        "album" => Value::string(&c.album, call.head),
+                        "url" => Value::string(&c.url, call.head),
+                    },
+                    call.head,
+                )
+            })
+            .collect();
+        return Ok(Value::list(rows, call.head));
+    };
+
+    let candidate = usize::try_from(select.item)
+        .ok()
+        .and_then(|i| candidates.get(i))
+        .ok_or_else(|| {
+            LabeledError::new(format!("no candidate with index {}", select.item)).with_label(
+                format!("{} candidates found", candidates.len()),
+                select.span,
+            )
+        })?;
+
+    let data = match http_get(engine, call.head, &candidate.url)? {
+        Value::Binary { val, .. } => val,
+        other => {
+            return Err(
+                LabeledError::new(format!("expected image data, got {}", other.get_type()))
+                    .with_label("unexpected response", select.span),
+            )
+        }
+    };
+    let picture = Picture::from_reader(&mut data.as_slice()).map_err(|e| {
+        LabeledError::new(e.to_string())
+            .with_label("downloaded artwork is not a supported image", select.span)
+    })?;
+    embed_front_cover(call, path, picture)
+}
+
+/// Builds Cover Art Archive candidates, using the MusicBrainz release ID tag when present
+/// and otherwise searching MusicBrainz by album and artist.
+fn caa_candidates(
+    engine: &EngineInterface,
+    span: Span,
+    path: &Path,
+) -> Result<Vec<ArtworkCandidate>, LabeledError> {
+    let (artist, album, release_id) = lookup_tags(path, span)?;
+
+    if let Some(id) = release_id {
+        return Ok(vec![ArtworkCandidate {
+            artist,
+            album,
+            url: format!("https://coverartarchive.org/release/{id}/front-500"),
+        }]);
+    }
+
+    let query = format!("release:\"{album}\" AND artist:\"{artist}\"");
+    let url = format!(
+        "https://musicbrainz.org/ws/2/release/?fmt=json&limit=10&query={}",
+        percent_encode(&query)
+    );
+    let response = http_get(engine, span, &url)?;
+    let releases = response
+        .get_data_by_key("releases")
+        .and_then(|v| v.into_list().ok())
+        .unwrap_or_default();
+
+    Ok(releases
+        .iter()
+        .filter_map(|release| {
+            let id = release.get_data_by_key("id")?.coerce_into_string().ok()?;
+            let title = release
+                .get_data_by_key("title")
+                .and_then(|v| v.coerce_into_string().ok())
+                .unwrap_or_default();
+            let credit = release
+                .get_data_by_key("artist-credit")
+                .and_then(|v| v.into_list().ok())
+                .and_then(|credits| credits.first()?.get_data_by_key("name"))
+                .and_then(|v| v.coerce_into_string().ok())
+                .unwrap_or_default();
+            Some(ArtworkCandidate {
+                artist: credit,
+                album: title,
+                url: format!("https://coverartarchive.org/release/{id}/front-500"),
+            })
+        })
+        .collect())
+}
+
+/// Builds iTunes Search API candidates from the album and artist tags, requesting
+/// 600×600 artwork instead of the 100×100 thumbnails the API returns by default.
+fn itunes_candidates(
+    engine: &EngineInterface,
+    span: Span,
+    path: &Path,
+) -> Result<Vec<ArtworkCandidate>, LabeledError> {
+    let (artist, album, _) = lookup_tags(path, span)?;
+    let url = format!(
+        "https://itunes.apple.com/search?entity=album&limit=10&term={}",
+        percent_encode(&format!("{artist} {album}"))
+    );
+    let response = http_get(engine, span, &url)?;
+    let results = response
+        .get_data_by_key("results")
+        .and_then(|v| v.into_list().ok())
+        .unwrap_or_default();
+
+    Ok(results
+        .iter()
+        .filter_map(|result| {
+            let thumb = result
+                .get_data_by_key("artworkUrl100")?
+                .coerce_into_string()
+                .ok()?;
+            let field = |key: &str| {
+                result
+                    .get_data_by_key(key)
+                    .and_then(|v| v.coerce_into_string().ok())
+                    .unwrap_or_default()
+            };
+            Some(ArtworkCandidate {
+                artist: field("artistName"),
+                album: field("collectionName"),
+                url: thumb.replace("100x100bb", "600x600bb"),
+            })
+        })
+        .collect())
+}
+
+/// Reads the artist (album artist preferred), album, and MusicBrainz release ID tags.
+fn lookup_tags(path: &Path, span: Span) -> Result<(String, String, Option<String>), LabeledError> {
+    let tagged_file = read_from_path(path)
+        .map_err(|e| LabeledError::new(e.to_string()).with_label("error reading tags", span))?;
+    let tag = tagged_file.primary_tag().ok_or_else(|| {
+        LabeledError::new("file has no tags to search with").with_label("no tags", span)
+    })?;
+    let get = |key: ItemKey| tag.get_string(key).map(str::to_string);
+
+    let artist = get(ItemKey::AlbumArtist).or_else(|| get(ItemKey::TrackArtist));
+    let album = get(ItemKey::AlbumTitle);
+    let release_id = get(ItemKey::MusicBrainzReleaseId);
+
+    match (artist, album) {
+        (Some(artist), Some(album)) => Ok((artist, album, release_id)),
+        _ if release_id.is_some() => Ok((String::new(), String::new(), release_id)),
+        _ => Err(
+            LabeledError::new("album and artist tags are required to search for artwork")
+                .with_label("missing tags", span)
+                .with_help("set them with `sound meta set -k album` / `-k artist`"),
+        ),
+    }
+}
+
+/// Fetches `url` through Nushell's `http get`, returning parsed JSON or raw binary.
+fn http_get(engine: &EngineInterface, span: Span, url: &str) -> Result<Value, LabeledError> {
+    let decl_id = engine.find_decl("http get")?.ok_or_else(|| {
+        LabeledError::new("`http get` is not available in this Nushell")
+            .with_label("cannot fetch artwork", span)
+    })?;
+    let user_agent = format!("nu_plugin_audio_hook/{}", env!("CARGO_PKG_VERSION"));
+    let call = EvaluatedCall::new(span)
+        .with_positional(Value::string(url, span))
+        .with_named(
+            Spanned {
+                item: "headers",
+                span,
+            },
+            Value::list(
+                vec![
+                    Value::string("User-Agent", span),
+                    Value::string(user_agent, span),
+                ],
+                span,
+            ),
+        );
+    engine
+        .call_decl(decl_id, call, PipelineData::empty(), true, false)?
+        .into_value(span)
+        .map_err(LabeledError::from)
+}
+
+/// Percent-encodes `input` for use in a URL query component.
+fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
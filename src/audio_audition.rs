@@ -0,0 +1,307 @@
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute,
+    terminal::{Clear, ClearType},
+};
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, record, Category, DynamicSuggestion, Example, LabeledError, Signature, Span,
+    Spanned, SyntaxShape, Type, Value,
+};
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{stderr, Write},
+    path::Path,
+    process::Command,
+    time::Duration,
+};
+
+use crate::{
+    completions::{encode_targets, ENCODE_TARGETS},
+    ear_guard::{ear_guard_flags, EarGuard},
+    multitrack::{RawModeGuard, SyncedPlayback},
+    pcm::Pcm,
+    utils::{format_duration, load_file_path, not_installed, run_tool},
+    Sound,
+};
+
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const SEEK_STEP: Duration = Duration::from_secs(5);
+
+/// Nushell command `sound audition` — compare delivery encodings by ear.
+///
+/// Each `--as` entry is encoded with ffmpeg into temp space and decoded back, then all
+/// versions play in sync. Number keys switch between them without losing the position:
+/// `0` is the original, `1`–`9` the encodings in the order given.
+pub struct SoundAuditionCmd;
+impl SimplePluginCommand for SoundAuditionCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound audition"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound audition")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .required("File Path", SyntaxShape::Filepath, "source file to encode")
+            .required_named(
+                "as",
+                SyntaxShape::String,
+                "comma-separated format:setting list, e.g. \"mp3:128k, opus:96k, ogg:q5\"",
+                Some('a'),
+            )
+            .category(Category::Experimental);
+        ear_guard_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "compare three delivery formats against the master",
+            example: "sound audition master.wav --as \"mp3:128k, opus:96k, ogg:q5\"",
+            result: None,
+        }]
+    }
+
+    fn description(&self) -> &str {
+        "encode a file to several formats (via ffmpeg) and A/B them with number keys; \
+        0 plays the original, space pauses, ←/→ seek, q quits"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "as" => Some(encode_targets()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        audition(engine, call)
+    }
+}
+
+/// One `--as` entry, e.g. `opus:96k`.
+struct Encoding {
+    label: String,
+    extension: &'static str,
+    args: Vec<String>,
+}
+
+fn audition(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let (file_span, path) = load_file_path(engine, call)?;
+    let spec: Spanned<String> = call.get_flag("as")?.ok_or_else(|| {
+        LabeledError::new("missing --as").with_label("list of encodings is required", span)
+    })?;
+    let encodings = parse_encodings(&spec)?;
+
+    let original = File::open(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error trying to open the file", file_span)
+    })?;
+    let mut clips = vec![Pcm::decode(original, file_span)?];
+    let mut rows = vec![variant_row(0, "original", &path, &clips[0], span)];
+
+    let temp_base = std::env::temp_dir().join(format!(
+        "nu_plugin_audio_hook-audition-{}",
+        std::process::id()
+    ));
+    let mut temp_files = Vec::new();
+    let encoded = (|| {
+        for (index, encoding) in encodings.iter().enumerate() {
+            let encoded = temp_base.with_extension(format!("{index}.{}", encoding.extension));
+            let decoded = temp_base.with_extension(format!("{index}.wav"));
+            temp_files.push(encoded.clone());
+            temp_files.push(decoded.clone());
+
+            let mut args = vec![OsString::from("-i"), path.clone().into_os_string()];
+            args.extend(encoding.args.iter().map(OsString::from));
+            args.push(encoded.clone().into_os_string());
+            ffmpeg(&args, span)?;
+            ffmpeg(
+                &[
+                    OsString::from("-i"),
+                    encoded.clone().into_os_string(),
+                    decoded.clone().into_os_string(),
+                ],
+                span,
+            )?;
+
+            let file = File::open(&decoded).map_err(|e| {
+                LabeledError::new(e.to_string()).with_label("error reading decoded preview", span)
+            })?;
+            let clip = Pcm::decode(file, span)?;
+            rows.push(variant_row(
+                index + 1,
+                &encoding.label,
+                &encoded,
+                &clip,
+                span,
+            ));
+            clips.push(clip);
+        }
+        Ok::<(), LabeledError>(())
+    })();
+    for file in &temp_files {
+        let _ = std::fs::remove_file(file);
+    }
+    encoded?;
+
+    let labels: Vec<&str> = std::iter::once("original")
+        .chain(encodings.iter().map(|e| e.label.as_str()))
+        .collect();
+    compare(engine, call, &clips, &labels)?;
+    Ok(Value::list(rows, span))
+}
+
+/// Parses `"mp3:128k, opus:96k, ogg:q5"` into ffmpeg encoder arguments. A setting ending
+/// in `k` is a bitrate, one starting with `q` a VBR quality level.
+fn parse_encodings(spec: &Spanned<String>) -> Result<Vec<Encoding>, LabeledError> {
+    let invalid =
+        |msg: String| LabeledError::new("invalid encoding list").with_label(msg, spec.span);
+    let mut encodings = Vec::new();
+    for entry in spec
+        .item
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+    {
+        let (format, setting) = entry.split_once(':').unwrap_or((entry, ""));
+        let format = format.trim().to_lowercase();
+        let setting = setting.trim().to_lowercase();
+        let Some(&(_, extension, codec)) =
+            ENCODE_TARGETS.iter().find(|(name, _, _)| *name == format)
+        else {
+            return Err(invalid(format!(
+                "unknown format '{format}' (expected mp3, opus, ogg, aac or flac)"
+            )));
+        };
+        let mut args = vec!["-c:a".to_string(), codec.to_string()];
+        if let Some(quality) = setting.strip_prefix('q') {
+            args.extend(["-q:a".to_string(), quality.to_string()]);
+        } else if setting.ends_with('k') {
+            args.extend(["-b:a".to_string(), setting.clone()]);
+        } else if !setting.is_empty() {
+            return Err(invalid(format!(
+                "setting '{setting}' should be a bitrate like 128k or a quality like q5"
+            )));
+        }
+        encodings.push(Encoding {
+            label: entry.to_string(),
+            extension,
+            args,
+        });
+    }
+    if encodings.is_empty() || encodings.len() > 9 {
+        return Err(invalid("give between 1 and 9 encodings".to_string()));
+    }
+    Ok(encodings)
+}
+
+fn ffmpeg(args: &[OsString], span: Span) -> Result<(), LabeledError> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(args);
+    run_tool(&mut command, span)?.ok_or_else(|| {
+        not_installed(
+            "ffmpeg",
+            "ffmpeg not found on PATH",
+            "install ffmpeg to encode previews",
+            span,
+        )
+    })?;
+    Ok(())
+}
+
+fn variant_row(key: usize, label: &str, file: &Path, clip: &Pcm, span: Span) -> Value {
+    let size = std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+    let seconds = clip.duration().as_secs_f64();
+    let kbps = if seconds > 0.0 {
+        (size as f64 * 8.0 / seconds / 1000.0).round()
+    } else {
+        0.0
+    };
+    Value::record(
+        record! {
+            "key" => Value::int(key as i64, span),
+            "encoding" => Value::string(label, span),
+            "size" => Value::filesize(size as i64, span),
+            "bitrate_kbps" => Value::float(kbps, span),
+        },
+        span,
+    )
+}
+
+/// Plays all clips in sync and lets the listener switch between them with number keys.
+fn compare(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    clips: &[Pcm],
+    labels: &[&str],
+) -> Result<(), LabeledError> {
+    let guard = EarGuard::from_call(engine, call)?;
+    let playback = SyncedPlayback::start(clips, guard, call.head)?;
+    let _raw = RawModeGuard::enable(call.head)?;
+    let mut err = stderr();
+    let mut active = 0;
+    let mut paused = false;
+    playback.solo(active);
+
+    while !playback.finished() {
+        engine.signals().check(&call.head)?;
+        if event::poll(KEY_POLL_INTERVAL).unwrap_or(false) {
+            if let Ok(Event::Key(KeyEvent { code, kind, .. })) = event::read() {
+                if kind == KeyEventKind::Press {
+                    match code {
+                        KeyCode::Char(c @ '0'..='9') => {
+                            let index = c as usize - '0' as usize;
+                            if index < clips.len() {
+                                active = index;
+                                playback.solo(active);
+                            }
+                        }
+                        KeyCode::Char(' ') => paused = playback.toggle_pause(),
+                        KeyCode::Right | KeyCode::Char('l') => {
+                            playback.seek(playback.position() + SEEK_STEP)
+                        }
+                        KeyCode::Left | KeyCode::Char('h') => {
+                            playback.seek(playback.position().saturating_sub(SEEK_STEP))
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => break,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
+        let _ = write!(
+            err,
+            "{} [{active}] {}  {} / {}",
+            if paused { "||" } else { ">" },
+            labels[active],
+            format_duration(playback.position()),
+            format_duration(playback.duration()),
+        );
+        let _ = err.flush();
+    }
+
+    playback.stop();
+    let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
+    Ok(())
+}
@@ -0,0 +1,279 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{record, Category, Example, LabeledError, Signature, Span, Type, Value};
+use rodio::cpal::{
+    self,
+    traits::{DeviceTrait, HostTrait},
+    SupportedBufferSize, SupportedStreamConfig,
+};
+use std::process::Command;
+
+use crate::{utils::run_tool, Sound};
+
+/// ALSA devices that route into the sound server's default sink, so they inherit its
+/// Bluetooth details.
+const SOUND_SERVER_DEVICES: [&str; 3] = ["default", "pulse", "pipewire"];
+
+/// Nushell command `sound devices` — lists audio output devices.
+///
+/// With `--verbose`, each device also reports its default stream configuration and the
+/// buffer latency range its host allows. When the sound server's default sink is a
+/// Bluetooth device, the devices that route into it get its codec and the latency the
+/// server measures, which includes the Bluetooth link.
+pub struct SoundDevicesCmd;
+
+impl SimplePluginCommand for SoundDevicesCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound devices"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound devices")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .switch(
+                "verbose",
+                "add sample rate, channel layout, latency and Bluetooth codec",
+                Some('v'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "list output devices",
+                example: "sound devices",
+                result: None,
+            },
+            Example {
+                description: "see why Bluetooth headphones lag: codec and measured latency",
+                example: "sound devices --verbose | where default | get bluetooth",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "list audio output devices, with their configuration and latency in verbose mode"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        list_devices(call.has_flag("verbose")?, call.head)
+    }
+}
+
+fn list_devices(verbose: bool, span: Span) -> Result<Value, LabeledError> {
+    let bluetooth = if verbose {
+        default_sink().filter(|sink| sink.bluetooth)
+    } else {
+        None
+    };
+    let mut rows = Vec::new();
+    for id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(id) else {
+            continue;
+        };
+        let default_name = host.default_output_device().and_then(|d| d.name().ok());
+        let devices = host.output_devices().map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("audio device exception", span)
+        })?;
+        for device in devices {
+            let Ok(name) = device.name() else {
+                continue;
+            };
+            let is_default = default_name.as_deref() == Some(name.as_str());
+            let mut row = record! {
+                "name" => Value::string(&name, span),
+                "host" => Value::string(id.name(), span),
+                "default" => Value::bool(is_default, span),
+            };
+            if verbose {
+                let config = device.default_output_config().ok();
+                for (column, value) in config_columns(config.as_ref(), span) {
+                    row.push(column, value);
+                }
+                let routed = is_default || SOUND_SERVER_DEVICES.contains(&name.as_str());
+                let bluetooth = bluetooth
+                    .as_ref()
+                    .filter(|_| routed)
+                    .map_or(Value::nothing(span), |sink| sink.to_value(span));
+                row.push("bluetooth", bluetooth);
+            }
+            rows.push(Value::record(row, span));
+        }
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Names of the devices `sound devices` lists, each with its host, for `--device`
+/// completions. A name several hosts share is listed once.
+pub fn output_device_names() -> Vec<(String, &'static str)> {
+    let mut names: Vec<(String, &'static str)> = Vec::new();
+    for id in cpal::available_hosts() {
+        let Some(devices) = cpal::host_from_id(id)
+            .ok()
+            .and_then(|host| host.output_devices().ok())
+        else {
+            continue;
+        };
+        for name in devices.filter_map(|device| device.name().ok()) {
+            if !names.iter().any(|(known, _)| *known == name) {
+                names.push((name, id.name()));
+            }
+        }
+    }
+    names
+}
+
+/// The verbose columns for a device's default output configuration; all null when the
+/// device could not report one.
+fn config_columns(config: Option<&SupportedStreamConfig>, span: Span) -> [(&str, Value); 5] {
+    let Some(config) = config else {
+        return [
+            ("sample_rate", Value::nothing(span)),
+            ("channels", Value::nothing(span)),
+            ("channel_layout", Value::nothing(span)),
+            ("sample_format", Value::nothing(span)),
+            ("latency_ms", Value::nothing(span)),
+        ];
+    };
+    let rate = config.sample_rate().0;
+    let latency = match config.buffer_size() {
+        SupportedBufferSize::Range { min, max } => {
+            let ms = |frames: u32| Value::float(round2(frames as f64 * 1000.0 / rate as f64), span);
+            Value::record(
+                record! {
+                    "min" => ms(*min),
+                    "max" => ms(*max),
+                },
+                span,
+            )
+        }
+        SupportedBufferSize::Unknown => Value::nothing(span),
+    };
+    [
+        ("sample_rate", Value::int(rate as i64, span)),
+        ("channels", Value::int(config.channels() as i64, span)),
+        (
+            "channel_layout",
+            Value::string(channel_layout(config.channels()), span),
+        ),
+        (
+            "sample_format",
+            Value::string(config.sample_format().to_string(), span),
+        ),
+        ("latency_ms", latency),
+    ]
+}
+
+/// Conventional name of a channel count's speaker layout.
+fn channel_layout(channels: u16) -> String {
+    match channels {
+        1 => "mono".to_string(),
+        2 => "stereo".to_string(),
+        3 => "2.1".to_string(),
+        4 => "quad".to_string(),
+        6 => "5.1".to_string(),
+        8 => "7.1".to_string(),
+        n => format!("{n} channels"),
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// What PulseAudio (or PipeWire's PulseAudio server) reports about a sink.
+#[derive(Debug, Default, PartialEq)]
+struct Sink {
+    name: String,
+    bluetooth: bool,
+    codec: Option<String>,
+    profile: Option<String>,
+    /// Latency the server measured, in microseconds.
+    latency_us: Option<u64>,
+}
+
+impl Sink {
+    fn to_value(&self, span: Span) -> Value {
+        let text = |value: &Option<String>| {
+            value
+                .as_ref()
+                .map_or(Value::nothing(span), |v| Value::string(v, span))
+        };
+        Value::record(
+            record! {
+                "sink" => Value::string(&self.name, span),
+                "codec" => text(&self.codec),
+                "profile" => text(&self.profile),
+                "latency_ms" => self.latency_us.map_or(Value::nothing(span), |us| {
+                    Value::float(round2(us as f64 / 1000.0), span)
+                }),
+            },
+            span,
+        )
+    }
+}
+
+/// The sound server's default sink, from `pactl`. `None` without a PulseAudio or
+/// PipeWire server, which is the case on macOS, Windows and plain ALSA systems.
+fn default_sink() -> Option<Sink> {
+    let info = pactl(&["info"])?;
+    let name = info
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Default Sink:"))?
+        .trim()
+        .to_string();
+    parse_sinks(&pactl(&["list", "sinks"])?)
+        .into_iter()
+        .find(|sink| sink.name == name)
+}
+
+fn pactl(args: &[&str]) -> Option<String> {
+    let mut command = Command::new("pactl");
+    command.args(args).env("LC_ALL", "C");
+    let output = run_tool(&mut command, Span::unknown()).ok().flatten()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses `pactl list sinks`. PulseAudio names the codec `bluetooth.codec`, PipeWire
+/// `api.bluez5.codec`.
+fn parse_sinks(text: &str) -> Vec<Sink> {
+    let mut sinks: Vec<Sink> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with("Sink #") {
+            sinks.push(Sink::default());
+            continue;
+        }
+        let Some(sink) = sinks.last_mut() else {
+            continue;
+        };
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Name:") {
+            sink.name = name.trim().to_string();
+        } else if let Some(latency) = line.strip_prefix("Latency:") {
+            sink.latency_us = latency
+                .split_whitespace()
+                .next()
+                .and_then(|us| us.parse().ok());
+        } else if let Some((key, value)) = line.split_once(" = ") {
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim() {
+                "device.bus" | "device.api" if value == "bluetooth" || value == "bluez5" => {
+                    sink.bluetooth = true
+                }
+                "bluetooth.codec" | "api.bluez5.codec" => sink.codec = Some(value),
+                "bluetooth.protocol" | "api.bluez5.profile" => sink.profile = Some(value),
+                _ => {}
+            }
+        }
+    }
+    sinks
+}
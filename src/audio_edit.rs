@@ -0,0 +1,539 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Range, Signature, Spanned, SyntaxShape, Type, Value,
+};
+use std::{f32::consts::FRAC_PI_2, fs::File, ops::Bound, path::PathBuf, time::Duration};
+
+use crate::{
+    audio_analyze::onset_strength,
+    network::{http_request, url_extension},
+    pcm::{seconds_value, Pcm, Section},
+    remote::{resolve_remote, RangeReader},
+    utils::{absolute_path, resolve_filepath, stem_with_suffix},
+    validate::{duration_flag, duration_shape},
+    Sound,
+};
+
+const DEFAULT_LOOP_CROSSFADE: Duration = Duration::from_millis(500);
+const DEFAULT_VARIANT_COUNT: i64 = 5;
+
+/// Nushell command `sound slice` — cuts a loop into sequentially numbered slices.
+///
+/// The clip is divided into `--beats` equal parts. With `--snap`, each cut point moves to
+/// the strongest transient within a quarter slice of its grid position, so drum hits land
+/// at the start of their slice instead of being split.
+pub struct SoundSliceCmd;
+impl SimplePluginCommand for SoundSliceCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound slice"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound slice")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .required("File Path", SyntaxShape::Filepath, "loop to slice")
+            .required_named(
+                "beats",
+                SyntaxShape::Int,
+                "number of slices to cut the clip into",
+                Some('b'),
+            )
+            .required_named(
+                "out-dir",
+                SyntaxShape::Filepath,
+                "directory the slices are written to (created if missing)",
+                Some('o'),
+            )
+            .switch(
+                "snap",
+                "move cut points to the nearest transient instead of the exact grid",
+                Some('s'),
+            )
+            .switch(
+                "gapless",
+                "trim the encoder delay and padding of MP3/AAC files before processing",
+                Some('g'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "cut a one-bar break into 16 equal slices",
+                example: "sound slice breakbeat.wav --beats 16 --out-dir slices/",
+                result: None,
+            },
+            Example {
+                description: "cut on drum hits near the grid",
+                example: "sound slice breakbeat.wav --beats 8 --out-dir slices/ --snap",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "cut a clip into equal or transient-aligned slices saved as numbered WAV files"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        slice(engine, call)
+    }
+}
+
+fn slice(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let (_, path, pcm) = Pcm::load_gapless(engine, call)?;
+
+    let beats: Spanned<i64> = call.get_flag("beats")?.ok_or_else(|| {
+        LabeledError::new("missing --beats").with_label("slice count is required", span)
+    })?;
+    let frames = pcm.frames();
+    if beats.item < 1 || beats.item as usize > frames {
+        return Err(LabeledError::new("invalid slice count")
+            .with_label(format!("must be between 1 and {frames}"), beats.span));
+    }
+    let count = beats.item as usize;
+
+    let out_dir: Spanned<String> = call.get_flag("out-dir")?.ok_or_else(|| {
+        LabeledError::new("missing --out-dir").with_label("output directory is required", span)
+    })?;
+    let out_dir = resolve_output_dir(engine, &out_dir)?;
+
+    let grid: Vec<usize> = (0..=count).map(|i| i * frames / count).collect();
+    let cuts = if call.has_flag("snap")? {
+        snap_to_transients(&pcm, &grid)
+    } else {
+        grid
+    };
+
+    let width = count.to_string().len().max(2);
+    let seconds = |frame: usize| frame as f64 / pcm.sample_rate as f64;
+
+    let mut rows = Vec::with_capacity(count);
+    for (index, window) in cuts.windows(2).enumerate() {
+        let name = stem_with_suffix(&path, "slice", &format!("-{:0width$}.wav", index + 1));
+        let file = out_dir.join(name);
+        std::fs::write(&file, pcm.slice(window[0], window[1]).to_wav()?).map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error writing {}", file.display()), span)
+        })?;
+        rows.push(Value::record(
+            record! {
+                "file" => Value::string(file.to_string_lossy(), span),
+                "start" => seconds_value(seconds(window[0]), span),
+                "end" => seconds_value(seconds(window[1]), span),
+            },
+            span,
+        ));
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Moves every inner cut point to the strongest onset within a quarter slice of its grid
+/// position. The first and last cut stay at the clip boundaries, and cuts are kept in
+/// order so no slice ends up empty.
+fn snap_to_transients(pcm: &Pcm, grid: &[usize]) -> Vec<usize> {
+    let (hop, strength) = onset_strength(pcm);
+    let mut cuts = grid.to_vec();
+    let reach = (grid[1] - grid[0]) / 4;
+    if reach < hop {
+        return cuts;
+    }
+    for i in 1..cuts.len() - 1 {
+        let lo = (grid[i].saturating_sub(reach) / hop).max(cuts[i - 1] / hop + 1);
+        let hi = ((grid[i] + reach) / hop).min(strength.len().saturating_sub(1));
+        if let Some(best) = (lo..=hi).max_by(|a, b| strength[*a].total_cmp(&strength[*b])) {
+            if strength[best] > 0.0 {
+                cuts[i] = (best * hop).clamp(cuts[i - 1] + 1, grid[i + 1] - 1);
+            }
+        }
+    }
+    cuts
+}
+
+/// Nushell command `sound loopify` — makes a clip loop seamlessly.
+///
+/// The last `--crossfade` of the clip is faded into its first `--crossfade` with an
+/// equal-power curve and the tail is dropped, so playback running off the end continues
+/// exactly where the loop restarts. The result is shorter than the input by the
+/// crossfade length.
+pub struct SoundLoopifyCmd;
+impl SimplePluginCommand for SoundLoopifyCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound loopify"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound loopify")
+            .input_output_types(vec![(Type::Nothing, Type::Binary)])
+            .required(
+                "File Path",
+                SyntaxShape::Filepath,
+                "clip to turn into a loop",
+            )
+            .named(
+                "crossfade",
+                duration_shape(),
+                "length of the crossfade between the end and the start (default: 500ms)",
+                Some('c'),
+            )
+            .switch(
+                "gapless",
+                "trim the encoder delay and padding of MP3/AAC files before processing",
+                Some('g'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "make an ambience recording loop seamlessly",
+                example: "sound loopify ambience.wav --crossfade 500ms | save --raw ambience-loop.wav",
+                result: None,
+            },
+            Example {
+                description: "loop an MP3 without the encoder's silence at the seam",
+                example: "sound loopify pad.mp3 --gapless | save --raw pad-loop.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "crossfade the end of a clip into its start to make it loop seamlessly (outputs WAV)"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        loopify(engine, call)
+    }
+}
+
+fn loopify(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let (file_span, _, pcm) = Pcm::load_gapless(engine, call)?;
+    let crossfade = duration_flag(call, "crossfade")?.unwrap_or(DEFAULT_LOOP_CROSSFADE);
+    let fade = pcm.frames_for(crossfade);
+    let frames = pcm.frames();
+    if fade == 0 || fade * 2 > frames {
+        return Err(
+            LabeledError::new("crossfade does not fit the clip").with_label(
+                format!(
+                    "crossfade must be non-zero and at most half of the clip ({:.3}s)",
+                    pcm.duration().as_secs_f64()
+                ),
+                file_span,
+            ),
+        );
+    }
+
+    let channels = pcm.channels as usize;
+    let tail_start = frames - fade;
+    let mut samples = pcm.samples[..tail_start * channels].to_vec();
+    for i in 0..fade {
+        let t = (i as f32 + 0.5) / fade as f32 * FRAC_PI_2;
+        let (fade_in, fade_out) = (t.sin(), t.cos());
+        for c in 0..channels {
+            let head = pcm.samples[i * channels + c];
+            let tail = pcm.samples[(tail_start + i) * channels + c];
+            samples[i * channels + c] = head * fade_in + tail * fade_out;
+        }
+    }
+
+    let output = Pcm::new(samples, pcm.channels, pcm.sample_rate);
+    Ok(Value::binary(output.to_wav()?, span))
+}
+
+/// Nushell command `sound trim` — cuts a section out of a local or remote file.
+///
+/// The decoder seeks straight to `--start` instead of decoding everything before it. A
+/// remote file is read through a [`RangeReader`] with coarse seeking, so only the headers
+/// and the blocks around the cut are downloaded rather than the whole file.
+pub struct SoundTrimCmd;
+impl SimplePluginCommand for SoundTrimCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound trim"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound trim")
+            .input_output_types(vec![(Type::Nothing, Type::Binary)])
+            .required(
+                "File Path",
+                SyntaxShape::Filepath,
+                "file, http(s) URL or s3:// object to cut from",
+            )
+            .named(
+                "start",
+                duration_shape(),
+                "where the section begins (default: the start of the file)",
+                Some('s'),
+            )
+            .named(
+                "duration",
+                duration_shape(),
+                "length of the section (default: up to the end of the file)",
+                Some('d'),
+            )
+            .named(
+                "headers",
+                SyntaxShape::Any,
+                "extra HTTP headers for a remote file, as a record or [name value] list",
+                Some('H'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "cut a minute out of a long recording",
+                example: "sound trim lecture.mp3 --start 1:30:00 --duration 1min | save --raw excerpt.wav",
+                result: None,
+            },
+            Example {
+                description: "grab a clip from a remote file without downloading all of it",
+                example: "sound trim https://example.com/podcast.mp3 --start 20min --duration 30sec | save --raw clip.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "cut a section out of a local or remote file, reading only the parts it needs (outputs WAV)"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        trim(engine, call)
+    }
+}
+
+fn trim(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let path: Spanned<String> = call.req(0)?;
+    let start = duration_flag(call, "start")?.unwrap_or_default();
+    let length = duration_flag(call, "duration")?;
+
+    let pcm = match resolve_remote(engine, &path.item, path.span)? {
+        Some(url) => {
+            let headers = call.get_flag_value("headers");
+            let head = http_request(engine, path.span, "http head", &url, headers.as_ref(), &[])?
+                .into_value(path.span)?;
+            let reader = RangeReader::open(engine, path.span, &url, headers, &head)?;
+            let section = Section {
+                start,
+                length,
+                coarse: true,
+                hint: url_extension(&url).map(str::to_string),
+            };
+            Pcm::decode_section(reader.size(), reader, section, path.span)?
+        }
+        None => {
+            let resolved = resolve_filepath(engine, path.span, PathBuf::from(&path.item))?;
+            let file = File::open(&resolved).map_err(|e| {
+                LabeledError::new(e.to_string())
+                    .with_label("error trying to open the file", path.span)
+            })?;
+            let len = file.metadata().map(|m| m.len()).map_err(|e| {
+                LabeledError::new(e.to_string())
+                    .with_label("error reading file metadata", path.span)
+            })?;
+            let section = Section {
+                start,
+                length,
+                coarse: false,
+                hint: None,
+            };
+            Pcm::decode_section(len, file, section, path.span)?
+        }
+    };
+    Ok(Value::binary(pcm.to_wav()?, call.head))
+}
+
+/// Nushell command `sound variants` — renders pitch/speed variations of a sample.
+///
+/// Variants are spread evenly across the `--pitch` range in semitones and rendered with
+/// varispeed, so higher variants are also shorter, the way game engines randomize
+/// repeated sound effects.
+pub struct SoundVariantsCmd;
+impl SimplePluginCommand for SoundVariantsCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound variants"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound variants")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .required("File Path", SyntaxShape::Filepath, "sample to vary")
+            .required_named(
+                "pitch",
+                SyntaxShape::Range,
+                "pitch range in semitones, e.g. -2..2",
+                Some('p'),
+            )
+            .named(
+                "count",
+                SyntaxShape::Int,
+                "number of variants to render (default: 5)",
+                Some('c'),
+            )
+            .named(
+                "out-dir",
+                SyntaxShape::Filepath,
+                "directory the variants are written to (default: next to the input file)",
+                Some('o'),
+            )
+            .switch(
+                "gapless",
+                "trim the encoder delay and padding of MP3/AAC files before processing",
+                Some('g'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "render five variations between two semitones down and up",
+            example: "sound variants hit.wav --pitch -2..2 --count 5 --out-dir sfx/",
+            result: None,
+        }]
+    }
+
+    fn description(&self) -> &str {
+        "render pitch/speed variations of a sample as numbered WAV files"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        variants(engine, call)
+    }
+}
+
+fn variants(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let (_, path, pcm) = Pcm::load_gapless(engine, call)?;
+
+    let pitch: Value = call.get_flag("pitch")?.ok_or_else(|| {
+        LabeledError::new("missing --pitch").with_label("pitch range is required", span)
+    })?;
+    let (low, high) = semitone_bounds(&pitch)?;
+
+    let count: Spanned<i64> = call.get_flag("count")?.unwrap_or(Spanned {
+        item: DEFAULT_VARIANT_COUNT,
+        span,
+    });
+    if !(1..=999).contains(&count.item) {
+        return Err(LabeledError::new("invalid variant count")
+            .with_label("must be between 1 and 999", count.span));
+    }
+    let count = count.item as usize;
+
+    let out_dir = match call.get_flag::<Spanned<String>>("out-dir")? {
+        Some(dir) => resolve_output_dir(engine, &dir)?,
+        None => path.parent().map(PathBuf::from).unwrap_or_default(),
+    };
+    let width = count.to_string().len().max(2);
+
+    let mut rows = Vec::with_capacity(count);
+    for index in 0..count {
+        let semitones = if count == 1 {
+            (low + high) / 2.0
+        } else {
+            low + (high - low) * index as f64 / (count - 1) as f64
+        };
+        let speed = 2f64.powf(semitones / 12.0);
+        let name = stem_with_suffix(&path, "variant", &format!("-{:0width$}.wav", index + 1));
+        let file = out_dir.join(name);
+        std::fs::write(&file, pcm.varispeed(speed).to_wav()?).map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error writing {}", file.display()), span)
+        })?;
+        rows.push(Value::record(
+            record! {
+                "file" => Value::string(file.to_string_lossy(), span),
+                "pitch" => Value::float((semitones * 100.0).round() / 100.0, span),
+                "speed" => Value::float((speed * 10000.0).round() / 10000.0, span),
+            },
+            span,
+        ));
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Reads the bounds of a bounded int or float range as semitones, limited to ±24.
+fn semitone_bounds(value: &Value) -> Result<(f64, f64), LabeledError> {
+    let span = value.span();
+    let (start, end) = match value {
+        Value::Range { val, .. } => match val.as_ref() {
+            Range::IntRange(range) => (
+                range.start() as f64,
+                match range.end() {
+                    Bound::Included(end) | Bound::Excluded(end) => Some(end as f64),
+                    Bound::Unbounded => None,
+                },
+            ),
+            Range::FloatRange(range) => (
+                range.start(),
+                match range.end() {
+                    Bound::Included(end) | Bound::Excluded(end) => Some(end),
+                    Bound::Unbounded => None,
+                },
+            ),
+        },
+        _ => {
+            return Err(LabeledError::new("invalid pitch range")
+                .with_label("expected a range such as -2..2", span))
+        }
+    };
+    let end = end.ok_or_else(|| {
+        LabeledError::new("invalid pitch range").with_label("range needs an end", span)
+    })?;
+    let (low, high) = (start.min(end), start.max(end));
+    if low < -24.0 || high > 24.0 {
+        return Err(LabeledError::new("pitch range too wide")
+            .with_label("semitones must be within -24..24", span));
+    }
+    Ok((low, high))
+}
+
+fn resolve_output_dir(
+    engine: &EngineInterface,
+    dir: &Spanned<String>,
+) -> Result<PathBuf, LabeledError> {
+    let path = absolute_path(engine, PathBuf::from(&dir.item), dir.span)?;
+    std::fs::create_dir_all(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error creating output directory", dir.span)
+    })?;
+    Ok(path)
+}
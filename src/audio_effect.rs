@@ -0,0 +1,212 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value};
+
+use crate::{
+    channel_mix::{downmix_mono, downmix_stereo, upmix_51},
+    dsp::Biquad,
+    pcm::Pcm,
+    Sound,
+};
+
+/// Nushell command `sound effect karaoke` — removes center-panned material.
+///
+/// Lead vocals are usually mixed identically into both channels, so subtracting one
+/// channel from the other (`L - R`) cancels them while keeping anything panned off
+/// center. Bass and kick drums sit in the center too; `--keep-bass` restores the mid
+/// signal below the given frequency so the instrumental keeps its low end.
+pub struct SoundKaraokeCmd;
+impl SimplePluginCommand for SoundKaraokeCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound effect karaoke"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound effect karaoke")
+            .input_output_types(vec![(Type::Nothing, Type::Binary)])
+            .required(
+                "File Path",
+                SyntaxShape::Filepath,
+                "stereo track to process",
+            )
+            .named(
+                "keep-bass",
+                SyntaxShape::Float,
+                "keep center content below this frequency in Hz (e.g. 150)",
+                Some('b'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "create an instrumental version of a song",
+                example: "sound effect karaoke song.flac | save --raw instrumental.wav",
+                result: None,
+            },
+            Example {
+                description: "cancel vocals but keep bass and kick drum",
+                example:
+                    "sound effect karaoke song.flac --keep-bass 150 | save --raw instrumental.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "remove center-panned vocals from a stereo track by channel cancellation (outputs WAV)"
+    }
+
+    fn extra_description(&self) -> &str {
+        "Only the instrumental can be made this way. There is no stem separation: isolating the \
+         vocals, or splitting a mix into drums, bass and other stems, needs a source-separation \
+         model and is not supported."
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        karaoke(engine, call)
+    }
+}
+
+fn karaoke(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let (file_span, _, pcm) = Pcm::load(engine, call)?;
+    if pcm.channels != 2 {
+        return Err(LabeledError::new("karaoke needs a stereo track")
+            .with_label(format!("file has {} channel(s)", pcm.channels), file_span));
+    }
+
+    let keep_bass = call.get_flag::<Spanned<f64>>("keep-bass")?;
+    let mut bass_filter = match keep_bass {
+        Some(cutoff) if cutoff.item <= 0.0 => {
+            return Err(LabeledError::new("invalid cutoff")
+                .with_label("frequency must be positive", cutoff.span))
+        }
+        Some(cutoff) => Some(Biquad::low_pass(cutoff.item, pcm.sample_rate)),
+        None => None,
+    };
+
+    let mut samples = Vec::with_capacity(pcm.samples.len());
+    for frame in pcm.samples.chunks_exact(2) {
+        let (left, right) = (frame[0], frame[1]);
+        let side = (left - right) / 2.0;
+        let bass = bass_filter
+            .as_mut()
+            .map(|f| f.process((left + right) / 2.0))
+            .unwrap_or(0.0);
+        samples.push(bass + side);
+        samples.push(bass - side);
+    }
+
+    let output = Pcm::new(samples, 2, pcm.sample_rate);
+    Ok(Value::binary(output.to_wav()?, call.head))
+}
+
+/// Nushell command `sound channels` — converts between channel layouts.
+///
+/// Downmixing uses ITU-R BS.775 coefficients (center and surrounds at -3 dB, LFE
+/// dropped) rather than discarding channels; upmixing derives a 5.1 signal from stereo
+/// with a passive matrix.
+pub struct SoundChannelsCmd;
+impl SimplePluginCommand for SoundChannelsCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound channels"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound channels")
+            .input_output_types(vec![(Type::Nothing, Type::Binary)])
+            .required("File Path", SyntaxShape::Filepath, "file to convert")
+            .named(
+                "downmix",
+                SyntaxShape::String,
+                "target layout: stereo or mono",
+                None,
+            )
+            .named(
+                "upmix",
+                SyntaxShape::String,
+                "target layout: 5.1 (from stereo)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "fold a 5.1 movie soundtrack to stereo",
+                example: "sound channels movie.flac --downmix stereo | save --raw movie-stereo.wav",
+                result: None,
+            },
+            Example {
+                description: "spread a stereo track over a 5.1 system",
+                example: "sound channels song.flac --upmix 5.1 | save --raw song-51.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "downmix multichannel audio to stereo/mono or upmix stereo to 5.1 (outputs WAV)"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        convert_channels(engine, call)
+    }
+}
+
+fn convert_channels(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let (file_span, _, pcm) = Pcm::load(engine, call)?;
+    let downmix = call.get_flag::<Spanned<String>>("downmix")?;
+    let upmix = call.get_flag::<Spanned<String>>("upmix")?;
+
+    let (target, converted) = match (downmix, upmix) {
+        (Some(target), None) => {
+            let converted = match target.item.as_str() {
+                "stereo" => downmix_stereo(&pcm),
+                "mono" => downmix_mono(&pcm),
+                _ => {
+                    return Err(LabeledError::new("unknown downmix target")
+                        .with_label("expected stereo or mono", target.span))
+                }
+            };
+            (target, converted)
+        }
+        (None, Some(target)) => {
+            if target.item != "5.1" {
+                return Err(LabeledError::new("unknown upmix target")
+                    .with_label("expected 5.1", target.span));
+            }
+            (target, upmix_51(&pcm))
+        }
+        _ => {
+            return Err(LabeledError::new("choose one conversion")
+                .with_label("pass either --downmix or --upmix", call.head))
+        }
+    };
+
+    let converted = converted.ok_or_else(|| {
+        LabeledError::new(format!(
+            "cannot convert {} channels to {}",
+            pcm.channels, target.item
+        ))
+        .with_label("unsupported source layout", file_span)
+    })?;
+    Ok(Value::binary(converted.to_wav()?, call.head))
+}
@@ -0,0 +1,1226 @@
+use lofty::aac::AacFile;
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::file::{AudioFile, FileType, TaggedFile, TaggedFileExt};
+use lofty::id3::v2::{Frame, Id3v2Tag};
+use lofty::iff::aiff::AiffFile;
+use lofty::iff::wav::WavFile;
+use lofty::mpeg::MpegFile;
+use lofty::read_from_path;
+use lofty::tag::{ItemKey, ItemValue, TagExt, TagItem, TagType};
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, PipelineData, Signature, Span, Spanned, SyntaxShape,
+    Type, Value,
+};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::OpenOptions;
+use std::io::Seek;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    audio_meta::primary_tag_mut_or_insert,
+    utils::{collect_audio_files, force_flags, load_file_path, load_path_args, with_write_access},
+    Sound,
+};
+
+/// Nushell command `sound meta genre normalize` — maps freeform genre tags onto a
+/// canonical set across a library.
+///
+/// The map is a record of `canonical name → list of aliases` (given inline or as a file
+/// Nushell can `open`, e.g. TOML). Genres are compared after lowercasing and dropping
+/// punctuation, so "Alt Rock", "alt-rock", and "ALT_ROCK" all match the alias "alt rock".
+pub struct SoundGenreNormalizeCmd;
+impl SimplePluginCommand for SoundGenreNormalizeCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta genre normalize"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound meta genre normalize")
+            .input_output_types(vec![
+                (Type::Nothing, Type::table()),
+                (Type::String, Type::table()),
+                (Type::List(Box::new(Type::String)), Type::table()),
+            ])
+            .rest(
+                "paths",
+                SyntaxShape::Filepath,
+                "audio files or directories to normalize",
+            )
+            .required_named(
+                "map",
+                SyntaxShape::Any,
+                "record of canonical genre → aliases, or a path to a file containing one",
+                Some('m'),
+            )
+            .switch(
+                "dry-run",
+                "report changes without writing any file",
+                Some('n'),
+            )
+            .category(Category::Experimental);
+        force_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "preview genre normalization for a library using a TOML map",
+                example: "sound meta genre normalize ~/Music --map genres.toml --dry-run",
+                result: None,
+            },
+            Example {
+                description: "normalize with an inline map and list values that need review",
+                example: "sound meta genre normalize ~/Music --map {'Alternative Rock': ['alt rock', 'alternative']} | where status == unmapped",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "map freeform genre tags onto a canonical list, reporting unmapped values for review"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        genre_normalize(engine, call, input)
+    }
+}
+
+fn genre_normalize(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let map_value: Value = call.get_flag("map")?.ok_or_else(|| {
+        LabeledError::new("set the genre map using `--map`").with_label("missing map", span)
+    })?;
+    let genre_map = load_genre_map(engine, map_value)?;
+    let dry_run = call.has_flag("dry-run")?;
+    let force = call.has_flag("force")?;
+    let files = collect_audio_files(&load_path_args(engine, call, input, 0)?, span)?;
+
+    let mut rows = Vec::with_capacity(files.len());
+    for path in files {
+        engine.signals().check(&span)?;
+        let Some(genre) = read_genre(&path) else {
+            continue;
+        };
+        let canonical = genre_map.get(&genre_key(&genre)).cloned();
+        let status = match &canonical {
+            None => "unmapped",
+            Some(c) if *c == genre => "unchanged",
+            Some(c) => {
+                if !dry_run {
+                    with_write_access(&path, force, span, || write_genre(&path, c, span))?;
+                }
+                "changed"
+            }
+        };
+        rows.push(Value::record(
+            record! {
+                "path" => Value::string(path.to_string_lossy(), span),
+                "genre" => Value::string(&genre, span),
+                "canonical" => canonical.map(|c| Value::string(c, span)).unwrap_or(Value::nothing(span)),
+                "status" => Value::string(status, span),
+            },
+            span,
+        ));
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Builds an alias → canonical lookup from the `--map` value. A string is treated as a
+/// file path and parsed with Nushell's `open`, so any format Nushell understands works.
+fn load_genre_map(
+    engine: &EngineInterface,
+    value: Value,
+) -> Result<HashMap<String, String>, LabeledError> {
+    let span = value.span();
+    let value = match value {
+        Value::String { .. } => {
+            let decl_id = engine.find_decl("open")?.ok_or_else(|| {
+                LabeledError::new("`open` is not available")
+                    .with_label("cannot read map file", span)
+            })?;
+            let call = EvaluatedCall::new(span).with_positional(value);
+            engine
+                .call_decl(decl_id, call, PipelineData::empty(), true, false)?
+                .into_value(span)?
+        }
+        other => other,
+    };
+    let record = value.into_record().map_err(|_| {
+        LabeledError::new("genre map must be a record of canonical name → aliases")
+            .with_label("expected record", span)
+    })?;
+
+    let mut map = HashMap::new();
+    for (canonical, aliases) in record {
+        map.insert(genre_key(&canonical), canonical.clone());
+        let aliases = match aliases {
+            Value::List { vals, .. } => vals,
+            single => vec![single],
+        };
+        for alias in aliases {
+            let alias_span = alias.span();
+            let alias = alias.coerce_into_string().map_err(|_| {
+                LabeledError::new(format!("aliases of '{canonical}' must be strings"))
+                    .with_label("expected string", alias_span)
+            })?;
+            map.insert(genre_key(&alias), canonical.clone());
+        }
+    }
+    Ok(map)
+}
+
+/// Comparison key for genre strings: lowercase alphanumerics only.
+fn genre_key(genre: &str) -> String {
+    genre
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn read_genre(path: &Path) -> Option<String> {
+    let tagged_file = read_from_path(path).ok()?;
+    tagged_file
+        .primary_tag()?
+        .get_string(ItemKey::Genre)
+        .map(str::to_string)
+}
+
+fn write_genre(path: &Path, genre: &str, span: Span) -> Result<(), LabeledError> {
+    let mut tagged_file = read_from_path(path)
+        .map_err(|e| LabeledError::new(e.to_string()).with_label("error reading file", span))?;
+    let tag = primary_tag_mut_or_insert(&mut tagged_file, span)?;
+    tag.insert_text(ItemKey::Genre, genre.to_string());
+    tagged_file
+        .save_to_path(path, WriteOptions::default())
+        .map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error saving {}", path.display()), span)
+        })
+}
+
+/// Nushell command `sound meta fix-encoding` — repairs mojibake in text tags.
+///
+/// Legacy taggers often stored e.g. CP1251 bytes in tags declared as Latin-1, so readers
+/// show "Ïðèâåò" instead of "Привет". Each affected string is mapped back to its original
+/// bytes and re-decoded with `--from`; the result is kept only when it reads more plausibly
+/// than the original. Changes are only previewed unless `--apply` is set.
+pub struct SoundFixEncodingCmd;
+impl SimplePluginCommand for SoundFixEncodingCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta fix-encoding"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound meta fix-encoding")
+            .input_output_types(vec![
+                (Type::Nothing, Type::table()),
+                (Type::String, Type::table()),
+                (Type::List(Box::new(Type::String)), Type::table()),
+            ])
+            .rest(
+                "paths",
+                SyntaxShape::Filepath,
+                "audio files or directories to repair",
+            )
+            .required_named(
+                "from",
+                SyntaxShape::String,
+                "codepage the garbled text was originally written in (e.g. cp1251, shift_jis, gbk)",
+                Some('f'),
+            )
+            .switch(
+                "apply",
+                "rewrite the repaired tags as UTF-8 (default: preview only)",
+                Some('a'),
+            )
+            .category(Category::Experimental);
+        force_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "preview repairs of Cyrillic tags written by a legacy tagger",
+                example: "sound meta fix-encoding old.mp3 --from cp1251",
+                result: None,
+            },
+            Example {
+                description: "repair a whole folder",
+                example: "sound meta fix-encoding ~/Music/russian --from cp1251 --apply",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "re-decode garbled (mojibake) text tags from a legacy codepage and rewrite them as UTF-8"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        fix_encoding(engine, call, input)
+    }
+}
+
+fn fix_encoding(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let from: Spanned<String> = call.get_flag("from")?.ok_or_else(|| {
+        LabeledError::new("set the source codepage using `--from`")
+            .with_label("missing codepage", span)
+    })?;
+    let encoding = encoding_rs::Encoding::for_label(from.item.as_bytes()).ok_or_else(|| {
+        LabeledError::new(format!("unknown encoding '{}'", from.item))
+            .with_label("not a known codepage label", from.span)
+    })?;
+    let apply = call.has_flag("apply")?;
+    let force = call.has_flag("force")?;
+    let files = collect_audio_files(&load_path_args(engine, call, input, 0)?, span)?;
+
+    let mut rows = Vec::new();
+    for path in files {
+        engine.signals().check(&span)?;
+        let Ok(mut tagged_file) = read_from_path(&path) else {
+            continue;
+        };
+        let tag_types: Vec<_> = tagged_file.tags().iter().map(|t| t.tag_type()).collect();
+        let mut file_changed = false;
+
+        for tag_type in tag_types {
+            let Some(tag) = tagged_file.tag_mut(tag_type) else {
+                continue;
+            };
+            let mut items: Vec<TagItem> = tag.items().cloned().collect();
+            let mut changed = false;
+            for item in items.iter_mut() {
+                let Some(before) = item.value().text() else {
+                    continue;
+                };
+                let Some(after) = redecode(before, encoding) else {
+                    continue;
+                };
+                rows.push(Value::record(
+                    record! {
+                        "path" => Value::string(path.to_string_lossy(), span),
+                        "tag" => Value::string(format!("{tag_type:?}"), span),
+                        "key" => Value::string(format!("{:?}", item.key()), span),
+                        "before" => Value::string(before, span),
+                        "after" => Value::string(&after, span),
+                    },
+                    span,
+                ));
+                let mut fixed = TagItem::new(item.key(), ItemValue::Text(after));
+                fixed.set_lang(*item.lang());
+                fixed.set_description(item.description().to_string());
+                *item = fixed;
+                changed = true;
+            }
+            if apply && changed {
+                tag.retain(|_| false);
+                for item in items {
+                    tag.push_unchecked(item);
+                }
+            }
+            file_changed |= changed;
+        }
+
+        if apply && file_changed {
+            with_write_access(&path, force, span, || {
+                tagged_file
+                    .save_to_path(&path, WriteOptions::default())
+                    .map_err(|e| {
+                        LabeledError::new(e.to_string())
+                            .with_label(format!("error saving {}", path.display()), span)
+                    })
+            })?;
+        }
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Recovers the original bytes of a string that was mis-decoded as Latin-1 / Windows-1252
+/// and decodes them with `encoding`. Returns `None` for pure ASCII, for strings that cannot
+/// be Latin-1 mojibake, when the bytes are invalid in `encoding` or decode unchanged, and
+/// when the result reads no more plausibly than the original, so correct accented text
+/// such as "Café" or "Björk" is left alone.
+fn redecode(text: &str, encoding: &'static encoding_rs::Encoding) -> Option<String> {
+    if text.is_ascii() {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut buf = [0u8; 4];
+    for c in text.chars() {
+        if (c as u32) <= 0xFF {
+            bytes.push(c as u8);
+            continue;
+        }
+        let (encoded, _, had_errors) = encoding_rs::WINDOWS_1252.encode(c.encode_utf8(&mut buf));
+        match encoded.as_ref() {
+            [byte] if !had_errors => bytes.push(*byte),
+            _ => return None,
+        }
+    }
+    let decoded = encoding.decode_without_bom_handling_and_without_replacement(&bytes)?;
+    (decoded != text && implausibility(&decoded) < implausibility(text))
+        .then(|| decoded.into_owned())
+}
+
+/// Scores how unlike real text `text` looks. Mojibake symbols (C1 controls, Latin-1 signs
+/// such as `©` or `¼`, box drawing) count twice; a word counts once when it mixes scripts
+/// ("Cafй") or strings accented Latin letters together ("Ïðèâåò").
+fn implausibility(text: &str) -> usize {
+    let symbols = text.chars().filter(|&c| is_mojibake_symbol(c)).count();
+    let odd_words = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| {
+            let mut scripts = word.chars().map(script);
+            let first = scripts.next();
+            let accented = |c: char| ('\u{C0}'..='\u{17F}').contains(&c) && c != '×' && c != '÷';
+            scripts.any(|s| Some(s) != first)
+                || word
+                    .chars()
+                    .zip(word.chars().skip(1))
+                    .any(|(a, b)| accented(a) && accented(b))
+        })
+        .count();
+    2 * symbols + odd_words
+}
+
+/// Characters that rarely appear in tags but often in text decoded with the wrong codepage.
+fn is_mojibake_symbol(c: char) -> bool {
+    matches!(c,
+        '\u{80}'..='\u{BF}' | '×' | '÷' | '\u{2500}'..='\u{259F}' | '\u{FFFD}'
+        | 'ƒ' | 'ˆ' | '˜' | '‚' | '„' | '†' | '‡' | '‰' | '‹' | '›' | '™')
+}
+
+/// Coarse writing system of a letter: Latin, CJK (including kana and hangul), or else
+/// its 256-codepoint block, which keeps Greek, Cyrillic, Hebrew and Arabic apart.
+fn script(c: char) -> u32 {
+    match c as u32 {
+        0..=0x24F | 0x1E00..=0x1EFF => 0,
+        0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF => 1,
+        other => other >> 8,
+    }
+}
+
+/// Longest text field kept by `sound meta sanitize` when `--max-length` is not given.
+const DEFAULT_MAX_FIELD_LENGTH: usize = 4096;
+
+/// ID3v2 frames that carry opaque data for the application that wrote them: store
+/// purchase records, database IDs and embedded files.
+const PRIVATE_FRAMES: [&str; 3] = ["PRIV", "UFID", "GEOB"];
+
+/// Nushell command `sound meta sanitize` — strips junk and private data from tags.
+///
+/// Text fields lose control characters (including bidirectional overrides, which can
+/// disguise text), embedded nulls and anything past `--max-length`. In files whose main
+/// tag is ID3v2 (MP3, WAV, AIFF and ADTS AAC), PRIV, UFID and GEOB frames are dropped.
+/// Returns one row per removal.
+pub struct SoundSanitizeCmd;
+impl SimplePluginCommand for SoundSanitizeCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta sanitize"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound meta sanitize")
+            .input_output_types(vec![
+                (Type::Nothing, Type::table()),
+                (Type::String, Type::table()),
+                (Type::List(Box::new(Type::String)), Type::table()),
+            ])
+            .rest(
+                "paths",
+                SyntaxShape::Filepath,
+                "audio files or directories to sanitize",
+            )
+            .named(
+                "max-length",
+                SyntaxShape::Int,
+                "truncate text fields to this many characters (default 4096)",
+                Some('l'),
+            )
+            .switch(
+                "dry-run",
+                "report what would be removed without writing any file",
+                Some('n'),
+            )
+            .category(Category::Experimental);
+        force_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "check what would be stripped before sharing a folder",
+                example: "sound meta sanitize ~/Music/share --dry-run",
+                result: None,
+            },
+            Example {
+                description:
+                    "sanitize uploads before serving them, keeping fields under 1000 characters",
+                example: "ls uploads/*.mp3 | get name | sound meta sanitize --max-length 1000",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "strip control characters, embedded nulls, overlong fields and private frames from tags"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        sanitize(engine, call, input)
+    }
+}
+
+fn sanitize(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let max_length = match call.get_flag::<Spanned<i64>>("max-length")? {
+        Some(max) if max.item < 1 => {
+            return Err(LabeledError::new("--max-length must be at least 1")
+                .with_label("invalid length", max.span))
+        }
+        Some(max) => max.item as usize,
+        None => DEFAULT_MAX_FIELD_LENGTH,
+    };
+    let dry_run = call.has_flag("dry-run")?;
+    let force = call.has_flag("force")?;
+    let files = collect_audio_files(&load_path_args(engine, call, input, 0)?, span)?;
+
+    let mut rows = Vec::new();
+    for path in files {
+        engine.signals().check(&span)?;
+        let report = match dry_run {
+            true => sanitize_file(&path, max_length, true, span)?,
+            false => with_write_access(&path, force, span, || {
+                sanitize_file(&path, max_length, false, span)
+            })?,
+        };
+        for (tag, key, removed) in report {
+            rows.push(Value::record(
+                record! {
+                    "path" => Value::string(path.to_string_lossy(), span),
+                    "tag" => Value::string(tag, span),
+                    "key" => Value::string(key, span),
+                    "removed" => Value::string(removed, span),
+                },
+                span,
+            ));
+        }
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Sanitizes every tag of one file, ID3v2 frame by frame and the others through lofty's
+/// generic tags.
+fn sanitize_file(
+    path: &Path,
+    max_length: usize,
+    dry_run: bool,
+    span: Span,
+) -> Result<Vec<Removal>, LabeledError> {
+    let mut report = match FileType::from_path(path) {
+        Some(FileType::Mpeg) => {
+            sanitize_id3v2_file(path, MpegFile::id3v2_mut, max_length, dry_run, span)?
+        }
+        Some(FileType::Wav) => {
+            sanitize_id3v2_file(path, WavFile::id3v2_mut, max_length, dry_run, span)?
+        }
+        Some(FileType::Aiff) => {
+            sanitize_id3v2_file(path, AiffFile::id3v2_mut, max_length, dry_run, span)?
+        }
+        Some(FileType::Aac) => {
+            sanitize_id3v2_file(path, AacFile::id3v2_mut, max_length, dry_run, span)?
+        }
+        _ => Vec::new(),
+    };
+    report.extend(sanitize_generic(path, max_length, dry_run, span)?);
+    Ok(report)
+}
+
+/// One removal: tag type, field and what was taken out.
+type Removal = (String, String, String);
+
+/// Sanitizes the ID3v2 tag of a file of type `F` frame by frame, `id3v2` picking the tag
+/// out of the file. Going through lofty's generic tag would drop the private frames
+/// silently instead of reporting them.
+fn sanitize_id3v2_file<F: AudioFile>(
+    path: &Path,
+    id3v2: fn(&mut F) -> Option<&mut Id3v2Tag>,
+    max_length: usize,
+    dry_run: bool,
+    span: Span,
+) -> Result<Vec<Removal>, LabeledError> {
+    let error = |e: &dyn std::fmt::Display| {
+        LabeledError::new(e.to_string())
+            .with_label(format!("error reading {}", path.display()), span)
+    };
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(!dry_run)
+        .open(path)
+        .map_err(|e| error(&e))?;
+    let Ok(mut audio_file) = F::read_from(&mut file, ParseOptions::new().read_properties(false))
+    else {
+        return Ok(Vec::new());
+    };
+    let Some(tag) = id3v2(&mut audio_file) else {
+        return Ok(Vec::new());
+    };
+    let report: Vec<Removal> = sanitize_id3v2(tag, max_length)
+        .into_iter()
+        .map(|(key, removed)| ("Id3v2".to_string(), key, removed))
+        .collect();
+    if !dry_run && !report.is_empty() {
+        file.rewind().map_err(|e| error(&e))?;
+        audio_file
+            .save_to(&mut file, WriteOptions::default())
+            .map_err(|e| {
+                LabeledError::new(e.to_string())
+                    .with_label(format!("error saving {}", path.display()), span)
+            })?;
+    }
+    Ok(report)
+}
+
+/// Removes private frames and cleans text frames, returning frame IDs with what was
+/// removed from them.
+fn sanitize_id3v2(tag: &mut Id3v2Tag, max_length: usize) -> Vec<(String, String)> {
+    let mut report = Vec::new();
+    let frames: Vec<Frame<'static>> = tag.clone().into_iter().collect();
+    for original in frames {
+        let id = original.id_str().to_string();
+        if PRIVATE_FRAMES.contains(&id.as_str()) {
+            report.push((id, private_frame_summary(&original)));
+            continue;
+        }
+        let mut frame = original.clone();
+        let mut changed = false;
+        let mut clean = |text: &mut Cow<'static, str>, field: TextField| {
+            let (cleaned, removed) = sanitize_text(text, field, max_length);
+            if !removed.is_empty() {
+                *text = Cow::Owned(cleaned);
+                report.extend(removed.into_iter().map(|removed| (id.clone(), removed)));
+                changed = true;
+            }
+        };
+        match &mut frame {
+            Frame::Text(text) => clean(&mut text.value, TextField::Values),
+            Frame::UserText(text) => {
+                clean(&mut text.description, TextField::Line);
+                clean(&mut text.content, TextField::Values);
+            }
+            Frame::Comment(comment) => {
+                clean(&mut comment.description, TextField::Line);
+                clean(&mut comment.content, TextField::Multiline);
+            }
+            Frame::UnsynchronizedText(lyrics) => {
+                clean(&mut lyrics.description, TextField::Line);
+                clean(&mut lyrics.content, TextField::Multiline);
+            }
+            _ => {}
+        }
+        if changed {
+            // Frames compare by identity (ID, description, language), so this drops
+            // the original even when only its content changed.
+            tag.retain(|f| f != &original);
+            tag.insert(frame);
+        }
+    }
+    tag.retain(|frame| !PRIVATE_FRAMES.contains(&frame.id_str()));
+    report
+}
+
+fn private_frame_summary(frame: &Frame<'_>) -> String {
+    let owner = |owner: &str| sanitize_text(owner, TextField::Line, 200).0;
+    match frame {
+        Frame::Private(private) => format!(
+            "private data from {}, {} bytes",
+            owner(&private.owner),
+            private.private_data.len()
+        ),
+        Frame::UniqueFileIdentifier(ufid) => format!("file identifier from {}", owner(&ufid.owner)),
+        Frame::Binary(binary) => format!("embedded object, {} bytes", binary.data.len()),
+        _ => "private frame".to_string(),
+    }
+}
+
+/// Cleans the text items of the tags of a file through lofty's generic tags, skipping
+/// ID3v2, which [`sanitize_id3v2_file`] handles. Only the tags that changed are written.
+fn sanitize_generic(
+    path: &Path,
+    max_length: usize,
+    dry_run: bool,
+    span: Span,
+) -> Result<Vec<Removal>, LabeledError> {
+    let Ok(mut tagged_file) = read_from_path(path) else {
+        return Ok(Vec::new());
+    };
+    let tag_types: Vec<_> = tagged_file
+        .tags()
+        .iter()
+        .map(|t| t.tag_type())
+        .filter(|&tag_type| tag_type != TagType::Id3v2)
+        .collect();
+    let mut report = Vec::new();
+
+    for tag_type in tag_types {
+        let Some(tag) = tagged_file.tag_mut(tag_type) else {
+            continue;
+        };
+        let mut items: Vec<TagItem> = tag.items().cloned().collect();
+        let mut changed = false;
+        for item in items.iter_mut() {
+            let Some(text) = item.value().text() else {
+                continue;
+            };
+            let field = match item.key() {
+                ItemKey::Comment | ItemKey::Lyrics => TextField::Multiline,
+                _ => TextField::Line,
+            };
+            let (cleaned, removed) = sanitize_text(text, field, max_length);
+            if removed.is_empty() {
+                continue;
+            }
+            let key = format!("{:?}", item.key());
+            report.extend(
+                removed
+                    .into_iter()
+                    .map(|removed| (format!("{tag_type:?}"), key.clone(), removed)),
+            );
+            let mut fixed = TagItem::new(item.key(), ItemValue::Text(cleaned));
+            fixed.set_lang(*item.lang());
+            fixed.set_description(item.description().to_string());
+            *item = fixed;
+            changed = true;
+        }
+        if !dry_run && changed {
+            tag.retain(|_| false);
+            for item in items {
+                tag.push_unchecked(item);
+            }
+            tag.save_to_path(path, WriteOptions::default())
+                .map_err(|e| {
+                    LabeledError::new(e.to_string())
+                        .with_label(format!("error saving {}", path.display()), span)
+                })?;
+        }
+    }
+    Ok(report)
+}
+
+/// Kind of text field, which decides what counts as junk in it.
+#[derive(Clone, Copy, PartialEq)]
+enum TextField {
+    /// Single-line text such as a title or a description.
+    Line,
+    /// ID3v2 text frames, where a null separates multiple values.
+    Values,
+    /// Comments and lyrics, which may keep line breaks and tabs.
+    Multiline,
+}
+
+/// Cleans one text value: drops control characters and nulls (keeping single nulls
+/// between the values of a [`TextField::Values`] field) and cuts it to `max_length`
+/// characters. Returns the cleaned text and a description of each kind of removal,
+/// empty when nothing changed.
+fn sanitize_text(text: &str, field: TextField, max_length: usize) -> (String, Vec<String>) {
+    let values: Vec<&str> = if field == TextField::Values {
+        text.split('\0').collect()
+    } else {
+        vec![text]
+    };
+    let mut controls = 0;
+    let mut kept: Vec<String> = Vec::with_capacity(values.len());
+    for value in values {
+        let clean: String = value
+            .chars()
+            .filter(|&c| {
+                let junk = c != '\0' && is_control(c, field);
+                controls += junk as usize;
+                !junk && c != '\0'
+            })
+            .collect();
+        if !clean.is_empty() || field != TextField::Values {
+            kept.push(clean);
+        }
+    }
+    // Whatever nulls are not kept as separators between values were removed.
+    let nulls = text.matches('\0').count() - kept.len().saturating_sub(1);
+    let mut cleaned = kept.join("\0");
+
+    let mut removed = Vec::new();
+    if controls > 0 {
+        removed.push(characters(controls, "control character"));
+    }
+    if nulls > 0 {
+        removed.push(characters(nulls, "null character"));
+    }
+    let length = cleaned.chars().count();
+    if length > max_length {
+        cleaned = cleaned.chars().take(max_length).collect();
+        removed.push(format!(
+            "{} past the {max_length}-character limit",
+            characters(length - max_length, "character")
+        ));
+    }
+    (cleaned, removed)
+}
+
+fn characters(count: usize, what: &str) -> String {
+    if count == 1 {
+        format!("1 {what}")
+    } else {
+        format!("{count} {what}s")
+    }
+}
+
+/// Control characters, plus the bidirectional overrides and isolates that can make text
+/// display differently from what it contains. Line breaks and tabs are allowed in
+/// multiline fields.
+fn is_control(c: char, field: TextField) -> bool {
+    if field == TextField::Multiline && matches!(c, '\n' | '\r' | '\t') {
+        return false;
+    }
+    c.is_control() || matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Nushell command `sound meta renumber` — rewrites track numbers across an album folder.
+///
+/// Files directly inside the folder are ordered by name (natural order, so `2` sorts before
+/// `10`) or by modification date, then numbered from 1 with a consistent `total_tracks`.
+/// Track numbers are zero-padded (default: width of the total) in formats that store them
+/// as text, such as Vorbis comments; ID3v2 and MP4 always store plain integers.
+pub struct SoundRenumberCmd;
+impl SimplePluginCommand for SoundRenumberCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta renumber"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound meta renumber")
+            .required(
+                "Directory",
+                SyntaxShape::Directory,
+                "album folder to renumber",
+            )
+            .named(
+                "by",
+                SyntaxShape::String,
+                "ordering: filename (natural order, default) or date (modification time)",
+                Some('b'),
+            )
+            .named(
+                "pad",
+                SyntaxShape::Int,
+                "zero-pad track numbers to this many digits (default: width of the total)",
+                Some('p'),
+            )
+            .switch(
+                "dry-run",
+                "report changes without writing any file",
+                Some('n'),
+            )
+            .category(Category::Experimental);
+        force_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "renumber an album by file name with two-digit track numbers",
+                example: "sound meta renumber ~/Music/album --by filename --pad 2",
+                result: None,
+            },
+            Example {
+                description: "preview numbering by modification date",
+                example: "sound meta renumber ~/Music/album --by date --dry-run",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "rewrite track numbers and totals consistently across an album folder"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        renumber(engine, call)
+    }
+}
+
+fn renumber(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let (dir_span, dir) = load_file_path(engine, call)?;
+    if !dir.is_dir() {
+        return Err(
+            LabeledError::new(format!("{} is not a directory", dir.display()))
+                .with_label("expected an album folder", dir_span),
+        );
+    }
+
+    let mut files: Vec<PathBuf> = collect_audio_files(std::slice::from_ref(&dir), span)?
+        .into_iter()
+        .filter(|f| f.parent() == Some(dir.as_path()))
+        .collect();
+
+    let by = call.get_flag::<Spanned<String>>("by")?;
+    match by.as_ref().map(|b| b.item.as_str()) {
+        None | Some("filename") => {
+            files.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()))
+        }
+        Some("date") => files.sort_by_key(|f| std::fs::metadata(f).and_then(|m| m.modified()).ok()),
+        Some(other) => {
+            return Err(
+                LabeledError::new(format!("unknown ordering '{other}'")).with_label(
+                    "expected filename or date",
+                    by.map(|b| b.span).unwrap_or(span),
+                ),
+            )
+        }
+    }
+
+    let total = files.len();
+    let pad = match call.get_flag::<Spanned<i64>>("pad")? {
+        Some(pad) if pad.item < 0 => {
+            return Err(LabeledError::new("--pad must not be negative")
+                .with_label("invalid padding", pad.span))
+        }
+        Some(pad) => pad.item as usize,
+        None => total.to_string().len(),
+    };
+    let dry_run = call.has_flag("dry-run")?;
+    let force = call.has_flag("force")?;
+
+    let mut rows = Vec::with_capacity(total);
+    for (index, path) in files.into_iter().enumerate() {
+        engine.signals().check(&span)?;
+        let track = format!("{:0pad$}", index + 1);
+        let tagged_file = read_from_path(&path).map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error reading {}", path.display()), span)
+        });
+        let previous = tagged_file
+            .as_ref()
+            .ok()
+            .and_then(|f| f.primary_tag())
+            .and_then(|t| t.get_string(ItemKey::TrackNumber))
+            .map(str::to_string);
+
+        // A file that cannot be read or written is reported in its row; the rest still
+        // get their numbers.
+        let written = match tagged_file {
+            Ok(_) if dry_run => Ok(()),
+            Ok(mut tagged_file) => with_write_access(&path, force, span, || {
+                write_track_number(&mut tagged_file, &path, &track, total, span)
+            }),
+            Err(e) => Err(e),
+        };
+        let error = written.err().map(|e| e.msg);
+
+        rows.push(Value::record(
+            record! {
+                "path" => Value::string(path.to_string_lossy(), span),
+                "previous" => previous.map(|p| Value::string(p, span)).unwrap_or(Value::nothing(span)),
+                "track" => Value::string(track, span),
+                "total_tracks" => Value::int(total as i64, span),
+                "error" => error.map(|e| Value::string(e, span)).unwrap_or(Value::nothing(span)),
+            },
+            span,
+        ));
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Sets the track number and total of `path`, read into `tagged_file`, and saves it.
+fn write_track_number(
+    tagged_file: &mut TaggedFile,
+    path: &Path,
+    track: &str,
+    total: usize,
+    span: Span,
+) -> Result<(), LabeledError> {
+    let tag = primary_tag_mut_or_insert(tagged_file, span)?;
+    tag.insert_text(ItemKey::TrackNumber, track.to_string());
+    tag.insert_text(ItemKey::TrackTotal, total.to_string());
+    tagged_file
+        .save_to_path(path, WriteOptions::default())
+        .map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error saving {}", path.display()), span)
+        })
+}
+
+/// Compares strings so that runs of digits are ordered numerically (`2` < `10`).
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |it: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = it.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(c);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let ord = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Nushell command `sound album fix-compilation` — flags multi-artist albums as compilations.
+///
+/// Files are grouped into albums by folder and `album` tag. An album whose tracks have more
+/// than one distinct artist (ignoring "feat." credits) gets `compilation = 1` and a shared
+/// `albumartist` on every track, unless its tracks already agree on an album artist.
+/// Returns one row per changed field, with an `error` for files that could not be saved.
+pub struct SoundFixCompilationCmd;
+impl SimplePluginCommand for SoundFixCompilationCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound album fix-compilation"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound album fix-compilation")
+            .required(
+                "Directory",
+                SyntaxShape::Directory,
+                "folder to scan for albums (recursively)",
+            )
+            .named(
+                "album-artist",
+                SyntaxShape::String,
+                "album artist to set on compilations (default: Various Artists)",
+                Some('A'),
+            )
+            .switch(
+                "dry-run",
+                "report changes without writing any file",
+                Some('n'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "preview compilation fixes for a library",
+                example: "sound album fix-compilation ~/Music --dry-run",
+                result: None,
+            },
+            Example {
+                description: "flag compilations using a custom album artist",
+                example: "sound album fix-compilation ~/Music/soundtracks --album-artist 'VA'",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "detect multi-artist albums, set the compilation flag and a consistent album artist"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        fix_compilation(engine, call)
+    }
+}
+
+/// Tags of one track relevant to compilation detection.
+struct AlbumTrack {
+    path: PathBuf,
+    artist: Option<String>,
+    album_artist: Option<String>,
+    compilation: Option<String>,
+}
+
+fn fix_compilation(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let (_, dir) = load_file_path(engine, call)?;
+    let album_artist: String = call
+        .get_flag("album-artist")?
+        .unwrap_or_else(|| "Various Artists".to_string());
+    let dry_run = call.has_flag("dry-run")?;
+
+    let mut albums: BTreeMap<(PathBuf, String), Vec<AlbumTrack>> = BTreeMap::new();
+    for path in collect_audio_files(&[dir], span)? {
+        engine.signals().check(&span)?;
+        let Ok(tagged_file) = read_from_path(&path) else {
+            continue;
+        };
+        let Some(tag) = tagged_file.primary_tag() else {
+            continue;
+        };
+        let Some(album) = tag.get_string(ItemKey::AlbumTitle).map(str::to_string) else {
+            continue;
+        };
+        let get = |key: ItemKey| tag.get_string(key).map(str::to_string);
+        let track = AlbumTrack {
+            artist: get(ItemKey::TrackArtist),
+            album_artist: get(ItemKey::AlbumArtist),
+            compilation: get(ItemKey::FlagCompilation),
+            path: path.clone(),
+        };
+        let folder = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        albums.entry((folder, album)).or_default().push(track);
+    }
+
+    let mut rows = Vec::new();
+    for ((_, album), tracks) in albums {
+        let artists: BTreeSet<String> = tracks
+            .iter()
+            .filter_map(|t| t.artist.as_deref())
+            .map(primary_artist)
+            .collect();
+        if artists.len() < 2 {
+            continue;
+        }
+        // Tracks that already share an album artist were grouped deliberately, e.g. a
+        // split single or an artist album full of guest spots; leave them be.
+        let album_artists: BTreeSet<Option<&str>> = tracks
+            .iter()
+            .map(|t| t.album_artist.as_deref().filter(|a| !a.trim().is_empty()))
+            .collect();
+        if matches!(album_artists.first(), Some(Some(_))) && album_artists.len() == 1 {
+            continue;
+        }
+
+        for track in tracks {
+            let changes = [
+                ("compilation", track.compilation, "1"),
+                ("albumartist", track.album_artist, album_artist.as_str()),
+            ];
+            let changes: Vec<_> = changes
+                .into_iter()
+                .filter(|(_, before, after)| before.as_deref() != Some(*after))
+                .collect();
+            if changes.is_empty() {
+                continue;
+            }
+
+            // A file that cannot be written is reported in its rows; the rest still get fixed.
+            let error = match dry_run {
+                true => None,
+                false => write_compilation(&track.path, &album_artist, span)
+                    .err()
+                    .map(|e| e.msg),
+            };
+
+            for (field, before, after) in changes {
+                rows.push(Value::record(
+                    record! {
+                        "path" => Value::string(track.path.to_string_lossy(), span),
+                        "album" => Value::string(&album, span),
+                        "artists" => Value::int(artists.len() as i64, span),
+                        "field" => Value::string(field, span),
+                        "before" => before.map(|b| Value::string(b, span)).unwrap_or(Value::nothing(span)),
+                        "after" => Value::string(after, span),
+                        "error" => error.as_ref().map(|e| Value::string(e, span)).unwrap_or(Value::nothing(span)),
+                    },
+                    span,
+                ));
+            }
+        }
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Flags `path` as part of a compilation by `album_artist`.
+fn write_compilation(path: &Path, album_artist: &str, span: Span) -> Result<(), LabeledError> {
+    let mut tagged_file = read_from_path(path).map_err(|e| {
+        LabeledError::new(e.to_string())
+            .with_label(format!("error reading {}", path.display()), span)
+    })?;
+    let tag = primary_tag_mut_or_insert(&mut tagged_file, span)?;
+    tag.insert_text(ItemKey::FlagCompilation, "1".to_string());
+    tag.insert_text(ItemKey::AlbumArtist, album_artist.to_string());
+    tagged_file
+        .save_to_path(path, WriteOptions::default())
+        .map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error saving {}", path.display()), span)
+        })
+}
+
+/// Strips featured-artist credits so "A feat. B" and "A" count as the same artist.
+fn primary_artist(artist: &str) -> String {
+    let lower = artist.to_lowercase();
+    let cut = [" feat. ", " feat ", " ft. ", " featuring ", " (feat"]
+        .iter()
+        .filter_map(|sep| lower.find(sep))
+        .min()
+        .unwrap_or(lower.len());
+    lower[..cut].trim().to_string()
+}
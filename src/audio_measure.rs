@@ -0,0 +1,409 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+use std::{f64::consts::PI, path::PathBuf, time::Duration};
+
+use crate::{
+    capture::play_and_record,
+    dsp::{convolve, Biquad},
+    ear_guard::{ear_guard_flags, EarGuard},
+    pcm::Pcm,
+    simd,
+    utils::{absolute_path, flag_span},
+    validate::{duration_flag, duration_shape},
+    Sound,
+};
+
+/// Silence between latency test impulses; must exceed the longest expected round trip.
+const IMPULSE_SPACING: Duration = Duration::from_millis(500);
+const DEFAULT_REPEATS: i64 = 5;
+const DEFAULT_SWEEP: Duration = Duration::from_secs(5);
+/// Recording continues this long after the sweep so the room's decay is captured.
+const SWEEP_TAIL: Duration = Duration::from_secs(2);
+const SWEEP_START_HZ: f64 = 20.0;
+const SWEEP_END_HZ: f64 = 20_000.0;
+const SWEEP_LEVEL: f64 = 0.5;
+const OCTAVE_BANDS: [f64; 7] = [125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0];
+
+/// Nushell command `sound latency` — measures round-trip audio latency.
+///
+/// A train of short clicks is played on the default output while the default input
+/// records. Each click is located in the recording and compared with the moment the
+/// audio host reports it reached the output; the median over all clicks is reported.
+pub struct SoundLatencyCmd;
+impl SimplePluginCommand for SoundLatencyCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound latency"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound latency")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .switch(
+                "loopback",
+                "output is cabled straight into the input; plays quieter clicks to avoid clipping",
+                Some('l'),
+            )
+            .named(
+                "repeat",
+                SyntaxShape::Int,
+                "number of clicks to measure (default: 5)",
+                Some('r'),
+            )
+            .category(Category::Experimental);
+        ear_guard_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "measure interface round-trip latency with a loopback cable",
+                example: "sound latency --loopback",
+                result: None,
+            },
+            Example {
+                description: "measure speaker-to-microphone latency over 10 clicks",
+                example: "sound latency --repeat 10",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play clicks and record them back to measure round-trip latency in ms and samples"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        latency(engine, call)
+    }
+}
+
+fn latency(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let repeats: Spanned<i64> = call.get_flag("repeat")?.unwrap_or(Spanned {
+        item: DEFAULT_REPEATS,
+        span,
+    });
+    if !(1..=50).contains(&repeats.item) {
+        return Err(LabeledError::new("invalid repeat count")
+            .with_label("must be between 1 and 50", repeats.span));
+    }
+    let repeats = repeats.item as usize;
+    let amplitude = if call.has_flag("loopback")? { 0.5 } else { 0.9 };
+    let guard = EarGuard::from_call(engine, call)?;
+
+    let (recording, output_rate) = play_and_record(
+        engine,
+        span,
+        |rate| guarded(guard, click_train(rate, repeats, amplitude)),
+        IMPULSE_SPACING,
+    )?;
+    let offset = recording.signal_offset.ok_or_else(|| {
+        LabeledError::new("audio host reported no stream timestamps")
+            .with_label("cannot align playback and recording", span)
+    })?;
+
+    let input_rate = recording.sample_rate as f64;
+    let peak = simd::peak(&recording.samples);
+    let threshold = peak * 0.5;
+    let spacing = click_spacing(output_rate) as f64 / output_rate as f64;
+
+    let mut measurements = Vec::new();
+    for r in 0..repeats {
+        let played_at = offset + r as f64 * spacing;
+        let start = (played_at.max(0.0) * input_rate) as usize;
+        let end = ((played_at + spacing) * input_rate) as usize;
+        let window = recording
+            .samples
+            .get(start..end.min(recording.samples.len()))
+            .unwrap_or_default();
+        if let Some(hit) = window.iter().position(|s| s.abs() >= threshold) {
+            measurements.push((start + hit) as f64 / input_rate - played_at);
+        }
+    }
+    if peak < 0.01 || measurements.is_empty() {
+        return Err(LabeledError::new("no click detected in the recording")
+            .with_label("input stayed silent", span)
+            .with_help("check the loopback cable or microphone and the input level"));
+    }
+
+    let mut sorted = measurements.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    let ms = |seconds: f64| Value::float((seconds * 100_000.0).round() / 100.0, span);
+    Ok(Value::record(
+        record! {
+            "latency_ms" => ms(median),
+            "latency_samples" => Value::int((median * input_rate).round() as i64, span),
+            "sample_rate" => Value::int(recording.sample_rate as i64, span),
+            "jitter_ms" => ms(sorted[sorted.len() - 1] - sorted[0]),
+            "detected" => Value::int(measurements.len() as i64, span),
+            "measurements_ms" => Value::list(measurements.into_iter().map(ms).collect(), span),
+        },
+        span,
+    ))
+}
+
+fn click_spacing(rate: u32) -> usize {
+    (IMPULSE_SPACING.as_secs_f64() * rate as f64) as usize
+}
+
+/// Scales a test signal under the `--protect-ears` ceiling, if one is set.
+fn guarded(guard: Option<EarGuard>, mut signal: Vec<f32>) -> Vec<f32> {
+    if let Some(guard) = guard {
+        guard.cap_samples(&mut signal);
+    }
+    signal
+}
+
+/// `repeats` one-millisecond clicks, each at the start of an [`IMPULSE_SPACING`] slot.
+fn click_train(rate: u32, repeats: usize, amplitude: f32) -> Vec<f32> {
+    let spacing = click_spacing(rate);
+    let click = (rate as usize / 1000).max(1);
+    let mut signal = vec![0.0; spacing * repeats];
+    for r in 0..repeats {
+        signal[r * spacing..r * spacing + click].fill(amplitude);
+    }
+    signal
+}
+
+/// Nushell command `sound measure room` — sine-sweep room measurement.
+///
+/// Plays an exponential sine sweep and records it, then convolves the recording with the
+/// sweep's inverse filter (Farina's method) to obtain the room impulse response. RT60 is
+/// estimated per octave band from the T20 slope of the Schroeder decay curve.
+pub struct SoundMeasureRoomCmd;
+impl SimplePluginCommand for SoundMeasureRoomCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound measure room"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound measure room")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .named(
+                "sweep",
+                duration_shape(),
+                "length of the sine sweep (default: 5sec); longer sweeps reject more noise",
+                Some('s'),
+            )
+            .named(
+                "ir",
+                SyntaxShape::Filepath,
+                "save the measured impulse response to this WAV file",
+                Some('i'),
+            )
+            .category(Category::Experimental);
+        ear_guard_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "estimate reverberation time per octave band",
+                example: "sound measure room | get bands",
+                result: None,
+            },
+            Example {
+                description: "measure with a long sweep and keep the impulse response",
+                example: "sound measure room --sweep 10sec --ir room.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a sine sweep, record the room's response, and report RT60 per octave band"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        measure_room(engine, call)
+    }
+}
+
+fn measure_room(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let sweep_length = duration_flag(call, "sweep")?.unwrap_or(DEFAULT_SWEEP);
+    if sweep_length < Duration::from_secs(1) || sweep_length > Duration::from_secs(60) {
+        return Err(LabeledError::new("invalid sweep length")
+            .with_label("must be between 1sec and 60sec", flag_span(call, "sweep")));
+    }
+    let ir_path = call.get_flag::<Spanned<String>>("ir")?;
+    let guard = EarGuard::from_call(engine, call)?;
+
+    let (recording, rate) = play_and_record(
+        engine,
+        span,
+        |rate| {
+            let sweep = exponential_sweep(rate, sweep_length)
+                .into_iter()
+                .map(|s| s as f32)
+                .collect();
+            guarded(guard, sweep)
+        },
+        SWEEP_TAIL,
+    )?;
+
+    // Bring the recording to the sweep's rate so the inverse filter lines up.
+    let mut captured = Pcm::new(recording.samples, 1, recording.sample_rate);
+    if recording.sample_rate != rate {
+        captured = captured.varispeed(recording.sample_rate as f64 / rate as f64);
+    }
+    let peak = simd::peak(&captured.samples);
+    if peak < 0.001 {
+        return Err(LabeledError::new("the recording is silent")
+            .with_label("no sweep was picked up by the input", span)
+            .with_help("check the microphone and input level"));
+    }
+
+    let sweep = exponential_sweep(rate, sweep_length);
+    let inverse = inverse_filter(&sweep, rate);
+    let captured: Vec<f64> = captured.samples.iter().map(|s| *s as f64).collect();
+    let response = convolve(&captured, &inverse);
+
+    // The linear impulse response starts at its peak; harmonic distortion products land
+    // before it and are cut off. Keep a few milliseconds of lead-in.
+    let peak_index = response
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let start = peak_index.saturating_sub(rate as usize / 200);
+    let end = (peak_index + (SWEEP_TAIL.as_secs_f64() * rate as f64) as usize).min(response.len());
+    let peak_value = response[peak_index].abs().max(f64::MIN_POSITIVE);
+    let ir: Vec<f64> = response[start..end]
+        .iter()
+        .map(|s| s / peak_value)
+        .collect();
+
+    let round = |v: f64| (v * 1000.0).round() / 1000.0;
+    let rt60_value =
+        |rt: Option<f64>| rt.map_or(Value::nothing(span), |v| Value::float(round(v), span));
+    let bands = OCTAVE_BANDS
+        .iter()
+        .filter(|band| **band < rate as f64 * 0.4)
+        .map(|band| {
+            let mut filter = Biquad::band_pass(*band, 2f64.sqrt(), rate);
+            let filtered: Vec<f64> = ir
+                .iter()
+                .map(|s| filter.process(*s as f32) as f64)
+                .collect();
+            Value::record(
+                record! {
+                    "band_hz" => Value::int(*band as i64, span),
+                    "rt60" => rt60_value(rt60(&filtered, rate)),
+                },
+                span,
+            )
+        })
+        .collect();
+
+    let mut result = record! {
+        "rt60" => rt60_value(rt60(&ir, rate)),
+        "bands" => Value::list(bands, span),
+        "sample_rate" => Value::int(rate as i64, span),
+    };
+    if let Some(path) = ir_path {
+        let path = absolute_path(engine, PathBuf::from(&path.item), path.span)?;
+        let wav = Pcm::new(ir.iter().map(|s| *s as f32).collect(), 1, rate).to_wav()?;
+        std::fs::write(&path, wav).map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error writing {}", path.display()), span)
+        })?;
+        result.push("ir", Value::string(path.to_string_lossy(), span));
+    }
+    Ok(Value::record(result, span))
+}
+
+/// Exponential sine sweep from 20 Hz to 20 kHz (capped below Nyquist) with short fades.
+fn exponential_sweep(rate: u32, length: Duration) -> Vec<f64> {
+    let end_hz = SWEEP_END_HZ.min(rate as f64 * 0.45);
+    let duration = length.as_secs_f64();
+    let l = duration / (end_hz / SWEEP_START_HZ).ln();
+    let len = (duration * rate as f64) as usize;
+    let fade = rate as usize / 20;
+    (0..len)
+        .map(|n| {
+            let t = n as f64 / rate as f64;
+            let gain = (n.min(len - 1 - n) as f64 / fade as f64).min(1.0);
+            SWEEP_LEVEL * gain * (2.0 * PI * SWEEP_START_HZ * l * ((t / l).exp() - 1.0)).sin()
+        })
+        .collect()
+}
+
+/// Time-reversed sweep with an envelope rising 6 dB per octave of the original sweep,
+/// which cancels the sweep's pink spectrum so that sweep ⊛ inverse is a flat,
+/// band-limited impulse.
+fn inverse_filter(sweep: &[f64], rate: u32) -> Vec<f64> {
+    let duration = sweep.len() as f64 / rate as f64;
+    let end_hz = SWEEP_END_HZ.min(rate as f64 * 0.45);
+    let l = duration / (end_hz / SWEEP_START_HZ).ln();
+    let len = sweep.len();
+    (0..len)
+        .map(|n| {
+            let original_t = (len - 1 - n) as f64 / rate as f64;
+            sweep[len - 1 - n] * ((original_t - duration) / l).exp()
+        })
+        .collect()
+}
+
+/// RT60 from the T20 range (-5 to -25 dB) of the Schroeder backward-integrated decay,
+/// falling back to T10 (-5 to -15 dB) when the noise floor is too high. `None` if the
+/// decay never drops 15 dB.
+fn rt60(ir: &[f64], rate: u32) -> Option<f64> {
+    let mut energy: Vec<f64> = ir.iter().map(|s| s * s).collect();
+    for i in (0..energy.len().saturating_sub(1)).rev() {
+        energy[i] += energy[i + 1];
+    }
+    let total = *energy.first()?;
+    if total <= 0.0 {
+        return None;
+    }
+    let decay: Vec<f64> = energy
+        .iter()
+        .map(|e| 10.0 * (e / total).max(1e-12).log10())
+        .collect();
+
+    [(-5.0, -25.0), (-5.0, -15.0)]
+        .iter()
+        .find_map(|(upper, lower)| {
+            let points: Vec<(f64, f64)> = decay
+                .iter()
+                .enumerate()
+                .filter(|(_, db)| **db <= *upper && **db >= *lower)
+                .map(|(i, db)| (i as f64 / rate as f64, *db))
+                .collect();
+            let reaches = decay.iter().any(|db| *db < *lower);
+            if points.len() < 2 || !reaches {
+                return None;
+            }
+            let n = points.len() as f64;
+            let mean_t = points.iter().map(|p| p.0).sum::<f64>() / n;
+            let mean_db = points.iter().map(|p| p.1).sum::<f64>() / n;
+            let covariance: f64 = points
+                .iter()
+                .map(|p| (p.0 - mean_t) * (p.1 - mean_db))
+                .sum();
+            let variance: f64 = points.iter().map(|p| (p.0 - mean_t).powi(2)).sum();
+            let slope = covariance / variance;
+            (slope < 0.0).then(|| -60.0 / slope)
+        })
+}
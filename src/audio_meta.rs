@@ -1,25 +1,42 @@
-use lofty::config::WriteOptions;
-use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::config::{ParseOptions, ParsingMode, WriteOptions};
+use lofty::file::{AudioFile, TaggedFile, TaggedFileExt};
 use lofty::prelude::Accessor;
-use lofty::{read_from_path, tag::Tag};
+use lofty::{probe::Probe, read_from_path, tag::Tag};
 use log::warn;
-use nu_plugin::{EvaluatedCall, SimplePluginCommand};
-use nu_protocol::{record, Category, LabeledError, Record, Signature, Span, SyntaxShape, Type, Value};
+use nu_plugin::{DynamicCompletionCall, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::engine::ArgType;
+use nu_protocol::{record, Category, DynamicSuggestion, Example, LabeledError, Record, Signature, Span, Spanned, SyntaxShape, Type, Value};
 use rodio::{Decoder, Source};
-use std::io::Seek;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Seek};
+use std::path::PathBuf;
 use std::time::Duration;
 use std::collections::HashSet;
 
 use crate::{
+    audio_xing::lame_value,
+    completions::{duration_formats, tag_keys},
     constants::{get_meta_records, TAG_MAP},
-    utils::{format_duration, load_file},
+    gapless::{lame_gapless, read_gapless},
+    itunes::read_itunes_fields,
+    network::{http_request, url_extension},
+    remote::{resolve_remote, RangeReader},
+    sample_format::sample_format_fields,
+    sidecar::{read_sidecar, sidecar_path, write_sidecar_value},
+    stream_meta::live_stream_meta,
+    utils::{
+        duration_value, load_file, path_value, resolve_filepath, with_write_access,
+        DurationFormat,
+    },
+    watchdog::{Watchdog, STALL_CODE},
     Sound,
 };
 /// Nushell command `sound meta set` — writes a single metadata tag to an audio file.
 ///
 /// Accepts a file path, a format-agnostic key name (`-k`), and a string value (`-v`).
 /// The key is looked up in [`TAG_MAP`] (case-insensitive) and written via lofty so the
-/// same key name works across MP3, FLAC, OGG, and MP4.
+/// same key name works across MP3, FLAC, OGG, and MP4. With `--sidecar` the value is
+/// written to `<file>.json` instead, for formats that cannot hold the tag.
 pub struct SoundMetaSetCmd;
 impl SimplePluginCommand for SoundMetaSetCmd {
     type Plugin = Sound;
@@ -32,7 +49,22 @@ impl SimplePluginCommand for SoundMetaSetCmd {
         Signature::new("sound meta set")
             .required("File Path", SyntaxShape::Filepath, "file to update")
             .required_named("key", SyntaxShape::String, "metadata key", Some('k'))
-            .required_named("value", SyntaxShape::String, "metadata value", Some('v'))
+            .required_named(
+                "value",
+                SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::Boolean, SyntaxShape::String]),
+                "metadata value; numbers and booleans keep their type in a sidecar",
+                Some('v'),
+            )
+            .switch(
+                "sidecar",
+                "write the tag to a `<file>.json` sidecar instead of the file itself",
+                Some('s'),
+            )
+            .switch(
+                "force",
+                "write a read-only file by making it writable and restoring its permissions afterwards",
+                Some('f'),
+            )
             .category(Category::Experimental)
     }
 
@@ -40,6 +72,21 @@ impl SimplePluginCommand for SoundMetaSetCmd {
         "set a metadata tag on an audio file"
     }
 
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "key" => Some(tag_keys()),
+            _ => None,
+        }
+    }
+
     fn run(
         &self,
         _plugin: &Self::Plugin,
@@ -55,8 +102,9 @@ impl SimplePluginCommand for SoundMetaSetCmd {
 ///
 /// With `--all` prints the full [`TAG_MAP`] key reference instead of reading a file.
 /// Otherwise returns a record containing file size, format, container bitrate,
-/// tag fields, numeric track/disc info, embedded artwork, and decoded-stream properties
-/// (duration, sample rate, channels).
+/// tag fields, numeric track/disc info, embedded artwork, and stream properties
+/// (duration, sample rate, channels), which are only decoded when the container headers
+/// lack them. `--tags-only` skips the stream properties altogether.
 pub struct SoundMetaGetCmd;
 impl SimplePluginCommand for SoundMetaGetCmd {
     type Plugin = Sound;
@@ -70,12 +118,121 @@ impl SimplePluginCommand for SoundMetaGetCmd {
             .input_output_types(vec![
                 (Type::Nothing, Type::Record(vec![].into())),
                 (Type::Binary,  Type::Record(vec![].into())),
+                (Type::List(Box::new(Type::String)), Type::List(Box::new(Type::Record(vec![].into())))),
+                (Type::List(Box::new(Type::Binary)), Type::List(Box::new(Type::Record(vec![].into())))),
             ])
             .switch("all", "List all possible frame names", Some('a'))
-            .optional("File Path", SyntaxShape::Filepath, "file to play")
+            .switch(
+                "sidecar",
+                "merge tags from a `<file>.json` sidecar (sidecar values take precedence)",
+                Some('s'),
+            )
+            .named(
+                "headers",
+                SyntaxShape::Any,
+                "extra HTTP headers for a remote file, as a record or [name value] list",
+                Some('H'),
+            )
+            .switch(
+                "no-artwork",
+                "skip embedded artwork entirely, for faster library-wide scans",
+                None,
+            )
+            .switch(
+                "artwork-data",
+                "include the image bytes of embedded artwork, not just type and size",
+                None,
+            )
+            .switch(
+                "tags-only",
+                "read only tags and file size, skipping stream properties and decoding, for faster library-wide scans",
+                Some('t'),
+            )
+            .switch(
+                "strict",
+                "fail on the first malformed tag or stream instead of working around it",
+                None,
+            )
+            .switch(
+                "lenient",
+                "recover what can be read from malformed files and list the problems under `warnings`",
+                None,
+            )
+            .named(
+                "duration-format",
+                SyntaxShape::String,
+                "how to report the duration: duration (default), seconds, hms or iso",
+                Some('f'),
+            )
+            .optional("File Path", SyntaxShape::Filepath, "file, http(s) URL or s3:// object to read")
             .category(Category::Experimental)
     }
 
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &nu_plugin::EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "duration-format" => Some(duration_formats()),
+            _ => None,
+        }
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "read tags and stream properties of a file",
+                example: "sound meta audio.mp3",
+                result: None,
+            },
+            Example {
+                description: "read tags of audio piped in as binary",
+                example: "open --raw audio.mp3 | sound meta",
+                result: None,
+            },
+            Example {
+                description: "read tags of several files, one row each",
+                example: "ls *.mp3 | get name | sound meta",
+                result: None,
+            },
+            Example {
+                description: "total length of an album",
+                example: "ls *.flac | get name | sound meta | get duration | math sum",
+                result: None,
+            },
+            Example {
+                description: "list the artists of a library without touching the audio",
+                example: "ls **/*.mp3 | get name | sound meta --tags-only --no-artwork | get artist | uniq",
+                result: None,
+            },
+            Example {
+                description: "scan downloads, failing on the first malformed file",
+                example: "ls ~/Downloads/*.mp3 | get name | sound meta --strict",
+                result: None,
+            },
+            Example {
+                description: "find files with damaged tags while still reading what is left",
+                example: "ls *.mp3 | get name | sound meta --lenient | where ($it.warnings | is-not-empty)",
+                result: None,
+            },
+            Example {
+                description: "durations as fixed-width H:MM:SS strings",
+                example: "sound meta audio.mp3 --duration-format hms | get duration",
+                result: None,
+            },
+            Example {
+                description: "list every metadata key name",
+                example: "sound meta --all",
+                result: Some(get_meta_records(Span::test_data())),
+            },
+        ]
+    }
+
     fn description(&self) -> &str {
         "get duration and metadata of an audio file"
     }
@@ -87,59 +244,416 @@ impl SimplePluginCommand for SoundMetaGetCmd {
         call: &EvaluatedCall,
         input: &Value,
     ) -> Result<Value, nu_protocol::LabeledError> {
-        if let Value::Binary { .. } = input {
-            return Err(LabeledError::new(
-                "binary pipeline input is not yet supported — streaming support is planned",
-            )
-            .with_label("unsupported input", call.head));
-        }
         if let Ok(true) = call.has_flag("all") {
             return Ok(get_meta_records(call.head));
         }
-        let (_, file, path) = load_file(engine, call)?;
-        parse_meta(call, file, path)
+        let format = DurationFormat::from_call(call)?;
+        read_meta(engine, call, input).map(|meta| format.apply(meta))
+    }
+}
+
+/// `sound meta` for a path argument or pipeline input, never both.
+fn read_meta(
+    engine: &nu_plugin::EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Value, LabeledError> {
+    let path = call.opt::<Spanned<String>>(0)?;
+    match (input, path) {
+        (Value::Binary { .. } | Value::List { .. }, Some(path)) => {
+            Err(LabeledError::new("both a path and pipeline input given")
+                .with_label("file path given here", path.span)
+                .with_label("pipeline input given here", input.span())
+                .with_help("pass either the path or the pipeline input, not both"))
+        }
+        (Value::Binary { val, .. }, None) => parse_bytes_meta(call, val, input.span()),
+        (Value::List { vals, .. }, None) => {
+            let records = vals
+                .iter()
+                .map(|value| {
+                    let span = value.span();
+                    let (shown, meta) = match value {
+                        Value::String { val, .. } => {
+                            let path = Spanned { item: val.clone(), span };
+                            (val.clone(), path_meta(engine, call, &path))
+                        }
+                        _ => {
+                            let path = path_value(value.clone())?;
+                            let shown = path.to_string_lossy().into_owned();
+                            (shown, local_meta(engine, call, path, span))
+                        }
+                    };
+                    let mut record = record! { "path" => Value::string(&shown, span) };
+                    match meta {
+                        // One corrupt file should not cost the rest of the scan.
+                        Err(err) if err.code.as_deref() == Some(STALL_CODE) => {
+                            warn!("skipping {shown}: {}", err.msg);
+                            record.push("error", Value::string(err.msg, span));
+                        }
+                        meta => {
+                            for (col, val) in meta?.into_record()? {
+                                record.push(col, val);
+                            }
+                        }
+                    }
+                    Ok(Value::record(record, call.head))
+                })
+                .collect::<Result<Vec<Value>, LabeledError>>()?;
+            Ok(Value::list(records, call.head))
+        }
+        (_, Some(path)) => path_meta(engine, call, &path),
+        (_, None) => Err(LabeledError::new("no file given")
+            .with_label("pass a path, or pipe in binary data or a list of paths", call.head)),
+    }
+}
+
+/// Metadata of a local or remote file named by `path`.
+fn path_meta(
+    engine: &nu_plugin::EngineInterface,
+    call: &EvaluatedCall,
+    path: &Spanned<String>,
+) -> Result<Value, LabeledError> {
+    if let Some(url) = resolve_remote(engine, &path.item, path.span)? {
+        return parse_remote_meta(engine, call, &url, path.span);
+    }
+    local_meta(engine, call, PathBuf::from(&path.item), path.span)
+}
+
+/// Metadata of the local file at `path`, which may be relative to the current directory.
+fn local_meta(
+    engine: &nu_plugin::EngineInterface,
+    call: &EvaluatedCall,
+    path: PathBuf,
+    span: Span,
+) -> Result<Value, LabeledError> {
+    let resolved = resolve_filepath(engine, span, path)?;
+    let file = File::open(&resolved).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error trying to open the file", span)
+    })?;
+    parse_meta(call, file, resolved)
+}
+
+/// `sound meta` for audio piped in as binary: tags are read by lofty and stream
+/// properties from the container headers, or by a decoder when the headers lack them,
+/// both from memory.
+fn parse_bytes_meta(call: &EvaluatedCall, bytes: &[u8], span: Span) -> Result<Value, LabeledError> {
+    if call.has_flag("sidecar")? {
+        return Err(LabeledError::new("--sidecar needs a file")
+            .with_label("binary input has no sidecar", span));
+    }
+    let mut record = record! {};
+    record.push("size", Value::filesize(bytes.len() as i64, call.head));
+    let artwork = Artwork::from_call(call)?;
+    let parsing = Parsing::from_call(call)?;
+    let tags_only = call.has_flag("tags-only")?;
+    let mut warnings = Vec::new();
+    let tagged_file = read_tagged(
+        |mut options| {
+            Probe::new(Cursor::new(bytes))
+                .options(options.read_properties(!tags_only))
+                .guess_file_type()?
+                .read()
+        },
+        artwork,
+        parsing,
+        &mut warnings,
+        span,
+    )?;
+    let lofty_duration = tagged_file
+        .as_ref()
+        .and_then(|tagged_file| push_tag_fields(&mut record, tagged_file, artwork, call.head));
+    if tags_only {
+        parsing.push_warnings(&mut record, warnings, call.head);
+        return Ok(Value::record(record, call.head));
+    }
+    let stream_meta = match header_stream_meta(tagged_file.as_ref(), parsing, call.head) {
+        Some(stream_meta) => Ok(stream_meta),
+        None => {
+            let (bytes, head) = (bytes.to_vec(), call.head);
+            // A corrupt file can send the decoder's probe into an endless loop.
+            Watchdog::run(span, move |watchdog| {
+                let len = bytes.len() as u64;
+                Ok(Decoder::builder()
+                    .with_data(watchdog.reader(Cursor::new(bytes)))
+                    .with_byte_len(len)
+                    .with_seekable(true)
+                    .build()
+                    .map(|source| parse_stream_meta(&source, lofty_duration, head)))
+            })?
+        }
+    };
+    match stream_meta {
+        Ok(stream_meta) => {
+            for (col, val) in stream_meta {
+                record.push(col, val);
+            }
+        }
+        Err(e) => parsing.stream_error(e, &mut warnings, span)?,
+    }
+    for (col, val) in sample_format_fields(&mut Cursor::new(bytes), call.head) {
+        record.push(col, val);
+    }
+    if let Some(gapless) = lame_gapless(&mut Cursor::new(bytes)) {
+        record.push("gapless", gapless.into_value(call.head));
+    }
+    if let Some(lame) = lame_value(&mut Cursor::new(bytes), call.head) {
+        record.push("lame", lame);
+    }
+    parsing.push_warnings(&mut record, warnings, call.head);
+    Ok(Value::record(record, call.head))
+}
+
+/// How much of the embedded artwork `sound meta` reports.
+#[derive(Clone, Copy, PartialEq)]
+enum Artwork {
+    /// `--no-artwork`: not read at all, which saves decoding megabytes of images per
+    /// file on library scans.
+    Skip,
+    /// Type, mime type, size and description of each image.
+    Summary,
+    /// `--artwork-data`: the summary plus the image bytes.
+    Data,
+}
+
+impl Artwork {
+    fn from_call(call: &EvaluatedCall) -> Result<Artwork, LabeledError> {
+        match (call.has_flag("no-artwork")?, call.has_flag("artwork-data")?) {
+            (true, true) => Err(LabeledError::new("conflicting artwork flags")
+                .with_label("--no-artwork and --artwork-data exclude each other", call.head)),
+            (true, false) => Ok(Artwork::Skip),
+            (false, true) => Ok(Artwork::Data),
+            (false, false) => Ok(Artwork::Summary),
+        }
+    }
+
+    fn parse_options(self) -> ParseOptions {
+        ParseOptions::new().read_cover_art(self != Artwork::Skip)
+    }
+}
+
+/// How forgiving `sound meta` is with malformed files.
+#[derive(Clone, Copy, PartialEq)]
+enum Parsing {
+    /// `--strict`: the first spec violation in the tags or the stream is an error.
+    Strict,
+    /// lofty's best attempt at the tags; a stream that cannot be decoded is only logged.
+    Default,
+    /// `--lenient`: whatever can be read is returned, and what could not is listed under
+    /// `warnings`.
+    Lenient,
+}
+
+impl Parsing {
+    fn from_call(call: &EvaluatedCall) -> Result<Parsing, LabeledError> {
+        match (call.has_flag("strict")?, call.has_flag("lenient")?) {
+            (true, true) => Err(LabeledError::new("conflicting parsing flags")
+                .with_label("--strict and --lenient exclude each other", call.head)),
+            (true, false) => Ok(Parsing::Strict),
+            (false, true) => Ok(Parsing::Lenient),
+            (false, false) => Ok(Parsing::Default),
+        }
+    }
+
+    /// Handles a stream the decoder cannot open.
+    fn stream_error(
+        self,
+        error: impl std::fmt::Display,
+        warnings: &mut Vec<String>,
+        span: Span,
+    ) -> Result<(), LabeledError> {
+        match self {
+            Parsing::Strict => Err(LabeledError::new(error.to_string())
+                .with_label("audio stream cannot be decoded", span)),
+            Parsing::Default => {
+                warn!("Failed to decode audio stream: {}", error);
+                Ok(())
+            }
+            Parsing::Lenient => {
+                warnings.push(format!("audio stream: {error}"));
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the `warnings` column in lenient mode, even when it is empty, so every row
+    /// of a scan has it.
+    fn push_warnings(self, record: &mut Record, warnings: Vec<String>, span: Span) {
+        if self == Parsing::Lenient {
+            let warnings = warnings
+                .into_iter()
+                .map(|warning| Value::string(warning, span))
+                .collect();
+            record.push("warnings", Value::list(warnings, span));
+        }
+    }
+}
+
+/// Reads tags with `probe`, which runs one parse with the given options.
+///
+/// Lenient parsing tries a strict parse first, so that anything lofty would otherwise
+/// paper over ends up in `warnings`, then falls back to lofty's relaxed mode. If even
+/// that fails, the file is reported without tags.
+fn read_tagged<F>(
+    mut probe: F,
+    artwork: Artwork,
+    parsing: Parsing,
+    warnings: &mut Vec<String>,
+    span: Span,
+) -> Result<Option<TaggedFile>, LabeledError>
+where
+    F: FnMut(ParseOptions) -> lofty::error::Result<TaggedFile>,
+{
+    let mut options = artwork.parse_options();
+    let mode = match parsing {
+        Parsing::Default => ParsingMode::BestAttempt,
+        Parsing::Strict | Parsing::Lenient => ParsingMode::Strict,
+    };
+    match probe(options.parsing_mode(mode)) {
+        Ok(tagged_file) => Ok(Some(tagged_file)),
+        Err(e) if parsing == Parsing::Lenient => {
+            warnings.push(format!("tags: {e}"));
+            match probe(options.parsing_mode(ParsingMode::Relaxed)) {
+                Ok(tagged_file) => Ok(Some(tagged_file)),
+                Err(e) => {
+                    // Nothing was recovered; the strict failure adds nothing to this one.
+                    warnings.pop();
+                    warnings.push(format!("tags unreadable: {e}"));
+                    Ok(None)
+                }
+            }
+        }
+        Err(e) => Err(LabeledError::new(e.to_string()).with_label("error reading tags", span)),
     }
 }
 
 /// Combines lofty tag data ([`parse_tags`]) with rodio stream data ([`parse_stream_meta`])
-/// into a single nushell `Record` value.
-fn parse_meta(
+/// into a single nushell `Record` value, overlaid with the sidecar when `--sidecar` is set.
+pub(crate) fn parse_meta(
     call: &EvaluatedCall,
-    mut file_value: std::fs::File,
+    file_value: std::fs::File,
     path: std::path::PathBuf,
 ) -> Result<Value, LabeledError> {
-    let (mut record, lofty_duration) = parse_tags(&path, call.head)?;
+    let parsing = Parsing::from_call(call)?;
+    let tags_only = call.has_flag("tags-only")?;
+    let mut warnings = Vec::new();
+    let (mut record, tagged_file) = parse_tags(
+        &path,
+        Artwork::from_call(call)?,
+        parsing,
+        tags_only,
+        &mut warnings,
+        call.head,
+    )?;
+
+    if !tags_only {
+        push_stream_fields(
+            &mut record,
+            file_value,
+            &path,
+            tagged_file.as_ref(),
+            parsing,
+            &mut warnings,
+            call.head,
+        )?;
+    }
 
+    if call.has_flag("sidecar").unwrap_or(false) {
+        if let Some(sidecar) = read_sidecar(&path, call.head)? {
+            for (col, val) in sidecar {
+                record.insert(col, val);
+            }
+        }
+    }
+    parsing.push_warnings(&mut record, warnings, call.head);
+
+    Ok(Value::record(record, call.head))
+}
+
+/// Pushes the stream properties of the local file at `path` onto `record`: duration,
+/// sample rate and channels, the sample format, and the gapless and LAME info of MP3s.
+///
+/// The duration, sample rate and channels come from the container headers lofty read
+/// into `tagged_file` when they hold all three; only otherwise, or with `--strict`, is
+/// a decoder opened, which costs far more than reading the headers.
+fn push_stream_fields(
+    record: &mut Record,
+    mut file_value: File,
+    path: &std::path::Path,
+    tagged_file: Option<&TaggedFile>,
+    parsing: Parsing,
+    warnings: &mut Vec<String>,
+    span: Span,
+) -> Result<(), LabeledError> {
     if let Err(e) = file_value.rewind() {
-        return Err(LabeledError::new(e.to_string()).with_label("error seeking file", call.head));
+        return Err(LabeledError::new(e.to_string()).with_label("error seeking file", span));
     }
 
-    match Decoder::try_from(file_value) {
-        Ok(source) => {
-            let stream_meta = parse_stream_meta(&source, lofty_duration, call.head);
+    let sample_format = sample_format_fields(&mut file_value, span);
+    if let Err(e) = file_value.rewind() {
+        return Err(LabeledError::new(e.to_string()).with_label("error seeking file", span));
+    }
+
+    let stream_meta = match header_stream_meta(tagged_file, parsing, span) {
+        Some(stream_meta) => Ok(stream_meta),
+        None => {
+            let lofty_duration = tagged_file.and_then(|tagged_file| {
+                Some(tagged_file.properties().duration()).filter(|d| !d.is_zero())
+            });
+            // A corrupt file can send the decoder's probe into an endless loop.
+            Watchdog::run(span, move |watchdog| {
+                let len = file_value.metadata().ok().map(|m| m.len());
+                let mut builder = Decoder::builder()
+                    .with_data(BufReader::new(watchdog.reader(file_value)))
+                    .with_seekable(true);
+                if let Some(len) = len {
+                    builder = builder.with_byte_len(len);
+                }
+                Ok(builder
+                    .build()
+                    .map(|source| parse_stream_meta(&source, lofty_duration, span)))
+            })?
+        }
+    };
+    match stream_meta {
+        Ok(stream_meta) => {
             for (col, val) in stream_meta {
                 record.push(col, val);
             }
         }
-        Err(e) => warn!("Failed to decode audio stream: {}", e),
+        Err(e) => parsing.stream_error(e, warnings, span)?,
     }
-
-    Ok(Value::record(record, call.head))
+    for (col, val) in sample_format {
+        record.push(col, val);
+    }
+    if let Some(gapless) = read_gapless(path) {
+        record.push("gapless", gapless.into_value(span));
+    }
+    if let Some(lame) = File::open(path).ok().and_then(|mut f| lame_value(&mut f, span)) {
+        record.push("lame", lame);
+    }
+    Ok(())
 }
 
 /// Reads lofty metadata from `path` and populates a nushell [`Record`].
 ///
 /// Covers file size, format extension, [`FileProperties`] (bitrate, bit depth),
-/// all [`TAG_MAP`] text fields, numeric track/disc accessors, and embedded artwork.
+/// all [`TAG_MAP`] text fields, numeric track/disc accessors, embedded artwork, and
+/// iTunes advisory/account/gapless fields.
 /// Opens its own file handle via `std::fs::metadata` / `lofty::read_from_path` so no
 /// caller-owned handle is required.
 ///
-/// Returns the record alongside the container-reported duration (if any) so the caller
-/// can pass it to [`parse_stream_meta`] as a fallback when rodio cannot determine the
-/// duration itself (e.g. with the minimp3 decoder).
-fn parse_tags(path: &std::path::Path, span: Span) -> Result<(Record, Option<Duration>), LabeledError> {
+/// Returns the record alongside the file lofty read, so the caller can take the stream
+/// properties from its container headers. With `tags_only` lofty skips those headers
+/// and the record holds no file properties.
+fn parse_tags(
+    path: &std::path::Path,
+    artwork: Artwork,
+    parsing: Parsing,
+    tags_only: bool,
+    warnings: &mut Vec<String>,
+    span: Span,
+) -> Result<(Record, Option<TaggedFile>), LabeledError> {
     let mut record = record! {};
-    let mut lofty_duration: Option<Duration> = None;
 
     let file_size = std::fs::metadata(path)
         .map(|m| m.len())
@@ -150,8 +664,38 @@ fn parse_tags(path: &std::path::Path, span: Span) -> Result<(Record, Option<Dura
         record.push("format", Value::string(ext.to_string_lossy().to_string(), span));
     }
 
-    let tagged_file = read_from_path(path)
-        .map_err(|e| LabeledError::new(e.to_string()).with_label("error reading tags", span))?;
+    let tagged_file = read_tagged(
+        |mut options| {
+            Probe::open(path)?
+                .options(options.read_properties(!tags_only))
+                .read()
+        },
+        artwork,
+        parsing,
+        warnings,
+        span,
+    )?;
+    let Some(tagged_file) = tagged_file else {
+        return Ok((record, None));
+    };
+    push_tag_fields(&mut record, &tagged_file, artwork, span);
+
+    // ── iTunes-specific fields ────────────────────────────────────────────
+    if let Some(itunes) = read_itunes_fields(path, tagged_file.file_type(), span) {
+        record.push("itunes", Value::record(itunes, span));
+    }
+    Ok((record, Some(tagged_file)))
+}
+
+/// Pushes file properties, [`TAG_MAP`] text fields, numeric track/disc info and embedded
+/// artwork from `tagged_file` onto `record`. Returns the container-header duration.
+fn push_tag_fields(
+    record: &mut Record,
+    tagged_file: &TaggedFile,
+    artwork: Artwork,
+    span: Span,
+) -> Option<Duration> {
+    let mut lofty_duration: Option<Duration> = None;
 
     // ── FileProperties ────────────────────────────────────────────────────
     let props = tagged_file.properties();
@@ -185,15 +729,15 @@ fn parse_tags(path: &std::path::Path, span: Span) -> Result<(Record, Option<Dura
                 continue;
             }
             if let Some(result) = tag.get_string(*val) {
-                insert_into_str(&mut record, key, Some(result.to_string()), span);
+                insert_into_str(record, key, Some(result.to_string()), span);
                 seen_keys.insert(*val);
             }
         }
 
-            insert_into_integer(&mut record, "track_no", tag.track(), span);
-            insert_into_integer(&mut record, "total_tracks", tag.track_total(), span);
-            insert_into_integer(&mut record, "disc_no", tag.disk(), span);
-            insert_into_integer(&mut record, "total_discs", tag.disk_total(), span);
+            insert_into_integer(record, "track_no", tag.track(), span);
+            insert_into_integer(record, "total_tracks", tag.track_total(), span);
+            insert_into_integer(record, "disc_no", tag.disk(), span);
+            insert_into_integer(record, "total_discs", tag.disk_total(), span);
 
             // ── Embedded artwork ──────────────────────────────────────────────
             let pictures = tag.pictures();
@@ -215,13 +759,109 @@ fn parse_tags(path: &std::path::Path, span: Span) -> Result<(Record, Option<Dura
                         if let Some(desc) = pic.description() {
                             art.push("description", Value::string(desc.to_string(), span));
                         }
+                        if artwork == Artwork::Data {
+                            art.push("data", Value::binary(pic.data().to_vec(), span));
+                        }
                         Value::record(art, span)
                     })
                     .collect();
                 record.push("artwork", Value::list(artwork, span));
             }
     }
-    Ok((record, lofty_duration))
+    lofty_duration
+}
+
+/// `sound meta` for a remote file: tags and properties are read by lofty through a
+/// [`RangeReader`], so only the parts of the file holding them are downloaded. Stream
+/// properties come from the container headers rather than a decoder. Live radio and HLS
+/// sources have no finite file and are described by [`live_stream_meta`] instead.
+fn parse_remote_meta(
+    engine: &nu_plugin::EngineInterface,
+    call: &EvaluatedCall,
+    url: &str,
+    span: Span,
+) -> Result<Value, LabeledError> {
+    let headers = call.get_flag_value("headers");
+    let icy = [("Icy-MetaData", "1".to_string())];
+    let head = http_request(engine, span, "http head", url, headers.as_ref(), &icy)?.into_value(span)?;
+    if let Some(record) = live_stream_meta(engine, span, url, headers.as_ref(), &head)? {
+        return Ok(Value::record(record, call.head));
+    }
+    let mut reader = RangeReader::open(engine, span, url, headers, &head)?;
+    let mut record = record! {};
+    record.push("size", Value::filesize(reader.size() as i64, span));
+    if let Some(ext) = url_extension(url) {
+        record.push("format", Value::string(ext, span));
+    }
+
+    let artwork = Artwork::from_call(call)?;
+    let parsing = Parsing::from_call(call)?;
+    let tags_only = call.has_flag("tags-only")?;
+    let mut warnings = Vec::new();
+    let tagged_file = read_tagged(
+        |mut options| {
+            reader.rewind()?;
+            Probe::new(&mut reader)
+                .options(options.read_properties(!tags_only))
+                .guess_file_type()?
+                .read()
+        },
+        artwork,
+        parsing,
+        &mut warnings,
+        span,
+    )?;
+    let duration = tagged_file
+        .as_ref()
+        .and_then(|tagged_file| push_tag_fields(&mut record, tagged_file, artwork, span));
+    if tags_only {
+        parsing.push_warnings(&mut record, warnings, span);
+        return Ok(Value::record(record, call.head));
+    }
+
+    record.push(
+        "duration",
+        duration.map_or(Value::nothing(span), |d| duration_value(d, span)),
+    );
+    if let Some(props) = tagged_file.as_ref().map(TaggedFile::properties) {
+        if let Some(rate) = props.sample_rate() {
+            record.push("sample_rate", Value::int(rate as i64, span));
+        }
+        if let Some(channels) = props.channels() {
+            record.push("channels", Value::int(channels as i64, span));
+        }
+    }
+    for (col, val) in sample_format_fields(&mut reader, span) {
+        record.push(col, val);
+    }
+    if let Some(gapless) = lame_gapless(&mut reader) {
+        record.push("gapless", gapless.into_value(span));
+    }
+    if let Some(lame) = lame_value(&mut reader, span) {
+        record.push("lame", lame);
+    }
+    parsing.push_warnings(&mut record, warnings, span);
+    Ok(Value::record(record, call.head))
+}
+
+/// Duration, sample rate and channel count from the container headers lofty read, in
+/// the columns [`parse_stream_meta`] fills; `None` when the headers lack any of them, or
+/// with `--strict`, which always decodes so a broken stream is caught.
+fn header_stream_meta(
+    tagged_file: Option<&TaggedFile>,
+    parsing: Parsing,
+    span: Span,
+) -> Option<Record> {
+    if parsing == Parsing::Strict {
+        return None;
+    }
+    let props = tagged_file?.properties();
+    let duration = Some(props.duration()).filter(|d| !d.is_zero())?;
+    Some(record! {
+        "duration" => duration_value(duration, span),
+        "sample_rate" => Value::int(props.sample_rate()? as i64, span),
+        "channels" => Value::int(props.channels()? as i64, span),
+    })
 }
 
 /// Extracts duration, sample rate, and channel count from a rodio [`Source`] and returns
@@ -232,13 +872,12 @@ fn parse_tags(path: &std::path::Path, span: Span) -> Result<(Record, Option<Dura
 /// minimp3 decoder is in use).  Only emits `Value::nothing` for the duration field when
 /// both sources are unavailable.
 ///
-/// Duration strings are formatted via [`format_duration`] (`M:SS` / `H:MM:SS`) so the
-/// output matches the live progress display in `audio_player`.
+/// The duration is a nushell duration; `--duration-format` converts it afterwards.
 fn parse_stream_meta(source: &impl Source, lofty_duration: Option<Duration>, span: Span) -> Record {
     let mut record = record! {};
     let duration = source.total_duration().or(lofty_duration);
     if let Some(d) = duration {
-        record.push("duration", Value::string(format_duration(d), span));
+        record.push("duration", duration_value(d, span));
     } else {
         warn!("Duration unavailable for source");
         record.push("duration", Value::nothing(span));
@@ -254,45 +893,60 @@ fn parse_stream_meta(source: &impl Source, lofty_duration: Option<Duration>, spa
 /// Looks up the normalised key in [`TAG_MAP`], obtains or creates the primary tag,
 /// calls `insert_text`, saves the file in-place, then re-reads and returns the
 /// updated metadata record so the caller always sees the final on-disk state.
+/// With `--sidecar` the tag is stored in the sidecar and the file is left untouched.
 fn audio_meta_set(engine: &nu_plugin::EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
-    let (_, file_value, path) = load_file(engine, call)?;
-    let key = match call.get_flag_value("key") {
-        Some(Value::String { val, .. }) => val,
-        _ => {
-            return Err(LabeledError::new("set key using `-k` flag".to_string())
-                .with_label("cannot get value of key", call.head));
-        }
-    };
-    let value = match call.get_flag_value("value") {
-        Some(Value::String { val, .. }) => val,
-        _ => {
-            return Err(LabeledError::new("set value using `-v` flag".to_string())
-                .with_label("cannot get value of value", call.head));
-        }
-    };
+    let (file_span, file_value, path) = load_file(engine, call)?;
+    let key: Spanned<String> = call.get_flag("key")?.ok_or_else(|| {
+        LabeledError::new("set key using `-k` flag".to_string())
+            .with_label("cannot get value of key", call.head)
+    })?;
+    let value: Value = call.get_flag_value("value").ok_or_else(|| {
+        LabeledError::new("set value using `-v` flag".to_string())
+            .with_label("cannot get value of value", call.head)
+    })?;
     drop(file_value);
 
-    let mut tagged_file = read_from_path(&path).map_err(|e| {
-        LabeledError::new(e.to_string()).with_label("error reading file", call.head)
+    let normalized_key = key.item.to_lowercase();
+    if !TAG_MAP.contains_key(normalized_key.as_str()) {
+        return Err(LabeledError::new(format!("Unknown metadata key: {}", normalized_key))
+            .with_label("key not found", key.span)
+            .with_help("run `sound meta --all` to list the key names"));
+    }
+
+    if call.has_flag("sidecar").unwrap_or(false) {
+        write_sidecar_value(&path, &normalized_key, &value, file_span)?;
+        let file = std::fs::File::open(&path).map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("error re-opening file for parsing", file_span)
+        })?;
+        return parse_meta(call, file, path);
+    }
+
+    let value = value.coerce_string()?;
+    with_write_access(&path, call.has_flag("force")?, file_span, || {
+        set_tag(&path, &normalized_key, value, key.span, file_span)
     })?;
 
-    let normalized_key = key.to_lowercase();
-    let item_key = TAG_MAP.get(normalized_key.as_str()).cloned().ok_or_else(|| {
-        LabeledError::new(format!("Unknown metadata key: {}", normalized_key))
-            .with_label("key not found", call.head)
+    let file = std::fs::File::open(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error re-opening file for parsing", file_span)
     })?;
+    parse_meta(call, file, path)
+}
 
-    let tag = match tagged_file.primary_tag_mut() {
-        Some(tag) => tag,
-        None => {
-            let tag_type = tagged_file.file_type().primary_tag_type();
-            tagged_file.insert_tag(Tag::new(tag_type));
-            tagged_file.primary_tag_mut().ok_or_else(|| {
-                LabeledError::new("failed to create primary tag for file".to_string())
-                    .with_label("tag insertion failed", call.head)
-            })?
-        }
-    };
+/// Writes `value` under the [`TAG_MAP`] key `normalized_key` into the file's primary tag.
+fn set_tag(
+    path: &std::path::Path,
+    normalized_key: &str,
+    value: String,
+    key_span: Span,
+    file_span: Span,
+) -> Result<(), LabeledError> {
+    let mut tagged_file = read_from_path(path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error reading file", file_span)
+    })?;
+
+    let item_key = TAG_MAP[normalized_key];
+
+    let tag = primary_tag_mut_or_insert(&mut tagged_file, file_span)?;
 
     let tag_type = tag.tag_type();
     if !tag.insert_text(item_key, value) {
@@ -300,18 +954,33 @@ fn audio_meta_set(engine: &nu_plugin::EngineInterface, call: &EvaluatedCall) ->
             "tag type {:?} rejected key '{}'",
             tag_type, normalized_key
         ))
-        .with_label("insert_text returned false", call.head));
+        .with_label("insert_text returned false", key_span)
+        .with_help(format!(
+            "use --sidecar to store it in {} instead",
+            sidecar_path(path).display()
+        )));
     }
 
-    tagged_file.save_to_path(&path, WriteOptions::default()).map_err(|e| {
-        LabeledError::new(e.to_string()).with_label("error saving file", call.head)
-    })?;
-
-    let file = std::fs::File::open(&path).map_err(|e| {
-        LabeledError::new(e.to_string()).with_label("error re-opening file for parsing", call.head)
-    })?;
-    parse_meta(call, file, path)
+    tagged_file.save_to_path(path, WriteOptions::default()).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error saving file", file_span)
+    })
 }
+/// Returns the primary tag of `tagged_file`, inserting an empty tag of the format's
+/// primary tag type first if the file has none yet.
+pub(crate) fn primary_tag_mut_or_insert(
+    tagged_file: &mut TaggedFile,
+    span: Span,
+) -> Result<&mut Tag, LabeledError> {
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.file_type().primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    tagged_file.primary_tag_mut().ok_or_else(|| {
+        LabeledError::new("failed to create primary tag for file".to_string())
+            .with_label("tag insertion failed", span)
+    })
+}
+
 /// Pushes a string field into `record` only when `val` is `Some`.
 fn insert_into_str(
     record: &mut Record,
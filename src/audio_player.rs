@@ -1,21 +1,54 @@
 use crossterm::{
-    cursor::{Hide, MoveToColumn, MoveUp, Show},
-    event::{self, Event, KeyCode, KeyEvent},
+    cursor::{Hide, MoveDown, MoveToColumn, MoveUp, Show},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
     execute, queue,
     style::{Attribute, SetAttribute},
-    terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, size, supports_keyboard_enhancement, Clear, ClearType,
+    },
 };
 use lofty::file::{AudioFile, TaggedFileExt};
 use lofty::prelude::Accessor;
-use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
-use nu_protocol::{Category, Example, LabeledError, Signature, SyntaxShape, Value};
-use rodio::{source::Source, Decoder, OutputStreamBuilder, Sink};
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::engine::ArgType;
+use nu_protocol::{
+    Category, DynamicSuggestion, Example, LabeledError, Signature, Span, Spanned, SyntaxShape,
+    Type, Value,
+};
+use rodio::{decoder::DecoderError, source::Source, Decoder, Sink};
 
-use std::io::{stderr, Write};
-use std::time::{Duration, Instant};
+use std::fs::File;
+use std::io::{stderr, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::{utils::{format_duration, load_file}, Sound};
+use crate::{
+    auto_gain::track_gain,
+    backend::{backend_flags, Backend},
+    completions::{backends, no_audio_policies, output_devices, repeat_modes, stream_roles},
+    config::{flag_or_config, plugin_config_value},
+    dsp::Rng,
+    ear_guard::{ear_guard_flags, EarGuard},
+    fade::{fade_length, Fade, FadeHandle},
+    headphone::{Binaural, Crossfeed},
+    live::LiveDecoder,
+    network::{http_request, http_stream, remote_url, url_extension, Download, Rebuffer, Tee, DEFAULT_CACHE},
+    no_audio::{no_audio_flags, NoAudio},
+    recovery::ResilientOutput,
+    remote::resolve_remote,
+    stream_identity::{role_flags, StreamRole},
+    stream_meta::is_live,
+    utils::{absolute_path, expand_glob, format_duration, is_glob, resolve_filepath},
+    validate::{self, duration_flag, duration_shape},
+    watchdog::{stalled_error, StallWatch, Watchdog},
+    waveform::{Waveform, WAVEFORM_LEVELS},
+    ytdlp::resolve_ytdlp,
+    Sound,
+};
 
 /// Interval for checking keyboard input.
 const KEY_POLL_INTERVAL: Duration = Duration::from_millis(200);
@@ -26,6 +59,14 @@ const RENDER_INTERVAL: Duration = Duration::from_millis(500);
 /// Amount to seek forward or backward when FF/RWD is pressed.
 const SEEK_STEP: Duration = Duration::from_secs(5);
 
+/// How long after the last seek keypress a scrub is committed, for terminals that do
+/// not report key releases. Longer than the usual key-repeat delay so holding a key
+/// does not seek between the first press and the first repeat.
+const SCRUB_COMMIT_DELAY: Duration = Duration::from_millis(500);
+
+/// Input polling interval while scrubbing, fast enough to keep up with key repeat.
+const SCRUB_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
 /// Minimum duration for interactive controls to be shown.
 const CONTROLS_THRESHOLD: Duration = Duration::from_secs(60);
 
@@ -35,6 +76,32 @@ const VOLUME_STEP: f32 = 0.05;
 /// Maximum volume (200%).
 const VOLUME_MAX: f32 = 2.0;
 
+/// How far the wall clock may run ahead of the monotonic clock between two polls
+/// before the gap is taken as a system suspend.
+const SUSPEND_GAP: Duration = Duration::from_secs(3);
+
+/// How long before the end of a track the next one of a playlist is opened and queued,
+/// so it starts without a gap.
+const GAPLESS_LOOKAHEAD: Duration = Duration::from_secs(1);
+
+/// Polling interval while a queued track takes over from the finished one.
+const GAPLESS_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What happens when a track ends.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Repeat {
+    Once,
+    All,
+    Track,
+}
+
+/// `--repeat` modes, with a description for completions.
+pub const REPEAT_MODES: [(&str, Repeat, &str); 3] = [
+    ("once", Repeat::Once, "play the tracks once and stop (default)"),
+    ("all", Repeat::All, "start over with the first track after the last"),
+    ("track", Repeat::Track, "play the same track again until n or p moves on"),
+];
+
 /// Selects the glyph set used for the live progress display.
 ///
 /// Priority order for resolution: `--nerd-fonts` flag → `NERD_FONTS=1` env var →
@@ -65,6 +132,14 @@ impl IconSet {
     /// Empty bar segment.
     fn empty(&self)        -> &'static str { match self { Self::NerdFont => "░",        Self::Unicode => "░",  Self::Ascii => "."   } }
 
+    /// Waveform strip glyph for a loudness level, `0..=WAVEFORM_LEVELS`.
+    fn waveform(&self, level: u8) -> &'static str {
+        const BLOCKS: [&str; 9] = [" ", "▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+        const ASCII:  [&str; 9] = [" ", ".", ":", "-", "=", "+", "*", "#", "@"];
+        let level = level.min(WAVEFORM_LEVELS) as usize;
+        match self { Self::Ascii => ASCII[level], _ => BLOCKS[level] }
+    }
+
     /// Volume icon — three tiers based on level.
     fn volume(&self, level: f32) -> &'static str {
         match self {
@@ -98,20 +173,34 @@ impl SimplePluginCommand for SoundPlayCmd {
     }
 
     fn signature(&self) -> nu_protocol::Signature {
-        Signature::new("sound play")
-            .required("File Path", SyntaxShape::Filepath, "file to play")
+        let signature = Signature::new("sound play")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::String, Type::Nothing),
+                (Type::List(Box::new(Type::String)), Type::Nothing),
+            ])
+            .rest(
+                "File Path",
+                SyntaxShape::Filepath,
+                "files, globs like *.flac, http(s) URLs or s3:// objects to play one after another",
+            )
             .named(
                 "duration",
-                SyntaxShape::Duration,
+                duration_shape(),
                 "truncate playback to this duration (default: auto-detected from file headers)",
                 Some('d'),
             )
             .named(
                 "amplify",
                 SyntaxShape::Float,
-                "initial volume: 1.0 = normal, 0.5 = half, 2.0 = double (default 1.0)",
+                "initial volume: 1.0 = normal, 0.5 = half, 2.0 = double (default 1.0, or play_amplify in the plugin config)",
                 Some('a'),
             )
+            .switch(
+                "auto-gain",
+                "level the track to a common loudness, from its ReplayGain tags or a quick measurement",
+                Some('g'),
+            )
             .switch(
                 "no-progress",
                 "disable live playback stats (use when piping or running in background)",
@@ -122,7 +211,64 @@ impl SimplePluginCommand for SoundPlayCmd {
                 "use Nerd Font icons in the progress display (or set NERD_FONTS=1)",
                 Some('n'),
             )
-            .category(Category::Experimental)
+            .switch(
+                "crossfeed",
+                "blend a little of each stereo channel into the other for relaxed headphone listening",
+                Some('x'),
+            )
+            .switch(
+                "binaural",
+                "render surround files as virtual speakers for headphones (simple head model)",
+                Some('b'),
+            )
+            .named(
+                "headers",
+                SyntaxShape::Any,
+                "extra HTTP headers when playing a URL, as a record or [name value] list",
+                Some('H'),
+            )
+            .switch(
+                "ytdlp",
+                "treat the argument as a web page and play its audio through yt-dlp",
+                Some('y'),
+            )
+            .named(
+                "tee",
+                SyntaxShape::Filepath,
+                "also write the stream's bytes to this file as they arrive (URL playback only)",
+                None,
+            )
+            .named(
+                "cache",
+                duration_shape(),
+                "read-ahead to keep buffered when playing a URL (default 10sec)",
+                None,
+            )
+            .switch(
+                "no-waveform",
+                "do not show the loudness strip under the progress bar of long files",
+                None,
+            )
+            .named(
+                "fade",
+                duration_shape(),
+                "fade length for pause, resume, seek and quit; 0sec disables (default 150ms, config: fade)",
+                None,
+            )
+            .switch(
+                "pause-on-suspend",
+                "pause (or stop, without controls) on wake from suspend; screen lock is not detected (config: pause_on_suspend)",
+                None,
+            )
+            .switch("shuffle", "play the tracks in random order", Some('s'))
+            .named(
+                "repeat",
+                SyntaxShape::String,
+                "once (default), all to loop the tracks, or track to loop each one",
+                Some('r'),
+            )
+            .category(Category::Experimental);
+        role_flags(backend_flags(no_audio_flags(ear_guard_flags(signature))))
     }
 
     fn examples(&self) -> Vec<Example<'_>> {
@@ -147,6 +293,21 @@ impl SimplePluginCommand for SoundPlayCmd {
                 example: "sound play audio.mp3",
                 result: None,
             },
+            Example {
+                description: "play a folder at an even volume, track after track",
+                example: "sound play *.flac --auto-gain",
+                result: None,
+            },
+            Example {
+                description: "listen to a folder like a player: shuffled, starting over when done",
+                example: "sound play ~/Music/*.mp3 --shuffle --repeat all",
+                result: None,
+            },
+            Example {
+                description: "play the tracks of a list, skipping with n and going back with p",
+                example: "ls ~/Music/album | where type == file | get name | sound play",
+                result: None,
+            },
             Example {
                 description: "play silently — no terminal output (background or pipe use)",
                 example: "sound play audio.mp3 --no-progress",
@@ -157,6 +318,36 @@ impl SimplePluginCommand for SoundPlayCmd {
                 example: "sound play audio.mp3 --nerd-fonts",
                 result: None,
             },
+            Example {
+                description: "stream a file over http with 30 seconds of read-ahead",
+                example: "sound play https://example.com/talk.mp3 --cache 30sec",
+                result: None,
+            },
+            Example {
+                description: "listen to a radio show and keep a copy of it",
+                example: "sound play https://radio.example.org/live.mp3 --tee show.mp3",
+                result: None,
+            },
+            Example {
+                description: "play the audio of a conference talk without downloading it first",
+                example: "sound play --ytdlp https://www.youtube.com/watch?v=...",
+                result: None,
+            },
+            Example {
+                description: "stream from a server that needs a bearer token",
+                example: "sound play https://media.internal/rec/standup.ogg -H {Authorization: $\"Bearer ($env.MEDIA_TOKEN)\"}",
+                result: None,
+            },
+            Example {
+                description: "play through JACK or PipeWire as its own client in the graph",
+                example: "sound play take3.wav --backend pipewire",
+                result: None,
+            },
+            Example {
+                description: "listen to a 5.1 file on headphones",
+                example: "sound play movie.flac --binaural",
+                result: None,
+            },
         ]
     }
 
@@ -169,14 +360,33 @@ impl SimplePluginCommand for SoundPlayCmd {
         Use --nerd-fonts (-n) or set NERD_FONTS=1 for richer icons."
     }
 
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "backend" => Some(backends()),
+            ArgType::Flag(flag) if flag == "device" => Some(output_devices()),
+            ArgType::Flag(flag) if flag == "role" => Some(stream_roles()),
+            ArgType::Flag(flag) if flag == "repeat" => Some(repeat_modes()),
+            _ => None,
+        }
+    }
+
     fn run(
         &self,
         _plugin: &Self::Plugin,
         engine: &EngineInterface,
         call: &EvaluatedCall,
-        _input: &Value,
+        input: &Value,
     ) -> Result<Value, nu_protocol::LabeledError> {
-        play_audio(engine, call).map(|_| Value::nothing(call.head))
+        play_audio(engine, call, input).map(|_| Value::nothing(call.head))
     }
 }
 
@@ -184,24 +394,373 @@ impl SimplePluginCommand for SoundPlayCmd {
 // Core playback
 // ---------------------------------------------------------------------------
 
-/// Opens the default audio output, decodes the file via rodio, and delegates to
-/// either [`wait_silent`] or [`wait_with_progress`] depending on `--no-progress`.
+/// Plays the tracks given as arguments or through the pipeline one after another,
+/// opening the audio output once for all of them.
+fn play_audio(engine: &EngineInterface, call: &EvaluatedCall, input: &Value) -> Result<(), LabeledError> {
+    if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+        return Ok(());
+    }
+    let mut tracks = playlist(engine, call, input)?;
+    let repeat = repeat_mode(call)?;
+    if call.has_flag("shuffle")? {
+        // Fisher–Yates, once: `p` goes back through the same order.
+        let mut rng = Rng::from_entropy();
+        for i in (1..tracks.len()).rev() {
+            tracks.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+        }
+    }
+    let no_progress = call.has_flag("no-progress").unwrap_or(false);
+    let cache = duration_flag(call, "cache")?.unwrap_or(DEFAULT_CACHE);
+    let tee = match call.get_flag::<Spanned<String>>("tee")? {
+        Some(tee) if tracks.len() > 1 => {
+            return Err(LabeledError::new("--tee records a single stream")
+                .with_label("more than one track to play", tee.span)
+                .with_help("play the tracks one at a time to keep a copy of each"));
+        }
+        Some(tee) => Some(create_tee(engine, &tee)?),
+        None => None,
+    };
+
+    let input = open_input(engine, call, &tracks[0], cache, tee, no_progress)?;
+    // The mixer reads the stream's name when it opens, so a playlist is named after
+    // its first track.
+    let title = track_name(&input, &tracks[0].item);
+    let role = StreamRole::from_call(call, StreamRole::Media)?;
+    let (mut output, mut sink) = ResilientOutput::open(
+        Backend::from_call(engine, call)?,
+        call.get_flag("device")?,
+        Some(title),
+        role,
+        call.head,
+    )?;
+
+    // Volume is now set on the Sink rather than baked into the source with
+    // amplify(), so it can be changed live and survives seeks correctly.
+    let guard = EarGuard::from_call(engine, call)?;
+    let max_volume = guard.map_or(VOLUME_MAX, |g| g.cap_volume(VOLUME_MAX));
+    let initial_volume: f32 = match flag_or_config(engine, call, "amplify", "play_amplify")? {
+        Some(value) => validate::gain(&value, "amplify")?.min(max_volume),
+        None => 1.0,
+    };
+    sink.set_volume(initial_volume);
+
+    // `--duration` cuts tracks short by stopping the sink, so they cannot run into
+    // each other.
+    let gapless = call.get_flag_value("duration").is_none();
+    let mut track = queue_track(engine, call, &sink, input, &tracks[0], tracks.len() > 1, guard)?;
+    let mut index = 0;
+    loop {
+        let playlist = (tracks.len() > 1).then_some((index, tracks.len()));
+        // Only a track that something follows hands over early.
+        let lookahead = match next_track(index, tracks.len(), TrackEnd::Finished, repeat) {
+            Some(_) if gapless => Some(GAPLESS_LOOKAHEAD),
+            _ => None,
+        };
+        let end = play_track(engine, call, &mut output, &sink, track, playlist, max_volume, lookahead)?;
+        let Some(next) = next_track(index, tracks.len(), end, repeat) else {
+            break;
+        };
+        index = next;
+        let input = open_input(engine, call, &tracks[index], cache, None, no_progress)?;
+        if end == TrackEnd::Finished && lookahead.is_some() {
+            // The end of the finished track is still playing; the next one is queued
+            // right behind it on the same sink.
+            track = queue_track(engine, call, &sink, input, &tracks[index], true, guard)?;
+            await_handover(engine, call, &sink, &mut output)?;
+        } else {
+            // Volume changes made during a track carry over to the next.
+            let volume = sink.volume();
+            sink = output.next_sink();
+            sink.set_volume(volume);
+            track = queue_track(engine, call, &sink, input, &tracks[index], true, guard)?;
+        }
+    }
+    Ok(())
+}
+
+/// Waits until the sink has moved on from a finished track to the one queued behind
+/// it. A track that outlasts the look-ahead by far, e.g. a stalled stream, is cut off.
+fn await_handover(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    sink: &Sink,
+    output: &mut ResilientOutput,
+) -> Result<(), LabeledError> {
+    let deadline = Instant::now() + GAPLESS_LOOKAHEAD * 3;
+    while sink.len() > 1 {
+        engine.signals().check(&call.head)?;
+        output.poll()?;
+        if Instant::now() >= deadline {
+            sink.skip_one();
+            break;
+        }
+        std::thread::sleep(GAPLESS_POLL_INTERVAL);
+    }
+    // The sink reports the new track's position once it has played a little of it.
+    std::thread::sleep(GAPLESS_POLL_INTERVAL);
+    Ok(())
+}
+
+/// Index of the track to play after the one at `index` ended as `end`, if any.
+fn next_track(index: usize, count: usize, end: TrackEnd, repeat: Repeat) -> Option<usize> {
+    let next = match (end, repeat) {
+        (TrackEnd::Quit, _) => return None,
+        (TrackEnd::Finished, Repeat::Track) => index,
+        (TrackEnd::Finished | TrackEnd::Next, Repeat::All) => (index + 1) % count,
+        (TrackEnd::Finished | TrackEnd::Next, _) => index + 1,
+        (TrackEnd::Previous, Repeat::All) => (index + count - 1) % count,
+        (TrackEnd::Previous, _) => index.saturating_sub(1),
+    };
+    (next < count).then_some(next)
+}
+
+/// The `--repeat` mode; [`Repeat::Once`] when not given.
+fn repeat_mode(call: &EvaluatedCall) -> Result<Repeat, LabeledError> {
+    let Some(mode) = call.get_flag::<Spanned<String>>("repeat")? else {
+        return Ok(Repeat::Once);
+    };
+    REPEAT_MODES
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(mode.item.trim()))
+        .map(|(_, repeat, _)| *repeat)
+        .ok_or_else(|| {
+            LabeledError::new(format!("unknown repeat mode '{}'", mode.item))
+                .with_label("expected once, all or track", mode.span)
+        })
+}
+
+/// The tracks to play, from the arguments and then the pipeline: files, URLs and globs,
+/// each glob expanded to the files it matches in sorted order.
+fn playlist(engine: &EngineInterface, call: &EvaluatedCall, input: &Value) -> Result<Vec<Spanned<String>>, LabeledError> {
+    let mut values: Vec<Value> = call.rest(0)?;
+    match input {
+        Value::List { vals, .. } => values.extend(vals.iter().cloned()),
+        Value::String { .. } => values.push(input.clone()),
+        _ => {}
+    }
+    let cwd = PathBuf::from(engine.get_current_dir().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("Could not get current directory", call.head)
+    })?);
+    let ytdlp = call.has_flag("ytdlp")?;
+    let mut tracks = Vec::new();
+    for value in values {
+        let span = value.span();
+        let item = value.coerce_into_string().map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("expected a file path or URL", span)
+        })?;
+        // A file whose name merely looks like a glob is played as it is.
+        if ytdlp || item.contains("://") || !is_glob(&item) || cwd.join(&item).exists() {
+            tracks.push(Spanned { item, span });
+            continue;
+        }
+        let files: Vec<PathBuf> = expand_glob(&cwd, &item).into_iter().filter(|path| path.is_file()).collect();
+        if files.is_empty() {
+            return Err(LabeledError::new("no files match")
+                .with_label(format!("nothing matches '{item}'"), span));
+        }
+        tracks.extend(files.into_iter().map(|file| Spanned {
+            item: file.to_string_lossy().into_owned(),
+            span,
+        }));
+    }
+    if tracks.is_empty() {
+        return Err(LabeledError::new("nothing to play")
+            .with_label("pass files, URLs or globs as arguments or through the pipeline", call.head));
+    }
+    Ok(tracks)
+}
+
+/// Opens a track from a local file, a URL, an s3:// object or, with `--ytdlp`, a web page.
+fn open_input(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    path: &Spanned<String>,
+    cache: Duration,
+    tee: Option<File>,
+    no_progress: bool,
+) -> Result<PlayInput, LabeledError> {
+    if call.has_flag("ytdlp")? {
+        let page = remote_url(&path.item).unwrap_or_else(|| path.item.clone());
+        let stream = resolve_ytdlp(engine, &page, path.span)?;
+        let mut input = open_url(engine, &stream.url, path.span, Some(&stream.headers), cache, tee, no_progress)?;
+        input.title = stream.title.or(input.title);
+        input.duration = input.duration.or(stream.duration);
+        return Ok(input);
+    }
+    match resolve_remote(engine, &path.item, path.span)? {
+        Some(url) => {
+            let headers = call.get_flag_value("headers");
+            open_url(engine, &url, path.span, headers.as_ref(), cache, tee, no_progress)
+        }
+        None if tee.is_some() => Err(LabeledError::new("--tee only records network sources")
+            .with_label("this is a local file", path.span)
+            .with_help("copy the file instead")),
+        None => open_file(engine, path),
+    }
+}
+
+/// "Artist — Title" for the OS mixer and the playlist display, or the file name for
+/// untagged input.
+fn track_name(input: &PlayInput, path: &str) -> String {
+    let name = [input.artist.as_deref(), input.title.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" — ");
+    if name.is_empty() {
+        path.rsplit(['/', '\\']).find(|part| !part.is_empty()).unwrap_or(path).to_string()
+    } else {
+        name
+    }
+}
+
+/// How a track stopped playing, which decides what a playlist plays next.
+#[derive(Clone, Copy, PartialEq)]
+enum TrackEnd {
+    /// Played to the end, or to `--duration`.
+    Finished,
+    /// Skipped with `n`.
+    Next,
+    /// Sent back with `p`.
+    Previous,
+    /// Stopped with `q`, or by a suspend without controls to resume from.
+    Quit,
+}
+
+/// A track appended to the sink, with what its playback loop needs.
+struct QueuedTrack {
+    fade: FadeHandle,
+    title: Option<String>,
+    artist: Option<String>,
+    /// When playback stops: `--duration`, or else the track's length.
+    total: Duration,
+    path: Option<PathBuf>,
+    download: Option<Download>,
+    watchdog: Option<Watchdog>,
+    duration: Option<Duration>,
+}
+
+/// Builds the source chain of a decoded track and appends it to `sink`. Untagged
+/// tracks of a `playlist` are titled with their file name.
 ///
 /// Duration is resolved in priority order: `-d` flag → `source.total_duration()` →
 /// `lofty::FileProperties::duration()` → 1-hour safety fallback.
-fn play_audio(engine: &EngineInterface, call: &EvaluatedCall) -> Result<(), LabeledError> {
-    let (file_span, file, path) = load_file(engine, call)?;
+fn queue_track(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    sink: &Sink,
+    input: PlayInput,
+    path: &Spanned<String>,
+    playlist: bool,
+    guard: Option<EarGuard>,
+) -> Result<QueuedTrack, LabeledError> {
+    let title = match (&input.title, &input.artist) {
+        (None, None) if playlist => Some(track_name(&input, &path.item)),
+        _ => input.title,
+    };
 
-    let mut output_stream = OutputStreamBuilder::open_default_stream().map_err(|err| {
-        LabeledError::new(err.to_string()).with_label("audio stream exception", call.head)
-    })?;
+    let gain = match &input.path {
+        Some(path) if call.has_flag("auto-gain")? => track_gain(path, input.duration),
+        _ => None,
+    };
+    let source: Box<dyn Source + Send> = match gain {
+        Some(gain) => Box::new(input.source.amplify(gain)),
+        None => input.source,
+    };
+    let source = headphone_chain(call, source)?;
+    let source: Box<dyn Source + Send> = match guard {
+        Some(guard) => Box::new(guard.protect(source)),
+        None => source,
+    };
+    let (source, fade) = Fade::new(source, fade_length(engine, call)?);
 
-    output_stream.log_on_drop(false);
+    sink.append(source);
 
-    let source = Decoder::try_from(file).map_err(|err| {
-        LabeledError::new(err.to_string()).with_label("audio decoder exception", file_span)
+    Ok(QueuedTrack {
+        fade,
+        title,
+        artist: input.artist,
+        total: duration_flag(call, "duration")?.or(input.duration).unwrap_or(Duration::from_secs(3600)),
+        path: input.path,
+        download: input.download,
+        watchdog: input.watchdog,
+        duration: input.duration,
+    })
+}
+
+/// Waits for a queued track to play, delegating to either [`wait_silent`] or
+/// [`wait_with_progress`] depending on `--no-progress`. `playlist` is the track's index
+/// and the number of tracks when there is more than one. With a `lookahead`, the track
+/// counts as finished that long before its end, leaving the rest playing while the next
+/// one is queued.
+#[allow(clippy::too_many_arguments)]
+fn play_track(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    output: &mut ResilientOutput,
+    sink: &Sink,
+    track: QueuedTrack,
+    playlist: Option<(usize, usize)>,
+    max_volume: f32,
+    lookahead: Option<Duration>,
+) -> Result<TrackEnd, LabeledError> {
+    let no_progress = call.has_flag("no-progress").unwrap_or(false);
+    let cache = duration_flag(call, "cache")?.unwrap_or(DEFAULT_CACHE);
+    let suspend = SuspendWatch::from_call(engine, call)?;
+    let decoder = track.watchdog.map(DecoderWatch::new);
+    let rebuffer = track
+        .download
+        .as_ref()
+        .map(|download| Rebuffer::new(download, cache, track.duration));
+
+    if no_progress {
+        wait_silent(engine, call, sink, output, track.total, lookahead, suspend, rebuffer, decoder)
+    } else {
+        let icon_set = resolve_icon_set(call);
+        let waveform = match &track.path {
+            Some(path) if track.total >= CONTROLS_THRESHOLD && !call.has_flag("no-waveform")? => {
+                Some(Waveform::spawn(path.clone(), track.total))
+            }
+            _ => None,
+        };
+        wait_with_progress(engine, call, sink, output, &track.fade, track.total, lookahead, sink.volume(), max_volume, icon_set, track.title, track.artist, playlist, suspend, waveform.as_ref(), rebuffer, decoder)
+    }
+}
+
+/// A decoded input ready for playback, with whatever is known about it up front.
+struct PlayInput {
+    source: Box<dyn Source + Send>,
+    title: Option<String>,
+    artist: Option<String>,
+    duration: Option<Duration>,
+    /// Local file, for the background waveform scan.
+    path: Option<PathBuf>,
+    /// Network download feeding the decoder.
+    download: Option<Download>,
+    /// Watchdog of a local file's decoder.
+    watchdog: Option<Watchdog>,
+}
+
+fn open_file(engine: &EngineInterface, path: &Spanned<String>) -> Result<PlayInput, LabeledError> {
+    let file_span = path.span;
+    let path = resolve_filepath(engine, file_span, PathBuf::from(&path.item))?;
+    let file = File::open(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error trying to open the file", file_span)
     })?;
 
+    let len = file.metadata().map(|m| m.len()).map_err(|err| {
+        LabeledError::new(err.to_string()).with_label("error reading file metadata", file_span)
+    })?;
+    let watchdog = Watchdog::default();
+    let source = Decoder::builder()
+        .with_data(BufReader::new(watchdog.reader(file)))
+        .with_byte_len(len)
+        .with_seekable(true)
+        .build()
+        .map_err(|err| {
+            LabeledError::new(err.to_string()).with_label("audio decoder exception", file_span)
+        })?;
+
     // Read the tagged file once; reuse the result for both metadata and duration fallback.
     let tagged_file_res = lofty::read_from_path(&path);
     let (title, artist) = tagged_file_res
@@ -211,45 +770,158 @@ fn play_audio(engine: &EngineInterface, call: &EvaluatedCall) -> Result<(), Labe
         .map(|tag| (tag.title().map(|s| s.to_string()), tag.artist().map(|s| s.to_string())))
         .unwrap_or((None, None));
 
-    // Volume is now set on the Sink rather than baked into the source with
-    // amplify(), so it can be changed live and survives seeks correctly.
-    let initial_volume: f32 = match call.get_flag_value("amplify") {
-        Some(Value::Float { val, .. }) => (val as f32).clamp(0.0, VOLUME_MAX),
-        _ => 1.0,
-    };
-
     // Prefer rodio's own duration; fall back to lofty's container-header duration
     // so that minimp3 (which cannot seek-scan) still reports the correct length
     // without needing a manual -d flag.
-    let source_duration: Option<Duration> = source.total_duration().or_else(|| {
+    let duration: Option<Duration> = source.total_duration().or_else(|| {
         tagged_file_res
             .ok()
             .map(|tf| tf.properties().duration())
             .filter(|d| !d.is_zero())
     });
 
-    let sink = Sink::connect_new(output_stream.mixer());
-    sink.append(source);
-    sink.set_volume(initial_volume);
+    Ok(PlayInput {
+        source: Box::new(source),
+        title,
+        artist,
+        duration,
+        path: Some(path),
+        download: None,
+        watchdog: Some(watchdog),
+    })
+}
+
+/// Creates the `--tee` file, relative to the shell's current directory.
+fn create_tee(engine: &EngineInterface, tee: &Spanned<String>) -> Result<File, LabeledError> {
+    let path = absolute_path(engine, PathBuf::from(&tee.item), tee.span)?;
+    File::create(&path).map_err(|e| {
+        LabeledError::new(e.to_string())
+            .with_label(format!("error creating {}", path.display()), tee.span)
+    })
+}
+
+/// Starts downloading `url` and decodes it as it arrives, once `cache` worth of audio
+/// has been read ahead. With `tee` the raw bytes are written there as they arrive.
+fn open_url(
+    engine: &EngineInterface,
+    url: &str,
+    span: Span,
+    headers: Option<&Value>,
+    cache: Duration,
+    tee: Option<File>,
+    no_progress: bool,
+) -> Result<PlayInput, LabeledError> {
+    let (reader, size) = http_stream(engine, span, url, headers, &[])?;
+    let reader = tee_reader(reader, tee.as_ref()).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error opening --tee file", span)
+    })?;
+    // Radio streams have no size; they are reopened when they drop instead of ending.
+    let live = size.is_none() && live_stream(engine, span, url, headers);
+    let download = if live {
+        let (engine, url, headers) = (engine.clone(), url.to_string(), headers.cloned());
+        Download::start_live(
+            reader,
+            Box::new(move || {
+                let (reader, _) = http_stream(&engine, span, &url, headers.as_ref(), &[])
+                    .map_err(|e| e.msg)?;
+                tee_reader(reader, tee.as_ref()).map_err(|e| e.to_string())
+            }),
+        )
+    } else {
+        Download::start(reader, size)
+    };
+
+    let mut err = stderr();
+    while !download.finished() && download.ahead(Duration::ZERO, None) < cache {
+        engine.signals().check(&span)?;
+        if !no_progress {
+            let _ = execute!(err, MoveToColumn(0));
+            let _ = write!(err, "buffering… {}", format_bytes(download.downloaded()));
+            let _ = execute!(err, Clear(ClearType::UntilNewLine));
+        }
+        std::thread::sleep(KEY_POLL_INTERVAL);
+    }
+    if !no_progress {
+        let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
+    }
 
-    let sleep_duration: Duration = match load_duration_from(call, "duration") {
-        Some(d) => d,
-        None => match source_duration {
-            Some(d) => d,
-            None => Duration::from_secs(3600),
-        },
+    let decoder_error =
+        |err: DecoderError| LabeledError::new(err.to_string()).with_label("audio decoder exception", span);
+    let source: Box<dyn Source + Send> = if live {
+        Box::new(LiveDecoder::new(download.reader(), url_extension(url)).map_err(decoder_error)?)
+    } else {
+        let mut builder = Decoder::builder()
+            .with_data(download.reader())
+            .with_seekable(true);
+        if let Some(size) = download.size() {
+            builder = builder.with_byte_len(size);
+        }
+        if let Some(extension) = url_extension(url) {
+            builder = builder.with_hint(extension);
+        }
+        Box::new(builder.build().map_err(decoder_error)?)
     };
 
-    let no_progress = call.has_flag("no-progress").unwrap_or(false);
+    Ok(PlayInput {
+        duration: source.total_duration(),
+        source,
+        title: Some(url.to_string()),
+        artist: None,
+        path: None,
+        download: Some(download),
+        watchdog: None,
+    })
+}
 
-    if no_progress {
-        wait_silent(engine, call, &sink, sleep_duration)
+/// Wraps `reader` so its bytes are also written to the `--tee` file, when there is one.
+fn tee_reader(
+    reader: Box<dyn Read + Send>,
+    tee: Option<&File>,
+) -> std::io::Result<Box<dyn Read + Send>> {
+    Ok(match tee {
+        Some(file) => Box::new(Tee::new(reader, file.try_clone()?)),
+        None => reader,
+    })
+}
+
+/// Whether `url` is internet radio, going by the headers of an `http head` request.
+/// Servers that do not answer HEAD are treated as serving a file.
+fn live_stream(engine: &EngineInterface, span: Span, url: &str, headers: Option<&Value>) -> bool {
+    http_request(engine, span, "http head", url, headers, &[])
+        .and_then(|data| data.into_value(span).map_err(LabeledError::from))
+        .is_ok_and(|head| is_live(&head))
+}
+
+/// Formats a byte count as `KiB`/`MiB` for the buffering display.
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1 << 20 {
+        format!("{:.1} MiB", bytes as f64 / (1u64 << 20) as f64)
     } else {
-        let icon_set = resolve_icon_set(call);
-        wait_with_progress(engine, call, &sink, sleep_duration, initial_volume, icon_set, title, artist)
+        format!("{} KiB", bytes >> 10)
     }
 }
 
+/// Applies `--binaural` and then `--crossfeed` to the decoded stream. Binaural rendering
+/// leaves layouts without known speaker positions (mono) untouched.
+fn headphone_chain<S: Source + Send + 'static>(
+    call: &EvaluatedCall,
+    source: S,
+) -> Result<Box<dyn Source + Send>, LabeledError> {
+    let source: Box<dyn Source + Send> = if call.has_flag("binaural")? {
+        match Binaural::new(source) {
+            Ok(binaural) => Box::new(binaural),
+            Err(source) => Box::new(source),
+        }
+    } else {
+        Box::new(source)
+    };
+    Ok(if call.has_flag("crossfeed")? {
+        Box::new(Crossfeed::new(source))
+    } else {
+        source
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Icon set resolution
 // ---------------------------------------------------------------------------
@@ -284,39 +956,93 @@ fn resolve_icon_set(call: &EvaluatedCall) -> IconSet {
 ///
 /// Exits early when `sink.empty()` returns `true` so the command returns promptly
 /// at the real end of the stream rather than sleeping for the full `total` duration.
+/// There are no controls to resume with, so a detected suspend stops playback.
+/// Network playback is held while `rebuffer` reports the read-ahead has run out.
+/// Playback fails if `decoder` reports the decoder has stalled. With a `lookahead`,
+/// playback counts as finished that long before `total`, while still playing.
+#[allow(clippy::too_many_arguments)]
 fn wait_silent(
     engine: &EngineInterface,
     call: &EvaluatedCall,
     sink: &Sink,
+    output: &mut ResilientOutput,
     total: Duration,
-) -> Result<(), LabeledError> {
-    let start = Instant::now();
-
-    while start.elapsed() < total && !sink.empty() {
+    lookahead: Option<Duration>,
+    mut suspend: Option<SuspendWatch>,
+    mut rebuffer: Option<Rebuffer>,
+    mut decoder: Option<DecoderWatch>,
+) -> Result<TrackEnd, LabeledError> {
+    while sink.get_pos() < total && !sink.empty() {
         engine.signals().check(&call.head)?;
+        let reconnecting = output.poll()?;
+        if suspend.as_mut().is_some_and(SuspendWatch::woke) {
+            sink.stop();
+            return Ok(TrackEnd::Quit);
+        }
+        let mut buffering = false;
+        if let Some(rebuffer) = rebuffer.as_mut() {
+            buffering = rebuffer.update(sink.get_pos());
+            if buffering {
+                sink.pause();
+            } else {
+                sink.play();
+            }
+        }
+        if let Some(decoder) = decoder.as_mut() {
+            decoder.check(sink.get_pos(), !reconnecting && !buffering, call.head)?;
+        }
+        if lookahead.is_some_and(|lookahead| sink.get_pos() + lookahead >= total) && !reconnecting && !buffering {
+            break;
+        }
         std::thread::sleep(KEY_POLL_INTERVAL);
     }
 
-    Ok(())
+    Ok(TrackEnd::Finished)
 }
 
 /// Renders a live progress line (and optional header) to stderr while the sink plays.
 ///
-/// For files longer than [`CONTROLS_THRESHOLD`] the terminal is placed in raw mode and
-/// keyboard events (space, arrows, `m`, `q`) are processed. Raw mode is always restored
-/// on exit, even if an error occurs.
+/// For files longer than [`CONTROLS_THRESHOLD`], and every track of a `playlist`, the
+/// terminal is placed in raw mode and keyboard events (space, arrows, `m`, `q`, and `n`
+/// and `p` between tracks) are processed. Raw mode is always restored
+/// on exit, even if an error occurs. After a detected suspend, interactive playback is
+/// paused and can be resumed with space; short files without controls are stopped.
+/// Pause, resume, seek and quit go through `fade` so they do not click.
+///
+/// Seek keys scrub: the bar follows the target while the key is held and the decoder
+/// seeks once, on key release where the terminal reports it, otherwise after
+/// [`SCRUB_COMMIT_DELAY`] without a seek keypress.
+///
+/// With a `waveform`, a loudness strip is drawn on the line below, aligned with the bar.
+/// For network playback, `rebuffer` holds output while the read-ahead is refilled and
+/// its fill level is shown on the progress line. While `output` is reconnecting after a
+/// device error, the progress line says so. Playback fails if `decoder` reports the
+/// decoder has stalled. The header counts the tracks of a `playlist`, given as the
+/// track's index and the number of tracks. With a `lookahead`, playback counts as
+/// finished that long before `total` unless paused, buffering or scrubbing, leaving the
+/// rest of the track playing.
+#[allow(clippy::too_many_arguments)]
 fn wait_with_progress(
     engine: &EngineInterface,
     call: &EvaluatedCall,
     sink: &Sink,
+    output: &mut ResilientOutput,
+    fade: &FadeHandle,
     total: Duration,
+    lookahead: Option<Duration>,
     initial_volume: f32,
+    max_volume: f32,
     icons: IconSet,
     title: Option<String>,
     artist: Option<String>,
-) -> Result<(), LabeledError> {
+    playlist: Option<(usize, usize)>,
+    mut suspend: Option<SuspendWatch>,
+    waveform: Option<&Waveform>,
+    mut rebuffer: Option<Rebuffer>,
+    mut decoder: Option<DecoderWatch>,
+) -> Result<TrackEnd, LabeledError> {
     let mut err = stderr();
-    let interactive = total >= CONTROLS_THRESHOLD;
+    let interactive = total >= CONTROLS_THRESHOLD || playlist.is_some();
 
     let mut position  = Duration::ZERO;
     let mut last_render = Instant::now().checked_sub(RENDER_INTERVAL).unwrap_or(Instant::now());
@@ -324,6 +1050,9 @@ fn wait_with_progress(
     let mut volume    = initial_volume;
     let mut pre_mute_volume = initial_volume;
     let mut first_render = true;
+    let mut reconnecting = false;
+    // Pending scrub target and the time of the last seek keypress.
+    let mut scrub: Option<(Duration, Instant)> = None;
 
     let _ = execute!(err, Hide);
 
@@ -336,7 +1065,10 @@ fn wait_with_progress(
 
         if !parts.is_empty() {
             let header_text = parts.join(" — ");
-            let prefix = format!("{}  ", icons.music());
+            let prefix = match playlist {
+                Some((index, count)) => format!("{}  [{}/{count}]  ", icons.music(), index + 1),
+                None => format!("{}  ", icons.music()),
+            };
             Some(format!("{}{}", prefix, header_text))
         } else {
             None
@@ -349,8 +1081,13 @@ fn wait_with_progress(
             return Err(LabeledError::new(e.to_string()).with_label("failed to enable raw terminal mode", call.head));
         }
     }
+    // Ask for key release events where the terminal supports them, to end scrubbing.
+    let release_events = interactive
+        && supports_keyboard_enhancement().unwrap_or(false)
+        && execute!(err, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)).is_ok();
 
     let result = (|| {
+        let mut end = TrackEnd::Finished;
         loop {
             // Cap at total: some codecs briefly report a position slightly
             // beyond the stream duration, which would incorrectly trip the
@@ -360,37 +1097,97 @@ fn wait_with_progress(
             if position >= total || sink.empty() {
                 break;
             }
+            let held = paused || reconnecting || scrub.is_some() || rebuffer.as_ref().is_some_and(Rebuffer::buffering);
+            if lookahead.is_some_and(|lookahead| position + lookahead >= total) && !held {
+                break;
+            }
 
             engine.signals().check(&call.head)?;
 
             let mut needs_render = false;
 
-            if interactive {
-                if event::poll(Duration::ZERO).unwrap_or(false) {
-                    if let Ok(Event::Key(KeyEvent { code, kind, .. })) = event::read() {
-                        if kind == event::KeyEventKind::Press {
-                            match code {
+            let was_reconnecting = reconnecting;
+            reconnecting = output.poll()?;
+            if reconnecting != was_reconnecting {
+                needs_render = true;
+            }
+
+            if suspend.as_mut().is_some_and(SuspendWatch::woke) && !paused {
+                if !interactive {
+                    sink.stop();
+                    end = TrackEnd::Quit;
+                    break;
+                }
+                fade.fade_out();
+                sink.pause();
+                paused = true;
+                needs_render = true;
+            }
+
+            // Hold output while a network stream refills its read-ahead. The user's own
+            // pause state is kept separately so resuming respects both.
+            let was_buffering = rebuffer.as_ref().is_some_and(Rebuffer::buffering);
+            let buffering = rebuffer.as_mut().is_some_and(|r| r.update(position));
+            if buffering != was_buffering {
+                if !paused && buffering {
+                    fade.fade_out();
+                    sink.pause();
+                } else if !paused {
+                    sink.play();
+                    fade.fade_in();
+                }
+                needs_render = true;
+            }
+
+            if let Some(decoder) = decoder.as_mut() {
+                decoder.check(position, !paused && !buffering && !reconnecting, call.head)?;
+            }
+
+            if let Some((target, last_press)) = scrub {
+                if last_press.elapsed() >= SCRUB_COMMIT_DELAY {
+                    seek_with_fade(sink, fade, paused, target);
+                    scrub = None;
+                    needs_render = true;
+                }
+            }
+
+            if interactive && event::poll(Duration::ZERO).unwrap_or(false) {
+                if let Ok(Event::Key(KeyEvent { code, kind, .. })) = event::read() {
+                    if kind == event::KeyEventKind::Release {
+                        // Releasing a seek key commits the scrub immediately.
+                        if let (KeyCode::Left | KeyCode::Right | KeyCode::Char('h' | 'l'), Some((target, _))) = (code, scrub) {
+                            seek_with_fade(sink, fade, paused, target);
+                            scrub = None;
+                            needs_render = true;
+                        }
+                    } else {
+                        match code {
                             // Space — toggle play/pause.
                             KeyCode::Char(' ') => {
-                                if paused { sink.play(); paused = false; }
-                                else      { sink.pause(); paused = true; }
+                                if paused {
+                                    if !buffering { sink.play(); fade.fade_in(); }
+                                    paused = false;
+                                } else {
+                                    if !buffering { fade.fade_out(); sink.pause(); }
+                                    paused = true;
+                                }
                                 needs_render = true;
                             }
-                            // Right / 'l' — seek forward.
+                            // Right / 'l' — scrub forward.
                             KeyCode::Right | KeyCode::Char('l') => {
-                                let target = (position + SEEK_STEP).min(total);
-                                let _ = sink.try_seek(target);
+                                let from = scrub.map_or(position, |(target, _)| target);
+                                scrub = Some(((from + SEEK_STEP).min(total), Instant::now()));
                                 needs_render = true;
                             }
-                            // Left / 'h' — seek backward.
+                            // Left / 'h' — scrub backward.
                             KeyCode::Left | KeyCode::Char('h') => {
-                                let target = position.saturating_sub(SEEK_STEP);
-                                let _ = sink.try_seek(target);
+                                let from = scrub.map_or(position, |(target, _)| target);
+                                scrub = Some((from.saturating_sub(SEEK_STEP), Instant::now()));
                                 needs_render = true;
                             }
                             // Up / 'k' — volume up.
                             KeyCode::Up | KeyCode::Char('k') => {
-                                volume = (volume + VOLUME_STEP).min(VOLUME_MAX);
+                                volume = (volume + VOLUME_STEP).min(max_volume);
                                 if volume > 0.0 { pre_mute_volume = volume; }
                                 sink.set_volume(volume);
                                 needs_render = true;
@@ -413,33 +1210,53 @@ fn wait_with_progress(
                                 sink.set_volume(volume);
                                 needs_render = true;
                             }
+                            // 'n' / 'p' — next or previous track of a playlist.
+                            KeyCode::Char(key @ ('n' | 'p')) if playlist.is_some() => {
+                                if !paused { fade.fade_out(); }
+                                sink.stop();
+                                end = if key == 'n' { TrackEnd::Next } else { TrackEnd::Previous };
+                                break;
+                            }
                             // 'q' / Escape — stop.
                             KeyCode::Char('q') | KeyCode::Esc => {
+                                if !paused { fade.fade_out(); }
                                 sink.stop();
+                                end = TrackEnd::Quit;
                                 break;
                             }
                             _ => {}
-                            }
                         }
                     }
                 }
             }
 
             if needs_render || last_render.elapsed() >= RENDER_INTERVAL {
-                render_progress(&mut err, position, total, paused, volume, interactive, &icons, header.as_deref(), first_render);
+                let shown = scrub.map_or(position, |(target, _)| target);
+                let status = if reconnecting {
+                    Some("reconnecting output".to_string())
+                } else {
+                    rebuffer.as_ref().and_then(Rebuffer::status)
+                };
+                render_progress(&mut err, shown, total, paused, volume, interactive, playlist.is_some(), &icons, header.as_deref(), status.as_deref(), waveform, first_render);
                 first_render = false;
                 last_render = Instant::now();
             }
-            std::thread::sleep(KEY_POLL_INTERVAL);
+            std::thread::sleep(if scrub.is_some() { SCRUB_POLL_INTERVAL } else { KEY_POLL_INTERVAL });
         }
 
-        render_progress(&mut err, position.min(total), total, false, volume, interactive, &icons, header.as_deref(), first_render);
-        Ok::<(), LabeledError>(())
+        render_progress(&mut err, position.min(total), total, false, volume, interactive, playlist.is_some(), &icons, header.as_deref(), None, waveform, first_render);
+        Ok::<TrackEnd, LabeledError>(end)
     })();
 
+    if release_events {
+        let _ = execute!(err, PopKeyboardEnhancementFlags);
+    }
     if interactive {
         let _ = disable_raw_mode();
     }
+    if waveform.is_some() {
+        let _ = execute!(err, MoveDown(1), MoveToColumn(0), Clear(ClearType::CurrentLine), MoveUp(1));
+    }
     if header.is_some() {
         let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
         let _ = execute!(err, Show, MoveUp(1), MoveToColumn(0), Clear(ClearType::CurrentLine));
@@ -450,6 +1267,94 @@ fn wait_with_progress(
     result
 }
 
+/// Seeks with a fade out and back in around the jump. A paused sink is already silent.
+fn seek_with_fade(sink: &Sink, fade: &FadeHandle, paused: bool, target: Duration) {
+    if !paused {
+        fade.fade_out();
+    }
+    let _ = sink.try_seek(target);
+    if !paused {
+        fade.fade_in();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Suspend detection
+// ---------------------------------------------------------------------------
+
+/// Notices when the machine has been suspended while playback was running.
+///
+/// The monotonic clock behind [`Instant`] stops during suspend on Linux and macOS while
+/// the wall clock keeps going, so a wall-clock jump much larger than the monotonic time
+/// between two polls means the system slept. Platforms whose monotonic clock keeps
+/// running through suspend simply never report a wake.
+struct SuspendWatch {
+    wall: SystemTime,
+    monotonic: Instant,
+}
+
+impl SuspendWatch {
+    /// Enabled by `--pause-on-suspend` or `pause_on_suspend: true` in the plugin config.
+    fn from_call(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<Option<SuspendWatch>, LabeledError> {
+        let configured = matches!(
+            plugin_config_value(engine, "pause_on_suspend", call.head)?,
+            Some(Value::Bool { val: true, .. })
+        );
+        if !call.has_flag("pause-on-suspend")? && !configured {
+            return Ok(None);
+        }
+        Ok(Some(SuspendWatch {
+            wall: SystemTime::now(),
+            monotonic: Instant::now(),
+        }))
+    }
+
+    /// Returns `true` once after each suspend/resume cycle.
+    fn woke(&mut self) -> bool {
+        let wall = SystemTime::now();
+        let monotonic = Instant::now();
+        let wall_gap = wall.duration_since(self.wall).unwrap_or_default();
+        let monotonic_gap = monotonic - self.monotonic;
+        self.wall = wall;
+        self.monotonic = monotonic;
+        wall_gap.saturating_sub(monotonic_gap) > SUSPEND_GAP
+    }
+}
+
+/// Gives up on a local file whose decoder has stopped producing audio: the position
+/// stands still for [`STALL_TIMEOUT`](crate::watchdog::STALL_TIMEOUT) while the sink
+/// should be playing. The watchdog is aborted so the decoder, stuck on the audio thread,
+/// fails its reads and unwinds.
+struct DecoderWatch {
+    watchdog: Watchdog,
+    stall: StallWatch<Duration>,
+}
+
+impl DecoderWatch {
+    fn new(watchdog: Watchdog) -> DecoderWatch {
+        DecoderWatch {
+            watchdog,
+            stall: StallWatch::new(),
+        }
+    }
+
+    /// Feeds the playback position; `playing` is whether it is expected to move.
+    fn check(&mut self, position: Duration, playing: bool, span: Span) -> Result<(), LabeledError> {
+        if !playing {
+            self.stall.reset();
+            return Ok(());
+        }
+        if self.stall.stalled(position) {
+            self.watchdog.abort();
+            return Err(stalled_error(span));
+        }
+        Ok(())
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Rendering
 // ---------------------------------------------------------------------------
@@ -463,6 +1368,11 @@ const MIN_RENDER_WIDTH: u16 = 40;
 /// Nerd Font:  ♪   0:42 / 4:05  [████████░░░░░░░░░░░░░░░░░░░░░░]  17%   100%  « [SPACE] »  [q]
 /// Unicode:    ♪ ▶  0:42 / 4:05  [████████░░░░░░░░░░░░░░░░░░░░░░]  17%  🔊 100%  « [SPACE] »  [q]
 /// ASCII:      > 0:42 / 4:05  [########......................]  17%  [V] 100%  << [SPACE] >>  [q]
+///
+/// A network `status` (buffer fill) is shown before the controls. With a `waveform`,
+/// the line below shows its loudness strip under the bar; the line stays blank until
+/// the background scan has finished.
+#[allow(clippy::too_many_arguments)]
 fn render_progress(
     err: &mut std::io::Stderr,
     elapsed: Duration,
@@ -470,8 +1380,11 @@ fn render_progress(
     paused: bool,
     volume: f32,
     interactive: bool,
+    playlist: bool,
     icons: &IconSet,
     header: Option<&str>,
+    status: Option<&str>,
+    waveform: Option<&Waveform>,
     first_render: bool,
 ) {
     // Bail out silently on very narrow terminals rather than wrapping garbage.
@@ -499,14 +1412,16 @@ fn render_progress(
 
     let controls_suffix = if interactive {
         let toggle_label = if paused { "play " } else { "pause" };
+        let tracks = if playlist { "  [n/p] track" } else { "" };
         format!(
-            "  {} [SPACE/{toggle_label}] {}  [↑↓/kj] vol  [m] mute  [q] quit",
+            "  {} [SPACE/{toggle_label}] {}  [↑↓/kj] vol  [m] mute{tracks}  [q] quit",
             icons.rewind(),
             icons.fast_forward(),
         )
     } else {
         String::new()
     };
+    let status_suffix = status.map(|status| format!("  {status}")).unwrap_or_default();
 
     // Dynamic width calculation
     let mut bar_width = 30;
@@ -531,6 +1446,7 @@ fn render_progress(
             + 1 // " "
             + vol_pct.to_string().width()
             + 1 // "%"
+            + status_suffix.width()
             + controls_suffix.width();
 
         let available = (cols as usize).saturating_sub(overhead);
@@ -606,11 +1522,23 @@ fn render_progress(
     let _ = buf.write_all(format!("{prefix}{icon}").as_bytes());
     let _ = queue!(buf, SetAttribute(Attribute::Reset));
     let _ = buf.write_all(
-        format!("  {elapsed_str} / {total_str}  {bar}  {percent}%  {vol_icon} {vol_bar} {vol_pct}%{controls_suffix}")
+        format!("  {elapsed_str} / {total_str}  {bar}  {percent}%  {vol_icon} {vol_bar} {vol_pct}%{status_suffix}{controls_suffix}")
             .as_bytes(),
     );
     let _ = queue!(buf, Clear(ClearType::UntilNewLine));
 
+    // Draw the waveform strip one line down, under the bar, and come back up.
+    if let Some(waveform) = waveform {
+        let _ = buf.write_all(b"\n");
+        let _ = queue!(buf, MoveToColumn(0));
+        if let Some(levels) = waveform.levels(bar_width) {
+            let offset = prefix.width() + icon.width() + 2 + elapsed_str.width() + 3 + total_str.width() + 2 + 1;
+            let strip: String = levels.iter().map(|level| icons.waveform(*level)).collect();
+            let _ = write!(buf, "{:offset$}{strip}", "");
+        }
+        let _ = queue!(buf, Clear(ClearType::UntilNewLine), MoveUp(1));
+    }
+
     let _ = err.write_all(&buf);
     let _ = err.flush();
 }
@@ -642,16 +1570,14 @@ fn render_bar(ratio: f64, width: usize, icons: &IconSet) -> String {
 
     let mut current_len = n_full;
 
-    if current_len < width {
-        if *icons == IconSet::NerdFont {
-            let remainder = f_width - n_full as f64;
-            let part_idx = (remainder * 8.0).floor() as usize;
-            if part_idx > 0 {
-                let partials = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
-                if part_idx <= partials.len() {
-                    s.push(partials[part_idx - 1]);
-                    current_len += 1;
-                }
+    if current_len < width && *icons == IconSet::NerdFont {
+        let remainder = f_width - n_full as f64;
+        let part_idx = (remainder * 8.0).floor() as usize;
+        if part_idx > 0 {
+            let partials = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+            if part_idx <= partials.len() {
+                s.push(partials[part_idx - 1]);
+                current_len += 1;
             }
         }
     }
@@ -684,16 +1610,3 @@ fn terminal_supports_unicode() -> bool {
         lang.contains("UTF-8") || lang.contains("UTF8")
     }
 }
-
-// ---------------------------------------------------------------------------
-// Helpers
-// ---------------------------------------------------------------------------
-
-/// Reads a nushell `Duration` flag by name and converts it to [`std::time::Duration`].
-/// Returns `None` if the flag was not supplied or contains a negative value.
-fn load_duration_from(call: &EvaluatedCall, name: &str) -> Option<Duration> {
-    match call.get_flag_value(name) {
-        Some(Value::Duration { val, .. }) if val >= 0 => Some(Duration::from_nanos(val as u64)),
-        _ => None,
-    }
-}
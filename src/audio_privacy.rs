@@ -0,0 +1,385 @@
+use lofty::config::ParseOptions;
+use lofty::file::{AudioFile, FileType, TaggedFileExt};
+use lofty::flac::FlacFile;
+use lofty::id3::v2::{Frame, Id3v2Tag};
+use lofty::mp4::{AtomData, AtomIdent, Mp4File};
+use lofty::mpeg::MpegFile;
+use lofty::ogg::{OpusFile, SpeexFile, VorbisComments, VorbisFile};
+use lofty::read_from_path;
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{record, Category, Example, LabeledError, Signature, SyntaxShape, Type, Value};
+use std::fs::File;
+use std::path::Path;
+
+use crate::{
+    utils::{collect_audio_files, load_path_args},
+    Sound,
+};
+
+/// Longest value shown in the report; the rest is elided.
+const MAX_PREVIEW: usize = 80;
+
+/// Field names that point at each kind of identifying data, matched case-insensitively
+/// as substrings of the key, frame description or freeform atom name.
+const NAME_PATTERNS: [(&str, Exposure); 17] = [
+    ("gps", Exposure::Location),
+    ("location", Exposure::Location),
+    ("latitude", Exposure::Location),
+    ("longitude", Exposure::Location),
+    ("geotag", Exposure::Location),
+    ("coordinates", Exposure::Location),
+    ("serial", Exposure::Serial),
+    ("device", Exposure::Serial),
+    ("uuid", Exposure::Serial),
+    ("hostname", Exposure::Serial),
+    ("machine", Exposure::Serial),
+    ("account", Exposure::Account),
+    ("email", Exposure::Account),
+    ("owner", Exposure::Account),
+    ("purchase", Exposure::Account),
+    ("encodedby", Exposure::Account),
+    ("encoded by", Exposure::Account),
+];
+
+/// MP4 atoms holding identifying data: `©xyz` is where phones store the recording
+/// location, `apID` the iTunes account, `ownr` its owner's name and `purd` the purchase
+/// date.
+const MP4_ATOMS: [(&[u8; 4], Exposure); 4] = [
+    (b"\xA9xyz", Exposure::Location),
+    (b"apID", Exposure::Account),
+    (b"ownr", Exposure::Account),
+    (b"purd", Exposure::Account),
+];
+
+/// Kind of identifying data a field gives away.
+#[derive(Clone, Copy, PartialEq)]
+enum Exposure {
+    /// Where a recording was made.
+    Location,
+    /// Serial numbers and other IDs of the recording device or computer.
+    Serial,
+    /// Store accounts, owner and encoder names, e-mail addresses.
+    Account,
+    /// Opaque IDs tying a file to a database or purchase (UFID and PRIV frames).
+    Identifier,
+}
+
+impl Exposure {
+    fn name(self) -> &'static str {
+        match self {
+            Exposure::Location => "location",
+            Exposure::Serial => "serial",
+            Exposure::Account => "account",
+            Exposure::Identifier => "identifier",
+        }
+    }
+}
+
+/// A tag field as read from the file: tag type, field name and its value as text.
+struct Field {
+    tag: String,
+    key: String,
+    value: String,
+}
+
+/// Nushell command `sound meta audit-privacy` — lists tag fields that may identify the
+/// person who made, bought or tagged a file.
+///
+/// Fields are flagged by name (GPS and location fields, serial numbers, accounts and
+/// owners, the encoder's name), by well-known MP4 atoms (`©xyz`, `apID`, `ownr`,
+/// `purd`), by value (e-mail addresses, ISO 6709 coordinates), and in MP3s every UFID
+/// and PRIV frame. Custom fields are read from the format's own tag (ID3v2 frames,
+/// Vorbis comments, MP4 atoms), as lofty's generic tags leave out names it does not
+/// know. Nothing is changed.
+pub struct SoundAuditPrivacyCmd;
+impl SimplePluginCommand for SoundAuditPrivacyCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta audit-privacy"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound meta audit-privacy")
+            .input_output_types(vec![
+                (Type::Nothing, Type::table()),
+                (Type::String, Type::table()),
+                (Type::List(Box::new(Type::String)), Type::table()),
+            ])
+            .rest(
+                "paths",
+                SyntaxShape::Filepath,
+                "audio files or directories to audit",
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "review a folder before publishing it",
+                example: "sound meta audit-privacy ~/Podcast/episodes",
+                result: None,
+            },
+            Example {
+                description: "find phone recordings that give away where they were made",
+                example:
+                    "ls *.m4a | get name | sound meta audit-privacy | where category == location",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "flag tag fields that may identify you: locations, serial numbers, accounts and file identifiers"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        audit_privacy(engine, call, input)
+    }
+}
+
+fn audit_privacy(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let files = collect_audio_files(&load_path_args(engine, call, input, 0)?, span)?;
+
+    let mut rows = Vec::new();
+    for path in files {
+        engine.signals().check(&span)?;
+        for (field, category) in read_fields(&path).into_iter().filter_map(|field| {
+            let category = classify(&field)?;
+            Some((field, category))
+        }) {
+            rows.push(Value::record(
+                record! {
+                    "path" => Value::string(path.to_string_lossy(), span),
+                    "tag" => Value::string(field.tag, span),
+                    "key" => Value::string(field.key, span),
+                    "category" => Value::string(category.name(), span),
+                    "value" => Value::string(preview(&field.value), span),
+                },
+                span,
+            ));
+        }
+    }
+    Ok(Value::list(rows, span))
+}
+
+/// Reads every text field of a file, from the format's own tag where it has one.
+/// Unreadable files have no fields.
+fn read_fields(path: &Path) -> Vec<Field> {
+    let options = ParseOptions::new().read_properties(false);
+    let native = File::open(path).ok().and_then(|mut file| {
+        let fields = match FileType::from_path(path)? {
+            FileType::Mpeg => id3v2_fields(MpegFile::read_from(&mut file, options).ok()?.id3v2()?),
+            FileType::Mp4 => mp4_fields(&Mp4File::read_from(&mut file, options).ok()?),
+            FileType::Flac => vorbis_fields(
+                FlacFile::read_from(&mut file, options)
+                    .ok()?
+                    .vorbis_comments()?,
+            ),
+            FileType::Vorbis => vorbis_fields(
+                VorbisFile::read_from(&mut file, options)
+                    .ok()?
+                    .vorbis_comments(),
+            ),
+            FileType::Opus => vorbis_fields(
+                OpusFile::read_from(&mut file, options)
+                    .ok()?
+                    .vorbis_comments(),
+            ),
+            FileType::Speex => vorbis_fields(
+                SpeexFile::read_from(&mut file, options)
+                    .ok()?
+                    .vorbis_comments(),
+            ),
+            _ => return None,
+        };
+        Some(fields)
+    });
+    native.unwrap_or_else(|| generic_fields(path))
+}
+
+fn id3v2_fields(tag: &Id3v2Tag) -> Vec<Field> {
+    let field = |key: String, value: &str| Field {
+        tag: "Id3v2".to_string(),
+        key,
+        value: value.to_string(),
+    };
+    tag.into_iter()
+        .filter_map(|frame| match frame {
+            Frame::Text(text) => Some(field(frame.id_str().to_string(), &text.value)),
+            Frame::UserText(text) => {
+                Some(field(format!("TXXX:{}", text.description), &text.content))
+            }
+            Frame::Comment(comment) => Some(field(
+                format!("COMM:{}", comment.description),
+                &comment.content,
+            )),
+            Frame::UserUrl(url) => Some(field(format!("WXXX:{}", url.description), &url.content)),
+            Frame::UniqueFileIdentifier(ufid) => Some(field(
+                "UFID".to_string(),
+                &format!(
+                    "{}: {}",
+                    ufid.owner,
+                    String::from_utf8_lossy(&ufid.identifier)
+                ),
+            )),
+            Frame::Private(private) => Some(field(
+                "PRIV".to_string(),
+                &format!("{}: {} bytes", private.owner, private.private_data.len()),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn mp4_fields(mp4: &Mp4File) -> Vec<Field> {
+    let Some(ilst) = mp4.ilst() else {
+        return Vec::new();
+    };
+    ilst.into_iter()
+        .filter_map(|atom| {
+            let key = match atom.ident() {
+                // Latin-1, so `©` (0xA9) reads as itself.
+                AtomIdent::Fourcc(fourcc) => fourcc.iter().map(|&b| b as char).collect(),
+                AtomIdent::Freeform { mean, name } => format!("----:{mean}:{name}"),
+            };
+            let value = atom.data().find_map(|data| match data {
+                AtomData::UTF8(text) | AtomData::UTF16(text) => Some(text.clone()),
+                _ => None,
+            })?;
+            Some(Field {
+                tag: "Mp4Ilst".to_string(),
+                key,
+                value,
+            })
+        })
+        .collect()
+}
+
+fn vorbis_fields(comments: &VorbisComments) -> Vec<Field> {
+    comments
+        .items()
+        .map(|(key, value)| Field {
+            tag: "VorbisComments".to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+/// Fields of formats without a native reader here, through lofty's generic tags.
+fn generic_fields(path: &Path) -> Vec<Field> {
+    let Ok(tagged_file) = read_from_path(path) else {
+        return Vec::new();
+    };
+    tagged_file
+        .tags()
+        .iter()
+        .flat_map(|tag| {
+            tag.items().filter_map(move |item| {
+                Some(Field {
+                    tag: format!("{:?}", tag.tag_type()),
+                    key: format!("{:?}", item.key()),
+                    value: item.value().text()?.to_string(),
+                })
+            })
+        })
+        .collect()
+}
+
+/// What kind of identifying data `field` holds, if any.
+fn classify(field: &Field) -> Option<Exposure> {
+    if field.key == "UFID" || field.key == "PRIV" {
+        return Some(Exposure::Identifier);
+    }
+    // ID3v2's "encoded by" frame, which usually holds a person's name.
+    if field.key == "TENC" {
+        return Some(Exposure::Account);
+    }
+    if let Some((_, category)) = MP4_ATOMS
+        .iter()
+        .find(|(fourcc, _)| latin1_eq(&field.key, fourcc))
+    {
+        return Some(*category);
+    }
+    let name = field.key.to_lowercase().replace(['_', '-'], "");
+    if let Some((_, category)) = NAME_PATTERNS
+        .iter()
+        .find(|(pattern, _)| name.contains(pattern))
+    {
+        return Some(*category);
+    }
+    if looks_like_coordinates(&field.value) {
+        return Some(Exposure::Location);
+    }
+    if contains_email(&field.value) {
+        return Some(Exposure::Account);
+    }
+    None
+}
+
+/// Whether `key`, read as Latin-1 by [`mp4_fields`], is the atom `fourcc`.
+fn latin1_eq(key: &str, fourcc: &[u8; 4]) -> bool {
+    key.chars()
+        .map(|c| c as u32)
+        .eq(fourcc.iter().map(|&b| b as u32))
+}
+
+/// ISO 6709 coordinates as phones write them, e.g. `+37.7749-122.4194+012.000/`.
+fn looks_like_coordinates(value: &str) -> bool {
+    let value = value.trim().trim_end_matches('/');
+    let signs = value.chars().filter(|c| matches!(c, '+' | '-')).count();
+    value.len() >= 8
+        && value.starts_with(['+', '-'])
+        && signs >= 2
+        && value.contains('.')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Whether any word of `value` looks like an e-mail address.
+fn contains_email(value: &str) -> bool {
+    value
+        .split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | ',' | ';'))
+        .any(|word| {
+            let Some((user, domain)) = word.split_once('@') else {
+                return false;
+            };
+            !user.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        })
+}
+
+/// `value` cut to [`MAX_PREVIEW`] characters, with control characters made visible.
+fn preview(value: &str) -> String {
+    let mut preview: String = value
+        .chars()
+        .take(MAX_PREVIEW)
+        .map(|c| {
+            if c.is_control() {
+                char::REPLACEMENT_CHARACTER
+            } else {
+                c
+            }
+        })
+        .collect();
+    if value.chars().count() > MAX_PREVIEW {
+        preview.push('…');
+    }
+    preview
+}
@@ -0,0 +1,800 @@
+use lofty::{
+    config::ParseOptions,
+    file::{FileType, TaggedFileExt},
+    id3::v1::GENRES,
+    prelude::Accessor,
+    probe::Probe,
+    tag::{Tag, TagType},
+};
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Record, Signature, Span, SyntaxShape, Type, Value,
+};
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+};
+
+use crate::{
+    audio_xing::rebuild_vbr_header,
+    utils::{collect_audio_files, force_flags, load_path_args, splice_file, with_write_access},
+    Sound,
+};
+
+/// Size of an ID3v1 tag, which fills the last bytes of a file.
+const ID3V1_LEN: u64 = 128;
+
+/// Size of the `TAG+` block some taggers put in front of an ID3v1 tag for longer fields.
+const ID3V1_EXTENDED_LEN: u64 = 227;
+
+/// FLAC metadata block types of the stream info, which comes first, and of padding.
+const FLAC_STREAMINFO: u8 = 0;
+const FLAC_PADDING: u8 = 1;
+
+/// Fields compared between tags, in the order [`fields_of`] and [`id3v1_fields`] return
+/// them.
+const FIELDS: [&str; 7] = [
+    "title", "artist", "album", "comment", "year", "track", "genre",
+];
+
+/// How many of [`FIELDS`] are text that ID3v1 cuts at 30 characters.
+const TRUNCATED_FIELDS: usize = 4;
+
+/// Nushell command `sound repair` — fixes structural problems in audio files without
+/// re-encoding them.
+///
+/// Each repair is opted into with its own switch:
+/// - `--rebuild-vbr-header` makes an MP3's Xing header match its stream.
+/// - `--strip-padding` removes the empty space taggers reserve in ID3v2 tags and FLAC
+///   metadata so later edits need not rewrite the file.
+/// - `--vacuum` removes tag blocks that add nothing: copies of the first ID3v2 tag
+///   stacked behind it, ID3v1 tags stacked in front of the last, and ID3v1 tags (or
+///   ID3v2 tags in FLAC files) whose fields the main tag already holds. Those holding
+///   a field the main tag lacks are kept and listed under `kept`.
+pub struct SoundRepairCmd;
+
+impl SimplePluginCommand for SoundRepairCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound repair"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound repair")
+            .input_output_types(vec![
+                (Type::Nothing, Type::table()),
+                (Type::String, Type::table()),
+                (Type::List(Box::new(Type::String)), Type::table()),
+            ])
+            .rest(
+                "paths",
+                SyntaxShape::Filepath,
+                "audio files or directories to repair, searched recursively",
+            )
+            .switch(
+                "rebuild-vbr-header",
+                "count an MP3's frames and write a matching Xing header, inserting one if needed",
+                None,
+            )
+            .switch(
+                "strip-padding",
+                "remove the padding reserved in ID3v2 tags and FLAC metadata",
+                None,
+            )
+            .switch(
+                "vacuum",
+                "remove stacked and duplicated ID3v1/ID3v2 tags that players ignore",
+                None,
+            )
+            .switch(
+                "dry-run",
+                "report what would be repaired without changing any file",
+                Some('n'),
+            )
+            .category(Category::Experimental);
+        force_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "fix the duration and seeking of a VBR MP3 without a header",
+                example: "sound repair --rebuild-vbr-header joined.mp3",
+                result: None,
+            },
+            Example {
+                description: "see how much space tag padding and duplicate tags take in a library",
+                example: "sound repair ~/Music --strip-padding --vacuum --dry-run | get reclaimed | math sum",
+                result: None,
+            },
+            Example {
+                description: "reclaim it",
+                example: "sound repair ~/Music --strip-padding --vacuum | where reclaimed > 0b",
+                result: None,
+            },
+            Example {
+                description: "repair every MP3 in a folder",
+                example: "ls *.mp3 | get name | sound repair --rebuild-vbr-header | select path vbr_header.action vbr_header.duration",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "repair audio files without re-encoding: rebuild MP3 VBR headers, strip tag padding, remove duplicate tags"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let span = call.head;
+        let vbr = call.has_flag("rebuild-vbr-header")?;
+        let strip_padding = call.has_flag("strip-padding")?;
+        let vacuum = call.has_flag("vacuum")?;
+        let dry_run = call.has_flag("dry-run")?;
+        let force = call.has_flag("force")?;
+        if !vbr && !strip_padding && !vacuum {
+            return Err(LabeledError::new("nothing to repair")
+                .with_label("no repair selected", span)
+                .with_help("pass --rebuild-vbr-header, --strip-padding or --vacuum"));
+        }
+        let files = collect_audio_files(&load_path_args(engine, call, input, 0)?, span)?;
+        let repairs = Repairs {
+            vbr,
+            strip_padding,
+            vacuum,
+            dry_run,
+            force,
+        };
+
+        let mut rows = Vec::new();
+        for path in files {
+            engine.signals().check(&span)?;
+            // A file that cannot be read or rewritten is reported in its row; the rest
+            // are still repaired.
+            let row = match repair_file(&path, &repairs, span) {
+                Ok(mut row) => {
+                    row.push("error", Value::nothing(span));
+                    row
+                }
+                Err(e) => record! {
+                    "path" => Value::string(path.to_string_lossy(), span),
+                    "error" => Value::string(e.msg, span),
+                },
+            };
+            rows.push(Value::record(row, span));
+        }
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// The repairs `sound repair` was asked for.
+struct Repairs {
+    vbr: bool,
+    strip_padding: bool,
+    vacuum: bool,
+    dry_run: bool,
+    force: bool,
+}
+
+/// Applies `repairs` to one file and describes what was done.
+fn repair_file(path: &Path, repairs: &Repairs, span: Span) -> Result<Record, LabeledError> {
+    let file_type = FileType::from_path(path);
+    let mut row = record! {
+        "path" => Value::string(path.to_string_lossy(), span),
+    };
+    if repairs.strip_padding || repairs.vacuum {
+        let cleanup = match file_type {
+            Some(FileType::Mpeg | FileType::Flac) => {
+                plan_cleanup(path, repairs.strip_padding, repairs.vacuum).map_err(|e| {
+                    LabeledError::new(e.to_string())
+                        .with_label(format!("error reading {}", path.display()), span)
+                })?
+            }
+            _ => Cleanup::default(),
+        };
+        if !repairs.dry_run && !cleanup.edits.is_empty() {
+            with_write_access(path, repairs.force, span, || {
+                splice_file(path, &cleanup.edits).map_err(|e| {
+                    LabeledError::new(e.to_string())
+                        .with_label(format!("error rewriting {}", path.display()), span)
+                })
+            })?;
+        }
+        let removed = cleanup
+            .removed
+            .iter()
+            .map(|what| Value::string(what, span))
+            .collect();
+        let kept = cleanup
+            .kept
+            .iter()
+            .map(|what| Value::string(what, span))
+            .collect();
+        row.push("removed", Value::list(removed, span));
+        row.push("kept", Value::list(kept, span));
+        row.push(
+            "reclaimed",
+            Value::filesize(cleanup.reclaimed() as i64, span),
+        );
+    }
+    if repairs.vbr {
+        let header = match file_type {
+            Some(FileType::Mpeg) if repairs.dry_run => {
+                Value::record(rebuild_vbr_header(path, true, span)?, span)
+            }
+            Some(FileType::Mpeg) => {
+                let header = with_write_access(path, repairs.force, span, || {
+                    rebuild_vbr_header(path, false, span)
+                })?;
+                Value::record(header, span)
+            }
+            _ => Value::nothing(span),
+        };
+        row.push("vbr_header", header);
+    }
+    Ok(row)
+}
+
+/// Byte ranges to cut from a file, each with what replaces it, a description of each
+/// thing removed, and of each redundant-looking tag kept because it holds more than the
+/// main tag.
+#[derive(Default)]
+struct Cleanup {
+    edits: Vec<(u64, u64, Vec<u8>)>,
+    removed: Vec<String>,
+    kept: Vec<String>,
+}
+
+impl Cleanup {
+    fn remove(&mut self, start: u64, end: u64, what: &str) {
+        self.edits.push((start, end, Vec::new()));
+        self.removed.push(what.to_string());
+    }
+
+    /// Removes the tag from `start` to `end` when `missing` is empty, and otherwise
+    /// records that it was kept for the fields it names.
+    fn remove_unless(&mut self, start: u64, end: u64, what: &str, missing: &[&str], main: &str) {
+        if missing.is_empty() {
+            self.remove(start, end, &format!("{what} (duplicates {main})"));
+        } else {
+            self.kept.push(format!(
+                "{what} (holds {} missing from {main})",
+                missing.join(", ")
+            ));
+        }
+    }
+
+    fn reclaimed(&self) -> u64 {
+        self.edits
+            .iter()
+            .map(|(start, end, replacement)| (end - start).saturating_sub(replacement.len() as u64))
+            .sum()
+    }
+}
+
+/// Works out what `--strip-padding` and `--vacuum` remove from an MP3 or FLAC file.
+fn plan_cleanup(path: &Path, strip_padding: bool, vacuum: bool) -> io::Result<Cleanup> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut cleanup = Cleanup::default();
+
+    // ID3v2 tags at the start, possibly several stacked by careless taggers.
+    let mut id3v2 = Vec::new();
+    let mut audio_start = 0;
+    while let Some(tag_len) = id3v2_len(&mut file, audio_start)? {
+        id3v2.push((audio_start, audio_start + tag_len));
+        audio_start += tag_len;
+    }
+    let flac = read_at(&mut file, audio_start, 4)?.as_deref() == Some(b"fLaC".as_slice());
+
+    // ID3v1 tags at the end, the last first; players read only the last.
+    let mut id3v1 = Vec::new();
+    let mut end = len;
+    while end >= audio_start + ID3V1_LEN {
+        let Some(tag) = read_at(&mut file, end - ID3V1_LEN, ID3V1_LEN as usize)? else {
+            break;
+        };
+        if &tag[..3] != b"TAG" {
+            break;
+        }
+        let mut start = end - ID3V1_LEN;
+        if start >= audio_start + ID3V1_EXTENDED_LEN
+            && read_at(&mut file, start - ID3V1_EXTENDED_LEN, 4)?.as_deref()
+                == Some(b"TAG+".as_slice())
+        {
+            start -= ID3V1_EXTENDED_LEN;
+        }
+        id3v1.push((start, end, tag));
+        end = start;
+    }
+
+    let mut keep_first_id3v2 = true;
+    if vacuum {
+        let tags = read_tags(path);
+        // The tag players use: Vorbis comments in FLAC, ID3v2 in MP3.
+        let main = tags.as_ref().and_then(|tags| {
+            let main = if flac {
+                TagType::VorbisComments
+            } else {
+                TagType::Id3v2
+            };
+            tags.iter().find(|tag| tag.tag_type() == main)
+        });
+        // Readers disagree on stacked tags (lofty merges them), so only exact copies of
+        // the first go.
+        if let Some(&(start, end)) = id3v2.first() {
+            let first = id3v2_content(&mut file, start, end)?;
+            for &(start, end) in id3v2.iter().skip(1) {
+                if id3v2_content(&mut file, start, end)? == first {
+                    cleanup.remove(start, end, "stacked id3v2 (duplicates the first)");
+                }
+            }
+        }
+        if let (true, Some(&(start, end)), Some(main)) = (flac, id3v2.first(), main) {
+            let id3 = tags
+                .as_ref()
+                .and_then(|tags| tags.iter().find(|tag| tag.tag_type() == TagType::Id3v2));
+            if let Some(id3) = id3 {
+                let missing = missing(main, &fields_of(id3));
+                cleanup.remove_unless(start, end, "id3v2", &missing, "vorbis comments");
+                keep_first_id3v2 = !missing.is_empty();
+            }
+        }
+        for (start, end, _) in id3v1.iter().skip(1) {
+            cleanup.remove(*start, *end, "stacked id3v1");
+        }
+        if let (Some((start, end, tag)), Some(main)) = (id3v1.first(), main) {
+            let main_name = if flac { "vorbis comments" } else { "id3v2" };
+            let missing = missing(main, &id3v1_fields(tag));
+            cleanup.remove_unless(*start, *end, "id3v1", &missing, main_name);
+        }
+    }
+
+    if strip_padding {
+        if let (true, Some(&(start, end))) = (keep_first_id3v2, id3v2.first()) {
+            let tag = read_range(&mut file, start, end)?;
+            if let Some(stripped) = id3v2_without_padding(&tag) {
+                cleanup.edits.push((start, end, stripped));
+                cleanup.removed.push("id3v2 padding".to_string());
+            }
+        }
+        if flac {
+            if let Some((end, blocks)) = flac_without_padding(&mut file, audio_start + 4)? {
+                cleanup.edits.push((audio_start + 4, end, blocks));
+                cleanup.removed.push("flac padding".to_string());
+            }
+        }
+    }
+    Ok(cleanup)
+}
+
+/// `len` bytes at `offset`, or `None` when the file ends first.
+fn read_at(file: &mut File, offset: u64, len: usize) -> io::Result<Option<Vec<u8>>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0u8; len];
+    match file.read_exact(&mut buffer) {
+        Ok(()) => Ok(Some(buffer)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn read_range(file: &mut File, start: u64, end: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(start))?;
+    let mut buffer = vec![0u8; (end - start) as usize];
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn syncsafe(bytes: &[u8]) -> Option<u64> {
+    bytes.iter().try_fold(0u64, |size, &byte| {
+        (byte < 0x80).then_some((size << 7) | byte as u64)
+    })
+}
+
+/// Length of the ID3v2 tag at `offset`, header and footer included, if there is one.
+fn id3v2_len(file: &mut File, offset: u64) -> io::Result<Option<u64>> {
+    let Some(header) = read_at(file, offset, 10)? else {
+        return Ok(None);
+    };
+    if &header[..3] != b"ID3" || !(2..=4).contains(&header[3]) {
+        return Ok(None);
+    }
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    Ok(syncsafe(&header[6..10]).map(|size| 10 + size + footer))
+}
+
+/// The ID3v2 tag `tag` with its padding cut off, or `None` when it has none or cannot
+/// safely be walked: a whole-tag unsynchronised v2.2/v2.3 tag, or one whose frames do
+/// not end in zero bytes.
+fn id3v2_without_padding(tag: &[u8]) -> Option<Vec<u8>> {
+    let (version, flags) = (tag[3], tag[5]);
+    // Tags with a footer may not be padded; v2.2 compression is undefined.
+    if flags & 0x10 != 0
+        || (version < 4 && flags & 0x80 != 0)
+        || (version == 2 && flags & 0x40 != 0)
+    {
+        return None;
+    }
+    let end = tag.len();
+    let mut at = 10;
+    // A v2.3 extended header records the padding size, which has to be zeroed.
+    let mut padding_field = None;
+    if version >= 3 && flags & 0x40 != 0 {
+        let size = tag.get(10..14)?;
+        at += match version {
+            3 => 4 + u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize,
+            _ => syncsafe(size)? as usize,
+        };
+        if version == 3 {
+            padding_field = Some(16..20).filter(|field| field.end <= at);
+        }
+    }
+    let header_len = if version == 2 { 6 } else { 10 };
+    while at + header_len <= end && tag[at] != 0 {
+        let id_len = if version == 2 { 3 } else { 4 };
+        if !tag[at..at + id_len]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        {
+            return None;
+        }
+        let size = match version {
+            2 => u32::from_be_bytes([0, tag[at + 3], tag[at + 4], tag[at + 5]]) as u64,
+            3 => u32::from_be_bytes([tag[at + 4], tag[at + 5], tag[at + 6], tag[at + 7]]) as u64,
+            _ => syncsafe(&tag[at + 4..at + 8])?,
+        };
+        at += header_len + size as usize;
+    }
+    if at >= end || tag[at..].iter().any(|&b| b != 0) {
+        return None;
+    }
+    let size = (at - 10) as u32;
+    let mut stripped = tag[..10].to_vec();
+    stripped[6..10].copy_from_slice(&[
+        (size >> 21) as u8 & 0x7F,
+        (size >> 14) as u8 & 0x7F,
+        (size >> 7) as u8 & 0x7F,
+        size as u8 & 0x7F,
+    ]);
+    stripped.extend_from_slice(&tag[10..at]);
+    if let Some(field) = padding_field {
+        stripped[field].fill(0);
+    }
+    Some(stripped)
+}
+
+/// The ID3v2 tag from `start` to `end` without its padding, so two tags that differ
+/// only in how much room they reserve compare equal.
+fn id3v2_content(file: &mut File, start: u64, end: u64) -> io::Result<Vec<u8>> {
+    let tag = read_range(file, start, end)?;
+    Ok(id3v2_without_padding(&tag).unwrap_or(tag))
+}
+
+/// The FLAC metadata blocks from `start` on without their `PADDING` blocks, and where
+/// the blocks end; `None` when there is no padding.
+fn flac_without_padding(file: &mut File, start: u64) -> io::Result<Option<(u64, Vec<u8>)>> {
+    let mut blocks = Vec::new();
+    let mut position = start;
+    let mut padded = false;
+    loop {
+        let Some(header) = read_at(file, position, 4)? else {
+            return Ok(None);
+        };
+        let kind = header[0] & 0x7F;
+        let last = header[0] & 0x80 != 0;
+        if kind == 127 {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as u64;
+        if kind == FLAC_PADDING {
+            padded = true;
+        } else {
+            let Some(body) = read_at(file, position + 4, len as usize)? else {
+                return Ok(None);
+            };
+            blocks.push((header, body));
+        }
+        position += 4 + len;
+        if last {
+            break;
+        }
+    }
+    // Without a leading STREAMINFO this is not metadata worth rewriting.
+    let streaminfo = blocks
+        .first()
+        .is_some_and(|(header, _)| header[0] & 0x7F == FLAC_STREAMINFO);
+    if !padded || !streaminfo {
+        return Ok(None);
+    }
+    let last = blocks.len() - 1;
+    let mut bytes = Vec::new();
+    for (i, (mut header, body)) in blocks.into_iter().enumerate() {
+        header[0] = (header[0] & 0x7F) | if i == last { 0x80 } else { 0 };
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&body);
+    }
+    Ok(Some((position, bytes)))
+}
+
+/// The file's tags, read without the audio properties; `None` when lofty cannot read
+/// it.
+fn read_tags(path: &Path) -> Option<Vec<Tag>> {
+    let tagged = Probe::open(path)
+        .ok()?
+        .options(ParseOptions::new().read_properties(false))
+        .read()
+        .ok()?;
+    Some(tagged.tags().to_vec())
+}
+
+/// The [`FIELDS`] of a tag. A genre given as an ID3v1 genre number, such as `(17)`, is
+/// turned into its name.
+fn fields_of(tag: &Tag) -> [Option<String>; 7] {
+    let genre = tag.genre().map(|genre| {
+        genre
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| GENRES.get(index))
+            .map_or_else(|| genre.into_owned(), |name| name.to_string())
+    });
+    [
+        tag.title().map(Cow::into_owned),
+        tag.artist().map(Cow::into_owned),
+        tag.album().map(Cow::into_owned),
+        tag.comment().map(Cow::into_owned),
+        tag.date().map(|date| date.year.to_string()),
+        tag.track().map(|track| track.to_string()),
+        genre,
+    ]
+}
+
+/// The [`FIELDS`] of an ID3v1 tag, whose text is Latin-1 padded with zeros or spaces.
+/// ID3v1.1 takes the last two bytes of the comment for a zero and the track number.
+fn id3v1_fields(tag: &[u8]) -> [Option<String>; 7] {
+    let text = |range: Range<usize>| {
+        let text: String = tag[range].iter().map(|&b| b as char).collect();
+        let text = text.trim_end_matches(['\0', ' ']);
+        (!text.is_empty()).then(|| text.to_string())
+    };
+    let track = (tag[125] == 0 && tag[126] != 0).then_some(tag[126]);
+    let comment = if track.is_some() { 97..125 } else { 97..127 };
+    [
+        text(3..33),
+        text(33..63),
+        text(63..93),
+        text(comment),
+        text(93..97),
+        track.map(|track| track.to_string()),
+        GENRES.get(tag[127] as usize).map(|genre| genre.to_string()),
+    ]
+}
+
+/// Names of the fields set in `fields` that `main` does not hold. ID3v1 cuts text at 30
+/// characters, so a text field counts as held when the main tag's value starts with it.
+fn missing(main: &Tag, fields: &[Option<String>; 7]) -> Vec<&'static str> {
+    let held = fields_of(main);
+    FIELDS
+        .iter()
+        .zip(held.iter().zip(fields))
+        .enumerate()
+        .filter(|(i, (_, (main, field)))| match (main, field) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(main), Some(field)) => {
+                let (main, field) = (main.to_lowercase(), field.to_lowercase());
+                if *i < TRUNCATED_FIELDS {
+                    !main.starts_with(&field)
+                } else {
+                    main != field
+                }
+            }
+        })
+        .map(|(_, (name, _))| *name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// A file in the temp directory holding `data`, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, data: &[u8]) -> TempFile {
+            // Tests run in parallel, so each file gets its own number.
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let number = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let path = std::env::temp_dir().join(format!(
+                "audio_hook_repair_{}_{number}_{name}",
+                std::process::id()
+            ));
+            std::fs::write(&path, data).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn syncsafe_bytes(size: usize) -> [u8; 4] {
+        [21, 14, 7, 0].map(|shift| (size >> shift) as u8 & 0x7F)
+    }
+
+    /// An ID3v2 tag of `version` holding `body` (frames and any extended header) and
+    /// `padding` zero bytes.
+    fn id3v2(version: u8, flags: u8, body: &[u8], padding: usize) -> Vec<u8> {
+        let mut tag = vec![b'I', b'D', b'3', version, 0, flags];
+        tag.extend(syncsafe_bytes(body.len() + padding));
+        tag.extend(body);
+        tag.extend(vec![0; padding]);
+        tag
+    }
+
+    fn title_frame(version: u8) -> Vec<u8> {
+        let mut frame = b"TIT2".to_vec();
+        let size = match version {
+            3 => 6u32.to_be_bytes(),
+            _ => syncsafe_bytes(6),
+        };
+        frame.extend(size);
+        frame.extend([0, 0, 3]);
+        frame.extend(b"Hello");
+        frame
+    }
+
+    #[test]
+    fn id3v2_padding_is_cut() {
+        let frame = title_frame(4);
+        let stripped = id3v2_without_padding(&id3v2(4, 0, &frame, 1024)).unwrap();
+        assert_eq!(stripped, id3v2(4, 0, &frame, 0));
+    }
+
+    #[test]
+    fn id3v23_extended_header_is_kept_with_zero_padding() {
+        // Extended header: size 6, no flags, 1024 bytes of padding.
+        let mut body = vec![0, 0, 0, 6, 0, 0];
+        body.extend(1024u32.to_be_bytes());
+        body.extend(title_frame(3));
+        let stripped = id3v2_without_padding(&id3v2(3, 0x40, &body, 1024)).unwrap();
+        body[6..10].fill(0);
+        assert_eq!(stripped, id3v2(3, 0x40, &body, 0));
+    }
+
+    #[test]
+    fn id3v2_without_safe_padding_is_left_alone() {
+        let frame = title_frame(4);
+        // No padding.
+        assert_eq!(id3v2_without_padding(&id3v2(4, 0, &frame, 0)), None);
+        // A footer, which rules out padding.
+        assert_eq!(id3v2_without_padding(&id3v2(4, 0x10, &frame, 16)), None);
+        // A v2.3 tag unsynchronised as a whole.
+        assert_eq!(
+            id3v2_without_padding(&id3v2(3, 0x80, &title_frame(3), 16)),
+            None
+        );
+        // Junk after the frames rather than zeros.
+        let mut tag = id3v2(4, 0, &frame, 16);
+        *tag.last_mut().unwrap() = 1;
+        assert_eq!(id3v2_without_padding(&tag), None);
+    }
+
+    /// A FLAC metadata block, flagged last when `last` is set.
+    fn flac_block(kind: u8, len: usize, last: bool) -> Vec<u8> {
+        let mut block = vec![kind | if last { 0x80 } else { 0 }];
+        block.extend(&(len as u32).to_be_bytes()[1..]);
+        block.extend(vec![kind + 1; len]);
+        block
+    }
+
+    fn flac_stripped(blocks: &[Vec<u8>]) -> Option<(u64, Vec<u8>)> {
+        let mut data = b"fLaC".to_vec();
+        data.extend(blocks.concat());
+        data.extend([0xFF, 0xF8]);
+        let file = TempFile::new("test.flac", &data);
+        flac_without_padding(&mut File::open(&file.0).unwrap(), 4).unwrap()
+    }
+
+    #[test]
+    fn flac_trailing_padding_is_cut() {
+        let streaminfo = flac_block(0, 34, false);
+        let comments = flac_block(4, 20, false);
+        let padding = flac_block(1, 8192, true);
+        let (end, bytes) = flac_stripped(&[streaminfo.clone(), comments.clone(), padding]).unwrap();
+        assert_eq!(end, 4 + 38 + 24 + 8196);
+        // The block before the padding becomes the last.
+        let mut expected = [streaminfo, comments].concat();
+        expected[38] |= 0x80;
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn flac_padding_between_blocks_is_cut() {
+        let streaminfo = flac_block(0, 34, false);
+        let comments = flac_block(4, 20, true);
+        let blocks = [
+            streaminfo.clone(),
+            flac_block(1, 100, false),
+            comments.clone(),
+        ];
+        let (end, bytes) = flac_stripped(&blocks).unwrap();
+        assert_eq!(end, 4 + 38 + 104 + 24);
+        assert_eq!(bytes, [streaminfo, comments].concat());
+    }
+
+    #[test]
+    fn flac_without_streaminfo_is_left_alone() {
+        assert_eq!(flac_stripped(&[flac_block(0, 34, true)]), None);
+        assert_eq!(flac_stripped(&[flac_block(1, 100, true)]), None);
+        assert_eq!(
+            flac_stripped(&[flac_block(4, 20, false), flac_block(1, 100, true)]),
+            None
+        );
+    }
+
+    fn id3v1(title: &str, year: &str, comment: &str, track: u8, genre: u8) -> Vec<u8> {
+        let mut tag = vec![0u8; ID3V1_LEN as usize];
+        tag[..3].copy_from_slice(b"TAG");
+        tag[3..3 + title.len()].copy_from_slice(title.as_bytes());
+        tag[93..93 + year.len()].copy_from_slice(year.as_bytes());
+        tag[97..97 + comment.len()].copy_from_slice(comment.as_bytes());
+        tag[126] = track;
+        tag[127] = genre;
+        tag
+    }
+
+    #[test]
+    fn id3v1_fields_are_read() {
+        let fields = id3v1_fields(&id3v1("Song", "1999", "nice", 7, 17));
+        assert_eq!(
+            fields,
+            [
+                Some("Song".to_string()),
+                None,
+                None,
+                Some("nice".to_string()),
+                Some("1999".to_string()),
+                Some("7".to_string()),
+                Some("Rock".to_string()),
+            ]
+        );
+        // ID3v1.0: no track, and genre 255 is unset.
+        assert_eq!(
+            id3v1_fields(&id3v1("Song", "", "", 0, 255))[4..],
+            [None, None, None]
+        );
+    }
+
+    #[test]
+    fn id3v1_is_only_redundant_when_every_field_is_held() {
+        let mut main = Tag::new(TagType::Id3v2);
+        main.set_title("Song title longer than ID3v1 can hold".to_string());
+        main.set_genre("(17)".to_string());
+        let tag = id3v1("Song title longer than ID3v1 c", "1999", "", 7, 17);
+        assert_eq!(missing(&main, &id3v1_fields(&tag)), ["year", "track"]);
+        main.set_track(7);
+        main.set_date("1999".parse().unwrap());
+        assert!(missing(&main, &id3v1_fields(&tag)).is_empty());
+        main.set_track(8);
+        assert_eq!(missing(&main, &id3v1_fields(&tag)), ["track"]);
+    }
+}
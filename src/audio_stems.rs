@@ -0,0 +1,209 @@
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute,
+    terminal::{Clear, ClearType},
+};
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{Category, Example, LabeledError, Signature, SyntaxShape, Type, Value};
+use std::{
+    fs::File,
+    io::{stderr, Write},
+    time::Duration,
+};
+
+use crate::{
+    ear_guard::{ear_guard_flags, EarGuard},
+    multitrack::{RawModeGuard, SyncedPlayback},
+    pcm::Pcm,
+    utils::{format_duration, load_path_args},
+    Sound,
+};
+
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const SEEK_STEP: Duration = Duration::from_secs(5);
+const MAX_STEMS: usize = 9;
+
+/// Nushell command `sound play-stems` — plays exported stems in sync for mix review.
+///
+/// Number keys select a stem, `m` toggles its mute and `s` its solo. When any stem is
+/// soloed only soloed stems are heard, as on a mixing desk.
+pub struct SoundPlayStemsCmd;
+impl SimplePluginCommand for SoundPlayStemsCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound play-stems"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound play-stems")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::String, Type::Nothing),
+                (Type::List(Box::new(Type::String)), Type::Nothing),
+            ])
+            .rest(
+                "stems",
+                SyntaxShape::Filepath,
+                "stem files to play together (up to 9)",
+            )
+            .category(Category::Experimental);
+        ear_guard_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "review three stems",
+                example: "sound play-stems drums.wav bass.wav vox.wav",
+                result: None,
+            },
+            Example {
+                description: "play every stem in an export folder",
+                example: "ls export/*.wav | get name | sound play-stems",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play stems in sync; 1-9 select a stem, m mutes, s solos, space pauses, ←/→ seek, q quits"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        play_stems(engine, call, input)
+    }
+}
+
+struct Stem {
+    name: String,
+    muted: bool,
+    soloed: bool,
+}
+
+fn play_stems(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let paths = load_path_args(engine, call, input, 0)?;
+    if paths.len() > MAX_STEMS {
+        return Err(LabeledError::new("too many stems").with_label(
+            format!("got {}, at most {MAX_STEMS} are supported", paths.len()),
+            span,
+        ));
+    }
+
+    let mut clips = Vec::with_capacity(paths.len());
+    let mut stems = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let file = File::open(path).map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error opening {}", path.display()), span)
+        })?;
+        clips.push(Pcm::decode(file, span)?);
+        stems.push(Stem {
+            name: path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            muted: false,
+            soloed: false,
+        });
+    }
+
+    // Every unmuted stem sounds at once, so each gets an equal share of the ceiling.
+    let guard = EarGuard::from_call(engine, call)?.map(|guard| guard.split(clips.len()));
+    let playback = SyncedPlayback::start(&clips, guard, span)?;
+    let _raw = RawModeGuard::enable(span)?;
+    let mut err = stderr();
+    let mut selected = 0;
+    let mut paused = false;
+
+    while !playback.finished() {
+        engine.signals().check(&span)?;
+        if event::poll(KEY_POLL_INTERVAL).unwrap_or(false) {
+            if let Ok(Event::Key(KeyEvent { code, kind, .. })) = event::read() {
+                if kind == KeyEventKind::Press {
+                    match code {
+                        KeyCode::Char(c @ '1'..='9') => {
+                            let index = c as usize - '1' as usize;
+                            if index < stems.len() {
+                                selected = index;
+                            }
+                        }
+                        KeyCode::Char('m') => stems[selected].muted = !stems[selected].muted,
+                        KeyCode::Char('s') => stems[selected].soloed = !stems[selected].soloed,
+                        KeyCode::Char(' ') => paused = playback.toggle_pause(),
+                        KeyCode::Right | KeyCode::Char('l') => {
+                            playback.seek(playback.position() + SEEK_STEP)
+                        }
+                        KeyCode::Left | KeyCode::Char('h') => {
+                            playback.seek(playback.position().saturating_sub(SEEK_STEP))
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => break,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        playback.set_volumes(&stem_volumes(&stems));
+
+        let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
+        let _ = write!(
+            err,
+            "{} {} / {}  {}",
+            if paused { "||" } else { ">" },
+            format_duration(playback.position()),
+            format_duration(playback.duration()),
+            render_stems(&stems, selected),
+        );
+        let _ = err.flush();
+    }
+
+    playback.stop();
+    let _ = execute!(err, MoveToColumn(0), Clear(ClearType::CurrentLine));
+    Ok(Value::nothing(span))
+}
+
+/// Mixing-desk logic: with any solo active only soloed stems play, otherwise every
+/// stem that is not muted.
+fn stem_volumes(stems: &[Stem]) -> Vec<f32> {
+    let any_solo = stems.iter().any(|s| s.soloed);
+    stems
+        .iter()
+        .map(|s| {
+            let audible = if any_solo { s.soloed } else { !s.muted };
+            if audible {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+fn render_stems(stems: &[Stem], selected: usize) -> String {
+    stems
+        .iter()
+        .enumerate()
+        .map(|(i, stem)| {
+            let flags = match (stem.muted, stem.soloed) {
+                (_, true) => " S",
+                (true, false) => " M",
+                _ => "",
+            };
+            let marker = if i == selected { '*' } else { ' ' };
+            format!("{marker}{}:{}{flags}", i + 1, stem.name)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
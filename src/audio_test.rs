@@ -0,0 +1,318 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Span, Spanned, SyntaxShape, Type, Value,
+};
+use rodio::{buffer::SamplesBuffer, OutputStream, OutputStreamBuilder, Sink};
+use std::{io::Write, time::Duration};
+
+use crate::{
+    dsp::{Biquad, Rng},
+    ear_guard::{ear_guard_flags, EarGuard},
+    stream_identity::{open_identified, StreamRole},
+    validate::{duration_flag, duration_shape},
+    Sound,
+};
+
+const TEST_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_CHANNEL_BURST: Duration = Duration::from_millis(1500);
+const DEFAULT_POLARITY_SEGMENT: Duration = Duration::from_secs(3);
+const SINK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Crossover for the LFE test burst; the subwoofer channel only carries low bass.
+const LFE_CUTOFF: f64 = 120.0;
+const TEST_LEVEL: f32 = 0.25;
+
+/// Channel order of each supported layout, in WAVE/SMPTE interleaving order.
+const LAYOUTS: [(&str, &[(&str, &str)]); 6] = [
+    ("mono", &[("C", "center")]),
+    ("stereo", &[("FL", "front left"), ("FR", "front right")]),
+    (
+        "2.1",
+        &[
+            ("FL", "front left"),
+            ("FR", "front right"),
+            ("LFE", "subwoofer"),
+        ],
+    ),
+    (
+        "quad",
+        &[
+            ("FL", "front left"),
+            ("FR", "front right"),
+            ("BL", "back left"),
+            ("BR", "back right"),
+        ],
+    ),
+    (
+        "5.1",
+        &[
+            ("FL", "front left"),
+            ("FR", "front right"),
+            ("FC", "front center"),
+            ("LFE", "subwoofer"),
+            ("BL", "surround left"),
+            ("BR", "surround right"),
+        ],
+    ),
+    (
+        "7.1",
+        &[
+            ("FL", "front left"),
+            ("FR", "front right"),
+            ("FC", "front center"),
+            ("LFE", "subwoofer"),
+            ("BL", "back left"),
+            ("BR", "back right"),
+            ("SL", "side left"),
+            ("SR", "side right"),
+        ],
+    ),
+];
+
+/// Nushell command `sound test channels` — plays a noise burst on each channel in turn.
+///
+/// The channel being played is announced on stderr so each speaker can be checked
+/// against its expected position. The subwoofer channel gets low-passed noise.
+pub struct SoundTestChannelsCmd;
+impl SimplePluginCommand for SoundTestChannelsCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound test channels"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound test channels")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .named(
+                "layout",
+                SyntaxShape::String,
+                "speaker layout: mono, stereo, 2.1, quad, 5.1 or 7.1 (default: stereo)",
+                Some('l'),
+            )
+            .named(
+                "duration",
+                duration_shape(),
+                "length of each channel's burst (default: 1.5sec)",
+                Some('d'),
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "seed for the noise, so runs repeat exactly (default: random)",
+                None,
+            )
+            .category(Category::Experimental);
+        ear_guard_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "walk through the speakers of a 5.1 system",
+            example: "sound test channels --layout 5.1",
+            result: None,
+        }]
+    }
+
+    fn description(&self) -> &str {
+        "play a noise burst on each output channel in turn to verify speaker order"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        test_channels(engine, call)
+    }
+}
+
+/// Nushell command `sound test polarity` — alternates in-phase and out-of-phase noise.
+///
+/// With correctly wired speakers the in-phase segment sounds focused between them with
+/// full bass, and the out-of-phase one diffuse and thin. If it is the other way round,
+/// one speaker is connected with reversed polarity.
+pub struct SoundTestPolarityCmd;
+impl SimplePluginCommand for SoundTestPolarityCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound test polarity"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound test polarity")
+            .input_output_types(vec![(Type::Nothing, Type::Nothing)])
+            .named(
+                "duration",
+                duration_shape(),
+                "length of each segment (default: 3sec)",
+                Some('d'),
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "seed for the noise, so runs repeat exactly (default: random)",
+                None,
+            )
+            .category(Category::Experimental);
+        ear_guard_flags(signature)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "check that both speakers are wired with the same polarity",
+            example: "sound test polarity",
+            result: None,
+        }]
+    }
+
+    fn description(&self) -> &str {
+        "alternate in-phase and out-of-phase stereo noise to check speaker polarity"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        test_polarity(engine, call)
+    }
+}
+
+fn test_channels(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let layout = call.get_flag::<Spanned<String>>("layout")?;
+    let channels = match &layout {
+        None => LAYOUTS[1].1,
+        Some(name) => LAYOUTS
+            .iter()
+            .find(|(layout, _)| layout.eq_ignore_ascii_case(&name.item))
+            .map(|(_, channels)| *channels)
+            .ok_or_else(|| {
+                LabeledError::new(format!("unknown layout '{}'", name.item))
+                    .with_label("expected mono, stereo, 2.1, quad, 5.1 or 7.1", name.span)
+            })?,
+    };
+    let burst = duration_flag(call, "duration")?.unwrap_or(DEFAULT_CHANNEL_BURST);
+    let count = channels.len() as u16;
+    let guard = EarGuard::from_call(engine, call)?;
+
+    let (_stream, sink) = open_test_output(count, span)?;
+    let mut rng = seeded_rng(call)?;
+    let mut rows = Vec::with_capacity(channels.len());
+    for (index, (short, name)) in channels.iter().enumerate() {
+        let mut burst = noise(&mut rng, burst);
+        if *short == "LFE" {
+            let mut filter = Biquad::low_pass(LFE_CUTOFF, TEST_SAMPLE_RATE);
+            burst.iter_mut().for_each(|s| *s = filter.process(*s));
+        }
+        let mut samples = vec![0.0; burst.len() * channels.len()];
+        for (frame, value) in burst.iter().enumerate() {
+            samples[frame * channels.len() + index] = *value;
+        }
+
+        announce(&format!("channel {}: {name} ({short})", index + 1));
+        play(&sink, guard, SamplesBuffer::new(count, TEST_SAMPLE_RATE, samples));
+        wait_for_sink(engine, &sink, span)?;
+        rows.push(Value::record(
+            record! {
+                "index" => Value::int(index as i64 + 1, span),
+                "channel" => Value::string(*short, span),
+                "name" => Value::string(*name, span),
+            },
+            span,
+        ));
+    }
+    announce("");
+    Ok(Value::list(rows, span))
+}
+
+fn test_polarity(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let segment = duration_flag(call, "duration")?.unwrap_or(DEFAULT_POLARITY_SEGMENT);
+    let guard = EarGuard::from_call(engine, call)?;
+    let (_stream, sink) = open_test_output(2, span)?;
+    let mut rng = seeded_rng(call)?;
+
+    for (label, sign) in [
+        ("in phase: should sound centered with full bass", 1.0),
+        ("out of phase: should sound diffuse and thin", -1.0),
+        ("in phase again", 1.0),
+    ] {
+        // Low-passed noise: polarity errors are most audible as lost bass.
+        let mut filter = Biquad::low_pass(500.0, TEST_SAMPLE_RATE);
+        let samples: Vec<f32> = noise(&mut rng, segment)
+            .into_iter()
+            .flat_map(|s| {
+                let s = filter.process(s) * 2.0;
+                [s, s * sign]
+            })
+            .collect();
+        announce(label);
+        play(&sink, guard, SamplesBuffer::new(2, TEST_SAMPLE_RATE, samples));
+        wait_for_sink(engine, &sink, span)?;
+    }
+    announce("");
+    Ok(Value::nothing(span))
+}
+
+fn open_test_output(channels: u16, span: Span) -> Result<(OutputStream, Sink), LabeledError> {
+    let mut stream = open_identified(Some("test signal"), StreamRole::Test, || {
+        OutputStreamBuilder::from_default_device()
+            .map(|builder| builder.with_channels(channels))
+            .and_then(|builder| builder.open_stream())
+    })
+    .map_err(|err| {
+        LabeledError::new(err.to_string())
+            .with_label(format!("cannot open a {channels}-channel output"), span)
+            .with_help("check that the default device is configured for this layout")
+    })?;
+    stream.log_on_drop(false);
+    let sink = Sink::connect_new(stream.mixer());
+    Ok((stream, sink))
+}
+
+fn play(sink: &Sink, guard: Option<EarGuard>, buffer: SamplesBuffer) {
+    match guard {
+        Some(guard) => sink.append(guard.protect(buffer)),
+        None => sink.append(buffer),
+    }
+}
+
+fn wait_for_sink(engine: &EngineInterface, sink: &Sink, span: Span) -> Result<(), LabeledError> {
+    while !sink.empty() {
+        engine.signals().check(&span)?;
+        std::thread::sleep(SINK_POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+/// The generator for the noise, from `--seed` if given.
+fn seeded_rng(call: &EvaluatedCall) -> Result<Rng, LabeledError> {
+    Ok(call
+        .get_flag::<i64>("seed")?
+        .map_or_else(Rng::from_entropy, |seed| Rng::new(seed as u64)))
+}
+
+/// White noise at the test level with 10 ms fades to avoid clicks.
+fn noise(rng: &mut Rng, duration: Duration) -> Vec<f32> {
+    let len = (duration.as_secs_f64() * TEST_SAMPLE_RATE as f64) as usize;
+    let fade = (TEST_SAMPLE_RATE as usize / 100).min(len / 2).max(1);
+    (0..len)
+        .map(|i| {
+            let white = rng.next_sample();
+            let gain = (i.min(len - 1 - i) as f32 / fade as f32).min(1.0);
+            white * gain * TEST_LEVEL
+        })
+        .collect()
+}
+
+fn announce(text: &str) {
+    let mut err = std::io::stderr();
+    let _ = write!(err, "\r\x1b[2K{text}");
+    let _ = err.flush();
+}
@@ -0,0 +1,250 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Span, Spanned, SyntaxShape, Type, Value,
+};
+use rodio::{source::UniformSourceIterator, Decoder};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    utils::{load_file, not_installed, run_tool},
+    wav::encode_wav,
+    Sound,
+};
+
+/// Sample rate whisper models are trained on; input is resampled to mono at this rate.
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// whisper.cpp CLI names tried in order when `WHISPER_CPP` is not set.
+const WHISPER_BINARIES: [&str; 2] = ["whisper-cli", "whisper-cpp"];
+
+/// Nushell command `sound transcribe` — speech-to-text via the whisper.cpp CLI.
+///
+/// The input is decoded and resampled to 16 kHz mono WAV in a temp file, passed to
+/// whisper.cpp with JSON output enabled, and the resulting segments are returned as a
+/// table of `start`, `end`, and `text`. Running an external binary keeps the plugin free
+/// of the multi-hundred-megabyte native dependency.
+pub struct SoundTranscribeCmd;
+impl SimplePluginCommand for SoundTranscribeCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound transcribe"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound transcribe")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .required("File Path", SyntaxShape::Filepath, "recording to transcribe")
+            .named(
+                "model",
+                SyntaxShape::String,
+                "model name (tiny, base, small, ...) looked up as ggml-<name>.bin in $env.WHISPER_MODELS_DIR, or a path to a model file (default: base)",
+                Some('m'),
+            )
+            .named(
+                "language",
+                SyntaxShape::String,
+                "spoken language code, e.g. en or de (default: auto-detect)",
+                Some('l'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "transcribe a meeting recording with the tiny model",
+                example: "sound transcribe recording.wav --model tiny",
+                result: None,
+            },
+            Example {
+                description: "collect the full text of a transcript",
+                example: "sound transcribe talk.mp3 --language en | get text | str join ' '",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "transcribe speech to timestamped text segments using whisper.cpp \
+        (requires `whisper-cli` on PATH or $env.WHISPER_CPP, and a ggml model file)"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        transcribe(engine, call)
+    }
+}
+
+fn transcribe(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let (file_span, file, _) = load_file(engine, call)?;
+    let model = resolve_model(engine, call)?;
+
+    let source = Decoder::try_from(file).map_err(|err| {
+        LabeledError::new(err.to_string()).with_label("audio decoder exception", file_span)
+    })?;
+    let wav = encode_wav(UniformSourceIterator::new(source, 1, WHISPER_SAMPLE_RATE))?;
+
+    let (base, mut temp) = create_temp_wav().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error creating temp file", span)
+    })?;
+    let wav_path = base.with_extension("wav");
+    let json_path = base.with_extension("json");
+    if let Err(e) = temp.write_all(&wav) {
+        let _ = std::fs::remove_file(&wav_path);
+        return Err(LabeledError::new(e.to_string()).with_label("error writing temp file", span));
+    }
+    drop(temp);
+
+    let result = run_whisper(engine, call, &model, &wav_path, &base)
+        .and_then(|_| read_segments(&json_path, span));
+    let _ = std::fs::remove_file(&wav_path);
+    let _ = std::fs::remove_file(&json_path);
+    result
+}
+
+/// Creates a temp WAV file no other transcription can be using, even one running in
+/// parallel in the same plugin process, and returns its path without the extension.
+fn create_temp_wav() -> std::io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let base = std::env::temp_dir().join(format!(
+            "nu_plugin_audio_hook-transcribe-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(base.with_extension("wav"))
+        {
+            Ok(file) => return Ok((base, file)),
+            // Left behind by an earlier process with the same pid.
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Resolves `--model` to a model file: an existing path (relative to the current
+/// directory) is used as-is, otherwise the name is looked up as `ggml-<name>.bin` in
+/// `$env.WHISPER_MODELS_DIR`.
+fn resolve_model(engine: &EngineInterface, call: &EvaluatedCall) -> Result<PathBuf, LabeledError> {
+    let model: Spanned<String> = call.get_flag("model")?.unwrap_or(Spanned {
+        item: "base".to_string(),
+        span: call.head,
+    });
+    let cwd = PathBuf::from(engine.get_current_dir()?);
+    let as_path = cwd.join(&model.item);
+    if as_path.is_file() {
+        return Ok(as_path);
+    }
+
+    let dir = engine
+        .get_env_var("WHISPER_MODELS_DIR")?
+        .and_then(|v| v.coerce_into_string().ok())
+        .map(|dir| cwd.join(dir));
+    let file_name = format!("ggml-{}.bin", model.item);
+    match dir.map(|d| d.join(&file_name)) {
+        Some(path) if path.is_file() => Ok(path),
+        Some(path) => Err(
+            LabeledError::new(format!("model file {} not found", path.display()))
+                .with_label("unknown model", model.span)
+                .with_help("download it with whisper.cpp's models/download-ggml-model.sh"),
+        ),
+        None => Err(LabeledError::new(format!("cannot locate {file_name}"))
+            .with_label("unknown model", model.span)
+            .with_help("pass a model file path or set $env.WHISPER_MODELS_DIR")),
+    }
+}
+
+fn run_whisper(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    model: &Path,
+    wav_path: &Path,
+    output_base: &Path,
+) -> Result<(), LabeledError> {
+    let span = call.head;
+    let configured = engine
+        .get_env_var("WHISPER_CPP")?
+        .and_then(|v| v.coerce_into_string().ok());
+    let candidates: Vec<String> = match configured {
+        Some(bin) => vec![bin],
+        None => WHISPER_BINARIES.iter().map(|b| b.to_string()).collect(),
+    };
+    let env = engine.get_env_vars()?;
+
+    for bin in &candidates {
+        let mut command = Command::new(bin);
+        command
+            .envs(
+                env.iter().filter_map(|(name, value)| {
+                    Some((name, value.coerce_str().ok()?.into_owned()))
+                }),
+            )
+            .arg("--model")
+            .arg(model)
+            .arg("--file")
+            .arg(wav_path)
+            .arg("--output-json")
+            .arg("--output-file")
+            .arg(output_base)
+            .arg("--no-prints");
+        if let Some(language) = call.get_flag::<String>("language")? {
+            command.arg("--language").arg(language);
+        }
+
+        if run_tool(&mut command, span)?.is_some() {
+            return Ok(());
+        }
+    }
+
+    Err(not_installed(
+        "whisper.cpp",
+        format!("none of {} found on PATH", candidates.join(", ")),
+        "install whisper.cpp or point $env.WHISPER_CPP at its CLI binary",
+        span,
+    ))
+}
+
+/// Converts whisper.cpp's JSON output (`transcription[].offsets` in milliseconds) to rows.
+fn read_segments(json_path: &Path, span: Span) -> Result<Value, LabeledError> {
+    let text = std::fs::read_to_string(json_path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("whisper.cpp produced no output", span)
+    })?;
+    let json: serde_json::Value = serde_json::from_str(&text).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error parsing whisper.cpp output", span)
+    })?;
+
+    let ms = |v: &serde_json::Value| Value::duration(v.as_i64().unwrap_or(0) * 1_000_000, span);
+    let rows = json["transcription"]
+        .as_array()
+        .map(|segments| {
+            segments
+                .iter()
+                .map(|segment| {
+                    Value::record(
+                        record! {
+                            "start" => ms(&segment["offsets"]["from"]),
+                            "end" => ms(&segment["offsets"]["to"]),
+                            "text" => Value::string(segment["text"].as_str().unwrap_or_default().trim(), span),
+                        },
+                        span,
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Value::list(rows, span))
+}
@@ -0,0 +1,817 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Record, Signature, Span, SyntaxShape, Type, Value,
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    sample_format::locate_first_mpeg_frame,
+    utils::{duration_value, load_file_path, splice_file},
+    Sound,
+};
+
+/// Xing header flags: which of the optional fields follow it.
+const FRAMES_FLAG: u32 = 1;
+const BYTES_FLAG: u32 = 2;
+const TOC_FLAG: u32 = 4;
+const QUALITY_FLAG: u32 = 8;
+
+/// Length of the LAME extension that follows the Xing fields.
+const LAME_TAG_LEN: usize = 36;
+
+/// Bit rates in kbit/s by bit rate index, for MPEG-1 layers I, II and III and MPEG-2/2.5
+/// layer I and layers II/III.
+const BITRATES: [[u32; 15]; 5] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+/// Nushell command `sound meta xing` — checks, rebuilds or strips an MP3's Xing/Info
+/// header.
+///
+/// Players take an MP3's duration and seek points from the frame count, byte count and
+/// table of contents in this header. Files that were cut, joined or repaired without
+/// updating it report the wrong duration and seek to the wrong place. The command counts
+/// the frames actually in the stream and compares; `--rebuild` writes the counted values
+/// and a fresh table of contents into the existing header, `--strip` removes the header
+/// frame, along with the LAME tag it carries.
+pub struct SoundMetaXingCmd;
+
+impl SimplePluginCommand for SoundMetaXingCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound meta xing"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::new("sound meta xing")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .required("File", SyntaxShape::Filepath, "MP3 file to check")
+            .switch(
+                "rebuild",
+                "rewrite the frame count, byte count and seek table from the actual stream",
+                Some('r'),
+            )
+            .switch(
+                "strip",
+                "remove the Xing/Info frame, including its LAME tag",
+                Some('s'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "check whether the header matches the stream",
+                example: "sound meta xing song.mp3 | select consistent header.duration stream.duration",
+                result: None,
+            },
+            Example {
+                description: "fix the duration of MP3s that were cut without updating the header",
+                example: "ls *.mp3 | where { sound meta xing $in.name | not $in.consistent } | each { sound meta xing $in.name --rebuild }",
+                result: None,
+            },
+            Example {
+                description: "show the encoder and preset LAME recorded",
+                example: "sound meta xing song.mp3 | get header.lame",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "check an MP3's Xing/Info and LAME header against the stream, and rebuild or strip it"
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let (file_span, path) = load_file_path(engine, call)?;
+        let rebuild = call.has_flag("rebuild")?;
+        let strip = call.has_flag("strip")?;
+        if rebuild && strip {
+            return Err(LabeledError::new("conflicting flags")
+                .with_label("--rebuild and --strip exclude each other", call.head));
+        }
+        xing(&path, rebuild, strip, file_span)
+    }
+}
+
+fn xing(path: &Path, rebuild: bool, strip: bool, span: Span) -> Result<Value, LabeledError> {
+    let io_error = |e: std::io::Error| {
+        LabeledError::new(e.to_string())
+            .with_label(format!("error reading {}", path.display()), span)
+    };
+    let mut file = File::open(path).map_err(io_error)?;
+    let header = read_xing(&mut file).ok_or_else(|| {
+        LabeledError::new("no Xing/Info header")
+            .with_label("not an MP3, or its first frame carries no Xing/Info header", span)
+            .with_help("players then estimate the duration from the bit rate, which is exact for CBR files")
+    })?;
+    let stream = scan_stream(&mut file, header.frame_offset).map_err(io_error)?;
+    drop(file);
+
+    let consistent = header
+        .frames()
+        .is_none_or(|frames| frames as u64 == stream.audio_frames())
+        && header
+            .bytes()
+            .is_none_or(|bytes| bytes as u64 == stream.bytes);
+    let mut record = record! {
+        "path" => Value::string(path.to_string_lossy(), span),
+        "header" => header.to_value(span),
+        "stream" => stream.to_value(span),
+        "consistent" => Value::bool(consistent, span),
+    };
+
+    let action = if rebuild {
+        let mut frame = header.frame.clone();
+        rebuild_header(&mut frame, &header, &stream);
+        let mut file = OpenOptions::new().write(true).open(path).map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("error opening file for writing", span)
+        })?;
+        file.seek(SeekFrom::Start(header.frame_offset))
+            .and_then(|_| file.write_all(&frame))
+            .map_err(|e| {
+                LabeledError::new(e.to_string()).with_label("error writing header", span)
+            })?;
+        "rebuilt"
+    } else if strip {
+        replace_range(
+            path,
+            header.frame_offset,
+            header.frame.len() as u64,
+            &[],
+            span,
+        )?;
+        "stripped"
+    } else {
+        "none"
+    };
+    record.push("action", Value::string(action, span));
+    Ok(Value::record(record, span))
+}
+
+/// The `Xing`/`Info` header of an MP3's first frame.
+pub struct XingHeader {
+    /// Where the frame holding the header starts in the file.
+    pub frame_offset: u64,
+    /// The whole frame, from its frame header on.
+    frame: Vec<u8>,
+    /// Offset of `Xing`/`Info` in the frame.
+    xing_at: usize,
+    flags: u32,
+}
+
+/// Reads the `Xing`/`Info` header of the first MPEG frame, if it has one. The reader is
+/// left at an unspecified position.
+pub fn read_xing<R: Read + Seek>(reader: &mut R) -> Option<XingHeader> {
+    let (frame_offset, mut frame, xing_at) = locate_first_mpeg_frame(reader)?;
+    let info = FrameInfo::parse(frame.get(..4)?)?;
+    frame.truncate(info.len);
+    let header = frame.get(xing_at..xing_at + 8)?;
+    if &header[..4] != b"Xing" && &header[..4] != b"Info" {
+        return None;
+    }
+    let flags = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    Some(XingHeader {
+        frame_offset,
+        frame,
+        xing_at,
+        flags,
+    })
+}
+
+/// The LAME tag of an MP3 as a record: encoder, preset and gain fields.
+pub fn lame_value<R: Read + Seek>(reader: &mut R, span: Span) -> Option<Value> {
+    let header = read_xing(reader)?;
+    Some(Value::record(header.lame()?.to_record(span), span))
+}
+
+impl XingHeader {
+    /// `Xing` for VBR files, `Info` for CBR.
+    fn kind(&self) -> String {
+        String::from_utf8_lossy(&self.frame[self.xing_at..self.xing_at + 4]).into_owned()
+    }
+
+    /// Offset in the frame of the optional field `flag`, when the header has it.
+    fn field(&self, flag: u32) -> Option<usize> {
+        if self.flags & flag == 0 {
+            return None;
+        }
+        let before = [(FRAMES_FLAG, 4), (BYTES_FLAG, 4), (TOC_FLAG, 100)]
+            .iter()
+            .filter(|(earlier, _)| *earlier < flag && self.flags & earlier != 0)
+            .map(|(_, len)| len)
+            .sum::<usize>();
+        Some(self.xing_at + 8 + before)
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes = self.frame.get(at..at + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Audio frames in the stream, not counting the header's own frame.
+    fn frames(&self) -> Option<u32> {
+        self.u32_at(self.field(FRAMES_FLAG)?)
+    }
+
+    /// Bytes of the stream, including the header's frame.
+    fn bytes(&self) -> Option<u32> {
+        self.u32_at(self.field(BYTES_FLAG)?)
+    }
+
+    /// Encoder quality, 0 (best) to 100.
+    fn quality(&self) -> Option<u32> {
+        self.u32_at(self.field(QUALITY_FLAG)?)
+    }
+
+    /// Offset of the LAME extension (written by LAME and by FFmpeg as `Lavf`/`Lavc`).
+    fn lame_at(&self) -> Option<usize> {
+        let at = self.xing_at
+            + 8
+            + [
+                (FRAMES_FLAG, 4),
+                (BYTES_FLAG, 4),
+                (TOC_FLAG, 100),
+                (QUALITY_FLAG, 4),
+            ]
+            .iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, len)| len)
+            .sum::<usize>();
+        let tag = self.frame.get(at..at + LAME_TAG_LEN)?;
+        matches!(&tag[..4], b"LAME" | b"Lavf" | b"Lavc").then_some(at)
+    }
+
+    /// The LAME extension, if the header has one.
+    pub fn lame(&self) -> Option<LameTag<'_>> {
+        let at = self.lame_at()?;
+        Some(LameTag(&self.frame[at..at + LAME_TAG_LEN]))
+    }
+
+    fn to_value(&self, span: Span) -> Value {
+        let info = FrameInfo::parse(&self.frame[..4]);
+        let optional =
+            |value: Option<u32>| value.map_or(Value::nothing(span), |v| Value::int(v as i64, span));
+        let duration = self
+            .frames()
+            .zip(info)
+            .map_or(Value::nothing(span), |(frames, info)| {
+                duration_value(info.duration(frames as u64), span)
+            });
+        Value::record(
+            record! {
+                "kind" => Value::string(self.kind(), span),
+                "frames" => optional(self.frames()),
+                "bytes" => optional(self.bytes()),
+                "toc" => Value::bool(self.field(TOC_FLAG).is_some(), span),
+                "quality" => optional(self.quality()),
+                "duration" => duration,
+                "lame" => self.lame().map_or(Value::nothing(span), |lame| Value::record(lame.to_record(span), span)),
+            },
+            span,
+        )
+    }
+}
+
+/// The 36-byte LAME extension of a Xing/Info header.
+pub struct LameTag<'a>(&'a [u8]);
+
+impl LameTag<'_> {
+    /// Encoder name and version, e.g. `LAME3.100`.
+    fn encoder(&self) -> String {
+        String::from_utf8_lossy(&self.0[..9])
+            .trim_end_matches(['\0', ' '])
+            .to_string()
+    }
+
+    fn vbr_method(&self) -> &'static str {
+        match self.0[9] & 0x0F {
+            1 => "cbr",
+            2 => "abr",
+            3 => "vbr-old",
+            4 => "vbr-mtrh",
+            5 => "vbr-mt",
+            8 => "cbr-2pass",
+            9 => "abr-2pass",
+            _ => "unknown",
+        }
+    }
+
+    /// The `--preset` or `-V` setting the file was encoded with.
+    fn preset(&self) -> Option<String> {
+        let preset = u16::from_be_bytes([self.0[26], self.0[27]]) & 0x07FF;
+        Some(match preset {
+            8..=320 => format!("abr {preset}"),
+            410..=500 if preset.is_multiple_of(10) => format!("V{}", (500 - preset) / 10),
+            1000 => "r3mix".to_string(),
+            1001 => "standard".to_string(),
+            1002 => "extreme".to_string(),
+            1003 => "insane".to_string(),
+            1004 => "standard/fast".to_string(),
+            1005 => "extreme/fast".to_string(),
+            1006 => "medium".to_string(),
+            1007 => "medium/fast".to_string(),
+            _ => return None,
+        })
+    }
+
+    /// A ReplayGain field in dB, `None` when unset.
+    fn replay_gain(&self, at: usize) -> Option<f64> {
+        let field = u16::from_be_bytes([self.0[at], self.0[at + 1]]);
+        if field >> 13 == 0 {
+            return None;
+        }
+        let gain = (field & 0x01FF) as f64 / 10.0;
+        Some(if field & 0x0200 != 0 { -gain } else { gain })
+    }
+
+    /// Raw encoder delay and padding, in samples, as LAME counts them.
+    pub fn delay_and_padding(&self) -> (u32, u32) {
+        let trim = u32::from_be_bytes([0, self.0[21], self.0[22], self.0[23]]);
+        (trim >> 12, trim & 0xFFF)
+    }
+
+    pub fn to_record(&self, span: Span) -> Record {
+        let gain = |gain: Option<f64>| gain.map_or(Value::nothing(span), |g| Value::float(g, span));
+        // Peak amplitude as a fixed-point number with 23 fraction bits; 0 when not measured.
+        let peak = u32::from_be_bytes([self.0[11], self.0[12], self.0[13], self.0[14]]);
+        let lowpass = self.0[10] as i64 * 100;
+        let bitrate = self.0[20];
+        record! {
+            "encoder" => Value::string(self.encoder(), span),
+            "vbr_method" => Value::string(self.vbr_method(), span),
+            "preset" => self.preset().map_or(Value::nothing(span), |p| Value::string(p, span)),
+            "bitrate" => if bitrate == 0 { Value::nothing(span) } else { Value::int(bitrate as i64, span) },
+            "lowpass_hz" => if lowpass == 0 { Value::nothing(span) } else { Value::int(lowpass, span) },
+            "peak" => if peak == 0 { Value::nothing(span) } else { Value::float(peak as f64 / (1 << 23) as f64, span) },
+            "track_gain_db" => gain(self.replay_gain(15)),
+            "album_gain_db" => gain(self.replay_gain(17)),
+            "mp3_gain_db" => Value::float(self.0[25] as i8 as f64 * 1.5, span),
+        }
+    }
+}
+
+/// What a frame header says about its frame.
+#[derive(Clone, Copy, PartialEq)]
+struct FrameInfo {
+    len: usize,
+    sample_rate: u32,
+    samples: u32,
+}
+
+impl FrameInfo {
+    /// Parses a 4-byte MPEG audio frame header; `None` when it is not one.
+    fn parse(header: &[u8]) -> Option<FrameInfo> {
+        let &[sync, b1, b2, _] = header else {
+            return None;
+        };
+        if sync != 0xFF || b1 & 0xE0 != 0xE0 {
+            return None;
+        }
+        let version = (b1 >> 3) & 3; // 3: MPEG-1, 2: MPEG-2, 0: MPEG-2.5
+        let layer = (b1 >> 1) & 3; // 3: layer I, 2: layer II, 1: layer III
+        let bitrate_index = (b2 >> 4) as usize;
+        let rate_index = ((b2 >> 2) & 3) as usize;
+        if version == 1
+            || layer == 0
+            || bitrate_index == 0
+            || bitrate_index == 15
+            || rate_index == 3
+        {
+            return None;
+        }
+        let mpeg1 = version == 3;
+        let table = match (mpeg1, layer) {
+            (true, 3) => 0,
+            (true, 2) => 1,
+            (true, _) => 2,
+            (false, 3) => 3,
+            (false, _) => 4,
+        };
+        let bitrate = BITRATES[table][bitrate_index] * 1000;
+        // MPEG-2 halves the MPEG-1 rates, MPEG-2.5 quarters them.
+        let sample_rate = [44_100, 48_000, 32_000][rate_index] >> [2, 0, 1, 0][version as usize];
+        let padding = ((b2 >> 1) & 1) as u32;
+        let (len, samples) = match layer {
+            3 => ((12 * bitrate / sample_rate + padding) * 4, 384),
+            1 if !mpeg1 => (72 * bitrate / sample_rate + padding, 576),
+            _ => (144 * bitrate / sample_rate + padding, 1152),
+        };
+        Some(FrameInfo {
+            len: len as usize,
+            sample_rate,
+            samples,
+        })
+    }
+
+    fn duration(&self, frames: u64) -> Duration {
+        Duration::from_secs_f64(frames as f64 * self.samples as f64 / self.sample_rate as f64)
+    }
+}
+
+/// The frames actually in the stream, counted from the header's frame on.
+struct Stream {
+    /// Offset of each frame from the first, the header's frame included.
+    offsets: Vec<u64>,
+    bytes: u64,
+    info: FrameInfo,
+    /// Header of the first frame.
+    first: [u8; 4],
+    /// Whether the bit rate changes from frame to frame.
+    vbr: bool,
+}
+
+impl Stream {
+    /// The stream with a header frame of `len` bytes put in front, for a stream
+    /// scanned from its first audio frame.
+    fn behind(mut self, len: usize) -> Stream {
+        for offset in &mut self.offsets {
+            *offset += len as u64;
+        }
+        self.offsets.insert(0, 0);
+        self.bytes += len as u64;
+        self
+    }
+
+    /// Audio frames, not counting the header's frame.
+    fn audio_frames(&self) -> u64 {
+        self.offsets.len().saturating_sub(1) as u64
+    }
+
+    fn to_value(&self, span: Span) -> Value {
+        Value::record(
+            record! {
+                "frames" => Value::int(self.audio_frames() as i64, span),
+                "bytes" => Value::int(self.bytes as i64, span),
+                "duration" => duration_value(self.info.duration(self.audio_frames()), span),
+            },
+            span,
+        )
+    }
+}
+
+/// Walks the frames from `start` until something that is not a frame of the same
+/// stream, such as an ID3v1 or APE tag at the end.
+fn scan_stream(file: &mut File, start: u64) -> std::io::Result<Stream> {
+    let available = file.metadata()?.len().saturating_sub(start);
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut offsets = Vec::new();
+    let mut first: Option<(FrameInfo, [u8; 4])> = None;
+    let mut vbr = false;
+    let mut position = 0u64;
+    let mut header = [0u8; 4];
+    loop {
+        if reader.read_exact(&mut header).is_err() {
+            break;
+        }
+        let Some(info) = FrameInfo::parse(&header) else {
+            break;
+        };
+        if let Some((first, first_header)) = first {
+            if first.sample_rate != info.sample_rate || first.samples != info.samples {
+                break;
+            }
+            vbr |= first_header[2] >> 4 != header[2] >> 4;
+        }
+        first.get_or_insert((info, header));
+        offsets.push(position);
+        position += info.len as u64;
+        reader.seek_relative(info.len as i64 - 4)?;
+    }
+    let (info, first) = first.ok_or_else(|| std::io::Error::other("no MPEG frames"))?;
+    Ok(Stream {
+        offsets,
+        // The last frame may be cut short by a truncated download.
+        bytes: position.min(available),
+        info,
+        first,
+        vbr,
+    })
+}
+
+/// Gives an MP3 a Xing/Info header that matches its stream, for `sound repair
+/// --rebuild-vbr-header`.
+///
+/// A header with a frame count, byte count and seek table is updated in place. One
+/// missing any of them, or a Fraunhofer `VBRI` header, is replaced by a new frame that
+/// keeps the LAME tag, if any; a file without a header gets a new frame in front of the
+/// audio. The new frame is an empty layer III frame, which decoders play as silence or
+/// skip. With `dry_run` the file is left alone.
+pub fn rebuild_vbr_header(path: &Path, dry_run: bool, span: Span) -> Result<Record, LabeledError> {
+    let io_error = |e: std::io::Error| {
+        LabeledError::new(e.to_string())
+            .with_label(format!("error reading {}", path.display()), span)
+    };
+    let not_mp3 = || LabeledError::new("not an MP3").with_label("no MPEG audio frame found", span);
+    let mut file = File::open(path).map_err(io_error)?;
+    let (offset, data, _) = locate_first_mpeg_frame(&mut file).ok_or_else(not_mp3)?;
+    let first = FrameInfo::parse(&data[..4]).ok_or_else(not_mp3)?;
+    if (data[1] >> 1) & 3 != 1 {
+        return Err(LabeledError::new("not a layer III stream").with_label(
+            "Xing headers only exist in MP3 (MPEG layer III) files",
+            span,
+        ));
+    }
+
+    let complete = FRAMES_FLAG | BYTES_FLAG | TOC_FLAG;
+    let existing = read_xing(&mut file);
+    // VBRI sits 32 bytes after the frame header, whatever the side information.
+    let vbri = data.get(36..40) == Some(b"VBRI".as_slice());
+    let (replaced, action) = match &existing {
+        Some(header) if header.flags & complete == complete => (header.frame.len(), "updated"),
+        Some(header) => (header.frame.len(), "replaced"),
+        None if vbri => (first.len, "replaced-vbri"),
+        None => (0, "inserted"),
+    };
+    let stream = scan_stream(&mut file, offset + replaced as u64).map_err(io_error)?;
+    drop(file);
+    let mut header = match existing {
+        Some(header) if action == "updated" => header,
+        existing => {
+            let lame = existing
+                .as_ref()
+                .and_then(|header| header.lame())
+                .map(|lame| lame.0.to_vec());
+            new_header_frame(stream.first, lame.as_deref(), offset).ok_or_else(not_mp3)?
+        }
+    };
+    let stream = stream.behind(header.frame.len());
+
+    let kind: &[u8; 4] = if stream.vbr { b"Xing" } else { b"Info" };
+    header.frame[header.xing_at..header.xing_at + 4].copy_from_slice(kind);
+    let mut frame = header.frame.clone();
+    rebuild_header(&mut frame, &header, &stream);
+    if !dry_run {
+        replace_range(path, offset, replaced as u64, &frame, span)?;
+    }
+
+    Ok(record! {
+        "action" => Value::string(action, span),
+        "kind" => Value::string(String::from_utf8_lossy(kind), span),
+        "frames" => Value::int(stream.audio_frames() as i64, span),
+        "bytes" => Value::int(stream.bytes as i64, span),
+        "duration" => duration_value(stream.info.duration(stream.audio_frames()), span),
+    })
+}
+
+/// An empty Xing header frame for a stream whose first audio frame has the header
+/// `template`: the same MPEG version, sample rate and channel mode, at the lowest bit
+/// rate that holds a frame count, byte count, seek table and `lame`. The counts are
+/// left for [`rebuild_header`] to fill in.
+fn new_header_frame(
+    template: [u8; 4],
+    lame: Option<&[u8]>,
+    frame_offset: u64,
+) -> Option<XingHeader> {
+    let mpeg1 = template[1] & 0x18 == 0x18;
+    let mono = template[3] >> 6 == 3;
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    let xing_at = 4 + side_info;
+    let flags = FRAMES_FLAG | BYTES_FLAG | TOC_FLAG;
+    let lame_at = xing_at + 8 + 4 + 4 + 100;
+    let needed = lame_at + lame.map_or(0, <[u8]>::len);
+    // No CRC, no padding, no private bit.
+    let header = (1..15u8)
+        .map(|index| {
+            [
+                0xFF,
+                template[1] | 1,
+                (index << 4) | (template[2] & 0x0C),
+                template[3],
+            ]
+        })
+        .find(|header| FrameInfo::parse(header).is_some_and(|info| info.len >= needed))?;
+    let mut frame = vec![0u8; FrameInfo::parse(&header)?.len];
+    frame[..4].copy_from_slice(&header);
+    frame[xing_at..xing_at + 4].copy_from_slice(b"Xing");
+    frame[xing_at + 4..xing_at + 8].copy_from_slice(&flags.to_be_bytes());
+    if let Some(lame) = lame {
+        frame[lame_at..lame_at + lame.len()].copy_from_slice(lame);
+    }
+    Some(XingHeader {
+        frame_offset,
+        frame,
+        xing_at,
+        flags,
+    })
+}
+
+/// Writes the counted frames and bytes and a new table of contents into the header,
+/// for the fields it has, then updates the LAME tag's music length and CRC.
+fn rebuild_header(frame: &mut [u8], header: &XingHeader, stream: &Stream) {
+    let put = |frame: &mut [u8], at: usize, value: u32| {
+        frame[at..at + 4].copy_from_slice(&value.to_be_bytes())
+    };
+    let bytes = stream.bytes.min(u32::MAX as u64) as u32;
+    if let Some(at) = header.field(FRAMES_FLAG) {
+        put(frame, at, stream.audio_frames().min(u32::MAX as u64) as u32);
+    }
+    if let Some(at) = header.field(BYTES_FLAG) {
+        put(frame, at, bytes);
+    }
+    if let Some(at) = header.field(TOC_FLAG) {
+        // Entry i is where i% of the playing time starts, as a fraction of the stream
+        // in 256ths.
+        let frames = stream.audio_frames();
+        for i in 0..100 {
+            let offset = stream
+                .offsets
+                .get(1 + (i as u64 * frames / 100) as usize)
+                .copied()
+                .unwrap_or(0);
+            frame[at + i] = (offset * 256 / stream.bytes.max(1)).min(255) as u8;
+        }
+    }
+    if let Some(at) = header.lame_at() {
+        put(frame, at + 28, bytes);
+        let crc = crc16(&frame[..at + 34]);
+        frame[at + 34..at + 36].copy_from_slice(&crc.to_be_bytes());
+    }
+}
+
+/// CRC-16 as LAME computes it for its tag (polynomial 0x8005, reflected).
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+/// Replaces `len` bytes at `offset` with `replacement`.
+fn replace_range(
+    path: &Path,
+    offset: u64,
+    len: u64,
+    replacement: &[u8],
+    span: Span,
+) -> Result<(), LabeledError> {
+    splice_file(path, &[(offset, offset + len, replacement.to_vec())]).map_err(|e| {
+        LabeledError::new(e.to_string())
+            .with_label(format!("error rewriting {}", path.display()), span)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// MPEG-1 layer III, 128 kbit/s, 44.1 kHz, joint stereo, no CRC or padding.
+    const AUDIO: [u8; 4] = [0xFF, 0xFB, 0x90, 0x44];
+
+    fn frame_info(header: [u8; 4]) -> Option<(usize, u32, u32)> {
+        FrameInfo::parse(&header).map(|info| (info.len, info.sample_rate, info.samples))
+    }
+
+    #[test]
+    fn frame_lengths_follow_version_and_layer() {
+        // MPEG-1 layer III, 128 kbit/s at 44.1 kHz, without and with padding.
+        assert_eq!(frame_info(AUDIO), Some((417, 44_100, 1152)));
+        assert_eq!(
+            frame_info([0xFF, 0xFB, 0x92, 0x44]),
+            Some((418, 44_100, 1152))
+        );
+        // MPEG-1 layer II, 192 kbit/s at 48 kHz.
+        assert_eq!(
+            frame_info([0xFF, 0xFD, 0xA4, 0x00]),
+            Some((576, 48_000, 1152))
+        );
+        // MPEG-1 layer I, 384 kbit/s at 44.1 kHz: 4-byte slots.
+        assert_eq!(
+            frame_info([0xFF, 0xFF, 0xC0, 0x00]),
+            Some((416, 44_100, 384))
+        );
+        // MPEG-2 layer III, 64 kbit/s at 22.05 kHz, padded.
+        assert_eq!(
+            frame_info([0xFF, 0xF3, 0x82, 0xC4]),
+            Some((209, 22_050, 576))
+        );
+        // MPEG-2.5 layer III, 32 kbit/s at 11.025 kHz.
+        assert_eq!(
+            frame_info([0xFF, 0xE3, 0x40, 0x00]),
+            Some((208, 11_025, 576))
+        );
+    }
+
+    #[test]
+    fn invalid_frame_headers_are_rejected() {
+        // Reserved version, reserved layer, free and bad bit rates, reserved sample rate.
+        for header in [
+            [0xFF, 0xEB, 0x90, 0x00],
+            [0xFF, 0xF9, 0x90, 0x00],
+            [0xFF, 0xFB, 0x00, 0x00],
+            [0xFF, 0xFB, 0xF0, 0x00],
+            [0xFF, 0xFB, 0x9C, 0x00],
+            [0xFE, 0xFB, 0x90, 0x00],
+        ] {
+            assert!(FrameInfo::parse(&header).is_none(), "{header:02X?}");
+        }
+        assert!(FrameInfo::parse(&AUDIO[..3]).is_none());
+    }
+
+    #[test]
+    fn crc16_is_the_lame_tag_crc() {
+        // LAME's tag CRC is CRC-16/ARC, whose catalogued check value this is.
+        assert_eq!(crc16(b"123456789"), 0xBB3D);
+        assert_eq!(crc16(&[]), 0);
+    }
+
+    /// `frames` audio frames of [`AUDIO`], as a scan would find them.
+    fn stream(frames: u64) -> Stream {
+        let info = FrameInfo::parse(&AUDIO).unwrap();
+        Stream {
+            offsets: (0..frames).map(|i| i * info.len as u64).collect(),
+            bytes: frames * info.len as u64,
+            info,
+            first: AUDIO,
+            vbr: false,
+        }
+    }
+
+    #[test]
+    fn rebuilt_header_matches_stream() {
+        let mut lame = [0u8; LAME_TAG_LEN];
+        lame[..9].copy_from_slice(b"LAME3.100");
+        let header = new_header_frame(AUDIO, Some(&lame), 0).unwrap();
+        let stream = stream(200).behind(header.frame.len());
+        let mut frame = header.frame.clone();
+        rebuild_header(&mut frame, &header, &stream);
+
+        // Read it back the way `sound meta xing` does, from a file of the frames.
+        let mut file = frame.clone();
+        for _ in 0..200 {
+            let mut audio = vec![0u8; 417];
+            audio[..4].copy_from_slice(&AUDIO);
+            file.extend(audio);
+        }
+        let rebuilt = read_xing(&mut Cursor::new(file)).unwrap();
+        assert_eq!(rebuilt.frames(), Some(200));
+        assert_eq!(rebuilt.bytes(), Some(stream.bytes as u32));
+        assert_eq!(rebuilt.bytes(), Some(frame.len() as u32 + 200 * 417));
+
+        let toc = rebuilt.field(TOC_FLAG).unwrap();
+        for (i, &entry) in frame[toc..toc + 100].iter().enumerate() {
+            let offset = stream.offsets[1 + i * 2];
+            assert_eq!(entry as u64, offset * 256 / stream.bytes);
+        }
+
+        let at = rebuilt.lame_at().unwrap();
+        assert_eq!(rebuilt.lame().unwrap().encoder(), "LAME3.100");
+        assert_eq!(rebuilt.u32_at(at + 28), rebuilt.bytes());
+        let crc = u16::from_be_bytes([frame[at + 34], frame[at + 35]]);
+        assert_eq!(crc, crc16(&frame[..at + 34]));
+        assert_ne!(crc, 0);
+    }
+
+    #[test]
+    fn rebuild_only_writes_fields_the_header_has() {
+        let mut header = new_header_frame(AUDIO, None, 0).unwrap();
+        header.flags = BYTES_FLAG;
+        let before = header.frame.clone();
+        let stream = stream(10).behind(header.frame.len());
+        let mut frame = header.frame.clone();
+        rebuild_header(&mut frame, &header, &stream);
+        let at = header.field(BYTES_FLAG).unwrap();
+        assert_eq!(at, header.xing_at + 8);
+        assert_eq!(frame[at..at + 4], (stream.bytes as u32).to_be_bytes());
+        assert_eq!(frame[..at], before[..at]);
+        assert_eq!(frame[at + 4..], before[at + 4..]);
+    }
+}
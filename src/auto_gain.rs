@@ -0,0 +1,154 @@
+use lofty::{file::TaggedFileExt, tag::ItemKey};
+use rodio::{Decoder, Source};
+use std::{fs::File, path::Path, time::Duration};
+
+use crate::{dsp::Biquad, simd};
+
+/// Loudness `--auto-gain` brings every track to: the ReplayGain 2.0 reference level.
+const TARGET_LUFS: f64 = -18.0;
+
+/// Excerpts decoded, spread evenly over the track, to estimate its loudness.
+const EXCERPTS: u32 = 12;
+
+/// Length of each excerpt.
+const EXCERPT_LENGTH: Duration = Duration::from_secs(2);
+
+/// Gating block of the BS.1770 loudness measurement.
+const BLOCK: Duration = Duration::from_millis(400);
+
+/// Limits on the applied gain, so near-silent tracks are not blown up.
+const MIN_GAIN_DB: f64 = -24.0;
+const MAX_GAIN_DB: f64 = 12.0;
+
+/// Linear gain that brings the track at `path` to [`TARGET_LUFS`].
+///
+/// Uses the file's ReplayGain track gain and peak when tagged; otherwise measures the
+/// integrated loudness (BS.1770, K-weighted and gated) of a dozen short excerpts, which
+/// takes a fraction of a second. The gain is lowered where needed to keep the peak
+/// below full scale.
+pub fn track_gain(path: &Path, duration: Option<Duration>) -> Option<f32> {
+    let (gain_db, peak) = tagged_gain(path).or_else(|| measure(path, duration))?;
+    Some(limited_gain(gain_db, peak))
+}
+
+/// Linear gain that brings a short decoded clip, such as a notification sound, to
+/// [`TARGET_LUFS`]. The whole clip is measured, in overlapping blocks as BS.1770
+/// specifies; a clip shorter than one block is measured as a single block. `None` for
+/// silence.
+pub fn clip_gain(samples: &[f32], channels: u16, rate: u32) -> Option<f32> {
+    let channels = channels.max(1) as usize;
+    let mut weighted = samples.to_vec();
+    weighted.truncate(samples.len() - samples.len() % channels);
+    k_weight(&mut weighted, channels, rate);
+    let frames = weighted.len() / channels;
+    let block_frames = ((BLOCK.as_secs_f64() * rate as f64) as usize).min(frames);
+    if block_frames == 0 {
+        return None;
+    }
+    // Blocks overlap by 75%.
+    let step = (block_frames / 4).max(1);
+    let blocks: Vec<f64> = (0..=frames - block_frames)
+        .step_by(step)
+        .map(|frame| {
+            let block = &weighted[frame * channels..(frame + block_frames) * channels];
+            simd::sum_squares(block) / block_frames as f64
+        })
+        .collect();
+    let gain_db = gated_gain(blocks)?;
+    Some(limited_gain(gain_db, simd::peak(samples) as f64))
+}
+
+/// `gain_db` within [`MIN_GAIN_DB`]..[`MAX_GAIN_DB`] as a linear factor, lowered where
+/// needed to keep a `peak` (linear, 0 when unknown) below full scale.
+fn limited_gain(gain_db: f64, peak: f64) -> f32 {
+    let gain_db = gain_db.clamp(MIN_GAIN_DB, MAX_GAIN_DB);
+    let gain = 10f64.powf(gain_db / 20.0);
+    let gain = if peak > 0.0 {
+        gain.min(1.0 / peak)
+    } else {
+        gain
+    };
+    gain as f32
+}
+
+/// ReplayGain track gain (dB) and peak (linear) from the file's tags.
+fn tagged_gain(path: &Path) -> Option<(f64, f64)> {
+    let tagged_file = lofty::read_from_path(path).ok()?;
+    let tag = tagged_file.primary_tag()?;
+    // Values are written as "-6.20 dB"; some taggers leave out the unit.
+    let number = |key: ItemKey| -> Option<f64> {
+        let value = tag.get_string(key)?.trim();
+        let value = value
+            .strip_suffix("dB")
+            .or_else(|| value.strip_suffix("DB"))
+            .unwrap_or(value);
+        value.trim().parse().ok()
+    };
+    let gain = number(ItemKey::ReplayGainTrackGain)?;
+    Some((gain, number(ItemKey::ReplayGainTrackPeak).unwrap_or(0.0)))
+}
+
+/// Estimated gain to [`TARGET_LUFS`] and sample peak, from excerpts of the track.
+fn measure(path: &Path, duration: Option<Duration>) -> Option<(f64, f64)> {
+    let mut decoder = Decoder::try_from(File::open(path).ok()?).ok()?;
+    let channels = decoder.channels().max(1) as usize;
+    let rate = decoder.sample_rate();
+    let excerpt_samples = (EXCERPT_LENGTH.as_secs_f64() * rate as f64) as usize * channels;
+    let block_samples = (BLOCK.as_secs_f64() * rate as f64) as usize * channels;
+
+    let mut blocks = Vec::new();
+    let mut peak = 0.0f64;
+    let seekable = duration.is_some_and(|d| d > EXCERPT_LENGTH * EXCERPTS);
+    for excerpt in 0..EXCERPTS {
+        if seekable {
+            let start = duration? * (2 * excerpt + 1) / (2 * EXCERPTS) - EXCERPT_LENGTH / 2;
+            if decoder.try_seek(start).is_err() {
+                break;
+            }
+        }
+        let mut excerpt: Vec<f32> = decoder.by_ref().take(excerpt_samples).collect();
+        peak = peak.max(simd::peak(&excerpt) as f64);
+        k_weight(&mut excerpt, channels, rate);
+        for block in excerpt.chunks_exact(block_samples) {
+            blocks.push(simd::sum_squares(block) / (block_samples / channels) as f64);
+        }
+    }
+    Some((gated_gain(blocks)?, peak))
+}
+
+/// BS.1770 K-weighting of interleaved samples: head-related shelf, then the RLB
+/// high-pass.
+fn k_weight(samples: &mut [f32], channels: usize, rate: u32) {
+    let mut filters: Vec<(Biquad, Biquad)> = (0..channels)
+        .map(|_| {
+            (
+                Biquad::high_shelf(1681.97, 4.0, 0.7072, rate),
+                Biquad::high_pass(38.14, 0.5003, rate),
+            )
+        })
+        .collect();
+    for (index, sample) in samples.iter_mut().enumerate() {
+        let (shelf, high_pass) = &mut filters[index % channels];
+        *sample = high_pass.process(shelf.process(*sample));
+    }
+}
+
+/// Gain in dB to [`TARGET_LUFS`] from the mean square of each K-weighted block, with
+/// the absolute (-70 LUFS) and relative (-10 LU) gates. `None` when all is silent.
+fn gated_gain(blocks: Vec<f64>) -> Option<f64> {
+    let loudness = |energy: f64| -0.691 + 10.0 * energy.log10();
+    let audible: Vec<f64> = blocks
+        .into_iter()
+        .filter(|e| loudness(*e) > -70.0)
+        .collect();
+    if audible.is_empty() {
+        return None;
+    }
+    let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+    let relative_gate = loudness(mean(&audible)) - 10.0;
+    let gated: Vec<f64> = audible
+        .into_iter()
+        .filter(|e| loudness(*e) > relative_gate)
+        .collect();
+    Some(TARGET_LUFS - loudness(mean(&gated)))
+}
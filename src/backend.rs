@@ -0,0 +1,208 @@
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Signature, Span, Spanned, SyntaxShape};
+use rodio::{
+    cpal::{
+        self,
+        traits::{DeviceTrait, HostTrait},
+    },
+    OutputStream, OutputStreamBuilder,
+};
+
+use crate::config::plugin_config_value;
+
+/// ALSA device that PipeWire's ALSA plugin (`pipewire-alsa`) provides.
+const PIPEWIRE_DEVICE: &str = "pipewire";
+
+/// `--backend` choices: the name used on the command line, the backend and a
+/// description for completions.
+pub const BACKENDS: [(&str, Backend, &str); 4] = [
+    (
+        "auto",
+        Backend::Auto,
+        "the system's default output (default)",
+    ),
+    ("alsa", Backend::Alsa, "the default ALSA device"),
+    (
+        "pipewire",
+        Backend::PipeWire,
+        "PipeWire's ALSA device, shown as its own node in the graph",
+    ),
+    (
+        "jack",
+        Backend::Jack,
+        "a JACK client (needs cpal built with JACK)",
+    ),
+];
+
+/// Audio host a playback command opens its output on.
+///
+/// Pro-audio setups route sound through a JACK or PipeWire graph instead of the default
+/// ALSA device, so the player shows up as its own client there and can be patched like
+/// any other application.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Auto,
+    Alsa,
+    PipeWire,
+    Jack,
+}
+
+/// Adds the `--backend` and `--device` flags to a playback command's signature.
+pub fn backend_flags(signature: Signature) -> Signature {
+    signature
+        .named(
+            "backend",
+            SyntaxShape::String,
+            "audio backend: auto (default), alsa, pipewire or jack (config: backend)",
+            None,
+        )
+        .named(
+            "device",
+            SyntaxShape::String,
+            "output device, by a name `sound devices` lists (default: the backend's default)",
+            None,
+        )
+}
+
+impl Backend {
+    /// Reads `--backend`, falling back to `backend` in the plugin config.
+    pub fn from_call(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<Backend, LabeledError> {
+        let value = match call.get_flag_value("backend") {
+            Some(value) => Some(value),
+            None => plugin_config_value(engine, "backend", call.head)?,
+        };
+        let Some(value) = value else {
+            return Ok(Backend::Auto);
+        };
+        let span = value.span();
+        let name = value.as_str().map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("expected a backend name", span)
+        })?;
+        BACKENDS
+            .iter()
+            .find(|(backend, _, _)| backend.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, backend, _)| *backend)
+            .ok_or_else(|| {
+                LabeledError::new(format!("unknown backend '{name}'"))
+                    .with_label("expected auto, alsa, pipewire or jack", span)
+            })
+    }
+
+    /// Opens an output stream on this backend, on `device` when one is named. `on_error`
+    /// is called from the audio thread when the stream fails after it has been opened.
+    pub fn open_stream<E>(
+        self,
+        device: Option<&Spanned<String>>,
+        span: Span,
+        on_error: E,
+    ) -> Result<OutputStream, LabeledError>
+    where
+        E: FnMut(cpal::StreamError) + Clone + Send + 'static,
+    {
+        let stream_error = |err: &dyn std::fmt::Display| {
+            LabeledError::new(err.to_string()).with_label("audio stream exception", span)
+        };
+        let device = match (self, device) {
+            (_, Some(name)) => Some(self.find_device(name)?),
+            (Backend::Auto, None) => {
+                // Without a usable default device, try the others in turn like rodio's
+                // `open_default_stream`, keeping the error callback on each of them.
+                let open = |builder: OutputStreamBuilder| {
+                    builder
+                        .with_error_callback(on_error.clone())
+                        .open_stream_or_fallback()
+                };
+                return OutputStreamBuilder::from_default_device()
+                    .and_then(open)
+                    .or_else(|err| {
+                        cpal::default_host()
+                            .output_devices()
+                            .ok()
+                            .and_then(|mut devices| {
+                                devices.find_map(|device| {
+                                    OutputStreamBuilder::from_device(device).and_then(open).ok()
+                                })
+                            })
+                            .ok_or(err)
+                    })
+                    .map_err(|e| stream_error(&e));
+            }
+            (Backend::Alsa, None) => host("ALSA", span)?.default_output_device(),
+            (Backend::Jack, None) => host("JACK", span)?.default_output_device(),
+            (Backend::PipeWire, None) => host("ALSA", span)?
+                .output_devices()
+                .map_err(|e| stream_error(&e))?
+                .find(|device| device.name().is_ok_and(|name| name == PIPEWIRE_DEVICE)),
+        };
+        let device = device.ok_or_else(|| {
+            let error = LabeledError::new("no output device on this backend")
+                .with_label(format!("{self:?} has no output device"), span);
+            match self {
+                Backend::PipeWire => {
+                    error.with_help("install pipewire-alsa, or use --backend jack")
+                }
+                Backend::Jack => error.with_help("start a JACK server (or pipewire-jack) first"),
+                _ => error,
+            }
+        })?;
+        OutputStreamBuilder::from_device(device)
+            .and_then(|builder| {
+                builder
+                    .with_error_callback(on_error)
+                    .open_stream_or_fallback()
+            })
+            .map_err(|e| stream_error(&e))
+    }
+
+    /// The output device called `name` among the devices of this backend's host, or of
+    /// every host for `auto`, which are the devices `sound devices` lists.
+    fn find_device(self, name: &Spanned<String>) -> Result<cpal::Device, LabeledError> {
+        let hosts = match self {
+            Backend::Auto => cpal::available_hosts()
+                .into_iter()
+                .filter_map(|id| cpal::host_from_id(id).ok())
+                .collect(),
+            Backend::Alsa => vec![host("ALSA", name.span)?],
+            Backend::Jack => vec![host("JACK", name.span)?],
+            Backend::PipeWire => {
+                return Err(LabeledError::new("conflicting flags")
+                    .with_label(
+                        "--backend pipewire always plays on PipeWire's device",
+                        name.span,
+                    )
+                    .with_help("use --backend alsa to pick another ALSA device"))
+            }
+        };
+        hosts
+            .iter()
+            .filter_map(|host| host.output_devices().ok())
+            .flatten()
+            .find(|device| device.name().is_ok_and(|device| device == name.item))
+            .ok_or_else(|| {
+                LabeledError::new(format!("no output device named '{}'", name.item))
+                    .with_label(format!("not an output device of {self:?}"), name.span)
+                    .with_help("run `sound devices` to list the device names")
+            })
+    }
+}
+
+/// The cpal host called `name`, if this build has it.
+fn host(name: &str, span: Span) -> Result<cpal::Host, LabeledError> {
+    let unavailable = || {
+        let available: Vec<&str> = cpal::available_hosts().iter().map(|id| id.name()).collect();
+        LabeledError::new(format!("{name} is not available"))
+            .with_label(
+                format!("this build supports: {}", available.join(", ")),
+                span,
+            )
+            .with_help("JACK needs the plugin built against cpal with its `jack` feature")
+    };
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(unavailable)?;
+    cpal::host_from_id(id).map_err(|_| unavailable())
+}
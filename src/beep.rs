@@ -0,0 +1,295 @@
+// Beep patterns for `sound beep --pattern`: short cues that tell outcomes apart by ear,
+// built in or read from a `--theme` of recordings or SoundFont presets.
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Record, Span, Value};
+use rodio::buffer::SamplesBuffer;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    midi::Message,
+    pcm::Pcm,
+    sound_make::{parse_frequencies, Tone, TONE_RATE},
+    sound_sequence::{note_envelope, Step},
+    soundfont::{SoundFont, Synth, BLOCK, DRUM_BANK, DRUM_CHANNEL},
+    utils::{path_value, resolve_filepath},
+    validate,
+};
+
+/// Cue a theme plays for a plain `sound beep`, without `--pattern`.
+const PLAIN_CUE: &str = "beep";
+
+/// Note a SoundFont theme plays its presets at: middle C.
+const CUE_KEY: u8 = 60;
+
+const CUE_VELOCITY: u8 = 100;
+
+/// How long a SoundFont cue's note is held before it is released.
+const CUE_HOLD: Duration = Duration::from_millis(300);
+
+/// Longest a SoundFont cue may ring, so a preset that never fades still stops.
+const MAX_CUE: Duration = Duration::from_secs(5);
+
+/// A built-in pattern: each beep as its frequency in Hz, its length and the silence
+/// after it, in milliseconds.
+type Beeps = &'static [(f32, u64, u64)];
+
+/// `--pattern` names, with a description for completions. Success climbs a major
+/// chord, error falls a fifth, warning repeats one note and question rises a third,
+/// so each reads like the intonation it stands for.
+pub const BEEP_PATTERNS: [(&str, Beeps, &str); 4] = [
+    (
+        "success",
+        &[(1046.5, 80, 20), (1318.5, 80, 20), (1568.0, 180, 0)],
+        "three quick notes climbing a major chord",
+    ),
+    (
+        "error",
+        &[(392.0, 180, 60), (261.6, 400, 0)],
+        "two low notes falling a fifth",
+    ),
+    (
+        "warning",
+        &[(880.0, 150, 100), (880.0, 150, 100), (880.0, 150, 0)],
+        "the same note three times",
+    ),
+    (
+        "question",
+        &[(784.0, 120, 40), (987.8, 250, 0)],
+        "two notes rising, like a raised voice",
+    ),
+];
+
+/// The beeps `--pattern` asks for, at `amplify`: a built-in pattern by name, or a list
+/// of `{freq, duration, gap}` records.
+pub fn pattern_steps(
+    call: &EvaluatedCall,
+    pattern: &Value,
+    amplify: f32,
+) -> Result<Vec<Step>, LabeledError> {
+    let envelope = note_envelope(call)?;
+    let beep = |frequencies: Vec<f32>, duration: Duration, gap: Duration| {
+        let tone = Tone {
+            frequencies,
+            amplify,
+            envelope,
+            ..Tone::sine(0.0, duration)
+        };
+        [Step::Tone(tone), Step::Rest(gap)]
+    };
+    let steps = match pattern {
+        Value::String { val, .. } => {
+            let (_, beeps, _) = BEEP_PATTERNS
+                .iter()
+                .find(|(name, _, _)| name.eq_ignore_ascii_case(val.trim()))
+                .ok_or_else(|| {
+                    LabeledError::new(format!("unknown beep pattern '{val}'"))
+                        .with_label("unknown pattern", pattern.span())
+                        .with_help("use success, error, warning or question, or a list of {freq, duration, gap} records")
+                })?;
+            beeps
+                .iter()
+                .flat_map(|&(frequency, duration, gap)| {
+                    beep(
+                        vec![frequency],
+                        Duration::from_millis(duration),
+                        Duration::from_millis(gap),
+                    )
+                })
+                .collect()
+        }
+        Value::List { vals, .. } if !vals.is_empty() => {
+            let mut steps = Vec::new();
+            for row in vals {
+                let record = row.as_record().map_err(|e| {
+                    LabeledError::new(e.to_string())
+                        .with_label("expected a {freq, duration, gap} record", row.span())
+                })?;
+                let (frequencies, duration, gap) = custom_beep(record, row.span())?;
+                steps.extend(beep(frequencies, duration, gap));
+            }
+            steps
+        }
+        _ => return Err(LabeledError::new("invalid --pattern")
+            .with_label(
+                "expected a pattern name or a list of records",
+                pattern.span(),
+            )
+            .with_help(
+                "e.g. --pattern success, or --pattern [{freq: 880, duration: 100ms, gap: 50ms}]",
+            )),
+    };
+    Ok(steps)
+}
+
+/// A record's `freq` (or `frequency`/`note`) is anything `sound make` accepts, including
+/// a chord; `duration` is required and `gap` defaults to no silence.
+fn custom_beep(
+    record: &Record,
+    span: Span,
+) -> Result<(Vec<f32>, Duration, Duration), LabeledError> {
+    let frequency = ["freq", "frequency", "note"]
+        .into_iter()
+        .find_map(|column| record.get(column))
+        .ok_or_else(|| {
+            LabeledError::new("missing freq").with_label("beep has no `freq` column", span)
+        })?;
+    let duration = record.get("duration").ok_or_else(|| {
+        LabeledError::new("missing duration").with_label("beep has no `duration` column", span)
+    })?;
+    let gap = match record.get("gap") {
+        Some(gap) => validate::duration(gap, "gap")?,
+        None => Duration::ZERO,
+    };
+    Ok((
+        parse_frequencies(frequency)?,
+        validate::duration(duration, "duration")?,
+        gap,
+    ))
+}
+
+/// The cue `theme` has for `pattern` (or for a plain beep), at `amplify`. A theme is a
+/// directory of audio files named after the cues, like `success.wav` or `error.ogg`, or
+/// a SoundFont whose presets are named after them. `None` when the theme has no such
+/// cue, or `pattern` is a list of records, so the built-in beeps play instead.
+pub fn theme_cue(
+    engine: &EngineInterface,
+    theme: Value,
+    pattern: Option<&Value>,
+    amplify: f32,
+) -> Result<Option<SamplesBuffer>, LabeledError> {
+    let cue = match pattern {
+        Some(Value::String { val, .. }) => val.trim(),
+        Some(_) => return Ok(None),
+        None => PLAIN_CUE,
+    };
+    // Themes made for other tools often call it `warn`.
+    let names: &[&str] = match cue {
+        cue if cue.eq_ignore_ascii_case("warning") || cue.eq_ignore_ascii_case("warn") => {
+            &["warning", "warn"]
+        }
+        cue => &[cue],
+    };
+    let span = theme.span();
+    let path = resolve_filepath(engine, span, path_value(theme)?)?;
+    if path.is_dir() {
+        let Some(file) = cue_file(&path, names, span)? else {
+            return Ok(None);
+        };
+        let file = File::open(&file).map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("error trying to open the cue", span)
+        })?;
+        let pcm = Pcm::decode(file, span)?;
+        let samples = pcm.samples.iter().map(|s| s * amplify).collect::<Vec<_>>();
+        return Ok(Some(SamplesBuffer::new(
+            pcm.channels,
+            pcm.sample_rate,
+            samples,
+        )));
+    }
+    let data = std::fs::read(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error trying to read the theme", span)
+    })?;
+    let font = SoundFont::read(&data, span)
+        .map_err(|e| e.with_help("a theme is a directory of audio files or a .sf2 SoundFont"))?;
+    Ok(names
+        .iter()
+        .find_map(|name| font.preset_named(name))
+        .map(|(bank, program)| render_preset(Arc::new(font), bank, program, amplify)))
+}
+
+/// The audio file in `dir` whose name, without its extension, is one of `names`.
+fn cue_file(dir: &Path, names: &[&str], span: Span) -> Result<Option<PathBuf>, LabeledError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error trying to read the theme", span)
+    })?;
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    // Sorted, so `success.ogg` beside `success.wav` always picks the same one.
+    files.sort();
+    Ok(names.iter().find_map(|name| {
+        files
+            .iter()
+            .find(|file| {
+                file.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| stem.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+    }))
+}
+
+/// Stereo render of one note of a SoundFont preset, held for [`CUE_HOLD`] and then let
+/// ring until it fades, for at most [`MAX_CUE`].
+fn render_preset(font: Arc<SoundFont>, bank: u16, program: u8, amplify: f32) -> SamplesBuffer {
+    let mut synth = Synth::new(font, TONE_RATE);
+    // The drum channel always plays the drum bank; other banks are picked by CC 0.
+    let channel = match bank {
+        DRUM_BANK => DRUM_CHANNEL,
+        bank => {
+            synth.handle(Message::Control {
+                channel: 0,
+                controller: 0,
+                value: bank.min(127) as u8,
+            });
+            0
+        }
+    };
+    synth.handle(Message::Program { channel, program });
+    synth.handle(Message::NoteOn {
+        channel,
+        key: CUE_KEY,
+        velocity: CUE_VELOCITY,
+    });
+    let frames = |duration: Duration| (duration.as_secs_f64() * TONE_RATE as f64) as usize;
+    let (hold, max) = (frames(CUE_HOLD), frames(MAX_CUE));
+    let mut block = [0.0; BLOCK * 2];
+    let mut samples = Vec::new();
+    let mut frame = 0;
+    let mut released = false;
+    while frame < max {
+        if frame >= hold && !released {
+            synth.handle(Message::NoteOff {
+                channel,
+                key: CUE_KEY,
+            });
+            released = true;
+        }
+        if released && synth.is_silent() {
+            break;
+        }
+        synth.render(&mut block);
+        samples.extend(block.iter().map(|s| s * amplify));
+        frame += BLOCK;
+    }
+    SamplesBuffer::new(2, TONE_RATE, samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::soundfont::tests::hostile_font;
+    use rodio::Source;
+    use std::{sync::mpsc, thread};
+
+    #[test]
+    fn hostile_soundfont_theme_renders_without_hanging() {
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            let font = SoundFont::read(&hostile_font("success"), Span::unknown()).unwrap();
+            let (bank, program) = font.preset_named("Success").unwrap();
+            let cue = render_preset(Arc::new(font), bank, program, 1.0);
+            done.send(cue.total_duration()).unwrap();
+        });
+        let length = finished.recv_timeout(Duration::from_secs(10));
+        let length = length.expect("rendering a hostile SoundFont cue hung");
+        assert!(length.is_some_and(|length| length <= MAX_CUE + Duration::from_millis(10)));
+    }
+}
@@ -0,0 +1,218 @@
+use log::warn;
+use nu_plugin::EngineInterface;
+use nu_protocol::{LabeledError, Span};
+use rodio::cpal::{
+    self,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample, StreamInstant,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Lead-in recorded before the test signal starts, so the input stream is running.
+const RECORD_LEAD_IN: Duration = Duration::from_millis(200);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Mono capture from the default input device, aligned to a played test signal.
+pub struct Recording {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    /// Seconds from the first recorded frame to the moment the first frame of the test
+    /// signal reached the output, from the audio host's stream timestamps. `None` if the
+    /// host did not report usable timestamps.
+    pub signal_offset: Option<f64>,
+}
+
+/// Plays a mono test signal on the default output device while recording the default
+/// input device, then keeps recording for `tail` after the signal ends.
+///
+/// `make_signal` receives the output sample rate. Playback uses cpal directly instead
+/// of a rodio sink so the host's playback timestamp of the first frame is known.
+pub fn play_and_record(
+    engine: &EngineInterface,
+    span: Span,
+    make_signal: impl FnOnce(u32) -> Vec<f32>,
+    tail: Duration,
+) -> Result<(Recording, u32), LabeledError> {
+    let host = cpal::default_host();
+    let device_error = |msg: &str| {
+        LabeledError::new(msg.to_string())
+            .with_label("audio device exception", span)
+            .with_help("connect an input and output device, or a loopback cable")
+    };
+    let output = host
+        .default_output_device()
+        .ok_or_else(|| device_error("no output device available"))?;
+    let input = host
+        .default_input_device()
+        .ok_or_else(|| device_error("no input device available"))?;
+    let stream_error = |e: &dyn std::fmt::Display| {
+        LabeledError::new(e.to_string()).with_label("audio stream exception", span)
+    };
+
+    let output_config = output
+        .default_output_config()
+        .map_err(|e| stream_error(&e))?;
+    let input_config = input.default_input_config().map_err(|e| stream_error(&e))?;
+    let output_rate = output_config.sample_rate().0;
+    let signal = make_signal(output_rate);
+    let signal_len = signal.len();
+
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let first_capture = Arc::new(Mutex::new(None::<StreamInstant>));
+    let first_playback = Arc::new(Mutex::new(None::<StreamInstant>));
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let input_stream = match input_config.sample_format() {
+        SampleFormat::I16 => {
+            build_input::<i16>(&input, &input_config.config(), &recorded, &first_capture)
+        }
+        SampleFormat::U16 => {
+            build_input::<u16>(&input, &input_config.config(), &recorded, &first_capture)
+        }
+        SampleFormat::I32 => {
+            build_input::<i32>(&input, &input_config.config(), &recorded, &first_capture)
+        }
+        _ => build_input::<f32>(&input, &input_config.config(), &recorded, &first_capture),
+    }
+    .map_err(|e| stream_error(&e))?;
+    input_stream.play().map_err(|e| stream_error(&e))?;
+    std::thread::sleep(RECORD_LEAD_IN);
+
+    let signal = Arc::new(signal);
+    let output_stream = match output_config.sample_format() {
+        SampleFormat::I16 => build_output::<i16>(
+            &output,
+            &output_config.config(),
+            &signal,
+            &first_playback,
+            &finished,
+        ),
+        SampleFormat::U16 => build_output::<u16>(
+            &output,
+            &output_config.config(),
+            &signal,
+            &first_playback,
+            &finished,
+        ),
+        SampleFormat::I32 => build_output::<i32>(
+            &output,
+            &output_config.config(),
+            &signal,
+            &first_playback,
+            &finished,
+        ),
+        _ => build_output::<f32>(
+            &output,
+            &output_config.config(),
+            &signal,
+            &first_playback,
+            &finished,
+        ),
+    }
+    .map_err(|e| stream_error(&e))?;
+    output_stream.play().map_err(|e| stream_error(&e))?;
+
+    let expected = Duration::from_secs_f64(signal_len as f64 / output_rate as f64);
+    let deadline = Instant::now() + expected + tail + Duration::from_secs(2);
+    while !finished.load(Ordering::Relaxed) && Instant::now() < deadline {
+        engine.signals().check(&span)?;
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    let tail_end = Instant::now() + tail;
+    while Instant::now() < tail_end {
+        engine.signals().check(&span)?;
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    drop(output_stream);
+    drop(input_stream);
+
+    let capture = *first_capture.lock().unwrap_or_else(|e| e.into_inner());
+    let playback = *first_playback.lock().unwrap_or_else(|e| e.into_inner());
+    let signal_offset = match (capture, playback) {
+        (Some(capture), Some(playback)) => playback
+            .duration_since(&capture)
+            .map(|d| d.as_secs_f64())
+            .or_else(|| capture.duration_since(&playback).map(|d| -d.as_secs_f64())),
+        _ => None,
+    };
+    let samples = std::mem::take(&mut *recorded.lock().unwrap_or_else(|e| e.into_inner()));
+    Ok((
+        Recording {
+            samples,
+            sample_rate: input_config.sample_rate().0,
+            signal_offset,
+        },
+        output_rate,
+    ))
+}
+
+fn build_input<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    recorded: &Arc<Mutex<Vec<f32>>>,
+    first_capture: &Arc<Mutex<Option<StreamInstant>>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    let recorded = Arc::clone(recorded);
+    let first_capture = Arc::clone(first_capture);
+    device.build_input_stream(
+        config,
+        move |data: &[T], info: &cpal::InputCallbackInfo| {
+            if let Ok(mut first) = first_capture.lock() {
+                first.get_or_insert(info.timestamp().capture);
+            }
+            if let Ok(mut recorded) = recorded.lock() {
+                recorded.extend(data.chunks(channels).map(|frame| {
+                    frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / frame.len() as f32
+                }));
+            }
+        },
+        |e| warn!("input stream error: {e}"),
+        None,
+    )
+}
+
+fn build_output<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    signal: &Arc<Vec<f32>>,
+    first_playback: &Arc<Mutex<Option<StreamInstant>>>,
+    finished: &Arc<AtomicBool>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels.max(1) as usize;
+    let signal = Arc::clone(signal);
+    let first_playback = Arc::clone(first_playback);
+    let finished = Arc::clone(finished);
+    let mut position = 0;
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+            if let Ok(mut first) = first_playback.lock() {
+                first.get_or_insert(info.timestamp().playback);
+            }
+            for frame in data.chunks_mut(channels) {
+                let value = signal.get(position).copied().unwrap_or(0.0);
+                frame.fill(T::from_sample(value));
+                position += 1;
+            }
+            if position >= signal.len() {
+                finished.store(true, Ordering::Relaxed);
+            }
+        },
+        |e| warn!("output stream error: {e}"),
+        None,
+    )
+}
@@ -0,0 +1,90 @@
+use crate::{dsp::Biquad, pcm::Pcm};
+
+/// -3 dB, the ITU-R BS.775 weight for center and surround channels in a stereo downmix.
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+/// Rear delay of the passive upmix; decorrelates the surrounds from the fronts.
+const UPMIX_REAR_DELAY_MS: u32 = 12;
+const UPMIX_REAR_CUTOFF: f64 = 7_000.0;
+const UPMIX_LFE_CUTOFF: f64 = 120.0;
+
+/// Stereo downmix weights `(left, right)` per input channel, for the default WAVE
+/// channel orders: 3.0 (FL FR FC), quad (FL FR BL BR), 5.0 (FL FR FC BL BR),
+/// 5.1 (FL FR FC LFE BL BR) and 7.1 (FL FR FC LFE BL BR SL SR). LFE is dropped as
+/// BS.775 recommends.
+pub(crate) fn stereo_weights(channels: u16) -> Option<Vec<(f32, f32)>> {
+    let front = [(1.0, 0.0), (0.0, 1.0)];
+    let center = (MINUS_3DB, MINUS_3DB);
+    let (left, right) = ((MINUS_3DB, 0.0), (0.0, MINUS_3DB));
+    let weights: Vec<(f32, f32)> = match channels {
+        1 => vec![(1.0, 1.0)],
+        2 => front.to_vec(),
+        3 => [&front[..], &[center]].concat(),
+        4 => [&front[..], &[left, right]].concat(),
+        5 => [&front[..], &[center, left, right]].concat(),
+        6 => [&front[..], &[center, (0.0, 0.0), left, right]].concat(),
+        8 => [&front[..], &[center, (0.0, 0.0), left, right, left, right]].concat(),
+        _ => return None,
+    };
+    Some(weights)
+}
+
+/// Mixes a multichannel clip down to stereo with BS.775 coefficients, normalized so a
+/// signal present at full scale in every channel cannot clip.
+pub fn downmix_stereo(pcm: &Pcm) -> Option<Pcm> {
+    let weights = stereo_weights(pcm.channels)?;
+    let norm_left: f32 = weights.iter().map(|w| w.0).sum();
+    let norm_right: f32 = weights.iter().map(|w| w.1).sum();
+    let mut samples = Vec::with_capacity(pcm.frames() * 2);
+    for frame in pcm.samples.chunks_exact(pcm.channels as usize) {
+        let (mut left, mut right) = (0.0, 0.0);
+        for (sample, (wl, wr)) in frame.iter().zip(&weights) {
+            left += sample * wl;
+            right += sample * wr;
+        }
+        samples.push(left / norm_left);
+        samples.push(right / norm_right);
+    }
+    Some(Pcm::new(samples, 2, pcm.sample_rate))
+}
+
+/// Folds a clip to mono through the stereo downmix, so surrounds are weighted correctly.
+pub fn downmix_mono(pcm: &Pcm) -> Option<Pcm> {
+    let stereo = downmix_stereo(pcm)?;
+    Some(Pcm::new(stereo.mono(), 1, pcm.sample_rate))
+}
+
+/// Passive-matrix stereo to 5.1 upmix (FL FR FC LFE BL BR).
+///
+/// Fronts carry the original channels, the center gets the -3 dB mid signal, the LFE
+/// the mid below 120 Hz, and the surrounds the side signal (`L - R`), delayed and
+/// low-passed with opposite polarity per side, as in a Pro Logic style decoder.
+pub fn upmix_51(pcm: &Pcm) -> Option<Pcm> {
+    if pcm.channels != 2 {
+        return None;
+    }
+    let delay = (pcm.sample_rate * UPMIX_REAR_DELAY_MS / 1000) as usize;
+    let mut lfe = Biquad::low_pass(UPMIX_LFE_CUTOFF, pcm.sample_rate);
+    let mut rear = Biquad::low_pass(UPMIX_REAR_CUTOFF, pcm.sample_rate);
+    let frames = pcm.frames();
+    let side = |frame: usize| (pcm.samples[frame * 2] - pcm.samples[frame * 2 + 1]) / 2.0;
+
+    let mut samples = Vec::with_capacity(frames * 6);
+    for frame in 0..frames {
+        let (left, right) = (pcm.samples[frame * 2], pcm.samples[frame * 2 + 1]);
+        let mid = (left + right) / 2.0;
+        let surround = rear.process(if frame >= delay {
+            side(frame - delay)
+        } else {
+            0.0
+        });
+        samples.extend_from_slice(&[
+            left,
+            right,
+            mid * MINUS_3DB,
+            lfe.process(mid),
+            surround * MINUS_3DB,
+            -surround * MINUS_3DB,
+        ]);
+    }
+    Some(Pcm::new(samples, 6, pcm.sample_rate))
+}
@@ -0,0 +1,145 @@
+use nu_protocol::DynamicSuggestion;
+
+use crate::{
+    arpeggio::ARP_ORDERS,
+    audio_devices::output_device_names,
+    audio_player::REPEAT_MODES,
+    backend::BACKENDS,
+    beep::BEEP_PATTERNS, constants::TAG_MAP, no_audio::NO_AUDIO_POLICIES, sound_make::WAVEFORMS,
+    stream_identity::STREAM_ROLES, utils::DURATION_FORMATS, wav::WAV_SAMPLE_FORMATS,
+};
+
+/// Encode targets of `sound audition --as`: the name used in the list, the file
+/// extension and the ffmpeg encoder.
+pub const ENCODE_TARGETS: [(&str, &str, &str); 7] = [
+    ("mp3", "mp3", "libmp3lame"),
+    ("opus", "opus", "libopus"),
+    ("ogg", "ogg", "libvorbis"),
+    ("vorbis", "ogg", "libvorbis"),
+    ("aac", "m4a", "aac"),
+    ("m4a", "m4a", "aac"),
+    ("flac", "flac", "flac"),
+];
+
+/// Metadata key names for `sound meta set -k`, with the lofty item each maps to.
+pub fn tag_keys() -> Vec<DynamicSuggestion> {
+    TAG_MAP
+        .iter()
+        .map(|(key, item)| suggestion(key, format!("{item:?}")))
+        .collect()
+}
+
+/// One typical `format:setting` entry per encode target, for `sound audition --as`.
+pub fn encode_targets() -> Vec<DynamicSuggestion> {
+    ENCODE_TARGETS
+        .iter()
+        .map(|(name, extension, encoder)| {
+            let setting = match *name {
+                "ogg" | "vorbis" => ":q5",
+                "flac" => "",
+                "opus" => ":96k",
+                _ => ":128k",
+            };
+            suggestion(
+                &format!("{name}{setting}"),
+                format!(".{extension} via {encoder}"),
+            )
+        })
+        .collect()
+}
+
+/// Image formats accepted by `sound meta artwork set --format`.
+pub fn image_formats() -> Vec<DynamicSuggestion> {
+    vec![
+        suggestion("jpeg", "JPEG image".to_string()),
+        suggestion("png", "PNG image".to_string()),
+    ]
+}
+
+/// Tone shapes for `sound make --waveform`.
+pub fn waveforms() -> Vec<DynamicSuggestion> {
+    WAVEFORMS
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Note orders for `sound make --arp`.
+pub fn arp_orders() -> Vec<DynamicSuggestion> {
+    ARP_ORDERS
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Built-in cues for `sound beep --pattern`.
+pub fn beep_patterns() -> Vec<DynamicSuggestion> {
+    BEEP_PATTERNS
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Duration styles for `sound meta --duration-format`.
+pub fn duration_formats() -> Vec<DynamicSuggestion> {
+    DURATION_FORMATS
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Audio backends for `sound play --backend`.
+pub fn backends() -> Vec<DynamicSuggestion> {
+    BACKENDS
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Output devices for `sound play --device`, from the list `sound devices` shows.
+pub fn output_devices() -> Vec<DynamicSuggestion> {
+    output_device_names()
+        .iter()
+        .map(|(name, host)| suggestion(name, format!("{host} output device")))
+        .collect()
+}
+
+/// Modes for `sound play --repeat`.
+pub fn repeat_modes() -> Vec<DynamicSuggestion> {
+    REPEAT_MODES
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Policies for `--if-no-audio` on playback commands.
+pub fn no_audio_policies() -> Vec<DynamicSuggestion> {
+    NO_AUDIO_POLICIES
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Stream roles for `--role` on playback commands.
+pub fn stream_roles() -> Vec<DynamicSuggestion> {
+    STREAM_ROLES
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+/// Sample formats for `--bits` on commands with `--data` output.
+pub fn wav_sample_formats() -> Vec<DynamicSuggestion> {
+    WAV_SAMPLE_FORMATS
+        .iter()
+        .map(|(name, _, description)| suggestion(name, description.to_string()))
+        .collect()
+}
+
+fn suggestion(value: &str, description: String) -> DynamicSuggestion {
+    DynamicSuggestion {
+        value: value.to_string(),
+        description: Some(description),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,33 @@
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Span, Value};
+
+/// Looks up `key` in the plugin's configuration record
+/// (`$env.config.plugins.audio_hook`). Returns `None` when the plugin has no
+/// configuration or the key is absent.
+pub fn plugin_config_value(
+    engine: &EngineInterface,
+    key: &str,
+    span: Span,
+) -> Result<Option<Value>, LabeledError> {
+    let config = engine.get_plugin_config().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("failed to read plugin config", span)
+    })?;
+    Ok(match config {
+        Some(Value::Record { val, .. }) => val.get(key).cloned(),
+        _ => None,
+    })
+}
+
+/// The value of `--flag` when given, or else of `key` in the plugin config, so users
+/// can set a default once in `config.nu` and still override it per call.
+pub fn flag_or_config(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    flag: &str,
+    key: &str,
+) -> Result<Option<Value>, LabeledError> {
+    match call.get_flag_value(flag) {
+        Some(value) => Ok(Some(value)),
+        None => plugin_config_value(engine, key, call.head),
+    }
+}
@@ -0,0 +1,84 @@
+// Cooldowns for notification sounds (`--cooldown`/`--key`): a flapping script plays an
+// alert once per cooldown instead of on every call.
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Signature, SyntaxShape};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    utils::flag_span,
+    validate::{self, duration_flag},
+};
+
+/// Adds `--cooldown` and `--key`.
+pub fn cooldown_flags(signature: Signature) -> Signature {
+    signature
+        .named(
+            "cooldown",
+            validate::duration_shape(),
+            "stay silent if the same --key played within this long",
+            None,
+        )
+        .named(
+            "key",
+            SyntaxShape::String,
+            "name of the alert for --cooldown, shared by calls that cool down together (default: the sound itself)",
+            None,
+        )
+}
+
+/// When each key's cooldown ends. Lives in the plugin, so it only lasts while the plugin
+/// process does.
+#[derive(Default)]
+pub struct Cooldowns(Arc<Mutex<HashMap<String, Instant>>>);
+
+impl Cooldowns {
+    /// Whether the call may play now. With `--cooldown`, a call whose key (`--key`, or
+    /// else `sound`, naming what would play) is still cooling down is skipped; otherwise
+    /// the key's cooldown starts over.
+    ///
+    /// The engine stops idle plugins after a few seconds, which would forget running
+    /// cooldowns, so garbage collection is disabled until the last one is over.
+    pub fn ready(
+        &self,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        sound: &str,
+    ) -> Result<bool, LabeledError> {
+        let Some(cooldown) = duration_flag(call, "cooldown")? else {
+            if call.get_flag_value("key").is_some() {
+                return Err(LabeledError::new("--key needs --cooldown")
+                    .with_label("only cooldowns are keyed", flag_span(call, "key")));
+            }
+            return Ok(true);
+        };
+        let key = call
+            .get_flag::<String>("key")?
+            .unwrap_or_else(|| sound.to_string());
+        let now = Instant::now();
+        let mut cooldowns = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        cooldowns.retain(|_, until| *until > now);
+        if cooldowns.contains_key(&key) {
+            return Ok(false);
+        }
+        cooldowns.insert(key, now + cooldown);
+        drop(cooldowns);
+
+        let _ = engine.set_gc_disabled(true);
+        let (engine, cooldowns) = (engine.clone(), self.0.clone());
+        std::thread::spawn(move || {
+            std::thread::sleep(cooldown + Duration::from_millis(1));
+            let mut cooldowns = cooldowns.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            cooldowns.retain(|_, until| *until > now);
+            if cooldowns.is_empty() {
+                let _ = engine.set_gc_disabled(false);
+            }
+        });
+        Ok(true)
+    }
+}
@@ -0,0 +1,288 @@
+use std::f64::consts::{FRAC_1_SQRT_2, LN_2, LOG2_E, PI, SQRT_2, TAU};
+
+/// Second-order IIR filter (RBJ audio-EQ cookbook), run in transposed direct form II.
+///
+/// One instance holds the state of a single channel; filter interleaved audio with one
+/// `Biquad` per channel.
+#[derive(Clone, Copy)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Butterworth low-pass at `cutoff` Hz.
+    pub fn low_pass(cutoff: f64, sample_rate: u32) -> Biquad {
+        let (cos, alpha) = Self::prewarp(cutoff, sample_rate, FRAC_1_SQRT_2);
+        let b1 = 1.0 - cos;
+        Self::normalized(b1 / 2.0, b1, b1 / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    /// Band-pass centered on `center` Hz with 0 dB peak gain; `q` of √2 spans one octave.
+    pub fn band_pass(center: f64, q: f64, sample_rate: u32) -> Biquad {
+        let (cos, alpha) = Self::prewarp(center, sample_rate, q);
+        Self::normalized(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    /// High-pass at `cutoff` Hz with resonance `q` (`FRAC_1_SQRT_2` for Butterworth).
+    pub fn high_pass(cutoff: f64, q: f64, sample_rate: u32) -> Biquad {
+        let (cos, alpha) = Self::prewarp(cutoff, sample_rate, q);
+        let b0 = (1.0 + cos) / 2.0;
+        Self::normalized(b0, -2.0 * b0, b0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    /// High shelf boosting (or cutting) everything above `corner` Hz by `gain_db`.
+    pub fn high_shelf(corner: f64, gain_db: f64, q: f64, sample_rate: u32) -> Biquad {
+        let (cos, alpha) = Self::prewarp(corner, sample_rate, q);
+        let a = 10f64.powf(gain_db / 40.0);
+        let root = 2.0 * a.sqrt() * alpha;
+        Self::normalized(
+            a * ((a + 1.0) + (a - 1.0) * cos + root),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - root),
+            (a + 1.0) - (a - 1.0) * cos + root,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - root,
+        )
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let x = input as f64;
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y as f32
+    }
+
+    fn prewarp(cutoff: f64, sample_rate: u32, q: f64) -> (f64, f64) {
+        let nyquist = sample_rate as f64 / 2.0;
+        let w0 = 2.0 * PI * cutoff.clamp(1.0, nyquist * 0.99) / sample_rate as f64;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn normalized(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Biquad {
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
+
+/// One-pole (6 dB/octave) low-pass, cheap enough to run per sample in streaming sources.
+#[derive(Clone, Copy)]
+pub struct OnePole {
+    a: f32,
+    z: f32,
+}
+
+impl OnePole {
+    pub fn low_pass(cutoff: f64, sample_rate: u32) -> OnePole {
+        let a = 1.0 - exp2(-TAU * cutoff / sample_rate as f64 * LOG2_E);
+        OnePole { a: a as f32, z: 0.0 }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.z += self.a * (input - self.z);
+        self.z
+    }
+
+    pub fn reset(&mut self) {
+        self.z = 0.0;
+    }
+}
+
+/// `sin(2π · cycles)`.
+///
+/// Computed with additions and multiplications only, which IEEE 754 rounds the same
+/// everywhere, so rendered tones come out bit-identical on every platform; `f64::sin`
+/// calls the system's libm, whose last bits differ between platforms.
+pub fn sin_cycles(cycles: f64) -> f64 {
+    // Fold onto the quarter wave around zero, where the series converges fastest.
+    let mut x = cycles - cycles.round();
+    if x > 0.25 {
+        x = 0.5 - x;
+    } else if x < -0.25 {
+        x = -0.5 - x;
+    }
+    let x = x * TAU;
+    let x2 = x * x;
+    // Taylor series to x^19 in Horner form; the next term is below 3e-16 at π/2.
+    let mut sum = 1.0;
+    for n in [342.0, 272.0, 210.0, 156.0, 110.0, 72.0, 42.0, 20.0, 6.0] {
+        sum = 1.0 - x2 / n * sum;
+    }
+    x * sum
+}
+
+/// `cos(2π · cycles)`, bit-identical everywhere like [`sin_cycles`].
+pub fn cos_cycles(cycles: f64) -> f64 {
+    sin_cycles(0.25 - cycles)
+}
+
+/// `2^x`, bit-identical everywhere like [`sin_cycles`]. `x` is limited to the normal
+/// range of `f64`.
+pub fn exp2(x: f64) -> f64 {
+    let whole = x.floor().clamp(-1022.0, 1023.0);
+    let y = (x - whole).clamp(0.0, 1.0) * LN_2;
+    // e^y for y below ln 2, Taylor series to y^17 in Horner form.
+    let mut sum = 1.0;
+    for n in (1..=17).rev() {
+        sum = 1.0 + y / n as f64 * sum;
+    }
+    sum * f64::from_bits(((whole as i64 + 1023) as u64) << 52)
+}
+
+/// `log2(x)`, bit-identical everywhere like [`sin_cycles`]. NaN below zero, minus
+/// infinity at zero.
+pub fn log2(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 || x.is_infinite() {
+        return if x == 0.0 { f64::NEG_INFINITY } else { x };
+    }
+    // Subnormals are scaled up by 2^54 to get a normal exponent field.
+    let (x, offset) = if x < f64::MIN_POSITIVE {
+        (x * 18_014_398_509_481_984.0, -54)
+    } else {
+        (x, 0)
+    };
+    let bits = x.to_bits();
+    let mut exponent = ((bits >> 52) & 0x7FF) as i64 - 1023 + offset;
+    let mut mantissa = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    // Centered on 1, from 1/√2 to √2, where the series converges fastest.
+    if mantissa > SQRT_2 {
+        mantissa /= 2.0;
+        exponent += 1;
+    }
+    // ln m = 2 atanh(s) with s = (m - 1) / (m + 1), at most 0.172; series to s^23.
+    let s = (mantissa - 1.0) / (mantissa + 1.0);
+    let s2 = s * s;
+    let mut sum = 0.0;
+    for k in (1..=11).rev() {
+        sum = s2 * (1.0 / (2 * k + 1) as f64 + sum);
+    }
+    exponent as f64 + 2.0 * s * (1.0 + sum) * LOG2_E
+}
+
+/// Small SplitMix64 generator for shuffles and randomized choices.
+///
+/// Not cryptographic; good enough for listening-test randomization without pulling in
+/// an RNG crate.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    /// Seeds from the clock and process id.
+    pub fn from_entropy() -> Rng {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng::new(nanos ^ ((std::process::id() as u64) << 32))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A sample of white noise, uniform in `[-1, 1)`.
+    pub fn next_sample(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+}
+
+/// In-place iterative radix-2 FFT over separate real and imaginary parts. The length
+/// must be a power of two. The inverse transform is scaled by `1/n`.
+pub fn fft(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f64;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f64;
+        re.iter_mut().for_each(|v| *v *= scale);
+        im.iter_mut().for_each(|v| *v *= scale);
+    }
+}
+
+/// Linear convolution of two real signals via FFT.
+pub fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let len = a.len() + b.len() - 1;
+    let n = len.next_power_of_two();
+    let (mut a_re, mut a_im) = (a.to_vec(), vec![0.0; n]);
+    let (mut b_re, mut b_im) = (b.to_vec(), vec![0.0; n]);
+    a_re.resize(n, 0.0);
+    b_re.resize(n, 0.0);
+    fft(&mut a_re, &mut a_im, false);
+    fft(&mut b_re, &mut b_im, false);
+    for i in 0..n {
+        let re = a_re[i] * b_re[i] - a_im[i] * b_im[i];
+        let im = a_re[i] * b_im[i] + a_im[i] * b_re[i];
+        a_re[i] = re;
+        a_im[i] = im;
+    }
+    fft(&mut a_re, &mut a_im, true);
+    a_re.truncate(len);
+    a_re
+}
@@ -0,0 +1,151 @@
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Signature, SyntaxShape, Value};
+use rodio::{
+    source::{Limit, LimitSettings, SeekError},
+    ChannelCount, SampleRate, Source,
+};
+use std::time::Duration;
+
+use crate::{config::plugin_config_value, simd};
+
+/// Ceiling used by `--protect-ears` when no `--max-db` is given.
+const DEFAULT_MAX_DB: f64 = -10.0;
+
+/// Output level cap shared by every playback command.
+///
+/// Enabled by `--protect-ears` or by `protect_ears: true` in the plugin config; the
+/// ceiling comes from `--max-db` or the config's `max_db`. Sources are run through
+/// rodio's limiter at the ceiling and then hard-clipped to it, and player volume is
+/// capped at 1.0 so nothing downstream can push the level back up.
+#[derive(Clone, Copy)]
+pub struct EarGuard {
+    max_db: f32,
+}
+
+/// Adds the `--protect-ears` and `--max-db` flags to a playback command's signature.
+pub fn ear_guard_flags(signature: Signature) -> Signature {
+    signature
+        .switch(
+            "protect-ears",
+            "hard-cap the output level (default ceiling -10 dBFS; config: protect_ears)",
+            None,
+        )
+        .named(
+            "max-db",
+            SyntaxShape::Number,
+            "output ceiling in dBFS for --protect-ears, e.g. -10 (config: max_db)",
+            None,
+        )
+}
+
+impl EarGuard {
+    pub fn from_call(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<Option<EarGuard>, LabeledError> {
+        let span = call.head;
+        let configured = matches!(
+            plugin_config_value(engine, "protect_ears", span)?,
+            Some(Value::Bool { val: true, .. })
+        );
+        if !call.has_flag("protect-ears")? && !configured {
+            return Ok(None);
+        }
+
+        let max_db = match call.get_flag_value("max-db") {
+            Some(value) => Some(value),
+            None => plugin_config_value(engine, "max_db", span)?,
+        };
+        let max_db = match max_db {
+            None => DEFAULT_MAX_DB,
+            Some(value) => {
+                let value_span = value.span();
+                let db = value.as_float().map_err(|e| {
+                    LabeledError::new(e.to_string())
+                        .with_label("expected a number of dB", value_span)
+                })?;
+                if !(-60.0..=0.0).contains(&db) {
+                    return Err(LabeledError::new("invalid output ceiling")
+                        .with_label("max-db must be between -60 and 0 dBFS", value_span));
+                }
+                db
+            }
+        };
+        Ok(Some(EarGuard {
+            max_db: max_db as f32,
+        }))
+    }
+
+    /// Linear amplitude of the ceiling.
+    pub fn ceiling(&self) -> f32 {
+        10f32.powf(self.max_db / 20.0)
+    }
+
+    /// A guard for one of `voices` sources mixed together, lowered so their sum still
+    /// stays under this ceiling.
+    pub fn split(&self, voices: usize) -> EarGuard {
+        EarGuard {
+            max_db: self.max_db - 20.0 * (voices.max(1) as f32).log10(),
+        }
+    }
+
+    pub fn protect<S: Source>(&self, source: S) -> HardCap<Limit<S>> {
+        let limited = source.limit(LimitSettings::default().with_threshold(self.max_db));
+        HardCap {
+            input: limited,
+            ceiling: self.ceiling(),
+        }
+    }
+
+    pub fn cap_volume(&self, volume: f32) -> f32 {
+        volume.min(1.0)
+    }
+
+    /// Scales a pre-rendered signal down so its peak sits at the ceiling. Unlike clipping,
+    /// this leaves measurement signals undistorted.
+    pub fn cap_samples(&self, samples: &mut [f32]) {
+        let ceiling = self.ceiling();
+        let peak = simd::peak(samples);
+        if peak > ceiling {
+            simd::scale(samples, ceiling / peak);
+        }
+    }
+}
+
+/// Clamps every sample to `±ceiling`; the last line of defence after the limiter.
+pub struct HardCap<S: Source> {
+    input: S,
+    ceiling: f32,
+}
+
+impl<S: Source> Iterator for HardCap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.input
+            .next()
+            .map(|s| s.clamp(-self.ceiling, self.ceiling))
+    }
+}
+
+impl<S: Source> Source for HardCap<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
@@ -0,0 +1,403 @@
+// Arithmetic expressions of time for `sound make --expr`.
+//
+// An expression is parsed once into a postfix program and then evaluated for every
+// sample, so it runs at native speed rather than as a closure call per sample. The
+// math functions are the arithmetic-only ones from `dsp`, which keeps the output
+// bit-identical across platforms like the built-in waveforms.
+
+use nu_protocol::{LabeledError, Span};
+use std::f64::consts::{E, LN_2, LOG2_10, LOG2_E, PI, TAU};
+
+use crate::dsp::{exp2, log2, sin_cycles};
+
+/// Functions an expression can call, with their arity.
+const FUNCTIONS: [(&str, Function); 17] = [
+    ("sin", Function::One(|x| sin_cycles(x / TAU))),
+    ("cos", Function::One(|x| sin_cycles(0.25 - x / TAU))),
+    (
+        "tan",
+        Function::One(|x| sin_cycles(x / TAU) / sin_cycles(0.25 - x / TAU)),
+    ),
+    ("exp", Function::One(|x| exp2(x * LOG2_E))),
+    ("ln", Function::One(|x| log2(x) * LN_2)),
+    ("log2", Function::One(log2)),
+    ("log10", Function::One(|x| log2(x) / LOG2_10)),
+    ("sqrt", Function::One(f64::sqrt)),
+    ("abs", Function::One(f64::abs)),
+    ("floor", Function::One(f64::floor)),
+    ("ceil", Function::One(f64::ceil)),
+    ("round", Function::One(f64::round)),
+    ("fract", Function::One(|x| x - x.floor())),
+    (
+        "sign",
+        Function::One(|x| if x == 0.0 { 0.0 } else { x.signum() }),
+    ),
+    ("min", Function::Two(f64::min)),
+    ("max", Function::Two(f64::max)),
+    ("pow", Function::Two(pow)),
+];
+
+const CONSTANTS: [(&str, f64); 3] = [("pi", PI), ("tau", TAU), ("e", E)];
+
+/// Deepest nesting of parentheses, signs, exponents and calls the parser accepts, so a
+/// hostile expression cannot overflow the stack.
+const MAX_NESTING: usize = 256;
+
+#[derive(Clone, Copy)]
+enum Function {
+    One(fn(f64) -> f64),
+    Two(fn(f64, f64) -> f64),
+}
+
+/// `base^exponent`: repeated multiplication for whole exponents, which also handles
+/// negative bases, and `2^(exponent · log2 base)` otherwise.
+fn pow(base: f64, exponent: f64) -> f64 {
+    if exponent.fract() == 0.0 && exponent.abs() <= 64.0 {
+        return base.powi(exponent as i32);
+    }
+    exp2(exponent * log2(base))
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Number(f64),
+    Time,
+    Frequency,
+    Negate,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+    Call(Function),
+}
+
+/// A parsed expression of `t`, the time in seconds, and `f`, the frequency of the note
+/// being played.
+pub struct Expression {
+    program: Vec<Op>,
+    /// Deepest the evaluation stack gets.
+    depth: usize,
+}
+
+impl Expression {
+    /// Parses `text`; errors point at the offending character within `span`.
+    pub fn parse(text: &str, span: Span) -> Result<Expression, LabeledError> {
+        let mut parser = Parser {
+            text,
+            at: 0,
+            program: Vec::new(),
+            nesting: 0,
+            span,
+        };
+        parser.sum()?;
+        parser.skip_whitespace();
+        if parser.at < text.len() {
+            return Err(parser.error("expected an operator"));
+        }
+        let mut depth: usize = 0;
+        let mut max_depth = 0;
+        for op in &parser.program {
+            depth = match op {
+                Op::Number(_) | Op::Time | Op::Frequency => depth + 1,
+                Op::Negate | Op::Call(Function::One(_)) => depth,
+                _ => depth - 1,
+            };
+            max_depth = max_depth.max(depth);
+        }
+        Ok(Expression {
+            program: parser.program,
+            depth: max_depth,
+        })
+    }
+
+    /// A stack big enough to evaluate this expression with.
+    pub fn stack(&self) -> Vec<f64> {
+        Vec::with_capacity(self.depth)
+    }
+
+    /// The value at time `t` for a note of frequency `f`.
+    pub fn eval(&self, t: f64, f: f64, stack: &mut Vec<f64>) -> f64 {
+        stack.clear();
+        for op in &self.program {
+            let value = match *op {
+                Op::Number(n) => n,
+                Op::Time => t,
+                Op::Frequency => f,
+                Op::Negate => -pop(stack),
+                Op::Call(Function::One(function)) => function(pop(stack)),
+                op => {
+                    let b = pop(stack);
+                    let a = pop(stack);
+                    match op {
+                        Op::Add => a + b,
+                        Op::Subtract => a - b,
+                        Op::Multiply => a * b,
+                        Op::Divide => a / b,
+                        Op::Remainder => a % b,
+                        Op::Power => pow(a, b),
+                        Op::Call(Function::Two(function)) => function(a, b),
+                        _ => unreachable!("unary operations are handled above"),
+                    }
+                }
+            };
+            stack.push(value);
+        }
+        pop(stack)
+    }
+}
+
+fn pop(stack: &mut Vec<f64>) -> f64 {
+    stack.pop().expect("the parser emits balanced programs")
+}
+
+/// Recursive-descent parser emitting postfix operations. Precedence from loosest:
+/// `+ -`, `* / %`, unary minus, `^` (right-associative, so `-2^2` is -4).
+struct Parser<'a> {
+    text: &'a str,
+    /// Byte offset of the next character.
+    at: usize,
+    program: Vec<Op>,
+    /// How many [`Parser::unary`] calls are under way.
+    nesting: usize,
+    span: Span,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> LabeledError {
+        let position = self.text[..self.at].chars().count() + 1;
+        LabeledError::new(format!("invalid --expr: {message}"))
+            .with_label(format!("at character {position}"), self.span)
+            .with_help("use t (seconds), f (the note's frequency), pi, tau, e, + - * / % ^ and sin, cos, tan, exp, ln, log2, log10, sqrt, abs, floor, ceil, round, fract, sign, min, max, pow")
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.at..];
+        self.at += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token` if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.text[self.at..].starts_with(token);
+        if found {
+            self.at += token.len();
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Result<(), LabeledError> {
+        self.product()?;
+        loop {
+            if self.eat("+") {
+                self.product()?;
+                self.program.push(Op::Add);
+            } else if self.eat("-") {
+                self.product()?;
+                self.program.push(Op::Subtract);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<(), LabeledError> {
+        self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                Op::Multiply
+            } else if self.eat("/") {
+                Op::Divide
+            } else if self.eat("%") {
+                Op::Remainder
+            } else {
+                return Ok(());
+            };
+            self.unary()?;
+            self.program.push(op);
+        }
+    }
+
+    /// Every level of nesting passes through here, which is where its depth is bounded.
+    fn unary(&mut self) -> Result<(), LabeledError> {
+        if self.nesting > MAX_NESTING {
+            return Err(self.error(&format!("nested more than {MAX_NESTING} levels deep")));
+        }
+        self.nesting += 1;
+        let result = self.signed();
+        self.nesting -= 1;
+        result
+    }
+
+    fn signed(&mut self) -> Result<(), LabeledError> {
+        if self.eat("-") {
+            self.unary()?;
+            self.program.push(Op::Negate);
+            return Ok(());
+        }
+        if self.eat("+") {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<(), LabeledError> {
+        self.atom()?;
+        if self.eat("^") || self.eat("**") {
+            self.unary()?;
+            self.program.push(Op::Power);
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self) -> Result<(), LabeledError> {
+        self.skip_whitespace();
+        let rest = &self.text[self.at..];
+        let Some(first) = rest.chars().next() else {
+            return Err(self.error("the expression ends early"));
+        };
+        if first.is_ascii_digit() || first == '.' {
+            return self.number();
+        }
+        if first == '(' {
+            self.at += 1;
+            self.sum()?;
+            if !self.eat(")") {
+                return Err(self.error("expected )"));
+            }
+            return Ok(());
+        }
+        if !first.is_ascii_alphabetic() {
+            return Err(self.error(&format!("unexpected '{first}'")));
+        }
+
+        let start = self.at;
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        let name = &rest[..len];
+        self.at += len;
+        match name {
+            "t" => self.program.push(Op::Time),
+            "f" => self.program.push(Op::Frequency),
+            _ => {
+                if let Some((_, value)) = CONSTANTS.iter().find(|(n, _)| *n == name) {
+                    self.program.push(Op::Number(*value));
+                    return Ok(());
+                }
+                let Some((_, function)) = FUNCTIONS.iter().find(|(n, _)| *n == name) else {
+                    self.at = start;
+                    return Err(self.error(&format!("unknown name '{name}'")));
+                };
+                if !self.eat("(") {
+                    return Err(self.error(&format!("expected ( after {name}")));
+                }
+                self.sum()?;
+                if let Function::Two(_) = function {
+                    if !self.eat(",") {
+                        return Err(self.error(&format!("{name} takes two arguments")));
+                    }
+                    self.sum()?;
+                }
+                if !self.eat(")") {
+                    return Err(self.error("expected )"));
+                }
+                self.program.push(Op::Call(*function));
+            }
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<(), LabeledError> {
+        let rest = &self.text[self.at..];
+        let bytes = rest.as_bytes();
+        let digits = |from: usize| {
+            bytes[from..]
+                .iter()
+                .position(|b| !b.is_ascii_digit())
+                .map_or(bytes.len(), |n| from + n)
+        };
+        let mut end = digits(0);
+        if bytes.get(end) == Some(&b'.') {
+            end = digits(end + 1);
+        }
+        // An exponent needs digits after the `e`; without them the number ends there.
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+            if bytes.get(end + 1 + sign).is_some_and(u8::is_ascii_digit) {
+                end = digits(end + 1 + sign);
+            }
+        }
+        let value = rest[..end]
+            .parse()
+            .map_err(|_| self.error(&format!("invalid number '{}'", &rest[..end])))?;
+        self.at += end;
+        self.program.push(Op::Number(value));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str, t: f64, f: f64) -> f64 {
+        let expression = Expression::parse(text, Span::test_data()).unwrap();
+        expression.eval(t, f, &mut expression.stack())
+    }
+
+    fn error(text: &str) -> (String, String) {
+        let error = Expression::parse(text, Span::test_data())
+            .err()
+            .expect("the expression should not parse");
+        (error.msg, error.labels[0].text.clone())
+    }
+
+    #[test]
+    fn follows_precedence_and_associativity() {
+        assert_eq!(eval("1 + 2 * 3", 0.0, 0.0), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", 0.0, 0.0), 9.0);
+        assert_eq!(eval("10 - 4 - 3", 0.0, 0.0), 3.0);
+        assert_eq!(eval("7 % 4 * 2", 0.0, 0.0), 6.0);
+        assert_eq!(eval("2 * 3 ^ 2", 0.0, 0.0), 18.0);
+        assert_eq!(eval("2 ^ 3 ^ 2", 0.0, 0.0), 512.0);
+        assert_eq!(eval("2 ** 3", 0.0, 0.0), 8.0);
+        assert_eq!(eval("-2 ^ 2", 0.0, 0.0), -4.0);
+        assert_eq!(eval("2 ^ -1", 0.0, 0.0), 0.5);
+        assert_eq!(eval("max(t, f) - min(t, f)", 2.0, 5.0), 3.0);
+        assert_eq!(eval("1.5e1 * t", 2.0, 0.0), 30.0);
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!(
+            error("sin(x)"),
+            (
+                "invalid --expr: unknown name 'x'".to_string(),
+                "at character 5".to_string()
+            )
+        );
+        assert_eq!(
+            error("2 * sine(t)"),
+            (
+                "invalid --expr: unknown name 'sine'".to_string(),
+                "at character 5".to_string()
+            )
+        );
+        assert_eq!(error("pi()").0, "invalid --expr: expected an operator");
+        assert_eq!(error("sqrt t").0, "invalid --expr: expected ( after sqrt");
+    }
+
+    #[test]
+    fn bounds_nesting_depth() {
+        let nested = |depth: usize| format!("{}t{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_NESTING - 1), 3.0, 0.0), 3.0);
+        let too_deep = format!("invalid --expr: nested more than {MAX_NESTING} levels deep");
+        assert_eq!(error(&nested(MAX_NESTING + 1)).0, too_deep);
+        assert_eq!(
+            error(&format!("{}1", "-".repeat(MAX_NESTING + 1))).0,
+            too_deep
+        );
+    }
+}
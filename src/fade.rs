@@ -0,0 +1,111 @@
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::LabeledError;
+use rodio::{source::SeekError, ChannelCount, SampleRate, Source};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{config::plugin_config_value, validate::{duration, duration_flag}};
+
+/// Fade length used for pause, resume, seek and quit when neither `--fade` nor the
+/// config's `fade` is set.
+const DEFAULT_FADE: Duration = Duration::from_millis(150);
+
+/// Resolves the fade length from `--fade`, then the plugin config's `fade`, then
+/// [`DEFAULT_FADE`]. `0sec` disables fading.
+pub fn fade_length(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+) -> Result<Duration, LabeledError> {
+    if let Some(length) = duration_flag(call, "fade")? {
+        return Ok(length);
+    }
+    Ok(match plugin_config_value(engine, "fade", call.head)? {
+        Some(value) => duration(&value, "fade").unwrap_or(DEFAULT_FADE),
+        None => DEFAULT_FADE,
+    })
+}
+
+/// Gain ramp at the end of the source chain, driven by a [`FadeHandle`].
+///
+/// Pausing, stopping or seeking a sink mid-waveform produces a click; fading to silence
+/// first and back afterwards makes those transitions inaudible.
+pub struct Fade<S: Source> {
+    input: S,
+    audible: Arc<AtomicBool>,
+    gain: f32,
+    step: f32,
+}
+
+/// Controls a [`Fade`] from the playback loop while the sink owns the source.
+pub struct FadeHandle {
+    audible: Arc<AtomicBool>,
+    length: Duration,
+}
+
+impl<S: Source> Fade<S> {
+    pub fn new(input: S, length: Duration) -> (Fade<S>, FadeHandle) {
+        let audible = Arc::new(AtomicBool::new(true));
+        let samples = length.as_secs_f32() * input.sample_rate() as f32 * input.channels() as f32;
+        let fade = Fade {
+            audible: Arc::clone(&audible),
+            gain: 1.0,
+            step: if samples >= 1.0 { 1.0 / samples } else { 1.0 },
+            input,
+        };
+        (fade, FadeHandle { audible, length })
+    }
+}
+
+impl FadeHandle {
+    /// Ramps to silence and blocks until the ramp has played out.
+    pub fn fade_out(&self) {
+        self.audible.store(false, Ordering::Relaxed);
+        std::thread::sleep(self.length);
+    }
+
+    /// Ramps back to full level; returns immediately.
+    pub fn fade_in(&self) {
+        self.audible.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<S: Source> Iterator for Fade<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        self.gain = if self.audible.load(Ordering::Relaxed) {
+            (self.gain + self.step).min(1.0)
+        } else {
+            (self.gain - self.step).max(0.0)
+        };
+        Some(sample * self.gain)
+    }
+}
+
+impl<S: Source> Source for Fade<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
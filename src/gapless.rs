@@ -0,0 +1,70 @@
+use lofty::{file::FileType, probe::Probe};
+use nu_protocol::{record, Span, Value};
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use crate::{audio_xing::read_xing, itunes::read_itunsmpb};
+
+/// Samples an MP3 decoder itself adds in front of the audio, on top of the delay the
+/// LAME tag records.
+const MP3_DECODER_DELAY: u32 = 529;
+
+/// Encoder delay and padding: the silent samples an MP3 or AAC encoder adds before and
+/// after the audio, which a gapless player removes after decoding.
+pub struct Gapless {
+    /// Samples per channel to drop from the start of the decoded stream.
+    pub encoder_delay: u32,
+    /// Samples per channel to drop from the end of the decoded stream.
+    pub padding: u32,
+    /// Where the values were read from: `lame` or `itunsmpb`.
+    pub source: &'static str,
+}
+
+impl Gapless {
+    pub fn into_value(self, span: Span) -> Value {
+        Value::record(
+            record! {
+                "encoder_delay" => Value::int(self.encoder_delay as i64, span),
+                "padding" => Value::int(self.padding as i64, span),
+                "source" => Value::string(self.source, span),
+            },
+            span,
+        )
+    }
+}
+
+/// Gapless info of the file at `path`: the LAME tag of an MP3, or else the `iTunSMPB`
+/// tag of an MP3 or MP4.
+pub fn read_gapless(path: &Path) -> Option<Gapless> {
+    let file_type = Probe::open(path)
+        .ok()?
+        .guess_file_type()
+        .ok()?
+        .file_type()?;
+    if file_type == FileType::Mpeg {
+        if let Some(gapless) = File::open(path).ok().and_then(|mut f| lame_gapless(&mut f)) {
+            return Some(gapless);
+        }
+    }
+    let smpb = read_itunsmpb(path, file_type)?;
+    Some(Gapless {
+        encoder_delay: smpb.encoder_delay,
+        padding: smpb.padding,
+        source: "itunsmpb",
+    })
+}
+
+/// Reads the encoder delay and padding from the LAME extension of an MP3's
+/// `Xing`/`Info` header. The values are converted to what has to be trimmed from the
+/// decoded stream, the way gapless decoders apply them.
+pub fn lame_gapless<R: Read + Seek>(reader: &mut R) -> Option<Gapless> {
+    let (delay, padding) = read_xing(reader)?.lame()?.delay_and_padding();
+    Some(Gapless {
+        encoder_delay: MP3_DECODER_DELAY + delay,
+        padding: padding.saturating_sub(MP3_DECODER_DELAY),
+        source: "lame",
+    })
+}
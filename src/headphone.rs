@@ -0,0 +1,231 @@
+use rodio::{source::SeekError, ChannelCount, SampleRate, Source};
+use std::time::Duration;
+
+use crate::{channel_mix::stereo_weights, dsp::OnePole};
+
+/// Bauer crossfeed corner frequency and level, the bs2b "default" preset.
+const CROSSFEED_CUTOFF: f64 = 700.0;
+const CROSSFEED_LEVEL_DB: f32 = -4.5;
+/// Spherical head model constants for the binaural renderer.
+const HEAD_RADIUS_M: f32 = 0.0875;
+const SPEED_OF_SOUND: f32 = 343.0;
+/// Far-ear head-shadow cutoff for a source directly to one side.
+const SHADOW_MIN_CUTOFF: f64 = 1_500.0;
+const SHADOW_MAX_CUTOFF: f64 = 20_000.0;
+/// Pinna shadowing for sources behind the listener.
+const REAR_CUTOFF: f64 = 8_000.0;
+
+/// Bauer-style stereophonic-to-binaural crossfeed for headphone listening.
+///
+/// Each ear receives the opposite channel low-passed at 700 Hz and 4.5 dB down, while
+/// the direct channel's treble is lifted by the same amount, so centered (mono) content
+/// keeps a flat response and only the exaggerated headphone separation is reduced.
+/// Sources that are not stereo pass through unchanged.
+pub struct Crossfeed<S: Source> {
+    input: S,
+    gain: f32,
+    filters: [OnePole; 2],
+    pending: Option<f32>,
+}
+
+impl<S: Source> Crossfeed<S> {
+    pub fn new(input: S) -> Crossfeed<S> {
+        let filter = OnePole::low_pass(CROSSFEED_CUTOFF, input.sample_rate());
+        Crossfeed {
+            gain: 10f32.powf(CROSSFEED_LEVEL_DB / 20.0),
+            filters: [filter; 2],
+            pending: None,
+            input,
+        }
+    }
+}
+
+impl<S: Source> Iterator for Crossfeed<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.input.channels() != 2 {
+            return self.input.next();
+        }
+        if let Some(right) = self.pending.take() {
+            return Some(right);
+        }
+        let (left, right) = (self.input.next()?, self.input.next()?);
+        let low = [
+            self.filters[0].process(left),
+            self.filters[1].process(right),
+        ];
+        let g = self.gain;
+        let out_left = (low[0] + (1.0 + g) * (left - low[0]) + g * low[1]) / (1.0 + g);
+        let out_right = (low[1] + (1.0 + g) * (right - low[1]) + g * low[0]) / (1.0 + g);
+        self.pending = Some(out_right);
+        Some(out_left)
+    }
+}
+
+impl<S: Source> Source for Crossfeed<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.filters.iter_mut().for_each(OnePole::reset);
+        self.pending = None;
+        self.input.try_seek(pos)
+    }
+}
+
+/// One input channel placed as a virtual speaker around the listener.
+struct VirtualSpeaker {
+    weight: f32,
+    /// Ear closer to the speaker: 0 = left, 1 = right.
+    near: usize,
+    /// Interaural time difference in frames, applied to the far ear.
+    delay: usize,
+    shadow: OnePole,
+    rear: Option<OnePole>,
+}
+
+/// Renders multichannel audio as two-channel binaural audio for headphones.
+///
+/// Every channel becomes a virtual speaker at its standard azimuth and reaches the far
+/// ear through a spherical head model: delayed by the Woodworth interaural time
+/// difference and low-passed by head shadow that deepens toward the side. Speakers
+/// behind the listener are also dulled slightly. Channel weights follow the stereo
+/// downmix (LFE dropped) and are normalized so the output cannot clip.
+pub struct Binaural<S: Source> {
+    input: S,
+    speakers: Vec<VirtualSpeaker>,
+    /// Per-channel ring buffers long enough for the largest ITD.
+    history: Vec<Vec<f32>>,
+    cursor: usize,
+    norm: f32,
+    pending: Option<f32>,
+}
+
+/// Standard azimuths in degrees (negative = left) for the WAVE channel orders handled by
+/// [`stereo_weights`].
+fn speaker_azimuths(channels: u16) -> Option<&'static [f32]> {
+    Some(match channels {
+        2 => &[-30.0, 30.0],
+        3 => &[-30.0, 30.0, 0.0],
+        4 => &[-45.0, 45.0, -135.0, 135.0],
+        5 => &[-30.0, 30.0, 0.0, -110.0, 110.0],
+        6 => &[-30.0, 30.0, 0.0, 0.0, -110.0, 110.0],
+        8 => &[-30.0, 30.0, 0.0, 0.0, -150.0, 150.0, -90.0, 90.0],
+        _ => return None,
+    })
+}
+
+impl<S: Source> Binaural<S> {
+    /// Returns the input unchanged (as `Err`) when its layout has no known speaker
+    /// positions, e.g. mono.
+    pub fn new(input: S) -> Result<Binaural<S>, S> {
+        let channels = input.channels();
+        let (Some(azimuths), Some(weights)) =
+            (speaker_azimuths(channels), stereo_weights(channels))
+        else {
+            return Err(input);
+        };
+        let rate = input.sample_rate();
+
+        let speakers: Vec<VirtualSpeaker> = azimuths
+            .iter()
+            .zip(weights)
+            .map(|(azimuth, (wl, wr))| {
+                let radians = azimuth.to_radians();
+                // Lateral angle: front and back positions with the same sine are
+                // indistinguishable to a spherical head.
+                let lateral = radians.sin().asin().abs();
+                let itd = HEAD_RADIUS_M / SPEED_OF_SOUND * (lateral + lateral.sin());
+                let side = lateral as f64 / std::f64::consts::FRAC_PI_2;
+                let cutoff = SHADOW_MAX_CUTOFF * (SHADOW_MIN_CUTOFF / SHADOW_MAX_CUTOFF).powf(side);
+                VirtualSpeaker {
+                    weight: wl.max(wr),
+                    near: usize::from(*azimuth > 0.0),
+                    delay: (itd * rate as f32).round() as usize,
+                    shadow: OnePole::low_pass(cutoff, rate),
+                    rear: (azimuth.abs() > 90.0).then(|| OnePole::low_pass(REAR_CUTOFF, rate)),
+                }
+            })
+            .collect();
+
+        let depth = speakers.iter().map(|s| s.delay).max().unwrap_or(0) + 1;
+        let norm = speakers.iter().map(|s| s.weight).sum::<f32>().max(1.0);
+        Ok(Binaural {
+            history: vec![vec![0.0; depth]; speakers.len()],
+            speakers,
+            cursor: 0,
+            norm,
+            pending: None,
+            input,
+        })
+    }
+}
+
+impl<S: Source> Iterator for Binaural<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.pending.take() {
+            return Some(right);
+        }
+        let depth = self.history[0].len();
+        let mut ears = [0.0f32; 2];
+        for (speaker, history) in self.speakers.iter_mut().zip(&mut self.history) {
+            let mut sample = self.input.next()? * speaker.weight;
+            if let Some(rear) = &mut speaker.rear {
+                sample = rear.process(sample);
+            }
+            history[self.cursor] = sample;
+            let delayed = history[(self.cursor + depth - speaker.delay) % depth];
+            ears[speaker.near] += sample;
+            ears[1 - speaker.near] += speaker.shadow.process(delayed);
+        }
+        self.cursor = (self.cursor + 1) % depth;
+        self.pending = Some(ears[1] / self.norm);
+        Some(ears[0] / self.norm)
+    }
+}
+
+impl<S: Source> Source for Binaural<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        2
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        for speaker in &mut self.speakers {
+            speaker.shadow.reset();
+            if let Some(rear) = &mut speaker.rear {
+                rear.reset();
+            }
+        }
+        self.history.iter_mut().for_each(|h| h.fill(0.0));
+        self.pending = None;
+        self.input.try_seek(pos)
+    }
+}
@@ -0,0 +1,243 @@
+// Recorded voices for the sequencer: a sample pitch-shifted to each note, or one cycle
+// of a waveform looped at the note's frequency.
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Signature, SyntaxShape, Value};
+use rodio::{source::SeekError, ChannelCount, SampleRate, Source};
+use std::{fs::File, sync::Arc, time::Duration};
+
+use crate::{
+    pcm::Pcm,
+    sound_make::{note_frequency, parse_frequency, TONE_RATE},
+    utils::{flag_span, path_value, resolve_filepath},
+};
+
+/// Longest `--wavetable` cycle, in samples.
+const MAX_CYCLE: usize = 65_536;
+
+/// Note a `--sample` sounds at when played back unchanged, unless `--root` says
+/// otherwise: middle C, the usual root of sampler instruments.
+const DEFAULT_ROOT: &str = "c4";
+
+/// Adds `--sample`, `--wavetable` and `--root`.
+pub fn instrument_flags(signature: Signature) -> Signature {
+    signature
+        .named(
+            "sample",
+            SyntaxShape::Filepath,
+            "audio file to play as the voice, sped up or slowed down to each note's pitch",
+            None,
+        )
+        .named(
+            "wavetable",
+            SyntaxShape::Filepath,
+            "audio file holding one cycle of a waveform, looped at each note's pitch",
+            None,
+        )
+        .named(
+            "root",
+            SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::String]),
+            "note or frequency the --sample was recorded at (default C4)",
+            None,
+        )
+}
+
+/// A voice read from an audio file, mixed down to mono.
+pub enum Instrument {
+    /// A recording, played faster or slower to change its pitch the way a sampler does.
+    /// Notes longer than the recording are silent after it ends.
+    Sample {
+        samples: Vec<f32>,
+        sample_rate: u32,
+        /// Frequency the recording sounds at when played back unchanged.
+        root: f64,
+    },
+    /// One cycle of a waveform.
+    Wavetable(Vec<f32>),
+}
+
+impl Instrument {
+    /// The instrument the flags ask for, if any.
+    pub fn from_call(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<Option<Instrument>, LabeledError> {
+        let sample = call.get_flag_value("sample");
+        let wavetable = call.get_flag_value("wavetable");
+        if sample.is_some() && wavetable.is_some() {
+            return Err(
+                LabeledError::new("--sample cannot be combined with --wavetable")
+                    .with_label("pick one voice", flag_span(call, "wavetable")),
+            );
+        }
+        if sample.is_some() || wavetable.is_some() {
+            if let Some(flag) = ["waveform", "duty"]
+                .into_iter()
+                .find(|flag| call.get_flag_value(flag).is_some())
+            {
+                return Err(LabeledError::new(format!(
+                    "--{flag} cannot be combined with a recorded voice"
+                ))
+                .with_label("the file sets the timbre", flag_span(call, flag)));
+            }
+        }
+        if let Some(file) = sample {
+            return Instrument::sample(engine, file, call.get_flag_value("root")).map(Some);
+        }
+        if let Some(root) = call.get_flag_value("root") {
+            return Err(LabeledError::new("--root needs --sample")
+                .with_label("only a sample has a recorded pitch", root.span()));
+        }
+        let Some(file) = wavetable else {
+            return Ok(None);
+        };
+        let span = file.span();
+        let cycle = decode(engine, file)?.mono();
+        if cycle.is_empty() || cycle.len() > MAX_CYCLE {
+            return Err(LabeledError::new("invalid wavetable")
+                .with_label(
+                    format!(
+                        "the file has {} samples, one cycle must have 1 to {MAX_CYCLE}",
+                        cycle.len()
+                    ),
+                    span,
+                )
+                .with_help("export a single cycle of the waveform, e.g. 2048 samples long"));
+        }
+        Ok(Some(Instrument::Wavetable(cycle)))
+    }
+
+    /// The sample in `file`, recorded at the note or frequency `root` (default C4).
+    pub fn sample(
+        engine: &EngineInterface,
+        file: Value,
+        root: Option<Value>,
+    ) -> Result<Instrument, LabeledError> {
+        let root = match root {
+            Some(root) => root_frequency(&root)?,
+            None => note_frequency(DEFAULT_ROOT).expect("the default root is a note"),
+        };
+        let pcm = decode(engine, file)?;
+        Ok(Instrument::Sample {
+            samples: pcm.mono(),
+            sample_rate: pcm.sample_rate,
+            root,
+        })
+    }
+
+    /// How long a sample lasts played at `frequency`; `None` for a wavetable, which
+    /// loops for as long as it is played.
+    pub fn length(&self, frequency: f32) -> Option<Duration> {
+        match self {
+            Instrument::Sample {
+                samples,
+                sample_rate,
+                root,
+            } => Some(Duration::from_secs_f64(
+                samples.len() as f64 / *sample_rate as f64 * root / frequency as f64,
+            )),
+            Instrument::Wavetable(_) => None,
+        }
+    }
+}
+
+fn decode(engine: &EngineInterface, file: Value) -> Result<Pcm, LabeledError> {
+    let span = file.span();
+    let path = resolve_filepath(engine, span, path_value(file)?)?;
+    let file = File::open(&path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error trying to open the file", span)
+    })?;
+    Pcm::decode(file, span)
+}
+
+fn root_frequency(value: &Value) -> Result<f64, LabeledError> {
+    let root = parse_frequency(value)?;
+    if !(root.is_finite() && root > 0.0) {
+        return Err(LabeledError::new("invalid --root")
+            .with_label("must be a positive frequency or a note name", value.span())
+            .with_help("e.g. C4 or 261.63"));
+    }
+    Ok(root)
+}
+
+/// Endless mono voice at [`TONE_RATE`] playing `instrument` at `frequency`, with linear
+/// interpolation between the file's samples.
+pub struct Voice {
+    instrument: Arc<Instrument>,
+    /// Position in the file's samples.
+    position: f64,
+    /// Samples of the file to advance per output sample.
+    step: f64,
+}
+
+impl Voice {
+    pub fn new(instrument: Arc<Instrument>, frequency: f32) -> Voice {
+        let step = match &*instrument {
+            Instrument::Sample {
+                sample_rate, root, ..
+            } => frequency as f64 / root * *sample_rate as f64 / TONE_RATE as f64,
+            Instrument::Wavetable(cycle) => {
+                frequency as f64 * cycle.len() as f64 / TONE_RATE as f64
+            }
+        };
+        Voice {
+            instrument,
+            position: 0.0,
+            step,
+        }
+    }
+}
+
+impl Iterator for Voice {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let index = self.position as usize;
+        let frac = (self.position - index as f64) as f32;
+        let sample = match &*self.instrument {
+            Instrument::Sample { samples, .. } => match samples.get(index) {
+                Some(&a) => {
+                    let b = samples.get(index + 1).copied().unwrap_or(0.0);
+                    a + (b - a) * frac
+                }
+                None => 0.0,
+            },
+            Instrument::Wavetable(cycle) => {
+                let a = cycle[index];
+                let b = cycle[(index + 1) % cycle.len()];
+                a + (b - a) * frac
+            }
+        };
+        self.position += self.step;
+        if let Instrument::Wavetable(cycle) = &*self.instrument {
+            self.position %= cycle.len() as f64;
+        }
+        Some(sample)
+    }
+}
+
+impl Source for Voice {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        1
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        TONE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.position = pos.as_secs_f64() * TONE_RATE as f64 * self.step;
+        if let Instrument::Wavetable(cycle) = &*self.instrument {
+            self.position %= cycle.len() as f64;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use lofty::config::ParseOptions;
+use lofty::file::{AudioFile, FileType};
+use lofty::id3::v2::{Frame, Id3v2Tag};
+use lofty::mp4::{AdvisoryRating, AtomData, AtomIdent, Ilst, Mp4File};
+use lofty::mpeg::MpegFile;
+use nu_protocol::{Record, Span, Value};
+use std::path::Path;
+
+/// Gapless playback info stored by iTunes in the `iTunSMPB` comment / freeform atom.
+pub struct ITunSmpb {
+    /// Priming samples the encoder inserted before the audio.
+    pub encoder_delay: u32,
+    /// Padding samples appended after the audio.
+    pub padding: u32,
+    /// Length of the original audio in samples, excluding delay and padding.
+    pub original_samples: u64,
+}
+
+/// Parses an `iTunSMPB` value such as
+/// `" 00000000 00000840 000001CA 00000000003F31F6 ..."` (space-separated hex fields:
+/// reserved, encoder delay, padding, original sample count).
+pub fn parse_itunsmpb(value: &str) -> Option<ITunSmpb> {
+    let mut fields = value.split_whitespace().skip(1);
+    let encoder_delay = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let padding = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let original_samples = u64::from_str_radix(fields.next()?, 16).ok()?;
+    Some(ITunSmpb {
+        encoder_delay,
+        padding,
+        original_samples,
+    })
+}
+
+/// Reads just the `iTunSMPB` gapless info of an MP4 or MPEG file.
+pub fn read_itunsmpb(path: &Path, file_type: FileType) -> Option<ITunSmpb> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let options = ParseOptions::new().read_properties(false);
+    let raw = match file_type {
+        FileType::Mp4 => {
+            let mp4 = Mp4File::read_from(&mut file, options).ok()?;
+            atom_text(mp4.ilst()?, &smpb_atom())
+        }
+        FileType::Mpeg => {
+            let mpeg = MpegFile::read_from(&mut file, options).ok()?;
+            id3v2_smpb(mpeg.id3v2()?)
+        }
+        _ => None,
+    }?;
+    parse_itunsmpb(&raw)
+}
+
+/// Reads iTunes-specific fields (advisory rating, purchase account, gapless flag, and
+/// `iTunSMPB` gapless info) from MP4 atoms or ID3v2 frames.
+///
+/// Returns `None` when the file is neither MP4 nor MPEG or carries none of the fields.
+pub fn read_itunes_fields(path: &Path, file_type: FileType, span: Span) -> Option<Record> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let options = ParseOptions::new().read_properties(false);
+    let mut record = Record::new();
+
+    let smpb = match file_type {
+        FileType::Mp4 => {
+            let mp4 = Mp4File::read_from(&mut file, options).ok()?;
+            let ilst = mp4.ilst()?;
+            if let Some(rating) = ilst.advisory_rating() {
+                record.push("advisory", Value::string(advisory_name(rating), span));
+            }
+            if let Some(account) = atom_text(ilst, &AtomIdent::Fourcc(*b"apID")) {
+                record.push("account", Value::string(account, span));
+            }
+            if ilst.get(&AtomIdent::Fourcc(*b"pgap")).is_some() {
+                record.push("gapless", Value::bool(ilst.is_gapless(), span));
+            }
+            atom_text(ilst, &smpb_atom())
+        }
+        FileType::Mpeg => {
+            let mpeg = MpegFile::read_from(&mut file, options).ok()?;
+            let id3v2 = mpeg.id3v2()?;
+            if let Some(rating) = id3v2.get_user_text("ITUNESADVISORY") {
+                let name = rating
+                    .parse::<u8>()
+                    .ok()
+                    .and_then(|r| AdvisoryRating::try_from(r).ok())
+                    .map(advisory_name)
+                    .unwrap_or(rating);
+                record.push("advisory", Value::string(name, span));
+            }
+            if let Some(gapless) = id3v2.get_user_text("ITUNPGAP") {
+                record.push("gapless", Value::bool(gapless.trim() == "1", span));
+            }
+            id3v2_smpb(id3v2)
+        }
+        _ => None,
+    };
+
+    if let Some(raw) = smpb {
+        if let Some(info) = parse_itunsmpb(&raw) {
+            record.push("encoder_delay", Value::int(info.encoder_delay as i64, span));
+            record.push("padding", Value::int(info.padding as i64, span));
+            record.push("original_samples", Value::int(info.original_samples as i64, span));
+        }
+        record.push("itunsmpb", Value::string(raw.trim(), span));
+    }
+
+    (!record.is_empty()).then_some(record)
+}
+
+fn advisory_name(rating: AdvisoryRating) -> &'static str {
+    match rating {
+        AdvisoryRating::Inoffensive => "inoffensive",
+        AdvisoryRating::Explicit => "explicit",
+        AdvisoryRating::Clean => "clean",
+    }
+}
+
+fn smpb_atom() -> AtomIdent<'static> {
+    AtomIdent::Freeform {
+        mean: "com.apple.iTunes".into(),
+        name: "iTunSMPB".into(),
+    }
+}
+
+fn atom_text(ilst: &Ilst, ident: &AtomIdent<'_>) -> Option<String> {
+    ilst.get(ident)?.data().find_map(|data| match data {
+        AtomData::UTF8(s) | AtomData::UTF16(s) => Some(s.clone()),
+        _ => None,
+    })
+}
+
+/// iTunes stores `iTunSMPB` in a COMM frame in MP3s; some taggers use TXXX instead.
+fn id3v2_smpb(tag: &Id3v2Tag) -> Option<String> {
+    tag.into_iter().find_map(|frame| match frame {
+        Frame::Comment(c) if c.description.eq_ignore_ascii_case("iTunSMPB") => Some(c.content.to_string()),
+        Frame::UserText(t) if t.description.eq_ignore_ascii_case("iTunSMPB") => Some(t.content.to_string()),
+        _ => None,
+    })
+}
@@ -0,0 +1,1451 @@
+// JPEG decoding (baseline and progressive, Huffman-coded, 8-bit) and baseline encoding,
+// enough to shrink and convert cover art.
+
+use std::f32::consts::PI;
+
+use crate::raster::{Raster, MAX_PIXELS};
+
+/// Natural (row-major) index of each coefficient, in the zigzag order of the stream.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Code length up to which Huffman codes are decoded with one table lookup.
+const FAST_BITS: usize = 9;
+
+/// Luminance quantization table from the JPEG standard (annex K), in natural order.
+const LUMA_QUANT: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+/// Chrominance quantization table from the JPEG standard (annex K), in natural order.
+const CHROMA_QUANT: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+/// Huffman tables from the JPEG standard (annex K): code counts per length, then symbols.
+const LUMA_DC: ([u8; 16], &[u8]) = (
+    [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+    &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+);
+const CHROMA_DC: ([u8; 16], &[u8]) = (
+    [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
+    &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+);
+const LUMA_AC: ([u8; 16], &[u8]) = (
+    [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d],
+    &[
+        0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61,
+        0x07, 0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52,
+        0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25,
+        0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45,
+        0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64,
+        0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83,
+        0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99,
+        0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6,
+        0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3,
+        0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8,
+        0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ],
+);
+const CHROMA_AC: ([u8; 16], &[u8]) = (
+    [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77],
+    &[
+        0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61,
+        0x71, 0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33,
+        0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18,
+        0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44,
+        0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63,
+        0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a,
+        0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97,
+        0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4,
+        0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca,
+        0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7,
+        0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ],
+);
+
+/// `COS[x][u]`: weight of frequency `u` at sample `x` in the 8-point DCT, scaled so one
+/// pass each way gives the JPEG (I)DCT.
+fn cos_table() -> [[f32; 8]; 8] {
+    let mut table = [[0.0; 8]; 8];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, weight) in row.iter_mut().enumerate() {
+            let c = if u == 0 {
+                std::f32::consts::FRAC_1_SQRT_2
+            } else {
+                1.0
+            };
+            *weight = c / 2.0 * ((2 * x + 1) as f32 * u as f32 * PI / 16.0).cos();
+        }
+    }
+    table
+}
+
+// ---------------------------------------------------------------------------
+// Decoding
+// ---------------------------------------------------------------------------
+
+/// Decodes a JPEG image, turned upright by its EXIF orientation.
+pub fn decode(data: &[u8]) -> Result<Raster, String> {
+    let mut quant = [[1u16; 64]; 4];
+    let mut dc: [Option<Huffman>; 4] = Default::default();
+    let mut ac: [Option<Huffman>; 4] = Default::default();
+    let mut frame: Option<Frame> = None;
+    let mut restart_interval = 0;
+    let mut adobe_transform = None;
+    let mut orientation = 1;
+    let mut scanned = false;
+
+    let mut pos = 2;
+    loop {
+        // Skip fill bytes and anything a broken encoder left between segments.
+        while pos + 1 < data.len() && (data[pos] != 0xFF || matches!(data[pos + 1], 0 | 0xFF)) {
+            pos += 1;
+        }
+        if pos + 1 >= data.len() {
+            break;
+        }
+        let marker = data[pos + 1];
+        pos += 2;
+        match marker {
+            0xD9 => break,
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+        let length = data
+            .get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+            .filter(|&length| length >= 2)
+            .ok_or("truncated JPEG")?;
+        let body = data.get(pos + 2..pos + length).ok_or("truncated JPEG")?;
+        pos += length;
+        match marker {
+            0xDB => read_quant(body, &mut quant)?,
+            0xC4 => read_huffman(body, &mut dc, &mut ac)?,
+            0xC0..=0xC2 => frame = Some(Frame::new(body, marker == 0xC2)?),
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                return Err(
+                    "lossless, hierarchical and arithmetic-coded JPEGs are not supported".into(),
+                )
+            }
+            0xDD => {
+                restart_interval = body
+                    .get(..2)
+                    .map_or(0, |b| u16::from_be_bytes([b[0], b[1]]) as usize)
+            }
+            0xE1 if body.starts_with(b"Exif\0\0") => {
+                orientation = exif_orientation(&body[6..]).unwrap_or(1)
+            }
+            0xEE if body.starts_with(b"Adobe") && body.len() >= 12 => {
+                adobe_transform = Some(body[11])
+            }
+            0xDA => {
+                let frame = frame.as_mut().ok_or("JPEG scan before the frame header")?;
+                let scan = Scan::new(body, frame, &dc, &ac)?;
+                pos = frame.decode_scan(data, pos, &scan, restart_interval)?;
+                scanned = true;
+            }
+            _ => {}
+        }
+    }
+
+    let frame = frame.ok_or("no image in the JPEG")?;
+    if !scanned {
+        return Err("no image data in the JPEG".into());
+    }
+    Ok(frame.render(&quant, adobe_transform).oriented(orientation))
+}
+
+fn read_quant(mut body: &[u8], quant: &mut [[u16; 64]; 4]) -> Result<(), String> {
+    while let Some(&info) = body.first() {
+        let (wide, id) = (info >> 4 != 0, (info & 15) as usize);
+        let size = if wide { 128 } else { 64 };
+        let values = body
+            .get(1..1 + size)
+            .ok_or("truncated JPEG quantization table")?;
+        let table = quant.get_mut(id).ok_or("invalid JPEG quantization table")?;
+        for (k, entry) in table.iter_mut().enumerate() {
+            *entry = if wide {
+                u16::from_be_bytes([values[2 * k], values[2 * k + 1]])
+            } else {
+                values[k] as u16
+            };
+        }
+        body = &body[1 + size..];
+    }
+    Ok(())
+}
+
+fn read_huffman(
+    mut body: &[u8],
+    dc: &mut [Option<Huffman>; 4],
+    ac: &mut [Option<Huffman>; 4],
+) -> Result<(), String> {
+    while body.len() >= 17 {
+        let (class, id) = (body[0] >> 4, (body[0] & 15) as usize);
+        let counts: [u8; 16] = body[1..17].try_into().expect("16 bytes");
+        let total: usize = counts.iter().map(|&count| count as usize).sum();
+        let values = body
+            .get(17..17 + total)
+            .ok_or("truncated JPEG Huffman table")?;
+        let table = match class {
+            0 => dc.get_mut(id),
+            1 => ac.get_mut(id),
+            _ => None,
+        };
+        *table.ok_or("invalid JPEG Huffman table")? = Some(Huffman::new(&counts, values)?);
+        body = &body[17 + total..];
+    }
+    Ok(())
+}
+
+/// Orientation tag of an EXIF block (1 = upright, through 8), if it has one.
+fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |i: usize| {
+        let b = tiff.get(i..i + 2)?;
+        Some(if big_endian {
+            u16::from_be_bytes([b[0], b[1]])
+        } else {
+            u16::from_le_bytes([b[0], b[1]])
+        })
+    };
+    let u32_at = |i: usize| {
+        let b: [u8; 4] = tiff.get(i..i + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    };
+    let ifd = u32_at(4)? as usize;
+    (0..u16_at(ifd)? as usize)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// A Huffman table, decoded with one lookup for short codes and by length for long ones.
+struct Huffman {
+    /// Code length and symbol for each [`FAST_BITS`]-bit prefix, or 0 for longer codes.
+    fast: Vec<u16>,
+    /// Largest code of each length, or -1 when there is none.
+    maxcode: [i32; 17],
+    mincode: [i32; 17],
+    /// Index in `values` of the first code of each length.
+    first: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8; 16], values: &[u8]) -> Result<Huffman, String> {
+        let mut table = Huffman {
+            fast: vec![0; 1 << FAST_BITS],
+            maxcode: [-1; 17],
+            mincode: [0; 17],
+            first: [0; 17],
+            values: values.to_vec(),
+        };
+        let (mut code, mut k) = (0usize, 0usize);
+        for len in 1..=16 {
+            table.first[len] = k as i32;
+            table.mincode[len] = code as i32;
+            for _ in 0..counts[len - 1] {
+                if code >= 1 << len || k >= values.len() {
+                    return Err("invalid JPEG Huffman table".into());
+                }
+                if len <= FAST_BITS {
+                    let shift = FAST_BITS - len;
+                    let entry = (len as u16) << 8 | values[k] as u16;
+                    table.fast[code << shift..(code + 1) << shift].fill(entry);
+                }
+                code += 1;
+                k += 1;
+            }
+            table.maxcode[len] = code as i32 - 1;
+            if counts[len - 1] == 0 {
+                table.maxcode[len] = -1;
+            }
+            code <<= 1;
+        }
+        Ok(table)
+    }
+}
+
+/// Reads the entropy-coded data of a scan, removing stuffed zero bytes and stopping at
+/// the next marker.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Unread bits, most significant first.
+    buffer: u32,
+    count: u32,
+    /// Whether a marker was reached; zeros are read from there on.
+    marker: bool,
+}
+
+impl Bits<'_> {
+    fn fill(&mut self) {
+        while self.count <= 24 {
+            let mut byte = 0;
+            if !self.marker && self.pos < self.data.len() {
+                byte = self.data[self.pos];
+                if byte != 0xFF {
+                    self.pos += 1;
+                } else if self.data.get(self.pos + 1) == Some(&0) {
+                    self.pos += 2;
+                } else {
+                    self.marker = true;
+                    byte = 0;
+                }
+            }
+            self.buffer |= (byte as u32) << (24 - self.count);
+            self.count += 8;
+        }
+    }
+
+    fn bits(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        self.fill();
+        let value = self.buffer >> (32 - n);
+        self.buffer <<= n;
+        self.count -= n;
+        value
+    }
+
+    fn bit(&mut self) -> bool {
+        self.bits(1) == 1
+    }
+
+    /// A coefficient of `size` bits, with the sign encoding of the JPEG standard.
+    fn extend(&mut self, size: u8) -> Result<i32, String> {
+        if size > 16 {
+            return Err("corrupt JPEG data".into());
+        }
+        let value = self.bits(size as u32) as i32;
+        Ok(if size > 0 && value < 1 << (size - 1) {
+            value - (1 << size) + 1
+        } else {
+            value
+        })
+    }
+
+    fn decode(&mut self, table: &Huffman) -> Result<u8, String> {
+        self.fill();
+        let fast = table.fast[(self.buffer >> (32 - FAST_BITS)) as usize];
+        if fast != 0 {
+            let len = (fast >> 8) as u32;
+            self.buffer <<= len;
+            self.count -= len;
+            return Ok(fast as u8);
+        }
+        for len in FAST_BITS + 1..=16 {
+            let code = (self.buffer >> (32 - len)) as i32;
+            if code <= table.maxcode[len] {
+                self.buffer <<= len;
+                self.count -= len as u32;
+                let index = table.first[len] + code - table.mincode[len];
+                return table
+                    .values
+                    .get(index as usize)
+                    .copied()
+                    .ok_or_else(|| "corrupt JPEG data".into());
+            }
+        }
+        Err("corrupt JPEG data".into())
+    }
+
+    /// Moves past a restart marker and starts over on a byte boundary.
+    fn restart(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+        self.marker = false;
+        let mut pos = self.pos;
+        while self.data.get(pos) == Some(&0xFF) {
+            pos += 1;
+        }
+        if pos > self.pos && matches!(self.data.get(pos), Some(0xD0..=0xD7)) {
+            self.pos = pos + 1;
+        }
+    }
+}
+
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    /// Blocks per line and per column, padded to whole MCUs.
+    blocks_w: usize,
+    blocks_h: usize,
+    /// Quantized coefficients of every block, 64 per block in zigzag order.
+    coefs: Vec<i16>,
+}
+
+struct Frame {
+    width: usize,
+    height: usize,
+    progressive: bool,
+    h_max: usize,
+    v_max: usize,
+    mcus_w: usize,
+    mcus_h: usize,
+    components: Vec<Component>,
+}
+
+/// The header of one scan: which components it codes and which coefficients.
+struct Scan<'a> {
+    /// Index in the frame's components, DC table and AC table of each component.
+    components: Vec<(usize, Option<&'a Huffman>, Option<&'a Huffman>)>,
+    start: usize,
+    end: usize,
+    high: u8,
+    low: u8,
+}
+
+impl Frame {
+    fn new(body: &[u8], progressive: bool) -> Result<Frame, String> {
+        if body.len() < 6 {
+            return Err("truncated JPEG frame header".into());
+        }
+        if body[0] != 8 {
+            return Err("only 8-bit JPEGs are supported".into());
+        }
+        let height = u16::from_be_bytes([body[1], body[2]]) as usize;
+        let width = u16::from_be_bytes([body[3], body[4]]) as usize;
+        let count = body[5] as usize;
+        if width == 0 || height == 0 {
+            return Err("JPEG without a size".into());
+        }
+        if width * height > MAX_PIXELS {
+            return Err(format!("{width}x{height} pixels is too large"));
+        }
+        if count != 1 && count != 3 {
+            return Err("only grayscale and color JPEGs are supported, not CMYK".into());
+        }
+        let mut components = Vec::with_capacity(count);
+        for spec in body[6..].chunks_exact(3).take(count) {
+            let (h, v) = ((spec[1] >> 4) as usize, (spec[1] & 15) as usize);
+            if !(1..=4).contains(&h) || !(1..=4).contains(&v) || spec[2] > 3 {
+                return Err("invalid JPEG frame header".into());
+            }
+            components.push(Component {
+                id: spec[0],
+                h,
+                v,
+                quant: spec[2] as usize,
+                blocks_w: 0,
+                blocks_h: 0,
+                coefs: Vec::new(),
+            });
+        }
+        if components.len() != count {
+            return Err("truncated JPEG frame header".into());
+        }
+        let h_max = components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = components.iter().map(|c| c.v).max().unwrap_or(1);
+        let mcus_w = width.div_ceil(8 * h_max);
+        let mcus_h = height.div_ceil(8 * v_max);
+        for component in &mut components {
+            component.blocks_w = mcus_w * component.h;
+            component.blocks_h = mcus_h * component.v;
+            component.coefs = vec![0; component.blocks_w * component.blocks_h * 64];
+        }
+        Ok(Frame {
+            width,
+            height,
+            progressive,
+            h_max,
+            v_max,
+            mcus_w,
+            mcus_h,
+            components,
+        })
+    }
+
+    /// Decodes the entropy-coded data of `scan` starting at `pos`, returning where it ends.
+    fn decode_scan(
+        &mut self,
+        data: &[u8],
+        pos: usize,
+        scan: &Scan,
+        restart_interval: usize,
+    ) -> Result<usize, String> {
+        let mut bits = Bits {
+            data,
+            pos,
+            buffer: 0,
+            count: 0,
+            marker: false,
+        };
+        let mut preds = vec![0i32; scan.components.len()];
+        let mut eob_run = 0u32;
+        let mut mcu = 0;
+        let mut restart = |bits: &mut Bits, preds: &mut Vec<i32>, eob_run: &mut u32| {
+            if restart_interval > 0 && mcu > 0 && mcu % restart_interval == 0 {
+                bits.restart();
+                preds.fill(0);
+                *eob_run = 0;
+            }
+            mcu += 1;
+        };
+
+        if let [(index, dc, ac)] = scan.components[..] {
+            // A scan of one component codes its blocks in raster order, only as far as
+            // the image reaches rather than in whole MCUs.
+            let component = &self.components[index];
+            let blocks_w = (self.width * component.h).div_ceil(self.h_max).div_ceil(8);
+            let blocks_h = (self.height * component.v).div_ceil(self.v_max).div_ceil(8);
+            let stride = component.blocks_w;
+            for y in 0..blocks_h {
+                for x in 0..blocks_w {
+                    restart(&mut bits, &mut preds, &mut eob_run);
+                    let block = (y * stride + x) * 64;
+                    let coefs = &mut self.components[index].coefs[block..block + 64];
+                    decode_block(
+                        self.progressive,
+                        &mut bits,
+                        coefs,
+                        scan,
+                        dc,
+                        ac,
+                        &mut preds[0],
+                        &mut eob_run,
+                    )?;
+                }
+            }
+        } else {
+            for mcu_y in 0..self.mcus_h {
+                for mcu_x in 0..self.mcus_w {
+                    restart(&mut bits, &mut preds, &mut eob_run);
+                    for (slot, &(index, dc, ac)) in scan.components.iter().enumerate() {
+                        let (h, v, stride) = {
+                            let c = &self.components[index];
+                            (c.h, c.v, c.blocks_w)
+                        };
+                        for y in 0..v {
+                            for x in 0..h {
+                                let block = ((mcu_y * v + y) * stride + mcu_x * h + x) * 64;
+                                let coefs = &mut self.components[index].coefs[block..block + 64];
+                                let pred = &mut preds[slot];
+                                decode_block(
+                                    self.progressive,
+                                    &mut bits,
+                                    coefs,
+                                    scan,
+                                    dc,
+                                    ac,
+                                    pred,
+                                    &mut eob_run,
+                                )?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(bits.pos)
+    }
+
+    /// Dequantizes and transforms every block and converts the result to RGBA.
+    fn render(&self, quant: &[[u16; 64]; 4], adobe_transform: Option<u8>) -> Raster {
+        let cos = cos_table();
+        let planes: Vec<(usize, Vec<u8>)> = self
+            .components
+            .iter()
+            .map(|component| {
+                let stride = component.blocks_w * 8;
+                let mut plane = vec![0u8; stride * component.blocks_h * 8];
+                let table = &quant[component.quant];
+                for (index, block) in component.coefs.chunks_exact(64).enumerate() {
+                    let (bx, by) = (index % component.blocks_w, index / component.blocks_w);
+                    let mut natural = [0.0f32; 64];
+                    for k in 0..64 {
+                        natural[ZIGZAG[k]] = block[k] as f32 * table[k] as f32;
+                    }
+                    let samples = idct(&natural, &cos);
+                    for y in 0..8 {
+                        let row = (by * 8 + y) * stride + bx * 8;
+                        for x in 0..8 {
+                            plane[row + x] =
+                                (samples[y * 8 + x] + 128.0).round().clamp(0.0, 255.0) as u8;
+                        }
+                    }
+                }
+                (stride, plane)
+            })
+            .collect();
+
+        let sample = |c: usize, x: usize, y: usize| {
+            let component = &self.components[c];
+            let (stride, plane) = &planes[c];
+            plane[y * component.v / self.v_max * stride + x * component.h / self.h_max] as f32
+        };
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel = match self.components.len() {
+                    1 => {
+                        let gray = sample(0, x, y) as u8;
+                        [gray, gray, gray, 255]
+                    }
+                    _ if adobe_transform == Some(0) => [
+                        sample(0, x, y) as u8,
+                        sample(1, x, y) as u8,
+                        sample(2, x, y) as u8,
+                        255,
+                    ],
+                    _ => {
+                        let (luma, cb, cr) = (
+                            sample(0, x, y),
+                            sample(1, x, y) - 128.0,
+                            sample(2, x, y) - 128.0,
+                        );
+                        let channel = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+                        [
+                            channel(luma + 1.402 * cr),
+                            channel(luma - 0.344_136 * cb - 0.714_136 * cr),
+                            channel(luma + 1.772 * cb),
+                            255,
+                        ]
+                    }
+                };
+                pixels.push(pixel);
+            }
+        }
+        Raster {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+impl<'a> Scan<'a> {
+    fn new(
+        body: &[u8],
+        frame: &Frame,
+        dc: &'a [Option<Huffman>; 4],
+        ac: &'a [Option<Huffman>; 4],
+    ) -> Result<Scan<'a>, String> {
+        let count = *body.first().ok_or("truncated JPEG scan header")? as usize;
+        let specs = body
+            .get(1..1 + 2 * count)
+            .ok_or("truncated JPEG scan header")?;
+        let spectral = body
+            .get(1 + 2 * count..4 + 2 * count)
+            .ok_or("truncated JPEG scan header")?;
+        let (start, end) = (spectral[0] as usize, spectral[1] as usize);
+        let (high, low) = (spectral[2] >> 4, spectral[2] & 15);
+        if start > end
+            || end > 63
+            || (count > 1 && start > 0)
+            || (!frame.progressive && (start, end) != (0, 63))
+        {
+            return Err("invalid JPEG scan header".into());
+        }
+        let mut components = Vec::with_capacity(count);
+        for spec in specs.chunks_exact(2) {
+            let index = frame
+                .components
+                .iter()
+                .position(|c| c.id == spec[0])
+                .ok_or("JPEG scan of an unknown component")?;
+            // Refining DC only reads raw bits, and a DC scan has no AC coefficients.
+            let dc = (start == 0 && high == 0)
+                .then(|| {
+                    dc[(spec[1] >> 4) as usize & 3]
+                        .as_ref()
+                        .ok_or("missing JPEG Huffman table")
+                })
+                .transpose()?;
+            let ac = (end > 0)
+                .then(|| {
+                    ac[(spec[1] & 15) as usize & 3]
+                        .as_ref()
+                        .ok_or("missing JPEG Huffman table")
+                })
+                .transpose()?;
+            components.push((index, dc, ac));
+        }
+        Ok(Scan {
+            components,
+            start,
+            end,
+            high,
+            low,
+        })
+    }
+}
+
+/// Decodes the part of one block that `scan` holds into `coefs`.
+#[allow(clippy::too_many_arguments)]
+fn decode_block(
+    progressive: bool,
+    bits: &mut Bits,
+    coefs: &mut [i16],
+    scan: &Scan,
+    dc: Option<&Huffman>,
+    ac: Option<&Huffman>,
+    pred: &mut i32,
+    eob_run: &mut u32,
+) -> Result<(), String> {
+    let corrupt = || String::from("corrupt JPEG data");
+    if scan.start == 0 {
+        match dc {
+            Some(dc) => {
+                let size = bits.decode(dc)?;
+                *pred += bits.extend(size)?;
+                coefs[0] = (*pred << scan.low) as i16;
+            }
+            None if bits.bit() => coefs[0] |= 1 << scan.low,
+            None => {}
+        }
+        if progressive {
+            return Ok(());
+        }
+    }
+    let Some(ac) = ac else {
+        return Ok(());
+    };
+
+    if !progressive {
+        let mut k = 1;
+        while k < 64 {
+            let rs = bits.decode(ac)?;
+            let (run, size) = ((rs >> 4) as usize, rs & 15);
+            if size == 0 {
+                if run != 15 {
+                    break;
+                }
+                k += 16;
+                continue;
+            }
+            k += run;
+            *coefs.get_mut(k).ok_or_else(corrupt)? = bits.extend(size)? as i16;
+            k += 1;
+        }
+        return Ok(());
+    }
+
+    if scan.high == 0 {
+        // First pass over these coefficients.
+        if *eob_run > 0 {
+            *eob_run -= 1;
+            return Ok(());
+        }
+        let mut k = scan.start;
+        while k <= scan.end {
+            let rs = bits.decode(ac)?;
+            let (run, size) = ((rs >> 4) as u32, rs & 15);
+            if size == 0 {
+                if run < 15 {
+                    *eob_run = (1 << run) - 1 + bits.bits(run);
+                    break;
+                }
+                k += 16;
+                continue;
+            }
+            k += run as usize;
+            *coefs.get_mut(k).ok_or_else(corrupt)? = (bits.extend(size)? * (1 << scan.low)) as i16;
+            k += 1;
+        }
+        return Ok(());
+    }
+
+    // Refinement: one more bit of every coefficient already known, and new ones of ±1.
+    let (plus, minus) = (1i16 << scan.low, -1i16 << scan.low);
+    let refine = |bits: &mut Bits, coef: &mut i16| {
+        if bits.bit() && *coef & plus == 0 {
+            *coef += if *coef >= 0 { plus } else { minus };
+        }
+    };
+    let mut k = scan.start;
+    if *eob_run == 0 {
+        while k <= scan.end {
+            let rs = bits.decode(ac)?;
+            let (mut run, size) = (rs >> 4, rs & 15);
+            let mut value = 0;
+            if size == 0 {
+                if run < 15 {
+                    *eob_run = (1 << run) + bits.bits(run as u32);
+                    break;
+                }
+            } else {
+                value = if bits.bit() { plus } else { minus };
+            }
+            while k <= scan.end {
+                if coefs[k] != 0 {
+                    refine(bits, &mut coefs[k]);
+                } else if run == 0 {
+                    break;
+                } else {
+                    run -= 1;
+                }
+                k += 1;
+            }
+            if value != 0 {
+                *coefs.get_mut(k).ok_or_else(corrupt)? = value;
+            }
+            k += 1;
+        }
+    }
+    if *eob_run > 0 {
+        while k <= scan.end {
+            if coefs[k] != 0 {
+                refine(bits, &mut coefs[k]);
+            }
+            k += 1;
+        }
+        *eob_run -= 1;
+    }
+    Ok(())
+}
+
+fn idct(coefs: &[f32; 64], cos: &[[f32; 8]; 8]) -> [f32; 64] {
+    let mut rows = [0.0f32; 64];
+    for v in 0..8 {
+        for x in 0..8 {
+            rows[v * 8 + x] = (0..8).map(|u| cos[x][u] * coefs[v * 8 + u]).sum();
+        }
+    }
+    let mut out = [0.0f32; 64];
+    for y in 0..8 {
+        for x in 0..8 {
+            out[y * 8 + x] = (0..8).map(|v| cos[y][v] * rows[v * 8 + x]).sum();
+        }
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Encoding
+// ---------------------------------------------------------------------------
+
+/// Encodes `image` as a baseline JPEG with 4:2:0 chroma subsampling. Transparent pixels
+/// are blended onto white, since JPEG has no alpha channel.
+pub fn encode(image: &Raster, quality: u8) -> Vec<u8> {
+    let luma_quant = scaled_quant(&LUMA_QUANT, quality);
+    let chroma_quant = scaled_quant(&CHROMA_QUANT, quality);
+    let (luma_dc, luma_ac) = (Codes::new(&LUMA_DC), Codes::new(&LUMA_AC));
+    let (chroma_dc, chroma_ac) = (Codes::new(&CHROMA_DC), Codes::new(&CHROMA_AC));
+
+    let mut out = vec![0xFF, 0xD8];
+    segment(&mut out, 0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    let mut tables = Vec::with_capacity(130);
+    for (id, table) in [&luma_quant, &chroma_quant].into_iter().enumerate() {
+        tables.push(id as u8);
+        tables.extend(table.iter().map(|&q| q as u8));
+    }
+    segment(&mut out, 0xDB, &tables);
+    let (width, height) = (image.width as u16, image.height as u16);
+    let mut frame = vec![8];
+    frame.extend(height.to_be_bytes());
+    frame.extend(width.to_be_bytes());
+    frame.extend([3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    segment(&mut out, 0xC0, &frame);
+    let mut huffman = Vec::new();
+    for (class_id, (counts, values)) in [
+        (0x00, LUMA_DC),
+        (0x10, LUMA_AC),
+        (0x01, CHROMA_DC),
+        (0x11, CHROMA_AC),
+    ] {
+        huffman.push(class_id);
+        huffman.extend(counts);
+        huffman.extend(values);
+    }
+    segment(&mut out, 0xC4, &huffman);
+    segment(&mut out, 0xDA, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let cos = cos_table();
+    let mut writer = BitWriter {
+        out,
+        buffer: 0,
+        count: 0,
+    };
+    let mut preds = [0i32; 3];
+    let ycbcr = |x: usize, y: usize| {
+        let [r, g, b, a] =
+            image.pixels[y.min(image.height - 1) * image.width + x.min(image.width - 1)];
+        let alpha = a as f32 / 255.0;
+        let blend = |c: u8| c as f32 * alpha + 255.0 * (1.0 - alpha);
+        let (r, g, b) = (blend(r), blend(g), blend(b));
+        [
+            0.299 * r + 0.587 * g + 0.114 * b,
+            -0.168_736 * r - 0.331_264 * g + 0.5 * b + 128.0,
+            0.5 * r - 0.418_688 * g - 0.081_312 * b + 128.0,
+        ]
+    };
+    for mcu_y in (0..image.height).step_by(16) {
+        for mcu_x in (0..image.width).step_by(16) {
+            for (bx, by) in [(0, 0), (8, 0), (0, 8), (8, 8)] {
+                let mut block = [0.0f32; 64];
+                for (i, sample) in block.iter_mut().enumerate() {
+                    *sample = ycbcr(mcu_x + bx + i % 8, mcu_y + by + i / 8)[0] - 128.0;
+                }
+                writer.block(&block, &luma_quant, &luma_dc, &luma_ac, &mut preds[0], &cos);
+            }
+            let mut chroma = [[0.0f32; 64]; 2];
+            let [blue, red] = &mut chroma;
+            for (i, (cb, cr)) in blue.iter_mut().zip(red.iter_mut()).enumerate() {
+                let (x, y) = (mcu_x + 2 * (i % 8), mcu_y + 2 * (i / 8));
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let [_, b, r] = ycbcr(x + dx, y + dy);
+                    *cb += b / 4.0;
+                    *cr += r / 4.0;
+                }
+                *cb -= 128.0;
+                *cr -= 128.0;
+            }
+            writer.block(
+                &chroma[0],
+                &chroma_quant,
+                &chroma_dc,
+                &chroma_ac,
+                &mut preds[1],
+                &cos,
+            );
+            writer.block(
+                &chroma[1],
+                &chroma_quant,
+                &chroma_dc,
+                &chroma_ac,
+                &mut preds[2],
+                &cos,
+            );
+        }
+    }
+    let mut out = writer.finish();
+    out.extend([0xFF, 0xD9]);
+    out
+}
+
+/// `table` scaled for `quality` (1–100) the way libjpeg does, in zigzag order.
+fn scaled_quant(table: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - 2 * quality
+    };
+    let mut scaled = [0; 64];
+    for (k, entry) in scaled.iter_mut().enumerate() {
+        *entry = ((table[ZIGZAG[k]] as u32 * scale + 50) / 100).clamp(1, 255) as u16;
+    }
+    scaled
+}
+
+fn segment(out: &mut Vec<u8>, marker: u8, body: &[u8]) {
+    out.extend([0xFF, marker]);
+    out.extend((body.len() as u16 + 2).to_be_bytes());
+    out.extend(body);
+}
+
+/// Code and length of every symbol of a Huffman table.
+struct Codes([(u16, u8); 256]);
+
+impl Codes {
+    fn new((counts, values): &([u8; 16], &[u8])) -> Codes {
+        let mut codes = [(0, 0); 256];
+        let (mut code, mut k) = (0u16, 0);
+        for (len, &count) in counts.iter().enumerate() {
+            for _ in 0..count {
+                codes[values[k] as usize] = (code, len as u8 + 1);
+                code += 1;
+                k += 1;
+            }
+            code <<= 1;
+        }
+        Codes(codes)
+    }
+}
+
+/// Writes entropy-coded data, stuffing a zero after every 0xFF byte.
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, bits: u32, len: u32) {
+        self.buffer = (self.buffer << len) | (bits & ((1 << len) - 1));
+        self.count += len;
+        while self.count >= 8 {
+            let byte = (self.buffer >> (self.count - 8)) as u8;
+            self.out.push(byte);
+            if byte == 0xFF {
+                self.out.push(0);
+            }
+            self.count -= 8;
+        }
+        self.buffer &= (1 << self.count) - 1;
+    }
+
+    fn symbol(&mut self, codes: &Codes, symbol: u8) {
+        let (code, len) = codes.0[symbol as usize];
+        self.put(code as u32, len as u32);
+    }
+
+    /// Transforms, quantizes and writes one block of level-shifted samples.
+    fn block(
+        &mut self,
+        samples: &[f32; 64],
+        quant: &[u16; 64],
+        dc: &Codes,
+        ac: &Codes,
+        pred: &mut i32,
+        cos: &[[f32; 8]; 8],
+    ) {
+        let coefs = fdct(samples, cos);
+        let mut zigzag = [0i32; 64];
+        for (k, value) in zigzag.iter_mut().enumerate() {
+            // Standard AC tables code magnitudes of up to 10 bits.
+            *value = ((coefs[ZIGZAG[k]] / quant[k] as f32).round() as i32).clamp(-1023, 1023);
+        }
+
+        let diff = zigzag[0] - *pred;
+        *pred = zigzag[0];
+        let size = magnitude(diff);
+        self.symbol(dc, size as u8);
+        self.put(diff_bits(diff, size), size);
+
+        let mut run = 0;
+        for &value in &zigzag[1..] {
+            if value == 0 {
+                run += 1;
+                continue;
+            }
+            while run > 15 {
+                self.symbol(ac, 0xF0);
+                run -= 16;
+            }
+            let size = magnitude(value);
+            self.symbol(ac, (run << 4 | size) as u8);
+            self.put(diff_bits(value, size), size);
+            run = 0;
+        }
+        if run > 0 {
+            self.symbol(ac, 0x00);
+        }
+    }
+
+    /// Pads the last byte with one bits and returns the output.
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.put(0x7F, 8 - self.count);
+        }
+        self.out
+    }
+}
+
+/// Number of bits in the magnitude of `value`.
+fn magnitude(value: i32) -> u32 {
+    32 - value.unsigned_abs().leading_zeros()
+}
+
+/// The `size` low bits coding `value`: itself when positive, one less when negative.
+fn diff_bits(value: i32, size: u32) -> u32 {
+    let bits = if value < 0 { value - 1 } else { value };
+    bits as u32 & ((1 << size) - 1)
+}
+
+fn fdct(samples: &[f32; 64], cos: &[[f32; 8]; 8]) -> [f32; 64] {
+    let mut rows = [0.0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            rows[y * 8 + u] = (0..8).map(|x| cos[x][u] * samples[y * 8 + x]).sum();
+        }
+    }
+    let mut out = [0.0f32; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            out[v * 8 + u] = (0..8).map(|y| cos[y][v] * rows[y * 8 + u]).sum();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize) -> Raster {
+        let pixels = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                [
+                    (x * 6) as u8,
+                    (y * 10) as u8,
+                    (128 + x * 2 - y * 3) as u8,
+                    255,
+                ]
+            })
+            .collect();
+        Raster {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Coefficients of the three blocks of a 24x8 grayscale image, in zigzag order and
+    /// left unquantized; the last block is empty.
+    fn blocks() -> Vec<[i32; 64]> {
+        let mut blocks = vec![[0; 64]; 3];
+        for (k, value) in [
+            (0, 37),
+            (1, -5),
+            (2, 3),
+            (5, 1),
+            (20, -1),
+            (40, 6),
+            (41, -2),
+        ] {
+            blocks[0][k] = value;
+        }
+        // Gaps of more than 16 zeros need ZRL codes.
+        for (k, value) in [(0, -21), (1, 1), (3, -2), (30, 1), (50, -3)] {
+            blocks[1][k] = value;
+        }
+        blocks
+    }
+
+    /// SOI, a quantization table of ones, the standard luminance Huffman tables and a
+    /// grayscale frame header of `marker` for the three [`blocks`].
+    fn gray_header(marker: u8) -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8];
+        segment(&mut out, 0xDB, &[[0].as_slice(), &[1; 64]].concat());
+        let mut huffman = vec![0x00];
+        huffman.extend(LUMA_DC.0);
+        huffman.extend(LUMA_DC.1);
+        huffman.push(0x10);
+        huffman.extend(LUMA_AC.0);
+        huffman.extend(LUMA_AC.1);
+        segment(&mut out, 0xC4, &huffman);
+        segment(&mut out, marker, &[8, 0, 8, 0, 24, 1, 1, 0x11, 0]);
+        out
+    }
+
+    /// Writes a scan header and then the entropy-coded data `write` produces.
+    fn scan(out: Vec<u8>, spectral: [u8; 3], write: impl FnOnce(&mut BitWriter)) -> Vec<u8> {
+        let mut out = out;
+        segment(&mut out, 0xDA, &[&[1, 1, 0x00][..], &spectral].concat());
+        let mut writer = BitWriter {
+            out,
+            buffer: 0,
+            count: 0,
+        };
+        write(&mut writer);
+        writer.finish()
+    }
+
+    fn dc_diff(writer: &mut BitWriter, codes: &Codes, diff: i32) {
+        let size = magnitude(diff);
+        writer.symbol(codes, size as u8);
+        writer.put(diff_bits(diff, size), size);
+    }
+
+    /// Run-length codes the AC coefficients `values`, ending with an EOB of one block.
+    fn ac_runs(writer: &mut BitWriter, codes: &Codes, values: &[i32]) {
+        let mut run = 0;
+        for &value in values {
+            if value == 0 {
+                run += 1;
+                continue;
+            }
+            while run > 15 {
+                writer.symbol(codes, 0xF0);
+                run -= 16;
+            }
+            let size = magnitude(value);
+            writer.symbol(codes, (run << 4 | size) as u8);
+            writer.put(diff_bits(value, size), size);
+            run = 0;
+        }
+        if run > 0 {
+            writer.symbol(codes, 0x00);
+        }
+    }
+
+    /// The AC refinement of a successive-approximation scan that dropped one bit: a
+    /// correction bit for every coefficient already known and the new ones of ±1.
+    fn ac_refinement(writer: &mut BitWriter, codes: &Codes, values: &[i32]) {
+        let (mut run, mut corrections) = (0, Vec::new());
+        let last_new = values.iter().rposition(|v| v.abs() == 1);
+        for (k, &value) in values.iter().enumerate() {
+            if value == 0 {
+                run += 1;
+                continue;
+            }
+            while run > 15 && last_new.is_some_and(|last| k <= last) {
+                writer.symbol(codes, 0xF0);
+                run -= 16;
+                for bit in corrections.drain(..) {
+                    writer.put(bit, 1);
+                }
+            }
+            if value.abs() > 1 {
+                corrections.push(value.unsigned_abs() & 1);
+                continue;
+            }
+            writer.symbol(codes, (run << 4 | 1) as u8);
+            writer.put((value > 0) as u32, 1);
+            for bit in corrections.drain(..) {
+                writer.put(bit, 1);
+            }
+            run = 0;
+        }
+        if run > 0 || !corrections.is_empty() {
+            writer.symbol(codes, 0x00);
+            for bit in corrections {
+                writer.put(bit, 1);
+            }
+        }
+    }
+
+    fn exif(orientation: u16, big_endian: bool) -> Vec<u8> {
+        let mut body = b"Exif\0\0".to_vec();
+        body.extend(if big_endian { b"MM" } else { b"II" });
+        // The TIFF magic and the offset of the first IFD, then an IFD of one entry: a
+        // SHORT holding the orientation, padded to four bytes. Fields as (value, size).
+        let fields = [
+            (42, 2),
+            (8, 4),
+            (1, 2),
+            (0x0112, 2),
+            (3, 2),
+            (1, 4),
+            (orientation as u32, 2),
+            (0, 2),
+            (0, 4),
+        ];
+        for (value, size) in fields {
+            match big_endian {
+                true => body.extend(&value.to_be_bytes()[4 - size..]),
+                false => body.extend(&value.to_le_bytes()[..size]),
+            }
+        }
+        body
+    }
+
+    #[test]
+    fn round_trips_within_the_quantization_error() {
+        // Sides that are not multiples of the 16-pixel MCU.
+        let image = gradient(40, 21);
+        let decoded = decode(&encode(&image, 95)).unwrap();
+        assert_eq!((decoded.width, decoded.height), (40, 21));
+        let errors: Vec<u32> = image
+            .pixels
+            .iter()
+            .zip(&decoded.pixels)
+            .flat_map(|(a, b)| (0..3).map(|c| a[c].abs_diff(b[c]) as u32))
+            .collect();
+        let mean = errors.iter().sum::<u32>() as f64 / errors.len() as f64;
+        let max = errors.iter().max().unwrap();
+        assert!(mean < 4.0 && *max <= 16, "mean error {mean}, largest {max}");
+        assert!(decoded.pixels.iter().all(|pixel| pixel[3] == 255));
+    }
+
+    #[test]
+    fn transparent_pixels_are_blended_onto_white() {
+        let image = Raster {
+            width: 8,
+            height: 8,
+            pixels: vec![[0, 0, 0, 0]; 64],
+        };
+        let decoded = decode(&encode(&image, 90)).unwrap();
+        assert!(decoded
+            .pixels
+            .iter()
+            .all(|p| p[..3].iter().all(|&c| c >= 253)));
+    }
+
+    #[test]
+    fn progressive_scans_decode_like_the_baseline_image() {
+        let (dc, ac) = (Codes::new(&LUMA_DC), Codes::new(&LUMA_AC));
+        let blocks = blocks();
+
+        let baseline = scan(gray_header(0xC0), [0, 63, 0], |writer| {
+            let mut pred = 0;
+            for block in &blocks {
+                dc_diff(writer, &dc, block[0] - pred);
+                pred = block[0];
+                ac_runs(writer, &ac, &block[1..]);
+            }
+        });
+        let mut baseline = baseline;
+        baseline.extend([0xFF, 0xD9]);
+
+        // Spectral selection and successive approximation: DC and AC without their
+        // lowest bit, then that bit for each.
+        let progressive = scan(gray_header(0xC2), [0, 0, 0x01], |writer| {
+            let mut pred = 0;
+            for block in &blocks {
+                dc_diff(writer, &dc, (block[0] >> 1) - pred);
+                pred = block[0] >> 1;
+            }
+        });
+        let progressive = scan(progressive, [1, 63, 0x01], |writer| {
+            for block in &blocks {
+                let halved: Vec<i32> = block[1..]
+                    .iter()
+                    .map(|v| v.signum() * (v.abs() >> 1))
+                    .collect();
+                ac_runs(writer, &ac, &halved);
+            }
+        });
+        let progressive = scan(progressive, [0, 0, 0x10], |writer| {
+            for block in &blocks {
+                writer.put(block[0] as u32 & 1, 1);
+            }
+        });
+        let mut progressive = scan(progressive, [1, 63, 0x10], |writer| {
+            for block in &blocks {
+                ac_refinement(writer, &ac, &block[1..]);
+            }
+        });
+        progressive.extend([0xFF, 0xD9]);
+
+        let expected = decode(&baseline).unwrap();
+        let decoded = decode(&progressive).unwrap();
+        assert_eq!((decoded.width, decoded.height), (24, 8));
+        assert!(decoded.pixels == expected.pixels);
+        // Each block comes out at its own level, the empty one at mid-gray.
+        assert!(expected.pixels[0] != expected.pixels[8]);
+        assert_eq!(expected.pixels[16..24], [[128, 128, 128, 255]; 8]);
+    }
+
+    #[test]
+    fn exif_orientation_is_applied_in_either_byte_order() {
+        // Four quadrants of distinct grays, so every flip and turn is told apart.
+        let pixels = (0..32 * 16)
+            .map(|i| {
+                let gray = [0, 85, 170, 255][(i % 32 / 16) + 2 * (i / 32 / 8)];
+                [gray, gray, gray, 255]
+            })
+            .collect();
+        let image = Raster {
+            width: 32,
+            height: 16,
+            pixels,
+        };
+        let plain = encode(&image, 90);
+        for big_endian in [true, false] {
+            for orientation in 1..=8 {
+                let mut data = plain[..2].to_vec();
+                segment(&mut data, 0xE1, &exif(orientation, big_endian));
+                data.extend(&plain[2..]);
+                let decoded = decode(&data).unwrap();
+                let expected = decode(&plain).unwrap().oriented(orientation);
+                assert_eq!(
+                    (decoded.width, decoded.height),
+                    (expected.width, expected.height)
+                );
+                assert!(
+                    decoded.pixels == expected.pixels,
+                    "orientation {orientation}"
+                );
+            }
+        }
+        assert_eq!(exif_orientation(&exif(6, true)[6..]), Some(6));
+        assert_eq!(exif_orientation(&exif(9, false)[6..]), None);
+        assert_eq!(exif_orientation(b"XX\0\x2a"), None);
+    }
+
+    #[test]
+    fn truncated_files_fail_or_decode_partially() {
+        let data = encode(&gradient(40, 21), 90);
+        let scan_start = data.windows(2).position(|m| m == [0xFF, 0xDA]).unwrap();
+        let scan_header_end = scan_start + 2 + 12;
+        for len in 0..data.len() {
+            match decode(&data[..len]) {
+                Ok(image) => {
+                    assert!(len >= scan_header_end, "cut at {len} decoded");
+                    assert_eq!((image.width, image.height), (40, 21));
+                }
+                Err(_) => assert!(len < scan_header_end, "cut at {len} failed"),
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        let frame = |marker: u8, body: &[u8]| {
+            let mut data = vec![0xFF, 0xD8];
+            segment(&mut data, marker, body);
+            decode(&data).err().unwrap()
+        };
+        assert_eq!(
+            frame(0xC0, &[12, 0, 8, 0, 8, 1, 1, 0x11, 0]),
+            "only 8-bit JPEGs are supported"
+        );
+        assert!(frame(0xC3, &[8, 0, 8, 0, 8, 1, 1, 0x11, 0]).starts_with("lossless"));
+        assert!(frame(0xC0, &[8, 0, 8, 0, 8, 4, 1, 0x11, 0]).ends_with("not CMYK"));
+        assert_eq!(
+            frame(0xC0, &[8, 0, 0, 0, 8, 1, 1, 0x11, 0]),
+            "JPEG without a size"
+        );
+        assert_eq!(
+            frame(0xC0, &[8, 0xFF, 0xFF, 0xFF, 0xFF, 1, 1, 0x11, 0]),
+            "65535x65535 pixels is too large"
+        );
+        assert_eq!(
+            frame(0xC0, &[8, 0, 8, 0, 8, 1, 1, 0x51, 0]),
+            "invalid JPEG frame header"
+        );
+        // Three codes of length one cannot exist.
+        let mut table = vec![0x00, 3];
+        table.extend([0; 15]);
+        table.extend([1, 2, 3]);
+        assert_eq!(frame(0xC4, &table), "invalid JPEG Huffman table");
+        assert_eq!(
+            frame(0xDA, &[1, 1, 0, 0, 63, 0]),
+            "JPEG scan before the frame header"
+        );
+
+        // A scan naming a component the frame lacks.
+        let mut data = gray_header(0xC0);
+        segment(&mut data, 0xDA, &[1, 2, 0x00, 0, 63, 0]);
+        assert_eq!(
+            decode(&data).err().unwrap(),
+            "JPEG scan of an unknown component"
+        );
+    }
+
+    #[test]
+    fn corrupt_image_data_does_not_panic() {
+        let data = encode(&gradient(40, 21), 90);
+        let scan_start = data.windows(2).position(|m| m == [0xFF, 0xDA]).unwrap() + 14;
+        let mut state = 0x2545_F491_u32;
+        for _ in 0..200 {
+            let mut corrupt = data.clone();
+            for byte in &mut corrupt[scan_start..data.len() - 2] {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                if state.is_multiple_of(8) {
+                    *byte = state as u8 >> 1;
+                }
+            }
+            if let Ok(image) = decode(&corrupt) {
+                assert_eq!(image.pixels.len(), 40 * 21);
+            }
+        }
+    }
+}
@@ -1,13 +1,79 @@
 //! `nu_plugin_audio_hook` — a Nushell plugin for generating, playing, and
 //! inspecting audio files.
 //!
-//! Registers five commands: `sound beep`, `sound make`, `sound play`,
-//! `sound meta`, and `sound meta set`.
+//! Registers the `sound` command family: tone generation (`sound beep`,
+//! `sound make`), playback (`sound play`), and metadata reading/editing
+//! (`sound meta` and its subcommands).
+mod arpeggio;
+mod audio_abx;
+mod audio_analyze;
+mod audio_artwork;
+mod audio_audition;
+mod audio_devices;
+mod audio_edit;
+mod audio_effect;
+mod audio_library;
+mod audio_measure;
 mod audio_meta;
 mod audio_player;
+mod audio_privacy;
+mod audio_repair;
+mod audio_stems;
+mod audio_test;
+mod audio_transcribe;
+mod audio_xing;
+mod auto_gain;
+mod backend;
+mod beep;
+mod capture;
+mod channel_mix;
+mod completions;
+mod config;
 mod constants;
+mod cooldown;
+mod dsp;
+mod ear_guard;
+mod expr;
+mod fade;
+mod gapless;
+mod headphone;
+mod instrument;
+mod itunes;
+mod jpeg;
+mod live;
+mod midi;
+mod multitrack;
+mod network;
+mod no_audio;
+mod pcm;
+mod png;
+mod raster;
+mod recovery;
+mod remote;
+mod sample_format;
+mod sidecar;
+mod simd;
 mod sound;
+mod sound_drums;
+mod sound_dtmf;
 mod sound_make;
+mod sound_midi;
+mod sound_mml;
+mod sound_morse;
+mod sound_notify;
+mod sound_rtttl;
+mod sound_sampler;
+mod sound_samples;
+mod sound_sequence;
+mod sound_synth;
+mod soundfont;
+mod stream_identity;
+mod stream_meta;
 mod utils;
+mod validate;
+mod watchdog;
+mod wav;
+mod waveform;
+mod ytdlp;
 pub use sound::Sound;
 // pub use sound_make::make_sound;
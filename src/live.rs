@@ -0,0 +1,104 @@
+use rodio::{decoder::DecoderError, source::SeekError, ChannelCount, Decoder, SampleRate, Source};
+use std::time::Duration;
+
+use crate::network::DownloadReader;
+
+/// Places tried for decodable data after a decoder gave up mid-stream.
+const RESYNC_ATTEMPTS: u64 = 16;
+
+/// Data skipped between resync attempts.
+const RESYNC_SKIP: u64 = 16 * 1024;
+
+/// Decoder for an endless network stream that starts over with a fresh decoder when the
+/// current one stops while the stream goes on.
+///
+/// Internet radio changes codec parameters between shows and resumes mid-frame after a
+/// reconnect; either ends a decoder, which would otherwise end playback.
+pub struct LiveDecoder {
+    decoder: Option<Decoder<DownloadReader>>,
+    stream: DownloadReader,
+    hint: Option<String>,
+}
+
+impl LiveDecoder {
+    pub fn new(stream: DownloadReader, hint: Option<&str>) -> Result<LiveDecoder, DecoderError> {
+        let decoder = build(stream.clone(), hint)?;
+        Ok(LiveDecoder {
+            decoder: Some(decoder),
+            stream,
+            hint: hint.map(str::to_string),
+        })
+    }
+
+    /// Opens a new decoder where the last one stopped reading, skipping ahead past data
+    /// it cannot make sense of. Returns `false` once the stream has really ended.
+    fn restart(&mut self) -> bool {
+        self.decoder = None;
+        if !self.stream.pending() {
+            return false;
+        }
+        let origin = self.stream.resume();
+        for attempt in 0..RESYNC_ATTEMPTS {
+            let mut reader = origin.clone();
+            reader.skip(attempt * RESYNC_SKIP);
+            if let Ok(decoder) = build(reader, self.hint.as_deref()) {
+                self.decoder = Some(decoder);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn build(
+    reader: DownloadReader,
+    hint: Option<&str>,
+) -> Result<Decoder<DownloadReader>, DecoderError> {
+    let mut builder = Decoder::builder().with_data(reader).with_seekable(false);
+    if let Some(hint) = hint {
+        builder = builder.with_hint(hint);
+    }
+    builder.build()
+}
+
+impl Iterator for LiveDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            if let Some(sample) = self.decoder.as_mut()?.next() {
+                return Some(sample);
+            }
+            if !self.restart() {
+                return None;
+            }
+        }
+    }
+}
+
+impl Source for LiveDecoder {
+    fn current_span_len(&self) -> Option<usize> {
+        self.decoder.as_ref().and_then(|d| d.current_span_len())
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.decoder.as_ref().map_or(2, |d| d.channels())
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.decoder.as_ref().map_or(44_100, |d| d.sample_rate())
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        match self.decoder.as_mut() {
+            Some(decoder) => decoder.try_seek(pos),
+            None => Err(SeekError::NotSupported {
+                underlying_source: std::any::type_name::<Self>(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// Standard MIDI File reading for `sound midi play`.
+//
+// Only what playback needs is kept: notes, program and controller changes and pitch
+// bend, with their times converted to seconds through the tempo map. SysEx and meta
+// events other than tempo are skipped.
+
+use nu_protocol::{LabeledError, Span};
+
+/// Microseconds per quarter note until the first tempo event: 120 bpm.
+const DEFAULT_TEMPO: u32 = 500_000;
+
+/// A channel message; channels count from 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message {
+    NoteOn {
+        channel: u8,
+        key: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        key: u8,
+    },
+    Program {
+        channel: u8,
+        program: u8,
+    },
+    Control {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    /// From -8192 to 8191, 0 being centered.
+    PitchBend {
+        channel: u8,
+        value: i16,
+    },
+}
+
+/// A message and when it happens, in seconds from the start.
+#[derive(Clone, Copy, Debug)]
+pub struct Event {
+    pub time: f64,
+    pub message: Message,
+}
+
+enum Kind {
+    Tempo(u32),
+    Message(Message),
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.at..self.at.checked_add(len)?)?;
+        self.at += len;
+        Some(bytes)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// A variable-length quantity: 7 bits per byte, high bit set on all but the last.
+    fn varint(&mut self) -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+/// Reads a Standard MIDI File, or one wrapped in a RIFF `RMID` container, into its
+/// channel messages in playing order.
+pub fn parse(data: &[u8], span: Span) -> Result<Vec<Event>, LabeledError> {
+    let invalid = |why: &str| LabeledError::new("not a valid MIDI file").with_label(why, span);
+    let data = unwrap_rmid(data).unwrap_or(data);
+    let mut reader = Reader { data, at: 0 };
+    if reader.bytes(4) != Some(b"MThd") {
+        return Err(invalid("missing the MThd header"));
+    }
+    let (Some(header_len), Some(format), Some(_tracks), Some(division)) =
+        (reader.u32(), reader.u16(), reader.u16(), reader.u16())
+    else {
+        return Err(invalid("the header is cut short"));
+    };
+    if header_len < 6 || reader.bytes(header_len as usize - 6).is_none() {
+        return Err(invalid("the header is cut short"));
+    }
+    if format > 2 {
+        return Err(invalid(&format!("unknown format {format}")));
+    }
+    if division == 0 {
+        return Err(invalid("the time division is 0"));
+    }
+
+    let mut timeline = Vec::new();
+    // Format 2 tracks are independent patterns, played one after another.
+    let mut track_start = 0u64;
+    while let Some(id) = reader.bytes(4) {
+        let len = reader
+            .u32()
+            .ok_or_else(|| invalid("a chunk is cut short"))? as usize;
+        // A truncated last track is common enough in the wild to play what is there.
+        let end = reader.at.saturating_add(len).min(data.len());
+        let chunk = &data[reader.at..end];
+        reader.at = end;
+        if id != b"MTrk" {
+            continue;
+        }
+        let end = read_track(chunk, track_start, &mut timeline).map_err(invalid)?;
+        if format == 2 {
+            track_start = end;
+        }
+    }
+    timeline.sort_by_key(|(tick, _)| *tick);
+
+    let mut events = Vec::new();
+    let (mut tempo, mut last_tick, mut time) = (DEFAULT_TEMPO, 0, 0.0);
+    for (tick, kind) in timeline {
+        time += (tick - last_tick) as f64 * seconds_per_tick(division, tempo);
+        last_tick = tick;
+        match kind {
+            Kind::Tempo(new_tempo) => tempo = new_tempo,
+            Kind::Message(message) => events.push(Event { time, message }),
+        }
+    }
+    Ok(events)
+}
+
+/// The SMF data inside a RIFF MIDI (`.rmi`) file.
+fn unwrap_rmid(data: &[u8]) -> Option<&[u8]> {
+    if data.get(..4)? != b"RIFF" || data.get(8..12)? != b"RMID" {
+        return None;
+    }
+    let mut at = 12;
+    while let Some(header) = data.get(at..at + 8) {
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let body = data.get(at + 8..(at + 8).saturating_add(len).min(data.len()))?;
+        if &header[..4] == b"data" {
+            return Some(body);
+        }
+        at += 8 + len + len % 2;
+    }
+    None
+}
+
+fn seconds_per_tick(division: u16, tempo: u32) -> f64 {
+    if division & 0x8000 == 0 {
+        return tempo as f64 / 1e6 / division as f64;
+    }
+    // SMPTE time: negative frames per second in the high byte, ticks per frame in the
+    // low one. 29 stands for 29.97 drop-frame.
+    let fps = match ((division >> 8) as i8).unsigned_abs() {
+        29 => 29.97,
+        fps => fps as f64,
+    };
+    1.0 / (fps * (division & 0xFF).max(1) as f64)
+}
+
+/// Appends the events of one track to `timeline`, with ticks counted from `start`, and
+/// returns the tick it ends at.
+fn read_track(
+    data: &[u8],
+    start: u64,
+    timeline: &mut Vec<(u64, Kind)>,
+) -> Result<u64, &'static str> {
+    let mut reader = Reader { data, at: 0 };
+    let mut tick = start;
+    let mut running = None;
+    while reader.at < data.len() {
+        let Some(delta) = reader.varint() else {
+            break;
+        };
+        tick += delta as u64;
+        let Some(mut status) = reader.byte() else {
+            break;
+        };
+        if status < 0x80 {
+            // Running status: the byte was the first data byte of a repeated status.
+            reader.at -= 1;
+            status = running.ok_or("a data byte has no status before it")?;
+        }
+        match status {
+            0xFF => {
+                let kind = reader.byte();
+                let len = reader.varint().ok_or("a meta event is cut short")?;
+                let Some(body) = reader.bytes(len as usize) else {
+                    break;
+                };
+                match kind {
+                    Some(0x51) if body.len() == 3 => {
+                        let tempo = u32::from_be_bytes([0, body[0], body[1], body[2]]);
+                        timeline.push((tick, Kind::Tempo(tempo.max(1))));
+                    }
+                    Some(0x2F) => break,
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = reader.varint().ok_or("a SysEx event is cut short")?;
+                if reader.bytes(len as usize).is_none() {
+                    break;
+                }
+                running = None;
+            }
+            0x80..=0xEF => {
+                running = Some(status);
+                let channel = status & 0x0F;
+                let first = reader.byte().ok_or("an event is cut short")? & 0x7F;
+                let second = match status & 0xF0 {
+                    0xC0 | 0xD0 => 0,
+                    _ => reader.byte().ok_or("an event is cut short")? & 0x7F,
+                };
+                let message = match status & 0xF0 {
+                    0x90 if second > 0 => Message::NoteOn {
+                        channel,
+                        key: first,
+                        velocity: second,
+                    },
+                    0x80 | 0x90 => Message::NoteOff {
+                        channel,
+                        key: first,
+                    },
+                    0xB0 => Message::Control {
+                        channel,
+                        controller: first,
+                        value: second,
+                    },
+                    0xC0 => Message::Program {
+                        channel,
+                        program: first,
+                    },
+                    0xE0 => Message::PitchBend {
+                        channel,
+                        value: ((second as i16) << 7 | first as i16) - 8192,
+                    },
+                    // Aftertouch.
+                    _ => continue,
+                };
+                timeline.push((tick, Kind::Message(message)));
+            }
+            _ => return Err("unexpected system message in a track"),
+        }
+    }
+    Ok(tick)
+}
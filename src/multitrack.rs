@@ -0,0 +1,143 @@
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use nu_protocol::{LabeledError, Span};
+use rodio::{buffer::SamplesBuffer, OutputStream, OutputStreamBuilder, Sink};
+use std::time::Duration;
+
+use crate::{
+    ear_guard::EarGuard,
+    pcm::Pcm,
+    stream_identity::{open_identified, StreamRole},
+};
+
+/// Plays several clips in lockstep on one output stream, one [`Sink`] per clip.
+///
+/// All sinks are started together and seeked together, so switching between versions
+/// (A/B comparison) or muting stems is a volume change instead of a restart, and the
+/// listening position is kept.
+pub struct SyncedPlayback {
+    sinks: Vec<Sink>,
+    duration: Duration,
+    _stream: OutputStream,
+}
+
+impl SyncedPlayback {
+    /// Starts all clips. With a `guard`, each clip is capped at its ceiling; pass a guard
+    /// already [`split`](EarGuard::split) when several clips are heard at once.
+    pub fn start(
+        clips: &[Pcm],
+        guard: Option<EarGuard>,
+        span: Span,
+    ) -> Result<SyncedPlayback, LabeledError> {
+        let mut stream = open_identified(
+            None,
+            StreamRole::Media,
+            OutputStreamBuilder::open_default_stream,
+        )
+        .map_err(|err| {
+            LabeledError::new(err.to_string()).with_label("audio stream exception", span)
+        })?;
+        stream.log_on_drop(false);
+
+        let sinks: Vec<Sink> = clips
+            .iter()
+            .map(|clip| {
+                let sink = Sink::connect_new(stream.mixer());
+                sink.pause();
+                let source =
+                    SamplesBuffer::new(clip.channels, clip.sample_rate, clip.samples.clone());
+                match guard {
+                    Some(guard) => sink.append(guard.protect(source)),
+                    None => sink.append(source),
+                }
+                sink
+            })
+            .collect();
+        for sink in &sinks {
+            sink.play();
+        }
+
+        let duration = clips.iter().map(Pcm::duration).max().unwrap_or_default();
+        Ok(SyncedPlayback {
+            sinks,
+            duration,
+            _stream: stream,
+        })
+    }
+
+    /// Length of the longest clip.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn position(&self) -> Duration {
+        self.sinks
+            .iter()
+            .map(Sink::get_pos)
+            .max()
+            .unwrap_or_default()
+            .min(self.duration)
+    }
+
+    /// `true` once every clip has played to its end.
+    pub fn finished(&self) -> bool {
+        self.sinks.iter().all(Sink::empty)
+    }
+
+    /// Sets each clip's volume; extra entries are ignored.
+    pub fn set_volumes(&self, volumes: &[f32]) {
+        for (sink, volume) in self.sinks.iter().zip(volumes) {
+            sink.set_volume(*volume);
+        }
+    }
+
+    /// Makes only clip `index` audible.
+    pub fn solo(&self, index: usize) {
+        for (i, sink) in self.sinks.iter().enumerate() {
+            sink.set_volume(if i == index { 1.0 } else { 0.0 });
+        }
+    }
+
+    pub fn toggle_pause(&self) -> bool {
+        let paused = !self.sinks.first().is_some_and(Sink::is_paused);
+        for sink in &self.sinks {
+            if paused {
+                sink.pause();
+            } else {
+                sink.play();
+            }
+        }
+        paused
+    }
+
+    pub fn seek(&self, position: Duration) {
+        let position = position.min(self.duration);
+        for sink in &self.sinks {
+            let _ = sink.try_seek(position);
+        }
+    }
+
+    pub fn stop(&self) {
+        for sink in &self.sinks {
+            sink.stop();
+        }
+    }
+}
+
+/// Keeps the terminal in raw mode for keyboard control and restores it when dropped,
+/// including on early returns and errors.
+pub struct RawModeGuard;
+
+impl RawModeGuard {
+    pub fn enable(span: Span) -> Result<RawModeGuard, LabeledError> {
+        enable_raw_mode().map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("failed to enable raw terminal mode", span)
+        })?;
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
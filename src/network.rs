@@ -0,0 +1,601 @@
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, PipelineData, Span, Spanned, Value};
+use std::{
+    fs::File,
+    io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+/// Byte rate assumed for a stream before its real rate can be estimated (320 kbps).
+const ASSUMED_BYTE_RATE: f64 = 40_000.0;
+
+/// Read-ahead below which playback is held to rebuffer.
+const LOW_WATER: Duration = Duration::from_secs(1);
+
+/// First wait before reopening a dropped endless stream; doubles on each failure.
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait between reconnection attempts.
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Data kept behind the read position of an endless stream, for short seeks back.
+const KEEP_BEHIND: u64 = 8 << 20;
+
+/// How often a reader waiting for data checks whether the download was cancelled.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// Read-ahead kept for network playback when `--cache` is not given.
+pub const DEFAULT_CACHE: Duration = Duration::from_secs(10);
+
+/// Returns the URL if `path` names an http(s) resource rather than a local file.
+pub fn remote_url(path: &str) -> Option<String> {
+    ["http:", "https:"]
+        .iter()
+        .find_map(|scheme| scheme_url(path, scheme))
+}
+
+/// Returns `path` as a `scheme//rest` URL if it starts with `scheme` (e.g. `"s3:"`).
+///
+/// Unquoted `Filepath` arguments reach the plugin with their `//` collapsed
+/// (`https:/host/file.mp3`), so that form is accepted and repaired too.
+pub fn scheme_url(path: &str, scheme: &str) -> Option<String> {
+    let prefix = path.get(..scheme.len())?;
+    if !prefix.eq_ignore_ascii_case(scheme) {
+        return None;
+    }
+    let rest = path[scheme.len()..].trim_start_matches('/');
+    if rest.is_empty() {
+        return None;
+    }
+    Some(format!("{prefix}//{rest}"))
+}
+
+/// Extension of the last path segment of `url`, used as a decoder hint.
+pub fn url_extension(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let segment = path.rsplit('/').next()?;
+    segment
+        .rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| !extension.is_empty() && extension.len() <= 5)
+}
+
+/// Opens `url` as a byte stream through Nushell's `http get --raw`, with `headers` and
+/// `extra` as for [`http_request`]. Returns the reader and the size, when the server
+/// reported one.
+pub fn http_stream(
+    engine: &EngineInterface,
+    span: Span,
+    url: &str,
+    headers: Option<&Value>,
+    extra: &[(&str, String)],
+) -> Result<(Box<dyn Read + Send>, Option<u64>), LabeledError> {
+    match http_request(engine, span, "http get", url, headers, extra)? {
+        PipelineData::ByteStream(stream, _) => {
+            let size = stream.known_size();
+            let reader = stream.reader().ok_or_else(|| {
+                LabeledError::new("empty response").with_label("server sent no data", span)
+            })?;
+            Ok((Box::new(reader), size))
+        }
+        PipelineData::Value(Value::Binary { val, .. }, _) => {
+            let size = val.len() as u64;
+            Ok((Box::new(Cursor::new(val)), Some(size)))
+        }
+        PipelineData::Value(Value::Error { error, .. }, _) => Err((*error).into()),
+        _ => Err(LabeledError::new("unexpected response")
+            .with_label("`http get` did not return binary data", span)),
+    }
+}
+
+/// Runs a Nushell `http` command (`http get`, `http head`) against `url`, so the user's
+/// proxy settings (`HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY`) and TLS configuration
+/// apply. `headers` are extra request headers, as a record or a flat `[name value]`
+/// list like `http get --headers`, and `extra` are added by the caller; credentials in
+/// the URL are sent as basic auth. `http get` responses are requested raw.
+pub fn http_request(
+    engine: &EngineInterface,
+    span: Span,
+    command: &str,
+    url: &str,
+    headers: Option<&Value>,
+    extra: &[(&str, String)],
+) -> Result<PipelineData, LabeledError> {
+    let decl_id = engine.find_decl(command)?.ok_or_else(|| {
+        LabeledError::new(format!("`{command}` is not available in this Nushell"))
+            .with_label("cannot open network source", span)
+    })?;
+    let user_agent = format!("nu_plugin_audio_hook/{}", env!("CARGO_PKG_VERSION"));
+    let mut header_list = vec![
+        Value::string("User-Agent", span),
+        Value::string(user_agent, span),
+    ];
+    if let Some(headers) = headers {
+        header_list.extend(header_pairs(headers)?);
+    }
+    for (name, value) in extra {
+        header_list.push(Value::string(*name, span));
+        header_list.push(Value::string(value, span));
+    }
+
+    let (url, credentials) = split_credentials(url);
+    let mut call = EvaluatedCall::new(span)
+        .with_positional(Value::string(url, span))
+        .with_named(
+            Spanned {
+                item: "headers",
+                span,
+            },
+            Value::list(header_list, span),
+        );
+    if command == "http get" {
+        call.add_named(Spanned { item: "raw", span }, Value::bool(true, span));
+    }
+    if let Some((user, password)) = credentials {
+        call.add_named(Spanned { item: "user", span }, Value::string(user, span));
+        call.add_named(
+            Spanned {
+                item: "password",
+                span,
+            },
+            Value::string(password, span),
+        );
+    }
+    Ok(engine.call_decl(decl_id, call, PipelineData::empty(), true, false)?)
+}
+
+/// Flattens a `--headers` record or `[name value ...]` list into name/value strings.
+fn header_pairs(headers: &Value) -> Result<Vec<Value>, LabeledError> {
+    let span = headers.span();
+    let invalid = || {
+        LabeledError::new("invalid headers")
+            .with_label("expected a record or a list of name/value pairs", span)
+    };
+    let pairs: Vec<(String, String)> = match headers {
+        Value::Record { val, .. } => val
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), value.coerce_string().map_err(|_| invalid())?)))
+            .collect::<Result<_, LabeledError>>()?,
+        Value::List { vals, .. } if vals.len() % 2 == 0 => vals
+            .chunks(2)
+            .map(|pair| {
+                let name = pair[0].coerce_string().map_err(|_| invalid())?;
+                let value = pair[1].coerce_string().map_err(|_| invalid())?;
+                Ok((name, value))
+            })
+            .collect::<Result<_, LabeledError>>()?,
+        _ => return Err(invalid()),
+    };
+    Ok(pairs
+        .into_iter()
+        .flat_map(|(name, value)| [Value::string(name, span), Value::string(value, span)])
+        .collect())
+}
+
+/// Splits `user:password@` out of a URL's authority, percent-decoding both parts.
+fn split_credentials(url: &str) -> (String, Option<(String, String)>) {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return (url.to_string(), None);
+    };
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+    let Some((userinfo, host)) = authority.rsplit_once('@') else {
+        return (url.to_string(), None);
+    };
+    let (user, password) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+    (
+        format!("{scheme}://{host}{path}"),
+        Some((percent_decode(user), percent_decode(password))),
+    )
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = input
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+struct Buffer {
+    data: Vec<u8>,
+    /// Bytes already dropped from the front of `data` (endless streams only).
+    offset: u64,
+    done: bool,
+    error: Option<String>,
+}
+
+impl Buffer {
+    fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+}
+
+struct Shared {
+    buffer: Mutex<Buffer>,
+    arrived: Condvar,
+    read_position: AtomicU64,
+    cancel: AtomicBool,
+    reconnecting: AtomicBool,
+    size: Option<u64>,
+}
+
+impl Shared {
+    /// Waits for more data to arrive, failing once the download has been cancelled. The
+    /// wait times out now and then to check, in case the download thread is stuck in a
+    /// read and never gets to report the cancellation.
+    fn wait<'a>(&self, buffer: MutexGuard<'a, Buffer>) -> std::io::Result<MutexGuard<'a, Buffer>> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(std::io::Error::other("download cancelled"));
+        }
+        self.arrived
+            .wait_timeout(buffer, CANCEL_POLL)
+            .map(|(buffer, _)| buffer)
+            .map_err(|_| std::io::Error::other("download thread panicked"))
+    }
+}
+
+/// Opens a network stream again after it dropped, for [`Download::start_live`].
+pub type Reconnect = Box<dyn FnMut() -> Result<Box<dyn Read + Send>, String> + Send>;
+
+/// A network stream being downloaded into memory on a background thread.
+///
+/// [`DownloadReader`]s read and seek within the downloaded data and block while the
+/// bytes they need are still on their way. Dropping the download stops it.
+pub struct Download {
+    shared: Arc<Shared>,
+}
+
+impl Download {
+    pub fn start(reader: Box<dyn Read + Send>, size: Option<u64>) -> Download {
+        Download::spawn(reader, size, None)
+    }
+
+    /// Downloads an endless stream (internet radio) that is reopened with `reconnect`
+    /// whenever it ends or fails, backing off from one second up to a minute between
+    /// attempts. Only the last [`KEEP_BEHIND`] bytes before the read position are kept.
+    pub fn start_live(reader: Box<dyn Read + Send>, reconnect: Reconnect) -> Download {
+        Download::spawn(reader, None, Some(reconnect))
+    }
+
+    fn spawn(
+        mut reader: Box<dyn Read + Send>,
+        size: Option<u64>,
+        mut reconnect: Option<Reconnect>,
+    ) -> Download {
+        let shared = Arc::new(Shared {
+            buffer: Mutex::new(Buffer {
+                data: Vec::with_capacity(size.unwrap_or(0).min(1 << 30) as usize),
+                offset: 0,
+                done: false,
+                error: None,
+            }),
+            arrived: Condvar::new(),
+            read_position: AtomicU64::new(0),
+            cancel: AtomicBool::new(false),
+            reconnecting: AtomicBool::new(false),
+            size,
+        });
+        let download = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            let mut chunk = vec![0; 64 * 1024];
+            let mut backoff = RECONNECT_BACKOFF;
+            loop {
+                if download.cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                let read = reader.read(&mut chunk);
+                if let (Some(reopen), Ok(0) | Err(_)) = (reconnect.as_mut(), &read) {
+                    download.reconnecting.store(true, Ordering::Relaxed);
+                    if let Some(reopened) = reopen_after(&download.cancel, backoff, reopen) {
+                        reader = reopened;
+                        download.reconnecting.store(false, Ordering::Relaxed);
+                    }
+                    backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                    continue;
+                }
+                let Ok(mut buffer) = download.buffer.lock() else {
+                    break;
+                };
+                match read {
+                    Ok(0) => buffer.done = true,
+                    Ok(n) => {
+                        buffer.data.extend_from_slice(&chunk[..n]);
+                        backoff = RECONNECT_BACKOFF;
+                    }
+                    Err(e) => {
+                        buffer.error = Some(e.to_string());
+                        buffer.done = true;
+                    }
+                }
+                if reconnect.is_some() {
+                    let read_position = download.read_position.load(Ordering::Relaxed);
+                    let behind = read_position.saturating_sub(buffer.offset);
+                    if behind > 2 * KEEP_BEHIND {
+                        let stale = ((behind - KEEP_BEHIND) as usize).min(buffer.data.len());
+                        buffer.data.drain(..stale);
+                        buffer.offset += stale as u64;
+                    }
+                }
+                let done = buffer.done;
+                drop(buffer);
+                download.arrived.notify_all();
+                if done {
+                    break;
+                }
+            }
+            // Readers blocked on more data must learn that none is coming, or a cancelled
+            // download would leave the decoder thread waiting forever.
+            if let Ok(mut buffer) = download.buffer.lock() {
+                if !buffer.done {
+                    buffer.error = Some("download cancelled".to_string());
+                    buffer.done = true;
+                }
+            }
+            download.arrived.notify_all();
+        });
+        Download { shared }
+    }
+
+    pub fn reader(&self) -> DownloadReader {
+        DownloadReader {
+            shared: Arc::clone(&self.shared),
+            position: 0,
+        }
+    }
+
+    /// Total size, when known from the response or once the download is complete.
+    pub fn size(&self) -> Option<u64> {
+        self.shared.size.or_else(|| {
+            let buffer = self.shared.buffer.lock().ok()?;
+            buffer.done.then_some(buffer.end())
+        })
+    }
+
+    pub fn downloaded(&self) -> u64 {
+        self.shared.buffer.lock().map_or(0, |buffer| buffer.end())
+    }
+
+    pub fn finished(&self) -> bool {
+        self.shared.buffer.lock().map_or(true, |buffer| buffer.done)
+    }
+
+    /// Whether an endless stream dropped and is being reopened.
+    pub fn reconnecting(&self) -> bool {
+        self.shared.reconnecting.load(Ordering::Relaxed)
+    }
+
+    /// Audio buffered ahead of the decoder. The byte rate comes from the size and
+    /// duration when both are known, otherwise from what has been played so far.
+    pub fn ahead(&self, position: Duration, total: Option<Duration>) -> Duration {
+        let read = self.shared.read_position.load(Ordering::Relaxed);
+        let ahead_bytes = self.downloaded().saturating_sub(read);
+        let rate = match (self.size(), total) {
+            (Some(size), Some(total)) if !total.is_zero() => size as f64 / total.as_secs_f64(),
+            _ if position >= Duration::from_secs(1) => read as f64 / position.as_secs_f64(),
+            _ => ASSUMED_BYTE_RATE,
+        };
+        Duration::from_secs_f64(ahead_bytes as f64 / rate.max(1.0))
+    }
+}
+
+/// Waits `backoff` (or until cancelled) and tries to reopen the stream once.
+fn reopen_after(
+    cancel: &AtomicBool,
+    backoff: Duration,
+    reconnect: &mut Reconnect,
+) -> Option<Box<dyn Read + Send>> {
+    let deadline = std::time::Instant::now() + backoff;
+    while std::time::Instant::now() < deadline {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    reconnect().ok()
+}
+
+impl Drop for Download {
+    fn drop(&mut self) {
+        self.shared.cancel.store(true, Ordering::Relaxed);
+        self.shared.arrived.notify_all();
+    }
+}
+
+/// Read/seek view of a [`Download`], for the decoder.
+#[derive(Clone)]
+pub struct DownloadReader {
+    shared: Arc<Shared>,
+    position: u64,
+}
+
+impl DownloadReader {
+    /// A new reader at the position the last read or seek of any reader left off.
+    pub fn resume(&self) -> DownloadReader {
+        DownloadReader {
+            shared: Arc::clone(&self.shared),
+            position: self.shared.read_position.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Moves the read position forward, e.g. past data a decoder cannot make sense of.
+    pub fn skip(&mut self, bytes: u64) {
+        self.position += bytes;
+    }
+
+    /// Whether more data may still arrive after what has been downloaded.
+    pub fn pending(&self) -> bool {
+        self.shared.buffer.lock().is_ok_and(|buffer| !buffer.done)
+    }
+}
+
+impl Read for DownloadReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut buffer = self
+            .shared
+            .buffer
+            .lock()
+            .map_err(|_| std::io::Error::other("download thread panicked"))?;
+        while !buffer.done && buffer.end() <= self.position {
+            buffer = self.shared.wait(buffer)?;
+        }
+        if let Some(error) = &buffer.error {
+            if buffer.end() <= self.position {
+                return Err(std::io::Error::other(error.clone()));
+            }
+        }
+        if self.position < buffer.offset {
+            return Err(std::io::Error::other("position is no longer buffered"));
+        }
+        let start = ((self.position - buffer.offset) as usize).min(buffer.data.len());
+        let count = buf.len().min(buffer.data.len() - start);
+        buf[..count].copy_from_slice(&buffer.data[start..start + count]);
+        self.position += count as u64;
+        self.shared
+            .read_position
+            .store(self.position, Ordering::Relaxed);
+        Ok(count)
+    }
+}
+
+impl Seek for DownloadReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                let size = match self.shared.size {
+                    Some(size) => size,
+                    None => {
+                        // Without a reported size the end is only known once everything
+                        // has arrived.
+                        let mut buffer = self
+                            .shared
+                            .buffer
+                            .lock()
+                            .map_err(|_| std::io::Error::other("download thread panicked"))?;
+                        while !buffer.done {
+                            buffer = self.shared.wait(buffer)?;
+                        }
+                        buffer.end()
+                    }
+                };
+                size.checked_add_signed(offset)
+            }
+        };
+        self.position = target.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before start")
+        })?;
+        self.shared
+            .read_position
+            .store(self.position, Ordering::Relaxed);
+        Ok(self.position)
+    }
+}
+
+/// Copies everything read from a network stream into a file, for `sound play --tee`.
+pub struct Tee {
+    inner: Box<dyn Read + Send>,
+    file: BufWriter<File>,
+}
+
+impl Tee {
+    pub fn new(inner: Box<dyn Read + Send>, file: File) -> Tee {
+        Tee {
+            inner,
+            file: BufWriter::new(file),
+        }
+    }
+}
+
+impl Read for Tee {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.file.write_all(&buf[..read])?;
+        if read == 0 {
+            self.file.flush()?;
+        }
+        Ok(read)
+    }
+}
+
+/// Holds playback while the read-ahead of a [`Download`] runs low.
+///
+/// Output is held when less than a second is buffered and released once `cache` is
+/// buffered again, or the download has finished.
+pub struct Rebuffer<'a> {
+    download: &'a Download,
+    cache: Duration,
+    total: Option<Duration>,
+    buffering: bool,
+    ahead: Duration,
+}
+
+impl<'a> Rebuffer<'a> {
+    pub fn new(download: &'a Download, cache: Duration, total: Option<Duration>) -> Rebuffer<'a> {
+        Rebuffer {
+            download,
+            cache,
+            total,
+            buffering: false,
+            ahead: Duration::ZERO,
+        }
+    }
+
+    pub fn buffering(&self) -> bool {
+        self.buffering
+    }
+
+    /// Updates the state for the current playback position; returns whether output
+    /// should be held.
+    pub fn update(&mut self, position: Duration) -> bool {
+        if self.download.finished() {
+            self.buffering = false;
+            return false;
+        }
+        self.ahead = self.download.ahead(position, self.total);
+        if self.buffering {
+            self.buffering = self.ahead < self.cache;
+        } else {
+            self.buffering = self.ahead < LOW_WATER;
+        }
+        self.buffering
+    }
+
+    /// Short status for the progress line: the fill level of the cache, or `None`
+    /// once everything has been downloaded.
+    pub fn status(&self) -> Option<String> {
+        if self.download.finished() {
+            return None;
+        }
+        if self.download.reconnecting() {
+            return Some("reconnecting…".to_string());
+        }
+        let fill = (self.ahead.as_secs_f64() / self.cache.as_secs_f64().max(0.001) * 100.0)
+            .clamp(0.0, 100.0)
+            .round();
+        Some(if self.buffering {
+            format!("buffering… {fill}%")
+        } else {
+            format!("buf {fill}%")
+        })
+    }
+}
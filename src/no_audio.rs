@@ -0,0 +1,97 @@
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Signature, Span, SyntaxShape};
+use rodio::cpal::{
+    self,
+    traits::{DeviceTrait, HostTrait},
+};
+use std::io::Write;
+
+use crate::config::plugin_config_value;
+
+/// Code of the error raised when there is no audio output, so scripts can tell a
+/// headless machine apart from other failures.
+pub const NO_AUDIO_CODE: &str = "audio_hook::no_audio_output";
+
+/// `--if-no-audio` policies: the name used on the command line, the policy and a
+/// description for completions.
+pub const NO_AUDIO_POLICIES: [(&str, NoAudio, &str); 3] = [
+    (
+        "error",
+        NoAudio::Error,
+        "fail with audio_hook::no_audio_output (default)",
+    ),
+    ("skip", NoAudio::Skip, "return without playing anything"),
+    ("bell", NoAudio::Bell, "ring the terminal bell instead"),
+];
+
+/// What a playback command does on a machine without an audio output, such as a server
+/// without ALSA or PulseAudio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoAudio {
+    Error,
+    Skip,
+    Bell,
+}
+
+/// Adds the `--if-no-audio` flag to a playback command's signature.
+pub fn no_audio_flags(signature: Signature) -> Signature {
+    signature.named(
+        "if-no-audio",
+        SyntaxShape::String,
+        "without an audio output: error (default), skip or bell (config: if_no_audio)",
+        None,
+    )
+}
+
+impl NoAudio {
+    /// Reads `--if-no-audio`, falling back to `if_no_audio` in the plugin config.
+    pub fn from_call(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<NoAudio, LabeledError> {
+        let value = match call.get_flag_value("if-no-audio") {
+            Some(value) => Some(value),
+            None => plugin_config_value(engine, "if_no_audio", call.head)?,
+        };
+        let Some(value) = value else {
+            return Ok(NoAudio::Error);
+        };
+        let span = value.span();
+        let name = value.as_str().map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("expected error, skip or bell", span)
+        })?;
+        NO_AUDIO_POLICIES
+            .iter()
+            .find(|(policy, _, _)| policy.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, policy, _)| *policy)
+            .ok_or_else(|| {
+                LabeledError::new(format!("unknown --if-no-audio policy '{name}'"))
+                    .with_label("expected error, skip or bell", span)
+            })
+    }
+
+    /// Checks for a usable default output device. Without one, `error` fails with
+    /// [`NO_AUDIO_CODE`], while `skip` and `bell` return `false` so the caller can
+    /// return without playing.
+    pub fn output_available(self, span: Span) -> Result<bool, LabeledError> {
+        let available = cpal::default_host()
+            .default_output_device()
+            .is_some_and(|device| device.default_output_config().is_ok());
+        if available {
+            return Ok(true);
+        }
+        match self {
+            NoAudio::Error => Err(LabeledError::new("no audio output available")
+                .with_code(NO_AUDIO_CODE)
+                .with_label("cannot play without an output device", span)
+                .with_help("pass --if-no-audio skip or bell to carry on in headless environments")),
+            NoAudio::Skip => Ok(false),
+            NoAudio::Bell => {
+                // stdout carries the plugin protocol; the terminal is on stderr.
+                let mut stderr = std::io::stderr();
+                let _ = stderr.write_all(b"\x07").and_then(|_| stderr.flush());
+                Ok(false)
+            }
+        }
+    }
+}
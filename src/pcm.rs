@@ -0,0 +1,307 @@
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Span, Value};
+use rodio::{buffer::SamplesBuffer, Decoder, Source};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{
+    gapless::read_gapless,
+    utils::load_file,
+    watchdog::{Watchdog, Watched},
+    wav::encode_wav,
+};
+
+/// A fully decoded clip held in memory as interleaved `f32` samples.
+///
+/// Offline analysis and effects commands decode once into a `Pcm` instead of streaming
+/// through rodio, since they need random access to the whole signal.
+#[derive(Clone)]
+pub struct Pcm {
+    pub samples: Vec<f32>,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+impl Pcm {
+    /// Decodes the file named by the first positional argument.
+    pub fn load(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<(Span, PathBuf, Pcm), LabeledError> {
+        let (span, file, path) = load_file(engine, call)?;
+        Ok((span, path, Pcm::decode(file, span)?))
+    }
+
+    /// Like [`Pcm::load`], but with `--gapless` the encoder delay and padding recorded in
+    /// the file's LAME or `iTunSMPB` tag are trimmed after decoding, so the clip starts
+    /// and ends exactly where the original audio did. Without tags the clip is decoded
+    /// untrimmed.
+    pub fn load_gapless(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<(Span, PathBuf, Pcm), LabeledError> {
+        if !call.has_flag("gapless")? {
+            return Pcm::load(engine, call);
+        }
+        let (span, file, path) = load_file(engine, call)?;
+        let len = file.metadata().map(|m| m.len()).map_err(|err| {
+            LabeledError::new(err.to_string()).with_label("error reading file metadata", span)
+        })?;
+        // Decoders that trim on their own only know some tags; trim here instead, once.
+        let pcm = Pcm::decode_watched(file, Some(len), false, span)?;
+        let pcm = match read_gapless(&path) {
+            Some(gapless) => pcm.slice(
+                gapless.encoder_delay as usize,
+                pcm.frames().saturating_sub(gapless.padding as usize),
+            ),
+            None => pcm,
+        };
+        Ok((span, path, pcm))
+    }
+
+    pub fn decode(file: File, span: Span) -> Result<Pcm, LabeledError> {
+        let len = file.metadata().ok().map(|m| m.len());
+        Pcm::decode_watched(file, len, true, span)
+    }
+
+    /// Decodes `reader` under a [`Watchdog`], so a corrupt file that sends the decoder
+    /// into an endless loop fails with [`STALL_CODE`](crate::watchdog::STALL_CODE)
+    /// instead of hanging the command.
+    fn decode_watched<R>(
+        reader: R,
+        byte_len: Option<u64>,
+        gapless: bool,
+        span: Span,
+    ) -> Result<Pcm, LabeledError>
+    where
+        R: Read + Seek + Send + Sync + 'static,
+    {
+        Watchdog::run(span, move |watchdog| {
+            let source = open_watched(&watchdog, reader, byte_len, gapless, span)?;
+            let channels = source.channels();
+            let sample_rate = source.sample_rate();
+            let samples = source.inspect(|_| watchdog.tick()).collect();
+            Ok(Pcm::new(samples, channels, sample_rate))
+        })
+    }
+
+    /// Decodes only `section` of `reader`, a file of `byte_len` bytes, seeking to its start
+    /// rather than decoding the audio before it.
+    pub fn decode_section<R>(
+        byte_len: u64,
+        reader: R,
+        section: Section,
+        span: Span,
+    ) -> Result<Pcm, LabeledError>
+    where
+        R: Read + Seek + Send + Sync + 'static,
+    {
+        Watchdog::run(span, move |watchdog| {
+            let mut builder = Decoder::builder()
+                .with_data(BufReader::new(watchdog.reader(reader)))
+                .with_byte_len(byte_len)
+                .with_seekable(true)
+                .with_coarse_seek(section.coarse);
+            if let Some(hint) = &section.hint {
+                builder = builder.with_hint(hint);
+            }
+            let mut source = builder.build().map_err(|err| {
+                LabeledError::new(err.to_string()).with_label("audio decoder exception", span)
+            })?;
+            if !section.start.is_zero() {
+                source.try_seek(section.start).map_err(|err| {
+                    LabeledError::new(err.to_string()).with_label("cannot seek to --start", span)
+                })?;
+            }
+            let channels = source.channels();
+            let sample_rate = source.sample_rate();
+            let limit = section.length.map_or(usize::MAX, |length| {
+                (length.as_secs_f64() * sample_rate as f64).round() as usize * channels as usize
+            });
+            let samples = source.take(limit).inspect(|_| watchdog.tick()).collect();
+            Ok(Pcm::new(samples, channels, sample_rate))
+        })
+    }
+
+    pub fn new(samples: Vec<f32>, channels: u16, sample_rate: u32) -> Pcm {
+        Pcm {
+            samples,
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// Number of sample frames (one sample per channel).
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+
+    /// Converts a duration to a frame count at this clip's sample rate.
+    pub fn frames_for(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * self.sample_rate as f64).round() as usize
+    }
+
+    /// Averages all channels down to a single mono signal.
+    pub fn mono(&self) -> Vec<f32> {
+        let channels = self.channels.max(1) as usize;
+        self.samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    }
+
+    /// Copies out frames `start..end` as a new clip.
+    pub fn slice(&self, start: usize, end: usize) -> Pcm {
+        let channels = self.channels as usize;
+        let end = end.min(self.frames());
+        let start = start.min(end);
+        Pcm::new(
+            self.samples[start * channels..end * channels].to_vec(),
+            self.channels,
+            self.sample_rate,
+        )
+    }
+
+    /// Plays the clip back `speed` times faster, shifting pitch along with tempo the way a
+    /// tape or sampler would. Uses linear interpolation between neighbouring frames.
+    pub fn varispeed(&self, speed: f64) -> Pcm {
+        let channels = self.channels as usize;
+        let frames = self.frames();
+        let out_frames = (frames as f64 / speed).floor() as usize;
+        let mut samples = Vec::with_capacity(out_frames * channels);
+        for i in 0..out_frames {
+            let position = i as f64 * speed;
+            let index = position as usize;
+            let frac = (position - index as f64) as f32;
+            let next = (index + 1).min(frames - 1);
+            for c in 0..channels {
+                let a = self.samples[index * channels + c];
+                let b = self.samples[next * channels + c];
+                samples.push(a + (b - a) * frac);
+            }
+        }
+        Pcm::new(samples, self.channels, self.sample_rate)
+    }
+
+    /// Encodes the clip as a 16-bit WAV, the format every `--data`-style output uses.
+    pub fn to_wav(&self) -> Result<Vec<u8>, LabeledError> {
+        encode_wav(SamplesBuffer::new(
+            self.channels,
+            self.sample_rate,
+            self.samples.clone(),
+        ))
+    }
+}
+
+/// The part of a file [`Pcm::decode_section`] decodes.
+pub struct Section {
+    pub start: Duration,
+    /// Up to the end of the file when `None`.
+    pub length: Option<Duration>,
+    /// Seek by estimating the position from the bitrate instead of walking the file,
+    /// which keeps range reads of a remote file down to the blocks around `start`.
+    pub coarse: bool,
+    /// File extension, for formats the decoder cannot tell from their first bytes.
+    pub hint: Option<String>,
+}
+
+/// Per-block summaries of a clip that was never held in memory; see [`load_blocks`].
+pub struct Blocks {
+    pub sample_rate: u32,
+    /// Frames per block; the last block may be shorter.
+    pub block_frames: usize,
+    /// Length of the whole clip in frames.
+    pub frames: usize,
+    pub values: Vec<f64>,
+}
+
+impl Blocks {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames as f64 / self.sample_rate as f64)
+    }
+}
+
+/// Streams the file named by the first positional argument through the decoder, mixing it
+/// down to mono and reducing every `block` of audio to one value with `summarize`.
+///
+/// For analyses that only need e.g. a level per block, this keeps memory flat no matter
+/// how long the recording is, where [`Pcm::load`] would hold every sample.
+pub fn load_blocks(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    block: Duration,
+    summarize: fn(&[f32]) -> f64,
+) -> Result<Blocks, LabeledError> {
+    let (span, file, _) = load_file(engine, call)?;
+    let len = file.metadata().ok().map(|m| m.len());
+    Watchdog::run(span, move |watchdog| {
+        let source = open_watched(&watchdog, file, len, true, span)?;
+        let channels = source.channels().max(1) as usize;
+        let sample_rate = source.sample_rate();
+        let block_frames = ((block.as_secs_f64() * sample_rate as f64).round() as usize).max(1);
+
+        let mut values = Vec::new();
+        let mut buffer = Vec::with_capacity(block_frames);
+        let (mut frame, mut filled, mut frames) = (0.0f32, 0, 0);
+        for sample in source {
+            watchdog.tick();
+            frame += sample;
+            filled += 1;
+            if filled < channels {
+                continue;
+            }
+            buffer.push(frame / channels as f32);
+            (frame, filled) = (0.0, 0);
+            frames += 1;
+            if buffer.len() == block_frames {
+                values.push(summarize(&buffer));
+                buffer.clear();
+            }
+        }
+        if !buffer.is_empty() {
+            values.push(summarize(&buffer));
+        }
+        Ok(Blocks {
+            sample_rate,
+            block_frames,
+            frames,
+            values,
+        })
+    })
+}
+
+/// Opens a decoder that reads `reader` through `watchdog`.
+fn open_watched<R>(
+    watchdog: &Watchdog,
+    reader: R,
+    byte_len: Option<u64>,
+    gapless: bool,
+    span: Span,
+) -> Result<Decoder<BufReader<Watched<R>>>, LabeledError>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    let mut builder = Decoder::builder()
+        .with_data(BufReader::new(watchdog.reader(reader)))
+        .with_seekable(true)
+        .with_gapless(gapless);
+    if let Some(len) = byte_len {
+        builder = builder.with_byte_len(len);
+    }
+    builder.build().map_err(|err| {
+        LabeledError::new(err.to_string()).with_label("audio decoder exception", span)
+    })
+}
+
+/// Builds a nushell duration from a position in seconds.
+pub fn seconds_value(seconds: f64, span: Span) -> Value {
+    Value::duration((seconds * 1e9).round() as i64, span)
+}
@@ -0,0 +1,487 @@
+// PNG decoding (every color type and bit depth, interlaced or not) and 8-bit encoding,
+// enough to shrink and convert cover art.
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+
+use crate::raster::{Raster, MAX_PIXELS};
+
+pub const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Origin and spacing of the seven passes of an Adam7-interlaced image.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+struct Header {
+    width: usize,
+    height: usize,
+    depth: u8,
+    color: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+}
+
+/// Decodes a PNG image.
+pub fn decode(data: &[u8]) -> Result<Raster, String> {
+    if !data.starts_with(SIGNATURE) {
+        return Err("not a PNG".into());
+    }
+    let (mut header, mut palette, mut transparency, mut compressed) =
+        (None, Vec::new(), Vec::new(), Vec::new());
+    let mut pos = SIGNATURE.len();
+    while let Some(length) = data.get(pos..pos + 4) {
+        let length = u32::from_be_bytes(length.try_into().expect("4 bytes")) as usize;
+        let kind = data.get(pos + 4..pos + 8).ok_or("truncated PNG")?;
+        let body = data.get(pos + 8..pos + 8 + length).ok_or("truncated PNG")?;
+        pos += 12 + length;
+        match kind {
+            b"IHDR" => header = Some(read_header(body)?),
+            b"PLTE" => {
+                palette = body
+                    .chunks_exact(3)
+                    .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                    .collect()
+            }
+            b"tRNS" => transparency = body.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+    let header = header.ok_or("PNG without a header")?;
+
+    let bits_per_pixel = header.channels() * header.depth as usize;
+    let passes: Vec<(usize, usize, usize, usize)> = if header.interlaced {
+        ADAM7.to_vec()
+    } else {
+        vec![(0, 0, 1, 1)]
+    };
+    // Size of each pass, skipping the empty passes of a small interlaced image.
+    let passes: Vec<_> = passes
+        .into_iter()
+        .map(|(x0, y0, dx, dy)| {
+            let (w, h) = (
+                (header.width + dx - 1 - x0) / dx,
+                (header.height + dy - 1 - y0) / dy,
+            );
+            (x0, y0, dx, dy, w, h)
+        })
+        .filter(|&(.., w, h)| w > 0 && h > 0)
+        .collect();
+    let expected: usize = passes
+        .iter()
+        .map(|&(.., w, h)| h * (1 + (w * bits_per_pixel).div_ceil(8)))
+        .sum();
+    let mut raw = Vec::with_capacity(expected);
+    ZlibDecoder::new(&compressed[..])
+        .take(expected as u64)
+        .read_to_end(&mut raw)
+        .map_err(|_| "corrupt PNG data")?;
+    if raw.len() < expected {
+        return Err("truncated PNG data".into());
+    }
+
+    let mut pixels = vec![[0u8; 4]; header.width * header.height];
+    let step = bits_per_pixel.div_ceil(8);
+    let mut offset = 0;
+    for (x0, y0, dx, dy, w, h) in passes {
+        let row_bytes = (w * bits_per_pixel).div_ceil(8);
+        let mut previous = vec![0u8; row_bytes];
+        for y in 0..h {
+            let filter = raw[offset];
+            let line = &mut raw[offset + 1..offset + 1 + row_bytes];
+            unfilter(filter, line, &previous, step)?;
+            previous.copy_from_slice(line);
+            offset += 1 + row_bytes;
+            for x in 0..w {
+                let pixel = to_rgba(&header, &previous, x, &palette, &transparency)?;
+                pixels[(y0 + y * dy) * header.width + x0 + x * dx] = pixel;
+            }
+        }
+    }
+    Ok(Raster {
+        width: header.width,
+        height: header.height,
+        pixels,
+    })
+}
+
+fn read_header(body: &[u8]) -> Result<Header, String> {
+    if body.len() < 13 {
+        return Err("truncated PNG header".into());
+    }
+    let width = u32::from_be_bytes(body[0..4].try_into().expect("4 bytes")) as usize;
+    let height = u32::from_be_bytes(body[4..8].try_into().expect("4 bytes")) as usize;
+    let (depth, color) = (body[8], body[9]);
+    let valid = match color {
+        0 => matches!(depth, 1 | 2 | 4 | 8 | 16),
+        3 => matches!(depth, 1 | 2 | 4 | 8),
+        2 | 4 | 6 => matches!(depth, 8 | 16),
+        _ => false,
+    };
+    if !valid || body[10] != 0 || body[11] != 0 || body[12] > 1 {
+        return Err("invalid PNG header".into());
+    }
+    if width == 0 || height == 0 || width > 65535 || height > 65535 || width * height > MAX_PIXELS {
+        return Err(format!("{width}x{height} pixels is too large"));
+    }
+    Ok(Header {
+        width,
+        height,
+        depth,
+        color,
+        interlaced: body[12] == 1,
+    })
+}
+
+/// Reverses the filter of one row in place; `step` is the bytes per pixel, at least one.
+fn unfilter(filter: u8, line: &mut [u8], previous: &[u8], step: usize) -> Result<(), String> {
+    for i in 0..line.len() {
+        let left = if i >= step { line[i - step] } else { 0 };
+        let up = previous[i];
+        let up_left = if i >= step { previous[i - step] } else { 0 };
+        let predicted = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return Err("corrupt PNG data".into()),
+        };
+        line[i] = line[i].wrapping_add(predicted);
+    }
+    Ok(())
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (a, b, c) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - up_left as i16).abs(),
+    );
+    if a <= b && a <= c {
+        left
+    } else if b <= c {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// The pixel at `x` of an unfiltered row.
+fn to_rgba(
+    header: &Header,
+    line: &[u8],
+    x: usize,
+    palette: &[[u8; 3]],
+    transparency: &[u8],
+) -> Result<[u8; 4], String> {
+    let channels = header.channels();
+    let depth = header.depth as usize;
+    let sample = |c: usize| -> u16 {
+        let index = x * channels + c;
+        match depth {
+            16 => u16::from_be_bytes([line[2 * index], line[2 * index + 1]]),
+            8 => line[index] as u16,
+            _ => {
+                let per_byte = 8 / depth;
+                let shift = 8 - depth * (index % per_byte + 1);
+                (line[index / per_byte] >> shift) as u16 & ((1 << depth) - 1)
+            }
+        }
+    };
+    let to_8bit = |value: u16| match depth {
+        16 => (value >> 8) as u8,
+        8 => value as u8,
+        _ => (value as u32 * 255 / ((1 << depth) - 1)) as u8,
+    };
+    // For gray and RGB images, tRNS names one fully transparent color.
+    let transparent = |values: &[u16]| {
+        transparency.len() >= 2 * values.len()
+            && values.iter().enumerate().all(|(i, &v)| {
+                u16::from_be_bytes([transparency[2 * i], transparency[2 * i + 1]]) == v
+            })
+    };
+    Ok(match header.color {
+        0 => {
+            let gray = sample(0);
+            let g = to_8bit(gray);
+            [g, g, g, if transparent(&[gray]) { 0 } else { 255 }]
+        }
+        2 => {
+            let rgb = [sample(0), sample(1), sample(2)];
+            let alpha = if transparent(&rgb) { 0 } else { 255 };
+            [to_8bit(rgb[0]), to_8bit(rgb[1]), to_8bit(rgb[2]), alpha]
+        }
+        3 => {
+            let index = sample(0) as usize;
+            let [r, g, b] = *palette.get(index).ok_or("PNG palette index out of range")?;
+            [r, g, b, transparency.get(index).copied().unwrap_or(255)]
+        }
+        4 => {
+            let g = to_8bit(sample(0));
+            [g, g, g, to_8bit(sample(1))]
+        }
+        _ => [
+            to_8bit(sample(0)),
+            to_8bit(sample(1)),
+            to_8bit(sample(2)),
+            to_8bit(sample(3)),
+        ],
+    })
+}
+
+/// Encodes `image` as an 8-bit RGB PNG, or RGBA when any pixel is transparent.
+pub fn encode(image: &Raster) -> Vec<u8> {
+    let alpha = image.pixels.iter().any(|pixel| pixel[3] != 255);
+    let channels = if alpha { 4 } else { 3 };
+    let row_bytes = image.width * channels;
+
+    // Filter each row with whichever filter leaves the smallest residuals, the usual
+    // heuristic for compressing photos well.
+    let mut filtered = Vec::with_capacity(image.height * (row_bytes + 1));
+    let mut previous = vec![0u8; row_bytes];
+    let mut line = Vec::with_capacity(row_bytes);
+    let mut candidate = vec![0u8; row_bytes];
+    for row in image.pixels.chunks_exact(image.width) {
+        line.clear();
+        line.extend(
+            row.iter()
+                .flat_map(|pixel| pixel[..channels].iter().copied()),
+        );
+        let mut best: Option<(u64, u8, Vec<u8>)> = None;
+        for filter in 0..=4u8 {
+            for i in 0..row_bytes {
+                let left = if i >= channels { line[i - channels] } else { 0 };
+                let up_left = if i >= channels {
+                    previous[i - channels]
+                } else {
+                    0
+                };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => previous[i],
+                    3 => ((left as u16 + previous[i] as u16) / 2) as u8,
+                    _ => paeth(left, previous[i], up_left),
+                };
+                candidate[i] = line[i].wrapping_sub(predicted);
+            }
+            let cost = candidate
+                .iter()
+                .map(|&b| (b as i8).unsigned_abs() as u64)
+                .sum();
+            if best
+                .as_ref()
+                .is_none_or(|(best_cost, ..)| cost < *best_cost)
+            {
+                best = Some((cost, filter, candidate.clone()));
+            }
+        }
+        let (_, filter, bytes) = best.expect("five filters were tried");
+        filtered.push(filter);
+        filtered.extend_from_slice(&bytes);
+        std::mem::swap(&mut previous, &mut line);
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&filtered).expect("writing to memory");
+    let compressed = encoder.finish().expect("writing to memory");
+
+    let mut header = Vec::with_capacity(13);
+    header.extend((image.width as u32).to_be_bytes());
+    header.extend((image.height as u32).to_be_bytes());
+    header.extend([8, if alpha { 6 } else { 2 }, 0, 0, 0]);
+
+    let mut out = SIGNATURE.to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &compressed);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+    out.extend((body.len() as u32).to_be_bytes());
+    out.extend(kind);
+    out.extend(body);
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(body);
+    out.extend(crc.finalize().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize, alpha: bool) -> Raster {
+        let pixels = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as u8, (i / width) as u8);
+                let a = if alpha { x.wrapping_mul(40) } else { 255 };
+                [x.wrapping_mul(20), y.wrapping_mul(30), x ^ y, a]
+            })
+            .collect();
+        Raster {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// A PNG with `header`'s fields and the unfiltered rows of `passes`, each pass a list
+    /// of rows, plus any `extra` chunks before the image data.
+    fn png(header: [u8; 13], passes: &[Vec<Vec<u8>>], extra: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let raw: Vec<u8> = passes
+            .iter()
+            .flatten()
+            .flat_map(|row| std::iter::once(0).chain(row.iter().copied()))
+            .collect();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(&raw).unwrap();
+        let mut out = SIGNATURE.to_vec();
+        chunk(&mut out, b"IHDR", &header);
+        for (kind, body) in extra {
+            chunk(&mut out, kind, body);
+        }
+        chunk(&mut out, b"IDAT", &encoder.finish().unwrap());
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    fn header(width: u32, height: u32, depth: u8, color: u8, interlaced: bool) -> [u8; 13] {
+        let mut header = [0; 13];
+        header[..4].copy_from_slice(&width.to_be_bytes());
+        header[4..8].copy_from_slice(&height.to_be_bytes());
+        header[8..].copy_from_slice(&[depth, color, 0, 0, interlaced as u8]);
+        header
+    }
+
+    #[test]
+    fn round_trips_opaque_and_transparent_images() {
+        for alpha in [false, true] {
+            let image = gradient(13, 7, alpha);
+            let decoded = decode(&encode(&image)).unwrap();
+            assert_eq!((decoded.width, decoded.height), (13, 7));
+            assert_eq!(decoded.pixels, image.pixels, "alpha: {alpha}");
+        }
+    }
+
+    #[test]
+    fn decodes_adam7_interlaced_images() {
+        let image = gradient(11, 9, true);
+        let passes: Vec<Vec<Vec<u8>>> = ADAM7
+            .iter()
+            .map(|&(x0, y0, dx, dy)| {
+                (y0..image.height)
+                    .step_by(dy)
+                    .map(|y| {
+                        (x0..image.width)
+                            .step_by(dx)
+                            .flat_map(|x| image.pixels[y * image.width + x])
+                            .collect::<Vec<u8>>()
+                    })
+                    .filter(|row| !row.is_empty())
+                    .collect()
+            })
+            .collect();
+        let decoded = decode(&png(header(11, 9, 8, 6, true), &passes, &[])).unwrap();
+        assert_eq!(decoded.pixels, image.pixels);
+    }
+
+    #[test]
+    fn decodes_palettes_and_low_bit_depths() {
+        // Two-bit palette indices 0, 1, 2, 1 packed into one byte; index 1 is transparent.
+        let palette: &[u8] = &[255, 0, 0, 0, 255, 0, 0, 0, 255];
+        let data = png(
+            header(4, 1, 2, 3, false),
+            &[vec![vec![0b00_01_10_01]]],
+            &[(b"PLTE", palette), (b"tRNS", &[255, 0])],
+        );
+        let decoded = decode(&data).unwrap();
+        assert_eq!(
+            decoded.pixels,
+            vec![
+                [255, 0, 0, 255],
+                [0, 255, 0, 0],
+                [0, 0, 255, 255],
+                [0, 255, 0, 0]
+            ]
+        );
+        // Four-bit gray scales up to the full 8-bit range.
+        let data = png(header(2, 1, 4, 0, false), &[vec![vec![0x0F]]], &[]);
+        let decoded = decode(&data).unwrap();
+        assert_eq!(decoded.pixels, vec![[0, 0, 0, 255], [255, 255, 255, 255]]);
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        let data = encode(&gradient(13, 7, false));
+        // The image data is followed by its CRC, which is not checked, and IEND.
+        let image_end = data.len() - 16;
+        for len in 0..image_end {
+            assert!(
+                decode(&data[..len]).is_err(),
+                "cut at {len} of {}",
+                data.len()
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        let rows = vec![vec![vec![1, 2, 3]]];
+        // An unknown filter type.
+        let mut data = png(header(1, 1, 8, 2, false), &rows, &[]);
+        let raw = [5, 1, 2, 3];
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(&raw).unwrap();
+        let idat = encoder.finish().unwrap();
+        data.truncate(SIGNATURE.len() + 25);
+        chunk(&mut data, b"IDAT", &idat);
+        assert_eq!(decode(&data).err().unwrap(), "corrupt PNG data");
+        // A palette index past the palette.
+        let data = png(header(1, 1, 8, 3, false), &[vec![vec![4]]], &[]);
+        assert_eq!(
+            decode(&data).err().unwrap(),
+            "PNG palette index out of range"
+        );
+        // A bit depth the color type does not allow, and no size.
+        let data = png(header(1, 1, 4, 2, false), &rows, &[]);
+        assert_eq!(decode(&data).err().unwrap(), "invalid PNG header");
+        let data = png(header(0, 1, 8, 2, false), &rows, &[]);
+        assert!(decode(&data).is_err());
+        // Image data that is not zlib.
+        let mut data = png(header(1, 1, 8, 2, false), &rows, &[]);
+        data.truncate(SIGNATURE.len() + 25);
+        chunk(&mut data, b"IDAT", b"not zlib");
+        assert_eq!(decode(&data).err().unwrap(), "corrupt PNG data");
+    }
+
+    #[test]
+    fn oversized_images_are_rejected_before_decoding() {
+        let data = png(header(65535, 65535, 8, 6, false), &[], &[]);
+        assert_eq!(
+            decode(&data).err().unwrap(),
+            "65535x65535 pixels is too large"
+        );
+    }
+}
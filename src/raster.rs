@@ -0,0 +1,223 @@
+// Decoded images for `sound meta artwork set`: downscaling and EXIF orientation on top
+// of the JPEG and PNG codecs.
+
+use nu_protocol::{LabeledError, Span};
+
+use crate::{jpeg, png};
+
+/// Largest image decoded, in pixels, so a hostile file cannot exhaust memory.
+pub const MAX_PIXELS: usize = 64 * 1024 * 1024;
+
+/// An image of 8-bit RGBA pixels.
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels row by row, top to bottom.
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Raster {
+    /// Decodes a JPEG or PNG image.
+    pub fn decode(data: &[u8], span: Span) -> Result<Raster, LabeledError> {
+        let decoded = if data.starts_with(&[0xFF, 0xD8]) {
+            jpeg::decode(data)
+        } else if data.starts_with(png::SIGNATURE) {
+            png::decode(data)
+        } else {
+            Err("only JPEG and PNG images can be resized or converted".to_string())
+        };
+        decoded.map_err(|why| LabeledError::new("cannot decode image").with_label(why, span))
+    }
+
+    /// Shrinks the image so neither side exceeds `max_side`, keeping its aspect ratio.
+    /// Each output pixel averages the source pixels it covers, weighted by alpha so
+    /// transparent pixels do not darken their neighbours.
+    ///
+    /// Rows are narrowed straight from the 8-bit pixels, so the only float buffer is
+    /// already `max_side` wide rather than a copy of the whole source.
+    pub fn fit(self, max_side: usize) -> Raster {
+        let largest = self.width.max(self.height);
+        if largest <= max_side {
+            return self;
+        }
+        let scale = max_side as f64 / largest as f64;
+        let width = ((self.width as f64 * scale).round() as usize).clamp(1, max_side);
+        let height = ((self.height as f64 * scale).round() as usize).clamp(1, max_side);
+
+        let premultiplied = |i: usize| {
+            let [r, g, b, a] = self.pixels[i];
+            let alpha = a as f32 / 255.0;
+            [
+                r as f32 * alpha,
+                g as f32 * alpha,
+                b as f32 * alpha,
+                a as f32,
+            ]
+        };
+        let rows = resample(premultiplied, self.height, self.width, width, 1, self.width);
+        let resized = resample(|i| rows[i], width, self.height, height, width, 1);
+        let pixels = resized
+            .into_iter()
+            .map(|[r, g, b, a]| {
+                let unpremultiply = |c: f32| {
+                    if a > 0.0 {
+                        (c * 255.0 / a).round().clamp(0.0, 255.0) as u8
+                    } else {
+                        0
+                    }
+                };
+                [
+                    unpremultiply(r),
+                    unpremultiply(g),
+                    unpremultiply(b),
+                    a.round().clamp(0.0, 255.0) as u8,
+                ]
+            })
+            .collect();
+        Raster {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Turns the image upright according to an EXIF orientation (1 to 8).
+    pub fn oriented(self, orientation: u16) -> Raster {
+        if !(2..=8).contains(&orientation) {
+            return self;
+        }
+        let (w, h) = (self.width, self.height);
+        let (width, height) = if orientation >= 5 { (h, w) } else { (w, h) };
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = match orientation {
+                    2 => (w - 1 - x, y),
+                    3 => (w - 1 - x, h - 1 - y),
+                    4 => (x, h - 1 - y),
+                    5 => (y, x),
+                    6 => (y, h - 1 - x),
+                    7 => (w - 1 - y, h - 1 - x),
+                    _ => (w - 1 - y, x),
+                };
+                pixels.push(self.pixels[sy * w + sx]);
+            }
+        }
+        Raster {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Box-filters `lines` lines of `from` samples each down to `to` samples, reading the
+/// input sample at each index from `input`. Samples of a line are `step` apart and lines
+/// `stride` apart; the output is laid out the same way, with `to` in place of `from`.
+fn resample(
+    input: impl Fn(usize) -> [f32; 4],
+    lines: usize,
+    from: usize,
+    to: usize,
+    step: usize,
+    stride: usize,
+) -> Vec<[f32; 4]> {
+    let ratio = from as f64 / to as f64;
+    // Source samples and their weights for each output sample.
+    let weights: Vec<Vec<(usize, f32)>> = (0..to)
+        .map(|i| {
+            let (start, end) = (i as f64 * ratio, (i + 1) as f64 * ratio);
+            (start.floor() as usize..(end.ceil() as usize).min(from))
+                .map(|j| {
+                    let cover = end.min(j as f64 + 1.0) - start.max(j as f64);
+                    (j, (cover / ratio) as f32)
+                })
+                .collect()
+        })
+        .collect();
+
+    // Rows resampled along x stay rows; columns resampled along y stay columns.
+    let (out_step, out_stride) = if step == 1 { (1, to) } else { (step, 1) };
+    let mut output = vec![[0.0f32; 4]; lines * to];
+    for line in 0..lines {
+        for (i, sources) in weights.iter().enumerate() {
+            let mut sum = [0.0f32; 4];
+            for &(j, weight) in sources {
+                let sample = input(line * stride + j * step);
+                for (total, value) in sum.iter_mut().zip(sample) {
+                    *total += value * weight;
+                }
+            }
+            output[line * out_stride + i * out_step] = sum;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A raster whose pixels are told apart by their red channel.
+    fn labelled(width: usize, height: usize, labels: &[u8]) -> Raster {
+        Raster {
+            width,
+            height,
+            pixels: labels.iter().map(|&label| [label, 0, 0, 255]).collect(),
+        }
+    }
+
+    fn labels(image: &Raster) -> (usize, usize, Vec<u8>) {
+        let labels = image.pixels.iter().map(|pixel| pixel[0]).collect();
+        (image.width, image.height, labels)
+    }
+
+    #[test]
+    fn every_exif_orientation_turns_the_image_upright() {
+        // How `a b c / d e f`, stored with each orientation, looks once upright.
+        let (a, b, c, d, e, f) = (1, 2, 3, 4, 5, 6);
+        let cases = [
+            (1, (3, 2, vec![a, b, c, d, e, f])),
+            (2, (3, 2, vec![c, b, a, f, e, d])),
+            (3, (3, 2, vec![f, e, d, c, b, a])),
+            (4, (3, 2, vec![d, e, f, a, b, c])),
+            (5, (2, 3, vec![a, d, b, e, c, f])),
+            (6, (2, 3, vec![d, a, e, b, f, c])),
+            (7, (2, 3, vec![f, c, e, b, d, a])),
+            (8, (2, 3, vec![c, f, b, e, a, d])),
+        ];
+        for (orientation, expected) in cases {
+            let image = labelled(3, 2, &[a, b, c, d, e, f]).oriented(orientation);
+            assert_eq!(labels(&image), expected, "orientation {orientation}");
+        }
+        // Out-of-range values leave the image as it is.
+        let image = labelled(3, 2, &[a, b, c, d, e, f]).oriented(9);
+        assert_eq!(labels(&image), (3, 2, vec![a, b, c, d, e, f]));
+    }
+
+    #[test]
+    fn fit_averages_the_pixels_each_output_pixel_covers() {
+        let image = labelled(4, 2, &[0, 100, 200, 200, 100, 100, 0, 0]).fit(2);
+        assert_eq!(labels(&image), (2, 1, vec![75, 100]));
+        // Already small enough: untouched.
+        let image = labelled(2, 1, &[7, 9]).fit(2);
+        assert_eq!(labels(&image), (2, 1, vec![7, 9]));
+    }
+
+    #[test]
+    fn fit_ignores_the_color_of_transparent_pixels() {
+        let image = Raster {
+            width: 2,
+            height: 1,
+            pixels: vec![[255, 255, 255, 255], [0, 0, 0, 0]],
+        }
+        .fit(1);
+        assert_eq!(image.pixels, vec![[255, 255, 255, 128]]);
+    }
+
+    #[test]
+    fn decode_rejects_other_formats() {
+        let error = Raster::decode(b"GIF89a", Span::unknown()).err().unwrap();
+        assert_eq!(error.msg, "cannot decode image");
+    }
+}
@@ -0,0 +1,191 @@
+use log::{info, warn};
+use nu_protocol::{LabeledError, Span, Spanned};
+use rodio::{
+    queue::SourcesQueueOutput,
+    source::{SeekError, Source},
+    ChannelCount, OutputStream, SampleRate, Sink,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::Backend,
+    stream_identity::{open_identified, StreamRole},
+};
+
+/// Wait before the first attempt to reopen a failed output; doubled after every
+/// failed attempt, up to [`MAX_RETRY_DELAY`].
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(4);
+
+/// How long a lost output is retried before playback gives up.
+const RECOVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An output stream that survives device errors.
+///
+/// The sink's queue is not added to the stream's mixer directly but through a
+/// [`Relay`], so it outlives the stream. When the audio host reports a stream error,
+/// such as a USB DAC dropping off the bus for a moment, the stream is dropped and
+/// reopened with backoff. Nothing pulls from the sink in between, so the decoder waits
+/// where it was and playback resumes from the same position.
+pub struct ResilientOutput {
+    backend: Backend,
+    device: Option<Spanned<String>>,
+    title: Option<String>,
+    role: StreamRole,
+    span: Span,
+    stream: Option<OutputStream>,
+    queue: Arc<Mutex<SourcesQueueOutput>>,
+    failed: Arc<AtomicBool>,
+    retry: Option<Retry>,
+}
+
+struct Retry {
+    since: Instant,
+    next: Instant,
+    delay: Duration,
+}
+
+impl ResilientOutput {
+    /// Opens `device`, or the default device of `backend`, and returns the output with a
+    /// sink playing into it. `title` and `role` identify the stream to the OS volume mixer.
+    pub fn open(
+        backend: Backend,
+        device: Option<Spanned<String>>,
+        title: Option<String>,
+        role: StreamRole,
+        span: Span,
+    ) -> Result<(ResilientOutput, Sink), LabeledError> {
+        let (sink, queue) = Sink::new();
+        let mut output = ResilientOutput {
+            backend,
+            device,
+            title,
+            role,
+            span,
+            stream: None,
+            queue: Arc::new(Mutex::new(queue)),
+            failed: Arc::new(AtomicBool::new(false)),
+            retry: None,
+        };
+        output.stream = Some(output.connect()?);
+        Ok((output, sink))
+    }
+
+    /// Replaces the sink playing into the output with a fresh one, dropping whatever the
+    /// old one still had queued, so the next track of a playlist starts from a clean
+    /// position without reopening the device.
+    pub fn next_sink(&mut self) -> Sink {
+        let (sink, queue) = Sink::new();
+        if let Ok(mut current) = self.queue.lock() {
+            *current = queue;
+        }
+        sink
+    }
+
+    /// Checks the stream and drives recovery; call it regularly while playing. Returns
+    /// `true` while the output is down, and an error once it has not come back within
+    /// [`RECOVERY_TIMEOUT`].
+    pub fn poll(&mut self) -> Result<bool, LabeledError> {
+        let now = Instant::now();
+        if self.retry.is_none() && self.failed.load(Ordering::SeqCst) {
+            warn!("audio output lost, reopening {:?}", self.backend);
+            // The dead stream still holds a relay; it must stop pulling first.
+            self.stream = None;
+            self.retry = Some(Retry {
+                since: now,
+                next: now,
+                delay: FIRST_RETRY_DELAY,
+            });
+        }
+        let Some(mut retry) = self.retry.take() else {
+            return Ok(false);
+        };
+        if now < retry.next {
+            self.retry = Some(retry);
+            return Ok(true);
+        }
+        match self.connect() {
+            Ok(stream) => {
+                info!("audio output reopened after {:?}", now - retry.since);
+                self.stream = Some(stream);
+                Ok(false)
+            }
+            Err(_) if now - retry.since < RECOVERY_TIMEOUT => {
+                warn!(
+                    "audio output still unavailable, retrying in {:?}",
+                    retry.delay
+                );
+                retry.next = now + retry.delay;
+                retry.delay = (retry.delay * 2).min(MAX_RETRY_DELAY);
+                self.retry = Some(retry);
+                Ok(true)
+            }
+            Err(err) => Err(LabeledError::new("audio output lost")
+                .with_label(
+                    format!(
+                        "the device did not come back within {}s",
+                        RECOVERY_TIMEOUT.as_secs()
+                    ),
+                    self.span,
+                )
+                .with_help(err.msg)),
+        }
+    }
+
+    fn connect(&self) -> Result<OutputStream, LabeledError> {
+        self.failed.store(false, Ordering::SeqCst);
+        let failed = self.failed.clone();
+        let mut stream = open_identified(self.title.as_deref(), self.role, || {
+            self.backend
+                .open_stream(self.device.as_ref(), self.span, move |err| {
+                    warn!("output stream error: {err}");
+                    failed.store(true, Ordering::SeqCst);
+                })
+        })?;
+        stream.log_on_drop(false);
+        stream.mixer().add(Relay(self.queue.clone()));
+        Ok(stream)
+    }
+}
+
+/// Plays a sink's queue into a stream's mixer while sharing it, so a later stream can
+/// take over. The queue plays silence while empty, so the relay never ends by itself.
+struct Relay(Arc<Mutex<SourcesQueueOutput>>);
+
+impl Iterator for Relay {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.0.lock().ok()?.next()
+    }
+}
+
+impl Source for Relay {
+    fn current_span_len(&self) -> Option<usize> {
+        self.0.lock().ok()?.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.0.lock().map_or(1, |queue| queue.channels())
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.0.lock().map_or(44_100, |queue| queue.sample_rate())
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, _pos: Duration) -> Result<(), SeekError> {
+        Err(SeekError::NotSupported {
+            underlying_source: std::any::type_name::<Self>(),
+        })
+    }
+}
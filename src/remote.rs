@@ -0,0 +1,212 @@
+use nu_plugin::EngineInterface;
+use nu_protocol::{LabeledError, Span, Value};
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    process::Command,
+};
+
+use crate::{
+    network::{http_request, remote_url, scheme_url},
+    utils::{not_installed, run_tool},
+};
+
+/// Size of each range request made by [`RangeReader`].
+const RANGE_BLOCK: u64 = 128 * 1024;
+
+/// Number of fetched blocks [`RangeReader`] keeps around.
+const CACHED_BLOCKS: usize = 8;
+
+/// Lifetime of presigned S3 URLs; long enough to play a long recording.
+const PRESIGN_EXPIRY_SECS: u32 = 6 * 3600;
+
+/// Schemes that need a mounted share rather than a URL.
+const MOUNT_SCHEMES: [&str; 3] = ["sftp:", "ssh:", "smb:"];
+
+/// Resolves a path argument that names a remote file to an http(s) URL.
+///
+/// http(s) URLs are returned as they are and `s3://bucket/key` objects are presigned
+/// with the AWS CLI, so credentials and profiles work as they do for `aws s3`. Returns
+/// `None` for local paths.
+pub fn resolve_remote(
+    engine: &EngineInterface,
+    path: &str,
+    span: Span,
+) -> Result<Option<String>, LabeledError> {
+    if let Some(url) = remote_url(path) {
+        return Ok(Some(url));
+    }
+    if let Some(object) = scheme_url(path, "s3:") {
+        return presign_s3(engine, &object, span).map(Some);
+    }
+    if let Some(scheme) = MOUNT_SCHEMES.iter().find(|s| scheme_url(path, s).is_some()) {
+        return Err(LabeledError::new(format!(
+            "{} sources are not supported",
+            scheme.trim_end_matches(':')
+        ))
+        .with_label("cannot open this location directly", span)
+        .with_help("mount the share (sshfs, mount.cifs, gio mount) and use the mounted path"));
+    }
+    Ok(None)
+}
+
+/// Runs `aws s3 presign` with the session's environment (`AWS_PROFILE`, credentials).
+fn presign_s3(engine: &EngineInterface, object: &str, span: Span) -> Result<String, LabeledError> {
+    let env = engine.get_env_vars()?;
+    let mut command = Command::new("aws");
+    command
+        .args(["s3", "presign", object, "--expires-in"])
+        .arg(PRESIGN_EXPIRY_SECS.to_string())
+        .envs(
+            env.iter()
+                .filter_map(|(name, value)| Some((name, value.coerce_str().ok()?.into_owned()))),
+        );
+    let output = run_tool(&mut command, span)?.ok_or_else(|| {
+        not_installed(
+            "the AWS CLI",
+            "s3:// sources are presigned with `aws s3 presign`",
+            "install the AWS CLI and configure credentials with `aws configure`",
+            span,
+        )
+    })?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Random-access reader over an http(s) resource that fetches only the byte ranges
+/// it is asked for, with `Range` requests of [`RANGE_BLOCK`] bytes.
+///
+/// Tag readers look at a few kilobytes at the start and end of a file, so reading
+/// metadata from a large remote file costs a handful of small requests instead of a
+/// full download. Servers that ignore `Range` answer with the whole file, which is
+/// then kept and served from memory.
+pub struct RangeReader {
+    engine: EngineInterface,
+    span: Span,
+    url: String,
+    headers: Option<Value>,
+    size: u64,
+    position: u64,
+    blocks: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl RangeReader {
+    /// Sizes the resource from `head`, the server's answer to `http head`.
+    pub fn open(
+        engine: &EngineInterface,
+        span: Span,
+        url: &str,
+        headers: Option<Value>,
+        head: &Value,
+    ) -> Result<RangeReader, LabeledError> {
+        let size = header_value(head, "content-length")
+            .and_then(|length| length.trim().parse().ok())
+            .ok_or_else(|| {
+                LabeledError::new("server did not report a size")
+                    .with_label("range reads need a Content-Length", span)
+            })?;
+        Ok(RangeReader {
+            engine: engine.clone(),
+            span,
+            url: url.to_string(),
+            headers,
+            size,
+            position: 0,
+            blocks: VecDeque::with_capacity(CACHED_BLOCKS),
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn fetch(&mut self, start: u64) -> std::io::Result<()> {
+        let end = (start + RANGE_BLOCK).min(self.size) - 1;
+        let range = [("Range", format!("bytes={start}-{end}"))];
+        let body = http_request(
+            &self.engine,
+            self.span,
+            "http get",
+            &self.url,
+            self.headers.as_ref(),
+            &range,
+        )
+        .and_then(|data| data.into_value(self.span).map_err(LabeledError::from))
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let body = match body {
+            Value::Binary { val, .. } => val,
+            Value::String { val, .. } => val.into_bytes(),
+            _ => {
+                return Err(std::io::Error::other(
+                    "unexpected response to range request",
+                ))
+            }
+        };
+
+        if body.len() as u64 > end - start + 1 {
+            // The server ignored the range and sent everything.
+            self.blocks.clear();
+            self.blocks.push_back((0, body));
+            self.size = self.blocks[0].1.len() as u64;
+            return Ok(());
+        }
+        if self.blocks.len() == CACHED_BLOCKS {
+            self.blocks.pop_front();
+        }
+        self.blocks.push_back((start, body));
+        Ok(())
+    }
+
+    fn cached(&self) -> Option<&[u8]> {
+        self.blocks.iter().find_map(|(start, data)| {
+            let offset = self.position.checked_sub(*start)?;
+            (offset < data.len() as u64).then(|| &data[offset as usize..])
+        })
+    }
+}
+
+impl Read for RangeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+        if self.cached().is_none() {
+            self.fetch(self.position)?;
+        }
+        let Some(data) = self.cached() else {
+            return Ok(0);
+        };
+        let count = buf.len().min(data.len());
+        buf[..count].copy_from_slice(&data[..count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for RangeReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+        };
+        self.position = target
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "seek before start"))?;
+        Ok(self.position)
+    }
+}
+
+/// Looks up a response header in `http head` output, a table of `name`/`value` rows.
+pub fn header_value(response: &Value, name: &str) -> Option<String> {
+    let rows = match response {
+        Value::List { vals, .. } => vals.as_slice(),
+        _ => return None,
+    };
+    rows.iter().find_map(|row| {
+        let record = row.as_record().ok()?;
+        let row_name = record.get("name")?.as_str().ok()?;
+        if !row_name.eq_ignore_ascii_case(name) {
+            return None;
+        }
+        record.get("value")?.coerce_string().ok()
+    })
+}
@@ -0,0 +1,259 @@
+use nu_protocol::{Record, Span, Value};
+use std::io::{Read, Seek, SeekFrom};
+
+/// Bytes searched for the first MPEG frame after the ID3v2 tag.
+const MPEG_SYNC_SEARCH: usize = 64 * 1024;
+
+/// How a file stores its samples, which matters when handing it to other tools and
+/// which neither lofty nor the decoder reports:
+///
+/// - `sample_format`: `u8`, `i16`, `i24`, `i32`, `f32` or `f64` for PCM and lossless
+///   files, or the codec name for compressed PCM (`ulaw`, `alaw`). Lossy codecs store
+///   no samples; they decode to `f32`, which is reported instead.
+/// - `endianness`: byte order of PCM samples.
+/// - `interleaved`: whether the channels' samples alternate (PCM) or are stored per
+///   channel (FLAC subframes).
+/// - `bitrate_mode`: `cbr` or `vbr`, where the file tells.
+///
+/// Fields the format does not define are left out. The reader is left at an
+/// unspecified position.
+pub fn sample_format_fields<R: Read + Seek>(reader: &mut R, span: Span) -> Record {
+    let mut fields = SampleFormat::default();
+    if reader.rewind().is_ok() {
+        let mut head = [0u8; 64];
+        if let Ok(len) = read_up_to(reader, &mut head) {
+            fields = match &head[..4.min(len)] {
+                b"RIFF" | b"RIFX" => wav(reader, head[3] == b'X'),
+                b"FORM" => aiff(reader, &head[8..12]),
+                b"fLaC" if len >= 22 => flac(&head),
+                b"OggS" if len >= 64 => ogg(&head),
+                [b'I', b'D', b'3', _] => mpeg(reader),
+                [0xFF, second, _, _] if second & 0xE0 == 0xE0 => mpeg(reader),
+                _ => None,
+            }
+            .unwrap_or_default();
+        }
+    }
+    fields.into_record(span)
+}
+
+#[derive(Default)]
+struct SampleFormat {
+    sample_format: Option<String>,
+    endianness: Option<&'static str>,
+    interleaved: Option<bool>,
+    bitrate_mode: Option<&'static str>,
+}
+
+impl SampleFormat {
+    fn pcm(sample_format: String, endianness: &'static str) -> SampleFormat {
+        SampleFormat {
+            sample_format: Some(sample_format),
+            endianness: Some(endianness),
+            interleaved: Some(true),
+            bitrate_mode: Some("cbr"),
+        }
+    }
+
+    fn into_record(self, span: Span) -> Record {
+        let mut record = Record::new();
+        if let Some(format) = self.sample_format {
+            record.push("sample_format", Value::string(format, span));
+        }
+        if let Some(endianness) = self.endianness {
+            record.push("endianness", Value::string(endianness, span));
+        }
+        if let Some(interleaved) = self.interleaved {
+            record.push("interleaved", Value::bool(interleaved, span));
+        }
+        if let Some(mode) = self.bitrate_mode {
+            record.push("bitrate_mode", Value::string(mode, span));
+        }
+        record
+    }
+}
+
+/// Name of integer PCM samples of `bits`: 8-bit WAV is unsigned, everything else signed.
+fn integer_format(bits: u16, unsigned_8: bool) -> String {
+    match bits {
+        8 if unsigned_8 => "u8".to_string(),
+        bits => format!("i{bits}"),
+    }
+}
+
+/// WAV: the `fmt ` chunk's format tag and bit depth. `RIFX` files are big-endian.
+fn wav<R: Read + Seek>(reader: &mut R, big_endian: bool) -> Option<SampleFormat> {
+    let fmt = find_chunk(reader, 12, b"fmt ", big_endian)?;
+    let word = |at: usize| -> Option<u16> {
+        let bytes = [*fmt.get(at)?, *fmt.get(at + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let mut tag = word(0)?;
+    let bits = word(14)?;
+    // WAVE_FORMAT_EXTENSIBLE: the actual format is the first word of the sub-format GUID.
+    if tag == 0xFFFE {
+        tag = word(24)?;
+    }
+    let format = match tag {
+        1 => integer_format(bits, true),
+        3 => format!("f{bits}"),
+        6 => "alaw".to_string(),
+        7 => "ulaw".to_string(),
+        _ => return None,
+    };
+    Some(SampleFormat::pcm(
+        format,
+        if big_endian { "big" } else { "little" },
+    ))
+}
+
+/// AIFF and AIFF-C: sample size from `COMM`, plus the compression type for AIFF-C.
+fn aiff<R: Read + Seek>(reader: &mut R, form: &[u8]) -> Option<SampleFormat> {
+    let comm = find_chunk(reader, 12, b"COMM", true)?;
+    let bits = u16::from_be_bytes([*comm.get(6)?, *comm.get(7)?]);
+    if form != b"AIFC" {
+        return Some(SampleFormat::pcm(integer_format(bits, false), "big"));
+    }
+    let (format, endianness) = match comm.get(18..22)? {
+        b"NONE" | b"twos" => (integer_format(bits, false), "big"),
+        b"sowt" => (integer_format(bits, false), "little"),
+        b"fl32" | b"FL32" => ("f32".to_string(), "big"),
+        b"fl64" | b"FL64" => ("f64".to_string(), "big"),
+        b"ulaw" | b"ULAW" => ("ulaw".to_string(), "big"),
+        b"alaw" | b"ALAW" => ("alaw".to_string(), "big"),
+        _ => return None,
+    };
+    Some(SampleFormat::pcm(format, endianness))
+}
+
+/// FLAC: bit depth from `STREAMINFO`, which directly follows the marker. Channels are
+/// coded as separate subframes, and the compressed frame size varies.
+fn flac(head: &[u8]) -> Option<SampleFormat> {
+    let bits = (((head[20] & 1) << 4) | (head[21] >> 4)) + 1;
+    Some(SampleFormat {
+        sample_format: Some(integer_format(bits as u16, false)),
+        endianness: None,
+        interleaved: Some(false),
+        bitrate_mode: Some("vbr"),
+    })
+}
+
+/// Ogg: Vorbis is CBR only when its identification header gives equal minimum, nominal
+/// and maximum bitrates. Opus and Speex do not say.
+fn ogg(head: &[u8]) -> Option<SampleFormat> {
+    let mut format = SampleFormat {
+        sample_format: Some("f32".to_string()),
+        ..Default::default()
+    };
+    if &head[28..35] == b"\x01vorbis" {
+        let rate =
+            |at: usize| i32::from_le_bytes([head[at], head[at + 1], head[at + 2], head[at + 3]]);
+        let (maximum, nominal, minimum) = (rate(44), rate(48), rate(52));
+        format.bitrate_mode = Some(if maximum > 0 && maximum == nominal && nominal == minimum {
+            "cbr"
+        } else {
+            "vbr"
+        });
+    }
+    Some(format)
+}
+
+/// MPEG audio: a `Xing` or `VBRI` header in the first frame marks VBR, an `Info` header
+/// or none at all CBR.
+fn mpeg<R: Read + Seek>(reader: &mut R) -> Option<SampleFormat> {
+    let (frame, xing) = first_mpeg_frame(reader)?;
+    let at = |offset: usize| frame.get(offset..offset + 4);
+    let bitrate_mode = match (at(xing), at(36)) {
+        (Some(b"Xing"), _) | (_, Some(b"VBRI")) => "vbr",
+        _ => "cbr",
+    };
+    Some(SampleFormat {
+        sample_format: Some("f32".to_string()),
+        endianness: None,
+        interleaved: None,
+        bitrate_mode: Some(bitrate_mode),
+    })
+}
+
+/// The start of the first MPEG audio frame after any ID3v2 tag, and the offset of its
+/// `Xing`/`Info` header, which follows the side information.
+pub fn first_mpeg_frame<R: Read + Seek>(reader: &mut R) -> Option<(Vec<u8>, usize)> {
+    let (_, frame, xing) = locate_first_mpeg_frame(reader)?;
+    Some((frame, xing))
+}
+
+/// Like [`first_mpeg_frame`], also returning where in the file the frame starts.
+pub fn locate_first_mpeg_frame<R: Read + Seek>(reader: &mut R) -> Option<(u64, Vec<u8>, usize)> {
+    reader.rewind().ok()?;
+    let mut id3 = [0u8; 10];
+    read_up_to(reader, &mut id3).ok()?;
+    let start = if &id3[..3] == b"ID3" {
+        let size = id3[6..10]
+            .iter()
+            .fold(0u64, |size, byte| (size << 7) | (*byte & 0x7F) as u64);
+        let footer = if id3[5] & 0x10 != 0 { 10 } else { 0 };
+        10 + size + footer
+    } else {
+        0
+    };
+    reader.seek(SeekFrom::Start(start)).ok()?;
+    let mut data = vec![0u8; MPEG_SYNC_SEARCH];
+    let len = read_up_to(reader, &mut data).ok()?;
+    data.truncate(len);
+
+    let frame = (0..data.len().saturating_sub(4))
+        .find(|&at| data[at] == 0xFF && data[at + 1] & 0xE0 == 0xE0 && data[at + 1] & 0x06 != 0)?;
+    let data = data.split_off(frame);
+    let mpeg1 = data[1] & 0x18 == 0x18;
+    let mono = data[3] >> 6 == 3;
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    Some((start + frame as u64, data, 4 + side_info))
+}
+
+/// Body of the first chunk named `id` in a RIFF/IFF file, scanning from `offset`.
+fn find_chunk<R: Read + Seek>(
+    reader: &mut R,
+    mut offset: u64,
+    id: &[u8; 4],
+    big_endian: bool,
+) -> Option<Vec<u8>> {
+    loop {
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let size = [header[4], header[5], header[6], header[7]];
+        let size = if big_endian {
+            u32::from_be_bytes(size)
+        } else {
+            u32::from_le_bytes(size)
+        } as u64;
+        if &header[..4] == id {
+            let mut body = vec![0u8; size.min(256) as usize];
+            let len = read_up_to(reader, &mut body).ok()?;
+            body.truncate(len);
+            return Some(body);
+        }
+        // Chunks are padded to an even size.
+        offset += 8 + size + (size & 1);
+    }
+}
+
+/// Reads until `buf` is full or the input ends; returns the bytes read.
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
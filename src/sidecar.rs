@@ -0,0 +1,115 @@
+use nu_protocol::{LabeledError, Record, Span, Value};
+use std::path::{Path, PathBuf};
+
+/// Returns the sidecar path for an audio file: the full file name with `.json` appended
+/// (`song.wav` → `song.wav.json`), so sidecars never collide between `a.wav` and `a.flac`.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// Reads the sidecar next to `path` and returns its fields as a nushell [`Record`].
+///
+/// Returns `Ok(None)` when no sidecar exists. Values keep their JSON type: numbers,
+/// booleans, lists and objects come back as the matching nushell values.
+pub fn read_sidecar(path: &Path, span: Span) -> Result<Option<Record>, LabeledError> {
+    let map = match load_map(path, span)? {
+        Some(map) => map,
+        None => return Ok(None),
+    };
+    let mut record = Record::new();
+    for (key, val) in map {
+        record.push(key, json_to_value(val, span));
+    }
+    Ok(Some(record))
+}
+
+/// Sets `key` to `value` in the sidecar next to `path`, creating the sidecar if needed.
+/// Numbers and booleans are stored as JSON numbers and booleans, not as text.
+///
+/// Existing entries are preserved; keys are stored in sorted order so sidecars diff cleanly.
+pub fn write_sidecar_value(
+    path: &Path,
+    key: &str,
+    value: &Value,
+    span: Span,
+) -> Result<(), LabeledError> {
+    let json = value_to_json(value)?;
+    let mut map = load_map(path, span)?.unwrap_or_default();
+    map.insert(key.to_string(), json);
+
+    let json = serde_json::to_string_pretty(&map).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error serializing sidecar", span)
+    })?;
+    std::fs::write(sidecar_path(path), json + "\n")
+        .map_err(|e| LabeledError::new(e.to_string()).with_label("error writing sidecar", span))
+}
+
+fn load_map(
+    path: &Path,
+    span: Span,
+) -> Result<Option<serde_json::Map<String, serde_json::Value>>, LabeledError> {
+    let sidecar = sidecar_path(path);
+    if !sidecar.is_file() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(&sidecar)
+        .map_err(|e| LabeledError::new(e.to_string()).with_label("error reading sidecar", span))?;
+    match serde_json::from_str(&text) {
+        Ok(serde_json::Value::Object(map)) => Ok(Some(map)),
+        Ok(_) => Err(LabeledError::new(format!(
+            "sidecar {} must contain a JSON object",
+            sidecar.display()
+        ))
+        .with_label("invalid sidecar", span)),
+        Err(e) => Err(LabeledError::new(e.to_string()).with_label("error parsing sidecar", span)),
+    }
+}
+
+fn json_to_value(json: serde_json::Value, span: Span) -> Value {
+    match json {
+        serde_json::Value::Null => Value::nothing(span),
+        serde_json::Value::Bool(b) => Value::bool(b, span),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::int(i, span),
+            None => Value::float(n.as_f64().unwrap_or(f64::NAN), span),
+        },
+        serde_json::Value::String(s) => Value::string(s, span),
+        serde_json::Value::Array(items) => Value::list(
+            items
+                .into_iter()
+                .map(|item| json_to_value(item, span))
+                .collect(),
+            span,
+        ),
+        serde_json::Value::Object(map) => {
+            let mut record = Record::new();
+            for (key, val) in map {
+                record.push(key, json_to_value(val, span));
+            }
+            Value::record(record, span)
+        }
+    }
+}
+
+fn value_to_json(value: &Value) -> Result<serde_json::Value, LabeledError> {
+    match value {
+        Value::Bool { val, .. } => Ok(serde_json::Value::Bool(*val)),
+        Value::Int { val, .. } => Ok(serde_json::Value::from(*val)),
+        Value::Float { val, .. } => serde_json::Number::from_f64(*val)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| {
+                LabeledError::new("invalid sidecar value")
+                    .with_label("JSON cannot hold NaN or infinity", value.span())
+            }),
+        Value::String { val, .. } => Ok(serde_json::Value::String(val.clone())),
+        _ => Err(LabeledError::new("invalid sidecar value").with_label(
+            format!(
+                "expected a string, number or bool, found {}",
+                value.get_type()
+            ),
+            value.span(),
+        )),
+    }
+}
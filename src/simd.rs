@@ -0,0 +1,219 @@
+// Sample-processing kernels with SIMD paths for the hot loops of analysis and gain.
+//
+// The reductions (peak, sum of squares) are where this pays: the compiler may not
+// reorder floating-point sums or NaN-aware maxima, so it leaves them scalar. On x86_64
+// the AVX paths are picked at runtime, with SSE2 (part of the baseline) otherwise; on
+// aarch64 NEON is always present. Anything else runs the scalar loops.
+
+/// Largest absolute value in `samples`, 0.0 for an empty slice. NaNs are skipped.
+pub fn peak(samples: &[f32]) -> f32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            // SAFETY: AVX was detected on this CPU.
+            return unsafe { x86::peak_avx(samples) };
+        }
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        unsafe { x86::peak_sse2(samples) }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is part of the aarch64 baseline.
+        unsafe { neon::peak(samples) }
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        scalar::peak(samples)
+    }
+}
+
+/// Sum of the squares of `samples`, accumulated in `f64`.
+pub fn sum_squares(samples: &[f32]) -> f64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            // SAFETY: AVX was detected on this CPU.
+            return unsafe { x86::sum_squares_avx(samples) };
+        }
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        unsafe { x86::sum_squares_sse2(samples) }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is part of the aarch64 baseline.
+        unsafe { neon::sum_squares(samples) }
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        scalar::sum_squares(samples)
+    }
+}
+
+/// Mean square of `samples`, 0.0 for an empty slice.
+pub fn mean_square(samples: &[f32]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    sum_squares(samples) / samples.len() as f64
+}
+
+/// Multiplies every sample by `gain` in place.
+pub fn scale(samples: &mut [f32], gain: f32) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx") {
+        // SAFETY: AVX was detected on this CPU.
+        return unsafe { x86::scale_avx(samples, gain) };
+    }
+    // The compiler vectorizes this with the baseline instruction set by itself.
+    scalar::scale(samples, gain)
+}
+
+mod scalar {
+    pub fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()))
+    }
+
+    #[cfg_attr(any(target_arch = "x86_64", target_arch = "aarch64"), allow(dead_code))]
+    pub fn sum_squares(samples: &[f32]) -> f64 {
+        samples.iter().map(|s| (*s as f64) * (*s as f64)).sum()
+    }
+
+    pub fn scale(samples: &mut [f32], gain: f32) {
+        samples.iter_mut().for_each(|s| *s *= gain);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::scalar;
+
+    /// `_mm*_max_ps` returns its second operand when either is NaN, so the running
+    /// maximum goes second and NaN samples leave it unchanged.
+    #[target_feature(enable = "avx")]
+    pub fn peak_avx(samples: &[f32]) -> f32 {
+        let abs = _mm256_castsi256_ps(_mm256_set1_epi32(0x7FFF_FFFF));
+        let mut max = _mm256_setzero_ps();
+        let chunks = samples.chunks_exact(8);
+        let tail = scalar::peak(chunks.remainder());
+        for chunk in chunks {
+            // SAFETY: the chunk holds 8 floats, and the load needs no alignment.
+            let v = unsafe { _mm256_loadu_ps(chunk.as_ptr()) };
+            max = _mm256_max_ps(_mm256_and_ps(v, abs), max);
+        }
+        let max = _mm_max_ps(_mm256_castps256_ps128(max), _mm256_extractf128_ps(max, 1));
+        horizontal_max(max).max(tail)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub fn peak_sse2(samples: &[f32]) -> f32 {
+        let abs = _mm_castsi128_ps(_mm_set1_epi32(0x7FFF_FFFF));
+        let mut max = _mm_setzero_ps();
+        let chunks = samples.chunks_exact(4);
+        let tail = scalar::peak(chunks.remainder());
+        for chunk in chunks {
+            // SAFETY: the chunk holds 4 floats, and the load needs no alignment.
+            let v = unsafe { _mm_loadu_ps(chunk.as_ptr()) };
+            max = _mm_max_ps(_mm_and_ps(v, abs), max);
+        }
+        horizontal_max(max).max(tail)
+    }
+
+    #[target_feature(enable = "sse2")]
+    fn horizontal_max(v: __m128) -> f32 {
+        let v = _mm_max_ps(v, _mm_movehl_ps(v, v));
+        let v = _mm_max_ss(v, _mm_shuffle_ps(v, v, 0b01));
+        _mm_cvtss_f32(v)
+    }
+
+    #[target_feature(enable = "avx")]
+    pub fn sum_squares_avx(samples: &[f32]) -> f64 {
+        let (mut low, mut high) = (_mm256_setzero_pd(), _mm256_setzero_pd());
+        let chunks = samples.chunks_exact(8);
+        let tail = scalar::sum_squares(chunks.remainder());
+        for chunk in chunks {
+            // SAFETY: the chunk holds 8 floats, and the load needs no alignment.
+            let v = unsafe { _mm256_loadu_ps(chunk.as_ptr()) };
+            let a = _mm256_cvtps_pd(_mm256_castps256_ps128(v));
+            let b = _mm256_cvtps_pd(_mm256_extractf128_ps(v, 1));
+            low = _mm256_add_pd(low, _mm256_mul_pd(a, a));
+            high = _mm256_add_pd(high, _mm256_mul_pd(b, b));
+        }
+        let sum = _mm256_add_pd(low, high);
+        let sum = _mm_add_pd(_mm256_castpd256_pd128(sum), _mm256_extractf128_pd(sum, 1));
+        horizontal_sum(sum) + tail
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub fn sum_squares_sse2(samples: &[f32]) -> f64 {
+        let (mut low, mut high) = (_mm_setzero_pd(), _mm_setzero_pd());
+        let chunks = samples.chunks_exact(4);
+        let tail = scalar::sum_squares(chunks.remainder());
+        for chunk in chunks {
+            // SAFETY: the chunk holds 4 floats, and the load needs no alignment.
+            let v = unsafe { _mm_loadu_ps(chunk.as_ptr()) };
+            let a = _mm_cvtps_pd(v);
+            let b = _mm_cvtps_pd(_mm_movehl_ps(v, v));
+            low = _mm_add_pd(low, _mm_mul_pd(a, a));
+            high = _mm_add_pd(high, _mm_mul_pd(b, b));
+        }
+        horizontal_sum(_mm_add_pd(low, high)) + tail
+    }
+
+    #[target_feature(enable = "sse2")]
+    fn horizontal_sum(v: __m128d) -> f64 {
+        _mm_cvtsd_f64(_mm_add_sd(v, _mm_unpackhi_pd(v, v)))
+    }
+
+    #[target_feature(enable = "avx")]
+    pub fn scale_avx(samples: &mut [f32], gain: f32) {
+        let gain_v = _mm256_set1_ps(gain);
+        let mut chunks = samples.chunks_exact_mut(8);
+        for chunk in &mut chunks {
+            // SAFETY: the chunk holds 8 floats, and neither access needs alignment.
+            unsafe {
+                let v = _mm256_loadu_ps(chunk.as_ptr());
+                _mm256_storeu_ps(chunk.as_mut_ptr(), _mm256_mul_ps(v, gain_v));
+            }
+        }
+        scalar::scale(chunks.into_remainder(), gain);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::scalar;
+
+    /// `vmaxnm` returns the number when one operand is NaN, so NaN samples are skipped.
+    #[target_feature(enable = "neon")]
+    pub fn peak(samples: &[f32]) -> f32 {
+        let mut max = vdupq_n_f32(0.0);
+        let chunks = samples.chunks_exact(4);
+        let tail = scalar::peak(chunks.remainder());
+        for chunk in chunks {
+            // SAFETY: the chunk holds 4 floats.
+            let v = unsafe { vld1q_f32(chunk.as_ptr()) };
+            max = vmaxnmq_f32(max, vabsq_f32(v));
+        }
+        vmaxnmvq_f32(max).max(tail)
+    }
+
+    #[target_feature(enable = "neon")]
+    pub fn sum_squares(samples: &[f32]) -> f64 {
+        let (mut low, mut high) = (vdupq_n_f64(0.0), vdupq_n_f64(0.0));
+        let chunks = samples.chunks_exact(4);
+        let tail = scalar::sum_squares(chunks.remainder());
+        for chunk in chunks {
+            // SAFETY: the chunk holds 4 floats.
+            let v = unsafe { vld1q_f32(chunk.as_ptr()) };
+            let a = vcvt_f64_f32(vget_low_f32(v));
+            let b = vcvt_high_f64_f32(v);
+            low = vaddq_f64(low, vmulq_f64(a, a));
+            high = vaddq_f64(high, vmulq_f64(b, b));
+        }
+        vaddvq_f64(vaddq_f64(low, high)) + tail
+    }
+}
@@ -1,15 +1,48 @@
 use nu_plugin::Plugin;
 
 use crate::{
+    audio_abx::SoundAbxCmd,
+    audio_analyze::{SoundAnalyzeLoopCmd, SoundAnalyzeSpeechCmd},
+    audio_artwork::{SoundArtworkFetchCmd, SoundArtworkSetCmd},
+    audio_audition::SoundAuditionCmd,
+    audio_devices::SoundDevicesCmd,
+    audio_edit::{SoundLoopifyCmd, SoundSliceCmd, SoundTrimCmd, SoundVariantsCmd},
+    audio_effect::{SoundChannelsCmd, SoundKaraokeCmd},
+    audio_library::{
+        SoundFixCompilationCmd, SoundFixEncodingCmd, SoundGenreNormalizeCmd, SoundRenumberCmd,
+        SoundSanitizeCmd,
+    },
+    audio_measure::{SoundLatencyCmd, SoundMeasureRoomCmd},
     audio_meta::{SoundMetaGetCmd, SoundMetaSetCmd},
     audio_player::SoundPlayCmd,
+    audio_privacy::SoundAuditPrivacyCmd,
+    audio_repair::SoundRepairCmd,
+    audio_stems::SoundPlayStemsCmd,
+    audio_test::{SoundTestChannelsCmd, SoundTestPolarityCmd},
+    audio_transcribe::SoundTranscribeCmd,
+    audio_xing::SoundMetaXingCmd,
+    cooldown::Cooldowns,
+    sound_drums::SoundDrumsCmd,
+    sound_dtmf::SoundDtmfCmd,
     sound_make::{SoundBeepCmd, SoundMakeCmd},
+    sound_midi::SoundMidiPlayCmd,
+    sound_mml::SoundPlayNotesCmd,
+    sound_morse::SoundMorseCmd,
+    sound_notify::SoundNotifyCmd,
+    sound_rtttl::SoundRtttlCmd,
+    sound_sampler::SoundSamplerCmd,
+    sound_samples::SoundFromSamplesCmd,
+    sound_sequence::SoundSequenceCmd,
+    sound_synth::SoundSynthCmd,
 };
 
 // use crate::make_sound;
 
 #[derive(Default)]
-pub struct Sound;
+pub struct Sound {
+    /// Running `--cooldown`s of the notification commands.
+    pub cooldowns: Cooldowns,
+}
 
 impl Plugin for Sound {
     fn commands(&self) -> Vec<Box<dyn nu_plugin::PluginCommand<Plugin = Self>>> {
@@ -17,8 +50,46 @@ impl Plugin for Sound {
             Box::new(SoundPlayCmd {}),
             Box::new(SoundMakeCmd {}),
             Box::new(SoundBeepCmd {}),
+            Box::new(SoundNotifyCmd {}),
+            Box::new(SoundSequenceCmd {}),
+            Box::new(SoundSynthCmd {}),
+            Box::new(SoundFromSamplesCmd {}),
+            Box::new(SoundDtmfCmd {}),
+            Box::new(SoundMorseCmd {}),
+            Box::new(SoundRtttlCmd {}),
+            Box::new(SoundPlayNotesCmd {}),
+            Box::new(SoundMidiPlayCmd {}),
+            Box::new(SoundDrumsCmd {}),
+            Box::new(SoundSamplerCmd {}),
             Box::new(SoundMetaGetCmd {}),
             Box::new(SoundMetaSetCmd {}),
+            Box::new(SoundArtworkSetCmd {}),
+            Box::new(SoundArtworkFetchCmd {}),
+            Box::new(SoundGenreNormalizeCmd {}),
+            Box::new(SoundFixEncodingCmd {}),
+            Box::new(SoundRenumberCmd {}),
+            Box::new(SoundSanitizeCmd {}),
+            Box::new(SoundAuditPrivacyCmd {}),
+            Box::new(SoundMetaXingCmd {}),
+            Box::new(SoundRepairCmd {}),
+            Box::new(SoundFixCompilationCmd {}),
+            Box::new(SoundTranscribeCmd {}),
+            Box::new(SoundAnalyzeSpeechCmd {}),
+            Box::new(SoundKaraokeCmd {}),
+            Box::new(SoundSliceCmd {}),
+            Box::new(SoundLoopifyCmd {}),
+            Box::new(SoundAnalyzeLoopCmd {}),
+            Box::new(SoundTrimCmd {}),
+            Box::new(SoundVariantsCmd {}),
+            Box::new(SoundAuditionCmd {}),
+            Box::new(SoundAbxCmd {}),
+            Box::new(SoundPlayStemsCmd {}),
+            Box::new(SoundDevicesCmd {}),
+            Box::new(SoundLatencyCmd {}),
+            Box::new(SoundMeasureRoomCmd {}),
+            Box::new(SoundChannelsCmd {}),
+            Box::new(SoundTestChannelsCmd {}),
+            Box::new(SoundTestPolarityCmd {}),
         ]
     }
 
@@ -0,0 +1,469 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Record, Signature, Span,
+    SyntaxShape, Type, Value,
+};
+use rodio::buffer::SamplesBuffer;
+use std::{f64::consts::LOG2_E, time::Duration};
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats},
+    dsp::{exp2, sin_cycles, OnePole, Rng},
+    ear_guard::{ear_guard_flags, EarGuard},
+    no_audio::{no_audio_flags, NoAudio},
+    sound_make::{play_source, MAX_LENGTH, TONE_RATE},
+    stream_identity::StreamRole,
+    validate,
+    wav::{int_in, wav_flags, WavOptions},
+    Sound,
+};
+
+const DEFAULT_BPM: i64 = 120;
+
+const BPMS: std::ops::RangeInclusive<i64> = 20..=400;
+
+/// Steps per beat when `--steps-per-beat` is not given: sixteenth notes.
+const DEFAULT_STEPS_PER_BEAT: i64 = 4;
+
+/// Level of the mix, leaving headroom for drums that hit together.
+const MIX_LEVEL: f32 = 0.6;
+
+/// Seed of the noise in the hits. Fixed, so every hit of a drum sounds the same and a
+/// pattern renders to the same bytes every time.
+const NOISE_SEED: u64 = 0x5EED_D7C5;
+
+/// Percussion voices, synthesized rather than sampled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Drum {
+    Kick,
+    Snare,
+    Hat,
+    OpenHat,
+    Clap,
+    Click,
+}
+
+/// Row names of the drums.
+const DRUMS: [(&str, Drum); 6] = [
+    ("kick", Drum::Kick),
+    ("snare", Drum::Snare),
+    ("hat", Drum::Hat),
+    ("open-hat", Drum::OpenHat),
+    ("clap", Drum::Clap),
+    ("click", Drum::Click),
+];
+
+/// Nushell command `sound drums` — plays step-sequencer drum patterns.
+///
+/// Each row is a string of steps for one drum: `x` is a hit, `X` an accent, `o` a ghost
+/// note, and `.`, `-` or `_` a rest; spaces and `|` can mark beats and bars. Rows
+/// shorter than the longest repeat, which makes polyrhythms easy.
+pub struct SoundDrumsCmd;
+
+impl SimplePluginCommand for SoundDrumsCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound drums"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound drums")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+                (Type::table(), Type::Nothing),
+                (Type::table(), Type::Binary),
+            ])
+            .optional(
+                "pattern",
+                SyntaxShape::OneOf(vec![SyntaxShape::String, SyntaxShape::Record(vec![])]),
+                "steps of one drum (\"x...x...\", or \"snare: ....x...\"), or a record of rows by drum",
+            )
+            .named(
+                "bpm",
+                SyntaxShape::Int,
+                "tempo in beats per minute (default 120)",
+                Some('b'),
+            )
+            .named(
+                "steps-per-beat",
+                SyntaxShape::Int,
+                "steps in each beat: 4 for sixteenth notes (default), 2 for eighths, 3 for triplets",
+                None,
+            )
+            .named(
+                "swing",
+                SyntaxShape::Float,
+                "delay every second step by this fraction of a step, from 0.0 to 0.75",
+                None,
+            )
+            .named(
+                "repeat",
+                SyntaxShape::Int,
+                "times to play the pattern (default 1)",
+                Some('r'),
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the beat by given value (e.g. 0.5 for half volume)",
+                Some('a'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow beats longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(signature)))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "four on the floor",
+                example: "sound drums \"x...x...x...x...\"",
+                result: None,
+            },
+            Example {
+                description: "a rock beat with an accented hi-hat on the beat",
+                example: "sound drums {kick: \"x.......x.x.....\", snare: \"....x.......x...\", hat: \"X.x.X.x.X.x.X.x.\"} --bpm 100 --repeat 4",
+                result: None,
+            },
+            Example {
+                description: "a click track: an accent on the first of four beats, for 8 bars",
+                example: "sound drums \"click: X x x x\" --steps-per-beat 1 --bpm 90 --repeat 8",
+                result: None,
+            },
+            Example {
+                description: "a swung groove from a table, saved as WAV",
+                example: "[[drum steps]; [kick \"x..x..x.\"] [clap \"..x...x.\"] [hat \"xxxxxxxx\"]] | sound drums --swing 0.3 --data | save --raw groove.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play drum patterns written as step strings, or output them as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let rows = load_rows(call, input)?;
+        let bpm = match call.get_flag_value("bpm") {
+            Some(value) => int_in(&value, BPMS, "--bpm", "beats per minute")?,
+            None => DEFAULT_BPM,
+        };
+        let steps_per_beat = match call.get_flag_value("steps-per-beat") {
+            Some(value) => int_in(&value, 1..=16, "--steps-per-beat", "steps")?,
+            None => DEFAULT_STEPS_PER_BEAT,
+        };
+        let swing = match call.get_flag_value("swing") {
+            Some(value) => swing(&value)?,
+            None => 0.0,
+        };
+        let repeat = match call.get_flag_value("repeat") {
+            Some(value) => int_in(&value, 1..=100_000, "--repeat", "times")? as usize,
+            None => 1,
+        };
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+
+        let step = 60.0 / bpm as f64 / steps_per_beat as f64;
+        let steps = rows.iter().map(|row| row.hits.len()).max().unwrap_or(0) * repeat;
+        let length = Duration::from_secs_f64(steps as f64 * step);
+        validate::length(length, MAX_LENGTH, call.has_flag("force")?, call.head)?;
+        let data = call.has_flag("data")?;
+        if data {
+            let max_length = WavOptions::from_call(call)?.max_length(TONE_RATE, 1);
+            if length > max_length {
+                return Err(
+                    LabeledError::new("pattern too long for a WAV file").with_label(
+                        format!("WAV data is limited to {}s", max_length.as_secs()),
+                        call.head,
+                    ),
+                );
+            }
+        }
+
+        let mut samples = sequence(&rows, steps, step, swing);
+        samples.iter_mut().for_each(|s| *s *= MIX_LEVEL * amplify);
+        let source = SamplesBuffer::new(1, TONE_RATE, samples);
+        if data {
+            return Ok(Value::binary(
+                WavOptions::from_call(call)?.encode(source)?,
+                call.head,
+            ));
+        }
+        if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        play_source(source, guard, StreamRole::Media)?;
+        Ok(Value::nothing(call.head))
+    }
+}
+
+fn swing(value: &Value) -> Result<f64, LabeledError> {
+    let swing = value.as_float().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("--swing must be a number", value.span())
+    })?;
+    if !(0.0..=0.75).contains(&swing) {
+        return Err(LabeledError::new("--swing out of range")
+            .with_label("must be between 0.0 and 0.75", value.span())
+            .with_help("0.33 gives the triplet feel of a classic shuffle"));
+    }
+    Ok(swing)
+}
+
+/// The steps of one drum: the level of each, `None` for a rest.
+struct Row {
+    drum: Drum,
+    hits: Vec<Option<f32>>,
+}
+
+/// Rows from the `pattern` argument, or else from a table of `drum` and `steps` columns
+/// in the pipeline.
+fn load_rows(call: &EvaluatedCall, input: &Value) -> Result<Vec<Row>, LabeledError> {
+    let rows = match call.opt::<Value>(0)? {
+        Some(Value::String {
+            val, internal_span, ..
+        }) => {
+            let (drum, steps) = match val.split_once(':') {
+                Some((drum, steps)) => (drum.trim(), steps),
+                None => ("kick", val.as_str()),
+            };
+            vec![parse_row(drum, steps, internal_span)?]
+        }
+        Some(Value::Record {
+            val, internal_span, ..
+        }) => record_rows(&val, internal_span)?,
+        Some(other) => {
+            return Err(LabeledError::new("invalid pattern").with_label(
+                format!("expected a string or a record, got {}", other.get_type()),
+                other.span(),
+            ))
+        }
+        None => match input {
+            Value::Nothing { .. } => {
+                return Err(LabeledError::new("no pattern")
+                    .with_label(
+                        "give a pattern or pipe in a table of drums and steps",
+                        call.head,
+                    )
+                    .with_help("e.g. sound drums \"x...x...x...x...\""))
+            }
+            input => table_rows(input)?,
+        },
+    };
+    if rows.iter().all(|row| row.hits.iter().all(Option::is_none)) {
+        return Err(LabeledError::new("nothing to play")
+            .with_label("the pattern has no hits", call.head)
+            .with_help("x is a hit, . a rest"));
+    }
+    Ok(rows)
+}
+
+fn record_rows(record: &Record, span: Span) -> Result<Vec<Row>, LabeledError> {
+    record
+        .iter()
+        .map(|(drum, steps)| {
+            let text = steps.as_str().map_err(|_| {
+                LabeledError::new(format!("invalid steps for {drum}"))
+                    .with_label("expected a string like \"x...x...\"", steps.span())
+            })?;
+            parse_row(drum, text, steps.span())
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|rows| match rows.is_empty() {
+            true => Err(LabeledError::new("no pattern").with_label("the record is empty", span)),
+            false => Ok(rows),
+        })
+}
+
+fn table_rows(input: &Value) -> Result<Vec<Row>, LabeledError> {
+    let rows = input.as_list().map_err(|e| {
+        LabeledError::new(e.to_string())
+            .with_label("expected a table of drums and steps", input.span())
+            .with_help("pipe in rows like [[drum steps]; [kick \"x...x...\"]]")
+    })?;
+    rows.iter()
+        .map(|row| {
+            let span = row.span();
+            let record = row.as_record().map_err(|e| {
+                LabeledError::new(e.to_string()).with_label("expected a record", span)
+            })?;
+            let column = |name: &str| {
+                record
+                    .get(name)
+                    .and_then(|v| v.as_str().ok())
+                    .ok_or_else(|| {
+                        LabeledError::new(format!("missing {name}"))
+                            .with_label(format!("row has no `{name}` column of text"), span)
+                    })
+            };
+            parse_row(column("drum")?, column("steps")?, span)
+        })
+        .collect()
+}
+
+fn parse_row(drum: &str, steps: &str, span: Span) -> Result<Row, LabeledError> {
+    let drum = DRUMS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(drum))
+        .map(|(_, drum)| *drum)
+        .ok_or_else(|| {
+            LabeledError::new(format!("unknown drum '{drum}'"))
+                .with_label("expected kick, snare, hat, open-hat, clap or click", span)
+        })?;
+    let hits = steps
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '|')
+        .map(|c| match c {
+            'X' => Ok(Some(1.0)),
+            'x' => Ok(Some(0.7)),
+            'o' | 'O' => Ok(Some(0.35)),
+            '.' | '-' | '_' => Ok(None),
+            c => Err(LabeledError::new(format!("invalid step '{c}'"))
+                .with_label("steps are x, X, o for hits and . - _ for rests", span)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if hits.is_empty() {
+        return Err(LabeledError::new("empty row").with_label("the row has no steps", span));
+    }
+    Ok(Row { drum, hits })
+}
+
+/// Mixes `steps` steps of `rows`, each `step` seconds long, with every second step
+/// delayed by `swing` of a step. Hits ring on past the last step.
+fn sequence(rows: &[Row], steps: usize, step: f64, swing: f64) -> Vec<f32> {
+    let start = |n: usize| {
+        let delay = if n % 2 == 1 { swing } else { 0.0 };
+        ((n as f64 + delay) * step * TONE_RATE as f64).round() as usize
+    };
+    let mut out = vec![0.0f32; start(steps)];
+    for row in rows {
+        let hit = row.drum.render();
+        for n in 0..steps {
+            let Some(level) = row.hits[n % row.hits.len()] else {
+                continue;
+            };
+            let at = start(n);
+            if out.len() < at + hit.len() {
+                out.resize(at + hit.len(), 0.0);
+            }
+            for (out, sample) in out[at..].iter_mut().zip(&hit) {
+                *out += sample * level;
+            }
+        }
+    }
+    out
+}
+
+/// `e^(-t / time)`: an exponential decay with time constant `time`.
+fn decay(t: f64, time: f64) -> f64 {
+    exp2(-t / time * LOG2_E)
+}
+
+impl Drum {
+    /// One hit at full level, at [`TONE_RATE`].
+    fn render(self) -> Vec<f32> {
+        let rate = TONE_RATE as f64;
+        let length = match self {
+            Drum::Kick => 0.5,
+            Drum::Snare => 0.3,
+            Drum::Hat => 0.15,
+            Drum::OpenHat => 0.6,
+            Drum::Clap => 0.35,
+            Drum::Click => 0.03,
+        };
+        let mut rng = Rng::new(NOISE_SEED);
+        // High-passed noise: the noise less its low-passed part.
+        let noise = |cutoff: f64| {
+            let mut low = OnePole::low_pass(cutoff, TONE_RATE);
+            move || {
+                let white = rng.next_sample();
+                (white - low.process(white)) as f64
+            }
+        };
+        let len = (length * rate) as usize;
+        match self {
+            Drum::Kick => {
+                // A sine whose pitch drops fast from 150 Hz to 50 Hz: the beater's click,
+                // then the boom.
+                let mut phase = 0.0;
+                (0..len)
+                    .map(|n| {
+                        let t = n as f64 / rate;
+                        phase += (50.0 + 100.0 * decay(t, 0.03)) / rate;
+                        (sin_cycles(phase) * decay(t, 0.15)) as f32
+                    })
+                    .collect()
+            }
+            Drum::Snare => {
+                let mut rattle = noise(1500.0);
+                (0..len)
+                    .map(|n| {
+                        let t = n as f64 / rate;
+                        let body = sin_cycles(185.0 * t) * decay(t, 0.05);
+                        (0.35 * body + 0.5 * rattle() * decay(t, 0.07)) as f32
+                    })
+                    .collect()
+            }
+            Drum::Hat | Drum::OpenHat => {
+                let time = if self == Drum::Hat { 0.02 } else { 0.15 };
+                let mut sizzle = noise(7000.0);
+                (0..len)
+                    .map(|n| (0.4 * sizzle() * decay(n as f64 / rate, time)) as f32)
+                    .collect()
+            }
+            Drum::Clap => {
+                // Three quick slaps 10ms apart, then the room's tail.
+                let mut clap = noise(900.0);
+                let mut body = OnePole::low_pass(2500.0, TONE_RATE);
+                (0..len)
+                    .map(|n| {
+                        let t = n as f64 / rate;
+                        let envelope = if t < 0.03 {
+                            decay(t % 0.01, 0.003)
+                        } else {
+                            decay(t - 0.03, 0.08)
+                        };
+                        (1.2 * body.process(clap() as f32) as f64 * envelope) as f32
+                    })
+                    .collect()
+            }
+            Drum::Click => (0..len)
+                .map(|n| {
+                    let t = n as f64 / rate;
+                    (0.8 * sin_cycles(2000.0 * t) * decay(t, 0.004)) as f32
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+use std::time::Duration;
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats},
+    ear_guard::ear_guard_flags,
+    no_audio::no_audio_flags,
+    sound_make::{Envelope, Tone, Waveform},
+    sound_sequence::{output_steps, Step},
+    validate::{self, duration_flag, duration_shape},
+    wav::wav_flags,
+    Sound,
+};
+
+/// Length of each tone when `--tone` is not given.
+const DEFAULT_TONE: Duration = Duration::from_millis(100);
+
+/// Silence between tones when `--gap` is not given.
+const DEFAULT_GAP: Duration = Duration::from_millis(100);
+
+/// Low (row) and high (column) frequencies of the DTMF keypad, in Hz.
+const ROWS: [f32; 4] = [697.0, 770.0, 852.0, 941.0];
+const COLUMNS: [f32; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+
+/// The keypad: symbol, row and column.
+const KEYPAD: [(char, usize, usize); 16] = [
+    ('1', 0, 0),
+    ('2', 0, 1),
+    ('3', 0, 2),
+    ('A', 0, 3),
+    ('4', 1, 0),
+    ('5', 1, 1),
+    ('6', 1, 2),
+    ('B', 1, 3),
+    ('7', 2, 0),
+    ('8', 2, 1),
+    ('9', 2, 2),
+    ('C', 2, 3),
+    ('*', 3, 0),
+    ('0', 3, 1),
+    ('#', 3, 2),
+    ('D', 3, 3),
+];
+
+/// Characters phone numbers are written with that do not dial anything.
+const SEPARATORS: [char; 5] = [' ', '-', '.', '(', ')'];
+
+/// Nushell command `sound dtmf` — the touch tones of a dial string.
+///
+/// Each symbol is the keypad's row and column frequency played together; symbols are
+/// separated by `--gap` of silence. The whole string is rendered as one
+/// [`sound sequence`](crate::sound_sequence), so it plays or exports the same way.
+pub struct SoundDtmfCmd;
+
+impl SimplePluginCommand for SoundDtmfCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound dtmf"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound dtmf")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required(
+                "Digits",
+                SyntaxShape::String,
+                "dial string of 0-9, *, # and A-D; spaces, dashes, dots and parentheses are skipped",
+            )
+            .named(
+                "tone",
+                duration_shape(),
+                "length of each tone (default 100ms)",
+                Some('t'),
+            )
+            .named(
+                "gap",
+                duration_shape(),
+                "silence between tones (default 100ms)",
+                Some('g'),
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the tones by given value (e.g. 0.5 for half volume)",
+                Some('a'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow dial strings longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(signature)))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "dial a phone number",
+                example: "sound dtmf \"555-0123\"",
+                result: None,
+            },
+            Example {
+                description: "fast dialing, as an autodialer would",
+                example: "sound dtmf \"0123456789*#\" --tone 50ms --gap 50ms",
+                result: None,
+            },
+            Example {
+                description: "save the tones of a voicemail PIN to a file",
+                example: "sound dtmf \"1234#\" --data | save --raw pin.wav",
+                result: None,
+            },
+            Example {
+                description: "one 100ms tone is a 44-byte WAV header and 4800 16-bit mono samples",
+                example: "sound dtmf 5 --data | bytes length",
+                result: Some(Value::test_int(44 + 4800 * 2)),
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play the DTMF (touch-tone) pairs of a dial string, or output them as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let digits: Spanned<String> = call.req(0)?;
+        let tone = duration_flag(call, "tone")?.unwrap_or(DEFAULT_TONE);
+        let gap = duration_flag(call, "gap")?.unwrap_or(DEFAULT_GAP);
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+        let steps = dial_steps(&digits.item, tone, gap, amplify, digits.span)?;
+        output_steps(engine, call, &steps)
+    }
+}
+
+/// The tone pair of a keypad symbol; letters are accepted in either case.
+fn dtmf_pair(symbol: char) -> Option<[f32; 2]> {
+    let symbol = symbol.to_ascii_uppercase();
+    KEYPAD
+        .iter()
+        .find(|(key, _, _)| *key == symbol)
+        .map(|(_, row, column)| [ROWS[*row], COLUMNS[*column]])
+}
+
+/// Steps for a dial string: one tone per symbol, with `gap` between consecutive tones.
+fn dial_steps(
+    digits: &str,
+    tone: Duration,
+    gap: Duration,
+    amplify: f32,
+    span: Span,
+) -> Result<Vec<Step>, LabeledError> {
+    let mut steps = Vec::new();
+    for symbol in digits.chars().filter(|c| !SEPARATORS.contains(c)) {
+        let frequencies = dtmf_pair(symbol).ok_or_else(|| {
+            LabeledError::new(format!("'{symbol}' is not a DTMF symbol"))
+                .with_label("expected 0-9, *, # or A-D", span)
+        })?;
+        if !steps.is_empty() && !gap.is_zero() {
+            steps.push(Step::Rest(gap));
+        }
+        steps.push(Step::Tone(Tone {
+            waveform: Waveform::Sine,
+            frequencies: frequencies.to_vec(),
+            right_frequencies: None,
+            pan: None,
+            harmonics: None,
+            vibrato: None,
+            tremolo: None,
+            expression: None,
+            instrument: None,
+            duration: tone,
+            amplify,
+            envelope: Envelope::default(),
+        }));
+    }
+    if steps.is_empty() {
+        return Err(LabeledError::new("nothing to dial")
+            .with_label("the dial string has no DTMF symbols", span));
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sound_sequence::render, utils::example_results, wav::WavOptions};
+
+    #[test]
+    fn example_result_matches_rendered_wav() {
+        let steps = dial_steps("5", DEFAULT_TONE, DEFAULT_GAP, 1.0, Span::test_data()).unwrap();
+        let wav = WavOptions::default().encode(render(&steps)).unwrap();
+        assert_eq!(
+            example_results(&SoundDtmfCmd),
+            [Value::test_int(wav.len() as i64)]
+        );
+    }
+}
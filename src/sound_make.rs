@@ -1,11 +1,109 @@
-use nu_plugin::{EvaluatedCall, SimplePluginCommand};
-use nu_protocol::{Category, Example, LabeledError, Signature, Span, SyntaxShape, Value};
-use rodio::source::{SineWave, Source};
-use rodio::{OutputStreamBuilder, Sink};
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, Span, Spanned,
+    SyntaxShape, Value,
+};
+use rodio::mixer::mixer;
+use rodio::source::{SeekError, Source};
+use rodio::{ChannelCount, OutputStreamBuilder, SampleRate, Sink};
 
-use std::time::Duration;
+use std::{f64::consts::TAU, sync::Arc, time::Duration};
 
-use crate::Sound;
+use crate::{
+    arpeggio::{arpeggio_flags, Arpeggio},
+    beep::{pattern_steps, theme_cue},
+    completions::{
+        arp_orders, beep_patterns, no_audio_policies, stream_roles, wav_sample_formats, waveforms,
+    },
+    config::flag_or_config,
+    cooldown::cooldown_flags,
+    dsp::{cos_cycles, exp2, sin_cycles},
+    ear_guard::{ear_guard_flags, EarGuard},
+    expr::Expression,
+    instrument::{Instrument, Voice},
+    no_audio::{no_audio_flags, NoAudio},
+    sound_sequence::{render, Step},
+    stream_identity::{open_identified, role_flags, StreamRole},
+    utils::flag_span,
+    validate::{self, duration_flag},
+    wav::{wav_flags, WavOptions},
+    Sound,
+};
+
+/// Sample rate tones are rendered at.
+pub const TONE_RATE: u32 = 48_000;
+
+/// Pitch of `sound beep` when neither `--frequency` nor the config sets one.
+const DEFAULT_BEEP_FREQUENCY: f32 = 1000.0;
+
+/// Length of `sound beep` when neither `--duration` nor the config sets one.
+const DEFAULT_BEEP_DURATION: Duration = Duration::from_millis(300);
+
+/// Longest tone made without `--force`.
+pub const MAX_LENGTH: Duration = Duration::from_secs(3600);
+
+/// Width of a pulse wave when `--duty` is not given: the thin, nasal pulse of 8-bit
+/// consoles.
+const DEFAULT_DUTY: f32 = 0.25;
+
+/// Most `--harmonics` weights accepted; higher partials of any audible note are past
+/// the Nyquist frequency anyway.
+const MAX_HARMONICS: usize = 64;
+
+/// Fastest `--vibrato` or `--tremolo`; faster modulation stops sounding like a wobble
+/// and turns into a rough, buzzing timbre.
+const MAX_MODULATION_RATE: f32 = 20.0;
+
+/// Highest carrier or modulator ratio of `--fm`.
+const MAX_FM_RATIO: f32 = 32.0;
+
+/// Highest modulation index of `--fm`; beyond it the tone is noise.
+const MAX_FM_INDEX: f32 = 50.0;
+
+/// Widest `--vibrato`, in semitones either way.
+const MAX_VIBRATO_DEPTH: f32 = 12.0;
+
+/// Adds the flags that shape generated tones: `--waveform`, `--duty` and the ADSR
+/// envelope.
+pub fn tone_flags(signature: Signature) -> Signature {
+    signature
+        .named(
+            "attack",
+            validate::duration_shape(),
+            "time to rise from silence to full level",
+            None,
+        )
+        .named(
+            "decay",
+            validate::duration_shape(),
+            "time to fall from full level to the sustain level",
+            None,
+        )
+        .named(
+            "sustain",
+            SyntaxShape::Float,
+            "level held after the decay, from 0.0 to 1.0 (default 1.0)",
+            None,
+        )
+        .named(
+            "release",
+            validate::duration_shape(),
+            "time to fade to silence at the end of the tone",
+            None,
+        )
+        .named(
+            "waveform",
+            SyntaxShape::String,
+            "shape of the tone: sine (default), square, triangle, sawtooth or pulse",
+            Some('w'),
+        )
+        .named(
+            "duty",
+            SyntaxShape::Float,
+            "fraction of each cycle a pulse wave is high, from 0.01 to 0.99 (default 0.25); implies --waveform pulse",
+            None,
+        )
+}
 
 pub struct SoundMakeCmd;
 
@@ -17,21 +115,84 @@ impl SimplePluginCommand for SoundMakeCmd {
     }
 
     fn signature(&self) -> nu_protocol::Signature {
-        Signature::new("sound make")
-            .required("Frequency", SyntaxShape::Float, "Frequency of the noise")
-            .required("Duration", SyntaxShape::Duration, "Duration of the noise")
+        let signature = Signature::new("sound make")
+            .required(
+                "Frequency",
+                frequency_shape(),
+                "Frequency of the noise, in Hz (440, 440.0, \"440hz\", \"1.2khz\") or as a note (\"A4\", \"C#5\"); a list or a chord name (\"C4maj\", \"Am7\") plays a chord",
+            )
+            .required("Duration", validate::duration_shape(), "Duration of the noise")
             .named(
                 "amplify",
                 SyntaxShape::Float,
                 "amplify or attenuate the sound by given value (e.g. 0.5 for half volume)",
                 Some('a'),
             )
+            .named(
+                "harmonics",
+                SyntaxShape::OneOf(vec![
+                    SyntaxShape::String,
+                    SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                ]),
+                "levels of the fundamental and its overtones, e.g. \"1.0,0.5,0.25\" or [1 0 0.3]",
+                Some('H'),
+            )
+            .named(
+                "vibrato",
+                SyntaxShape::String,
+                "wobble the pitch: <rate>:<depth> in Hz and semitones either way, e.g. 6hz:0.5",
+                None,
+            )
+            .named(
+                "tremolo",
+                SyntaxShape::String,
+                "wobble the level: <rate>:<depth> in Hz and the fraction the level dips, e.g. 8hz:0.6",
+                None,
+            )
+            .named(
+                "expr",
+                SyntaxShape::String,
+                "compute each sample from t (seconds) and f (the note's frequency) instead of a waveform, e.g. \"sin(2*pi*f*t) * exp(-3*t)\"",
+                Some('e'),
+            )
+            .named(
+                "fm",
+                SyntaxShape::String,
+                "FM synthesis instead of a waveform: <carrier>:<modulator>:<index>, ratios to the note's frequency and the modulation depth, e.g. 1:1.4:3",
+                None,
+            )
+            .named(
+                "ring",
+                SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::String]),
+                "ring-modulate the tone with a sine of this frequency, in Hz or as a note",
+                None,
+            )
+            .named(
+                "pan",
+                SyntaxShape::Float,
+                "stereo position from -1.0 (left) to 1.0 (right); makes a stereo tone",
+                Some('p'),
+            )
+            .named(
+                "right-frequency",
+                frequency_shape(),
+                "play this frequency on the right channel instead, making a stereo tone (e.g. binaural beats)",
+                Some('r'),
+            )
             .switch(
                 "data",
                 "output binary data (WAV) instead of playing",
                 Some('d'),
             )
-            .category(Category::Experimental)
+            .switch(
+                "force",
+                "allow tones longer than an hour",
+                Some('f'),
+            )
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(arpeggio_flags(
+            tone_flags(signature),
+        ))))
     }
     fn examples(&self) -> Vec<Example<'_>> {
         vec![
@@ -40,6 +201,86 @@ impl SimplePluginCommand for SoundMakeCmd {
                 example: "sound make 1000 200ms",
                 result: None,
             },
+            Example {
+                description: "play the note A4 (440 Hz)",
+                example: "sound make A4 200ms",
+                result: None,
+            },
+            Example {
+                description: "play a C major chord",
+                example: "sound make [C4 E4 G4] 1sec",
+                result: None,
+            },
+            Example {
+                description: "the same chord by name, and a minor seventh in octave 3",
+                example: "sound make C4maj 1sec; sound make A3m7 1sec",
+                result: None,
+            },
+            Example {
+                description: "arpeggiate a chord in sixteenth notes at 120 bpm, up and back down",
+                example: "sound make C4maj7 2sec --arp up-down --waveform triangle",
+                result: None,
+            },
+            Example {
+                description: "a quick rising notification: three eighth-note triplets at 150 bpm",
+                example: "sound make [E5 G#5 B5] 400ms --arp up --rate 1/8t --bpm 150 --waveform square -a 0.3",
+                result: None,
+            },
+            Example {
+                description: "a chord from frequencies, saved as WAV",
+                example: "sound make [261.6 329.6 392.0] 1sec --data | save --raw chord.wav",
+                result: None,
+            },
+            Example {
+                description: "a richer alert: the fundamental with half-level 2nd and quarter-level 3rd harmonics",
+                example: "sound make 660 300ms --harmonics \"1.0,0.5,0.25\"",
+                result: None,
+            },
+            Example {
+                description: "an organ-like tone from odd harmonics only",
+                example: "sound make C4 1sec --harmonics [1 0 0.5 0 0.3] --release 100ms",
+                result: None,
+            },
+            Example {
+                description: "a warbling alert: the pitch swings half a semitone six times a second",
+                example: "sound make A5 1sec --vibrato 6hz:0.5",
+                result: None,
+            },
+            Example {
+                description: "a pulsing tone whose level dips by 60% eight times a second",
+                example: "sound make 880 1sec --tremolo 8hz:0.6 --waveform triangle",
+                result: None,
+            },
+            Example {
+                description: "a struck bell: a sine with an inharmonic overtone, dying away",
+                example: "sound make 440 2sec --expr \"(sin(2*pi*f*t) + 0.5 * sin(2*pi*2.76*f*t)) * exp(-3*t) / 1.5\"",
+                result: None,
+            },
+            Example {
+                description: "a siren sweeping an octave up and down once a second, for any chord",
+                example: "sound make [C4 E4] 3sec --expr \"sin(2*pi*f*(t - cos(2*pi*t) / (2*pi)))\"",
+                result: None,
+            },
+            Example {
+                description: "an FM bell: a carrier at the note, a modulator at 1.4 times it, dying away",
+                example: "sound make C5 2sec --fm 1:1.4:4 --decay 1500ms --sustain 0",
+                result: None,
+            },
+            Example {
+                description: "a sci-fi robot voice: a square wave ring-modulated by 30 Hz",
+                example: "sound make 220 1sec --waveform square --ring 30 -a 0.4",
+                result: None,
+            },
+            Example {
+                description: "a 10 Hz binaural beat: 400 Hz on the left, 410 Hz on the right (use headphones)",
+                example: "sound make 400 10sec --right-frequency 410",
+                result: None,
+            },
+            Example {
+                description: "a tone from the left, then one from the right",
+                example: "sound make C5 300ms --pan -0.8; sound make G5 300ms --pan 0.8",
+                result: None,
+            },
             Example {
                 description: "create a simple noise frequency with 50% volume",
                 example: "sound make 1000 200ms -a 0.5",
@@ -51,25 +292,83 @@ impl SimplePluginCommand for SoundMakeCmd {
                     "[ 300.0, 500.0,  1000.0, 400.0, 600.0 ] | each { |it| sound make $it 150ms }",
                 result: None,
             },
+            Example {
+                description: "play a square wave, as an 8-bit style alert",
+                example: "sound make 880 150ms --waveform square -a 0.3",
+                result: None,
+            },
+            Example {
+                description: "a chiptune blip: a thin 12.5% pulse",
+                example: "sound make E6 80ms --waveform pulse --duty 0.125 -a 0.3",
+                result: None,
+            },
+            Example {
+                description: "a plucked note: quick attack, decay to a quiet sustain, soft release",
+                example: "sound make A4 1sec --attack 5ms --decay 200ms --sustain 0.3 --release 300ms",
+                result: None,
+            },
             Example {
                 description: "save a noise to a file",
                 example: "sound make 1000 200ms --data | save --raw output.wav",
                 result: None,
             },
+            Example {
+                description: "a 10ms tone is a 44-byte WAV header and 480 16-bit mono samples",
+                example: "sound make 1000 10ms --data | bytes length",
+                result: Some(Value::test_int(44 + 480 * 2)),
+            },
+            Example {
+                description: "in stereo the same tone has twice the samples",
+                example: "sound make 1000 10ms --right-frequency 1010 --data | bytes length",
+                result: Some(Value::test_int(44 + 2 * 480 * 2)),
+            },
+            Example {
+                description: "CD-quality stereo for a tool that expects 44.1 kHz",
+                example: "sound make A4 1sec --data --rate 44100 --channels 2 | save --raw a4.wav",
+                result: None,
+            },
+            Example {
+                description: "32-bit float WAV for a DAW",
+                example: "sound make 1000 1sec --data --bits 32f | save --raw tone.wav",
+                result: None,
+            },
+            Example {
+                description: "the generated data is a RIFF/WAVE file",
+                example: "sound make 1000 10ms --data | first 4",
+                result: Some(Value::test_binary(b"RIFF".to_vec())),
+            },
         ]
     }
     fn description(&self) -> &str {
         "creates a noise with given frequency and duration"
     }
 
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "waveform" => Some(waveforms()),
+            ArgType::Flag(flag) if flag == "arp" => Some(arp_orders()),
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
     fn run(
         &self,
         _plugin: &Self::Plugin,
-        _engine: &nu_plugin::EngineInterface,
+        engine: &nu_plugin::EngineInterface,
         call: &EvaluatedCall,
         _input: &Value,
     ) -> Result<Value, nu_protocol::LabeledError> {
-        make_sound(call)
+        make_sound(engine, call)
     }
 }
 
@@ -83,176 +382,1477 @@ impl SimplePluginCommand for SoundBeepCmd {
     }
 
     fn signature(&self) -> nu_protocol::Signature {
-        Signature::new("sound beep").category(Category::Experimental)
+        role_flags(cooldown_flags(no_audio_flags(ear_guard_flags(
+            Signature::new("sound beep")
+                .named(
+                    "frequency",
+                    SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::String]),
+                    "pitch of the beep, in Hz or as a note (default 1000, or beep_frequency in the plugin config)",
+                    None,
+                )
+                .named(
+                    "duration",
+                    validate::duration_shape(),
+                    "length of the beep (default 300ms, or beep_duration in the plugin config)",
+                    None,
+                )
+                .named(
+                    "amplify",
+                    SyntaxShape::Float,
+                    "volume of the beep or pattern (default 1.0, or beep_volume in the plugin config)",
+                    Some('a'),
+                )
+                .named(
+                    "pattern",
+                    SyntaxShape::OneOf(vec![
+                        SyntaxShape::String,
+                        SyntaxShape::List(Box::new(SyntaxShape::Record(vec![]))),
+                    ]),
+                    "play a cue instead: success, error, warning or question, or a list of {freq, duration, gap} records",
+                    Some('p'),
+                )
+                .named(
+                    "theme",
+                    SyntaxShape::Filepath,
+                    "directory of audio files or SoundFont with the cues to play, named after the patterns (default: beep_theme in the plugin config)",
+                    None,
+                )
+                .category(Category::Experimental),
+        ))))
     }
     fn examples(&self) -> Vec<Example<'_>> {
-        vec![Example {
-            description: "create a simple beep sound",
-            example: "sound beep",
-            result: None,
-        }]
+        vec![
+            Example {
+                description: "create a simple beep sound",
+                example: "sound beep",
+                result: None,
+            },
+            Example {
+                description: "beep when a job finishes, even on a headless server",
+                example: "make build; sound beep --if-no-audio bell",
+                result: None,
+            },
+            Example {
+                description: "a lower, shorter, quieter beep",
+                example: "sound beep --frequency A5 --duration 120ms --amplify 0.4",
+                result: None,
+            },
+            Example {
+                description: "tell success from failure by ear",
+                example: "if (do { cargo test } | complete).exit_code == 0 { sound beep -p success } else { sound beep -p error }",
+                result: None,
+            },
+            Example {
+                description: "a flapping job warns at most once every 30 seconds",
+                example: "sound beep -p warning --cooldown 30sec --key build-done",
+                result: None,
+            },
+            Example {
+                description: "play the team's own success sound from a theme directory holding success.wav",
+                example: "sound beep --pattern success --theme ~/.config/cues",
+                result: None,
+            },
+            Example {
+                description: "a custom cue: two short high beeps and a longer low one",
+                example: "sound beep --pattern [{freq: 1200, duration: 60ms, gap: 40ms} {freq: 1200, duration: 60ms, gap: 40ms} {freq: 600, duration: 200ms}]",
+                result: None,
+            },
+        ]
     }
     fn description(&self) -> &str {
         "creates a beep noise"
     }
 
-    fn run(
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
         &self,
         _plugin: &Self::Plugin,
-        _engine: &nu_plugin::EngineInterface,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "pattern" => Some(beep_patterns()),
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "role" => Some(stream_roles()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        engine: &nu_plugin::EngineInterface,
         call: &EvaluatedCall,
         _input: &Value,
     ) -> Result<Value, nu_protocol::LabeledError> {
-        sine_wave(1000.0, Duration::from_millis(300), 1.0)?;
-        return Ok(Value::nothing(call.head));
+        let pattern = call.get_flag_value("pattern");
+        if let Some(flag) = ["frequency", "duration"]
+            .into_iter()
+            .find(|flag| pattern.is_some() && call.get_flag_value(flag).is_some())
+        {
+            return Err(LabeledError::new("conflicting flags")
+                .with_label(
+                    format!("--pattern sets its own {flag}s"),
+                    flag_span(call, flag),
+                )
+                .with_help("put the frequency and duration of each beep in the pattern"));
+        }
+        let sound = match &pattern {
+            Some(Value::String { val, .. }) => format!("beep:{}", val.trim().to_lowercase()),
+            _ => "beep".to_string(),
+        };
+        if !plugin.cooldowns.ready(engine, call, &sound)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let amplify = match flag_or_config(engine, call, "amplify", "beep_volume")? {
+            Some(value) => validate::gain(&value, "beep volume")?,
+            None => 1.0,
+        };
+        let cue = match flag_or_config(engine, call, "theme", "beep_theme")? {
+            Some(theme) => theme_cue(engine, theme, pattern.as_ref(), amplify)?,
+            None => None,
+        };
+        // A pattern too long to play is the pattern's fault.
+        let length_span = pattern.as_ref().map_or(call.head, Value::span);
+        let source = match cue {
+            Some(cue) => cue,
+            None => render(&beep_steps(engine, call, pattern, amplify)?),
+        };
+        let length = source.total_duration().unwrap_or_default();
+        validate::length(length, MAX_LENGTH, false, length_span)?;
+        if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        let role = StreamRole::from_call(call, StreamRole::Notification)?;
+        play_source(source, guard, role)?;
+        Ok(Value::nothing(call.head))
     }
 }
 
-fn make_sound(call: &EvaluatedCall) -> Result<Value, LabeledError> {
-    let (frequency_value, duration_value, amplify_value) = load_values(call)?;
+/// The built-in beeps `sound beep` plays: its `--pattern`, or a single beep at the
+/// configured frequency and duration.
+fn beep_steps(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    pattern: Option<Value>,
+    amplify: f32,
+) -> Result<Vec<Step>, LabeledError> {
+    if let Some(pattern) = pattern {
+        return pattern_steps(call, &pattern, amplify);
+    }
+    let frequency = match flag_or_config(engine, call, "frequency", "beep_frequency")? {
+        Some(value) => validate::frequency(parse_frequency(&value)?, TONE_RATE, value.span())?,
+        None => DEFAULT_BEEP_FREQUENCY,
+    };
+    let duration = match flag_or_config(engine, call, "duration", "beep_duration")? {
+        Some(value) => validate::duration(&value, "beep duration")?,
+        None => DEFAULT_BEEP_DURATION,
+    };
+    Ok(vec![Step::Tone(Tone {
+        amplify,
+        ..Tone::sine(frequency, duration)
+    })])
+}
+
+fn make_sound(engine: &EngineInterface, call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let tone = load_tone(call)?;
+    let source: Box<dyn Source + Send> = match Arpeggio::from_call(call)? {
+        Some(arpeggio) => Box::new(arpeggio.render(&tone, call)?),
+        None => Box::new(tone.source()),
+    };
+    let source: Box<dyn Source + Send> = match call.get_flag_value("ring") {
+        Some(value) => {
+            let frequency = validate::frequency(parse_frequency(&value)?, TONE_RATE, value.span())?;
+            Box::new(Ring::new(source, frequency))
+        }
+        None => source,
+    };
 
     if call
         .has_flag("data")
         .map_err(|e| LabeledError::new(e.to_string()).with_label("checking 'data' flag", call.head))?
     {
-        let wav_data = generate_wav(frequency_value, duration_value, amplify_value)?;
+        let wav_data = WavOptions::from_call(call)?.encode(source)?;
         Ok(Value::binary(wav_data, call.head))
     } else {
-        sine_wave(frequency_value, duration_value, amplify_value)?;
+        if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        play_source(source, guard, StreamRole::Notification)?;
         Ok(Value::nothing(call.head))
     }
 }
 
-fn sine_wave(
-    frequency_value: f32,
-    duration_value: Duration,
-    amplify_value: f32,
-) -> Result<(), LabeledError> {
-    let mut stream_handle = OutputStreamBuilder::open_default_stream().map_err(|err| {
-        LabeledError::new(err.to_string()).with_label("audio stream exception", Span::unknown())
-    })?;
+/// A generated tone, as described by `sound make`'s arguments. Several frequencies
+/// sound together as a chord.
+#[derive(Clone)]
+pub struct Tone {
+    pub waveform: Waveform,
+    pub frequencies: Vec<f32>,
+    /// Frequencies of the right channel. When set the tone is stereo, with
+    /// `frequencies` on the left.
+    pub right_frequencies: Option<Vec<f32>>,
+    /// Stereo position from -1.0 (left) to 1.0 (right). When set the tone is stereo.
+    pub pan: Option<f32>,
+    /// Relative levels of each note's partials: the fundamental, then the 2nd, 3rd, ...
+    /// harmonic. `None` plays the fundamental alone.
+    pub harmonics: Option<Vec<f32>>,
+    /// Pitch modulation; its depth is in semitones.
+    pub vibrato: Option<Modulation>,
+    /// Level modulation; its depth is the fraction the level dips.
+    pub tremolo: Option<Modulation>,
+    /// Computes each note's samples instead of the waveform, harmonics and vibrato.
+    pub expression: Option<Arc<Expression>>,
+    /// Plays each note with a recorded voice instead of the waveform, harmonics and
+    /// vibrato.
+    pub instrument: Option<Arc<Instrument>>,
+    pub duration: Duration,
+    pub amplify: f32,
+    pub envelope: Envelope,
+}
+
+impl Tone {
+    /// A plain sine at full level with no envelope.
+    pub fn sine(frequency: f32, duration: Duration) -> Tone {
+        Tone {
+            waveform: Waveform::Sine,
+            frequencies: vec![frequency],
+            right_frequencies: None,
+            pan: None,
+            harmonics: None,
+            vibrato: None,
+            tremolo: None,
+            expression: None,
+            instrument: None,
+            duration,
+            amplify: 1.0,
+            envelope: Envelope::default(),
+        }
+    }
+
+    /// The tone as a source at [`TONE_RATE`]: mono, or stereo when it has
+    /// [`right_frequencies`](Tone::right_frequencies) or a [`pan`](Tone::pan).
+    ///
+    /// Panning uses the constant-power law, so a tone keeps its loudness as it moves
+    /// across; in the center each channel is 3 dB down.
+    pub fn source(&self) -> impl Source + Send {
+        let stereo = self.right_frequencies.is_some() || self.pan.is_some();
+        let (left_gain, right_gain) = match self.pan {
+            Some(pan) => {
+                // In cycles, a quarter turn across: 0 hard left, 0.25 hard right.
+                let angle = (pan as f64 + 1.0) / 8.0;
+                (cos_cycles(angle) as f32, sin_cycles(angle) as f32)
+            }
+            None => (1.0, 1.0),
+        };
+        let right_frequencies = self.right_frequencies.as_ref().unwrap_or(&self.frequencies);
+        Stereo {
+            left: self.channel(&self.frequencies, left_gain),
+            right: stereo.then(|| self.channel(right_frequencies, right_gain)),
+            right_next: false,
+        }
+    }
+
+    /// One channel of the tone at `gain`. Chord notes are mixed at equal level, scaled
+    /// so the chord peaks no higher than a single note.
+    fn channel(&self, frequencies: &[f32], gain: f32) -> impl Source + Send {
+        let (mixer, chord) = mixer(1, TONE_RATE);
+        let level = 1.0 / frequencies.len().max(1) as f32;
+        let harmonics = self.harmonics.as_deref().unwrap_or(&[1.0]);
+        for &frequency in frequencies {
+            let note: Box<dyn Source + Send> = match (&self.instrument, &self.expression) {
+                (Some(instrument), _) => Box::new(Voice::new(Arc::clone(instrument), frequency)),
+                (None, Some(expression)) => {
+                    Box::new(Evaluated::new(Arc::clone(expression), frequency))
+                }
+                (None, None) => {
+                    Box::new(Partials::new(self.waveform, frequency, harmonics, self.vibrato))
+                }
+            };
+            mixer.add(note.take_duration(self.duration).amplify(level));
+        }
+        Enveloped::new(
+            Tremolo::new(chord.amplify(self.amplify * gain), self.tremolo),
+            self.envelope,
+            self.duration,
+        )
+    }
+}
+
+/// Plays generated audio on the default output device, through the ear guard when one
+/// is set, and waits until it has finished.
+pub fn play_source<S>(
+    source: S,
+    guard: Option<EarGuard>,
+    role: StreamRole,
+) -> Result<(), LabeledError>
+where
+    S: Source + Send + 'static,
+{
+    let mut stream_handle =
+        open_identified(Some("tone"), role, OutputStreamBuilder::open_default_stream).map_err(
+            |err| {
+                LabeledError::new(err.to_string())
+                    .with_label("audio stream exception", Span::unknown())
+            },
+        )?;
 
     stream_handle.log_on_drop(false);
 
     let sink = Sink::connect_new(stream_handle.mixer());
-    let source = SineWave::new(frequency_value)
-        .take_duration(duration_value)
-        .amplify(amplify_value);
-    sink.append(source);
+    match guard {
+        Some(guard) => sink.append(guard.protect(source)),
+        None => sink.append(source),
+    }
     sink.sleep_until_end();
     Ok(())
 }
 
-fn generate_wav(
-    frequency: f32,
-    duration: Duration,
-    amplify: f32,
-) -> Result<Vec<u8>, LabeledError> {
-    let source = SineWave::new(frequency)
-        .take_duration(duration)
-        .amplify(amplify);
-    let sample_rate = source.sample_rate();
-    let num_channels = source.channels();
+fn load_tone(call: &EvaluatedCall) -> Result<Tone, LabeledError> {
+    let frequency: Value = call.req(0).map_err(|err| {
+        LabeledError::new(err.to_string()).with_label("Frequency value not found", call.head)
+    })?;
+
+    let frequencies = parse_frequencies(&frequency)?;
+    let duration: Value = call.req(1).map_err(|err| {
+        LabeledError::new(err.to_string()).with_label("Duration value not found", call.head)
+    })?;
 
-    let samples: Vec<i16> = source
-        .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
-        .collect();
+    let right_frequencies = call
+        .get_flag_value("right-frequency")
+        .map(|value| parse_frequencies(&value))
+        .transpose()?;
 
-    let bits_per_sample = 16u16;
-    let byte_rate_u64 = sample_rate as u64 * num_channels as u64 * bits_per_sample as u64 / 8;
-    if byte_rate_u64 > u32::MAX as u64 {
-        return Err(LabeledError::new("WAV header overflow").with_label("byte_rate exceeds u32", Span::unknown()));
+    let duration_value = validate::duration(&duration, "duration")?;
+    let force = call.has_flag("force")?;
+    validate::length(duration_value, MAX_LENGTH, force, duration.span())?;
+    let pan = call
+        .get_flag_value("pan")
+        .map(|value| validate::pan(&value, "--pan"))
+        .transpose()?;
+    let harmonics = call
+        .get_flag_value("harmonics")
+        .map(|value| parse_harmonics(&value))
+        .transpose()?;
+    let vibrato = call
+        .get_flag_value("vibrato")
+        .map(|value| Modulation::parse(&value, "--vibrato", MAX_VIBRATO_DEPTH, "semitones"))
+        .transpose()?;
+    let tremolo = call
+        .get_flag_value("tremolo")
+        .map(|value| Modulation::parse(&value, "--tremolo", 1.0, "of the level"))
+        .transpose()?;
+    let channels = if right_frequencies.is_some() || pan.is_some() { 2 } else { 1 };
+    let max_wav_length = WavOptions::from_call(call)?.max_length(TONE_RATE, channels);
+    if call.has_flag("data")? && duration_value > max_wav_length {
+        return Err(LabeledError::new("duration too long for a WAV file")
+            .with_label(
+                format!("WAV data is limited to {}s", max_wav_length.as_secs()),
+                duration.span(),
+            )
+            .with_help("play it instead, or generate shorter pieces"));
     }
-    let byte_rate = byte_rate_u64 as u32;
 
-    let block_align_u64 = num_channels as u64 * bits_per_sample as u64 / 8;
-    if block_align_u64 > u16::MAX as u64 {
-        return Err(LabeledError::new("WAV header overflow").with_label("block_align exceeds u16", Span::unknown()));
+    let amplify_value = match call.get_flag_value("amplify") {
+        Some(amplify) => validate::gain(&amplify, "--amplify")?,
+        None => 1.0,
+    };
+    let expression = match call.get_flag::<Spanned<String>>("expr")? {
+        Some(text) => {
+            if let Some(flag) = ["waveform", "duty", "harmonics", "vibrato", "fm"]
+                .into_iter()
+                .find(|flag| call.get_flag_value(flag).is_some())
+            {
+                return Err(LabeledError::new(format!("--expr cannot be combined with --{flag}"))
+                    .with_label("the expression computes the whole waveform", flag_span(call, flag))
+                    .with_help("write the shape, overtones or pitch wobble into the expression instead"));
+            }
+            Some(Arc::new(Expression::parse(&text.item, text.span)?))
+        }
+        None => None,
+    };
+    Ok(Tone {
+        waveform: Waveform::from_call(call)?,
+        frequencies,
+        right_frequencies,
+        pan,
+        harmonics,
+        vibrato,
+        tremolo,
+        expression,
+        instrument: None,
+        duration: duration_value,
+        amplify: amplify_value,
+        envelope: Envelope::from_call(call)?,
+    })
+}
+
+/// Interleaves two mono sources into a stereo one, or passes `left` through alone
+/// when there is no `right`. Both sides must be the same length.
+struct Stereo<S> {
+    left: S,
+    right: Option<S>,
+    right_next: bool,
+}
+
+impl<S: Source> Iterator for Stereo<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let Some(right) = self.right.as_mut() else {
+            return self.left.next();
+        };
+        self.right_next = !self.right_next;
+        if self.right_next {
+            self.left.next()
+        } else {
+            Some(right.next().unwrap_or(0.0))
+        }
     }
-    let block_align = block_align_u64 as u16;
+}
 
-    let subchunk2_size_u64 = samples.len() as u64 * bits_per_sample as u64 / 8;
-    if subchunk2_size_u64 > u32::MAX as u64 {
-        return Err(LabeledError::new("WAV data too large").with_label("exceeds u32 limit", Span::unknown()));
+impl<S: Source> Source for Stereo<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
     }
-    let subchunk2_size = subchunk2_size_u64 as u32;
 
-    let chunk_size = 36u32.checked_add(subchunk2_size).ok_or_else(|| {
-        LabeledError::new("WAV header overflow").with_label("chunk_size overflow", Span::unknown())
-    })?;
+    fn channels(&self) -> ChannelCount {
+        if self.right.is_some() {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.left.sample_rate()
+    }
 
-    let mut buffer = Vec::with_capacity(44 + subchunk2_size as usize);
+    fn total_duration(&self) -> Option<Duration> {
+        self.left.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.left.try_seek(pos)?;
+        if let Some(right) = self.right.as_mut() {
+            right.try_seek(pos)?;
+        }
+        self.right_next = false;
+        Ok(())
+    }
+}
 
-    // RIFF header
-    buffer.extend_from_slice(b"RIFF");
-    buffer.extend_from_slice(&chunk_size.to_le_bytes());
-    buffer.extend_from_slice(b"WAVE");
+/// ADSR amplitude envelope. The release happens within the tone's duration, so the
+/// tone keeps its length; without an envelope the tone starts and stops abruptly,
+/// which clicks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    pub attack: Duration,
+    pub decay: Duration,
+    /// Level held after the decay, relative to the peak.
+    pub sustain: f32,
+    pub release: Duration,
+}
 
-    // fmt subchunk
-    buffer.extend_from_slice(b"fmt ");
-    buffer.extend_from_slice(&16u32.to_le_bytes()); // Subchunk1Size for PCM
-    buffer.extend_from_slice(&1u16.to_le_bytes()); // AudioFormat (1 = PCM)
-    buffer.extend_from_slice(&num_channels.to_le_bytes());
-    buffer.extend_from_slice(&sample_rate.to_le_bytes());
-    buffer.extend_from_slice(&byte_rate.to_le_bytes());
-    buffer.extend_from_slice(&block_align.to_le_bytes());
-    buffer.extend_from_slice(&bits_per_sample.to_le_bytes());
+impl Default for Envelope {
+    fn default() -> Envelope {
+        Envelope {
+            attack: Duration::ZERO,
+            decay: Duration::ZERO,
+            sustain: 1.0,
+            release: Duration::ZERO,
+        }
+    }
+}
 
-    // data subchunk
-    buffer.extend_from_slice(b"data");
-    buffer.extend_from_slice(&subchunk2_size.to_le_bytes());
-    for sample in samples {
-        buffer.extend_from_slice(&sample.to_le_bytes());
+impl Envelope {
+    pub fn from_call(call: &EvaluatedCall) -> Result<Envelope, LabeledError> {
+        let sustain = match call.get_flag_value("sustain") {
+            Some(value) => validate::level(&value, "--sustain")?,
+            None => 1.0,
+        };
+        Ok(Envelope {
+            attack: duration_flag(call, "attack")?.unwrap_or_default(),
+            decay: duration_flag(call, "decay")?.unwrap_or_default(),
+            sustain,
+            release: duration_flag(call, "release")?.unwrap_or_default(),
+        })
     }
 
-    Ok(buffer)
+    /// Gain `elapsed` into a tone of `length`.
+    fn gain(&self, elapsed: f64, length: f64) -> f32 {
+        let attack = self.attack.as_secs_f64();
+        let decay = self.decay.as_secs_f64();
+        let release = self.release.as_secs_f64();
+        let sustain = self.sustain as f64;
+        let level = if elapsed < attack {
+            elapsed / attack
+        } else if elapsed < attack + decay {
+            1.0 - (1.0 - sustain) * (elapsed - attack) / decay
+        } else {
+            sustain
+        };
+        // Fades from whatever level was reached, so a release longer than the tone
+        // still ends in silence.
+        let remaining = length - elapsed;
+        let fade = if remaining < release {
+            (remaining / release).max(0.0)
+        } else {
+            1.0
+        };
+        (level * fade) as f32
+    }
 }
 
-fn load_values(call: &EvaluatedCall) -> Result<(f32, Duration, f32), LabeledError> {
-    let frequency: Value = call.req(0).map_err(|err| {
-        LabeledError::new(err.to_string()).with_label("Frequency value not found", call.head)
-    })?;
+/// Applies an [`Envelope`] to a source of known `length`.
+pub struct Enveloped<S> {
+    input: S,
+    envelope: Envelope,
+    length: f64,
+    /// Samples played, across channels.
+    position: u64,
+}
 
-    let frequency_value: f32 = match frequency.as_float() {
-        Ok(value) => value as f32,
-        Err(err) => {
-            return Err(LabeledError::new(err.to_string()).with_label(
-                "Frequency value must be of type Float (f32)",
-                frequency.span(),
-            ))
+impl<S: Source> Enveloped<S> {
+    pub fn new(input: S, envelope: Envelope, length: Duration) -> Enveloped<S> {
+        Enveloped {
+            input,
+            envelope,
+            length: length.as_secs_f64(),
+            position: 0,
         }
-    };
-    let duration: Value = call.req(1).map_err(|err| {
-        LabeledError::new(err.to_string()).with_label("Duration value not found", call.head)
-    })?;
+    }
+}
+
+impl<S: Source> Iterator for Enveloped<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        let rate = self.input.sample_rate() as f64 * self.input.channels() as f64;
+        let elapsed = self.position as f64 / rate;
+        self.position += 1;
+        Some(sample * self.envelope.gain(elapsed, self.length))
+    }
+}
+
+impl<S: Source> Source for Enveloped<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        let rate = self.input.sample_rate() as f64 * self.input.channels() as f64;
+        self.position = (pos.as_secs_f64() * rate) as u64;
+        Ok(())
+    }
+}
+
+/// Shape of a generated tone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+    /// Rectangle wave that is high for the given fraction of each cycle.
+    Pulse(f32),
+    /// Two-operator FM: a sine carrier whose phase is swung by a sine modulator.
+    /// `carrier` and `modulator` are multiples of the note's frequency; `index` is the
+    /// peak phase swing in radians, which sets how bright the tone is.
+    Fm {
+        carrier: f32,
+        modulator: f32,
+        index: f32,
+    },
+}
+
+/// `--waveform` names, with a description for completions.
+pub const WAVEFORMS: [(&str, Waveform, &str); 5] = [
+    ("sine", Waveform::Sine, "pure tone"),
+    ("square", Waveform::Square, "hollow, buzzy tone of odd harmonics"),
+    ("triangle", Waveform::Triangle, "soft tone of weak odd harmonics"),
+    ("sawtooth", Waveform::Sawtooth, "bright tone of all harmonics"),
+    (
+        "pulse",
+        Waveform::Pulse(DEFAULT_DUTY),
+        "thin, nasal chiptune tone; set its width with --duty",
+    ),
+];
 
-    let dur_span = duration.span();
-    let duration_value = match duration {
-        Value::Duration { val, .. } => {
-            if val < 0 {
-                return Err(LabeledError::new("Negative duration").with_label("Negative duration", dur_span));
+impl Waveform {
+    /// The waveform named by `--waveform`, a sine by default. `--duty` sets the width
+    /// of a pulse wave, and picks one when no waveform is named.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Waveform, LabeledError> {
+        if let Some(fm) = call.get_flag_value("fm") {
+            if let Some(flag) = ["waveform", "duty"]
+                .into_iter()
+                .find(|flag| call.get_flag_value(flag).is_some())
+            {
+                return Err(LabeledError::new(format!("--fm cannot be combined with --{flag}"))
+                    .with_label("FM makes its own waveform", flag_span(call, flag)));
             }
-            Duration::from_nanos(val as u64)
+            return Waveform::parse_fm(&fm);
         }
-        _ => {
-            return Err(LabeledError::new("cannot parse duration value as Duration")
-                .with_label("Expected duration", dur_span))
+        let waveform = call
+            .get_flag::<Spanned<String>>("waveform")?
+            .map(|name| Waveform::parse(&name))
+            .transpose()?;
+        let Some(value) = call.get_flag_value("duty") else {
+            return Ok(waveform.unwrap_or(Waveform::Sine));
+        };
+        let span = value.span();
+        let duty = value.as_float().map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("--duty must be a number", span)
+        })?;
+        if !(0.01..=0.99).contains(&duty) {
+            return Err(LabeledError::new("--duty out of range")
+                .with_label("must be between 0.01 and 0.99", span)
+                .with_help("0.5 is a square wave, 0.125 and 0.25 the thin pulses of 8-bit consoles"));
         }
+        match waveform {
+            None | Some(Waveform::Pulse(_)) => Ok(Waveform::Pulse(duty as f32)),
+            Some(_) => Err(LabeledError::new("--duty needs a pulse wave")
+                .with_label("only pulse waves have a duty cycle", span)
+                .with_help("use --waveform pulse, or leave --waveform out")),
+        }
+    }
+
+    /// An endless oscillator of this shape at `frequency`, with its pitch wobbled by
+    /// `vibrato`.
+    fn oscillator(self, frequency: f32, vibrato: Option<Modulation>) -> Box<dyn Source + Send> {
+        let vibrato = vibrato.map(Vibrato::new);
+        let shape = match self {
+            Waveform::Sine => Shape::Sine,
+            Waveform::Square => Shape::Square,
+            Waveform::Triangle => Shape::Triangle,
+            Waveform::Sawtooth => Shape::Sawtooth,
+            Waveform::Pulse(duty) => return Box::new(Pulse::new(frequency, duty, vibrato)),
+            Waveform::Fm {
+                carrier,
+                modulator,
+                index,
+            } => return Box::new(Fm::new(frequency, carrier, modulator, index, vibrato)),
+        };
+        Box::new(Oscillator::new(shape, frequency, vibrato))
+    }
+
+    /// Reads `--fm <carrier>:<modulator>:<index>`.
+    fn parse_fm(value: &Value) -> Result<Waveform, LabeledError> {
+        let span = value.span();
+        let invalid = |label: String| {
+            LabeledError::new("invalid --fm")
+                .with_label(label, span)
+                .with_help("e.g. --fm 1:1.4:3 for a bell, 1:1:2 for a brassy tone, 1:3.5:1.5 for a clang")
+        };
+        let text = value
+            .coerce_str()
+            .map_err(|_| invalid("expected <carrier>:<modulator>:<index>".to_string()))?;
+        let numbers = text
+            .split(':')
+            .map(|n| n.trim().parse::<f32>().ok())
+            .collect::<Option<Vec<_>>>()
+            .filter(|numbers| numbers.len() == 3)
+            .ok_or_else(|| invalid("expected three numbers separated by colons".to_string()))?;
+        let (carrier, modulator, index) = (numbers[0], numbers[1], numbers[2]);
+        for (ratio, name) in [(carrier, "carrier"), (modulator, "modulator")] {
+            if !(ratio > 0.0 && ratio <= MAX_FM_RATIO) {
+                return Err(invalid(format!(
+                    "the {name} ratio must be above 0 and at most {MAX_FM_RATIO}"
+                )));
+            }
+        }
+        if !(0.0..=MAX_FM_INDEX).contains(&index) {
+            return Err(invalid(format!("the index must be between 0 and {MAX_FM_INDEX}")));
+        }
+        Ok(Waveform::Fm {
+            carrier,
+            modulator,
+            index,
+        })
+    }
+
+    fn parse(name: &Spanned<String>) -> Result<Waveform, LabeledError> {
+        WAVEFORMS
+            .iter()
+            .find(|(n, _, _)| n.eq_ignore_ascii_case(name.item.trim()))
+            .map(|(_, waveform, _)| *waveform)
+            .ok_or_else(|| {
+                LabeledError::new(format!("unknown waveform '{}'", name.item))
+                    .with_label("unknown waveform", name.span)
+                    .with_help("use sine, square, triangle, sawtooth or pulse")
+            })
+    }
+}
+
+/// The shapes [`Oscillator`] makes; pulse waves have their own source, [`Pulse`].
+#[derive(Clone, Copy)]
+enum Shape {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+/// Endless mono tone at [`TONE_RATE`].
+///
+/// Square and sawtooth are band-limited with PolyBLEP, which smooths each jump over a
+/// sample either side; without it their harmonics above Nyquist fold back as audible,
+/// inharmonic whistles on high notes.
+struct Oscillator {
+    shape: Shape,
+    /// Position within the current cycle, in `0..1`.
+    phase: f64,
+    /// Phase advance per sample: frequency over sample rate.
+    step: f64,
+    vibrato: Option<Vibrato>,
+}
+
+impl Oscillator {
+    fn new(shape: Shape, frequency: f32, vibrato: Option<Vibrato>) -> Oscillator {
+        Oscillator {
+            shape,
+            phase: 0.0,
+            step: frequency as f64 / TONE_RATE as f64,
+            vibrato,
+        }
+    }
+}
+
+/// PolyBLEP residual for a jump at phase 0, `phase` being the position in the cycle.
+fn poly_blep(phase: f64, step: f64) -> f64 {
+    if phase < step {
+        let t = phase / step;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - step {
+        let t = (phase - 1.0) / step;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+impl Iterator for Oscillator {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let step = self.step * self.vibrato.as_mut().map_or(1.0, Vibrato::ratio);
+        let phase = self.phase;
+        let sample = match self.shape {
+            Shape::Sine => sin_cycles(phase),
+            Shape::Square => {
+                let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+                naive + poly_blep(phase, step) - poly_blep((phase + 0.5) % 1.0, step)
+            }
+            // Shifted a quarter cycle so it starts at zero like the sine.
+            Shape::Triangle => 1.0 - 4.0 * (((phase + 0.25) % 1.0) - 0.5).abs(),
+            Shape::Sawtooth => 2.0 * phase - 1.0 - poly_blep(phase, step),
+        };
+        self.phase = (phase + step) % 1.0;
+        Some(sample as f32)
+    }
+}
+
+impl Source for Oscillator {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        1
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        TONE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.phase = (pos.as_secs_f64() * self.step * TONE_RATE as f64) % 1.0;
+        if let Some(vibrato) = &mut self.vibrato {
+            vibrato.lfo.seek(pos);
+        }
+        Ok(())
+    }
+}
+
+/// Endless mono tone at [`TONE_RATE`] computed by an `--expr` expression. Samples are
+/// clipped to ±1.0, and NaN or infinite ones played as silence.
+struct Evaluated {
+    expression: Arc<Expression>,
+    frequency: f64,
+    stack: Vec<f64>,
+    /// Samples played.
+    position: u64,
+}
+
+impl Evaluated {
+    fn new(expression: Arc<Expression>, frequency: f32) -> Evaluated {
+        Evaluated {
+            stack: expression.stack(),
+            expression,
+            frequency: frequency as f64,
+            position: 0,
+        }
+    }
+}
+
+impl Iterator for Evaluated {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let t = self.position as f64 / TONE_RATE as f64;
+        self.position += 1;
+        let sample = self.expression.eval(t, self.frequency, &mut self.stack);
+        Some(if sample.is_finite() {
+            sample.clamp(-1.0, 1.0) as f32
+        } else {
+            0.0
+        })
+    }
+}
+
+impl Source for Evaluated {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        1
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        TONE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.position = (pos.as_secs_f64() * TONE_RATE as f64) as u64;
+        Ok(())
+    }
+}
+
+/// A note and its overtones: oscillators at whole multiples of a fundamental, summed at
+/// the given weights.
+///
+/// Partials at or above the Nyquist frequency are left out, since they would fold back
+/// as inharmonic tones. The weights of the rest are normalized to sum to one, so the
+/// note peaks no higher than a plain oscillator.
+struct Partials {
+    oscillators: Vec<(Box<dyn Source + Send>, f32)>,
+}
+
+impl Partials {
+    fn new(
+        waveform: Waveform,
+        fundamental: f32,
+        weights: &[f32],
+        vibrato: Option<Modulation>,
+    ) -> Partials {
+        let nyquist = TONE_RATE as f32 / 2.0;
+        let mut oscillators: Vec<(Box<dyn Source + Send>, f32)> = weights
+            .iter()
+            .enumerate()
+            .map(|(n, &weight)| (fundamental * (n + 1) as f32, weight))
+            .filter(|&(frequency, weight)| frequency < nyquist && weight > 0.0)
+            .map(|(frequency, weight)| (waveform.oscillator(frequency, vibrato), weight))
+            .collect();
+        let total: f32 = oscillators.iter().map(|(_, weight)| weight).sum();
+        if total > 0.0 {
+            for (_, weight) in &mut oscillators {
+                *weight /= total;
+            }
+        }
+        Partials { oscillators }
+    }
+}
+
+impl Iterator for Partials {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        Some(
+            self.oscillators
+                .iter_mut()
+                .map(|(oscillator, weight)| oscillator.next().unwrap_or(0.0) * *weight)
+                .sum(),
+        )
+    }
+}
+
+impl Source for Partials {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        1
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        TONE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        for (oscillator, _) in &mut self.oscillators {
+            oscillator.try_seek(pos)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads `--harmonics`: comma-separated weights in a string, or a list of numbers. The
+/// first weight is the fundamental's; at least one must be above zero.
+fn parse_harmonics(value: &Value) -> Result<Vec<f32>, LabeledError> {
+    let span = value.span();
+    let invalid = |help: &str| {
+        LabeledError::new("invalid --harmonics")
+            .with_label(help.to_string(), span)
+            .with_help("e.g. \"1.0,0.5,0.25\": the fundamental at full level, the 2nd harmonic at half, the 3rd at a quarter")
     };
+    let weights = match value {
+        Value::String { val, .. } => val
+            .split(',')
+            .map(|weight| weight.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid("expected numbers separated by commas"))?,
+        Value::List { vals, .. } => vals
+            .iter()
+            .map(Value::as_float)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid("expected a list of numbers"))?,
+        _ => return Err(invalid("expected a string or a list of numbers")),
+    };
+    if weights.len() > MAX_HARMONICS {
+        return Err(invalid(&format!("at most {MAX_HARMONICS} harmonics")));
+    }
+    if weights.iter().any(|weight| !weight.is_finite() || *weight < 0.0) {
+        return Err(invalid("weights must be zero or more"));
+    }
+    if !weights.iter().any(|weight| *weight > 0.0) {
+        return Err(invalid("at least one weight must be above zero"));
+    }
+    Ok(weights.into_iter().map(|weight| weight as f32).collect())
+}
 
-    let amplify: Value = match call.get_flag("amplify") {
-        Ok(value) => match value {
-            Some(value) => value,
-            None => Value::float(1.0, call.head),
-        },
-        Err(err) => {
-            return Err(LabeledError::new(err.to_string())
-                .with_label("Amplify value error", call.head))
+/// Endless mono two-operator FM tone at [`TONE_RATE`]. Vibrato bends both operators
+/// together, so the timbre holds as the pitch moves.
+struct Fm {
+    /// Positions within the current cycles of the carrier and the modulator, in `0..1`.
+    carrier_phase: f64,
+    modulator_phase: f64,
+    carrier_step: f64,
+    modulator_step: f64,
+    /// Peak phase swing, in cycles.
+    index: f64,
+    vibrato: Option<Vibrato>,
+}
+
+impl Fm {
+    fn new(frequency: f32, carrier: f32, modulator: f32, index: f32, vibrato: Option<Vibrato>) -> Fm {
+        let step = frequency as f64 / TONE_RATE as f64;
+        Fm {
+            carrier_phase: 0.0,
+            modulator_phase: 0.0,
+            carrier_step: step * carrier as f64,
+            modulator_step: step * modulator as f64,
+            index: index as f64 / TAU,
+            vibrato,
+        }
+    }
+}
+
+impl Iterator for Fm {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let ratio = self.vibrato.as_mut().map_or(1.0, Vibrato::ratio);
+        let sample = sin_cycles(self.carrier_phase + self.index * sin_cycles(self.modulator_phase));
+        self.carrier_phase = (self.carrier_phase + self.carrier_step * ratio) % 1.0;
+        self.modulator_phase = (self.modulator_phase + self.modulator_step * ratio) % 1.0;
+        Some(sample as f32)
+    }
+}
+
+impl Source for Fm {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        1
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        TONE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let samples = pos.as_secs_f64() * TONE_RATE as f64;
+        self.carrier_phase = (samples * self.carrier_step) % 1.0;
+        self.modulator_phase = (samples * self.modulator_step) % 1.0;
+        if let Some(vibrato) = &mut self.vibrato {
+            vibrato.lfo.seek(pos);
         }
+        Ok(())
+    }
+}
+
+/// Endless mono pulse wave at [`TONE_RATE`]: high for `duty` of each cycle, low for the
+/// rest.
+///
+/// Both edges are band-limited with PolyBLEP, like the square's; at a duty of 0.5 the
+/// two are the same wave.
+struct Pulse {
+    /// Position within the current cycle, in `0..1`.
+    phase: f64,
+    /// Phase advance per sample: frequency over sample rate.
+    step: f64,
+    duty: f64,
+    vibrato: Option<Vibrato>,
+}
+
+impl Pulse {
+    fn new(frequency: f32, duty: f32, vibrato: Option<Vibrato>) -> Pulse {
+        Pulse {
+            phase: 0.0,
+            step: frequency as f64 / TONE_RATE as f64,
+            duty: duty as f64,
+            vibrato,
+        }
+    }
+}
+
+impl Iterator for Pulse {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let step = self.step * self.vibrato.as_mut().map_or(1.0, Vibrato::ratio);
+        let (phase, duty) = (self.phase, self.duty);
+        let naive = if phase < duty { 1.0 } else { -1.0 };
+        let sample = naive + poly_blep(phase, step) - poly_blep((phase + 1.0 - duty) % 1.0, step);
+        self.phase = (phase + step) % 1.0;
+        Some(sample as f32)
+    }
+}
+
+impl Source for Pulse {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        1
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        TONE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.phase = (pos.as_secs_f64() * self.step * TONE_RATE as f64) % 1.0;
+        if let Some(vibrato) = &mut self.vibrato {
+            vibrato.lfo.seek(pos);
+        }
+        Ok(())
+    }
+}
+
+/// A periodic wobble from `--vibrato` or `--tremolo`: `rate` cycles per second, and a
+/// `depth` whose unit depends on what is modulated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Modulation {
+    pub rate: f32,
+    pub depth: f32,
+}
+
+impl Modulation {
+    /// Reads `<rate>,<depth>` or `<rate>hz:<depth>`, the depth being above zero and at
+    /// most `max_depth` `unit`.
+    fn parse(value: &Value, flag: &str, max_depth: f32, unit: &str) -> Result<Modulation, LabeledError> {
+        let span = value.span();
+        let invalid = |label: String| {
+            LabeledError::new(format!("invalid {flag}"))
+                .with_label(label, span)
+                .with_help(format!("e.g. {flag} 6hz:0.5 or {flag} 6,0.5: six cycles a second, 0.5 {unit} deep"))
+        };
+        let text = value
+            .coerce_str()
+            .map_err(|_| invalid("expected <rate>:<depth>".to_string()))?
+            .to_ascii_lowercase();
+        let (rate, depth) = text
+            .split_once([',', ':'])
+            .and_then(|(rate, depth)| {
+                let rate = rate.trim();
+                let rate = rate.strip_suffix("hz").unwrap_or(rate);
+                Some((rate.trim().parse::<f32>().ok()?, depth.trim().parse::<f32>().ok()?))
+            })
+            .ok_or_else(|| invalid("expected a rate and a depth separated by : or a comma".to_string()))?;
+        if !(rate > 0.0 && rate <= MAX_MODULATION_RATE) {
+            return Err(invalid(format!("the rate must be above 0 and at most {MAX_MODULATION_RATE} Hz")));
+        }
+        if !(depth > 0.0 && depth <= max_depth) {
+            return Err(invalid(format!("the depth must be above 0 and at most {max_depth} {unit}")));
+        }
+        Ok(Modulation { rate, depth })
+    }
+}
+
+/// Sine low-frequency oscillator at [`TONE_RATE`], driving vibrato and tremolo.
+#[derive(Clone, Copy)]
+struct Lfo {
+    /// Position within the current cycle, in `0..1`.
+    phase: f64,
+    step: f64,
+}
+
+impl Lfo {
+    fn new(rate: f32) -> Lfo {
+        Lfo {
+            phase: 0.0,
+            step: rate as f64 / TONE_RATE as f64,
+        }
+    }
+
+    /// The next value, from -1.0 to 1.0, starting at zero.
+    fn next(&mut self) -> f64 {
+        let value = sin_cycles(self.phase);
+        self.phase = (self.phase + self.step) % 1.0;
+        value
+    }
+
+    fn seek(&mut self, pos: Duration) {
+        self.phase = (pos.as_secs_f64() * self.step * TONE_RATE as f64) % 1.0;
+    }
+}
+
+/// Pitch wobble of an oscillator. Every partial of a note gets its own copy, all
+/// starting in step, so the overtones stay locked to the fundamental.
+#[derive(Clone, Copy)]
+struct Vibrato {
+    lfo: Lfo,
+    semitones: f64,
+}
+
+impl Vibrato {
+    fn new(modulation: Modulation) -> Vibrato {
+        Vibrato {
+            lfo: Lfo::new(modulation.rate),
+            semitones: modulation.depth as f64,
+        }
+    }
+
+    /// Factor for the next sample's frequency.
+    fn ratio(&mut self) -> f64 {
+        exp2(self.semitones * self.lfo.next() / 12.0)
+    }
+}
+
+/// Level wobble of a mono source: the level dips from full by up to `depth` and comes
+/// back once per LFO cycle, starting at full level. Passes the source through unchanged
+/// without a modulation.
+struct Tremolo<S> {
+    input: S,
+    lfo: Lfo,
+    depth: f64,
+}
+
+impl<S: Source> Tremolo<S> {
+    fn new(input: S, modulation: Option<Modulation>) -> Tremolo<S> {
+        let Modulation { rate, depth } = modulation.unwrap_or(Modulation { rate: 1.0, depth: 0.0 });
+        Tremolo {
+            input,
+            lfo: Lfo::new(rate),
+            depth: depth as f64,
+        }
+    }
+}
+
+impl<S: Source> Iterator for Tremolo<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        if self.depth == 0.0 {
+            return Some(sample);
+        }
+        // A cosine shifted a quarter cycle from the sine LFO, so the tone starts at full level.
+        let swing = (1.0 - cos_cycles(self.lfo.phase)) / 2.0;
+        self.lfo.next();
+        Some(sample * (1.0 - self.depth * swing) as f32)
+    }
+}
+
+impl<S: Source> Source for Tremolo<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.lfo.seek(pos);
+        Ok(())
+    }
+}
+
+/// Ring modulation: every frame of `input` multiplied by a sine, which replaces each
+/// frequency in it by its sum and difference with the sine's, for metallic and robotic
+/// tones.
+struct Ring<S> {
+    input: S,
+    lfo: Lfo,
+    /// The sine's value for the current frame.
+    value: f32,
+    /// Channel of the next sample within its frame.
+    channel: ChannelCount,
+}
+
+impl<S: Source> Ring<S> {
+    fn new(input: S, frequency: f32) -> Ring<S> {
+        Ring {
+            input,
+            lfo: Lfo::new(frequency),
+            value: 0.0,
+            channel: 0,
+        }
+    }
+}
+
+impl<S: Source> Iterator for Ring<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        if self.channel == 0 {
+            self.value = self.lfo.next() as f32;
+        }
+        self.channel = (self.channel + 1) % self.input.channels().max(1);
+        Some(sample * self.value)
+    }
+}
+
+impl<S: Source> Source for Ring<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.lfo.seek(pos);
+        self.channel = 0;
+        Ok(())
+    }
+}
+
+/// Shape of a frequency argument: one frequency, or a list of them for a chord.
+pub fn frequency_shape() -> SyntaxShape {
+    let single = SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::String]);
+    SyntaxShape::OneOf(vec![
+        SyntaxShape::Number,
+        SyntaxShape::String,
+        SyntaxShape::List(Box::new(single)),
+    ])
+}
+
+/// Reads one frequency, or a list of them as a chord, each checked to be playable at
+/// [`TONE_RATE`].
+pub fn parse_frequencies(value: &Value) -> Result<Vec<f32>, LabeledError> {
+    let single = |value: &Value| {
+        validate::frequency(parse_frequency(value)?, TONE_RATE, value.span())
+    };
+    match value {
+        Value::List { vals, .. } if vals.is_empty() => Err(LabeledError::new("empty chord")
+            .with_label("give at least one frequency", value.span())),
+        Value::List { vals, .. } => vals.iter().map(single).collect(),
+        Value::String { val, .. } => match chord_frequencies(&val.trim().to_ascii_lowercase()) {
+            Some(chord) => chord
+                .into_iter()
+                .map(|frequency| validate::frequency(frequency, TONE_RATE, value.span()))
+                .collect(),
+            None => Ok(vec![single(value)?]),
+        },
+        value => Ok(vec![single(value)?]),
+    }
+}
+
+/// Chord qualities, as semitones above the root.
+const CHORDS: [(&str, &[i32]); 22] = [
+    ("maj", &[0, 4, 7]),
+    ("m", &[0, 3, 7]),
+    ("min", &[0, 3, 7]),
+    ("dim", &[0, 3, 6]),
+    ("aug", &[0, 4, 8]),
+    ("+", &[0, 4, 8]),
+    ("sus2", &[0, 2, 7]),
+    ("sus4", &[0, 5, 7]),
+    ("sus", &[0, 5, 7]),
+    ("5", &[0, 7]),
+    ("6", &[0, 4, 7, 9]),
+    ("m6", &[0, 3, 7, 9]),
+    ("7", &[0, 4, 7, 10]),
+    ("maj7", &[0, 4, 7, 11]),
+    ("m7", &[0, 3, 7, 10]),
+    ("min7", &[0, 3, 7, 10]),
+    ("dim7", &[0, 3, 6, 9]),
+    ("m7b5", &[0, 3, 6, 10]),
+    ("add9", &[0, 4, 7, 14]),
+    ("9", &[0, 4, 7, 10, 14]),
+    ("maj9", &[0, 4, 7, 11, 14]),
+    ("m9", &[0, 3, 7, 10, 14]),
+];
+
+/// Frequencies of a chord such as `c4maj`, `a3m7` or `f#sus4`: a root note, with the
+/// octave optional (default 4), then a quality from [`CHORDS`].
+fn chord_frequencies(chord: &str) -> Option<Vec<f64>> {
+    let mut chars = chord.char_indices();
+    chars.next().filter(|(_, c)| ('a'..='g').contains(c))?;
+    let mut end = chars.next().map_or(chord.len(), |(i, _)| i);
+    if let Some(accidental) = chord[end..].chars().next().filter(|c| "#♯b♭".contains(*c)) {
+        end += accidental.len_utf8();
+    }
+    let (root, quality) = match chord[end..].chars().next() {
+        Some(octave) if octave.is_ascii_digit() => (chord[..end + 1].to_string(), &chord[end + 1..]),
+        _ => (format!("{}4", &chord[..end]), &chord[end..]),
     };
-    let amplify_value: f32 = amplify.as_float().map_err(|err| {
-        LabeledError::new(err.to_string())
-            .with_label("Amplify value must be of type Float (f32)", amplify.span())
-    })? as f32;
-    Ok((frequency_value, duration_value, amplify_value))
+    let (_, intervals) = CHORDS.iter().find(|(name, _)| *name == quality)?;
+    let root = note_frequency(&root)?;
+    Some(
+        intervals
+            .iter()
+            .map(|&semitones| root * exp2(semitones as f64 / 12.0))
+            .collect(),
+    )
+}
+
+/// Reads a frequency given as an int, a float, a string with a `hz`/`khz` unit, or a
+/// note name in scientific pitch notation (`A4` = 440 Hz, equal temperament).
+pub fn parse_frequency(value: &Value) -> Result<f64, LabeledError> {
+    let span = value.span();
+    let invalid = || {
+        LabeledError::new("invalid frequency")
+            .with_label("expected Hz or a note name", span)
+            .with_help("e.g. 440, 440.0, \"440hz\", \"1.2khz\", \"A4\" or \"C#5\"")
+    };
+    let text = match value {
+        Value::Int { val, .. } => return Ok(*val as f64),
+        Value::Float { val, .. } => return Ok(*val),
+        Value::String { val, .. } => val.trim().to_ascii_lowercase(),
+        _ => return Err(invalid()),
+    };
+    if let Some(khz) = text.strip_suffix("khz") {
+        return khz.trim().parse::<f64>().map(|k| k * 1000.0).map_err(|_| invalid());
+    }
+    let hz = text.strip_suffix("hz").unwrap_or(&text).trim();
+    if let Ok(hz) = hz.parse::<f64>() {
+        return Ok(hz);
+    }
+    note_frequency(&text).ok_or_else(invalid)
+}
+
+/// Frequency of a note such as `a4`, `c#5` or `bb2`.
+pub fn note_frequency(note: &str) -> Option<f64> {
+    let mut chars = note.chars();
+    let semitone = match chars.next()? {
+        'c' => -9,
+        'd' => -7,
+        'e' => -5,
+        'f' => -4,
+        'g' => -2,
+        'a' => 0,
+        'b' => 2,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (accidental, octave) = match rest.chars().next()? {
+        '#' | '♯' => (1, &rest[rest.chars().next()?.len_utf8()..]),
+        'b' | '♭' => (-1, &rest[rest.chars().next()?.len_utf8()..]),
+        _ => (0, rest),
+    };
+    let octave: i32 = octave.parse().ok()?;
+    let semitones = semitone + accidental + 12 * (octave - 4);
+    Some(440.0 * exp2(semitones as f64 / 12.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::example_results;
+
+    #[test]
+    fn example_results_match_rendered_wavs() {
+        let mono = Tone::sine(1000.0, Duration::from_millis(10));
+        let stereo = Tone {
+            right_frequencies: Some(vec![1010.0]),
+            ..mono.clone()
+        };
+        let mono = WavOptions::default().encode(mono.source()).unwrap();
+        let stereo = WavOptions::default().encode(stereo.source()).unwrap();
+        assert_eq!(
+            example_results(&SoundMakeCmd),
+            [
+                Value::test_int(mono.len() as i64),
+                Value::test_int(stereo.len() as i64),
+                Value::test_binary(mono[..4].to_vec()),
+            ]
+        );
+    }
 }
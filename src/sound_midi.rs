@@ -0,0 +1,255 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, SyntaxShape,
+    Type, Value,
+};
+use rodio::{ChannelCount, SampleRate, Source};
+use std::{path::Path, sync::Arc, time::Duration};
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats},
+    ear_guard::{ear_guard_flags, EarGuard},
+    midi::{self, Event, Message},
+    no_audio::{no_audio_flags, NoAudio},
+    sound_make::{play_source, TONE_RATE},
+    soundfont::{SoundFont, Synth, BLOCK},
+    stream_identity::StreamRole,
+    utils::{load_file_path, path_value, resolve_filepath},
+    validate,
+    wav::{wav_flags, WavOptions},
+    Sound,
+};
+
+/// Longest song without `--force`.
+const MAX_LENGTH: Duration = Duration::from_secs(60 * 60);
+
+/// How long notes may ring out after the last event before the render is cut.
+const TAIL: Duration = Duration::from_secs(3);
+
+/// Nushell command `sound midi play` — plays a Standard MIDI File through a SoundFont.
+///
+/// Channel 10 plays the drum kit of bank 128; a bank or program the font lacks falls
+/// back to the General MIDI one.
+pub struct SoundMidiPlayCmd;
+
+impl SimplePluginCommand for SoundMidiPlayCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound midi play"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound midi play")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required(
+                "file",
+                SyntaxShape::Filepath,
+                "MIDI file (.mid, .rmi) to play",
+            )
+            .required_named(
+                "soundfont",
+                SyntaxShape::Filepath,
+                "SoundFont 2 file (.sf2) with the instruments",
+                Some('s'),
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the song by given value (e.g. 0.5 for half volume)",
+                Some('a'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow songs longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(signature)))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "play a song with a General MIDI SoundFont",
+                example: "sound midi play song.mid --soundfont gm.sf2",
+                result: None,
+            },
+            Example {
+                description: "render it to a WAV file instead",
+                example: "sound midi play song.mid --soundfont gm.sf2 --data | save --raw song.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a MIDI file through a SoundFont, or output it as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let (span, path) = load_file_path(engine, call)?;
+        let events = midi::parse(&read(&path, span)?, span)?;
+        if !events
+            .iter()
+            .any(|e| matches!(e.message, Message::NoteOn { .. }))
+        {
+            return Err(
+                LabeledError::new("nothing to play").with_label("the file has no notes", span)
+            );
+        }
+        let font_value = call.get_flag_value("soundfont").ok_or_else(|| {
+            LabeledError::new("missing --soundfont").with_label("needs a .sf2 file", call.head)
+        })?;
+        let font_span = font_value.span();
+        let font_path = resolve_filepath(engine, font_span, path_value(font_value)?)?;
+        let font = SoundFont::read(&read(&font_path, font_span)?, font_span)?;
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+
+        let source = MidiSource::new(Arc::new(font), events, amplify);
+        let length = source.length();
+        validate::length(length, MAX_LENGTH, call.has_flag("force")?, call.head)?;
+        if call.has_flag("data")? {
+            let wav = WavOptions::from_call(call)?;
+            let max_length = wav.max_length(TONE_RATE, 2);
+            if length > max_length {
+                return Err(
+                    LabeledError::new("song too long for a WAV file").with_label(
+                        format!("WAV data is limited to {}s", max_length.as_secs()),
+                        call.head,
+                    ),
+                );
+            }
+            return Ok(Value::binary(wav.encode(source)?, call.head));
+        }
+        if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        play_source(source, guard, StreamRole::Media)?;
+        Ok(Value::nothing(call.head))
+    }
+}
+
+fn read(path: &Path, span: nu_protocol::Span) -> Result<Vec<u8>, LabeledError> {
+    std::fs::read(path).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("error trying to read the file", span)
+    })
+}
+
+/// Interleaved stereo of a MIDI song, rendered a block at a time as it is pulled.
+struct MidiSource {
+    synth: Synth,
+    events: Vec<Event>,
+    next_event: usize,
+    /// Frames rendered so far.
+    frame: u64,
+    /// Frame of the last event, after which the song ends once every voice has.
+    last_frame: u64,
+    buffer: [f32; BLOCK * 2],
+    at: usize,
+    len: usize,
+    amplify: f32,
+}
+
+impl MidiSource {
+    fn new(font: Arc<SoundFont>, events: Vec<Event>, amplify: f32) -> MidiSource {
+        let last_frame = events.last().map_or(0, |e| frame_at(e.time));
+        MidiSource {
+            synth: Synth::new(font, TONE_RATE),
+            events,
+            next_event: 0,
+            frame: 0,
+            last_frame,
+            buffer: [0.0; BLOCK * 2],
+            at: 0,
+            len: 0,
+            amplify,
+        }
+    }
+
+    /// The longest the song can take, ringing out included.
+    fn length(&self) -> Duration {
+        Duration::from_secs_f64(self.last_frame as f64 / TONE_RATE as f64) + TAIL
+    }
+}
+
+fn frame_at(time: f64) -> u64 {
+    (time * TONE_RATE as f64).round() as u64
+}
+
+impl Iterator for MidiSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.at == self.len {
+            let end = self.last_frame + frame_at(TAIL.as_secs_f64());
+            if self.frame >= end || (self.frame > self.last_frame && self.synth.is_silent()) {
+                return None;
+            }
+            // Events land on the start of the block they fall in.
+            let block_end = self.frame + BLOCK as u64;
+            while self.next_event < self.events.len()
+                && frame_at(self.events[self.next_event].time) < block_end
+            {
+                self.synth.handle(self.events[self.next_event].message);
+                self.next_event += 1;
+            }
+            let frames = (end - self.frame).min(BLOCK as u64) as usize;
+            self.synth.render(&mut self.buffer[..frames * 2]);
+            self.frame += frames as u64;
+            self.at = 0;
+            self.len = frames * 2;
+        }
+        let sample = self.buffer[self.at] * self.amplify;
+        self.at += 1;
+        Some(sample)
+    }
+}
+
+impl Source for MidiSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        2
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        TONE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,279 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+use std::{iter::Peekable, str::CharIndices, time::Duration};
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats, waveforms},
+    ear_guard::ear_guard_flags,
+    no_audio::no_audio_flags,
+    sound_make::{note_frequency, tone_flags, Envelope, Tone, Waveform, TONE_RATE},
+    sound_sequence::{note_envelope, output_steps, render, Step},
+    validate,
+    wav::{encode_wav, wav_flags},
+    Sound,
+};
+
+const OCTAVES: std::ops::RangeInclusive<i32> = 0..=8;
+
+const LENGTHS: std::ops::RangeInclusive<u32> = 1..=64;
+
+const TEMPOS: std::ops::RangeInclusive<u32> = 32..=255;
+
+const VOLUMES: std::ops::RangeInclusive<u32> = 0..=15;
+
+/// Nushell command `sound play-notes` — plays a melody written in Music Macro Language.
+///
+/// The dialect is the one of BASIC's `PLAY`: notes `c` to `b` with `#`/`+` or `-` and
+/// an optional length and dots, `r` or `p` for a rest, `o` to set the octave (`o4`
+/// holds A4 = 440 Hz) and `>`/`<` to step it, `l` for the default length, `t` for the
+/// tempo in quarter notes per minute, `v` for the volume from 0 to 15, and `mn`, `ml`
+/// and `ms` for normal, legato and staccato notes.
+pub struct SoundPlayNotesCmd;
+
+impl SimplePluginCommand for SoundPlayNotesCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound play-notes"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound play-notes")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required(
+                "notes",
+                SyntaxShape::String,
+                "melody in Music Macro Language, e.g. \"t140 l8 cdefgab>c\"",
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the tone by given value (e.g. 0.5 for half volume)",
+                Some('a'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow melodies longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(tone_flags(signature))))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "play a C major scale, C4 to C5",
+                example: "sound play-notes \"cdefgab>c\"",
+                result: None,
+            },
+            Example {
+                description: "eighth notes at 160 bpm, a dotted quarter, a rest and a staccato run",
+                example: "sound play-notes \"t160 l8 o5 e e r e r c e4. ms g4 r4 <g4\" --waveform square --amplify 0.3",
+                result: None,
+            },
+            Example {
+                description: "a quarter note at the default 120 bpm lasts 500ms: a 44-byte WAV header and 24000 16-bit samples",
+                example: "sound play-notes a --data | bytes length",
+                result: mml_steps(
+                    "a",
+                    Waveform::Sine,
+                    Envelope::default(),
+                    1.0,
+                    Span::test_data(),
+                )
+                .ok()
+                .and_then(|steps| encode_wav(render(&steps)).ok())
+                .map(|wav| Value::test_int(wav.len() as i64)),
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a melody written in Music Macro Language, or output it as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "waveform" => Some(waveforms()),
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let notes: Spanned<String> = call.req(0)?;
+        let waveform = Waveform::from_call(call)?;
+        let envelope = note_envelope(call)?;
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+        let steps = mml_steps(&notes.item, waveform, envelope, amplify, notes.span)?;
+        output_steps(engine, call, &steps)
+    }
+}
+
+/// Reads the numbers and dots that follow the commands of an MML string.
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    /// The number that follows, if any.
+    fn number(&mut self) -> Option<u32> {
+        let mut number = None::<u32>;
+        while let Some((_, digit)) = self.chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            let digit = digit.to_digit(10).expect("checked to be a digit");
+            number = Some(number.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+        }
+        number
+    }
+
+    /// How many dots follow.
+    fn dots(&mut self) -> u32 {
+        let mut dots = 0;
+        while self.chars.next_if(|(_, c)| *c == '.').is_some() {
+            dots += 1;
+        }
+        dots
+    }
+}
+
+/// An error about the command at byte `at` of `text`, counted in characters for the
+/// user.
+fn syntax_error(text: &str, at: usize, message: String, span: Span) -> LabeledError {
+    let position = text[..at].chars().count() + 1;
+    LabeledError::new(message)
+        .with_label(format!("at character {position}"), span)
+        .with_help("notes are c to b with # or -, r is a rest; o, <, >, l, t, v and mn/ml/ms set octave, length, tempo, volume and articulation")
+}
+
+/// Steps playing the MML string `text`.
+fn mml_steps(
+    text: &str,
+    waveform: Waveform,
+    envelope: Envelope,
+    amplify: f32,
+    span: Span,
+) -> Result<Vec<Step>, LabeledError> {
+    let lower = text.to_ascii_lowercase();
+    let mut parser = Parser {
+        chars: lower.char_indices().peekable(),
+    };
+    let (mut octave, mut length, mut tempo, mut volume) = (4, 4, 120, 15);
+    // Fraction of each note that sounds; the rest of it is silence.
+    let mut articulation = 7.0 / 8.0;
+
+    let mut steps = Vec::new();
+    while let Some((at, c)) = parser.chars.next() {
+        let error = |message: String| syntax_error(&lower, at, message, span);
+        let out_of_range =
+            |what: &str, range: &str| error(format!("{what} out of range, must be {range}"));
+        match c {
+            'c' | 'd' | 'e' | 'f' | 'g' | 'a' | 'b' | 'r' | 'p' => {
+                let accidental = match parser.chars.next_if(|(_, c)| "#+-".contains(*c)) {
+                    Some((_, '-')) => "b",
+                    Some(_) => "#",
+                    None => "",
+                };
+                let note_length = match parser.number() {
+                    Some(note_length) if !LENGTHS.contains(&note_length) => {
+                        return Err(out_of_range("note length", "1 to 64"))
+                    }
+                    note_length => note_length.unwrap_or(length),
+                };
+                let dots = parser.dots();
+                let whole = 240.0 / tempo as f64;
+                let duration = whole / note_length as f64 * (2.0 - 0.5f64.powi(dots as i32));
+                let duration = Duration::from_secs_f64(duration);
+                if c == 'r' || c == 'p' {
+                    if !accidental.is_empty() {
+                        return Err(error("a rest cannot be sharp or flat".into()));
+                    }
+                    steps.push(Step::Rest(duration));
+                    continue;
+                }
+                let frequency = note_frequency(&format!("{c}{accidental}{octave}"))
+                    .expect("letters and octaves are checked");
+                let sounding = duration.mul_f64(articulation);
+                steps.push(Step::Tone(Tone {
+                    waveform,
+                    frequencies: vec![validate::frequency(frequency, TONE_RATE, span)?],
+                    right_frequencies: None,
+                    pan: None,
+                    harmonics: None,
+                    vibrato: None,
+                    tremolo: None,
+                    expression: None,
+                    instrument: None,
+                    duration: sounding,
+                    amplify: amplify * volume as f32 / *VOLUMES.end() as f32,
+                    envelope,
+                }));
+                if sounding < duration {
+                    steps.push(Step::Rest(duration - sounding));
+                }
+            }
+            'o' => match parser.number().map(|o| o as i32) {
+                Some(o) if OCTAVES.contains(&o) => octave = o,
+                Some(_) => return Err(out_of_range("octave", "0 to 8")),
+                None => return Err(error("o needs an octave".into())),
+            },
+            '>' if octave < *OCTAVES.end() => octave += 1,
+            '<' if octave > *OCTAVES.start() => octave -= 1,
+            '>' | '<' => return Err(out_of_range("octave", "0 to 8")),
+            'l' => match parser.number() {
+                Some(l) if LENGTHS.contains(&l) => length = l,
+                Some(_) => return Err(out_of_range("length", "1 to 64")),
+                None => return Err(error("l needs a length".into())),
+            },
+            't' => match parser.number() {
+                Some(t) if TEMPOS.contains(&t) => tempo = t,
+                Some(_) => return Err(out_of_range("tempo", "32 to 255")),
+                None => return Err(error("t needs a tempo".into())),
+            },
+            'v' => match parser.number() {
+                Some(v) if VOLUMES.contains(&v) => volume = v,
+                Some(_) => return Err(out_of_range("volume", "0 to 15")),
+                None => return Err(error("v needs a volume".into())),
+            },
+            'm' => match parser.chars.next() {
+                Some((_, 'n')) => articulation = 7.0 / 8.0,
+                Some((_, 'l')) => articulation = 1.0,
+                Some((_, 's')) => articulation = 3.0 / 4.0,
+                _ => return Err(error("m must be followed by n, l or s".into())),
+            },
+            c if c.is_whitespace() => {}
+            c => return Err(error(format!("unexpected '{c}'"))),
+        }
+    }
+    if steps.is_empty() {
+        return Err(
+            LabeledError::new("nothing to play").with_label("the melody has no notes", span)
+        );
+    }
+    Ok(steps)
+}
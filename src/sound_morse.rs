@@ -0,0 +1,302 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+use std::time::Duration;
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats},
+    ear_guard::ear_guard_flags,
+    no_audio::no_audio_flags,
+    sound_make::{parse_frequency, Envelope, Tone, Waveform, TONE_RATE},
+    sound_sequence::{output_steps, Step},
+    validate,
+    wav::wav_flags,
+    Sound,
+};
+
+/// Speed when `--wpm` is not given.
+const DEFAULT_WPM: f64 = 20.0;
+
+/// Tone pitch when `--frequency` is not given; a common CW sidetone.
+const DEFAULT_FREQUENCY: f32 = 600.0;
+
+/// Longest rise and fall of each element. Keying without it clicks; operators call
+/// that "key clicks".
+const MAX_EDGE: Duration = Duration::from_millis(5);
+
+/// International Morse code: `.` is a dit, `-` a dah.
+const CODE: [(char, &str); 54] = [
+    ('A', ".-"),
+    ('B', "-..."),
+    ('C', "-.-."),
+    ('D', "-.."),
+    ('E', "."),
+    ('F', "..-."),
+    ('G', "--."),
+    ('H', "...."),
+    ('I', ".."),
+    ('J', ".---"),
+    ('K', "-.-"),
+    ('L', ".-.."),
+    ('M', "--"),
+    ('N', "-."),
+    ('O', "---"),
+    ('P', ".--."),
+    ('Q', "--.-"),
+    ('R', ".-."),
+    ('S', "..."),
+    ('T', "-"),
+    ('U', "..-"),
+    ('V', "...-"),
+    ('W', ".--"),
+    ('X', "-..-"),
+    ('Y', "-.--"),
+    ('Z', "--.."),
+    ('0', "-----"),
+    ('1', ".----"),
+    ('2', "..---"),
+    ('3', "...--"),
+    ('4', "....-"),
+    ('5', "....."),
+    ('6', "-...."),
+    ('7', "--..."),
+    ('8', "---.."),
+    ('9', "----."),
+    ('.', ".-.-.-"),
+    (',', "--..--"),
+    ('?', "..--.."),
+    ('\'', ".----."),
+    ('!', "-.-.--"),
+    ('/', "-..-."),
+    ('(', "-.--."),
+    (')', "-.--.-"),
+    ('&', ".-..."),
+    (':', "---..."),
+    (';', "-.-.-."),
+    ('=', "-...-"),
+    ('+', ".-.-."),
+    ('-', "-....-"),
+    ('_', "..--.-"),
+    ('"', ".-..-."),
+    ('$', "...-..-"),
+    ('@', ".--.-."),
+];
+
+/// Nushell command `sound morse` — keys text as Morse code.
+///
+/// Timing follows the PARIS standard: a dit lasts 1200 / `--wpm` milliseconds, a dah
+/// three dits, with one dit between elements, three between letters and seven between
+/// words.
+pub struct SoundMorseCmd;
+
+impl SimplePluginCommand for SoundMorseCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound morse"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound morse")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required(
+                "Text",
+                SyntaxShape::String,
+                "text to key: letters, digits and common punctuation",
+            )
+            .named(
+                "wpm",
+                SyntaxShape::Number,
+                "speed in words per minute (default 20)",
+                Some('s'),
+            )
+            .named(
+                "frequency",
+                SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::String]),
+                "tone frequency in Hz or as a note (default 600)",
+                Some('F'),
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the tone by given value (e.g. 0.5 for half volume)",
+                Some('a'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow messages longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(signature)))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "send a distress call",
+                example: "sound morse \"SOS help\"",
+                result: None,
+            },
+            Example {
+                description: "slow practice at 12 words per minute on a lower tone",
+                example: "sound morse \"paris paris\" --wpm 12 --frequency 500",
+                result: None,
+            },
+            Example {
+                description: "save an alert to a file",
+                example: "sound morse \"build ok\" --data | save --raw alert.wav",
+                result: None,
+            },
+            Example {
+                description:
+                    "E is one dit, 60ms at 20 wpm: a 44-byte WAV header and 2880 16-bit samples",
+                example: "sound morse E --data | bytes length",
+                result: Some(Value::test_int(44 + 2880 * 2)),
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play text as Morse code, or output it as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let text: Spanned<String> = call.req(0)?;
+        let wpm = match call.get_flag_value("wpm") {
+            Some(value) => {
+                let wpm = value.as_float().map_err(|e| {
+                    LabeledError::new(e.to_string()).with_label("expected a number", value.span())
+                })?;
+                if !(1.0..=100.0).contains(&wpm) {
+                    return Err(LabeledError::new("--wpm out of range")
+                        .with_label("must be between 1 and 100 words per minute", value.span()));
+                }
+                wpm
+            }
+            None => DEFAULT_WPM,
+        };
+        let frequency = match call.get_flag_value("frequency") {
+            Some(value) => validate::frequency(parse_frequency(&value)?, TONE_RATE, value.span())?,
+            None => DEFAULT_FREQUENCY,
+        };
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+        let steps = morse_steps(&text.item, wpm, frequency, amplify, text.span)?;
+        output_steps(engine, call, &steps)
+    }
+}
+
+/// Dits and dahs of a character; letters are accepted in either case.
+fn morse_code(c: char) -> Option<&'static str> {
+    let c = c.to_ascii_uppercase();
+    CODE.iter()
+        .find(|(key, _)| *key == c)
+        .map(|(_, code)| *code)
+}
+
+/// Steps keying `text` at `wpm`. Runs of whitespace are one word gap; leading and
+/// trailing whitespace is ignored.
+fn morse_steps(
+    text: &str,
+    wpm: f64,
+    frequency: f32,
+    amplify: f32,
+    span: Span,
+) -> Result<Vec<Step>, LabeledError> {
+    let dit = Duration::from_secs_f64(1.2 / wpm);
+    let edge = MAX_EDGE.min(dit / 4);
+    let element = |dits: u32| {
+        Step::Tone(Tone {
+            waveform: Waveform::Sine,
+            frequencies: vec![frequency],
+            right_frequencies: None,
+            pan: None,
+            harmonics: None,
+            vibrato: None,
+            tremolo: None,
+            expression: None,
+            instrument: None,
+            duration: dit * dits,
+            amplify,
+            envelope: Envelope {
+                attack: edge,
+                release: edge,
+                ..Envelope::default()
+            },
+        })
+    };
+
+    let mut steps = Vec::new();
+    for (w, word) in text.split_whitespace().enumerate() {
+        if w > 0 {
+            steps.push(Step::Rest(dit * 7));
+        }
+        for (l, letter) in word.chars().enumerate() {
+            let code = morse_code(letter).ok_or_else(|| {
+                LabeledError::new(format!("'{letter}' has no Morse code"))
+                    .with_label("use letters, digits and common punctuation", span)
+            })?;
+            if l > 0 {
+                steps.push(Step::Rest(dit * 3));
+            }
+            for (e, mark) in code.chars().enumerate() {
+                if e > 0 {
+                    steps.push(Step::Rest(dit));
+                }
+                steps.push(element(if mark == '-' { 3 } else { 1 }));
+            }
+        }
+    }
+    if steps.is_empty() {
+        return Err(LabeledError::new("nothing to send").with_label("the text is empty", span));
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sound_sequence::render, utils::example_results, wav::WavOptions};
+
+    #[test]
+    fn example_result_matches_rendered_wav() {
+        let steps =
+            morse_steps("E", DEFAULT_WPM, DEFAULT_FREQUENCY, 1.0, Span::test_data()).unwrap();
+        let wav = WavOptions::default().encode(render(&steps)).unwrap();
+        assert_eq!(
+            example_results(&SoundMorseCmd),
+            [Value::test_int(wav.len() as i64)]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, SyntaxShape,
+    Value,
+};
+use rodio::buffer::SamplesBuffer;
+use std::time::Duration;
+
+use crate::{
+    auto_gain::clip_gain,
+    completions::{no_audio_policies, stream_roles},
+    config::plugin_config_value,
+    cooldown::cooldown_flags,
+    ear_guard::{ear_guard_flags, EarGuard},
+    no_audio::{no_audio_flags, NoAudio},
+    pcm::Pcm,
+    sound_make::play_source,
+    stream_identity::{role_flags, StreamRole},
+    utils::load_file_path,
+    validate, Sound,
+};
+
+/// Longest notification sound; longer audio is for `sound play`.
+const MAX_LENGTH: Duration = Duration::from_secs(60);
+
+/// Nushell command `sound notify` — plays a short sound file as a notification.
+///
+/// The whole file is decoded up front so `--normalize` can measure it, which keeps
+/// alerts collected from different places at one loudness.
+pub struct SoundNotifyCmd;
+
+impl SimplePluginCommand for SoundNotifyCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound notify"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound notify")
+            .required("file", SyntaxShape::Filepath, "short audio file to play")
+            .switch(
+                "normalize",
+                "level the sound to a common loudness first (default: notify_normalize in the plugin config)",
+                Some('N'),
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the sound by given value, after --normalize",
+                Some('a'),
+            )
+            .category(Category::Experimental);
+        role_flags(cooldown_flags(no_audio_flags(ear_guard_flags(signature))))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "play a notification sound",
+                example: "sound notify ding.ogg",
+                result: None,
+            },
+            Example {
+                description: "alerts from different sources, at the same loudness",
+                example: "if $failed { sound notify --normalize alarm.wav } else { sound notify --normalize chime.mp3 }",
+                result: None,
+            },
+            Example {
+                description: "a failing health check alerts at most once a minute",
+                example: "if not $healthy { sound notify alarm.wav --cooldown 1min --key health }",
+                result: None,
+            },
+            Example {
+                description: "level-matched, but a little quieter than the rest",
+                example: "sound notify -N pop.wav --amplify 0.5",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a short audio file as a notification, optionally level-matched"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "role" => Some(stream_roles()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let (_, path) = load_file_path(engine, call)?;
+        let sound = format!("notify:{}", path.display());
+        if !plugin.cooldowns.ready(engine, call, &sound)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let (span, _, pcm) = Pcm::load(engine, call)?;
+        if pcm.duration() > MAX_LENGTH {
+            return Err(LabeledError::new("notification sound too long")
+                .with_label(
+                    format!(
+                        "the file lasts {:.0}s, notifications up to {}s",
+                        pcm.duration().as_secs_f64(),
+                        MAX_LENGTH.as_secs()
+                    ),
+                    span,
+                )
+                .with_help("use sound play for longer audio"));
+        }
+        let configured = matches!(
+            plugin_config_value(engine, "notify_normalize", call.head)?,
+            Some(Value::Bool { val: true, .. })
+        );
+        // Silence has no loudness to match and plays as it is.
+        let gain = if call.has_flag("normalize")? || configured {
+            clip_gain(&pcm.samples, pcm.channels, pcm.sample_rate).unwrap_or(1.0)
+        } else {
+            1.0
+        };
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+        let samples: Vec<f32> = pcm.samples.iter().map(|s| s * gain * amplify).collect();
+
+        if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        let role = StreamRole::from_call(call, StreamRole::Notification)?;
+        play_source(
+            SamplesBuffer::new(pcm.channels, pcm.sample_rate, samples),
+            guard,
+            role,
+        )?;
+        Ok(Value::nothing(call.head))
+    }
+}
@@ -0,0 +1,278 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+use std::time::Duration;
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats, waveforms},
+    ear_guard::ear_guard_flags,
+    no_audio::no_audio_flags,
+    sound_make::{note_frequency, tone_flags, Envelope, Tone, Waveform, TONE_RATE},
+    sound_sequence::{note_envelope, output_steps, Step},
+    validate,
+    wav::wav_flags,
+    Sound,
+};
+
+/// Note lengths RTTTL allows, as fractions of a whole note.
+const DURATIONS: [u32; 6] = [1, 2, 4, 8, 16, 32];
+
+const OCTAVES: std::ops::RangeInclusive<u32> = 1..=8;
+
+const TEMPOS: std::ops::RangeInclusive<u32> = 1..=900;
+
+/// Nushell command `sound rtttl` — plays a ringtone in the Nokia RTTTL format.
+///
+/// A ringtone is `name:defaults:notes`. The defaults set the note length `d`, the
+/// octave `o` and the tempo `b` in quarter notes per minute, and fall back to
+/// `d=4,o=6,b=63`. Each note is `[length]letter[#][.][octave][.]`, where `p` is a
+/// pause and a dot makes the note half as long again.
+pub struct SoundRtttlCmd;
+
+impl SimplePluginCommand for SoundRtttlCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound rtttl"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound rtttl")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required(
+                "ringtone",
+                SyntaxShape::String,
+                "ringtone in RTTTL, e.g. \"beep:d=8,o=5,b=120:c,e,g\"",
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the tone by given value (e.g. 0.5 for half volume)",
+                Some('a'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow ringtones longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(tone_flags(signature))))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "play the opening of a classic ringtone",
+                example: "sound rtttl \"Mission:d=16,o=6,b=95:32d,32d#,32d,32d#,32d,32d#,32d,32d#,32d,32d,32d#,32e,32f,32f#,32g,g,8p,g,8p,a#,p,c7,p,g,8p,g,8p,f,p,f#,p\"",
+                result: None,
+            },
+            Example {
+                description: "on a square wave, like the phones that played them",
+                example: "sound rtttl \"Scale:d=8,o=5,b=160:c,d,e,f,g,a,b,4c6\" --waveform square --amplify 0.3",
+                result: None,
+            },
+            Example {
+                description: "a quarter note at 120 bpm lasts 500ms: a 44-byte WAV header and 24000 16-bit samples",
+                example: "sound rtttl \"One:d=4,o=5,b=120:a\" --data | bytes length",
+                result: Some(Value::test_int(44 + 24000 * 2)),
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a Nokia RTTTL ringtone, or output it as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "waveform" => Some(waveforms()),
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let ringtone: Spanned<String> = call.req(0)?;
+        let waveform = Waveform::from_call(call)?;
+        let envelope = note_envelope(call)?;
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+        let steps = rtttl_steps(&ringtone.item, waveform, envelope, amplify, ringtone.span)?;
+        output_steps(engine, call, &steps)
+    }
+}
+
+/// Steps playing `ringtone`.
+fn rtttl_steps(
+    ringtone: &str,
+    waveform: Waveform,
+    envelope: Envelope,
+    amplify: f32,
+    span: Span,
+) -> Result<Vec<Step>, LabeledError> {
+    let sections: Vec<&str> = ringtone.splitn(3, ':').collect();
+    let [_name, defaults, notes] = sections[..] else {
+        return Err(LabeledError::new("not an RTTTL ringtone")
+            .with_label("expected name:defaults:notes", span)
+            .with_help("e.g. \"beep:d=8,o=5,b=120:c,e,g\""));
+    };
+
+    let (mut length, mut octave, mut tempo) = (4, 6, 63);
+    for setting in defaults.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || {
+            LabeledError::new(format!("invalid default '{setting}'"))
+                .with_label("expected d=<length>, o=<octave> or b=<tempo>", span)
+        };
+        let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+        let value: u32 = value.trim().parse().map_err(|_| invalid())?;
+        match key.trim().to_ascii_lowercase().as_str() {
+            "d" if DURATIONS.contains(&value) => length = value,
+            "o" if OCTAVES.contains(&value) => octave = value,
+            "b" if TEMPOS.contains(&value) => tempo = value,
+            "d" | "o" | "b" => {
+                return Err(
+                    LabeledError::new(format!("default '{setting}' out of range")).with_label(
+                        "lengths are 1, 2, 4, 8, 16 or 32, octaves 1 to 8 and tempos 1 to 900",
+                        span,
+                    ),
+                )
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    let whole = Duration::from_secs(240) / tempo;
+    let mut steps = Vec::new();
+    for note in notes.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let Note {
+            length: note_length,
+            pitch,
+            dotted,
+        } = parse_note(note, octave).ok_or_else(|| {
+            LabeledError::new(format!("invalid note '{note}'"))
+                .with_label("expected [length]letter[#][.][octave][.], e.g. 8c#6", span)
+                .with_help("letters are c, d, e, f, g, a, b (or h) and p for a pause")
+        })?;
+        let mut duration = whole / note_length.unwrap_or(length);
+        if dotted {
+            duration += duration / 2;
+        }
+        let Some((letter, sharp, octave)) = pitch else {
+            steps.push(Step::Rest(duration));
+            continue;
+        };
+        let name = format!("{letter}{}{octave}", if sharp { "#" } else { "" });
+        let frequency = note_frequency(&name).expect("letters and octaves are checked");
+        steps.push(Step::Tone(Tone {
+            waveform,
+            frequencies: vec![validate::frequency(frequency, TONE_RATE, span)?],
+            right_frequencies: None,
+            pan: None,
+            harmonics: None,
+            vibrato: None,
+            tremolo: None,
+            expression: None,
+            instrument: None,
+            duration,
+            amplify,
+            envelope,
+        }));
+    }
+    if steps.is_empty() {
+        return Err(
+            LabeledError::new("nothing to play").with_label("the ringtone has no notes", span)
+        );
+    }
+    Ok(steps)
+}
+
+/// One parsed note of a ringtone.
+struct Note {
+    /// Fraction of a whole note, when not the default.
+    length: Option<u32>,
+    /// Letter, sharp and octave; `None` for a pause.
+    pitch: Option<(char, bool, u32)>,
+    dotted: bool,
+}
+
+/// Parses a note, with `octave` as the default. The dot is accepted on either side of
+/// the octave, since ringtones in the wild use both.
+fn parse_note(note: &str, octave: u32) -> Option<Note> {
+    let note = note.to_ascii_lowercase();
+    let digits = note.find(|c: char| !c.is_ascii_digit())?;
+    let length = match &note[..digits] {
+        "" => None,
+        length => Some(length.parse().ok().filter(|l| DURATIONS.contains(l))?),
+    };
+    let mut rest = note[digits..].chars().peekable();
+    let letter = match rest.next()? {
+        'h' => 'b',
+        letter @ ('c' | 'd' | 'e' | 'f' | 'g' | 'a' | 'b' | 'p') => letter,
+        _ => return None,
+    };
+    let sharp = rest.next_if_eq(&'#').is_some();
+    let mut dotted = rest.next_if_eq(&'.').is_some();
+    let scale: String = std::iter::from_fn(|| rest.next_if(char::is_ascii_digit)).collect();
+    if !dotted {
+        dotted = rest.next_if_eq(&'.').is_some();
+    }
+    if rest.next().is_some() {
+        return None;
+    }
+    let octave = match scale.as_str() {
+        "" => octave,
+        scale => scale.parse().ok().filter(|o| OCTAVES.contains(o))?,
+    };
+    let pitch = (letter != 'p').then_some((letter, sharp, octave));
+    Some(Note {
+        length,
+        pitch,
+        dotted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sound_sequence::render, utils::example_results, wav::WavOptions};
+
+    #[test]
+    fn example_result_matches_rendered_wav() {
+        let steps = rtttl_steps(
+            "One:d=4,o=5,b=120:a",
+            Waveform::Sine,
+            Envelope::default(),
+            1.0,
+            Span::test_data(),
+        )
+        .unwrap();
+        let wav = WavOptions::default().encode(render(&steps)).unwrap();
+        assert_eq!(
+            example_results(&SoundRtttlCmd),
+            [Value::test_int(wav.len() as i64)]
+        );
+    }
+}
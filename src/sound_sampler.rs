@@ -0,0 +1,202 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, SyntaxShape,
+    Type, Value,
+};
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats},
+    ear_guard::{ear_guard_flags, EarGuard},
+    instrument::Instrument,
+    no_audio::{no_audio_flags, NoAudio},
+    sound_make::{
+        frequency_shape, parse_frequencies, play_source, Envelope, Tone, Waveform, MAX_LENGTH,
+        TONE_RATE,
+    },
+    stream_identity::StreamRole,
+    validate,
+    wav::{wav_flags, WavOptions},
+    Sound,
+};
+
+/// Fade at the end of a note cut short by `--duration`, so the cut does not click.
+const CUT_RELEASE: Duration = Duration::from_millis(5);
+
+/// Nushell command `sound sampler` — plays a short recording pitched to other notes.
+///
+/// The recording is sped up or slowed down the way a hardware sampler does, so it gets
+/// shorter as it goes up; `sound sequence --sample` plays melodies with it.
+pub struct SoundSamplerCmd;
+
+impl SimplePluginCommand for SoundSamplerCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound sampler"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound sampler")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required("file", SyntaxShape::Filepath, "short audio file to play")
+            .named(
+                "note",
+                frequency_shape(),
+                "note or frequency to play the sample at; a list or chord name plays a chord (default: as recorded)",
+                Some('n'),
+            )
+            .named(
+                "root",
+                SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::String]),
+                "note or frequency the sample was recorded at (default C4)",
+                None,
+            )
+            .named(
+                "duration",
+                validate::duration_shape(),
+                "length to play, cutting the sample short or padding it with silence (default: the whole sample)",
+                None,
+            )
+            .named(
+                "amplify",
+                SyntaxShape::Float,
+                "amplify or attenuate the sample by given value (e.g. 0.5 for half volume)",
+                Some('a'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow durations longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(signature)))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "play a kick drum an octave below where it was recorded",
+                example: "sound sampler kick.wav --note C3",
+                result: None,
+            },
+            Example {
+                description: "a chord from one recorded note, whose pitch is A4",
+                example: "sound sampler pluck.wav --root A4 --note [A4 C#5 E5]",
+                result: None,
+            },
+            Example {
+                description: "render the sample at several pitches, one file each",
+                example: "[C3 G3 C4] | each {|note| sound sampler blip.wav --note $note --data | save --raw $\"blip-($note).wav\" }",
+                result: None,
+            },
+            Example {
+                description: "the same sample as the voice of a melody",
+                example: "[[note duration]; [C4 200ms] [E4 200ms] [G4 400ms]] | sound sequence --sample blip.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a short audio file pitched to a note, like a sampler"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let file: Value = call.req(0)?;
+        let root = call.get_flag_value("root");
+        let instrument = Instrument::sample(engine, file, root)?;
+        let Instrument::Sample { root, .. } = instrument else {
+            unreachable!("Instrument::sample makes a sample");
+        };
+        let frequencies = match call.get_flag_value("note") {
+            Some(note) => parse_frequencies(&note)?,
+            None => vec![root as f32],
+        };
+        let duration = call
+            .get_flag_value("duration")
+            .map(|value| validate::duration(&value, "--duration"))
+            .transpose()?;
+        let amplify = match call.get_flag_value("amplify") {
+            Some(amplify) => validate::gain(&amplify, "--amplify")?,
+            None => 1.0,
+        };
+
+        // Unless cut short, the chord lasts as long as its lowest, slowest note.
+        let envelope = match duration {
+            Some(_) => Envelope {
+                release: CUT_RELEASE,
+                ..Envelope::default()
+            },
+            None => Envelope::default(),
+        };
+        let duration = duration.unwrap_or_else(|| {
+            frequencies
+                .iter()
+                .filter_map(|&frequency| instrument.length(frequency))
+                .max()
+                .unwrap_or_default()
+        });
+        validate::length(duration, MAX_LENGTH, call.has_flag("force")?, call.head)?;
+        let tone = Tone {
+            waveform: Waveform::Sine,
+            frequencies,
+            right_frequencies: None,
+            pan: None,
+            harmonics: None,
+            vibrato: None,
+            tremolo: None,
+            expression: None,
+            instrument: Some(Arc::new(instrument)),
+            duration,
+            amplify,
+            envelope,
+        };
+
+        if call.has_flag("data")? {
+            let wav = WavOptions::from_call(call)?;
+            let max_length = wav.max_length(TONE_RATE, 1);
+            if duration > max_length {
+                return Err(
+                    LabeledError::new("sample too long for a WAV file").with_label(
+                        format!("WAV data is limited to {}s", max_length.as_secs()),
+                        call.head,
+                    ),
+                );
+            }
+            return Ok(Value::binary(wav.encode(tone.source())?, call.head));
+        }
+        if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        play_source(tone.source(), guard, StreamRole::Media)?;
+        Ok(Value::nothing(call.head))
+    }
+}
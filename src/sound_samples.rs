@@ -0,0 +1,192 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Signature, SyntaxShape,
+    Type, Value,
+};
+use rodio::buffer::SamplesBuffer;
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats},
+    ear_guard::{ear_guard_flags, EarGuard},
+    no_audio::{no_audio_flags, NoAudio},
+    sound_make::{play_source, TONE_RATE},
+    stream_identity::StreamRole,
+    wav::{int_in, WavOptions, MAX_CHANNELS, RATE_RANGE},
+    Sound,
+};
+
+/// Nushell command `sound from-samples` — plays a list of raw samples, or wraps it in a
+/// WAV file.
+///
+/// A list of numbers is mono. A list of lists is one frame per item, with a sample per
+/// channel, so `[[0.1 -0.1] [0.2 -0.2]]` is two stereo frames. Samples run from -1.0 to
+/// 1.0; anything outside is clipped.
+pub struct SoundFromSamplesCmd;
+
+impl SimplePluginCommand for SoundFromSamplesCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound from-samples"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound from-samples")
+            .input_output_types(vec![
+                (Type::List(Box::new(Type::Number)), Type::Nothing),
+                (Type::List(Box::new(Type::Number)), Type::Binary),
+                (
+                    Type::List(Box::new(Type::List(Box::new(Type::Number)))),
+                    Type::Nothing,
+                ),
+                (
+                    Type::List(Box::new(Type::List(Box::new(Type::Number)))),
+                    Type::Binary,
+                ),
+            ])
+            .named(
+                "rate",
+                SyntaxShape::Int,
+                "sample rate of the samples in Hz (default 48000)",
+                Some('r'),
+            )
+            .named(
+                "bits",
+                SyntaxShape::OneOf(vec![SyntaxShape::Int, SyntaxShape::String]),
+                "sample format of --data output: 16 (default), 24 or 32f",
+                None,
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .category(Category::Experimental);
+        no_audio_flags(ear_guard_flags(signature))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "play a 440 Hz sine computed in nushell",
+                example: "0..44099 | each { |n| 2 * 3.14159 * 440 * $n / 44100 | math sin } | sound from-samples --rate 44100",
+                result: None,
+            },
+            Example {
+                description: "white noise at half level, saved as WAV",
+                example: "1..48000 | each { random float -0.5..0.5 } | sound from-samples --data | save --raw noise.wav",
+                result: None,
+            },
+            Example {
+                description: "stereo: one frame per item, the left sample first",
+                example: "[[0.0 0.0] [0.5 -0.5] [1.0 -1.0]] | sound from-samples --data | bytes length",
+                result: Some(Value::test_int(44 + 3 * 2 * 2)),
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a list of raw samples, or output them as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let rate = match call.get_flag_value("rate") {
+            Some(value) => int_in(&value, RATE_RANGE, "--rate", "Hz")? as u32,
+            None => TONE_RATE,
+        };
+        // --rate describes the input here, so the WAV keeps it rather than resampling.
+        let wav = WavOptions {
+            rate: None,
+            ..WavOptions::from_call(call)?
+        };
+        let (channels, samples) = interleave(input)?;
+        let source = SamplesBuffer::new(channels, rate, samples);
+        if call.has_flag("data")? {
+            return Ok(Value::binary(wav.encode(source)?, call.head));
+        }
+        if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        play_source(source, guard, StreamRole::Media)?;
+        Ok(Value::nothing(call.head))
+    }
+}
+
+/// The channel count and interleaved samples of a list of samples or of frames.
+fn interleave(input: &Value) -> Result<(u16, Vec<f32>), LabeledError> {
+    let items = input.as_list().map_err(|e| {
+        LabeledError::new(e.to_string())
+            .with_label("expected a list of samples", input.span())
+            .with_help("pipe in numbers from -1.0 to 1.0, e.g. [0.0 0.5 1.0 0.5]")
+    })?;
+    let Some(first) = items.first() else {
+        return Err(LabeledError::new("no samples").with_label("the list is empty", input.span()));
+    };
+    let Value::List { vals, .. } = first else {
+        let samples = items.iter().map(sample).collect::<Result<_, _>>()?;
+        return Ok((1, samples));
+    };
+    let channels = vals.len();
+    if !(1..=MAX_CHANNELS as usize).contains(&channels) {
+        return Err(LabeledError::new("unsupported channel count").with_label(
+            format!("frames must have 1 to {MAX_CHANNELS} samples, this one has {channels}"),
+            first.span(),
+        ));
+    }
+    let mut samples = Vec::with_capacity(items.len() * channels);
+    for item in items {
+        let frame = item.as_list().map_err(|_| {
+            LabeledError::new("mixed samples and frames")
+                .with_label("expected a list of one sample per channel", item.span())
+        })?;
+        if frame.len() != channels {
+            return Err(
+                LabeledError::new("frames differ in channel count").with_label(
+                    format!("expected {channels} samples, found {}", frame.len()),
+                    item.span(),
+                ),
+            );
+        }
+        for value in frame {
+            samples.push(sample(value)?);
+        }
+    }
+    Ok((channels as u16, samples))
+}
+
+/// One sample, clipped to `[-1.0, 1.0]`.
+fn sample(value: &Value) -> Result<f32, LabeledError> {
+    let span = value.span();
+    let sample = value.as_float().map_err(|_| {
+        LabeledError::new("expected a number")
+            .with_label(format!("found {}", value.get_type()), span)
+    })?;
+    if !sample.is_finite() {
+        return Err(LabeledError::new("sample is not a finite number")
+            .with_label("NaN and infinity cannot be played", span));
+    }
+    Ok(sample.clamp(-1.0, 1.0) as f32)
+}
@@ -0,0 +1,354 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::ArgType, Category, DynamicSuggestion, Example, LabeledError, Record, Signature, Span,
+    Type, Value,
+};
+use rodio::buffer::SamplesBuffer;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats, waveforms},
+    ear_guard::{ear_guard_flags, EarGuard},
+    instrument::{instrument_flags, Instrument},
+    no_audio::{no_audio_flags, NoAudio},
+    sound_make::{
+        parse_frequencies, play_source, tone_flags, Envelope, Tone, Waveform, MAX_LENGTH,
+        TONE_RATE,
+    },
+    stream_identity::StreamRole,
+    validate,
+    wav::{wav_flags, WavOptions},
+    Sound,
+};
+
+/// Rise and fall [`note_envelope`] gives each note.
+const NOTE_EDGE: Duration = Duration::from_millis(5);
+
+/// Nushell command `sound sequence` — renders a table of notes as one continuous tone.
+///
+/// Every row becomes a [`Step`]; the steps are rendered back to back into a single
+/// buffer, so notes follow each other without the gaps and device re-opening of an
+/// `each { sound make ... }` loop. Rows with a `start` are mixed at that offset
+/// instead, so voices can overlap.
+pub struct SoundSequenceCmd;
+
+impl SimplePluginCommand for SoundSequenceCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound sequence"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound sequence")
+            .input_output_types(vec![
+                (Type::table(), Type::Nothing),
+                (Type::table(), Type::Binary),
+                (Type::List(Box::new(Type::Any)), Type::Nothing),
+                (Type::List(Box::new(Type::Any)), Type::Binary),
+            ])
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow sequences longer than an hour", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(instrument_flags(
+            tone_flags(signature),
+        ))))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "play the start of a scale",
+                example: "[[note duration]; [C4 200ms] [D4 200ms] [E4 200ms] [F4 400ms]] | sound sequence",
+                result: None,
+            },
+            Example {
+                description: "a quieter note, then a rest",
+                example: "[[note duration amplitude]; [A4 300ms 0.5] [rest 100ms 0] [A5 300ms 1.0]] | sound sequence",
+                result: None,
+            },
+            Example {
+                description: "plucked square-wave notes without clicks between them",
+                example: "[[note duration]; [E5 150ms] [G5 150ms] [E6 300ms]] | sound sequence -w square --attack 5ms --release 50ms",
+                result: None,
+            },
+            Example {
+                description: "a held bass note under a melody: rows with a start overlap",
+                example: "[[note start duration amplitude]; [C3 0ms 1200ms 0.4] [E4 0ms 300ms 0.5] [G4 300ms 300ms 0.5] [C5 600ms 600ms 0.5]] | sound sequence",
+                result: None,
+            },
+            Example {
+                description: "a jingle played with a recorded piano note, pitched from its C4",
+                example: "[[note duration]; [C5 150ms] [E5 150ms] [G5 300ms]] | sound sequence --sample piano-c4.wav --release 80ms",
+                result: None,
+            },
+            Example {
+                description: "a melody with a single-cycle waveform from a wavetable synth",
+                example: "[[note duration]; [A3 250ms] [C4 250ms] [E4 500ms]] | sound sequence --wavetable cycle.wav",
+                result: None,
+            },
+            Example {
+                description: "two 10ms steps are a 44-byte WAV header and 960 16-bit mono samples",
+                example: "[[note duration]; [A4 10ms] [rest 10ms]] | sound sequence --data | bytes length",
+                result: Some(Value::test_int(44 + 960 * 2)),
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a table of {note, duration, amplitude} rows as one continuous sequence, or overlapping with a start column"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "waveform" => Some(waveforms()),
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let rows = load_steps(engine, call, input)?;
+        if rows.iter().all(|(start, _)| start.is_none()) {
+            let steps: Vec<Step> = rows.into_iter().map(|(_, step)| step).collect();
+            return output_steps(engine, call, &steps);
+        }
+        // A row without a start begins where the one before it ended.
+        let mut voices = Vec::new();
+        let mut end = Duration::ZERO;
+        for (start, step) in rows {
+            let start = start.unwrap_or(end);
+            end = start + step.duration();
+            voices.push((start, step));
+        }
+        output(engine, call, voices_length(&voices), || mix(&voices))
+    }
+}
+
+/// One step of a sequence.
+pub enum Step {
+    Tone(Tone),
+    Rest(Duration),
+}
+
+impl Step {
+    pub fn duration(&self) -> Duration {
+        match self {
+            Step::Tone(tone) => tone.duration,
+            Step::Rest(duration) => *duration,
+        }
+    }
+}
+
+/// Renders `steps` back to back into one mono buffer at [`TONE_RATE`].
+pub fn render(steps: &[Step]) -> SamplesBuffer {
+    let mut samples = Vec::new();
+    for step in steps {
+        match step {
+            Step::Tone(tone) => samples.extend(tone.source()),
+            Step::Rest(duration) => {
+                let len = (duration.as_secs_f64() * TONE_RATE as f64).round() as usize;
+                samples.resize(samples.len() + len, 0.0);
+            }
+        }
+    }
+    SamplesBuffer::new(1, TONE_RATE, samples)
+}
+
+/// Renders `voices` into one mono buffer at [`TONE_RATE`], each starting at its offset.
+/// Voices that overlap are summed, so they can sound together like the notes of a
+/// chord, but unlike a chord they are not scaled down to fit.
+fn mix(voices: &[(Duration, Step)]) -> SamplesBuffer {
+    let frames = |duration: Duration| (duration.as_secs_f64() * TONE_RATE as f64).round() as usize;
+    let mut samples = vec![0.0; frames(voices_length(voices))];
+    for (start, step) in voices {
+        let Step::Tone(tone) = step else {
+            continue;
+        };
+        let offset = frames(*start);
+        let note: Vec<f32> = tone.source().collect();
+        if samples.len() < offset + note.len() {
+            samples.resize(offset + note.len(), 0.0);
+        }
+        for (out, sample) in samples[offset..].iter_mut().zip(note) {
+            *out += sample;
+        }
+    }
+    SamplesBuffer::new(1, TONE_RATE, samples)
+}
+
+/// When the last of `voices` ends.
+fn voices_length(voices: &[(Duration, Step)]) -> Duration {
+    voices
+        .iter()
+        .map(|(start, step)| *start + step.duration())
+        .max()
+        .unwrap_or_default()
+}
+
+/// Plays `steps`, or returns them as WAV data with `--data`. Sequences over an hour
+/// need `--force`.
+pub fn output_steps(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    steps: &[Step],
+) -> Result<Value, LabeledError> {
+    let length: Duration = steps.iter().map(Step::duration).sum();
+    output(engine, call, length, || render(steps))
+}
+
+/// Plays the buffer `render` makes, or returns it as WAV data with `--data`, after
+/// checking its `length` against the limits.
+fn output(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    length: Duration,
+    render: impl Fn() -> SamplesBuffer,
+) -> Result<Value, LabeledError> {
+    validate::length(length, MAX_LENGTH, call.has_flag("force")?, call.head)?;
+    if call.has_flag("data")? {
+        let wav = WavOptions::from_call(call)?;
+        let max_length = wav.max_length(TONE_RATE, 1);
+        if length > max_length {
+            return Err(
+                LabeledError::new("sequence too long for a WAV file").with_label(
+                    format!("WAV data is limited to {}s", max_length.as_secs()),
+                    call.head,
+                ),
+            );
+        }
+        return Ok(Value::binary(wav.encode(render())?, call.head));
+    }
+    if !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+        return Ok(Value::nothing(call.head));
+    }
+    let guard = EarGuard::from_call(engine, call)?;
+    play_source(render(), guard, StreamRole::Notification)?;
+    Ok(Value::nothing(call.head))
+}
+
+/// The envelope flags, or a short rise and fall when none are given, so notes written
+/// as text stay apart when repeated and do not click.
+pub fn note_envelope(call: &EvaluatedCall) -> Result<Envelope, LabeledError> {
+    match Envelope::from_call(call)? {
+        envelope if envelope == Envelope::default() => Ok(Envelope {
+            attack: NOTE_EDGE,
+            release: NOTE_EDGE,
+            ..Envelope::default()
+        }),
+        envelope => Ok(envelope),
+    }
+}
+
+fn load_steps(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Vec<(Option<Duration>, Step)>, LabeledError> {
+    let rows = input.as_list().map_err(|e| {
+        LabeledError::new(e.to_string())
+            .with_label("expected a table of notes", input.span())
+            .with_help("pipe in rows like [[note duration]; [A4 200ms]]")
+    })?;
+    let waveform = Waveform::from_call(call)?;
+    let envelope = Envelope::from_call(call)?;
+    let instrument = Instrument::from_call(engine, call)?.map(Arc::new);
+    rows.iter()
+        .map(|row| {
+            let record = row.as_record().map_err(|e| {
+                LabeledError::new(e.to_string()).with_label("expected a record", row.span())
+            })?;
+            let start = record
+                .get("start")
+                .map(|start| validate::duration(start, "start"))
+                .transpose()?;
+            Ok((
+                start,
+                load_step(record, row.span(), waveform, envelope, instrument.clone())?,
+            ))
+        })
+        .collect()
+}
+
+/// A row's `note` (or `frequency`) is anything `sound make` accepts, including a list
+/// for a chord, or `rest`/null for silence; `duration` is required and `amplitude`
+/// defaults to 1.0. An optional `start` is read by [`load_steps`].
+fn load_step(
+    record: &Record,
+    span: Span,
+    waveform: Waveform,
+    envelope: Envelope,
+    instrument: Option<Arc<Instrument>>,
+) -> Result<Step, LabeledError> {
+    let duration = record.get("duration").ok_or_else(|| {
+        LabeledError::new("missing duration").with_label("row has no `duration` column", span)
+    })?;
+    let duration = validate::duration(duration, "duration")?;
+    let note = record.get("note").or_else(|| record.get("frequency"));
+    let is_rest = match note {
+        None | Some(Value::Nothing { .. }) => true,
+        Some(Value::String { val, .. }) => ["rest", "r", "-"].contains(&val.trim()),
+        Some(_) => false,
+    };
+    if is_rest {
+        return Ok(Step::Rest(duration));
+    }
+    let note = note.expect("rests are handled above");
+    let frequencies = parse_frequencies(note)?;
+    let amplify = match record.get("amplitude") {
+        Some(amplitude) => validate::gain(amplitude, "amplitude")?,
+        None => 1.0,
+    };
+    Ok(Step::Tone(Tone {
+        waveform,
+        frequencies,
+        right_frequencies: None,
+        pan: None,
+        harmonics: None,
+        vibrato: None,
+        tremolo: None,
+        expression: None,
+        instrument,
+        duration,
+        amplify,
+        envelope,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::example_results;
+
+    #[test]
+    fn example_result_matches_rendered_wav() {
+        let steps = [
+            Step::Tone(Tone::sine(440.0, Duration::from_millis(10))),
+            Step::Rest(Duration::from_millis(10)),
+        ];
+        let wav = WavOptions::default().encode(render(&steps)).unwrap();
+        assert_eq!(
+            example_results(&SoundSequenceCmd),
+            [Value::test_int(wav.len() as i64)]
+        );
+    }
+}
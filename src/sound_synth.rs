@@ -0,0 +1,258 @@
+use nu_plugin::{DynamicCompletionCall, EngineInterface, EvaluatedCall, SimplePluginCommand};
+use nu_protocol::{
+    engine::{ArgType, Closure},
+    Category, DynamicSuggestion, Example, LabeledError, Signature, Span, Spanned, SyntaxShape,
+    Type, Value,
+};
+use rodio::buffer::SamplesBuffer;
+use std::time::Duration;
+
+use crate::{
+    completions::{no_audio_policies, wav_sample_formats},
+    ear_guard::{ear_guard_flags, EarGuard},
+    no_audio::{no_audio_flags, NoAudio},
+    sound_make::play_source,
+    stream_identity::StreamRole,
+    validate::{self, duration_flag},
+    wav::{int_in, wav_flags, WavOptions},
+    Sound,
+};
+
+/// Rate the closure is sampled at when `--sample-rate` is not given. Every sample is a
+/// closure call, so this trades bandwidth (up to 4 kHz) for speed.
+const DEFAULT_SAMPLE_RATE: u32 = 8_000;
+
+const SAMPLE_RATES: std::ops::RangeInclusive<i64> = 1_000..=192_000;
+
+const DEFAULT_DURATION: Duration = Duration::from_secs(1);
+
+/// Longest render without `--force`; at the default rate a minute is already 480,000
+/// closure calls.
+const MAX_LENGTH: Duration = Duration::from_secs(60);
+
+const MAX_BLOCK: i64 = 65_536;
+
+/// Nushell command `sound synth` — renders audio from a closure.
+///
+/// The closure gets the time in seconds and returns the sample there, from -1.0 to 1.0;
+/// anything outside is clipped. With `--block` it gets a list of times and returns a
+/// list of samples, which saves most of the per-call overhead between the plugin and
+/// the shell.
+pub struct SoundSynthCmd;
+
+impl SimplePluginCommand for SoundSynthCmd {
+    type Plugin = Sound;
+
+    fn name(&self) -> &str {
+        "sound synth"
+    }
+
+    fn signature(&self) -> Signature {
+        let signature = Signature::new("sound synth")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Nothing),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required(
+                "generator",
+                SyntaxShape::Closure(Some(vec![SyntaxShape::Any])),
+                "closure from the time in seconds to a sample from -1.0 to 1.0; with --block, from a list of times to a list of samples",
+            )
+            .named(
+                "duration",
+                validate::duration_shape(),
+                "length of the sound (default 1sec)",
+                Some('l'),
+            )
+            .named(
+                "sample-rate",
+                SyntaxShape::Int,
+                "times per second the closure is sampled (default 8000)",
+                Some('s'),
+            )
+            .named(
+                "block",
+                SyntaxShape::Int,
+                "pass the closure this many times at once, as a list, and expect a list back",
+                Some('b'),
+            )
+            .switch(
+                "data",
+                "output binary data (WAV) instead of playing",
+                Some('d'),
+            )
+            .switch("force", "allow sounds longer than a minute", Some('f'))
+            .category(Category::Experimental);
+        wav_flags(no_audio_flags(ear_guard_flags(signature)))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "a 440 Hz sine",
+                example: "sound synth { |t| (2 * 3.14159 * 440 * $t | math sin) }",
+                result: None,
+            },
+            Example {
+                description: "a falling chirp, half a second long",
+                example: "sound synth { |t| (2 * 3.14159 * (1000 - 800 * $t) * $t | math sin) * 0.5 } --duration 500ms",
+                result: None,
+            },
+            Example {
+                description: "a square wave, computed a block at a time and saved as WAV",
+                example: "sound synth --block 1024 { |ts| $ts | each { |t| if ($t * 220 mod 1) < 0.5 { 0.3 } else { -0.3 } } } --data | save --raw square.wav",
+                result: None,
+            },
+            Example {
+                description: "sample the closure at 16 kHz and write a 48 kHz WAV",
+                example: "sound synth { |t| (2 * 3.14159 * 3000 * $t | math sin) } --sample-rate 16000 --data --rate 48000 | save --raw tone.wav",
+                result: None,
+            },
+        ]
+    }
+
+    fn description(&self) -> &str {
+        "play a sound computed sample by sample by a closure, or output it as WAV"
+    }
+
+    #[allow(deprecated)]
+    fn get_dynamic_completion(
+        &self,
+        _plugin: &Self::Plugin,
+        _engine: &EngineInterface,
+        _call: DynamicCompletionCall,
+        arg_type: ArgType,
+        _experimental: nu_protocol::engine::ExperimentalMarker,
+    ) -> Option<Vec<DynamicSuggestion>> {
+        match arg_type {
+            ArgType::Flag(flag) if flag == "if-no-audio" => Some(no_audio_policies()),
+            ArgType::Flag(flag) if flag == "bits" => Some(wav_sample_formats()),
+            _ => None,
+        }
+    }
+
+    fn run(
+        &self,
+        _plugin: &Self::Plugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let closure: Spanned<Closure> = call.req(0)?;
+        let duration = duration_flag(call, "duration")?.unwrap_or(DEFAULT_DURATION);
+        validate::length(duration, MAX_LENGTH, call.has_flag("force")?, call.head)?;
+        let rate = match call.get_flag_value("sample-rate") {
+            Some(value) => int_in(&value, SAMPLE_RATES, "--sample-rate", "Hz")? as u32,
+            None => DEFAULT_SAMPLE_RATE,
+        };
+        let block = call
+            .get_flag_value("block")
+            .map(|value| int_in(&value, 1..=MAX_BLOCK, "--block", "samples"))
+            .transpose()?
+            .map(|block| block as usize);
+        let data = call.has_flag("data")?;
+        let wav = WavOptions::from_call(call)?;
+        if data && duration > wav.max_length(rate, 1) {
+            return Err(
+                LabeledError::new("duration too long for a WAV file").with_label(
+                    format!(
+                        "WAV data is limited to {}s",
+                        wav.max_length(rate, 1).as_secs()
+                    ),
+                    call.head,
+                ),
+            );
+        }
+        // Checked before the closure runs, since rendering can take a while.
+        if !data && !NoAudio::from_call(engine, call)?.output_available(call.head)? {
+            return Ok(Value::nothing(call.head));
+        }
+
+        let len = (duration.as_secs_f64() * rate as f64).round() as usize;
+        let samples = synthesize(engine, &closure, len, rate, block, call.head)?;
+        let source = SamplesBuffer::new(1, rate, samples);
+        if data {
+            return Ok(Value::binary(wav.encode(source)?, call.head));
+        }
+        let guard = EarGuard::from_call(engine, call)?;
+        play_source(source, guard, StreamRole::Media)?;
+        Ok(Value::nothing(call.head))
+    }
+}
+
+/// Calls `closure` for `len` samples at `rate`, one at a time or `block` at a time.
+fn synthesize(
+    engine: &EngineInterface,
+    closure: &Spanned<Closure>,
+    len: usize,
+    rate: u32,
+    block: Option<usize>,
+    span: Span,
+) -> Result<Vec<f32>, LabeledError> {
+    let time = |n: usize| n as f64 / rate as f64;
+    let call = |argument: Value| {
+        engine
+            .eval_closure(closure, vec![argument], None)
+            .map_err(|e| {
+                LabeledError::new(e.to_string()).with_label("the generator failed", closure.span)
+            })
+    };
+    let mut samples = Vec::with_capacity(len);
+    match block {
+        None => {
+            for n in 0..len {
+                // Checking every sample would cost more than the check saves.
+                if n % 1024 == 0 {
+                    engine.signals().check(&span)?;
+                }
+                let sample = call(Value::float(time(n), span))?;
+                samples.push(sample_value(&sample, time(n), closure.span)?);
+            }
+        }
+        Some(block) => {
+            for start in (0..len).step_by(block) {
+                engine.signals().check(&span)?;
+                let end = (start + block).min(len);
+                let times = (start..end).map(|n| Value::float(time(n), span)).collect();
+                let result = call(Value::list(times, span))?;
+                let values = result.as_list().map_err(|_| {
+                    LabeledError::new("the generator must return a list with --block")
+                        .with_label(format!("returned {}", result.get_type()), closure.span)
+                })?;
+                if values.len() != end - start {
+                    return Err(LabeledError::new("wrong number of samples")
+                        .with_label(
+                            format!(
+                                "got {} times but returned {} samples",
+                                end - start,
+                                values.len()
+                            ),
+                            closure.span,
+                        )
+                        .with_help("return one sample per time, e.g. $ts | each { |t| ... }"));
+                }
+                for (n, value) in (start..end).zip(values) {
+                    samples.push(sample_value(value, time(n), closure.span)?);
+                }
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// A returned sample as `f32`, clipped to `[-1.0, 1.0]`. `t` is the time it was asked
+/// for, for the error message.
+fn sample_value(value: &Value, t: f64, span: Span) -> Result<f32, LabeledError> {
+    let sample = value.as_float().map_err(|_| {
+        LabeledError::new("the generator must return numbers")
+            .with_label(format!("returned {} at t = {t}s", value.get_type()), span)
+    })?;
+    if !sample.is_finite() {
+        return Err(
+            LabeledError::new("the generator returned a non-finite sample")
+                .with_label(format!("returned {sample} at t = {t}s"), span)
+                .with_help("NaN and infinity cannot be played"),
+        );
+    }
+    Ok(sample.clamp(-1.0, 1.0) as f32)
+}
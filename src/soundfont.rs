@@ -0,0 +1,940 @@
+// SoundFont 2 reading and a sample-playback synthesizer driven by MIDI messages.
+//
+// Covers what General MIDI files lean on: key and velocity splits, tuning, loops,
+// attenuation, pan, the volume envelope and exclusive classes (so an open hi-hat is cut
+// by the closed one). Filters, LFOs, the modulation envelope, modulators, chorus and
+// reverb are not implemented; instruments sound dry and without vibrato.
+//
+// Gains and pitches go through the arithmetic-only helpers in `dsp`, so a render is the
+// same on every platform.
+
+use nu_protocol::{LabeledError, Span};
+use std::sync::Arc;
+
+use crate::{
+    dsp::{cos_cycles, exp2, sin_cycles},
+    midi::Message,
+};
+
+/// Frames rendered between envelope, pitch and controller updates; events are applied
+/// on these boundaries too.
+pub const BLOCK: usize = 64;
+
+/// Voices sounding at once; the oldest is cut when a note needs one more.
+const MAX_VOICES: usize = 256;
+
+/// The channel General MIDI reserves for drums, counting from 0.
+pub const DRUM_CHANNEL: u8 = 9;
+
+/// SoundFont bank of the drum kits.
+pub const DRUM_BANK: u16 = 128;
+
+/// Level under which a released voice is dropped: -100 dB.
+const SILENT: f64 = 1e-5;
+
+/// Most samples a voice moves on per output frame: ten octaves up at equal rates. Past
+/// that a note is noise anyway, and a font asking for more would wrap its loop forever.
+const MAX_STEP: f64 = 1024.0;
+
+/// Generator numbers from the SoundFont 2.04 specification, section 8.1.3.
+mod generator {
+    pub const START_OFFSET: usize = 0;
+    pub const END_OFFSET: usize = 1;
+    pub const LOOP_START_OFFSET: usize = 2;
+    pub const LOOP_END_OFFSET: usize = 3;
+    pub const START_COARSE_OFFSET: usize = 4;
+    pub const END_COARSE_OFFSET: usize = 12;
+    pub const PAN: usize = 17;
+    pub const DELAY_VOL_ENV: usize = 33;
+    pub const ATTACK_VOL_ENV: usize = 34;
+    pub const HOLD_VOL_ENV: usize = 35;
+    pub const DECAY_VOL_ENV: usize = 36;
+    pub const SUSTAIN_VOL_ENV: usize = 37;
+    pub const RELEASE_VOL_ENV: usize = 38;
+    pub const INSTRUMENT: usize = 41;
+    pub const KEY_RANGE: usize = 43;
+    pub const VEL_RANGE: usize = 44;
+    pub const LOOP_START_COARSE_OFFSET: usize = 45;
+    pub const KEYNUM: usize = 46;
+    pub const VELOCITY: usize = 47;
+    pub const INITIAL_ATTENUATION: usize = 48;
+    pub const LOOP_END_COARSE_OFFSET: usize = 50;
+    pub const COARSE_TUNE: usize = 51;
+    pub const FINE_TUNE: usize = 52;
+    pub const SAMPLE_ID: usize = 53;
+    pub const SAMPLE_MODES: usize = 54;
+    pub const SCALE_TUNING: usize = 56;
+    pub const EXCLUSIVE_CLASS: usize = 57;
+    pub const OVERRIDING_ROOT_KEY: usize = 58;
+    pub const COUNT: usize = 61;
+}
+
+/// Generators a preset zone may not offset: ranges, indices, sample addresses and the
+/// ones that pick rather than adjust.
+const NOT_ADDITIVE: [usize; 16] = [
+    generator::START_OFFSET,
+    generator::END_OFFSET,
+    generator::LOOP_START_OFFSET,
+    generator::LOOP_END_OFFSET,
+    generator::START_COARSE_OFFSET,
+    generator::END_COARSE_OFFSET,
+    generator::INSTRUMENT,
+    generator::KEY_RANGE,
+    generator::VEL_RANGE,
+    generator::LOOP_START_COARSE_OFFSET,
+    generator::KEYNUM,
+    generator::VELOCITY,
+    generator::LOOP_END_COARSE_OFFSET,
+    generator::SAMPLE_ID,
+    generator::SAMPLE_MODES,
+    generator::EXCLUSIVE_CLASS,
+];
+
+type Generators = [i16; generator::COUNT];
+
+/// The generators of a zone, as `(generator, amount)`.
+type Zone = Vec<(usize, i16)>;
+
+/// Instrument generator values a zone starts from.
+fn default_generators() -> Generators {
+    let mut generators = [0; generator::COUNT];
+    // Envelope and LFO delays and times of -12000 timecents, about a millisecond.
+    for g in [21, 23, 25, 26, 27, 28, 30, 33, 34, 35, 36, 38] {
+        generators[g] = -12000;
+    }
+    generators[8] = 13500;
+    generators[generator::KEY_RANGE] = 0x7F00;
+    generators[generator::VEL_RANGE] = 0x7F00;
+    generators[generator::KEYNUM] = -1;
+    generators[generator::VELOCITY] = -1;
+    generators[generator::SCALE_TUNING] = 100;
+    generators[generator::OVERRIDING_ROOT_KEY] = -1;
+    generators
+}
+
+/// A range generator as `(low, high)`.
+fn range(amount: i16) -> (u8, u8) {
+    let [low, high] = (amount as u16).to_le_bytes();
+    (low, high)
+}
+
+struct SampleHeader {
+    start: u32,
+    end: u32,
+    loop_start: u32,
+    loop_end: u32,
+    rate: u32,
+    root: u8,
+    correction: i8,
+}
+
+/// The generators of one key and velocity split of an instrument or, once the preset's
+/// offsets are added, of a preset.
+struct Region {
+    keys: (u8, u8),
+    velocities: (u8, u8),
+    generators: Generators,
+    sample: usize,
+}
+
+struct Preset {
+    name: String,
+    bank: u16,
+    program: u16,
+    regions: Vec<Region>,
+}
+
+/// The presets and sample data of a SoundFont 2 file.
+pub struct SoundFont {
+    samples: Vec<f32>,
+    headers: Vec<SampleHeader>,
+    presets: Vec<Preset>,
+}
+
+/// The chunks in a RIFF list body, as `(id, data)`.
+fn chunks(mut data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    std::iter::from_fn(move || {
+        let header = data.get(..8)?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let end = 8usize.saturating_add(len).min(data.len());
+        let chunk = (&header[..4], &data[8..end]);
+        data = data.get(end + len % 2..).unwrap_or_default();
+        Some(chunk)
+    })
+}
+
+fn u16_at(record: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([record[at], record[at + 1]])
+}
+
+fn u32_at(record: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([record[at], record[at + 1], record[at + 2], record[at + 3]])
+}
+
+/// The zones of a preset or instrument as generator lists, from its bag indices.
+fn zones<'a>(
+    bags: &'a [&[u8]],
+    generators: &'a [&[u8]],
+    bag_range: std::ops::Range<usize>,
+) -> impl Iterator<Item = Zone> + 'a {
+    bag_range.filter_map(move |bag| {
+        let start = u16_at(bags.get(bag)?, 0) as usize;
+        let end = u16_at(bags.get(bag + 1)?, 0) as usize;
+        Some(
+            generators
+                .get(start..end)?
+                .iter()
+                .map(|g| (u16_at(g, 0) as usize, u16_at(g, 2) as i16))
+                .filter(|(g, _)| *g < generator::COUNT)
+                .collect(),
+        )
+    })
+}
+
+/// Splits zones into the global one, if the first zone lacks the terminal generator
+/// `last`, and the others.
+fn split_global(mut zones: Vec<Zone>, last: usize) -> (Zone, Vec<Zone>) {
+    match zones.first() {
+        Some(first) if first.last().map(|(g, _)| *g) != Some(last) => {
+            let global = zones.remove(0);
+            (global, zones)
+        }
+        _ => (Vec::new(), zones),
+    }
+}
+
+fn intersect(a: (u8, u8), b: (u8, u8)) -> Option<(u8, u8)> {
+    let range = (a.0.max(b.0), a.1.min(b.1));
+    (range.0 <= range.1).then_some(range)
+}
+
+impl SoundFont {
+    /// Reads a SoundFont 2 file from memory.
+    pub fn read(data: &[u8], span: Span) -> Result<SoundFont, LabeledError> {
+        let invalid = |why: &str| {
+            LabeledError::new("not a valid SoundFont").with_label(why.to_string(), span)
+        };
+        if data.get(..4) != Some(b"RIFF") || data.get(8..12) != Some(b"sfbk") {
+            return Err(invalid("expected a RIFF sfbk file (.sf2)"));
+        }
+        let (mut smpl, mut pdta) = (None, Vec::new());
+        for (id, body) in chunks(&data[12..]) {
+            if id != b"LIST" || body.len() < 4 {
+                continue;
+            }
+            for (id, chunk) in chunks(&body[4..]) {
+                match &body[..4] {
+                    b"sdta" if id == b"smpl" => smpl = Some(chunk),
+                    b"pdta" => pdta.push((id, chunk)),
+                    _ => {}
+                }
+            }
+        }
+        let smpl = smpl.ok_or_else(|| {
+            invalid("no sample data; SF3 files with compressed samples are not supported")
+        })?;
+        let records = |name: &[u8], size: usize| -> Vec<&[u8]> {
+            pdta.iter()
+                .find(|(id, _)| *id == name)
+                .map(|(_, chunk)| chunk.chunks_exact(size).collect())
+                .unwrap_or_default()
+        };
+        let (phdr, pbag, pgen) = (
+            records(b"phdr", 38),
+            records(b"pbag", 4),
+            records(b"pgen", 4),
+        );
+        let (inst, ibag, igen) = (
+            records(b"inst", 22),
+            records(b"ibag", 4),
+            records(b"igen", 4),
+        );
+        let shdr = records(b"shdr", 46);
+        if phdr.len() < 2 || inst.len() < 2 || shdr.len() < 2 {
+            return Err(invalid("no presets, instruments or samples"));
+        }
+
+        let samples = smpl
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0)
+            .collect();
+        // The last header of each list is the terminal record.
+        let headers = shdr[..shdr.len() - 1]
+            .iter()
+            .map(|h| SampleHeader {
+                start: u32_at(h, 20),
+                end: u32_at(h, 24),
+                loop_start: u32_at(h, 28),
+                loop_end: u32_at(h, 32),
+                // ROM samples (type bit 15) are not in the file; a rate of 0 mutes them.
+                rate: match u16_at(h, 44) & 0x8000 {
+                    0 => u32_at(h, 36),
+                    _ => 0,
+                },
+                root: h[40],
+                correction: h[41] as i8,
+            })
+            .collect();
+
+        let instruments: Vec<Vec<Region>> = inst
+            .windows(2)
+            .map(|pair| {
+                let bags = u16_at(pair[0], 20) as usize..u16_at(pair[1], 20) as usize;
+                let zones = zones(&ibag, &igen, bags).collect();
+                let (global, zones) = split_global(zones, generator::SAMPLE_ID);
+                zones
+                    .into_iter()
+                    .filter_map(|zone| {
+                        let mut generators = default_generators();
+                        for (g, amount) in global.iter().chain(&zone) {
+                            generators[*g] = *amount;
+                        }
+                        let sample = zone
+                            .last()
+                            .filter(|(g, _)| *g == generator::SAMPLE_ID)
+                            .map(|(_, id)| *id as u16 as usize)?;
+                        Some(Region {
+                            keys: range(generators[generator::KEY_RANGE]),
+                            velocities: range(generators[generator::VEL_RANGE]),
+                            generators,
+                            sample,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let presets = phdr
+            .windows(2)
+            .map(|pair| {
+                let bags = u16_at(pair[0], 24) as usize..u16_at(pair[1], 24) as usize;
+                let zones = zones(&pbag, &pgen, bags).collect();
+                let (global, zones) = split_global(zones, generator::INSTRUMENT);
+                let mut regions = Vec::new();
+                for zone in zones {
+                    let mut offsets = [0i16; generator::COUNT];
+                    offsets[generator::KEY_RANGE] = 0x7F00;
+                    offsets[generator::VEL_RANGE] = 0x7F00;
+                    for (g, amount) in global.iter().chain(&zone) {
+                        offsets[*g] = *amount;
+                    }
+                    let Some(instrument) = zone
+                        .last()
+                        .filter(|(g, _)| *g == generator::INSTRUMENT)
+                        .and_then(|(_, id)| instruments.get(*id as u16 as usize))
+                    else {
+                        continue;
+                    };
+                    for zone in instrument {
+                        let (Some(keys), Some(velocities)) = (
+                            intersect(zone.keys, range(offsets[generator::KEY_RANGE])),
+                            intersect(zone.velocities, range(offsets[generator::VEL_RANGE])),
+                        ) else {
+                            continue;
+                        };
+                        let mut generators = zone.generators;
+                        for (g, value) in generators.iter_mut().enumerate() {
+                            if !NOT_ADDITIVE.contains(&g) {
+                                *value = value.saturating_add(offsets[g]);
+                            }
+                        }
+                        regions.push(Region {
+                            keys,
+                            velocities,
+                            generators,
+                            sample: zone.sample,
+                        });
+                    }
+                }
+                // The name fills 20 bytes, padded with NULs.
+                let name = pair[0][..20].split(|&b| b == 0).next().unwrap_or_default();
+                Preset {
+                    name: String::from_utf8_lossy(name).trim().to_string(),
+                    bank: u16_at(pair[0], 22),
+                    program: u16_at(pair[0], 20),
+                    regions,
+                }
+            })
+            .collect();
+        Ok(SoundFont {
+            samples,
+            headers,
+            presets,
+        })
+    }
+
+    /// Bank and program of the preset called `name`, ignoring case.
+    pub fn preset_named(&self, name: &str) -> Option<(u16, u8)> {
+        self.presets
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+            .map(|p| (p.bank, p.program as u8))
+    }
+
+    /// The preset for `bank` and `program`, falling back to the General MIDI bank (or
+    /// the standard drum kit) when the file has no such variation.
+    fn preset(&self, bank: u16, program: u8) -> Option<&Preset> {
+        let find = |bank: u16, program: u8| {
+            self.presets
+                .iter()
+                .find(|p| p.bank == bank && p.program == program as u16)
+        };
+        find(bank, program).or_else(|| match bank {
+            DRUM_BANK => find(DRUM_BANK, 0),
+            _ => find(0, program),
+        })
+    }
+}
+
+/// Seconds from timecents.
+fn timecents(tc: i16) -> f64 {
+    exp2(tc as f64 / 1200.0)
+}
+
+/// Amplitude from an attenuation in centibels.
+fn centibels(cb: f64) -> f64 {
+    exp2(-cb / 200.0 * std::f64::consts::LOG2_10)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Delay,
+    Attack,
+    Hold,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// The SoundFont volume envelope: attack is linear in amplitude, decay and release are
+/// linear in decibels, at 100 dB per decay or release time.
+struct Envelope {
+    stage: Stage,
+    elapsed: f64,
+    delay: f64,
+    attack: f64,
+    hold: f64,
+    decay: f64,
+    /// Attenuation of the sustain level in centibels.
+    sustain: f64,
+    release: f64,
+    level: f64,
+    released_from: f64,
+}
+
+impl Envelope {
+    fn new(generators: &Generators) -> Envelope {
+        Envelope {
+            stage: Stage::Delay,
+            elapsed: 0.0,
+            delay: timecents(generators[generator::DELAY_VOL_ENV]),
+            attack: timecents(generators[generator::ATTACK_VOL_ENV]),
+            hold: timecents(generators[generator::HOLD_VOL_ENV]),
+            decay: timecents(generators[generator::DECAY_VOL_ENV]),
+            sustain: generators[generator::SUSTAIN_VOL_ENV].clamp(0, 1440) as f64,
+            release: timecents(generators[generator::RELEASE_VOL_ENV]),
+            level: 0.0,
+            released_from: 0.0,
+        }
+    }
+
+    fn release(&mut self) {
+        if self.stage != Stage::Release {
+            self.stage = Stage::Release;
+            self.elapsed = 0.0;
+            self.released_from = self.level;
+        }
+    }
+
+    /// Moves `dt` seconds on and returns the level there.
+    fn advance(&mut self, dt: f64) -> f64 {
+        self.elapsed += dt;
+        loop {
+            let (length, next) = match self.stage {
+                Stage::Delay => (self.delay, Stage::Attack),
+                Stage::Attack => (self.attack, Stage::Hold),
+                Stage::Hold => (self.hold, Stage::Decay),
+                Stage::Decay => (self.decay * self.sustain / 1000.0, Stage::Sustain),
+                Stage::Sustain | Stage::Release => break,
+            };
+            if self.elapsed < length {
+                break;
+            }
+            self.elapsed -= length;
+            self.stage = next;
+        }
+        self.level = match self.stage {
+            Stage::Delay => 0.0,
+            Stage::Attack => self.elapsed / self.attack,
+            Stage::Hold => 1.0,
+            Stage::Decay => centibels(1000.0 * self.elapsed / self.decay),
+            Stage::Sustain => centibels(self.sustain),
+            Stage::Release => self.released_from * centibels(1000.0 * self.elapsed / self.release),
+        };
+        self.level
+    }
+
+    fn finished(&self) -> bool {
+        self.stage == Stage::Release && self.level < SILENT
+    }
+}
+
+struct Voice {
+    channel: u8,
+    key: u8,
+    exclusive: i16,
+    /// Position in the font's sample data.
+    position: f64,
+    end: usize,
+    /// Loop start and end, when the sample loops.
+    loop_points: Option<(usize, usize)>,
+    /// Whether the loop is left on release, to play the sample's tail.
+    loop_until_release: bool,
+    /// Pitch relative to the sample's own, in cents, before pitch bend.
+    cents: f64,
+    rate_ratio: f64,
+    gain: f64,
+    pan: f64,
+    envelope: Envelope,
+    /// Released by a note off while the sustain pedal was down.
+    sustained: bool,
+    /// Left and right gains at the end of the last block, to ramp from.
+    gains: (f32, f32),
+}
+
+impl Voice {
+    fn new(
+        font: &SoundFont,
+        region: &Region,
+        channel: u8,
+        key: u8,
+        velocity: u8,
+        output_rate: u32,
+    ) -> Option<Voice> {
+        let g = &region.generators;
+        let header = font.headers.get(region.sample)?;
+        if header.rate == 0 || header.end <= header.start {
+            return None;
+        }
+        let address = |base: u32, fine: usize, coarse: usize| {
+            (base as i64 + g[fine] as i64 + 32768 * g[coarse] as i64)
+                .clamp(0, font.samples.len() as i64) as usize
+        };
+        use generator::*;
+        let start = address(header.start, START_OFFSET, START_COARSE_OFFSET);
+        let end = address(header.end, END_OFFSET, END_COARSE_OFFSET);
+        let loop_start = address(
+            header.loop_start,
+            LOOP_START_OFFSET,
+            LOOP_START_COARSE_OFFSET,
+        );
+        let loop_end = address(header.loop_end, LOOP_END_OFFSET, LOOP_END_COARSE_OFFSET);
+        if end <= start + 1 {
+            return None;
+        }
+        let modes = g[SAMPLE_MODES] & 3;
+        let loop_points = (matches!(modes, 1 | 3)
+            && start <= loop_start
+            && loop_start < loop_end
+            && loop_end <= end)
+            .then_some((loop_start, loop_end));
+
+        let root = match g[OVERRIDING_ROOT_KEY] {
+            root @ 0..=127 => root,
+            _ if header.root <= 127 => header.root as i16,
+            _ => 60,
+        };
+        let pitch_key = match g[KEYNUM] {
+            keynum @ 0..=127 => keynum,
+            _ => key as i16,
+        };
+        // Tuning outside the ranges of the specification, section 8.1.3, is clamped.
+        let cents = (pitch_key - root) as f64 * g[SCALE_TUNING].clamp(0, 1200) as f64
+            + g[COARSE_TUNE].clamp(-120, 120) as f64 * 100.0
+            + g[FINE_TUNE].clamp(-99, 99) as f64
+            + header.correction as f64;
+        let velocity = match g[VELOCITY] {
+            fixed @ 1..=127 => fixed as f64,
+            _ => velocity as f64,
+        };
+        // Most fonts are voiced for the E-mu hardware, which applied 0.4 of the
+        // attenuation the specification asks for.
+        let attenuation = 0.4 * g[INITIAL_ATTENUATION].clamp(0, 1440) as f64;
+        Some(Voice {
+            channel,
+            key,
+            exclusive: g[EXCLUSIVE_CLASS],
+            position: start as f64,
+            end,
+            loop_points,
+            loop_until_release: modes == 3,
+            cents,
+            rate_ratio: header.rate as f64 / output_rate as f64,
+            gain: centibels(attenuation) * (velocity / 127.0).powi(2),
+            pan: (g[PAN].clamp(-500, 500) as f64) / 1000.0,
+            envelope: Envelope::new(g),
+            sustained: false,
+            gains: (0.0, 0.0),
+        })
+    }
+
+    /// Adds the next `out.len() / 2` frames to the interleaved stereo `out`. Returns
+    /// false once the voice has ended.
+    fn render(&mut self, samples: &[f32], channel: &Channel, rate: u32, out: &mut [f32]) -> bool {
+        let frames = out.len() / 2;
+        let level = self.envelope.advance(frames as f64 / rate as f64);
+        let step =
+            (exp2((self.cents + channel.bend_cents()) / 1200.0) * self.rate_ratio).min(MAX_STEP);
+        // Constant-power pan, in cycles: a quarter turn from hard left to hard right.
+        let pan = (self.pan + (channel.pan as f64 - 64.0) / 127.0).clamp(-0.5, 0.5);
+        let gain = self.gain * level * channel.gain();
+        let target = (
+            (gain * cos_cycles((pan + 0.5) / 4.0)) as f32,
+            (gain * sin_cycles((pan + 0.5) / 4.0)) as f32,
+        );
+        let (mut left, mut right) = self.gains;
+        let ramp = (
+            (target.0 - left) / frames as f32,
+            (target.1 - right) / frames as f32,
+        );
+        self.gains = target;
+
+        let looping = self
+            .loop_points
+            .filter(|_| !(self.loop_until_release && self.envelope.stage == Stage::Release));
+        for frame in out.chunks_exact_mut(2) {
+            let index = self.position as usize;
+            let next = match looping {
+                Some((start, end)) if index + 1 >= end => start,
+                _ => index + 1,
+            };
+            let fraction = (self.position - index as f64) as f32;
+            let a = samples.get(index).copied().unwrap_or(0.0);
+            let b = samples.get(next).copied().unwrap_or(0.0);
+            let sample = a + (b - a) * fraction;
+            left += ramp.0;
+            right += ramp.1;
+            frame[0] += sample * left;
+            frame[1] += sample * right;
+
+            self.position += step;
+            match looping {
+                Some((start, end)) if self.position >= end as f64 => {
+                    self.position =
+                        start as f64 + (self.position - start as f64) % (end - start) as f64;
+                }
+                None if self.position >= (self.end - 1) as f64 => return false,
+                _ => {}
+            }
+        }
+        !self.envelope.finished()
+    }
+}
+
+/// Controller state of one MIDI channel.
+struct Channel {
+    bank: u16,
+    program: u8,
+    volume: u8,
+    expression: u8,
+    pan: u8,
+    sustain: bool,
+    bend: i16,
+    /// Pitch bend range in semitones, set through RPN 0.
+    bend_range: u8,
+    /// Registered parameter selected for data entry, as (MSB, LSB).
+    rpn: (u8, u8),
+}
+
+impl Channel {
+    fn new(index: u8) -> Channel {
+        Channel {
+            bank: if index == DRUM_CHANNEL { DRUM_BANK } else { 0 },
+            program: 0,
+            volume: 100,
+            expression: 127,
+            pan: 64,
+            sustain: false,
+            bend: 0,
+            bend_range: 2,
+            rpn: (127, 127),
+        }
+    }
+
+    fn bend_cents(&self) -> f64 {
+        self.bend as f64 / 8192.0 * self.bend_range as f64 * 100.0
+    }
+
+    /// Channel volume and expression, each with the squared curve General MIDI expects.
+    fn gain(&self) -> f64 {
+        let volume = self.volume as f64 / 127.0;
+        let expression = self.expression as f64 / 127.0;
+        (volume * expression).powi(2)
+    }
+}
+
+/// Plays MIDI messages on the instruments of a SoundFont, rendering interleaved stereo.
+pub struct Synth {
+    font: Arc<SoundFont>,
+    rate: u32,
+    channels: Vec<Channel>,
+    voices: Vec<Voice>,
+}
+
+impl Synth {
+    pub fn new(font: Arc<SoundFont>, rate: u32) -> Synth {
+        Synth {
+            font,
+            rate,
+            channels: (0..16).map(Channel::new).collect(),
+            voices: Vec::new(),
+        }
+    }
+
+    /// Whether every voice has ended.
+    pub fn is_silent(&self) -> bool {
+        self.voices.is_empty()
+    }
+
+    pub fn handle(&mut self, message: Message) {
+        match message {
+            Message::NoteOn {
+                channel,
+                key,
+                velocity,
+            } => self.note_on(channel, key, velocity),
+            Message::NoteOff { channel, key } => {
+                let sustain = self.channels[channel as usize].sustain;
+                for voice in self
+                    .voices
+                    .iter_mut()
+                    .filter(|v| v.channel == channel && v.key == key)
+                {
+                    match sustain {
+                        true => voice.sustained = true,
+                        false => voice.envelope.release(),
+                    }
+                }
+            }
+            Message::Program { channel, program } => {
+                self.channels[channel as usize].program = program;
+            }
+            Message::PitchBend { channel, value } => self.channels[channel as usize].bend = value,
+            Message::Control {
+                channel,
+                controller,
+                value,
+            } => self.control(channel, controller, value),
+        }
+    }
+
+    fn control(&mut self, index: u8, controller: u8, value: u8) {
+        let channel = &mut self.channels[index as usize];
+        match controller {
+            // Drum channels keep the drum bank whatever the bank select says.
+            0 if index != DRUM_CHANNEL => channel.bank = value as u16,
+            6 if channel.rpn == (0, 0) => channel.bend_range = value.min(24),
+            7 => channel.volume = value,
+            10 => channel.pan = value,
+            11 => channel.expression = value,
+            64 => {
+                channel.sustain = value >= 64;
+                if !channel.sustain {
+                    for voice in self
+                        .voices
+                        .iter_mut()
+                        .filter(|v| v.channel == index && v.sustained)
+                    {
+                        voice.envelope.release();
+                    }
+                }
+            }
+            100 => channel.rpn.1 = value,
+            101 => channel.rpn.0 = value,
+            120 => self.voices.retain(|v| v.channel != index),
+            121 => {
+                channel.expression = 127;
+                channel.sustain = false;
+                channel.bend = 0;
+                channel.rpn = (127, 127);
+            }
+            123 => {
+                for voice in self.voices.iter_mut().filter(|v| v.channel == index) {
+                    voice.envelope.release();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn note_on(&mut self, channel: u8, key: u8, velocity: u8) {
+        let font = Arc::clone(&self.font);
+        let state = &self.channels[channel as usize];
+        let Some(preset) = font.preset(state.bank, state.program) else {
+            return;
+        };
+        // A repeated key restarts the note rather than stacking on it.
+        for voice in self
+            .voices
+            .iter_mut()
+            .filter(|v| v.channel == channel && v.key == key)
+        {
+            voice.envelope.release();
+        }
+        let voices: Vec<Voice> = preset
+            .regions
+            .iter()
+            .filter(|r| {
+                (r.keys.0..=r.keys.1).contains(&key)
+                    && (r.velocities.0..=r.velocities.1).contains(&velocity)
+            })
+            .filter_map(|region| Voice::new(&font, region, channel, key, velocity, self.rate))
+            .collect();
+        for voice in &voices {
+            if voice.exclusive != 0 {
+                self.voices
+                    .retain(|v| v.channel != channel || v.exclusive != voice.exclusive);
+            }
+        }
+        for voice in voices {
+            if self.voices.len() >= MAX_VOICES {
+                self.voices.remove(0);
+            }
+            self.voices.push(voice);
+        }
+    }
+
+    /// Renders the next `out.len() / 2` frames into the interleaved stereo `out`, which
+    /// should be at most [`BLOCK`] frames long.
+    pub fn render(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        let (font, channels, rate) = (&self.font, &self.channels, self.rate);
+        self.voices.retain_mut(|voice| {
+            voice.render(&font.samples, &channels[voice.channel as usize], rate, out)
+        });
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{sync::mpsc, thread, time::Duration};
+
+    /// A RIFF chunk: id, little-endian length and the body, padded to an even length.
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = [&id[..], &(body.len() as u32).to_le_bytes(), body].concat();
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    fn named(name: &str, len: usize) -> Vec<u8> {
+        let mut record = vec![0; len];
+        record[..name.len()].copy_from_slice(name.as_bytes());
+        record
+    }
+
+    /// A SoundFont with one preset, `preset`, whose only zone plays a looped sample with
+    /// the instrument `generators`.
+    fn font(preset: &str, generators: &[(u16, i16)]) -> Vec<u8> {
+        let smpl: Vec<u8> = (0..64i16).flat_map(|i| (i * 256).to_le_bytes()).collect();
+
+        let mut phdr = named(preset, 38);
+        phdr.extend(named("EOP", 38));
+        phdr[38 + 24..38 + 26].copy_from_slice(&1u16.to_le_bytes());
+        let pbag = [0, 0, 0, 0, 1, 0, 0, 0];
+        let pgen = [&[41, 0, 0, 0][..], &[0; 4]].concat();
+        let mut inst = named("hostile", 22);
+        inst.extend(named("EOI", 22));
+        inst[22 + 20..22 + 22].copy_from_slice(&1u16.to_le_bytes());
+        let mut igen: Vec<u8> = generators
+            .iter()
+            .chain(&[(generator::SAMPLE_ID as u16, 0)])
+            .flat_map(|(g, amount)| [g.to_le_bytes(), amount.to_le_bytes()].concat())
+            .collect();
+        let ibag = [
+            &[0, 0, 0, 0][..],
+            &(igen.len() as u16 / 4).to_le_bytes(),
+            &[0, 0],
+        ]
+        .concat();
+        igen.extend([0; 4]);
+        let mut shdr = named("sample", 46);
+        for (at, value) in [(20, 0), (24, 64), (28, 10), (32, 11), (36, 44_100)] {
+            shdr[at..at + 4].copy_from_slice(&u32::to_le_bytes(value));
+        }
+        shdr[40] = 60;
+        shdr.extend(named("EOS", 46));
+
+        let sdta = [&b"sdta"[..], &chunk(b"smpl", &smpl)].concat();
+        let pdta = [
+            &b"pdta"[..],
+            &chunk(b"phdr", &phdr),
+            &chunk(b"pbag", &pbag),
+            &chunk(b"pgen", &pgen),
+            &chunk(b"inst", &inst),
+            &chunk(b"ibag", &ibag),
+            &chunk(b"igen", &igen),
+            &chunk(b"shdr", &shdr),
+        ]
+        .concat();
+        let body = [&b"sfbk"[..], &chunk(b"LIST", &sdta), &chunk(b"LIST", &pdta)].concat();
+        chunk(b"RIFF", &body)
+    }
+
+    /// Tuning far outside the specification on a one-sample loop: unclamped, the pitch
+    /// overflows to infinity and wrapping the loop never ends.
+    pub(crate) fn hostile_font(preset: &str) -> Vec<u8> {
+        use generator::*;
+        font(
+            preset,
+            &[
+                (SAMPLE_MODES as u16, 1),
+                (COARSE_TUNE as u16, i16::MAX),
+                (FINE_TUNE as u16, i16::MAX),
+                (SCALE_TUNING as u16, i16::MAX),
+                (KEYNUM as u16, 127),
+            ],
+        )
+    }
+
+    #[test]
+    fn reads_presets_and_their_zones() {
+        let font = SoundFont::read(&font("Lead", &[]), Span::unknown()).unwrap();
+        assert_eq!(font.preset_named("lead"), Some((0, 0)));
+        let region = &font.preset(0, 0).unwrap().regions[0];
+        assert_eq!((region.keys, region.velocities), ((0, 127), (0, 127)));
+        assert_eq!(font.headers[0].loop_end, 11);
+    }
+
+    #[test]
+    fn tuning_generators_are_clamped_to_the_specification() {
+        let font = SoundFont::read(&hostile_font("Lead"), Span::unknown()).unwrap();
+        let voice = Voice::new(&font, &font.presets[0].regions[0], 0, 60, 100, 44_100).unwrap();
+        // 67 keys above the root at 1200 cents each, 120 semitones and 99 cents up.
+        assert_eq!(voice.cents, 67.0 * 1200.0 + 12_000.0 + 99.0);
+        assert!(voice.loop_points.is_some());
+    }
+
+    #[test]
+    fn hostile_tuning_renders_without_hanging() {
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            let font = SoundFont::read(&hostile_font("Lead"), Span::unknown()).unwrap();
+            let mut synth = Synth::new(Arc::new(font), 44_100);
+            synth.handle(Message::NoteOn {
+                channel: 0,
+                key: 60,
+                velocity: 100,
+            });
+            let mut block = [0.0; BLOCK * 2];
+            for _ in 0..100 {
+                synth.render(&mut block);
+            }
+            done.send(block.iter().all(|s| s.is_finite())).unwrap();
+        });
+        let finite = finished.recv_timeout(Duration::from_secs(10));
+        assert_eq!(finite, Ok(true), "rendering a hostile font hung");
+    }
+}
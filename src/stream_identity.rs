@@ -0,0 +1,227 @@
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Signature, SyntaxShape};
+use std::{
+    io,
+    process::{Command, Output, Stdio},
+    sync::{Mutex, MutexGuard},
+};
+
+/// Application name sound servers show for every stream the plugin opens, and key
+/// their per-application volume memory on.
+const APPLICATION_NAME: &str = "nushell";
+
+/// Stream name used when there is nothing better to call the stream.
+const DEFAULT_STREAM_NAME: &str = "audio_hook";
+
+/// `--role` choices: the name used on the command line, the role and a description for
+/// completions.
+pub const STREAM_ROLES: [(&str, StreamRole, &str); 3] = [
+    (
+        "media",
+        StreamRole::Media,
+        "follows the media volume and gets ducked",
+    ),
+    (
+        "notification",
+        StreamRole::Notification,
+        "follows the notification volume",
+    ),
+    (
+        "alarm",
+        StreamRole::Alarm,
+        "alarms and alerts that should not be ducked",
+    ),
+];
+
+/// What a stream is for, which sound servers use to pick a volume group and to duck
+/// other streams.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StreamRole {
+    /// Playback of a file, a stream or music.
+    Media,
+    /// Beeps, cues and generated tones.
+    Notification,
+    Alarm,
+    /// Test signals and measurements.
+    Test,
+}
+
+/// Adds the `--role` flag to a playback command's signature.
+pub fn role_flags(signature: Signature) -> Signature {
+    signature.named(
+        "role",
+        SyntaxShape::String,
+        "stream role for the OS mixer: media, notification or alarm",
+        None,
+    )
+}
+
+impl StreamRole {
+    /// Reads `--role`, or `default` when it is not given.
+    pub fn from_call(
+        call: &EvaluatedCall,
+        default: StreamRole,
+    ) -> Result<StreamRole, LabeledError> {
+        let Some(value) = call.get_flag_value("role") else {
+            return Ok(default);
+        };
+        let span = value.span();
+        let name = value.as_str().map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label("expected media, notification or alarm", span)
+        })?;
+        STREAM_ROLES
+            .iter()
+            .find(|(role, _, _)| role.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, role, _)| *role)
+            .ok_or_else(|| {
+                LabeledError::new(format!("unknown role '{name}'"))
+                    .with_label("expected media, notification or alarm", span)
+            })
+    }
+
+    /// PulseAudio's `media.role`.
+    fn pulse(self) -> &'static str {
+        match self {
+            StreamRole::Media => "music",
+            StreamRole::Notification => "event",
+            StreamRole::Alarm => "alarm",
+            StreamRole::Test => "test",
+        }
+    }
+
+    /// PipeWire's `media.role`.
+    fn pipewire(self) -> &'static str {
+        match self {
+            StreamRole::Media => "Music",
+            StreamRole::Notification => "Notification",
+            StreamRole::Alarm => "Alarm",
+            StreamRole::Test => "Test",
+        }
+    }
+}
+
+/// Serializes every change to the process environment, and every child process spawn,
+/// so no other thread sees the stream properties while they are set.
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+/// Opens an output stream with `open`, named `title` in the OS volume mixer, so it shows
+/// up as "nushell: <title>" instead of an anonymous ALSA client and per-application
+/// volume settings stick to it. `role` picks the volume group and ducking rules.
+///
+/// cpal has no API for stream properties; the PulseAudio and PipeWire ALSA plugins read
+/// them from the environment when a stream is opened. They are set for the duration of
+/// `open` only, and only where the user has not set them already, so a user's own
+/// `PULSE_PROP_*` or `PIPEWIRE_PROPS` wins. Other platforms ignore the variables and show
+/// the process name.
+pub fn open_identified<T>(title: Option<&str>, role: StreamRole, open: impl FnOnce() -> T) -> T {
+    let title = title.unwrap_or(DEFAULT_STREAM_NAME);
+    let properties = [
+        ("PULSE_PROP_application.name", APPLICATION_NAME.to_string()),
+        ("PULSE_PROP_media.name", title.to_string()),
+        ("PULSE_PROP_media.role", role.pulse().to_string()),
+        (
+            "PIPEWIRE_PROPS",
+            format!(
+                "{{ application.name = {} media.name = {} media.role = {} }}",
+                spa_string(APPLICATION_NAME),
+                spa_string(title),
+                spa_string(role.pipewire())
+            ),
+        ),
+    ];
+
+    let _lock = lock();
+    let set: Vec<&str> = properties
+        .iter()
+        .filter(|(name, _)| std::env::var_os(name).is_none())
+        .map(|(name, value)| {
+            std::env::set_var(name, value);
+            *name
+        })
+        .collect();
+    let opened = open();
+    for name in set {
+        std::env::remove_var(name);
+    }
+    opened
+}
+
+/// Runs `command` to completion and collects its output, like [`Command::output`], but
+/// spawns it while no stream properties are set, so the child does not inherit them.
+pub fn command_output(command: &mut Command) -> io::Result<Output> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let child = {
+        let _lock = lock();
+        command.spawn()?
+    };
+    child.wait_with_output()
+}
+
+fn lock() -> MutexGuard<'static, ()> {
+    // The guarded data is `()`, so a panic while holding the lock leaves nothing broken.
+    ENVIRONMENT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Quotes a value for PipeWire's SPA-JSON property syntax.
+fn spa_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+
+    #[test]
+    fn sets_properties_only_while_opening() {
+        let seen = open_identified(Some("a \"quoted\" title"), StreamRole::Alarm, || {
+            (
+                std::env::var("PULSE_PROP_media.name").ok(),
+                std::env::var("PULSE_PROP_media.role").ok(),
+                std::env::var("PIPEWIRE_PROPS").ok(),
+            )
+        });
+        assert_eq!(seen.0.as_deref(), Some("a \"quoted\" title"));
+        assert_eq!(seen.1.as_deref(), Some("alarm"));
+        assert_eq!(
+            seen.2.as_deref(),
+            Some(concat!(
+                r#"{ application.name = "nushell" media.name = "a \"quoted\" title" "#,
+                r#"media.role = "Alarm" }"#
+            ))
+        );
+        assert!(std::env::var_os("PULSE_PROP_media.role").is_none());
+        assert!(std::env::var_os("PULSE_PROP_media.name").is_none());
+        assert!(std::env::var_os("PIPEWIRE_PROPS").is_none());
+    }
+
+    #[test]
+    fn keeps_properties_the_user_set() {
+        let name = "PULSE_PROP_application.name";
+        let user = OsString::from("my player");
+        {
+            let _lock = lock();
+            std::env::set_var(name, &user);
+        }
+        let seen = open_identified(None, StreamRole::Media, || std::env::var_os(name));
+        assert_eq!(seen, Some(user.clone()));
+        assert_eq!(std::env::var_os(name), Some(user));
+        let _lock = lock();
+        std::env::remove_var(name);
+    }
+}
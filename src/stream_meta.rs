@@ -0,0 +1,371 @@
+use nu_plugin::EngineInterface;
+use nu_protocol::{LabeledError, Record, Span, Value};
+use std::{
+    io::{self, Read},
+    time::Duration,
+};
+
+use crate::{
+    network::{http_stream, url_extension},
+    remote::header_value,
+    utils::duration_value,
+};
+
+/// Largest playlist read for HLS metadata; real playlists are a few kilobytes.
+const PLAYLIST_LIMIT: u64 = 1024 * 1024;
+
+/// Largest `icy-metaint` accepted before giving up on reading the current title.
+const ICY_METAINT_LIMIT: usize = 1024 * 1024;
+
+/// `sound meta` for live sources: Icecast/Shoutcast (ICY) streams and HLS playlists.
+///
+/// `head` is the server's answer to an `http head` request sent with
+/// `Icy-MetaData: 1`. Returns `None` when `url` is an ordinary file, which is then
+/// read with range requests.
+pub fn live_stream_meta(
+    engine: &EngineInterface,
+    span: Span,
+    url: &str,
+    headers: Option<&Value>,
+    head: &Value,
+) -> Result<Option<Record>, LabeledError> {
+    let content_type = header_value(head, "content-type")
+        .map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase()
+        })
+        .unwrap_or_default();
+    if url_extension(url).is_some_and(|ext| ext.eq_ignore_ascii_case("m3u8"))
+        || content_type.ends_with("mpegurl")
+    {
+        return hls_meta(engine, span, url, headers).map(Some);
+    }
+
+    let icy = icy_headers(head);
+    if !icy && !endless(head, &content_type) {
+        return Ok(None);
+    }
+
+    let mut record = Record::new();
+    record.push(
+        "format",
+        Value::string(if icy { "icy" } else { "stream" }, span),
+    );
+    record.push("live", Value::bool(true, span));
+    for (header, column) in [
+        ("icy-name", "name"),
+        ("icy-description", "description"),
+        ("icy-genre", "genre"),
+        ("icy-url", "url"),
+    ] {
+        if let Some(value) = header_value(head, header).filter(|v| !v.trim().is_empty()) {
+            record.push(column, Value::string(value.trim(), span));
+        }
+    }
+
+    let metaint = header_value(head, "icy-metaint").and_then(|v| v.trim().parse().ok());
+    if let Some(metaint) = metaint.filter(|m| (1..=ICY_METAINT_LIMIT).contains(m)) {
+        let title = icy_title(engine, span, url, headers, metaint).map_err(|e| {
+            LabeledError::new(e.to_string()).with_label("error reading stream title", span)
+        })?;
+        if let Some(title) = title {
+            record.push("title", Value::string(title, span));
+        }
+    }
+
+    // `ice-audio-info` is Icecast's `ice-samplerate=44100;ice-bitrate=128;...`.
+    let audio_info = header_value(head, "ice-audio-info").unwrap_or_default();
+    let info = |key: &str| {
+        audio_info.split(';').find_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case(key)
+                .then(|| value.trim().parse::<i64>().ok())?
+        })
+    };
+    // Shoutcast sends `icy-br: 128` or, for some encoders, `icy-br: 128, 128`.
+    let bitrate = header_value(head, "icy-br")
+        .and_then(|v| v.split(',').next()?.trim().parse::<i64>().ok())
+        .or_else(|| info("ice-bitrate"));
+    if let Some(bitrate) = bitrate {
+        record.push("bitrate", Value::int(bitrate, span));
+    }
+    if let Some(codec) = codec_from_content_type(&content_type) {
+        record.push("codec", Value::string(codec, span));
+    }
+    let sample_rate = header_value(head, "icy-sr")
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .or_else(|| info("ice-samplerate"));
+    if let Some(rate) = sample_rate {
+        record.push("sample_rate", Value::int(rate, span));
+    }
+    if let Some(channels) = info("ice-channels") {
+        record.push("channels", Value::int(channels, span));
+    }
+    Ok(Some(record))
+}
+
+/// Whether `head`, the answer to an `http head` request, describes an endless stream
+/// (internet radio) rather than a file.
+pub fn is_live(head: &Value) -> bool {
+    let content_type = header_value(head, "content-type").unwrap_or_default();
+    icy_headers(head) || endless(head, &content_type.to_ascii_lowercase())
+}
+
+/// Icecast and Shoutcast announce themselves with `icy-*` and `ice-*` headers.
+fn icy_headers(head: &Value) -> bool {
+    ["icy-", "ice-"]
+        .iter()
+        .any(|prefix| header_names(head).any(|name| name.starts_with(prefix)))
+}
+
+/// Audio served without a length is a live stream without ICY headers.
+fn endless(head: &Value, content_type: &str) -> bool {
+    content_type.starts_with("audio/") && header_value(head, "content-length").is_none()
+}
+
+fn header_names(head: &Value) -> impl Iterator<Item = String> + '_ {
+    let rows = match head {
+        Value::List { vals, .. } => vals.as_slice(),
+        _ => &[],
+    };
+    rows.iter().filter_map(|row| {
+        let name = row.as_record().ok()?.get("name")?.as_str().ok()?;
+        Some(name.to_ascii_lowercase())
+    })
+}
+
+/// Reads the first ICY metadata block of the stream and returns its `StreamTitle`.
+///
+/// With `Icy-MetaData: 1` the server interleaves a metadata block after every
+/// `metaint` bytes of audio: one length byte (in units of 16 bytes) followed by
+/// `StreamTitle='...';StreamUrl='...';`.
+fn icy_title(
+    engine: &EngineInterface,
+    span: Span,
+    url: &str,
+    headers: Option<&Value>,
+    metaint: usize,
+) -> io::Result<Option<String>> {
+    let extra = [("Icy-MetaData", "1".to_string())];
+    let (mut reader, _) =
+        http_stream(engine, span, url, headers, &extra).map_err(|e| io::Error::other(e.msg))?;
+    io::copy(&mut reader.by_ref().take(metaint as u64), &mut io::sink())?;
+    let mut length = [0u8];
+    reader.read_exact(&mut length)?;
+    let mut block = vec![0u8; length[0] as usize * 16];
+    reader.read_exact(&mut block)?;
+
+    let text = String::from_utf8_lossy(&block);
+    let title = text
+        .split_once("StreamTitle='")
+        .map(|(_, rest)| rest.split_once("';").map_or(rest, |(title, _)| title))
+        .map(|title| title.trim_end_matches('\0').trim().to_string())
+        .filter(|title| !title.is_empty());
+    Ok(title)
+}
+
+/// Reads an HLS master or media playlist. For a master playlist the variant with the
+/// highest bandwidth is reported and its media playlist read for the current title.
+fn hls_meta(
+    engine: &EngineInterface,
+    span: Span,
+    url: &str,
+    headers: Option<&Value>,
+) -> Result<Record, LabeledError> {
+    let mut record = Record::new();
+    record.push("format", Value::string("hls", span));
+
+    let mut playlist = read_playlist(engine, span, url, headers)?;
+    if playlist.contains("#EXT-X-STREAM-INF") {
+        if let Some(name) = session_title(&playlist) {
+            record.push("name", Value::string(name, span));
+        }
+        let Some(variant) = best_variant(&playlist) else {
+            return Err(LabeledError::new("HLS playlist has no variants")
+                .with_label("master playlist lists no streams", span));
+        };
+        if let Some(bandwidth) = variant.bandwidth {
+            record.push("bitrate", Value::int(bandwidth as i64 / 1000, span));
+        }
+        if let Some(codec) = variant.codecs.as_deref().and_then(audio_codec) {
+            record.push("codec", Value::string(codec, span));
+        }
+        let media_url = join_url(url, &variant.uri);
+        playlist = read_playlist(engine, span, &media_url, headers)?;
+    }
+
+    let ended = playlist.contains("#EXT-X-ENDLIST");
+    record.push("live", Value::bool(!ended, span));
+    let segments = media_segments(&playlist);
+    if let Some(title) = segments
+        .iter()
+        .rev()
+        .find_map(|(_, title, _)| Some(title.trim()).filter(|title| !title.is_empty()))
+    {
+        record.push("title", Value::string(title, span));
+    }
+    if !record.contains("codec") {
+        let codec = segments
+            .last()
+            .and_then(|(_, _, uri)| url_extension((*uri)?))
+            .map(|ext| ext.to_ascii_lowercase())
+            .filter(|ext| ["aac", "mp3", "flac", "opus", "ac3", "ec3"].contains(&ext.as_str()));
+        if let Some(codec) = codec {
+            record.push("codec", Value::string(codec, span));
+        }
+    }
+    if ended {
+        let total: f64 = segments.iter().map(|(length, _, _)| length).sum();
+        record.push(
+            "duration",
+            duration_value(Duration::from_secs_f64(total), span),
+        );
+    }
+    Ok(record)
+}
+
+fn read_playlist(
+    engine: &EngineInterface,
+    span: Span,
+    url: &str,
+    headers: Option<&Value>,
+) -> Result<String, LabeledError> {
+    let (reader, _) = http_stream(engine, span, url, headers, &[])?;
+    let mut bytes = Vec::new();
+    reader
+        .take(PLAYLIST_LIMIT)
+        .read_to_end(&mut bytes)
+        .map_err(|e| LabeledError::new(e.to_string()).with_label("error reading playlist", span))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+struct Variant {
+    bandwidth: Option<u64>,
+    codecs: Option<String>,
+    uri: String,
+}
+
+/// Variant of a master playlist with the highest `BANDWIDTH`.
+fn best_variant(playlist: &str) -> Option<Variant> {
+    let mut lines = playlist.lines().map(str::trim);
+    let mut variants = Vec::new();
+    while let Some(line) = lines.next() {
+        let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") else {
+            continue;
+        };
+        let Some(uri) = lines.find(|l| !l.is_empty() && !l.starts_with('#')) else {
+            break;
+        };
+        variants.push(Variant {
+            bandwidth: attribute(attributes, "BANDWIDTH").and_then(|b| b.parse().ok()),
+            codecs: attribute(attributes, "CODECS"),
+            uri: uri.to_string(),
+        });
+    }
+    variants
+        .into_iter()
+        .max_by_key(|v| v.bandwidth.unwrap_or(0))
+}
+
+/// Title from `#EXT-X-SESSION-DATA:DATA-ID="com.apple.hls.title",VALUE="..."`.
+fn session_title(playlist: &str) -> Option<String> {
+    playlist.lines().find_map(|line| {
+        let attributes = line.trim().strip_prefix("#EXT-X-SESSION-DATA:")?;
+        (attribute(attributes, "DATA-ID")? == "com.apple.hls.title")
+            .then(|| attribute(attributes, "VALUE"))?
+    })
+}
+
+/// Segments of a media playlist as `(length in seconds, title, uri)`.
+fn media_segments(playlist: &str) -> Vec<(f64, &str, Option<&str>)> {
+    let mut segments = Vec::new();
+    let mut lines = playlist.lines().map(str::trim).peekable();
+    while let Some(line) = lines.next() {
+        let Some(info) = line.strip_prefix("#EXTINF:") else {
+            continue;
+        };
+        let (length, title) = info.split_once(',').unwrap_or((info, ""));
+        while lines
+            .peek()
+            .is_some_and(|l| l.is_empty() || l.starts_with('#'))
+        {
+            lines.next();
+        }
+        segments.push((length.trim().parse().unwrap_or(0.0), title, lines.next()));
+    }
+    segments
+}
+
+/// Value of `name` in an HLS attribute list (`NAME=value,NAME="quoted, value"`).
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=')?;
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let (value, next) = quoted.split_once('"').unwrap_or((quoted, ""));
+                (value, next.trim_start_matches(','))
+            }
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        if key.trim() == name {
+            return Some(value.to_string());
+        }
+        rest = next;
+    }
+    None
+}
+
+/// Audio codec name from an RFC 6381 `CODECS` list, which may also name video codecs.
+fn audio_codec(codecs: &str) -> Option<String> {
+    codecs.split(',').map(str::trim).find_map(|codec| {
+        let name = match codec {
+            "mp4a.40.2" => "aac",
+            "mp4a.40.5" => "he-aac",
+            "mp4a.40.29" => "he-aac v2",
+            "mp4a.40.34" | "mp4a.6B" | "mp4a.69" => "mp3",
+            "opus" | "Opus" => "opus",
+            "fLaC" | "flac" => "flac",
+            "ac-3" => "ac3",
+            "ec-3" => "eac3",
+            _ if codec.starts_with("mp4a") => "aac",
+            _ => return None,
+        };
+        Some(name.to_string())
+    })
+}
+
+fn codec_from_content_type(content_type: &str) -> Option<&str> {
+    Some(match content_type {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => "aac",
+        "audio/ogg" | "application/ogg" => "ogg",
+        "audio/opus" => "opus",
+        "audio/flac" | "audio/x-flac" => "flac",
+        _ => return None,
+    })
+}
+
+/// Resolves a playlist entry against the playlist's own URL.
+fn join_url(base: &str, reference: &str) -> String {
+    if reference.contains("://") {
+        return reference.to_string();
+    }
+    let base = base.split(['?', '#']).next().unwrap_or(base);
+    if let Some(path) = reference.strip_prefix('/') {
+        let origin_end = base
+            .find("://")
+            .and_then(|scheme| base[scheme + 3..].find('/').map(|i| scheme + 3 + i))
+            .unwrap_or(base.len());
+        return format!("{}/{path}", &base[..origin_end]);
+    }
+    match base.rsplit_once('/') {
+        Some((directory, _)) => format!("{directory}/{reference}"),
+        None => reference.to_string(),
+    }
+}
@@ -1,44 +1,427 @@
 use nu_plugin::{EngineInterface, EvaluatedCall};
-use nu_protocol::{LabeledError, Span, Value};
-use std::{fs::File, path::PathBuf, time::Duration};
+use nu_protocol::{LabeledError, Signature, Span, Spanned, Value};
+use std::{
+    ffi::{OsStr, OsString},
+    fs::{File, OpenOptions, Permissions},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    process::{Command, Output},
+    time::Duration,
+};
 
+use crate::stream_identity::command_output;
+
+/// Resolves `file_path` against the current directory and canonicalizes it.
+///
+/// Windows canonicalization yields verbatim paths (`\\?\C:\…`, `\\?\UNC\server\…`)
+/// that lofty and users handle poorly; they are turned back into the ordinary form where
+/// that means the same file. Some network shares cannot be canonicalized at all, so an
+/// existing path that fails is used as an absolute path instead.
 pub fn resolve_filepath(
     engine: &EngineInterface,
     span: Span,
     file_path: PathBuf,
 ) -> Result<PathBuf, LabeledError> {
-    let file_path = if file_path.is_absolute() {
-        Ok::<PathBuf, LabeledError>(file_path)
+    let file_path = absolute_path(engine, file_path, span)?;
+    let file_path = match file_path.canonicalize() {
+        Ok(canonical) => canonical,
+        Err(_) if file_path.exists() => std::path::absolute(&file_path).unwrap_or(file_path),
+        Err(e) => {
+            return Err(LabeledError::new(e.to_string()).with_label("Failed to canonicalize path", span))
+        }
+    };
+    Ok(if cfg!(windows) {
+        simplify_verbatim(file_path)
     } else {
-        let current_path = engine.get_current_dir().map_err(|e| {
-            LabeledError::new(e.to_string()).with_label("Could not get current directory", span)
-        })?;
-        Ok(PathBuf::from(current_path).join(file_path))
-    }?
-    .canonicalize()
-    .map_err(|e| LabeledError::new(e.to_string()).with_label("Failed to canonicalize path", span))?;
-    Ok(file_path)
+        file_path
+    })
+}
+
+/// Joins a relative `path` to the shell's current directory. Unlike [`resolve_filepath`]
+/// the path need not exist, so this is for files and directories about to be created.
+pub fn absolute_path(
+    engine: &EngineInterface,
+    path: PathBuf,
+    span: Span,
+) -> Result<PathBuf, LabeledError> {
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    let cwd = engine.get_current_dir().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("Could not get current directory", span)
+    })?;
+    Ok(PathBuf::from(cwd).join(path))
+}
+
+/// Runs the external program of `command` to completion and returns its output. `None`
+/// means the program is not on PATH; a program that cannot be started or exits with a
+/// failure is an error with its stderr as the message.
+pub fn run_tool(command: &mut Command, span: Span) -> Result<Option<Output>, LabeledError> {
+    let bin = command.get_program().to_string_lossy().into_owned();
+    let output = match command_output(command) {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(LabeledError::new(e.to_string())
+                .with_label(format!("failed to run {bin}"), span))
+        }
+    };
+    if !output.status.success() {
+        return Err(
+            LabeledError::new(String::from_utf8_lossy(&output.stderr).trim().to_string())
+                .with_label(format!("{bin} failed with {}", output.status), span),
+        );
+    }
+    Ok(Some(output))
+}
+
+/// The error for an external `tool` that [`run_tool`] did not find, with `label` saying
+/// what was looked for and `help` how to get it.
+pub fn not_installed(tool: &str, label: impl Into<String>, help: &str, span: Span) -> LabeledError {
+    LabeledError::new(format!("{tool} is not installed"))
+        .with_label(label, span)
+        .with_help(help)
+}
+
+/// Names Windows reserves for devices; a verbatim path is the only way to reach a file
+/// called like this, so such paths are left alone.
+const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Rewrites `\\?\C:\dir` as `C:\dir` and `\\?\UNC\server\share` as `\\server\share` when
+/// the short form names the same file: below the legacy length limit and free of
+/// components Win32 path parsing would alter.
+fn simplify_verbatim(path: PathBuf) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path;
+    };
+    let simple = if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{unc}")
+    } else if let Some(local) = text.strip_prefix(r"\\?\") {
+        let bytes = local.as_bytes();
+        if bytes.len() < 3 || !bytes[0].is_ascii_alphabetic() || &bytes[1..3] != b":\\" {
+            return path;
+        }
+        local.to_string()
+    } else {
+        return path;
+    };
+    let lossless = simple.len() < 260
+        && !simple.contains('/')
+        && simple.split('\\').skip(1).all(|component| {
+            let stem = component.split('.').next().unwrap_or(component);
+            !component.ends_with(['.', ' '])
+                && !RESERVED_NAMES.contains(&stem.trim_end().to_ascii_lowercase().as_str())
+        });
+    if lossless {
+        PathBuf::from(simple)
+    } else {
+        path
+    }
 }
 
 pub fn load_file_path(
     engine: &EngineInterface,
     call: &EvaluatedCall,
 ) -> Result<(Span, PathBuf), LabeledError> {
-    let file_path: Value = call.req(0).map_err(|e| {
+    load_file_path_at(engine, call, 0)
+}
+
+/// Like [`load_file_path`], for the positional argument at `index`.
+pub fn load_file_path_at(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    index: usize,
+) -> Result<(Span, PathBuf), LabeledError> {
+    let file_path: Value = call.req(index).map_err(|e| {
         LabeledError::new(e.to_string()).with_label("Expected file path", call.head)
     })?;
 
     let span = file_path.span();
 
-    let file_path = match file_path {
-        Value::String { val, .. } => PathBuf::from(val),
-        _ => return Err(LabeledError::new("invalid input").with_label("Expected file path", span)),
-    };
-
-    let file_path = resolve_filepath(engine, span, file_path)?;
+    let file_path = resolve_filepath(engine, span, path_value(file_path)?)?;
     Ok((span, file_path))
 }
 
+/// Converts a path argument to a [`PathBuf`]. Besides strings this takes binary values
+/// holding the raw bytes of a path on Unix, the only way to pass a file name that is not
+/// valid UTF-8 through nushell (e.g. `0x[...]` bytes).
+pub fn path_value(value: Value) -> Result<PathBuf, LabeledError> {
+    let span = value.span();
+    match value {
+        Value::String { val, .. } => Ok(PathBuf::from(val)),
+        #[cfg(unix)]
+        Value::Binary { val, .. } => {
+            use std::os::unix::ffi::OsStringExt;
+            Ok(PathBuf::from(OsString::from_vec(val)))
+        }
+        _ => Err(LabeledError::new("invalid input").with_label("Expected file path", span)),
+    }
+}
+
+/// Builds a nushell duration from a [`Duration`].
+pub fn duration_value(duration: Duration, span: Span) -> Value {
+    Value::duration(duration.as_nanos().min(i64::MAX as u128) as i64, span)
+}
+
+/// How `sound meta` reports durations (`--duration-format`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DurationFormat {
+    /// A nushell duration, which sorts and sums with built-in commands.
+    Duration,
+    /// Seconds as a float.
+    Seconds,
+    /// Zero-padded `HH:MM:SS` text, which sorts correctly as a string.
+    Hms,
+    /// ISO 8601, e.g. `PT4M5.2S`.
+    Iso,
+}
+
+/// `--duration-format` names, with a description for completions.
+pub const DURATION_FORMATS: [(&str, DurationFormat, &str); 4] = [
+    ("duration", DurationFormat::Duration, "nushell duration (default)"),
+    ("seconds", DurationFormat::Seconds, "seconds as a float"),
+    ("hms", DurationFormat::Hms, "zero-padded HH:MM:SS"),
+    ("iso", DurationFormat::Iso, "ISO 8601, e.g. PT4M5.2S"),
+];
+
+impl DurationFormat {
+    pub fn from_call(call: &EvaluatedCall) -> Result<DurationFormat, LabeledError> {
+        let Some(name) = call.get_flag::<Spanned<String>>("duration-format")? else {
+            return Ok(DurationFormat::Duration);
+        };
+        DURATION_FORMATS
+            .iter()
+            .find(|(n, _, _)| n.eq_ignore_ascii_case(name.item.trim()))
+            .map(|(_, format, _)| *format)
+            .ok_or_else(|| {
+                LabeledError::new(format!("unknown duration format '{}'", name.item))
+                    .with_label("unknown format", name.span)
+                    .with_help("use duration, seconds, hms or iso")
+            })
+    }
+
+    /// Converts the `duration` column of a record, or of each record in a list.
+    pub fn apply(self, value: Value) -> Value {
+        if self == DurationFormat::Duration {
+            return value;
+        }
+        let span = value.span();
+        match value {
+            Value::Record { val, .. } => {
+                let mut record = val.into_owned();
+                if let Some(Value::Duration { val, .. }) = record.get("duration") {
+                    let duration = Duration::from_nanos((*val).max(0) as u64);
+                    record.insert("duration", self.format(duration, span));
+                }
+                Value::record(record, span)
+            }
+            Value::List { vals, .. } => {
+                Value::list(vals.into_iter().map(|v| self.apply(v)).collect(), span)
+            }
+            other => other,
+        }
+    }
+
+    fn format(self, duration: Duration, span: Span) -> Value {
+        let millis = duration.as_millis();
+        let (hours, minutes) = (millis / 3_600_000, millis / 60_000 % 60);
+        let seconds = (millis % 60_000) as f64 / 1000.0;
+        match self {
+            DurationFormat::Duration => duration_value(duration, span),
+            DurationFormat::Seconds => Value::float(millis as f64 / 1000.0, span),
+            DurationFormat::Hms => Value::string(
+                format!("{hours:02}:{minutes:02}:{:02}", duration.as_secs() % 60),
+                span,
+            ),
+            DurationFormat::Iso => {
+                let mut iso = "PT".to_string();
+                if hours > 0 {
+                    iso += &format!("{hours}H");
+                }
+                if minutes > 0 {
+                    iso += &format!("{minutes}M");
+                }
+                if seconds > 0.0 || millis == 0 {
+                    iso += &format!("{seconds}S");
+                }
+                Value::string(iso, span)
+            }
+        }
+    }
+}
+
+/// Checks that `path` can be written before tags are changed, so a read-only file gives
+/// an error naming its permissions rather than lofty's bare "permission denied".
+///
+/// With `force`, a file the user owns is made writable for the write; the permissions
+/// returned must then be put back with [`restore_permissions`].
+pub fn ensure_writable(
+    path: &Path,
+    force: bool,
+    span: Span,
+) -> Result<Option<Permissions>, LabeledError> {
+    match OpenOptions::new().write(true).open(path) {
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {}
+        Err(e) => {
+            return Err(LabeledError::new(e.to_string()).with_label("error opening file for writing", span))
+        }
+    }
+    let original = std::fs::metadata(path)
+        .map_err(|e| LabeledError::new(e.to_string()).with_label("error reading file permissions", span))?
+        .permissions();
+    let bits = describe_permissions(&original);
+    if !force {
+        return Err(LabeledError::new(format!("{} is not writable", path.display()))
+            .with_label(format!("permission denied, {bits}"), span)
+            .with_help("pass --force to make it writable for the write and restore its permissions afterwards"));
+    }
+    let mut writable = original.clone();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        writable.set_mode(writable.mode() | 0o200);
+    }
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    writable.set_readonly(false);
+    std::fs::set_permissions(path, writable).map_err(|e| {
+        LabeledError::new(format!("cannot make {} writable", path.display()))
+            .with_label(format!("{e}, {bits}"), span)
+            .with_help("only the file's owner can change its permissions")
+    })?;
+    Ok(Some(original))
+}
+
+/// Adds `--force` to a command that writes files in place, for [`with_write_access`].
+pub fn force_flags(signature: Signature) -> Signature {
+    signature.switch(
+        "force",
+        "write read-only files by making them writable and restoring their permissions afterwards",
+        None,
+    )
+}
+
+/// Runs `write` on `path` once [`ensure_writable`] has passed, and puts lifted
+/// permissions back afterwards, whether the write succeeded or not.
+pub fn with_write_access<T>(
+    path: &Path,
+    force: bool,
+    span: Span,
+    write: impl FnOnce() -> Result<T, LabeledError>,
+) -> Result<T, LabeledError> {
+    let permissions = ensure_writable(path, force, span)?;
+    let written = write();
+    restore_permissions(path, permissions, span)?;
+    written
+}
+
+/// Puts back permissions lifted by [`ensure_writable`].
+pub fn restore_permissions(path: &Path, original: Option<Permissions>, span: Span) -> Result<(), LabeledError> {
+    match original {
+        Some(permissions) => std::fs::set_permissions(path, permissions).map_err(|e| {
+            LabeledError::new(e.to_string())
+                .with_label(format!("error restoring the permissions of {}", path.display()), span)
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn describe_permissions(permissions: &Permissions) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = permissions.mode();
+    let symbolic: String = (0..9)
+        .map(|bit| {
+            if mode & (0o400 >> bit) == 0 {
+                '-'
+            } else {
+                ['r', 'w', 'x'][bit % 3]
+            }
+        })
+        .collect();
+    format!("mode {:04o} ({symbolic})", mode & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn describe_permissions(permissions: &Permissions) -> String {
+    if permissions.readonly() {
+        "read-only attribute set".to_string()
+    } else {
+        "read-only attribute not set".to_string()
+    }
+}
+
+/// Rewrites `path` with the bytes in each `start..end` range replaced by the edit's
+/// bytes; the ranges must not overlap. The new file is written next to the original and
+/// renamed over it, so an interrupted write leaves the original intact. Symlinks are
+/// followed, and a file with other hard links is overwritten in place instead, since a
+/// rename would split it from them.
+pub fn splice_file(path: &Path, edits: &[(u64, u64, Vec<u8>)]) -> std::io::Result<()> {
+    let path = path.canonicalize()?;
+    let mut edits: Vec<_> = edits.iter().collect();
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".splice-tmp");
+    let temp = path.with_file_name(name);
+    let result = (|| {
+        let mut source = File::open(&path)?;
+        let mut target = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp)?;
+        let mut position = 0;
+        for (start, end, replacement) in edits {
+            std::io::copy(&mut (&mut source).take(start - position), &mut target)?;
+            target.write_all(replacement)?;
+            position = source.seek(SeekFrom::Start(*end))?;
+        }
+        std::io::copy(&mut source, &mut target)?;
+        let metadata = source.metadata()?;
+        if hard_linked(&metadata) {
+            drop(source);
+            let len = target.stream_position()?;
+            target.rewind()?;
+            let mut original = File::options().write(true).open(&path)?;
+            std::io::copy(&mut target, &mut original)?;
+            original.set_len(len)?;
+            original.sync_all()?;
+            return std::fs::remove_file(&temp);
+        }
+        target.set_permissions(metadata.permissions())?;
+        target.sync_all()?;
+        std::fs::rename(&temp, &path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(unix)]
+fn hard_linked(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn hard_linked(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// File name made of `path`'s stem and `suffix`. The stem's bytes are kept as they are,
+/// so names that are not valid UTF-8 survive; `fallback` stands in when there is none.
+pub fn stem_with_suffix(path: &Path, fallback: &str, suffix: &str) -> OsString {
+    let mut name = path.file_stem().map_or_else(|| OsString::from(fallback), OsStr::to_os_string);
+    name.push(suffix);
+    name
+}
+
 /// Formats a [`Duration`] as `M:SS`, or `H:MM:SS` for durations ≥ 1 hour.
 ///
 /// Shared by `audio_meta` (duration field in metadata records) and `audio_player`
@@ -56,6 +439,12 @@ pub fn format_duration(d: Duration) -> String {
     }
 }
 
+/// Span of the value given for flag `name`, for errors about that value; the call's
+/// head when the flag is absent.
+pub fn flag_span(call: &EvaluatedCall, name: &str) -> Span {
+    call.get_flag_value(name).map_or(call.head, |value| value.span())
+}
+
 pub fn load_file(
     engine: &EngineInterface,
     call: &EvaluatedCall,
@@ -66,3 +455,265 @@ pub fn load_file(
     })?;
     Ok((span, file, path))
 }
+
+/// Collects path arguments from the rest positionals starting at `start` and from a
+/// pipeline string or list of strings, resolving each against the current directory.
+pub fn load_path_args(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: &Value,
+    start: usize,
+) -> Result<Vec<PathBuf>, LabeledError> {
+    let mut values: Vec<Value> = call.rest(start).map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("Expected file paths", call.head)
+    })?;
+    match input {
+        Value::List { vals, .. } => values.extend(vals.iter().cloned()),
+        Value::String { .. } | Value::Binary { .. } => values.push(input.clone()),
+        _ => {}
+    }
+
+    let mut paths = Vec::with_capacity(values.len());
+    for value in values {
+        let span = value.span();
+        paths.push(resolve_filepath(engine, span, path_value(value)?)?);
+    }
+    if paths.is_empty() {
+        return Err(LabeledError::new("no paths given")
+            .with_label("pass files or directories as arguments or through the pipeline", call.head));
+    }
+    Ok(paths)
+}
+
+/// Whether `pattern` has wildcards for [`expand_glob`].
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Paths matching `pattern`, sorted, taking a relative pattern from `cwd`. Any component
+/// may hold `*`, `?` and `[...]` classes (`[a-z]`, `[!0-9]`); as in a shell, wildcards
+/// skip names starting with a dot unless the component itself does.
+pub fn expand_glob(cwd: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut paths = vec![cwd.to_path_buf()];
+    for component in Path::new(pattern).components() {
+        let part = match component {
+            Component::Normal(part) => part.to_string_lossy(),
+            // Roots and drive prefixes replace `cwd`; `.` and `..` are walked as they are.
+            other => {
+                paths.iter_mut().for_each(|path| path.push(other));
+                continue;
+            }
+        };
+        if !is_glob(&part) {
+            paths.iter_mut().for_each(|path| path.push(&*part));
+            continue;
+        }
+        let wildcards: Vec<char> = part.chars().collect();
+        paths = paths
+            .iter()
+            .flat_map(|dir| {
+                let names = std::fs::read_dir(dir)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| Some(entry.ok()?.file_name()));
+                names
+                    .filter(|name| {
+                        let name = name.to_string_lossy();
+                        (!name.starts_with('.') || part.starts_with('.'))
+                            && wildcard_match(&wildcards, &name.chars().collect::<Vec<_>>())
+                    })
+                    .map(|name| dir.join(name))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+    paths.retain(|path| path.exists());
+    paths.sort();
+    paths
+}
+
+/// Whether `name` matches the wildcards of one path component.
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // After a mismatch, retry from the last `*` with it covering one more character.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => class_match(&pattern[p..], name[n]),
+            Some(&c) => (c == name[n]).then_some(1),
+            None => None,
+        };
+        match (step, star) {
+            (Some(len), _) => {
+                p += len;
+                n += 1;
+            }
+            (None, Some((after, from))) => {
+                p = after;
+                n = from + 1;
+                star = Some((after, from + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Length of the `[...]` class at the start of `pattern` if it matches `c`. A `]` right
+/// after the opening bracket is part of the class, and a class that is never closed is
+/// a literal `[`.
+fn class_match(pattern: &[char], c: char) -> Option<usize> {
+    let negated = matches!(pattern.get(1), Some('!' | '^'));
+    let first = if negated { 2 } else { 1 };
+    let Some(end) = pattern
+        .iter()
+        .skip(first + 1)
+        .position(|&c| c == ']')
+        .map(|i| i + first + 1)
+    else {
+        return (c == '[').then_some(1);
+    };
+    let set = &pattern[first..end];
+    let mut matched = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            matched |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= set[i] == c;
+            i += 1;
+        }
+    }
+    (matched != negated).then_some(end + 1)
+}
+
+/// Expands `paths` into a sorted, de-duplicated list of audio files, descending into
+/// directories recursively. A file counts as audio when lofty recognises its extension.
+pub fn collect_audio_files(paths: &[PathBuf], span: Span) -> Result<Vec<PathBuf>, LabeledError> {
+    let mut files = Vec::new();
+    for path in paths {
+        walk_audio_files(path, &mut files, span)?;
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn walk_audio_files(path: &Path, files: &mut Vec<PathBuf>, span: Span) -> Result<(), LabeledError> {
+    if path.is_dir() {
+        let entries = std::fs::read_dir(path).map_err(|e| {
+            LabeledError::new(e.to_string()).with_label(format!("error reading {}", path.display()), span)
+        })?;
+        for entry in entries.flatten() {
+            walk_audio_files(&entry.path(), files, span)?;
+        }
+    } else if lofty::file::FileType::from_path(path).is_some() {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/// The results of the examples of `command` that have one, for tests that check them
+/// against what the command renders.
+#[cfg(test)]
+pub fn example_results(command: &impl nu_plugin::SimplePluginCommand) -> Vec<Value> {
+    command.examples().into_iter().filter_map(|e| e.result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own in the temp directory, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = std::env::temp_dir()
+                .join(format!("audio_hook_utils_{}_{name}", std::process::id()));
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn edits() -> Vec<(u64, u64, Vec<u8>)> {
+        // Given out of order; one shrinks, one grows, one inserts.
+        vec![
+            (8, 10, b"IJKLMN".to_vec()),
+            (0, 3, b"x".to_vec()),
+            (5, 5, b"--".to_vec()),
+        ]
+    }
+
+    const SPLICED: &[u8] = b"xDE--FGHIJKLMN";
+
+    #[test]
+    fn splice_replaces_ranges() {
+        let dir = TempDir::new("splice");
+        let path = dir.0.join("file");
+        std::fs::write(&path, b"ABCDEFGHIJ").unwrap();
+        splice_file(&path, &edits()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), SPLICED);
+        assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn splice_rewrites_symlink_target() {
+        let dir = TempDir::new("symlink");
+        let (target, link) = (dir.0.join("target"), dir.0.join("link"));
+        std::fs::write(&target, b"ABCDEFGHIJ").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        splice_file(&link, &edits()).unwrap();
+        assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(std::fs::read(&target).unwrap(), SPLICED);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn splice_keeps_hard_links_together() {
+        let dir = TempDir::new("hardlink");
+        let (first, second) = (dir.0.join("first"), dir.0.join("second"));
+        std::fs::write(&first, b"ABCDEFGHIJKLMNOPQRSTUVWXYZ").unwrap();
+        std::fs::hard_link(&first, &second).unwrap();
+        // Shrinking must truncate the shared file too.
+        splice_file(&second, &[(2, 20, Vec::new())]).unwrap();
+        assert_eq!(std::fs::read(&first).unwrap(), b"ABUVWXYZ");
+        assert_eq!(std::fs::read(&second).unwrap(), b"ABUVWXYZ");
+        assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn run_tool_reports_a_missing_program_as_none() {
+        let mut command = Command::new("audio_hook_no_such_program");
+        assert!(run_tool(&mut command, Span::test_data()).unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_tool_fails_with_the_stderr_of_a_failed_program() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo out; echo broken >&2; exit 3"]);
+        let error = run_tool(&mut command, Span::test_data()).unwrap_err();
+        assert_eq!(error.msg, "broken");
+        assert_eq!(error.labels[0].text, "sh failed with exit status: 3");
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo out"]);
+        let output = run_tool(&mut command, Span::test_data()).unwrap().unwrap();
+        assert_eq!(output.stdout, b"out\n");
+    }
+}
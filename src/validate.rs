@@ -0,0 +1,235 @@
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Span, SyntaxShape, Value};
+use std::time::Duration;
+
+/// Highest gain accepted for `--amplify`; beyond this a signal is only clipping.
+const MAX_GAIN: f64 = 100.0;
+
+/// Reads a duration flag, rejecting negative values instead of ignoring them.
+pub fn duration_flag(call: &EvaluatedCall, name: &str) -> Result<Option<Duration>, LabeledError> {
+    call.get_flag_value(name)
+        .map(|value| duration(&value, &format!("--{name}")))
+        .transpose()
+}
+
+/// Shape of duration arguments: a nushell duration, or a string parsed by [`duration`].
+pub fn duration_shape() -> SyntaxShape {
+    SyntaxShape::OneOf(vec![SyntaxShape::Duration, SyntaxShape::String])
+}
+
+/// Converts a duration argument, rejecting negative values. Besides nushell durations,
+/// accepts strings as copied from players: clock times (`1:30`, `1:02:03.5`), numbers
+/// with units (`90.5s`, `1m30s`, `250ms`) and plain seconds (`90.5`). `what` names the
+/// argument in the error.
+pub fn duration(value: &Value, what: &str) -> Result<Duration, LabeledError> {
+    let span = value.span();
+    match value {
+        Value::Duration { val, .. } if *val >= 0 => Ok(Duration::from_nanos(*val as u64)),
+        Value::Duration { .. } => Err(LabeledError::new(format!("negative {what}"))
+            .with_label("durations must be zero or more", span)),
+        Value::String { val, .. } => parse_duration(val).ok_or_else(|| {
+            LabeledError::new(format!("cannot read {what}"))
+                .with_label("not a duration", span)
+                .with_help("use a duration such as 2sec, or a string like \"1:30\" or \"90.5s\"")
+        }),
+        _ => Err(LabeledError::new(format!("{what} is not a duration"))
+            .with_label("expected a duration such as 500ms or 2sec", span)),
+    }
+}
+
+/// Parses `[[h:]m:]s[.frac]`, a sequence of numbers with units, or plain seconds.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_ascii_lowercase();
+    if text.contains(':') {
+        let parts: Vec<&str> = text.split(':').collect();
+        if parts.len() > 3 {
+            return None;
+        }
+        let (last, whole) = parts.split_last()?;
+        let mut seconds = 0.0;
+        for part in whole {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            seconds = (seconds + part.parse::<f64>().ok()?) * 60.0;
+        }
+        return seconds_duration(seconds + number(last)?);
+    }
+    if let Some(seconds) = number(&text) {
+        return seconds_duration(seconds);
+    }
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut rest = text.as_str();
+    let mut seconds = 0.0;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let (amount, tail) = rest.split_at(split);
+        let amount = number(amount)?;
+        let tail = tail.trim_start();
+        let split = tail
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(split);
+        let scale = match unit {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+            _ => return None,
+        };
+        seconds += amount * scale;
+        rest = tail.trim_start();
+    }
+    seconds_duration(seconds)
+}
+
+/// A non-negative decimal number without sign or exponent.
+fn number(text: &str) -> Option<f64> {
+    let valid = !text.is_empty()
+        && text.bytes().any(|b| b.is_ascii_digit())
+        && text.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+    valid.then(|| text.parse().ok()).flatten()
+}
+
+fn seconds_duration(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Checks a gain (`--amplify`): a finite, non-negative factor of at most [`MAX_GAIN`].
+pub fn gain(value: &Value, what: &str) -> Result<f32, LabeledError> {
+    let span = value.span();
+    let gain = value.as_float().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label(format!("{what} must be a number"), span)
+    })?;
+    if !gain.is_finite() {
+        return Err(LabeledError::new(format!("{what} is not a finite number"))
+            .with_label("NaN and infinity cannot be played", span));
+    }
+    if !(0.0..=MAX_GAIN).contains(&gain) {
+        return Err(LabeledError::new(format!("{what} out of range"))
+            .with_label(format!("must be between 0 and {MAX_GAIN}"), span)
+            .with_help("1.0 is the original level, 0.5 half, 2.0 double"));
+    }
+    Ok(gain as f32)
+}
+
+/// Checks a level relative to full scale: a finite number from 0.0 to 1.0.
+pub fn level(value: &Value, what: &str) -> Result<f32, LabeledError> {
+    let span = value.span();
+    let level = value.as_float().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label(format!("{what} must be a number"), span)
+    })?;
+    if !(0.0..=1.0).contains(&level) {
+        return Err(LabeledError::new(format!("{what} out of range"))
+            .with_label("must be between 0.0 and 1.0", span));
+    }
+    Ok(level as f32)
+}
+
+/// Checks a stereo position: a finite number from -1.0 (left) to 1.0 (right).
+pub fn pan(value: &Value, what: &str) -> Result<f32, LabeledError> {
+    let span = value.span();
+    let pan = value.as_float().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label(format!("{what} must be a number"), span)
+    })?;
+    if !(-1.0..=1.0).contains(&pan) {
+        return Err(LabeledError::new(format!("{what} out of range"))
+            .with_label("must be between -1.0 (left) and 1.0 (right)", span));
+    }
+    Ok(pan as f32)
+}
+
+/// Checks that `frequency` can be represented at `sample_rate`: above zero and below
+/// the Nyquist frequency.
+pub fn frequency(frequency: f64, sample_rate: u32, span: Span) -> Result<f32, LabeledError> {
+    let nyquist = sample_rate as f64 / 2.0;
+    if !frequency.is_finite() || frequency <= 0.0 || frequency >= nyquist {
+        return Err(LabeledError::new("frequency out of range")
+            .with_label(format!("must be above 0 and below {nyquist} Hz"), span)
+            .with_help(format!("tones are rendered at {sample_rate} Hz")));
+    }
+    Ok(frequency as f32)
+}
+
+/// Rejects lengths over `limit` unless `force` is set, so a typo like `3wk` does not
+/// tie up the shell or allocate gigabytes.
+pub fn length(
+    length: Duration,
+    limit: Duration,
+    force: bool,
+    span: Span,
+) -> Result<(), LabeledError> {
+    if length > limit && !force {
+        return Err(LabeledError::new("duration is unusually long")
+            .with_label(format!("longer than {}s", limit.as_secs()), span)
+            .with_help("pass --force if you really mean it"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units_clock_times_and_plain_seconds() {
+        let cases = [
+            ("90.5", 90_500),
+            ("1:30", 90_000),
+            ("1:02:03.5", 3_723_500),
+            ("90.5s", 90_500),
+            ("1m30s", 90_000),
+            ("1m 30sec", 90_000),
+            ("250ms", 250),
+            ("2 Minutes", 120_000),
+            ("1.5h", 5_400_000),
+            ("1hr 1min 1s", 3_661_000),
+        ];
+        for (text, millis) in cases {
+            assert_eq!(
+                parse_duration(text),
+                Some(Duration::from_millis(millis)),
+                "{text}"
+            );
+        }
+        assert_eq!(parse_duration("5us"), Some(Duration::from_micros(5)));
+        assert_eq!(parse_duration("5µs"), Some(Duration::from_micros(5)));
+        assert_eq!(parse_duration("40ns"), Some(Duration::from_nanos(40)));
+    }
+
+    #[test]
+    fn rejects_bad_suffixes_and_malformed_numbers() {
+        for text in [
+            "", "s", "10x", "10 days", "10s5", "1e3s", "1.2.3", "ten", "1::30", "1:2:3:4", "1:x",
+        ] {
+            assert_eq!(parse_duration(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_negatives() {
+        for text in ["-5", "-5s", "1m -30s", "-1:30", "1:-30"] {
+            assert_eq!(parse_duration(text), None, "{text}");
+        }
+        let negative = Value::test_duration(-1);
+        assert_eq!(
+            duration(&negative, "--start").unwrap_err().msg,
+            "negative --start"
+        );
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert_eq!(parse_duration("1e400"), None);
+        assert_eq!(parse_duration(&"9".repeat(400)), None);
+        assert_eq!(parse_duration("99999999999999999999999h"), None);
+        assert_eq!(parse_duration("99999999999999999999999:00:00"), None);
+    }
+}
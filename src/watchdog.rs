@@ -0,0 +1,165 @@
+use nu_protocol::{LabeledError, Span};
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Code of the error raised when a decoder stops making progress, so batch commands can
+/// skip the file and scripts can tell it apart from other failures.
+pub const STALL_CODE: &str = "audio_hook::decoder_stalled";
+
+/// How long a decoder may run without producing audio before it is given up on.
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Guards a decoder against corrupt files that make it spin forever without producing
+/// samples.
+///
+/// A decoding thread cannot be killed, so the decoder reads its input through
+/// [`Watchdog::reader`]; once the watchdog gives up, every read fails and the decoder
+/// unwinds on its own. Reading input counts as progress, so jobs that only probe a file
+/// need no ticks of their own.
+#[derive(Clone, Default)]
+pub struct Watchdog {
+    progress: Arc<AtomicU64>,
+    aborted: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    /// Wraps the decoder's input so each read ticks the watchdog and fails once it has
+    /// given up.
+    pub fn reader<R>(&self, inner: R) -> Watched<R> {
+        Watched {
+            inner,
+            progress: self.progress.clone(),
+            aborted: self.aborted.clone(),
+        }
+    }
+
+    /// Records that the decoder produced something.
+    pub fn tick(&self) {
+        self.progress.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+
+    /// Runs `job` on its own thread and returns its result, or a [`STALL_CODE`] error
+    /// once it has not called [`tick`](Watchdog::tick) for [`STALL_TIMEOUT`].
+    pub fn run<T, F>(span: Span, job: F) -> Result<T, LabeledError>
+    where
+        T: Send + 'static,
+        F: FnOnce(Watchdog) -> Result<T, LabeledError> + Send + 'static,
+    {
+        let watchdog = Watchdog::default();
+        let worker = watchdog.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(job(worker));
+        });
+
+        let mut stall = StallWatch::new();
+        loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(result) => return result,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(LabeledError::new("decoder crashed")
+                        .with_label("the decoder thread panicked", span))
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if stall.stalled(watchdog.progress.load(Ordering::Relaxed)) {
+                watchdog.abort();
+                return Err(stalled_error(span));
+            }
+        }
+    }
+}
+
+/// The error for a decoder that stopped making progress.
+pub fn stalled_error(span: Span) -> LabeledError {
+    LabeledError::new("decoder stalled")
+        .with_code(STALL_CODE)
+        .with_label(
+            format!(
+                "no audio decoded for {}s; the file is probably corrupt",
+                STALL_TIMEOUT.as_secs()
+            ),
+            span,
+        )
+}
+
+/// Notices a progress counter that has not moved for [`STALL_TIMEOUT`].
+pub struct StallWatch<T = u64> {
+    last: Option<T>,
+    since: Instant,
+}
+
+impl<T: PartialEq> StallWatch<T> {
+    pub fn new() -> StallWatch<T> {
+        StallWatch {
+            last: None,
+            since: Instant::now(),
+        }
+    }
+
+    /// Feeds the current progress; `true` once it has not changed for
+    /// [`STALL_TIMEOUT`].
+    pub fn stalled(&mut self, progress: T) -> bool {
+        if self.last.as_ref() != Some(&progress) {
+            self.last = Some(progress);
+            self.since = Instant::now();
+            return false;
+        }
+        self.since.elapsed() >= STALL_TIMEOUT
+    }
+
+    /// Restarts the timer, for times the progress is expected to stand still, such as
+    /// while paused.
+    pub fn reset(&mut self) {
+        self.since = Instant::now();
+    }
+}
+
+/// A decoder input that ticks its [`Watchdog`] on every read that returns data and fails
+/// every read and seek once the watchdog has given up.
+pub struct Watched<R> {
+    inner: R,
+    progress: Arc<AtomicU64>,
+    aborted: Arc<AtomicBool>,
+}
+
+impl<R> Watched<R> {
+    fn check(&self) -> io::Result<()> {
+        if self.aborted.load(Ordering::Relaxed) {
+            return Err(io::Error::other("decoder stalled"));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Watched<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        let read = self.inner.read(buf)?;
+        if read > 0 {
+            self.progress.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for Watched<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.check()?;
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,273 @@
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Signature, Span, SyntaxShape, Value};
+use rodio::{source::UniformSourceIterator, Source};
+use std::time::Duration;
+
+/// `--bits` choices: the name used on the command line, the sample format and a
+/// description for completions.
+pub const WAV_SAMPLE_FORMATS: [(&str, WavSampleFormat, &str); 3] = [
+    (
+        "16",
+        WavSampleFormat::Int16,
+        "16-bit integer PCM (default)",
+    ),
+    ("24", WavSampleFormat::Int24, "24-bit integer PCM"),
+    ("32f", WavSampleFormat::Float32, "32-bit float PCM"),
+];
+
+/// Sample rates `--rate` accepts, in Hz.
+pub const RATE_RANGE: std::ops::RangeInclusive<i64> = 8_000..=384_000;
+
+/// Most channels `--channels` accepts (7.1).
+pub const MAX_CHANNELS: i64 = 8;
+
+/// How samples are stored in a WAV file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WavSampleFormat {
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl WavSampleFormat {
+    fn bits(self) -> u16 {
+        match self {
+            WavSampleFormat::Int16 => 16,
+            WavSampleFormat::Int24 => 24,
+            WavSampleFormat::Float32 => 32,
+        }
+    }
+
+    /// The `fmt ` chunk's format tag.
+    fn tag(self) -> u16 {
+        match self {
+            WavSampleFormat::Float32 => 3, // WAVE_FORMAT_IEEE_FLOAT
+            _ => 1,                        // WAVE_FORMAT_PCM
+        }
+    }
+
+    fn write(self, sample: f32, buffer: &mut Vec<u8>) {
+        let sample = sample.clamp(-1.0, 1.0);
+        match self {
+            WavSampleFormat::Int16 => {
+                buffer.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes())
+            }
+            WavSampleFormat::Int24 => {
+                let value = (sample as f64 * 8_388_607.0) as i32;
+                buffer.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            WavSampleFormat::Float32 => buffer.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+}
+
+/// Adds `--rate`, `--bits` and `--channels`, which shape the WAV data of `--data`.
+pub fn wav_flags(signature: Signature) -> Signature {
+    signature
+        .named(
+            "rate",
+            SyntaxShape::Int,
+            "sample rate of --data output in Hz (default 48000)",
+            None,
+        )
+        .named(
+            "bits",
+            SyntaxShape::OneOf(vec![SyntaxShape::Int, SyntaxShape::String]),
+            "sample format of --data output: 16 (default), 24 or 32f",
+            None,
+        )
+        .named(
+            "channels",
+            SyntaxShape::Int,
+            "channel count of --data output; mono is copied to the first two channels",
+            None,
+        )
+}
+
+/// The format `--data` output is written in. Unset fields keep the source's own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WavOptions {
+    pub rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub format: WavSampleFormat,
+}
+
+impl Default for WavOptions {
+    fn default() -> Self {
+        WavOptions {
+            rate: None,
+            channels: None,
+            format: WavSampleFormat::Int16,
+        }
+    }
+}
+
+impl WavOptions {
+    /// Reads `--rate`, `--bits` and `--channels`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<WavOptions, LabeledError> {
+        let rate = match call.get_flag_value("rate") {
+            Some(value) => Some(int_in(&value, RATE_RANGE, "--rate", "Hz")? as u32),
+            None => None,
+        };
+        let channels = match call.get_flag_value("channels") {
+            Some(value) => Some(int_in(&value, 1..=MAX_CHANNELS, "--channels", "channels")? as u16),
+            None => None,
+        };
+        let format = match call.get_flag_value("bits") {
+            Some(value) => {
+                let span = value.span();
+                let name = match &value {
+                    Value::Int { val, .. } => val.to_string(),
+                    _ => value.coerce_string().map_err(|e| {
+                        LabeledError::new(e.to_string()).with_label("expected 16, 24 or 32f", span)
+                    })?,
+                };
+                WAV_SAMPLE_FORMATS
+                    .iter()
+                    .find(|(bits, _, _)| bits.eq_ignore_ascii_case(name.trim()))
+                    .map(|(_, format, _)| *format)
+                    .ok_or_else(|| {
+                        LabeledError::new(format!("unsupported --bits '{name}'"))
+                            .with_label("expected 16, 24 or 32f", span)
+                    })?
+            }
+            None => WavSampleFormat::Int16,
+        };
+        Ok(WavOptions {
+            rate,
+            channels,
+            format,
+        })
+    }
+
+    /// Longest audio these options can write for a source of `rate` and `channels`: a
+    /// WAV file's data size is a `u32`.
+    pub fn max_length(&self, rate: u32, channels: u16) -> Duration {
+        let bytes_per_second = self.rate.unwrap_or(rate) as u64
+            * self.channels.unwrap_or(channels) as u64
+            * (self.format.bits() / 8) as u64;
+        Duration::from_secs(u32::MAX as u64 / bytes_per_second.max(1))
+    }
+
+    /// Renders `source` to an in-memory WAV file in these options' format, resampling
+    /// and remapping channels as needed.
+    pub fn encode<S: Source>(&self, source: S) -> Result<Vec<u8>, LabeledError> {
+        if self.rate.is_none() && self.channels.is_none() {
+            return write_wav(source, self.format);
+        }
+        let channels = self.channels.unwrap_or(source.channels());
+        let rate = self.rate.unwrap_or(source.sample_rate());
+        write_wav(
+            UniformSourceIterator::new(source, channels, rate),
+            self.format,
+        )
+    }
+}
+
+/// Reads an integer flag, checked to be within `range` of `unit`.
+pub fn int_in(
+    value: &Value,
+    range: std::ops::RangeInclusive<i64>,
+    flag: &str,
+    unit: &str,
+) -> Result<i64, LabeledError> {
+    let int = value.as_int().map_err(|e| {
+        LabeledError::new(e.to_string()).with_label("expected an integer", value.span())
+    })?;
+    if !range.contains(&int) {
+        return Err(LabeledError::new(format!("{flag} out of range")).with_label(
+            format!(
+                "must be between {} and {} {unit}",
+                range.start(),
+                range.end()
+            ),
+            value.span(),
+        ));
+    }
+    Ok(int)
+}
+
+/// Renders `source` to an in-memory 16-bit PCM WAV file.
+///
+/// The source must be finite; its samples are clamped to `[-1.0, 1.0]` before
+/// quantization.
+pub fn encode_wav<S: Source>(source: S) -> Result<Vec<u8>, LabeledError> {
+    write_wav(source, WavSampleFormat::Int16)
+}
+
+/// Writes `source` as a WAV file with samples stored as `format`. Header fields are
+/// range-checked so oversized renders fail with an error instead of producing a corrupt
+/// file. Float data gets the extended `fmt ` chunk and the `fact` chunk that non-PCM
+/// WAV files require.
+fn write_wav<S: Source>(source: S, format: WavSampleFormat) -> Result<Vec<u8>, LabeledError> {
+    let sample_rate = source.sample_rate();
+    let num_channels = source.channels();
+
+    let bits_per_sample = format.bits();
+    let byte_rate_u64 = sample_rate as u64 * num_channels as u64 * bits_per_sample as u64 / 8;
+    if byte_rate_u64 > u32::MAX as u64 {
+        return Err(LabeledError::new("WAV header overflow")
+            .with_label("byte_rate exceeds u32", Span::unknown()));
+    }
+    let byte_rate = byte_rate_u64 as u32;
+
+    let block_align_u64 = num_channels as u64 * bits_per_sample as u64 / 8;
+    if block_align_u64 > u16::MAX as u64 {
+        return Err(LabeledError::new("WAV header overflow")
+            .with_label("block_align exceeds u16", Span::unknown()));
+    }
+    let block_align = block_align_u64 as u16;
+    let float = format == WavSampleFormat::Float32;
+
+    let mut buffer = Vec::new();
+
+    // RIFF header; the chunk size is filled in once the data is written.
+    buffer.extend_from_slice(b"RIFF");
+    buffer.extend_from_slice(&0u32.to_le_bytes());
+    buffer.extend_from_slice(b"WAVE");
+
+    // fmt subchunk
+    buffer.extend_from_slice(b"fmt ");
+    let subchunk1_size: u32 = if float { 18 } else { 16 };
+    buffer.extend_from_slice(&subchunk1_size.to_le_bytes());
+    buffer.extend_from_slice(&format.tag().to_le_bytes()); // AudioFormat
+    buffer.extend_from_slice(&num_channels.to_le_bytes());
+    buffer.extend_from_slice(&sample_rate.to_le_bytes());
+    buffer.extend_from_slice(&byte_rate.to_le_bytes());
+    buffer.extend_from_slice(&block_align.to_le_bytes());
+    buffer.extend_from_slice(&bits_per_sample.to_le_bytes());
+    let fact = if float {
+        buffer.extend_from_slice(&0u16.to_le_bytes()); // cbSize
+
+        // fact subchunk: frames per channel, filled in below
+        buffer.extend_from_slice(b"fact");
+        buffer.extend_from_slice(&4u32.to_le_bytes());
+        buffer.extend_from_slice(&0u32.to_le_bytes());
+        Some(buffer.len() - 4)
+    } else {
+        None
+    };
+
+    // data subchunk
+    buffer.extend_from_slice(b"data");
+    buffer.extend_from_slice(&0u32.to_le_bytes());
+    let data_start = buffer.len();
+    for sample in source {
+        format.write(sample, &mut buffer);
+    }
+
+    let subchunk2_size = u32::try_from(buffer.len() - data_start).map_err(|_| {
+        LabeledError::new("WAV data too large").with_label("exceeds u32 limit", Span::unknown())
+    })?;
+    let chunk_size = u32::try_from(buffer.len() - 8).map_err(|_| {
+        LabeledError::new("WAV header overflow").with_label("chunk_size overflow", Span::unknown())
+    })?;
+    buffer[4..8].copy_from_slice(&chunk_size.to_le_bytes());
+    buffer[data_start - 4..data_start].copy_from_slice(&subchunk2_size.to_le_bytes());
+    if let Some(fact) = fact {
+        let frames = subchunk2_size / block_align.max(1) as u32;
+        buffer[fact..fact + 4].copy_from_slice(&frames.to_le_bytes());
+    }
+
+    Ok(buffer)
+}
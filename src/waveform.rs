@@ -0,0 +1,95 @@
+use rodio::{Decoder, Source};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Resolution of the peak overview; rendering picks columns out of these buckets.
+const PEAK_BUCKETS: usize = 1024;
+
+/// Dynamic range shown by the strip, below the loudest bucket.
+const STRIP_RANGE_DB: f32 = 48.0;
+
+/// Highest level returned by [`Waveform::levels`]; level 0 is silence.
+pub const WAVEFORM_LEVELS: u8 = 8;
+
+/// Peak overview of a file, scanned on a background thread while it plays.
+///
+/// The file is decoded a second time, independently of playback, and reduced to
+/// [`PEAK_BUCKETS`] peaks spread over `total`. Dropping the overview stops the scan.
+pub struct Waveform {
+    peaks: Arc<Mutex<Option<Vec<f32>>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Waveform {
+    pub fn spawn(path: PathBuf, total: Duration) -> Waveform {
+        let peaks = Arc::new(Mutex::new(None));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (result, stop) = (Arc::clone(&peaks), Arc::clone(&cancel));
+        std::thread::spawn(move || {
+            if let Some(scanned) = scan(&path, total, &stop) {
+                if let Ok(mut slot) = result.lock() {
+                    *slot = Some(scanned);
+                }
+            }
+        });
+        Waveform { peaks, cancel }
+    }
+
+    /// Loudness level per column, `0..=WAVEFORM_LEVELS` on a dB scale relative to the
+    /// loudest part of the file, or `None` while the scan is still running.
+    pub fn levels(&self, width: usize) -> Option<Vec<u8>> {
+        let peaks = self.peaks.lock().ok()?;
+        let peaks = peaks.as_ref()?;
+        let loudest = peaks.iter().fold(0.0f32, |max, p| max.max(*p));
+        let levels = (0..width)
+            .map(|column| {
+                let start = column * peaks.len() / width;
+                let end = ((column + 1) * peaks.len() / width).clamp(start + 1, peaks.len());
+                let peak = peaks[start..end].iter().fold(0.0f32, |max, p| max.max(*p));
+                if loudest <= 0.0 || peak <= 0.0 {
+                    return 0;
+                }
+                let db = 20.0 * (peak / loudest).log10();
+                let level = (db + STRIP_RANGE_DB) / STRIP_RANGE_DB * WAVEFORM_LEVELS as f32;
+                level.round().clamp(0.0, WAVEFORM_LEVELS as f32) as u8
+            })
+            .collect();
+        Some(levels)
+    }
+}
+
+impl Drop for Waveform {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn scan(path: &Path, total: Duration, cancel: &AtomicBool) -> Option<Vec<f32>> {
+    let decoder = Decoder::try_from(File::open(path).ok()?).ok()?;
+    let channels = decoder.channels().max(1) as u64;
+    let total_frames = (total.as_secs_f64() * decoder.sample_rate() as f64) as u64;
+    if total_frames == 0 {
+        return None;
+    }
+
+    let mut peaks = vec![0.0f32; PEAK_BUCKETS];
+    for (index, sample) in decoder.enumerate() {
+        if index % 65_536 == 0 && cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let frame = index as u64 / channels;
+        if frame >= total_frames {
+            break;
+        }
+        let bucket = (frame * PEAK_BUCKETS as u64 / total_frames) as usize;
+        peaks[bucket] = peaks[bucket].max(sample.abs());
+    }
+    Some(peaks)
+}
@@ -0,0 +1,113 @@
+use nu_plugin::EngineInterface;
+use nu_protocol::{LabeledError, Record, Span, Value};
+use std::{process::Command, time::Duration};
+
+use crate::utils::{not_installed, run_tool};
+
+/// Audio stream of a web page (video site, podcast page, …) as resolved by yt-dlp.
+pub struct YtdlpStream {
+    pub url: String,
+    pub title: Option<String>,
+    pub duration: Option<Duration>,
+    /// Request headers yt-dlp says the stream needs, as a record for `--headers`.
+    pub headers: Value,
+}
+
+/// Asks yt-dlp for the direct audio stream behind `page`, restricted to formats this
+/// build can decode. The binary is `yt-dlp` on PATH unless `$env.YT_DLP` names one.
+pub fn resolve_ytdlp(
+    engine: &EngineInterface,
+    page: &str,
+    span: Span,
+) -> Result<YtdlpStream, LabeledError> {
+    let bin = engine
+        .get_env_var("YT_DLP")?
+        .and_then(|v| v.coerce_string().ok())
+        .unwrap_or_else(|| "yt-dlp".to_string());
+    let mut command = Command::new(&bin);
+    command
+        .args(["--no-playlist", "--no-warnings", "--format"])
+        .arg(format_selector())
+        .args([
+            "--print",
+            "%(title)s",
+            "--print",
+            "%(duration)s",
+            "--print",
+            "%(http_headers)j",
+            "--print",
+            "urls",
+        ])
+        .arg(page);
+    let output = run_tool(&mut command, span)
+        .map_err(|error| {
+            if cfg!(feature = "symphonia-all") {
+                error
+            } else {
+                error.with_help(
+                    "many sites only offer AAC or Opus audio; build with `--features all-decoders` to play them",
+                )
+            }
+        })?
+        .ok_or_else(|| {
+            not_installed(
+                "yt-dlp",
+                format!("{bin} not found on PATH"),
+                "install yt-dlp or point $env.YT_DLP at its binary",
+                span,
+            )
+        })?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let (Some(title), Some(duration), Some(headers), Some(url)) =
+        (lines.next(), lines.next(), lines.next(), lines.next())
+    else {
+        return Err(LabeledError::new("unexpected yt-dlp output")
+            .with_label("expected title, duration, headers and stream URL", span));
+    };
+
+    Ok(YtdlpStream {
+        url: url.trim().to_string(),
+        title: Some(title.to_string()).filter(|t| t != "NA"),
+        duration: duration
+            .parse::<f64>()
+            .ok()
+            .filter(|secs| *secs > 0.0)
+            .map(Duration::from_secs_f64),
+        headers: headers_record(headers, span),
+    })
+}
+
+/// yt-dlp `--format` choices, best first, limited to codecs compiled into this build.
+fn format_selector() -> String {
+    let mut choices = Vec::new();
+    if cfg!(any(
+        feature = "symphonia-all",
+        all(feature = "symphonia-aac", feature = "symphonia-isomp4")
+    )) {
+        choices.push("bestaudio[ext=m4a]");
+    }
+    choices.extend([
+        "bestaudio[acodec=flac]",
+        "bestaudio[acodec=vorbis]",
+        "bestaudio[acodec=mp3]",
+    ]);
+    if cfg!(feature = "symphonia-all") {
+        choices.push("bestaudio");
+    }
+    choices.join("/")
+}
+
+/// Converts yt-dlp's `http_headers` JSON object into a record of strings.
+fn headers_record(json: &str, span: Span) -> Value {
+    let mut record = Record::new();
+    if let Ok(serde_json::Value::Object(headers)) = serde_json::from_str(json) {
+        for (name, value) in headers {
+            if let Some(value) = value.as_str() {
+                record.push(name, Value::string(value, span));
+            }
+        }
+    }
+    Value::record(record, span)
+}